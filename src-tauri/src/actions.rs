@@ -0,0 +1,231 @@
+// User-defined actions on search results, such as "Open in VS Code" or
+// "Upload via scp": command templates offered for paths with given
+// extensions or color labels
+
+use crate::error::{AppError, ErrorKind};
+use crate::marks::ColorLabel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomAction {
+    /// Stable identifier `run_action` takes
+    pub id: String,
+    /// Label shown in menus
+    pub name: String,
+    /// Program and arguments, split like a shell would on spaces and quotes.
+    /// `%path`, `%name` and `%dir` stand for the entry's full path, file name
+    /// and folder, `%%` for a literal percent sign. No shell is involved, so
+    /// on Windows batch scripts such as `code.cmd` need `cmd /c` in front.
+    pub command: String,
+    /// Extensions, without the dot, the action is offered for; any when empty.
+    /// Folders have no extension, so they only get actions without any.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Color labels the action is offered for; any when empty
+    #[serde(default)]
+    pub labels: Vec<ColorLabel>,
+}
+
+impl CustomAction {
+    /// Whether the action is offered for `path`, labeled `label`
+    pub fn applies_to(&self, path: &Path, is_folder: bool, label: Option<ColorLabel>) -> bool {
+        let extension_matches = self.extensions.is_empty()
+            || (!is_folder
+                && path.extension().is_some_and(|extension| {
+                    let extension = extension.to_string_lossy();
+                    self.extensions
+                        .iter()
+                        .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+                }));
+        let label_matches =
+            self.labels.is_empty() || label.is_some_and(|label| self.labels.contains(&label));
+        extension_matches && label_matches
+    }
+
+    /// Program and arguments of the action run on `path`
+    pub fn command_line(&self, path: &Path) -> Result<Vec<String>, String> {
+        command_line(&self.command, path)
+    }
+}
+
+/// Program and arguments of the command template `command` run on `path`
+pub fn command_line(command: &str, path: &Path) -> Result<Vec<String>, String> {
+    let full = path.to_string_lossy();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_else(|| full.clone());
+    let dir = path
+        .parent()
+        .map(|dir| dir.to_string_lossy())
+        .unwrap_or_default();
+    Ok(split_command(command)?
+        .iter()
+        .map(|word| expand(word, &full, &name, &dir))
+        .collect())
+}
+
+/// Check that action ids are unique and every command parses
+pub fn validate(actions: &[CustomAction]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for action in actions {
+        if action.id.trim().is_empty() || action.name.trim().is_empty() {
+            return Err("Actions need an id and a name".to_string());
+        }
+        if !ids.insert(action.id.as_str()) {
+            return Err(format!("Duplicate action id {:?}", action.id));
+        }
+        split_command(&action.command).map_err(|e| format!("Action {:?}: {}", action.id, e))?;
+    }
+    Ok(())
+}
+
+/// Words of a command template: whitespace separates them, single or double
+/// quotes keep spaces inside one, and a backslash escapes a quote in double
+/// quotes. Other backslashes are kept, as they are in Windows paths.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') if c == '"' && chars.peek() == Some(&'"') => {
+                            word.push(chars.next().unwrap_or('"'))
+                        }
+                        Some(inner) => word.push(inner),
+                        None => return Err("Unclosed quote in command".to_string()),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    if words.is_empty() {
+        return Err("Command is empty".to_string());
+    }
+    Ok(words)
+}
+
+/// Replace the placeholders in one word. Unknown ones are kept as written,
+/// so URLs with percent escapes pass through.
+fn expand(word: &str, path: &str, name: &str, dir: &str) -> String {
+    let mut expanded = String::with_capacity(word.len());
+    let mut rest = word;
+    while let Some(at) = rest.find('%') {
+        expanded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let (value, placeholder) = [("%%", "%"), ("%path", path), ("%name", name), ("%dir", dir)]
+            .into_iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+            .map_or(("%", "%"), |(placeholder, value)| (value, placeholder));
+        expanded.push_str(value);
+        rest = &rest[placeholder.len()..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Start `action` on `path` from the entry's folder, without waiting for it
+pub fn run(action: &CustomAction, path: &Path) -> Result<(), AppError> {
+    let path_str = path.to_string_lossy().to_string();
+    let command_line = action
+        .command_line(path)
+        .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    let mut command = std::process::Command::new(&command_line[0]);
+    command.args(&command_line[1..]);
+    if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+        // Windows can't start a process in a directory beyond MAX_PATH
+        command.current_dir(crate::longpath::process_dir(dir));
+    }
+    match command.spawn() {
+        Ok(_) => {
+            log::info!("Ran action {} on {}", action.id, path_str);
+            Ok(())
+        }
+        // A missing program is the likely failure, so report it rather than the entry
+        Err(e) => Err(AppError::io(&command_line[0], &e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(command: &str, extensions: &[&str], labels: &[ColorLabel]) -> CustomAction {
+        CustomAction {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            command: command.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            labels: labels.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_split_and_expand() {
+        let upload = action(
+            r#"scp "%path" 'host:/drop/%name' --note="50%% done""#,
+            &[],
+            &[],
+        );
+        assert_eq!(
+            upload
+                .command_line(Path::new("/home/me/My Photos/beach.jpg"))
+                .unwrap(),
+            vec![
+                "scp",
+                "/home/me/My Photos/beach.jpg",
+                "host:/drop/beach.jpg",
+                "--note=50% done"
+            ]
+        );
+        let open = action(r"C:\Tools\viewer.exe %dir %20", &[], &[]);
+        assert_eq!(
+            open.command_line(Path::new("/data/a.txt")).unwrap(),
+            vec![r"C:\Tools\viewer.exe", "/data", "%20"]
+        );
+        assert_eq!(
+            split_command(r#"echo "say \"hi\"""#).unwrap(),
+            vec!["echo", r#"say "hi""#]
+        );
+        assert!(split_command("open 'unclosed").is_err());
+        assert!(split_command("   ").is_err());
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let code = action("code %path", &["rs", ".TS"], &[]);
+        assert!(code.applies_to(Path::new("/src/main.rs"), false, None));
+        assert!(code.applies_to(Path::new("/src/app.ts"), false, None));
+        assert!(!code.applies_to(Path::new("/src/notes.md"), false, None));
+        assert!(!code.applies_to(Path::new("/src/lib.rs"), true, None));
+
+        let publish = action("publish %path", &["jpg"], &[ColorLabel::Green]);
+        assert!(publish.applies_to(Path::new("/a.jpg"), false, Some(ColorLabel::Green)));
+        assert!(!publish.applies_to(Path::new("/a.jpg"), false, Some(ColorLabel::Red)));
+        assert!(!publish.applies_to(Path::new("/a.png"), false, Some(ColorLabel::Green)));
+
+        let anything = action("open %path", &[], &[]);
+        assert!(anything.applies_to(Path::new("/folder"), true, None));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[action("code %path", &[], &[])]).is_ok());
+        let duplicate = action("code %path", &[], &[]);
+        assert!(validate(&[duplicate.clone(), duplicate]).is_err());
+        assert!(validate(&[action("", &[], &[])]).is_err());
+        let mut unnamed = action("code", &[], &[]);
+        unnamed.name = " ".to_string();
+        assert!(validate(&[unnamed]).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+// Index backups: sled and tantivy snapshotted into one zstd-compressed tar archive
+
+use crate::rebuild;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Archive layout version; archives from newer versions are refused
+const BACKUP_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const DB_DIR: &str = "db";
+const SEARCH_DIR: &str = "search";
+const PARTIAL_EXTENSION: &str = "partial";
+const UNPACK_EXTENSION: &str = "restore";
+
+/// Describes the archived index, stored as the archive's first entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub schema_version: u32,
+    pub files: usize,
+    /// Unix seconds
+    pub created: i64,
+}
+
+impl BackupManifest {
+    pub fn new(files: usize) -> Self {
+        BackupManifest {
+            version: BACKUP_VERSION,
+            schema_version: crate::search::SCHEMA_VERSION,
+            files,
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64,
+        }
+    }
+}
+
+/// Archive both index directories into `destination`, returning its size in
+/// bytes. The archive is written beside it and renamed into place once
+/// complete. Callers must keep both indexes from changing meanwhile.
+pub fn create_backup(
+    (db_path, search_index_path): (&Path, &Path),
+    manifest: &BackupManifest,
+    destination: &Path,
+) -> io::Result<u64> {
+    let partial = destination.with_extension(PARTIAL_EXTENSION);
+    let written = File::create(&partial)
+        .and_then(|file| write_archive((db_path, search_index_path), manifest, file))
+        .and_then(|_| fs::rename(&partial, destination));
+    if let Err(e) = written {
+        log::error!("Failed to write backup {:?}: {}", destination, e);
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(fs::metadata(destination)?.len())
+}
+
+fn write_archive(
+    (db_path, search_index_path): (&Path, &Path),
+    manifest: &BackupManifest,
+    file: File,
+) -> io::Result<()> {
+    let mut archive = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    let manifest = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, MANIFEST_NAME, manifest.as_slice())?;
+    archive.append_dir_all(DB_DIR, db_path)?;
+    archive.append_dir_all(SEARCH_DIR, search_index_path)?;
+    archive.into_inner()?.finish()?.sync_all()
+}
+
+/// Unpack an archive into `db_path` and `search_index_path`, those of a new
+/// index generation, and return its manifest. The live indexes are left
+/// alone, so a damaged archive cannot replace a working index.
+pub fn extract_backup(
+    source: &Path,
+    (db_path, search_index_path): (&Path, &Path),
+) -> io::Result<BackupManifest> {
+    let unpack_dir = db_path.with_extension(UNPACK_EXTENSION);
+    rebuild::clear_staging(&unpack_dir)?;
+    let extracted = unpack(source, &unpack_dir, (db_path, search_index_path));
+    if let Err(e) = rebuild::clear_staging(&unpack_dir) {
+        log::warn!("Failed to remove {:?}: {}", unpack_dir, e);
+    }
+    extracted
+}
+
+fn unpack(
+    source: &Path,
+    unpack_dir: &Path,
+    (db_path, search_index_path): (&Path, &Path),
+) -> io::Result<BackupManifest> {
+    // unpack() refuses entries that would land outside unpack_dir
+    tar::Archive::new(zstd::Decoder::new(File::open(source)?)?).unpack(unpack_dir)?;
+
+    let manifest: BackupManifest = match fs::read(unpack_dir.join(MANIFEST_NAME)) {
+        Ok(manifest) => serde_json::from_slice(&manifest)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(invalid_archive("it has no manifest"))
+        }
+        Err(e) => return Err(e),
+    };
+    if manifest.version > BACKUP_VERSION {
+        return Err(invalid_archive(&format!(
+            "version {} is newer than supported version {}",
+            manifest.version, BACKUP_VERSION
+        )));
+    }
+
+    let moves: [(PathBuf, &Path); 2] = [
+        (unpack_dir.join(DB_DIR), db_path),
+        (unpack_dir.join(SEARCH_DIR), search_index_path),
+    ];
+    for (unpacked, staging) in &moves {
+        if !unpacked.is_dir() {
+            return Err(invalid_archive(&format!(
+                "it has no {:?} directory",
+                unpacked.file_name().unwrap_or_default()
+            )));
+        }
+        rebuild::clear_staging(staging)?;
+        fs::rename(unpacked, staging)?;
+    }
+    Ok(manifest)
+}
+
+fn invalid_archive(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Not an index backup: {}", reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn make_index(dir: &Path) -> (PathBuf, PathBuf) {
+        let db_path = dir.join(".index_db");
+        let search_index_path = dir.join(".search_index");
+        fs::create_dir_all(db_path.join("blobs")).unwrap();
+        fs::write(db_path.join("conf"), "sled").unwrap();
+        fs::write(db_path.join("blobs").join("1"), "blob").unwrap();
+        fs::create_dir_all(&search_index_path).unwrap();
+        fs::write(search_index_path.join("meta.json"), "{}").unwrap();
+        (db_path, search_index_path)
+    }
+
+    #[test]
+    fn test_backup_round_trip_into_generation() {
+        let temp_dir = tempdir().unwrap();
+        let (db_path, search_index_path) = make_index(temp_dir.path());
+        let archive = temp_dir.path().join("index.tar.zst");
+        let manifest = BackupManifest::new(42);
+
+        let bytes = create_backup((&db_path, &search_index_path), &manifest, &archive).unwrap();
+        assert!(bytes > 0);
+        assert!(!archive.with_extension(PARTIAL_EXTENSION).exists());
+
+        let restore_dir = temp_dir.path().join("restore");
+        let (staging_db, staging_search) = rebuild::new_generation(&restore_dir).unwrap();
+        let restored = extract_backup(&archive, (&staging_db, &staging_search)).unwrap();
+
+        assert_eq!(restored, manifest);
+        assert_eq!(
+            fs::read_to_string(staging_db.join("blobs").join("1")).unwrap(),
+            "blob"
+        );
+        assert!(staging_search.join("meta.json").exists());
+        let (live_db, _) = rebuild::live_paths(&restore_dir);
+        assert!(!live_db.exists(), "Live index is not touched");
+        assert!(!staging_db.with_extension(UNPACK_EXTENSION).exists());
+    }
+
+    #[test]
+    fn test_extract_rejects_invalid_archives() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join(".index_db");
+        let search_index_path = temp_dir.path().join(".search_index");
+
+        let garbage = temp_dir.path().join("garbage.tar.zst");
+        fs::write(&garbage, "not an archive").unwrap();
+        assert!(extract_backup(&garbage, (&db_path, &search_index_path)).is_err());
+
+        // A valid archive without a manifest
+        let empty = temp_dir.path().join("empty.tar.zst");
+        let file = File::create(&empty).unwrap();
+        let archive = tar::Builder::new(zstd::Encoder::new(file, 0).unwrap());
+        archive.into_inner().unwrap().finish().unwrap();
+        let err = extract_backup(&empty, (&db_path, &search_index_path)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!db_path.exists());
+        assert!(!db_path.with_extension(UNPACK_EXTENSION).exists());
+    }
+}
@@ -0,0 +1,302 @@
+// Streaming sink that writes discovered entities to sled and tantivy in batches
+
+use crate::index::IndexManager;
+use crate::search::{DocFields, SearchIndex};
+use tantivy::IndexWriter;
+
+/// Entities written to sled per batch during a build
+pub const SLED_BATCH_SIZE: usize = 1000;
+/// Documents between intermediate tantivy commits during a build
+pub const COMMIT_INTERVAL: usize = 100_000;
+
+/// Buffers at most `SLED_BATCH_SIZE` entities, so memory stays bounded no
+/// matter how many files a root contains
+pub struct IndexBuilder<'a> {
+    index_manager: &'a IndexManager,
+    writer: IndexWriter,
+    fields: DocFields,
+    pending: Vec<crate::FileEntity>,
+    files_indexed: usize,
+    last_commit_at: usize,
+    marks: Option<crate::marks::MarkStore>,
+}
+
+impl<'a> IndexBuilder<'a> {
+    pub fn new(
+        index_manager: &'a IndexManager,
+        search_index: &SearchIndex,
+    ) -> Result<Self, String> {
+        let fields = search_index
+            .fields()
+            .map_err(|e| format!("Failed to resolve schema fields: {}", e))?;
+        let writer = search_index
+            .writer()
+            .map_err(|e| format!("Failed to create index writer: {}", e))?;
+
+        Ok(IndexBuilder {
+            index_manager,
+            writer,
+            fields,
+            pending: Vec::with_capacity(SLED_BATCH_SIZE),
+            files_indexed: 0,
+            last_commit_at: 0,
+            marks: None,
+        })
+    }
+
+    /// Copy the labels and ratings in `marks` onto entities as they are added
+    pub fn with_marks(mut self, marks: Option<crate::marks::MarkStore>) -> Self {
+        self.marks = marks;
+        self
+    }
+
+    /// Number of entities accepted so far, including ones not yet flushed
+    pub fn files_indexed(&self) -> usize {
+        self.files_indexed
+    }
+
+    pub fn add(&mut self, entity: crate::FileEntity) -> Result<(), String> {
+        self.pending.push(entity);
+        self.files_indexed += 1;
+        if self.pending.len() >= SLED_BATCH_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered entities to sled (one batch) and the tantivy writer
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(marks) = &self.marks {
+            marks.apply(&mut self.pending);
+        }
+
+        self.index_manager
+            .save_file_entities(&self.pending)
+            .map_err(|e| {
+                log::error!(
+                    "Failed to save batch of {} entities: {}",
+                    self.pending.len(),
+                    e
+                );
+                format!("Failed to save entity: {}", e)
+            })?;
+
+        for entity in self.pending.drain(..) {
+            self.writer
+                .add_document(self.fields.build_document(&entity))
+                .map_err(|e| {
+                    log::error!(
+                        "Failed to add document to search index for {}: {}",
+                        entity.path,
+                        e
+                    );
+                    format!("Failed to add document: {}", e)
+                })?;
+        }
+
+        // Periodic commits bound the writer's memory on very large builds
+        if self.files_indexed - self.last_commit_at >= COMMIT_INTERVAL {
+            self.commit_writer()?;
+            self.last_commit_at = self.files_indexed;
+            log::info!("Intermediate commit after {} files", self.files_indexed);
+        }
+        Ok(())
+    }
+
+    /// Flush remaining entities and make everything durable
+    pub fn finish(mut self) -> Result<usize, String> {
+        self.flush()?;
+        log::info!("Committing index...");
+        self.commit_writer()?;
+        self.index_manager.flush().map_err(|e| {
+            log::error!("Failed to flush index DB: {}", e);
+            format!("Failed to flush index DB: {}", e)
+        })?;
+        Ok(self.files_indexed)
+    }
+
+    fn commit_writer(&mut self) -> Result<(), String> {
+        self.writer.commit().map(|_| ()).map_err(|e| {
+            log::error!("Failed to commit index: {}", e);
+            format!("Failed to commit index: {}", e)
+        })
+    }
+}
+
+/// Regenerate every search document from the entities stored in sled,
+/// without touching the filesystem
+pub fn rebuild_search_index(
+    index_manager: &IndexManager,
+    search_index: &SearchIndex,
+) -> Result<usize, String> {
+    let fields = search_index
+        .fields()
+        .map_err(|e| format!("Failed to resolve schema fields: {}", e))?;
+    let mut writer: IndexWriter = search_index
+        .writer()
+        .map_err(|e| format!("Failed to create index writer: {}", e))?;
+    writer
+        .delete_all_documents()
+        .map_err(|e| format!("Failed to clear search index: {}", e))?;
+
+    let mut since_commit = 0;
+    let documents = index_manager.for_each_entity(|entity| {
+        writer
+            .add_document(fields.build_document(&entity))
+            .map_err(|e| format!("Failed to add document: {}", e))?;
+        since_commit += 1;
+        if since_commit >= COMMIT_INTERVAL {
+            writer
+                .commit()
+                .map_err(|e| format!("Failed to commit index: {}", e))?;
+            since_commit = 0;
+        }
+        Ok(())
+    })?;
+
+    writer.commit().map_err(|e| {
+        log::error!("Failed to commit index: {}", e);
+        format!("Failed to commit index: {}", e)
+    })?;
+    log::info!("Rebuilt search index from {} stored entities", documents);
+    Ok(documents)
+}
+
+/// Bring the search index up to date with journaled sled changes it may have
+/// missed, e.g. after a crash between the two writes. Each changed entity is
+/// re-read from sled, so replaying a delta twice is harmless. Returns the
+/// number of deltas replayed.
+pub fn replay_journal(
+    index_manager: &IndexManager,
+    search_index: &SearchIndex,
+) -> Result<usize, String> {
+    let pending = index_manager
+        .pending_deltas()
+        .map_err(|e| format!("Failed to read index journal: {}", e))?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let mut removed_ids = Vec::new();
+    let mut upserted = Vec::new();
+    for id in pending.iter().flat_map(|(_, ids)| ids) {
+        match index_manager.get_file_entity(id) {
+            Ok(Some(entity)) => upserted.push(entity),
+            Ok(None) => removed_ids.push(id.clone()),
+            Err(e) => return Err(format!("Failed to read journaled entry: {}", e)),
+        }
+    }
+    search_index
+        .apply_changes(&removed_ids, &upserted)
+        .map_err(|e| format!("Failed to replay index journal: {}", e))?;
+
+    for (seq, _) in &pending {
+        index_manager
+            .complete_delta(*seq)
+            .map_err(|e| format!("Failed to clear index journal: {}", e))?;
+    }
+    log::info!(
+        "Replayed {} journaled change(s) into the search index",
+        pending.len()
+    );
+    Ok(pending.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_builder_streams_walk_into_both_stores() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        for i in 0..10 {
+            File::create(root.join(format!("stream{}.txt", i))).unwrap();
+        }
+
+        let index_manager = IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        let search_index = SearchIndex::new(&temp_dir.path().join("search")).unwrap();
+
+        let mut builder = IndexBuilder::new(&index_manager, &search_index).unwrap();
+        index_manager
+            .walk_directory(&root, crate::index::WalkOptions::default(), |entity| {
+                builder.add(entity)
+            })
+            .unwrap();
+        assert_eq!(builder.files_indexed(), 11);
+        assert_eq!(builder.finish().unwrap(), 11);
+
+        assert_eq!(index_manager.count_files().unwrap(), 11);
+        assert_eq!(search_index.search("stream3", false, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_builder_flushes_full_batches() {
+        let temp_dir = tempdir().unwrap();
+        let index_manager = IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        let search_index = SearchIndex::new(&temp_dir.path().join("search")).unwrap();
+
+        let mut builder = IndexBuilder::new(&index_manager, &search_index).unwrap();
+        for i in 0..SLED_BATCH_SIZE + 5 {
+            let path = format!("/virtual/file{}.txt", i);
+            builder
+                .add(crate::FileEntity {
+                    id: crate::index::path_id(&path),
+                    name: format!("file{}.txt", i),
+                    path,
+                    size: 0,
+                    modified: 0,
+                    is_folder: false,
+                    hidden: false,
+                    attributes: Default::default(),
+                    created: None,
+                    accessed: None,
+                })
+                .unwrap();
+        }
+
+        // One full batch is already in sled before finish()
+        assert_eq!(index_manager.count_files().unwrap(), SLED_BATCH_SIZE);
+        builder.finish().unwrap();
+        assert_eq!(index_manager.count_files().unwrap(), SLED_BATCH_SIZE + 5);
+    }
+
+    #[test]
+    fn test_replay_journal_converges_search_with_sled() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        File::create(root.join("kept.txt")).unwrap();
+        File::create(root.join("gone.txt")).unwrap();
+
+        let index_manager = IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        let search_index = SearchIndex::new(&temp_dir.path().join("search")).unwrap();
+        let mut builder = IndexBuilder::new(&index_manager, &search_index).unwrap();
+        index_manager
+            .walk_directory(&root, crate::index::WalkOptions::default(), |entity| {
+                builder.add(entity)
+            })
+            .unwrap();
+        builder.finish().unwrap();
+
+        // sled changes whose search commit never happened
+        let gone = crate::index::entity_for_path(&root.join("gone.txt")).unwrap();
+        File::create(root.join("added.txt")).unwrap();
+        let added = crate::index::entity_for_path(&root.join("added.txt")).unwrap();
+        index_manager.apply_delta(&[gone.id], &[added]).unwrap();
+        assert_eq!(search_index.search("added", false, 10).unwrap().len(), 0);
+
+        assert_eq!(replay_journal(&index_manager, &search_index).unwrap(), 1);
+        assert_eq!(search_index.search("added", false, 10).unwrap().len(), 1);
+        assert_eq!(search_index.search("gone", false, 10).unwrap().len(), 0);
+        assert_eq!(search_index.search("kept", false, 10).unwrap().len(), 1);
+        assert!(index_manager.pending_deltas().unwrap().is_empty());
+        assert_eq!(replay_journal(&index_manager, &search_index).unwrap(), 0);
+    }
+}
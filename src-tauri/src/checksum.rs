@@ -0,0 +1,242 @@
+// File checksums, to verify a download found through search against the hash
+// its publisher lists
+
+use crate::error::{AppError, ErrorKind};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Bytes hashed at a time
+const CHUNK_SIZE: usize = 1 << 20;
+/// Least time between two progress reports
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha1 => "SHA-1",
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Blake3 => "BLAKE3",
+        }
+    }
+
+    /// Length of the hash in hex digits
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChecksumProgress {
+    pub path: String,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Checksum {
+    pub path: String,
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hex
+    pub hash: String,
+    pub size: u64,
+    /// Whether `hash` is the expected one, when one was given
+    pub matches: Option<bool>,
+}
+
+enum Hasher {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Hasher {
+        match algorithm {
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            HashAlgorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        let bytes = match self {
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// The hex hash in `expected`, which may be a line of `sha256sum`-style
+/// output with the file name after the hash. Fails when it can't be a hash
+/// of `algorithm`, which usually means another algorithm was picked.
+pub fn expected_hash(expected: &str, algorithm: HashAlgorithm) -> Result<String, AppError> {
+    let hash = expected
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "The expected hash must be hexadecimal",
+        ));
+    }
+    if hash.len() != algorithm.hex_len() {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "The expected hash has {} hex digits, but {} hashes have {}",
+                hash.len(),
+                algorithm.name(),
+                algorithm.hex_len()
+            ),
+        ));
+    }
+    Ok(hash)
+}
+
+/// Hash the file at `path`, reporting progress through `on_progress` at
+/// most every PROGRESS_INTERVAL and once at the end, and compare the result
+/// with `expected` when given
+pub fn compute(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    expected: Option<&str>,
+    mut on_progress: impl FnMut(&ChecksumProgress),
+) -> Result<Checksum, AppError> {
+    let expected = expected
+        .map(|expected| expected_hash(expected, algorithm))
+        .transpose()?;
+    let path_str = path.to_string_lossy().to_string();
+    let io_error = |e: std::io::Error| AppError::io(path_str.clone(), &e);
+    // Hashing a cloud placeholder would download all of it
+    if crate::index::is_online_only(&std::fs::metadata(path).map_err(io_error)?) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "File is online-only; make it available offline to hash it",
+        )
+        .with_path(path_str));
+    }
+    let mut file = std::fs::File::open(path).map_err(io_error)?;
+    let metadata = file.metadata().map_err(io_error)?;
+    if !metadata.is_file() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Not a file").with_path(path_str));
+    }
+
+    let mut progress = ChecksumProgress {
+        path: path_str.clone(),
+        bytes_done: 0,
+        bytes_total: metadata.len(),
+    };
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut last_report = Instant::now();
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(io_error(e)),
+        };
+        hasher.update(&buffer[..read]);
+        progress.bytes_done += read as u64;
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            last_report = Instant::now();
+            on_progress(&progress);
+        }
+    }
+    on_progress(&progress);
+
+    let hash = hasher.finish();
+    Ok(Checksum {
+        path: path_str,
+        algorithm,
+        matches: expected.map(|expected| expected == hash),
+        hash,
+        size: progress.bytes_done,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("abc.txt");
+        std::fs::write(&file, "abc").unwrap();
+        for (algorithm, hash) in [
+            (HashAlgorithm::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+            (
+                HashAlgorithm::Sha1,
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+            ),
+            (
+                HashAlgorithm::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                HashAlgorithm::Blake3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+        ] {
+            let mut reports = 0;
+            let checksum = compute(&file, algorithm, None, |_| reports += 1).unwrap();
+            assert_eq!(checksum.hash, hash, "{:?}", algorithm);
+            assert_eq!((checksum.size, checksum.matches), (3, None));
+            assert_eq!(reports, 1);
+        }
+    }
+
+    #[test]
+    fn test_verify_against_expected() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("abc.txt");
+        std::fs::write(&file, "abc").unwrap();
+        let sha256sum_line =
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD  abc.txt\n";
+        let checksum = compute(&file, HashAlgorithm::Sha256, Some(sha256sum_line), |_| {}).unwrap();
+        assert_eq!(checksum.matches, Some(true));
+        let other = "0".repeat(64);
+        let checksum = compute(&file, HashAlgorithm::Blake3, Some(&other), |_| {}).unwrap();
+        assert_eq!(checksum.matches, Some(false));
+
+        assert!(compute(&file, HashAlgorithm::Md5, Some(&other), |_| {}).is_err());
+        assert!(expected_hash("not-a-hash", HashAlgorithm::Md5).is_err());
+        assert!(compute(dir.path(), HashAlgorithm::Md5, None, |_| {}).is_err());
+    }
+}
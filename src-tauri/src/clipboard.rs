@@ -0,0 +1,190 @@
+// Copy a file's content to the OS clipboard without routing it through the webview
+
+use crate::error::{AppError, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are copied as a file reference instead of text
+pub const MAX_TEXT_BYTES: u64 = 16 * 1024 * 1024;
+/// Images larger than this (on disk) are copied as a file reference
+pub const MAX_IMAGE_BYTES: u64 = 64 * 1024 * 1024;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp"];
+
+/// What ends up on the clipboard for a given file
+#[derive(Debug, PartialEq)]
+pub enum ClipboardContent {
+    Text(String),
+    Image {
+        width: usize,
+        height: usize,
+        rgba: Vec<u8>,
+    },
+    Files(Vec<PathBuf>),
+}
+
+impl ClipboardContent {
+    /// Format name reported back to the frontend
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClipboardContent::Text(_) => "text",
+            ClipboardContent::Image { .. } => "image",
+            ClipboardContent::Files(_) => "files",
+        }
+    }
+}
+
+/// Pick the clipboard format for `path`: decoded pixels for images, the
+/// content of small UTF-8 files as text, and a file reference otherwise,
+/// online-only placeholders included
+pub fn clipboard_content(path: &Path) -> Result<ClipboardContent, AppError> {
+    let metadata = std::fs::metadata(path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    let reference = || ClipboardContent::Files(vec![path.to_path_buf()]);
+    // Reading a cloud placeholder would download it
+    if metadata.is_dir() || crate::index::is_online_only(&metadata) {
+        return Ok(reference());
+    }
+
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) && metadata.len() <= MAX_IMAGE_BYTES {
+        match image::open(path) {
+            Ok(img) => {
+                let rgba = img.to_rgba8();
+                return Ok(ClipboardContent::Image {
+                    width: rgba.width() as usize,
+                    height: rgba.height() as usize,
+                    rgba: rgba.into_raw(),
+                });
+            }
+            Err(e) => log::warn!("Could not decode image {}: {}", path.display(), e),
+        }
+    }
+
+    if metadata.len() <= MAX_TEXT_BYTES {
+        let bytes = std::fs::read(path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+        // NUL bytes mean binary data even when the bytes happen to be valid UTF-8
+        if !bytes.contains(&0) {
+            if let Ok(text) = String::from_utf8(bytes) {
+                return Ok(ClipboardContent::Text(text));
+            }
+        }
+    }
+    Ok(reference())
+}
+
+fn clipboard_error(err: arboard::Error) -> AppError {
+    AppError::new(
+        ErrorKind::Io,
+        format!("Clipboard operation failed: {}", err),
+    )
+}
+
+fn set_content(
+    clipboard: &mut arboard::Clipboard,
+    content: ClipboardContent,
+) -> Result<(), arboard::Error> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    use arboard::SetExtLinux;
+
+    let set = clipboard.set();
+    // X11/Wayland clipboards are served by the owning process; wait() keeps
+    // serving until another application takes ownership
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let set = set.wait();
+    match content {
+        ClipboardContent::Text(text) => set.text(text),
+        ClipboardContent::Image {
+            width,
+            height,
+            rgba,
+        } => set.image(arboard::ImageData {
+            width,
+            height,
+            bytes: rgba.into(),
+        }),
+        ClipboardContent::Files(paths) => set.file_list(&paths),
+    }
+}
+
+/// Put `path` on the clipboard and return the format used ("text", "image"
+/// or "files")
+pub fn copy_file_to_clipboard(path: &Path) -> Result<&'static str, AppError> {
+    let content = clipboard_content(path)?;
+    let kind = content.kind();
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let mut clipboard = arboard::Clipboard::new().map_err(clipboard_error)?;
+        std::thread::spawn(move || {
+            if let Err(e) = set_content(&mut clipboard, content) {
+                log::error!("Failed to set clipboard: {}", e);
+            }
+        });
+    }
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let mut clipboard = arboard::Clipboard::new().map_err(clipboard_error)?;
+        set_content(&mut clipboard, content).map_err(clipboard_error)?;
+    }
+
+    log::info!("Copied {} to clipboard as {}", path.display(), kind);
+    Ok(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_text_files_are_copied_as_text() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        std::fs::write(&path, "hello clipboard").unwrap();
+
+        assert_eq!(
+            clipboard_content(&path).unwrap(),
+            ClipboardContent::Text("hello clipboard".to_string())
+        );
+    }
+
+    #[test]
+    fn test_binary_files_and_folders_are_copied_as_references() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data.bin");
+        std::fs::write(&path, [0u8, 1, 2, 3]).unwrap();
+
+        assert_eq!(clipboard_content(&path).unwrap().kind(), "files");
+        assert_eq!(clipboard_content(temp_dir.path()).unwrap().kind(), "files");
+    }
+
+    #[test]
+    fn test_images_are_decoded_to_rgba() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("pixel.png");
+        image::RgbaImage::from_pixel(2, 3, image::Rgba([255, 0, 0, 255]))
+            .save(&path)
+            .unwrap();
+
+        match clipboard_content(&path).unwrap() {
+            ClipboardContent::Image {
+                width,
+                height,
+                rgba,
+            } => {
+                assert_eq!((width, height), (2, 3));
+                assert_eq!(rgba.len(), 2 * 3 * 4);
+            }
+            other => panic!("expected image, got {:?}", other.kind()),
+        }
+    }
+
+    #[test]
+    fn test_missing_file_is_not_found() {
+        let temp_dir = tempdir().unwrap();
+        let err = clipboard_content(&temp_dir.path().join("missing.txt")).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotFound);
+    }
+}
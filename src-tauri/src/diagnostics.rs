@@ -0,0 +1,207 @@
+// Recent log lines and diagnostics bundles (logs, index stats, system info
+// and settings in one zip) for bug reports
+
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Base name of the flexi_logger files in the log directory
+pub const LOG_BASENAME: &str = "crosseverything";
+/// Upper bound for get_recent_logs, which holds the lines in memory
+pub const MAX_LOG_LINES: usize = 10_000;
+const PARTIAL_EXTENSION: &str = "partial";
+const REDACTED: &str = "[redacted]";
+/// Keys whose values never leave the machine, matched case-insensitively
+const SECRET_KEYS: [&str; 5] = ["token", "secret", "password", "key", "private"];
+
+/// Log files in `log_dir`, oldest first. Rotated files carry a timestamp and
+/// the current one is `<basename>_rCURRENT.log`, so modification time orders
+/// them.
+pub fn log_files(log_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(log_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with(LOG_BASENAME) && name.ends_with(".log")
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// The last `lines` log lines across rotated files, oldest first
+pub fn recent_log_lines(log_dir: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let lines = lines.min(MAX_LOG_LINES);
+    let mut recent: Vec<String> = Vec::new();
+    for path in log_files(log_dir)?.iter().rev() {
+        if recent.len() >= lines {
+            break;
+        }
+        let content = fs::read(path)?;
+        let mut older: Vec<String> = String::from_utf8_lossy(&content)
+            .lines()
+            .rev()
+            .take(lines - recent.len())
+            .map(str::to_string)
+            .collect();
+        older.reverse();
+        older.append(&mut recent);
+        recent = older;
+    }
+    Ok(recent)
+}
+
+/// Replace the values of secret-looking keys anywhere in `value`
+pub fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, entry) in map.iter_mut() {
+                let key = key.to_lowercase();
+                if SECRET_KEYS.iter().any(|secret| key.contains(secret)) {
+                    *entry = Value::String(REDACTED.to_string());
+                } else {
+                    redact_secrets(entry);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+/// Write `reports` (file name and JSON) and the log files of `log_dir` into a
+/// zip at `destination`, returning its size in bytes. Reports should already
+/// be redacted. The zip is written beside it and renamed into place once
+/// complete.
+pub fn create_bundle(
+    destination: &Path,
+    reports: &[(&str, Value)],
+    log_dir: &Path,
+) -> io::Result<u64> {
+    let partial = destination.with_extension(PARTIAL_EXTENSION);
+    let written = File::create(&partial)
+        .and_then(|file| write_bundle(file, reports, log_dir))
+        .and_then(|_| fs::rename(&partial, destination));
+    if let Err(e) = written {
+        log::error!(
+            "Failed to write diagnostics bundle {:?}: {}",
+            destination,
+            e
+        );
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(fs::metadata(destination)?.len())
+}
+
+fn write_bundle(file: File, reports: &[(&str, Value)], log_dir: &Path) -> io::Result<()> {
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut zip = zip::ZipWriter::new(file);
+    for (name, report) in reports {
+        zip.start_file(*name, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(report)?)?;
+    }
+    // A missing log directory only means logging never started
+    let logs = match log_files(log_dir) {
+        Ok(logs) => logs,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    for path in logs {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(format!("logs/{}", name), options)?;
+        io::copy(&mut File::open(&path)?, &mut zip)?;
+    }
+    zip.finish()?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    fn write_log(dir: &Path, name: &str, lines: &[&str]) {
+        fs::write(dir.join(name), lines.join("\n") + "\n").unwrap();
+        // Modification times order the files
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_recent_log_lines_span_rotated_files() {
+        let temp_dir = tempdir().unwrap();
+        write_log(
+            temp_dir.path(),
+            "crosseverything_r2026-01-01_10-00-00.log",
+            &["a", "b", "c"],
+        );
+        write_log(temp_dir.path(), "crosseverything_rCURRENT.log", &["d", "e"]);
+        write_log(temp_dir.path(), "other.log", &["x"]);
+
+        assert_eq!(recent_log_lines(temp_dir.path(), 2).unwrap(), ["d", "e"]);
+        assert_eq!(
+            recent_log_lines(temp_dir.path(), 4).unwrap(),
+            ["b", "c", "d", "e"]
+        );
+        assert_eq!(recent_log_lines(temp_dir.path(), 100).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut value = json!({
+            "writer": {"heap_mb": 50},
+            "mcp": {"auth_token": "abc", "port": 8080},
+            "servers": [{"api_key": "xyz"}],
+            "private_paths": ["/home/me/diary"]
+        });
+        redact_secrets(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "writer": {"heap_mb": 50},
+                "mcp": {"auth_token": REDACTED, "port": 8080},
+                "servers": [{"api_key": REDACTED}],
+                "private_paths": REDACTED
+            })
+        );
+    }
+
+    #[test]
+    fn test_bundle_contains_reports_and_logs() {
+        let temp_dir = tempdir().unwrap();
+        let log_dir = temp_dir.path().join("logs");
+        fs::create_dir(&log_dir).unwrap();
+        write_log(&log_dir, "crosseverything_rCURRENT.log", &["started"]);
+        let destination = temp_dir.path().join("diagnostics.zip");
+
+        let bytes = create_bundle(
+            &destination,
+            &[("system.json", json!({"os": "linux"}))],
+            &log_dir,
+        )
+        .unwrap();
+        assert!(bytes > 0);
+        assert!(!destination.with_extension(PARTIAL_EXTENSION).exists());
+
+        let mut zip = zip::ZipArchive::new(File::open(&destination).unwrap()).unwrap();
+        let mut system = String::new();
+        zip.by_name("system.json")
+            .unwrap()
+            .read_to_string(&mut system)
+            .unwrap();
+        assert!(system.contains("linux"));
+        let mut log = String::new();
+        zip.by_name("logs/crosseverything_rCURRENT.log")
+            .unwrap()
+            .read_to_string(&mut log)
+            .unwrap();
+        assert_eq!(log, "started\n");
+    }
+}
@@ -0,0 +1,232 @@
+// Opt-in complete volume coverage on Windows: running elevated with the
+// backup privilege enabled lets the walk read directories whose ACLs only
+// admit administrators or SYSTEM. Elsewhere these are no-ops.
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the backup privilege is enabled on the process token
+static BACKUP_PRIVILEGE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ElevationStatus {
+    /// Whether elevated indexing exists on this platform
+    pub supported: bool,
+    pub elevated: bool,
+    /// Whether reads bypass ACLs through the backup privilege
+    pub backup_privilege: bool,
+}
+
+pub fn status() -> ElevationStatus {
+    ElevationStatus {
+        supported: cfg!(windows),
+        elevated: is_elevated(),
+        backup_privilege: BACKUP_PRIVILEGE.load(Ordering::SeqCst),
+    }
+}
+
+/// Quote `arg` for a Windows command line when it contains spaces or quotes
+#[cfg_attr(not(windows), allow(dead_code))]
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(windows)]
+fn is_elevated() -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::Security::{
+        GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY,
+    };
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    let mut token: HANDLE = std::ptr::null_mut();
+    // SAFETY: the pseudo handle of the current process is always valid
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return false;
+    }
+    let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+    let mut returned = 0;
+    // SAFETY: `elevation` is the buffer TokenElevation expects, sized below
+    let ok = unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut TOKEN_ELEVATION as *mut _,
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut returned,
+        )
+    };
+    // SAFETY: `token` was opened above and is not used afterwards
+    unsafe { CloseHandle(token) };
+    ok != 0 && elevation.TokenIsElevated != 0
+}
+
+#[cfg(not(windows))]
+fn is_elevated() -> bool {
+    false
+}
+
+/// Enable SeBackupPrivilege for the whole process. Only elevated
+/// administrators hold it; otherwise this fails with ERROR_NOT_ALL_ASSIGNED.
+#[cfg(windows)]
+pub fn enable_backup_privilege() -> std::io::Result<()> {
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, GetLastError, ERROR_NOT_ALL_ASSIGNED, HANDLE, LUID,
+    };
+    use windows_sys::Win32::Security::{
+        AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_BACKUP_NAME,
+        SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+    };
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    let mut token: HANDLE = std::ptr::null_mut();
+    // SAFETY: the pseudo handle of the current process is always valid
+    if unsafe {
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut token,
+        )
+    } == 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut luid = LUID {
+        LowPart: 0,
+        HighPart: 0,
+    };
+    // SAFETY: SE_BACKUP_NAME is a NUL-terminated constant
+    let result =
+        if unsafe { LookupPrivilegeValueW(std::ptr::null(), SE_BACKUP_NAME, &mut luid) } == 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            let privileges = TOKEN_PRIVILEGES {
+                PrivilegeCount: 1,
+                Privileges: [LUID_AND_ATTRIBUTES {
+                    Luid: luid,
+                    Attributes: SE_PRIVILEGE_ENABLED,
+                }],
+            };
+            // SAFETY: `privileges` is fully initialized; no previous state is requested
+            let ok = unsafe {
+                AdjustTokenPrivileges(
+                    token,
+                    0,
+                    &privileges,
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            };
+            // AdjustTokenPrivileges succeeds even when the token lacks the privilege
+            // SAFETY: reads the calling thread's last error
+            let last_error = unsafe { GetLastError() };
+            if ok == 0 {
+                Err(std::io::Error::last_os_error())
+            } else if last_error == ERROR_NOT_ALL_ASSIGNED {
+                Err(std::io::Error::from_raw_os_error(last_error as i32))
+            } else {
+                Ok(())
+            }
+        };
+    // SAFETY: `token` was opened above and is not used afterwards
+    unsafe { CloseHandle(token) };
+
+    if result.is_ok() {
+        BACKUP_PRIVILEGE.store(true, Ordering::SeqCst);
+    }
+    result
+}
+
+#[cfg(not(windows))]
+pub fn enable_backup_privilege() -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "The backup privilege only exists on Windows",
+    ))
+}
+
+/// Start another instance of the app with the "runas" verb so Windows
+/// prompts for elevation, passing this instance's arguments along. The
+/// caller exits once it returns. Cancelling the UAC prompt returns an `IO`
+/// error with `os_code` 1223.
+#[cfg(windows)]
+pub fn relaunch_elevated() -> Result<(), AppError> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::{ShellExecuteExW, SEE_MASK_NOASYNC, SHELLEXECUTEINFOW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let exe = std::env::current_exe().map_err(|e| AppError::io("current_exe", &e))?;
+    let parameters = std::env::args()
+        .skip(1)
+        .map(|arg| quote_arg(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let wide = |s: &std::ffi::OsStr| -> Vec<u16> { s.encode_wide().chain(Some(0)).collect() };
+    let verb = wide("runas".as_ref());
+    let file = crate::longpath::shell_wide(&exe);
+    let parameters = wide(parameters.as_ref());
+
+    // SAFETY: SHELLEXECUTEINFOW is plain data; all-zero is a valid empty value
+    let mut info: SHELLEXECUTEINFOW = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+    info.fMask = SEE_MASK_NOASYNC;
+    info.lpVerb = verb.as_ptr();
+    info.lpFile = file.as_ptr();
+    info.lpParameters = parameters.as_ptr();
+    info.nShow = SW_SHOWNORMAL;
+
+    // SAFETY: every string pointer refers to a NUL-terminated buffer above
+    if unsafe { ShellExecuteExW(&mut info) } == 0 {
+        let err = std::io::Error::last_os_error();
+        log::error!("Elevated relaunch failed: {}", err);
+        return Err(AppError::io(exe.to_string_lossy(), &err));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn relaunch_elevated() -> Result<(), AppError> {
+    Err(AppError::new(
+        crate::error::ErrorKind::Unsupported,
+        "Elevated indexing is only available on Windows",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(quote_arg("--hidden"), "--hidden");
+        assert_eq!(quote_arg(""), "\"\"");
+        assert_eq!(quote_arg(r"C:\My Files"), r#""C:\My Files""#);
+        assert_eq!(quote_arg(r"C:\My Files\"), r#""C:\My Files\\""#);
+        assert_eq!(quote_arg(r#"say "hi""#), r#""say \"hi\"""#);
+    }
+}
@@ -0,0 +1,431 @@
+// Saved emails: the sender, subject and date of .eml files, read from their
+// headers, and of Outlook .msg files, read from their MAPI properties, while
+// indexing so `from:` and `subject:` can find messages. Bodies are not read.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// Most bytes read looking for the end of the headers, or of one .msg
+/// property
+const MAX_HEADER_BYTES: u64 = 64 * 1024;
+
+/// MAPI properties read from .msg files, by property ID
+const PR_SUBJECT: u16 = 0x0037;
+const PR_CLIENT_SUBMIT_TIME: u16 = 0x0039;
+const PR_TRANSPORT_MESSAGE_HEADERS: u16 = 0x007D;
+const PR_SENDER_NAME: u16 = 0x0C1A;
+const PR_SENDER_EMAIL_ADDRESS: u16 = 0x0C1F;
+const PR_MESSAGE_DELIVERY_TIME: u16 = 0x0E06;
+const PR_MESSAGE_CODEPAGE: u16 = 0x3FFD;
+const PR_SENDER_SMTP_ADDRESS: u16 = 0x5D01;
+
+/// Seconds from the FILETIME epoch, 1601, to the Unix one
+const FILETIME_UNIX_OFFSET: i64 = 11_644_473_600;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EmailHeaders {
+    /// Decoded `From:` header, name and address as written
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    /// `Date:` header in Unix seconds
+    pub date: Option<i64>,
+}
+
+/// Headers of the saved email at `path`; None for other files, unreadable
+/// ones, or when none of the headers is present
+pub fn headers(path: &Path) -> Option<EmailHeaders> {
+    let extension = path.extension()?;
+    if extension.eq_ignore_ascii_case("eml") {
+        eml_headers(path)
+    } else if extension.eq_ignore_ascii_case("msg") {
+        msg_headers(path)
+    } else {
+        None
+    }
+}
+
+fn eml_headers(path: &Path) -> Option<EmailHeaders> {
+    let mut head = Vec::new();
+    let read = std::fs::File::open(path)
+        .and_then(|file| file.take(MAX_HEADER_BYTES).read_to_end(&mut head));
+    if let Err(e) = read {
+        log::debug!("Could not read email headers of {}: {}", path.display(), e);
+        return None;
+    }
+    parse_headers(&head)
+}
+
+/// Sender, subject and date from the header block at the start of `message`
+fn parse_headers(message: &[u8]) -> Option<EmailHeaders> {
+    let text = String::from_utf8_lossy(message);
+    let mut headers = EmailHeaders::default();
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+        match unfolded.last_mut() {
+            Some(last) if line.starts_with([' ', '\t']) => last.push_str(line),
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+    for line in &unfolded {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "from" if headers.from.is_none() => headers.from = Some(decode_words(value)),
+            "subject" if headers.subject.is_none() => headers.subject = Some(decode_words(value)),
+            "date" if headers.date.is_none() => headers.date = parse_date(value),
+            _ => {}
+        }
+    }
+    (headers != EmailHeaders::default()).then_some(headers)
+}
+
+/// Sender, subject and date of the Outlook message at `path`, an OLE
+/// compound file holding each property in a stream of its own. The original
+/// headers of mail received over SMTP fill in what the properties lack.
+fn msg_headers(path: &Path) -> Option<EmailHeaders> {
+    let mut file = match cfb::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            log::debug!(
+                "Could not read email properties of {}: {}",
+                path.display(),
+                e
+            );
+            return None;
+        }
+    };
+    let fixed = fixed_properties(&read_stream(&mut file, "__properties_version1.0")?);
+    let codepage = fixed.get(&PR_MESSAGE_CODEPAGE).map(|&value| value as u32);
+    let mut string = |id| msg_string(&mut file, id, codepage);
+
+    // Exchange senders carry an X.500 address unless the SMTP one is set
+    let address = string(PR_SENDER_SMTP_ADDRESS)
+        .or_else(|| string(PR_SENDER_EMAIL_ADDRESS).filter(|address| address.contains('@')));
+    let from = match (string(PR_SENDER_NAME), address) {
+        (Some(name), Some(address)) if name != address => Some(format!("{} <{}>", name, address)),
+        (name, address) => address.or(name),
+    };
+    let mut headers = EmailHeaders {
+        from,
+        subject: string(PR_SUBJECT),
+        date: [PR_CLIENT_SUBMIT_TIME, PR_MESSAGE_DELIVERY_TIME]
+            .iter()
+            .find_map(|id| fixed.get(id).copied().and_then(filetime_secs)),
+    };
+    if let Some(transport) =
+        string(PR_TRANSPORT_MESSAGE_HEADERS).and_then(|text| parse_headers(text.as_bytes()))
+    {
+        headers.from = headers.from.or(transport.from);
+        headers.subject = headers.subject.or(transport.subject);
+        headers.date = headers.date.or(transport.date);
+    }
+    (headers != EmailHeaders::default()).then_some(headers)
+}
+
+/// Contents of the stream `name` at the top of a .msg file, if present
+fn read_stream<F: Read + Seek>(file: &mut cfb::CompoundFile<F>, name: &str) -> Option<Vec<u8>> {
+    let stream = file.open_stream(format!("/{}", name)).ok()?;
+    let mut bytes = Vec::new();
+    stream.take(MAX_HEADER_BYTES).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Values of the fixed-size properties of a message, by property ID, from
+/// its property stream: a 32-byte header, then 16 bytes per property holding
+/// its tag, flags and value
+fn fixed_properties(stream: &[u8]) -> HashMap<u16, u64> {
+    stream
+        .get(32..)
+        .unwrap_or_default()
+        .chunks_exact(16)
+        .map(|entry| {
+            let tag = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+            let mut value = [0; 8];
+            value.copy_from_slice(&entry[8..16]);
+            ((tag >> 16) as u16, u64::from_le_bytes(value))
+        })
+        .collect()
+}
+
+/// String property `id` of a .msg file, stored as UTF-16 or in the
+/// message's code page
+fn msg_string<F: Read + Seek>(
+    file: &mut cfb::CompoundFile<F>,
+    id: u16,
+    codepage: Option<u32>,
+) -> Option<String> {
+    let text = match read_stream(file, &format!("__substg1.0_{:04X}001F", id)) {
+        Some(bytes) => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => {
+            let bytes = read_stream(file, &format!("__substg1.0_{:04X}001E", id))?;
+            let (text, _, _) = codepage_encoding(codepage).decode(&bytes);
+            text.into_owned()
+        }
+    };
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Encoding of a Windows code page, Windows-1252 when unknown
+fn codepage_encoding(codepage: Option<u32>) -> &'static encoding_rs::Encoding {
+    match codepage {
+        Some(65001) => encoding_rs::UTF_8,
+        Some(932) => encoding_rs::SHIFT_JIS,
+        Some(950) => encoding_rs::BIG5,
+        Some(codepage) => {
+            encoding_rs::Encoding::for_label(format!("windows-{}", codepage).as_bytes())
+                .unwrap_or(encoding_rs::WINDOWS_1252)
+        }
+        None => encoding_rs::WINDOWS_1252,
+    }
+}
+
+/// Unix seconds of a FILETIME, in 100 ns ticks since 1601; None when unset
+fn filetime_secs(ticks: u64) -> Option<i64> {
+    (ticks != 0).then(|| (ticks / 10_000_000) as i64 - FILETIME_UNIX_OFFSET)
+}
+
+/// Unix seconds of an RFC 2822 date, ignoring a trailing comment such as
+/// `(PDT)` that some mailers add
+fn parse_date(value: &str) -> Option<i64> {
+    let value = value.split('(').next().unwrap_or(value).trim();
+    chrono::DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+/// Decode the RFC 2047 encoded words (`=?charset?B?...?=` and
+/// `=?charset?Q?...?=`) in a header value. Whitespace between two encoded
+/// words is dropped, as the RFC asks; words that don't decode stay as written.
+fn decode_words(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        let word = encoded_word(candidate);
+        if !(after_word && word.is_some() && before.trim().is_empty()) {
+            decoded.push_str(before);
+        }
+        match word {
+            Some((text, length)) => {
+                decoded.push_str(&text);
+                rest = &candidate[length..];
+                after_word = true;
+            }
+            None => {
+                decoded.push_str("=?");
+                rest = &candidate[2..];
+                after_word = false;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The text of the encoded word `candidate` starts with, and its length
+fn encoded_word(candidate: &str) -> Option<(String, usize)> {
+    let inner = candidate.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let payload = &inner[..end];
+    let bytes = match encoding {
+        "B" | "b" => base64(payload)?,
+        "Q" | "q" => quoted_printable(payload)?,
+        _ => return None,
+    };
+    // A language may follow the charset, as in `utf-8*en`
+    let charset = charset.split('*').next().unwrap_or(charset);
+    let encoding = encoding_rs::Encoding::for_label(charset.as_bytes())?;
+    let (text, _, _) = encoding.decode(&bytes);
+    Some((text.into_owned(), candidate.len() - inner[end + 2..].len()))
+}
+
+/// Decode base64 with or without padding
+fn base64(payload: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(payload.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in payload.bytes().take_while(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Decode the `Q` encoding: quoted-printable with `_` for a space
+fn quoted_printable(payload: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(payload.len());
+    let mut input = payload.bytes();
+    while let Some(c) = input.next() {
+        match c {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = [input.next()?, input.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            c => bytes.push(c),
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let message = b"Received: from mx.example.com\r\n\
+            From: =?UTF-8?B?6rmA7LKg7IiY?= <chulsoo@example.kr>\r\n\
+            Subject: =?utf-8?Q?March_invoice_=E2=80=93?=\r\n =?utf-8?Q?_paid?= (copy)\r\n\
+            Date: Tue, 1 Jul 2003 10:52:37 +0200 (CEST)\r\n\
+            \r\n\
+            From: not a header\r\n";
+        let headers = parse_headers(message).unwrap();
+        assert_eq!(headers.from.as_deref(), Some("김철수 <chulsoo@example.kr>"));
+        assert_eq!(
+            headers.subject.as_deref(),
+            Some("March invoice \u{2013} paid (copy)")
+        );
+        assert_eq!(headers.date, Some(1057049557));
+    }
+
+    #[test]
+    fn test_decode_words() {
+        assert_eq!(decode_words("plain text"), "plain text");
+        assert_eq!(
+            decode_words("=?ks_c_5601-1987?B?x9Gx2yDH7LT1?="),
+            "한글 헤더"
+        );
+        assert_eq!(
+            decode_words("=?iso-8859-1?q?caf=E9?= au lait"),
+            "café au lait"
+        );
+        assert_eq!(decode_words("=?unknown?B?eA==?= x"), "=?unknown?B?eA==?= x");
+        assert_eq!(decode_words("50% =? off"), "50% =? off");
+    }
+
+    #[test]
+    fn test_headers_reads_only_emails() {
+        let dir = tempfile::tempdir().unwrap();
+        let email = dir.path().join("Lunch.EML");
+        std::fs::write(&email, "Subject: Lunch\n\nSee you at noon\n").unwrap();
+        let headers = headers(&email).unwrap();
+        assert_eq!(headers.subject.as_deref(), Some("Lunch"));
+        assert_eq!((headers.from, headers.date), (None, None));
+
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "Subject: Lunch\n").unwrap();
+        assert_eq!(super::headers(&text), None);
+        let empty = dir.path().join("empty.eml");
+        std::fs::write(&empty, "just a body").unwrap();
+        assert_eq!(super::headers(&empty), None);
+    }
+
+    /// Write an Outlook message with the property `streams`, named by tag,
+    /// and the fixed-size `properties`
+    fn write_msg(path: &Path, streams: &[(&str, Vec<u8>)], properties: &[(u32, u64)]) {
+        use std::io::Write;
+
+        let mut file = cfb::create(path).unwrap();
+        for (tag, bytes) in streams {
+            let mut stream = file.create_stream(format!("/__substg1.0_{}", tag)).unwrap();
+            stream.write_all(bytes).unwrap();
+        }
+        let mut fixed = vec![0; 32];
+        for (tag, value) in properties {
+            fixed.extend_from_slice(&tag.to_le_bytes());
+            fixed.extend_from_slice(&[0; 4]);
+            fixed.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut stream = file.create_stream("/__properties_version1.0").unwrap();
+        stream.write_all(&fixed).unwrap();
+        drop(stream);
+        file.flush().unwrap();
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_headers_reads_msg_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let sent = dir.path().join("Invoice.MSG");
+        write_msg(
+            &sent,
+            &[
+                ("0037001F", utf16("March invoice \u{2013} paid\0")),
+                ("0C1A001F", utf16("Zoë Park")),
+                ("0C1F001F", utf16("/O=EXAMPLE/OU=FIRST/CN=ZOE")),
+                ("5D01001F", utf16("zoe@billing.example.com")),
+            ],
+            &[(0x0039_0040, 127_003_236_000_000_000)],
+        );
+        let headers = headers(&sent).unwrap();
+        assert_eq!(
+            headers.from.as_deref(),
+            Some("Zoë Park <zoe@billing.example.com>")
+        );
+        assert_eq!(
+            headers.subject.as_deref(),
+            Some("March invoice \u{2013} paid")
+        );
+        assert_eq!(headers.date, Some(1_055_850_000));
+
+        // 8-bit strings in the message's code page; the date from the
+        // original headers
+        let received = dir.path().join("received.msg");
+        write_msg(
+            &received,
+            &[
+                ("0037001E", vec![0xC7, 0xD1, 0xB1, 0xDB]),
+                ("0C1A001E", b"Bob".to_vec()),
+                (
+                    "007D001F",
+                    utf16(
+                        "From: Bob <bob@example.com>\r\nDate: Tue, 1 Jul 2003 10:52:37 +0200\r\n",
+                    ),
+                ),
+            ],
+            &[(0x3FFD_0003, 949)],
+        );
+        let headers = super::headers(&received).unwrap();
+        assert_eq!(headers.subject.as_deref(), Some("한글"));
+        assert_eq!(headers.from.as_deref(), Some("Bob"));
+        assert_eq!(headers.date, Some(1057049557));
+
+        let broken = dir.path().join("broken.msg");
+        std::fs::write(&broken, "From: not a compound file\n").unwrap();
+        assert_eq!(super::headers(&broken), None);
+    }
+}
@@ -0,0 +1,342 @@
+// Meaning of names, notes and email subjects as vectors, so semantic_search
+// finds "Steuererklärung 2024.pdf" for "tax documents" without a shared word.
+// Vectors come from an embedding command the user sets up around a local
+// model: it reads one text per line on stdin and prints one vector per line,
+// its numbers separated by spaces or commas. Off until a command is set.
+// Entries are queued as they are indexed and embedded in batches in the
+// background; the vectors are kept in sled beside the entries. A search
+// compares the query's vector with every stored one; there is no approximate
+// nearest-neighbour index.
+
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+
+/// Most texts given to the command at once
+pub const BATCH: usize = 32;
+/// Most characters of one text
+const MAX_TEXT_CHARS: usize = 1_000;
+
+static COMMAND: RwLock<Option<String>> = RwLock::new(None);
+
+/// The command is split like those of actions, so it must parse
+pub fn validate(command: Option<&str>) -> Result<(), String> {
+    match command {
+        Some(command) => crate::actions::split_command(command)
+            .map(|_| ())
+            .map_err(|e| format!("Embedding command: {}", e)),
+        None => Ok(()),
+    }
+}
+
+/// Embed with `command` from now on; None turns semantic search off
+pub fn set_command(command: Option<&str>) {
+    *COMMAND.write().unwrap_or_else(|e| e.into_inner()) = command.map(str::to_string);
+}
+
+pub fn command() -> Option<String> {
+    COMMAND.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// What is embedded for `entity`: its name with the extension dropped and
+/// separators made spaces, then its note and email subject. None for hidden
+/// entries.
+pub fn text(entity: &crate::FileEntity) -> Option<String> {
+    if entity.hidden {
+        return None;
+    }
+    let name = if entity.is_folder {
+        entity.name.as_str()
+    } else {
+        Path::new(&entity.name)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(&entity.name)
+    };
+    let attributes = &entity.attributes;
+    let subject = attributes
+        .email
+        .as_ref()
+        .and_then(|email| email.subject.as_deref());
+    let text: String = [Some(name), attributes.note.as_deref(), subject]
+        .into_iter()
+        .flatten()
+        .map(|part| part.replace(['_', '-', '.'], " "))
+        .collect::<Vec<_>>()
+        .join(". ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TEXT_CHARS)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Fingerprint of `text` embedded by `command`. Stored with the vector, so
+/// one left from an older text or another model is told apart.
+pub fn stamp(command: &str, text: &str) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(command.as_bytes());
+    hasher.update([0]);
+    hasher.update(text.as_bytes());
+    let digest = hasher.finalize();
+    u64::from_be_bytes(digest[..8].try_into().unwrap_or_default())
+}
+
+/// Run `command` on `texts`, one per line, and return the vector printed for
+/// each
+pub fn embed(command: &str, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let command_line = crate::actions::split_command(command)?;
+    let mut child = Command::new(&command_line[0])
+        .args(&command_line[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", command_line[0], e))?;
+    // Written from another thread, as the command may print before it has
+    // read everything
+    let mut stdin = child.stdin.take().ok_or("No stdin for the command")?;
+    let mut input = texts.join("\n");
+    input.push('\n');
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {}", command_line[0], e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", command_line[0], output.status));
+    }
+    if let Ok(Err(e)) = writer.join() {
+        return Err(format!("Failed to write to {}: {}", command_line[0], e));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let vectors = stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse_vector(line).ok_or_else(|| format!("{} printed no vector", command_line[0]))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if vectors.len() != texts.len() {
+        return Err(format!(
+            "{} printed {} vector(s) for {} text(s)",
+            command_line[0],
+            vectors.len(),
+            texts.len()
+        ));
+    }
+    if vectors
+        .windows(2)
+        .any(|pair| pair[0].len() != pair[1].len())
+    {
+        return Err(format!(
+            "{} printed vectors of differing length",
+            command_line[0]
+        ));
+    }
+    Ok(vectors)
+}
+
+/// Numbers of one line, in brackets or not, separated by spaces or commas
+fn parse_vector(line: &str) -> Option<Vec<f32>> {
+    let vector = line
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(str::parse::<f32>)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    (!vector.is_empty() && vector.iter().all(|x| x.is_finite())).then_some(vector)
+}
+
+/// Vector and stamp as stored in sled
+pub fn encode(stamp: u64, vector: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + vector.len() * 4);
+    bytes.extend_from_slice(&stamp.to_be_bytes());
+    for x in vector {
+        bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Option<(u64, Vec<f32>)> {
+    if bytes.len() < 8 || (bytes.len() - 8) % 4 != 0 {
+        return None;
+    }
+    let stamp = u64::from_be_bytes(bytes[..8].try_into().ok()?);
+    let vector = bytes[8..]
+        .chunks_exact(4)
+        .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+        .collect();
+    Some((stamp, vector))
+}
+
+/// Cosine similarity; 0 for vectors of differing length or no length
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut a_norm, mut b_norm) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        a_norm += x * x;
+        b_norm += y * y;
+    }
+    if a_norm == 0.0 || b_norm == 0.0 {
+        return 0.0;
+    }
+    dot / (a_norm.sqrt() * b_norm.sqrt())
+}
+
+/// Stored vector compared with a query: its similarity, entity ID and stamp
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor {
+    pub score: f32,
+    pub id: String,
+    pub stamp: u64,
+}
+
+/// Ordered so the heap's top is the worst kept neighbor
+struct Worst(Neighbor);
+
+impl PartialEq for Worst {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Worst {}
+
+impl PartialOrd for Worst {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Worst {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.score.total_cmp(&self.0.score)
+    }
+}
+
+/// The `limit` of `vectors` most similar to `query`, best first
+pub fn nearest<I>(query: &[f32], vectors: I, limit: usize) -> Vec<Neighbor>
+where
+    I: IntoIterator<Item = (String, u64, Vec<f32>)>,
+{
+    let mut kept = BinaryHeap::with_capacity(limit + 1);
+    for (id, stamp, vector) in vectors {
+        if vector.len() != query.len() {
+            continue;
+        }
+        kept.push(Worst(Neighbor {
+            score: cosine(query, &vector),
+            id,
+            stamp,
+        }));
+        if kept.len() > limit {
+            kept.pop();
+        }
+    }
+    kept.into_sorted_vec()
+        .into_iter()
+        .map(|worst| worst.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(name: &str) -> crate::FileEntity {
+        crate::FileEntity {
+            id: name.to_string(),
+            name: name.to_string(),
+            path: format!("/docs/{}", name),
+            size: 1024,
+            modified: 0,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        }
+    }
+
+    #[test]
+    fn test_text_joins_name_note_and_subject() {
+        assert_eq!(
+            text(&entity("tax_return-2024.final.pdf")).as_deref(),
+            Some("tax return 2024 final")
+        );
+        let mut mail = entity("Re: rent.eml");
+        mail.attributes.note = Some("Landlord\nwants it by Friday".to_string());
+        mail.attributes.email = Some(crate::email::EmailHeaders {
+            subject: Some("Rent for March".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            text(&mail).as_deref(),
+            Some("Re: rent. Landlord wants it by Friday. Rent for March")
+        );
+        let mut hidden = entity(".secrets.txt");
+        hidden.hidden = true;
+        assert_eq!(text(&hidden), None);
+
+        assert_ne!(stamp("embed", "rent"), stamp("embed", "rent "));
+        assert_ne!(stamp("embed", "rent"), stamp("embed2", "rent"));
+        assert!(validate(Some("embed --model 'all MiniLM'")).is_ok());
+        assert!(validate(Some("embed '")).is_err());
+    }
+
+    #[test]
+    fn test_vectors_round_trip_and_rank() {
+        assert_eq!(parse_vector("[0.5, -1, 2e-1]"), Some(vec![0.5, -1.0, 0.2]));
+        assert_eq!(parse_vector("1 2\t3"), Some(vec![1.0, 2.0, 3.0]));
+        assert_eq!(parse_vector("[]"), None);
+        assert_eq!(parse_vector("1 NaN"), None);
+        assert_eq!(parse_vector("error: no model"), None);
+
+        let bytes = encode(7, &[0.25, -3.0]);
+        assert_eq!(decode(&bytes), Some((7, vec![0.25, -3.0])));
+        assert_eq!(decode(&bytes[..9]), None);
+
+        assert!((cosine(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine(&[1.0, 0.0], &[0.0, 0.0]), 0.0);
+        assert_eq!(cosine(&[1.0], &[1.0, 0.0]), 0.0);
+
+        let vectors = vec![
+            ("a".to_string(), 1, vec![0.0, 1.0]),
+            ("b".to_string(), 2, vec![1.0, 0.1]),
+            ("c".to_string(), 3, vec![1.0, 0.0, 0.0]),
+            ("d".to_string(), 4, vec![1.0, 1.0]),
+        ];
+        let ids = |limit| -> Vec<String> {
+            nearest(&[1.0, 0.0], vectors.clone(), limit)
+                .into_iter()
+                .map(|neighbor| neighbor.id)
+                .collect()
+        };
+        assert_eq!(ids(2), vec!["b", "d"]);
+        assert_eq!(ids(10), vec!["b", "d", "a"]);
+        assert!(ids(0).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_embed_reads_a_vector_per_text() {
+        let texts = vec!["tax return".to_string(), "holiday photos".to_string()];
+        let vectors = embed("sed -e s/.*/[1,0.5]/", &texts).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.5], vec![1.0, 0.5]]);
+        assert!(embed("head -n 1", &texts).is_err());
+        assert!(embed("false", &texts).is_err());
+    }
+}
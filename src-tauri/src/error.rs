@@ -0,0 +1,127 @@
+// Structured error returned by Tauri commands, serialized for the frontend
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorKind {
+    IndexNotReady,
+    InvalidRegex,
+    IndexingInProgress,
+    InvalidInput,
+    NotFound,
+    Io,
+    #[allow(dead_code)] // Only constructed on platforms lacking an API
+    Unsupported,
+    /// The caller abandoned the operation
+    Cancelled,
+    TimedOut,
+    Internal,
+}
+
+/// Serialized as `{ kind, message, path?, os_code? }` so the frontend can match
+/// on `kind` instead of parsing message strings
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_code: Option<i32>,
+}
+
+impl AppError {
+    /// Error with `message` shown in the current locale
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        AppError {
+            kind,
+            message: crate::i18n::tr(&message.into()),
+            path: None,
+            os_code: None,
+        }
+    }
+
+    pub fn index_not_ready() -> Self {
+        Self::new(
+            ErrorKind::IndexNotReady,
+            "Search index is not ready. Build the index first.",
+        )
+    }
+
+    pub fn invalid_regex(err: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::InvalidRegex, format!("Invalid regex: {}", err))
+    }
+
+    /// I/O failure tied to a path, keeping the OS error code when there is one
+    pub fn io(path: impl Into<String>, err: &std::io::Error) -> Self {
+        let kind = if err.kind() == std::io::ErrorKind::NotFound {
+            ErrorKind::NotFound
+        } else {
+            ErrorKind::Io
+        };
+        AppError {
+            kind,
+            message: err.to_string(),
+            path: Some(path.into()),
+            os_code: err.raw_os_error(),
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{} ({})", self.message, path),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Internal helpers still report plain strings; they surface as `INTERNAL`
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_kind_and_omits_empty_fields() {
+        let json = serde_json::to_value(AppError::index_not_ready()).unwrap();
+        assert_eq!(json["kind"], "INDEX_NOT_READY");
+        assert!(json["message"].is_string());
+        assert!(json.get("path").is_none());
+        assert!(json.get("os_code").is_none());
+    }
+
+    #[test]
+    fn test_io_error_keeps_path_and_code() {
+        let err = std::io::Error::from_raw_os_error(2);
+        let app_err = AppError::io("/missing", &err);
+        assert_eq!(app_err.kind, ErrorKind::NotFound);
+        assert_eq!(app_err.os_code, Some(2));
+
+        let json = serde_json::to_value(&app_err).unwrap();
+        assert_eq!(json["path"], "/missing");
+        assert_eq!(json["os_code"], 2);
+    }
+
+    #[test]
+    fn test_from_string_is_internal() {
+        let err: AppError = "boom".to_string().into();
+        assert_eq!(err.kind, ErrorKind::Internal);
+        assert_eq!(err.to_string(), "boom");
+    }
+}
@@ -0,0 +1,896 @@
+// File operations on search results (trash, restore, properties, drag-out,
+// terminal, elevated open, new file/folder)
+
+use crate::error::{AppError, ErrorKind};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// An item currently in the OS trash / Recycle Bin
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TrashEntry {
+    /// Platform identifier used to restore the item
+    pub id: String,
+    pub name: String,
+    pub original_path: String,
+    pub time_deleted: i64, // Unix timestamp in seconds
+}
+
+fn trash_error(err: trash::Error) -> AppError {
+    AppError::new(ErrorKind::Io, format!("Trash operation failed: {}", err))
+}
+
+/// Move files or folders to the trash (Recycle Bin on Windows, Finder trash on
+/// macOS, freedesktop trash on Linux)
+pub fn move_to_trash(paths: &[PathBuf]) -> Result<(), AppError> {
+    // Not `exists`, which would reject symlinks whose target is gone
+    if let Some(missing) = paths.iter().find(|p| p.symlink_metadata().is_err()) {
+        return Err(AppError::new(ErrorKind::NotFound, "File not found")
+            .with_path(missing.to_string_lossy()));
+    }
+    trash::delete_all(paths).map_err(|e| {
+        log::error!("Failed to move {} item(s) to trash: {}", paths.len(), e);
+        trash_error(e)
+    })
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+mod platform {
+    use super::*;
+    use trash::os_limited;
+
+    pub fn list_trash() -> Result<Vec<TrashEntry>, AppError> {
+        let mut entries: Vec<TrashEntry> = os_limited::list()
+            .map_err(trash_error)?
+            .into_iter()
+            .map(|item| TrashEntry {
+                id: item.id.to_string_lossy().to_string(),
+                name: item.name.to_string_lossy().to_string(),
+                original_path: item.original_path().to_string_lossy().to_string(),
+                time_deleted: item.time_deleted,
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.time_deleted));
+        Ok(entries)
+    }
+
+    pub fn restore_from_trash(ids: &[String]) -> Result<Vec<PathBuf>, AppError> {
+        let items: Vec<_> = os_limited::list()
+            .map_err(trash_error)?
+            .into_iter()
+            .filter(|item| {
+                ids.iter()
+                    .any(|id| item.id.to_string_lossy() == id.as_str())
+            })
+            .collect();
+        if items.len() != ids.len() {
+            return Err(AppError::new(
+                ErrorKind::NotFound,
+                "Some items are no longer in the trash",
+            ));
+        }
+
+        let restored = items.iter().map(|item| item.original_path()).collect();
+        os_limited::restore_all(items).map_err(|e| {
+            log::error!("Failed to restore from trash: {}", e);
+            trash_error(e)
+        })?;
+        Ok(restored)
+    }
+}
+
+// macOS exposes no API to enumerate or restore trashed items
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+mod platform {
+    use super::*;
+
+    fn unsupported() -> AppError {
+        AppError::new(
+            ErrorKind::Unsupported,
+            "Listing and restoring trash items is not supported on this platform",
+        )
+    }
+
+    pub fn list_trash() -> Result<Vec<TrashEntry>, AppError> {
+        Err(unsupported())
+    }
+
+    pub fn restore_from_trash(_ids: &[String]) -> Result<Vec<PathBuf>, AppError> {
+        Err(unsupported())
+    }
+}
+
+pub use platform::{list_trash, restore_from_trash};
+
+/// Stat data shown by the properties window
+#[derive(Debug, Clone, Serialize)]
+pub struct FileProperties {
+    pub path: String,
+    pub name: String,
+    pub is_folder: bool,
+    pub is_symlink: bool,
+    pub link_target: Option<String>,
+    pub size: u64,
+    pub modified: Option<i64>,
+    pub created: Option<i64>,
+    pub accessed: Option<i64>,
+    pub attributes: crate::FileAttributes,
+}
+
+pub fn file_properties(path: &Path) -> Result<FileProperties, AppError> {
+    let path_str = path.to_string_lossy().to_string();
+    let link_metadata =
+        std::fs::symlink_metadata(path).map_err(|e| AppError::io(path_str.clone(), &e))?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+    // Broken links fall back to the link's own metadata
+    let metadata = std::fs::metadata(path).unwrap_or(link_metadata);
+    let time = |t: std::io::Result<std::time::SystemTime>| t.ok().and_then(crate::index::unix_secs);
+
+    Ok(FileProperties {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path_str.clone()),
+        path: path_str,
+        is_folder: metadata.is_dir(),
+        is_symlink,
+        link_target: is_symlink
+            .then(|| std::fs::read_link(path).ok())
+            .flatten()
+            .map(|target| target.to_string_lossy().to_string()),
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        modified: time(metadata.modified()),
+        created: time(metadata.created()),
+        accessed: time(metadata.accessed()),
+        attributes: crate::index::file_attributes(&metadata),
+    })
+}
+
+/// Open the platform's properties dialog for `path`: the shell property sheet
+/// on Windows, Finder's Get Info on macOS, and a generated window elsewhere
+pub fn show_properties(app: &tauri::AppHandle, path: &Path) -> Result<(), AppError> {
+    if !path.exists() {
+        return Err(
+            AppError::new(ErrorKind::NotFound, "File not found").with_path(path.to_string_lossy())
+        );
+    }
+    native_properties(app, path)
+}
+
+#[cfg(windows)]
+fn native_properties(app: &tauri::AppHandle, path: &Path) -> Result<(), AppError> {
+    use windows_sys::Win32::UI::Shell::{SHObjectProperties, SHOP_FILEPATH};
+
+    let wide = crate::longpath::shell_wide(path);
+    // The property sheet is modeless and needs the UI thread's message loop
+    app.run_on_main_thread(move || {
+        // SAFETY: `wide` is a NUL-terminated UTF-16 path that outlives the call
+        let ok = unsafe {
+            SHObjectProperties(
+                std::ptr::null_mut(),
+                SHOP_FILEPATH as u32,
+                wide.as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        if ok == 0 {
+            log::error!("SHObjectProperties failed");
+        }
+    })
+    .map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            format!("Failed to open properties: {}", e),
+        )
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn native_properties(_app: &tauri::AppHandle, path: &Path) -> Result<(), AppError> {
+    let escaped = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    let script = format!(
+        "tell application \"Finder\"\nactivate\nopen information window of (POSIX file \"{}\" as alias)\nend tell",
+        escaped
+    );
+    let status = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .status()
+        .map_err(|e| AppError::io("osascript", &e))?;
+    if !status.success() {
+        return Err(AppError::new(
+            ErrorKind::Internal,
+            format!("Finder Get Info failed: {}", status),
+        )
+        .with_path(path.to_string_lossy()));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn native_properties(app: &tauri::AppHandle, path: &Path) -> Result<(), AppError> {
+    use tauri::Manager;
+
+    let properties = file_properties(path)?;
+    let label = format!(
+        "properties-{}",
+        &crate::index::path_id(&properties.path)[..16]
+    );
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let url = format!(
+        "data:text/html;charset=utf-8,{}",
+        percent_encode(&properties_html(&properties))
+    );
+    let url = url.parse().map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            format!("Invalid properties URL: {}", e),
+        )
+    })?;
+    tauri::WebviewWindowBuilder::new(app, label, tauri::WebviewUrl::External(url))
+        .title(format!("{} Properties", properties.name))
+        .inner_size(460.0, 420.0)
+        .resizable(false)
+        .build()
+        .map_err(|e| {
+            AppError::new(
+                ErrorKind::Internal,
+                format!("Failed to open properties window: {}", e),
+            )
+        })?;
+    Ok(())
+}
+
+/// Static HTML table of the stat data, used where no native dialog exists
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+fn properties_html(properties: &FileProperties) -> String {
+    let time = |t: Option<i64>| {
+        t.map(crate::format_timestamp_iso8601)
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let attributes = &properties.attributes;
+    let mut rows = vec![
+        ("Name", properties.name.clone()),
+        ("Location", properties.path.clone()),
+        (
+            "Type",
+            if properties.is_folder {
+                "Folder"
+            } else {
+                "File"
+            }
+            .to_string(),
+        ),
+        ("Size", format!("{} bytes", properties.size)),
+        ("Modified", time(properties.modified)),
+        ("Created", time(properties.created)),
+        ("Accessed", time(properties.accessed)),
+        (
+            "Read-only",
+            if attributes.readonly { "Yes" } else { "No" }.to_string(),
+        ),
+    ];
+    if let Some(target) = &properties.link_target {
+        rows.push(("Link target", target.clone()));
+    }
+    if let Some(mode) = attributes.mode {
+        rows.push(("Permissions", format!("{:o}", mode)));
+    }
+    if let (Some(uid), Some(gid)) = (attributes.uid, attributes.gid) {
+        rows.push(("Owner", format!("uid {} / gid {}", uid, gid)));
+    }
+
+    let body: String = rows
+        .iter()
+        .map(|(label, value)| format!("<tr><th>{}</th><td>{}</td></tr>", label, escape_html(value)))
+        .collect();
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><style>\
+         body{{font-family:sans-serif;font-size:13px;margin:16px}}\
+         th{{text-align:left;padding:4px 12px 4px 0;color:#555;white-space:nowrap}}\
+         td{{word-break:break-all}}</style></head><body><table>{}</table></body></html>",
+        body
+    )
+}
+
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Which optional explorer actions this platform supports, so the UI can hide
+/// the rest
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct PlatformCapabilities {
+    pub open_elevated: bool,
+    pub list_trash: bool,
+    pub drag_files: bool,
+    /// unblock_file can remove download markers
+    pub unblock: bool,
+    pub inspect_streams: bool,
+    /// open_in_wsl, reveal_in_explorer and list_wsl_distros work
+    pub wsl: bool,
+}
+
+pub fn platform_capabilities() -> PlatformCapabilities {
+    PlatformCapabilities {
+        open_elevated: cfg!(windows),
+        list_trash: cfg!(any(
+            target_os = "windows",
+            all(
+                unix,
+                not(target_os = "macos"),
+                not(target_os = "ios"),
+                not(target_os = "android")
+            )
+        )),
+        drag_files: cfg!(any(windows, target_os = "macos", target_os = "linux")),
+        unblock: crate::quarantine::SUPPORTED,
+        inspect_streams: crate::streams::SUPPORTED,
+        wsl: crate::wsl::SUPPORTED,
+    }
+}
+
+/// Launch `path` with the "runas" verb so Windows prompts for elevation.
+/// Cancelling the UAC prompt returns an `IO` error with `os_code` 1223.
+#[cfg(windows)]
+pub fn open_elevated(path: &Path) -> Result<(), AppError> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::{ShellExecuteExW, SEE_MASK_NOASYNC, SHELLEXECUTEINFOW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    if !path.exists() {
+        return Err(
+            AppError::new(ErrorKind::NotFound, "File not found").with_path(path.to_string_lossy())
+        );
+    }
+    let wide = |s: &std::ffi::OsStr| -> Vec<u16> { s.encode_wide().chain(Some(0)).collect() };
+    let verb = wide("runas".as_ref());
+    let file = crate::longpath::shell_wide(path);
+    let directory = crate::longpath::shell_wide(path.parent().unwrap_or(path));
+
+    // SAFETY: SHELLEXECUTEINFOW is plain data; all-zero is a valid empty value
+    let mut info: SHELLEXECUTEINFOW = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+    info.fMask = SEE_MASK_NOASYNC;
+    info.lpVerb = verb.as_ptr();
+    info.lpFile = file.as_ptr();
+    info.lpDirectory = directory.as_ptr();
+    info.nShow = SW_SHOWNORMAL;
+
+    // SAFETY: every string pointer refers to a NUL-terminated buffer above
+    if unsafe { ShellExecuteExW(&mut info) } == 0 {
+        let err = std::io::Error::last_os_error();
+        log::error!("Elevated open of {} failed: {}", path.display(), err);
+        return Err(AppError::io(path.to_string_lossy(), &err));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn open_elevated(_path: &Path) -> Result<(), AppError> {
+    Err(AppError::new(
+        ErrorKind::Unsupported,
+        "Elevated open is only available on Windows",
+    ))
+}
+
+/// A terminal launch: program plus arguments, run with the folder as its
+/// working directory
+#[derive(Debug, Clone, PartialEq)]
+struct TerminalLaunch {
+    program: String,
+    args: Vec<String>,
+}
+
+impl TerminalLaunch {
+    fn new(program: &str, args: &[&str]) -> Self {
+        TerminalLaunch {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+}
+
+/// Arguments that point a known terminal at `dir`; unknown programs rely on
+/// the inherited working directory alone
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn terminal_launch(program: &str, dir: &str) -> TerminalLaunch {
+    let name = Path::new(program)
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match name.as_str() {
+        "wt" => TerminalLaunch::new(program, &["-d", dir]),
+        "cmd" => TerminalLaunch::new(program, &["/C", "start", "cmd.exe", "/K"]),
+        "gnome-terminal" | "xfce4-terminal" | "mate-terminal" | "tilix" => {
+            TerminalLaunch::new(program, &[&format!("--working-directory={}", dir)])
+        }
+        "konsole" => TerminalLaunch::new(program, &["--workdir", dir]),
+        "alacritty" | "wezterm" => TerminalLaunch::new(program, &["--working-directory", dir]),
+        "kitty" => TerminalLaunch::new(program, &["--directory", dir]),
+        _ => TerminalLaunch::new(program, &[]),
+    }
+}
+
+/// Terminals to try in order: the configured one first, then platform defaults
+#[cfg(not(target_os = "macos"))]
+fn terminal_candidates(dir: &str, configured: Option<&str>) -> Vec<TerminalLaunch> {
+    let mut programs: Vec<String> = configured.map(str::to_string).into_iter().collect();
+    #[cfg(windows)]
+    programs.extend(["wt.exe", "cmd.exe"].map(String::from));
+    #[cfg(not(windows))]
+    {
+        if let Ok(terminal) = std::env::var("TERMINAL") {
+            programs.push(terminal);
+        }
+        programs.extend(
+            [
+                "x-terminal-emulator",
+                "gnome-terminal",
+                "konsole",
+                "xfce4-terminal",
+                "xterm",
+            ]
+            .map(String::from),
+        );
+    }
+    programs.iter().map(|p| terminal_launch(p, dir)).collect()
+}
+
+/// `open -a` resolves app names such as "iTerm" or "Terminal"
+#[cfg(target_os = "macos")]
+fn terminal_candidates(dir: &str, configured: Option<&str>) -> Vec<TerminalLaunch> {
+    let app = configured.unwrap_or("Terminal");
+    vec![TerminalLaunch::new("open", &["-a", app, dir])]
+}
+
+/// Open a terminal in `path`, or in its parent folder when `path` is a file.
+/// `terminal` names a preferred program; otherwise Windows Terminal/cmd,
+/// Terminal.app, or the first installed Linux terminal is used.
+pub fn open_terminal_here(path: &Path, terminal: Option<&str>) -> Result<(), AppError> {
+    let metadata = std::fs::metadata(path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    let dir = if metadata.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    // Windows can't start a process in a directory beyond MAX_PATH
+    let dir = crate::longpath::process_dir(dir);
+    let dir_str = dir.to_string_lossy().to_string();
+    let terminal = terminal.map(str::trim).filter(|t| !t.is_empty());
+
+    for launch in terminal_candidates(&dir_str, terminal) {
+        match std::process::Command::new(&launch.program)
+            .args(&launch.args)
+            .current_dir(&dir)
+            .spawn()
+        {
+            Ok(_) => {
+                log::info!("Opened {} in {}", launch.program, dir_str);
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::debug!("Terminal {} not available", launch.program);
+            }
+            Err(e) => return Err(AppError::io(dir_str, &e)),
+        }
+    }
+    Err(AppError::new(ErrorKind::NotFound, "No terminal emulator found").with_path(dir_str))
+}
+
+/// Starting points for `create_file`: a default name and initial content
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileTemplate {
+    pub id: &'static str,
+    pub default_name: &'static str,
+    pub content: &'static str,
+}
+
+pub const FILE_TEMPLATES: &[FileTemplate] = &[
+    FileTemplate {
+        id: "text",
+        default_name: "New Text Document.txt",
+        content: "",
+    },
+    FileTemplate {
+        id: "markdown",
+        default_name: "New Document.md",
+        content: "# New Document\n",
+    },
+    FileTemplate {
+        id: "empty",
+        default_name: "New File",
+        content: "",
+    },
+];
+
+pub const DEFAULT_FOLDER_NAME: &str = "New folder";
+
+/// Reject names that would escape the parent folder or are empty
+pub fn validate_entry_name(name: &str) -> Result<(), AppError> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() || trimmed == "." || trimmed == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid name: {:?}", name),
+        ));
+    }
+    Ok(())
+}
+
+/// `name` inside `parent`, numbered "Name (2).ext", "Name (3).ext", ... when
+/// taken, the way file managers name new entries
+pub fn unique_child(parent: &Path, name: &str) -> PathBuf {
+    let candidate = parent.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let as_path = Path::new(name);
+    let stem = as_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string());
+    let extension = as_path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| parent.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|p| !p.exists())
+        .expect("unbounded range always yields a free name")
+}
+
+fn parent_dir(parent: &Path) -> Result<(), AppError> {
+    let metadata =
+        std::fs::metadata(parent).map_err(|e| AppError::io(parent.to_string_lossy(), &e))?;
+    if !metadata.is_dir() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Not a folder")
+            .with_path(parent.to_string_lossy()));
+    }
+    Ok(())
+}
+
+/// Create a file in `parent` from a template (default "text"). Without an
+/// explicit name the template's default name is used, numbered if taken; an
+/// explicit name that already exists is an error rather than an overwrite.
+pub fn create_file(
+    parent: &Path,
+    name: Option<&str>,
+    template: Option<&str>,
+) -> Result<PathBuf, AppError> {
+    use std::io::Write;
+
+    let template_id = template.unwrap_or("text");
+    let template = FILE_TEMPLATES
+        .iter()
+        .find(|t| t.id == template_id)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown file template: {}", template_id),
+            )
+        })?;
+    parent_dir(parent)?;
+    let path = match name {
+        Some(name) => {
+            validate_entry_name(name)?;
+            parent.join(name)
+        }
+        None => unique_child(parent, template.default_name),
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    file.write_all(template.content.as_bytes())
+        .map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    log::info!("Created file {}", path.display());
+    Ok(path)
+}
+
+/// Create a folder in `parent`, named "New folder" (numbered if taken) unless
+/// `name` is given
+pub fn create_folder(parent: &Path, name: Option<&str>) -> Result<PathBuf, AppError> {
+    parent_dir(parent)?;
+    let path = match name {
+        Some(name) => {
+            validate_entry_name(name)?;
+            parent.join(name)
+        }
+        None => unique_child(parent, DEFAULT_FOLDER_NAME),
+    };
+    std::fs::create_dir(&path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    log::info!("Created folder {}", path.display());
+    Ok(path)
+}
+
+/// Absolute paths for a drag; every item must still exist on disk
+pub fn drag_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, AppError> {
+    if paths.is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Nothing to drag"));
+    }
+    paths
+        .iter()
+        .map(|path| {
+            std::fs::canonicalize(path)
+                .map(crate::longpath::simplify)
+                .map_err(|e| AppError::io(path.to_string_lossy(), &e))
+        })
+        .collect()
+}
+
+/// Start a native OS drag of `paths` out of `window`, so results can be dropped
+/// into other applications. Emits `drag-finished` with `{ result }` once the
+/// user drops or cancels.
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+pub fn start_drag(window: &tauri::WebviewWindow, paths: &[PathBuf]) -> Result<(), AppError> {
+    use tauri::Emitter;
+
+    let files = drag_paths(paths)?;
+    let target = window.clone();
+    // Drag sessions must begin on the UI thread that owns the window
+    window
+        .run_on_main_thread(move || {
+            let emitter = target.clone();
+            let on_drop = move |result: drag::DragResult, _: drag::CursorPosition| {
+                let result = match result {
+                    drag::DragResult::Dropped => "dropped",
+                    drag::DragResult::Cancel => "cancelled",
+                };
+                let _ = emitter.emit("drag-finished", serde_json::json!({ "result": result }));
+            };
+            let icon = drag::Image::Raw(include_bytes!("../icons/32x32.png").to_vec());
+
+            #[cfg(target_os = "linux")]
+            let started = match target.gtk_window() {
+                Ok(gtk_window) => drag::start_drag(
+                    &gtk_window,
+                    drag::DragItem::Files(files),
+                    icon,
+                    on_drop,
+                    Default::default(),
+                ),
+                Err(e) => {
+                    log::error!("No GTK window to drag from: {}", e);
+                    return;
+                }
+            };
+            #[cfg(not(target_os = "linux"))]
+            let started = drag::start_drag(
+                &target,
+                drag::DragItem::Files(files),
+                icon,
+                on_drop,
+                Default::default(),
+            );
+
+            if let Err(e) = started {
+                log::error!("Failed to start drag: {}", e);
+            }
+        })
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Failed to start drag: {}", e)))
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn start_drag(_window: &tauri::WebviewWindow, paths: &[PathBuf]) -> Result<(), AppError> {
+    drag_paths(paths)?;
+    Err(AppError::new(
+        ErrorKind::Unsupported,
+        "Dragging files out is not supported on this platform",
+    ))
+}
+
+/// Entity IDs for `path` and, for folders already removed from disk, everything
+/// the index has beneath it
+pub fn indexed_ids_under(index_manager: &crate::index::IndexManager, path: &Path) -> Vec<String> {
+    let path_str = path.to_string_lossy().to_string();
+    let prefix = format!("{}{}", path_str, std::path::MAIN_SEPARATOR);
+    let mut ids = vec![crate::index::path_id(&path_str)];
+    if let Ok(below) = index_manager.ids_with_prefix(&prefix) {
+        ids.extend(below);
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_move_to_trash_rejects_missing_paths() {
+        let temp_dir = tempdir().unwrap();
+        let missing = temp_dir.path().join("gone.txt");
+
+        let err = move_to_trash(std::slice::from_ref(&missing)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotFound);
+        assert_eq!(err.path, Some(missing.to_string_lossy().to_string()));
+    }
+
+    #[test]
+    fn test_file_properties_reports_stat_data() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("report.txt");
+        std::fs::write(&path, "hello").unwrap();
+
+        let properties = file_properties(&path).unwrap();
+        assert_eq!(properties.name, "report.txt");
+        assert_eq!(properties.size, 5);
+        assert!(!properties.is_folder);
+        assert!(!properties.is_symlink);
+        assert!(properties.modified.is_some());
+
+        let err = file_properties(&temp_dir.path().join("missing")).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_properties_html_escapes_values() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("<b>&.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let html = properties_html(&file_properties(&path).unwrap());
+        assert!(html.contains("&lt;b&gt;&amp;.txt"));
+        assert!(!html.contains("<b>&"));
+        assert_eq!(percent_encode("a b#"), "a%20b%23");
+    }
+
+    #[test]
+    fn test_drag_paths_are_absolute_and_must_exist() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("drag.txt");
+        std::fs::write(&path, "").unwrap();
+
+        let paths = drag_paths(std::slice::from_ref(&path)).unwrap();
+        assert!(paths[0].is_absolute());
+        assert_eq!(paths[0], std::fs::canonicalize(&path).unwrap());
+
+        let err = drag_paths(&[temp_dir.path().join("gone.txt")]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotFound);
+        assert_eq!(drag_paths(&[]).unwrap_err().kind, ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_terminal_candidates_prefer_configured_terminal() {
+        let candidates = terminal_candidates("/work", Some("kitty"));
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(
+            candidates[0],
+            TerminalLaunch::new("kitty", &["--directory", "/work"])
+        );
+        #[cfg(target_os = "macos")]
+        assert_eq!(candidates[0].args, vec!["-a", "kitty", "/work"]);
+
+        assert_eq!(
+            terminal_launch("/usr/bin/gnome-terminal", "/work").args,
+            vec!["--working-directory=/work"]
+        );
+        assert!(terminal_launch("xterm", "/work").args.is_empty());
+    }
+
+    #[test]
+    fn test_open_terminal_here_rejects_missing_path() {
+        let temp_dir = tempdir().unwrap();
+        let err = open_terminal_here(&temp_dir.path().join("missing"), None).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_open_elevated_matches_capabilities() {
+        let capabilities = platform_capabilities();
+        let temp_dir = tempdir().unwrap();
+        if !capabilities.open_elevated {
+            let err = open_elevated(temp_dir.path()).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Unsupported);
+        }
+        let json = serde_json::to_value(capabilities).unwrap();
+        assert!(json["open_elevated"].is_boolean());
+    }
+
+    #[test]
+    fn test_create_file_numbers_default_names() {
+        let temp_dir = tempdir().unwrap();
+        let first = create_file(temp_dir.path(), None, None).unwrap();
+        let second = create_file(temp_dir.path(), None, None).unwrap();
+        assert_eq!(first.file_name().unwrap(), "New Text Document.txt");
+        assert_eq!(second.file_name().unwrap(), "New Text Document (2).txt");
+
+        let markdown = create_file(temp_dir.path(), Some("todo.md"), Some("markdown")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(markdown).unwrap(),
+            "# New Document\n"
+        );
+
+        let err = create_file(temp_dir.path(), Some("todo.md"), None).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Io);
+        let err = create_file(temp_dir.path(), None, Some("spreadsheet")).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_create_folder_validates_names() {
+        let temp_dir = tempdir().unwrap();
+        let folder = create_folder(temp_dir.path(), None).unwrap();
+        assert!(folder.is_dir());
+        assert_eq!(
+            create_folder(temp_dir.path(), None)
+                .unwrap()
+                .file_name()
+                .unwrap(),
+            "New folder (2)"
+        );
+
+        for bad in ["", "..", "a/b"] {
+            let err = create_folder(temp_dir.path(), Some(bad)).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidInput);
+        }
+        let err = create_folder(&temp_dir.path().join("missing"), None).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_indexed_ids_under_includes_descendants() {
+        let temp_dir = tempdir().unwrap();
+        let manager = crate::index::IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        let root = temp_dir.path().join("project");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::File::create(root.join("src").join("main.rs")).unwrap();
+        std::fs::File::create(temp_dir.path().join("project-notes.txt")).unwrap();
+        for path in [
+            root.clone(),
+            root.join("src"),
+            root.join("src").join("main.rs"),
+            temp_dir.path().join("project-notes.txt"),
+        ] {
+            manager.add_or_update_file(&path).unwrap();
+        }
+
+        let ids = indexed_ids_under(&manager, &root);
+        assert_eq!(
+            ids.len(),
+            3,
+            "Sibling with a shared name prefix is excluded"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+// Sizes and dates as the UI shows them, in the user's locale, so every view
+// gets the same text instead of formatting raw bytes and timestamps itself
+
+use crate::i18n::{self, Locale};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// Dates further back than this show as a date rather than relative
+const RELATIVE_DAYS: i64 = 30;
+
+/// Display text of a search result
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResultDisplay {
+    /// Size with a unit; empty for folders
+    pub size: String,
+    /// Modification time relative to now, such as `2 hours ago`
+    pub modified: String,
+    /// Modification date and time
+    pub modified_at: String,
+}
+
+/// Fill in `display` of each result, in `locale` as of `now`
+pub fn annotate(results: &mut [crate::search::SearchResult], now: i64, locale: Locale) {
+    for result in results {
+        let modified = parse_timestamp(&result.modified);
+        result.display = Some(ResultDisplay {
+            size: if result.is_folder {
+                String::new()
+            } else {
+                size(result.size, locale)
+            },
+            modified: modified
+                .map(|modified| relative(modified, now, locale))
+                .unwrap_or_default(),
+            modified_at: modified
+                .map(|modified| date_time(modified, locale))
+                .unwrap_or_default(),
+        });
+    }
+}
+
+/// Unix seconds of an ISO 8601 timestamp
+pub fn parse_timestamp(timestamp: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+/// Size with a binary unit, such as `1.5 MB`
+pub fn size(bytes: u64, locale: Locale) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+    if bytes < 1024 {
+        return i18n::translate_fmt(locale, "{} bytes", &[&bytes]);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1} {}", value, UNITS[unit])
+    } else {
+        format!("{:.0} {}", value, UNITS[unit])
+    }
+}
+
+/// `timestamp` relative to `now`, such as `5 minutes ago` or `yesterday`,
+/// or its date when older than `RELATIVE_DAYS` or in the future
+pub fn relative(timestamp: i64, now: i64, locale: Locale) -> String {
+    let seconds = now - timestamp;
+    let (template, count) = match seconds {
+        s if s < -60 => return date(timestamp, locale),
+        s if s < 60 => return i18n::translate(locale, "just now"),
+        s if s < 120 => ("{} minute ago", 1),
+        s if s < 3600 => ("{} minutes ago", s / 60),
+        s if s < 7200 => ("{} hour ago", 1),
+        s if s < 86400 => ("{} hours ago", s / 3600),
+        s if s < 2 * 86400 => return i18n::translate(locale, "yesterday"),
+        s if s < RELATIVE_DAYS * 86400 => ("{} days ago", s / 86400),
+        _ => return date(timestamp, locale),
+    };
+    i18n::translate_fmt(locale, template, &[&count])
+}
+
+fn local(timestamp: i64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(timestamp, 0).single()
+}
+
+/// Local date, such as `Mar 1, 2024` or `2024. 3. 1.`
+pub fn date(timestamp: i64, locale: Locale) -> String {
+    let pattern = match locale {
+        Locale::En => "%b %-d, %Y",
+        Locale::Ko => "%Y. %-m. %-d.",
+    };
+    local(timestamp)
+        .map(|date| date.format(pattern).to_string())
+        .unwrap_or_default()
+}
+
+/// Local date and time to the minute
+pub fn date_time(timestamp: i64, locale: Locale) -> String {
+    let pattern = match locale {
+        Locale::En => "%b %-d, %Y %-I:%M %p",
+        Locale::Ko => "%Y. %-m. %-d. %H:%M",
+    };
+    local(timestamp)
+        .map(|date| date.format(pattern).to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_and_relative_dates() {
+        assert_eq!(size(512, Locale::En), "512 bytes");
+        assert_eq!(size(512, Locale::Ko), "512바이트");
+        assert_eq!(size(1536, Locale::En), "1.5 KB");
+        assert_eq!(size(300 * 1024 * 1024, Locale::Ko), "300 MB");
+
+        let now = 1_700_000_000;
+        assert_eq!(relative(now - 10, now, Locale::En), "just now");
+        assert_eq!(relative(now - 90, now, Locale::En), "1 minute ago");
+        assert_eq!(relative(now - 2 * 3600, now, Locale::En), "2 hours ago");
+        assert_eq!(relative(now - 2 * 3600, now, Locale::Ko), "2시간 전");
+        assert_eq!(relative(now - 100_000, now, Locale::Ko), "어제");
+        assert_eq!(relative(now - 5 * 86400, now, Locale::En), "5 days ago");
+        assert_eq!(
+            relative(now - 90 * 86400, now, Locale::En),
+            date(now - 90 * 86400, Locale::En)
+        );
+        assert_eq!(
+            relative(now + 3600, now, Locale::En),
+            date(now + 3600, Locale::En)
+        );
+        assert_eq!(parse_timestamp("2023-11-14T22:13:20Z"), Some(now));
+    }
+}
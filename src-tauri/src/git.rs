@@ -0,0 +1,305 @@
+// Git work trees met while indexing. Entries below one are annotated with the
+// repository root and their status, read with a single `git status` per
+// repository. Statuses are a snapshot from when an entry was indexed: a commit
+// does not touch the files it records, so they refresh on the next re-index.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How long a repository's status is reused for single-path updates, so a
+/// burst of watcher events runs git once per repository
+const STATUS_TTL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GitStatus {
+    /// Tracked and unchanged
+    Tracked,
+    /// Tracked with staged or unstaged changes
+    Modified,
+    Untracked,
+    Ignored,
+}
+
+impl GitStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GitStatus::Tracked => "tracked",
+            GitStatus::Modified => "modified",
+            GitStatus::Untracked => "untracked",
+            GitStatus::Ignored => "ignored",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<GitStatus> {
+        [
+            GitStatus::Tracked,
+            GitStatus::Modified,
+            GitStatus::Untracked,
+            GitStatus::Ignored,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == value)
+    }
+}
+
+/// Whether `dir` is the top of a work tree. `.git` is a folder in a plain
+/// clone and a file in linked worktrees and submodules.
+pub fn is_repo_root(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+/// Name `repo:` filters match a repository by: its folder name, lowercased
+pub fn repo_name(root: &Path) -> String {
+    root.file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Status of one work tree
+#[derive(Debug)]
+pub struct Repo {
+    root: PathBuf,
+    /// Entries `git status` reported, relative to the root. A folder listed
+    /// as untracked or ignored covers everything below it. None when git
+    /// could not be run, leaving statuses unknown.
+    changes: Option<HashMap<PathBuf, GitStatus>>,
+}
+
+impl Repo {
+    /// Read the status of the work tree at `root`
+    pub fn load(root: &Path) -> Repo {
+        let changes = match status_output(root) {
+            Ok(output) => Some(parse_status(&output)),
+            Err(e) => {
+                log::debug!("No git status for {}: {}", root.display(), e);
+                None
+            }
+        };
+        Repo {
+            root: root.to_path_buf(),
+            changes,
+        }
+    }
+
+    /// Set the repository and status of the entry at `path`, which lies
+    /// inside this work tree. The root and git's own files get no status.
+    pub fn annotate(&self, path: &Path, attributes: &mut crate::FileAttributes) {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return;
+        };
+        attributes.repo_root = Some(self.root.to_string_lossy().to_string());
+        if relative.as_os_str().is_empty() || relative.starts_with(".git") {
+            return;
+        }
+        let Some(changes) = &self.changes else {
+            return;
+        };
+        let status = relative
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty())
+            .find_map(|ancestor| changes.get(ancestor))
+            .copied()
+            .unwrap_or(GitStatus::Tracked);
+        attributes.git_status = Some(status);
+    }
+}
+
+fn status_output(root: &Path) -> std::io::Result<Vec<u8>> {
+    let mut command = std::process::Command::new("git");
+    command
+        // Reading status would otherwise refresh and lock the repo's index
+        .arg("--no-optional-locks")
+        .arg("-C")
+        .arg(root)
+        .args(["status", "--porcelain=v1", "-z", "--ignored"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("git status exited with {}", output.status),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Parse `git status --porcelain=v1 -z` output into statuses by relative
+/// path. Folders are listed with a trailing slash; renames and copies are
+/// followed by their source path, which is skipped.
+fn parse_status(output: &[u8]) -> HashMap<PathBuf, GitStatus> {
+    let mut changes = HashMap::new();
+    let mut records = output.split(|&b| b == 0).filter(|r| !r.is_empty());
+    while let Some(record) = records.next() {
+        let (Some(code), Some(path)) = (record.get(..2), record.get(3..)) else {
+            continue;
+        };
+        let status = match code {
+            b"??" => GitStatus::Untracked,
+            b"!!" => GitStatus::Ignored,
+            _ => GitStatus::Modified,
+        };
+        if code.contains(&b'R') || code.contains(&b'C') {
+            records.next();
+        }
+        let path = String::from_utf8_lossy(path);
+        changes.insert(PathBuf::from(path.trim_end_matches('/')), status);
+    }
+    changes
+}
+
+/// Work tree `path` lies in, `path` itself included
+fn find_root(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|dir| is_repo_root(dir))
+}
+
+/// Repositories loaded for single-path updates, with when they were loaded
+type RepoCache = Mutex<HashMap<PathBuf, (Instant, Arc<Repo>)>>;
+
+/// Annotate an entry indexed on its own, outside of a walk. Repositories are
+/// loaded at most once per STATUS_TTL.
+pub fn annotate(path: &Path, attributes: &mut crate::FileAttributes) {
+    static REPOS: OnceLock<RepoCache> = OnceLock::new();
+
+    let Some(root) = find_root(path) else {
+        return;
+    };
+    let repo = {
+        let mut repos = REPOS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        repos.retain(|_, (loaded, _)| loaded.elapsed() < STATUS_TTL);
+        repos
+            .entry(root.to_path_buf())
+            .or_insert_with(|| (Instant::now(), Arc::new(Repo::load(root))))
+            .1
+            .clone()
+    };
+    repo.annotate(path, attributes);
+}
+
+/// Repositories enclosing the entries of a depth-first walk, innermost last
+#[derive(Default)]
+pub struct RepoStack {
+    /// Walk depth each repository's root was met at; None for one enclosing
+    /// the walk's root, which stays for the whole walk
+    repos: Vec<(Option<usize>, Arc<Repo>)>,
+}
+
+impl RepoStack {
+    /// Stack for a walk starting at `root`, holding the repository it lies in
+    pub fn new(root: &Path) -> RepoStack {
+        let mut stack = RepoStack::default();
+        if let Some(enclosing) = root.parent().and_then(find_root) {
+            stack.repos.push((None, Arc::new(Repo::load(enclosing))));
+        }
+        stack
+    }
+
+    /// Annotate the entry at `path`, met `depth` levels into the walk,
+    /// loading the repository when `path` is the root of one
+    pub fn annotate(
+        &mut self,
+        path: &Path,
+        depth: usize,
+        repo_root: bool,
+        attributes: &mut crate::FileAttributes,
+    ) {
+        while matches!(self.repos.last(), Some((Some(at), _)) if *at >= depth) {
+            self.repos.pop();
+        }
+        if repo_root {
+            self.repos.push((Some(depth), Arc::new(Repo::load(path))));
+        }
+        if let Some((_, repo)) = self.repos.last() {
+            repo.annotate(path, attributes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let output = b" M src/lib.rs\0A  new.rs\0R  moved.rs\0old.rs\0?? scratch/\0!! target/\0";
+        let changes = parse_status(output);
+        assert_eq!(changes.len(), 5);
+        assert_eq!(changes[Path::new("src/lib.rs")], GitStatus::Modified);
+        assert_eq!(changes[Path::new("new.rs")], GitStatus::Modified);
+        assert_eq!(changes[Path::new("moved.rs")], GitStatus::Modified);
+        assert!(!changes.contains_key(Path::new("old.rs")));
+        assert_eq!(changes[Path::new("scratch")], GitStatus::Untracked);
+        assert_eq!(changes[Path::new("target")], GitStatus::Ignored);
+    }
+
+    #[test]
+    fn test_repo_annotate() {
+        let repo = Repo {
+            root: PathBuf::from("/code/app"),
+            changes: Some(parse_status(b" M src/lib.rs\0!! target/\0")),
+        };
+        let status = |path: &str| {
+            let mut attributes = crate::FileAttributes::default();
+            repo.annotate(Path::new(path), &mut attributes);
+            (attributes.repo_root, attributes.git_status)
+        };
+        let root = Some("/code/app".to_string());
+        assert_eq!(
+            status("/code/app/src/lib.rs"),
+            (root.clone(), Some(GitStatus::Modified))
+        );
+        assert_eq!(
+            status("/code/app/src/main.rs"),
+            (root.clone(), Some(GitStatus::Tracked))
+        );
+        assert_eq!(
+            status("/code/app/target/debug/app"),
+            (root.clone(), Some(GitStatus::Ignored))
+        );
+        assert_eq!(status("/code/app/.git/HEAD"), (root.clone(), None));
+        assert_eq!(status("/code/app"), (root, None));
+        assert_eq!(status("/code/other/file"), (None, None));
+    }
+
+    #[test]
+    fn test_walk_annotates_repositories() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("Project");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::write(repo.join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("outside.txt"), "").unwrap();
+
+        let manager = crate::index::IndexManager::new(&dir.path().join("db")).unwrap();
+        let mut annotated = HashMap::new();
+        manager
+            .walk_directory(&repo, crate::index::WalkOptions::default(), |entity| {
+                annotated.insert(entity.name, entity.attributes.repo_root);
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        let root = Some(repo.to_string_lossy().to_string());
+        assert_eq!(annotated["main.rs"], root);
+        assert_eq!(annotated["Project"], root);
+        assert_eq!(repo_name(&repo), "project");
+
+        let mut attributes = crate::FileAttributes::default();
+        annotate(&dir.path().join("outside.txt"), &mut attributes);
+        assert_eq!(attributes.repo_root, None);
+        annotate(&repo.join("src/main.rs"), &mut attributes);
+        assert_eq!(attributes.repo_root, root);
+    }
+}
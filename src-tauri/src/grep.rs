@@ -0,0 +1,222 @@
+// In-file content grep over a set of search results
+
+use memmap2::Mmap;
+use regex::bytes::Regex;
+use serde::Serialize;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Files larger than this are skipped to keep scans responsive
+pub const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+/// Stop scanning once this many matching lines have been reported
+pub const MAX_MATCHES: usize = 10_000;
+/// Number of leading bytes inspected for NUL to detect binary files
+const BINARY_SNIFF_LEN: usize = 8192;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GrepSummary {
+    pub files_scanned: usize,
+    pub files_matched: usize,
+    pub total_matches: usize,
+    pub truncated: bool,
+}
+
+/// Scan `paths` in parallel, invoking `on_match` for each matching line
+pub fn grep_files<F>(paths: &[PathBuf], pattern: &Regex, on_match: F) -> GrepSummary
+where
+    F: Fn(GrepMatch) + Sync,
+{
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let scanned = AtomicUsize::new(0);
+    let matched_files = AtomicUsize::new(0);
+    let total_matches = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if total_matches.load(Ordering::Relaxed) >= MAX_MATCHES {
+                    break;
+                }
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+
+                let mut file_matched = false;
+                let scanned_file = scan_file(path, pattern, |m| {
+                    if total_matches.fetch_add(1, Ordering::Relaxed) < MAX_MATCHES {
+                        file_matched = true;
+                        on_match(m);
+                        true
+                    } else {
+                        false
+                    }
+                });
+                if scanned_file {
+                    scanned.fetch_add(1, Ordering::Relaxed);
+                }
+                if file_matched {
+                    matched_files.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let total = total_matches.load(Ordering::Relaxed);
+    GrepSummary {
+        files_scanned: scanned.load(Ordering::Relaxed),
+        files_matched: matched_files.load(Ordering::Relaxed),
+        total_matches: total.min(MAX_MATCHES),
+        truncated: total > MAX_MATCHES,
+    }
+}
+
+/// Scan a single file; returns false if the file was skipped.
+/// `on_match` returns false to stop scanning this file.
+fn scan_file<F>(path: &Path, pattern: &Regex, mut on_match: F) -> bool
+where
+    F: FnMut(GrepMatch) -> bool,
+{
+    // Reading a cloud placeholder would download it
+    if std::fs::metadata(path).is_ok_and(|m| crate::index::is_online_only(&m)) {
+        log::debug!("Skipping online-only {} for grep", path.display());
+        return false;
+    }
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            log::debug!("Skipping {} for grep: {}", path.display(), e);
+            return false;
+        }
+    };
+    let metadata = match file.metadata() {
+        Ok(m) => m,
+        Err(e) => {
+            log::debug!("Skipping {} for grep: {}", path.display(), e);
+            return false;
+        }
+    };
+    if !metadata.is_file() || metadata.len() > MAX_FILE_SIZE {
+        return false;
+    }
+    if metadata.len() == 0 {
+        return true;
+    }
+
+    // SAFETY: the mapping is read-only and only lives for this scan. If another
+    // process truncates the file concurrently we may read garbage, which only
+    // affects the reported lines.
+    let mmap = match unsafe { Mmap::map(&file) } {
+        Ok(m) => m,
+        Err(e) => {
+            log::debug!("Failed to mmap {}: {}", path.display(), e);
+            return false;
+        }
+    };
+
+    let sniff = &mmap[..mmap.len().min(BINARY_SNIFF_LEN)];
+    if sniff.contains(&0) {
+        return false;
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+    for (i, line) in mmap.split(|b| *b == b'\n').enumerate() {
+        if pattern.is_match(line) {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let keep_going = on_match(GrepMatch {
+                path: path_str.clone(),
+                line_number: i + 1,
+                line: String::from_utf8_lossy(line).to_string(),
+            });
+            if !keep_going {
+                break;
+            }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    fn collect(paths: &[PathBuf], pattern: &str) -> (Vec<GrepMatch>, GrepSummary) {
+        let regex = Regex::new(pattern).unwrap();
+        let found = Mutex::new(Vec::new());
+        let summary = grep_files(paths, &regex, |m| found.lock().unwrap().push(m));
+        let mut found = found.into_inner().unwrap();
+        found.sort_by(|a, b| (&a.path, a.line_number).cmp(&(&b.path, b.line_number)));
+        (found, summary)
+    }
+
+    #[test]
+    fn test_grep_reports_line_numbers() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "alpha\nbeta\r\ngamma beta\n").unwrap();
+
+        let (found, summary) = collect(&[file], "beta");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].line_number, 2);
+        assert_eq!(found[0].line, "beta", "CRLF should be stripped");
+        assert_eq!(found[1].line_number, 3);
+        assert_eq!(summary.files_scanned, 1);
+        assert_eq!(summary.files_matched, 1);
+        assert_eq!(summary.total_matches, 2);
+        assert!(!summary.truncated);
+    }
+
+    #[test]
+    fn test_grep_skips_binary_dirs_and_missing() {
+        let temp_dir = tempdir().unwrap();
+        let binary = temp_dir.path().join("bin.dat");
+        fs::write(&binary, b"needle\0needle").unwrap();
+        let missing = temp_dir.path().join("missing.txt");
+        let dir = temp_dir.path().to_path_buf();
+
+        let (found, summary) = collect(&[binary, missing, dir], "needle");
+        assert!(found.is_empty());
+        assert_eq!(summary.files_scanned, 0);
+    }
+
+    #[test]
+    fn test_grep_many_files_in_parallel() {
+        let temp_dir = tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..20)
+            .map(|i| {
+                let p = temp_dir.path().join(format!("f{}.txt", i));
+                let body = if i % 2 == 0 { "hit\nmiss\n" } else { "miss\n" };
+                fs::write(&p, body).unwrap();
+                p
+            })
+            .collect();
+
+        let (found, summary) = collect(&paths, "^hit$");
+        assert_eq!(found.len(), 10);
+        assert_eq!(summary.files_scanned, 20);
+        assert_eq!(summary.files_matched, 10);
+    }
+
+    #[test]
+    fn test_grep_empty_file_list() {
+        let (found, summary) = collect(&[], "x");
+        assert!(found.is_empty());
+        assert_eq!(summary.files_scanned, 0);
+    }
+}
@@ -0,0 +1,272 @@
+// Translations of the text the backend shows itself: tray labels and error
+// messages. Catalogs are embedded and keyed by the English text, so code keeps
+// writing English and anything missing from a catalog stays English.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    En,
+    Ko,
+}
+
+/// Locale of the UI, as set by the frontend
+static CURRENT: AtomicU8 = AtomicU8::new(Locale::En as u8);
+
+/// Korean text of each English message. Entries with `{}` are templates
+/// filled by `trf`; entries ending a message before `: ` translate the part
+/// ahead of the detail.
+const KO: &[(&str, &str)] = &[
+    // Tray
+    ("No index", "색인 없음"),
+    ("Indexing... {} files", "색인 중... 파일 {}개"),
+    ("{} files indexed", "파일 {}개 색인됨"),
+    ("Show", "열기"),
+    ("New search window", "새 검색 창"),
+    ("Rebuild index", "색인 다시 만들기"),
+    ("Pause watcher", "변경 감시 일시 중지"),
+    ("Quit", "종료"),
+    // Sizes and dates
+    ("{} bytes", "{}바이트"),
+    ("just now", "방금 전"),
+    ("{} minute ago", "{}분 전"),
+    ("{} minutes ago", "{}분 전"),
+    ("{} hour ago", "{}시간 전"),
+    ("{} hours ago", "{}시간 전"),
+    ("yesterday", "어제"),
+    ("{} days ago", "{}일 전"),
+    // Errors
+    (
+        "Search index is not ready. Build the index first.",
+        "검색 색인이 준비되지 않았습니다. 먼저 색인을 만드세요.",
+    ),
+    ("Invalid regex", "잘못된 정규식"),
+    ("Search failed", "검색 실패"),
+    ("Search was cancelled", "검색이 취소되었습니다"),
+    (
+        "Search took too long and was stopped",
+        "검색이 너무 오래 걸려 중단되었습니다",
+    ),
+    ("Completion failed", "자동 완성 실패"),
+    ("Smart view failed", "스마트 보기 실패"),
+    ("Unknown smart view", "알 수 없는 스마트 보기"),
+    ("Unknown profile", "알 수 없는 프로필"),
+    ("Unknown file template", "알 수 없는 파일 템플릿"),
+    ("Indexing is already in progress", "이미 색인 중입니다"),
+    (
+        "Cannot switch profiles while indexing is in progress",
+        "색인 중에는 프로필을 바꿀 수 없습니다",
+    ),
+    (
+        "Cannot restore a backup while indexing is in progress",
+        "색인 중에는 백업을 복원할 수 없습니다",
+    ),
+    (
+        "Cannot relaunch while indexing is in progress",
+        "색인 중에는 다시 시작할 수 없습니다",
+    ),
+    ("File not found", "파일을 찾을 수 없습니다"),
+    ("Not a file", "파일이 아닙니다"),
+    ("Not a folder", "폴더가 아닙니다"),
+    ("Paths must be absolute", "경로는 절대 경로여야 합니다"),
+    (
+        "Search scope must be an absolute path",
+        "검색 범위는 절대 경로여야 합니다",
+    ),
+    ("Invalid source path", "잘못된 원본 경로"),
+    ("Can't rename a root", "루트의 이름은 바꿀 수 없습니다"),
+    (
+        "A folder can't be copied or moved into itself",
+        "폴더를 자기 자신 안으로 복사하거나 이동할 수 없습니다",
+    ),
+    (
+        "Another entry has taken its place",
+        "다른 항목이 그 자리에 있습니다",
+    ),
+    (
+        "Entry was only partly moved",
+        "항목이 일부만 이동되었습니다",
+    ),
+    ("Nothing to transfer", "옮길 항목이 없습니다"),
+    ("Nothing to drag", "끌어 놓을 항목이 없습니다"),
+    ("Failed to start drag", "끌기를 시작하지 못했습니다"),
+    ("Trash operation failed", "휴지통 작업 실패"),
+    ("No longer in the trash", "더 이상 휴지통에 없습니다"),
+    (
+        "Some items are no longer in the trash",
+        "일부 항목이 더 이상 휴지통에 없습니다",
+    ),
+    ("Clipboard operation failed", "클립보드 작업 실패"),
+    (
+        "The undo journal could not be opened",
+        "실행 취소 기록을 열 수 없습니다",
+    ),
+    (
+        "The onboarding state could not be opened",
+        "처음 설정 상태를 열 수 없습니다",
+    ),
+    ("Root is not being watched", "감시 중인 루트가 아닙니다"),
+    ("Failed to watch", "감시를 시작하지 못했습니다"),
+    (
+        "Only indexed roots of the active profile can be watched",
+        "현재 프로필의 색인된 루트만 감시할 수 있습니다",
+    ),
+    (
+        "No terminal emulator found",
+        "터미널 에뮬레이터를 찾을 수 없습니다",
+    ),
+    (
+        "No volume is mounted there",
+        "그 위치에 연결된 볼륨이 없습니다",
+    ),
+    (
+        "Private path must be absolute",
+        "비공개 경로는 절대 경로여야 합니다",
+    ),
+    ("Unknown snapshot", "알 수 없는 스냅숏"),
+    ("Not inside a WSL distro", "WSL 배포판 안의 경로가 아닙니다"),
+    (
+        "Explorer is only available on Windows",
+        "탐색기는 Windows에서만 사용할 수 있습니다",
+    ),
+    ("Folder dialog failed", "폴더 선택 창 오류"),
+    (
+        "No folder dialog found; install zenity or kdialog",
+        "폴더 선택 창이 없습니다. zenity나 kdialog를 설치하세요",
+    ),
+    (
+        "File is online-only; make it available offline to hash it",
+        "온라인 전용 파일입니다. 해시를 구하려면 오프라인으로 사용할 수 있게 하세요",
+    ),
+    (
+        "Access to some protected folders is still missing",
+        "아직 일부 보호된 폴더에 접근할 수 없습니다",
+    ),
+    (
+        "Choose at least one folder to index",
+        "색인할 폴더를 하나 이상 선택하세요",
+    ),
+    (
+        "The first index is not built yet",
+        "첫 색인이 아직 만들어지지 않았습니다",
+    ),
+];
+
+impl Locale {
+    fn from_u8(value: u8) -> Locale {
+        match value {
+            1 => Locale::Ko,
+            _ => Locale::En,
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => &[],
+            Locale::Ko => KO,
+        }
+    }
+
+    fn lookup(self, text: &str) -> Option<&'static str> {
+        self.catalog()
+            .iter()
+            .find(|(english, _)| *english == text)
+            .map(|(_, translated)| *translated)
+    }
+}
+
+pub fn current() -> Locale {
+    Locale::from_u8(CURRENT.load(Ordering::Relaxed))
+}
+
+pub fn set_current(locale: Locale) {
+    CURRENT.store(locale as u8, Ordering::Relaxed);
+}
+
+/// `text` in `locale`. A message without its own entry still has its lead
+/// translated when it reads `<lead>: <detail>`.
+pub fn translate(locale: Locale, text: &str) -> String {
+    if let Some(translated) = locale.lookup(text) {
+        return translated.to_string();
+    }
+    if let Some((lead, detail)) = text.split_once(": ") {
+        if let Some(translated) = locale.lookup(lead) {
+            return format!("{}: {}", translated, detail);
+        }
+    }
+    text.to_string()
+}
+
+/// `template` in `locale` with each `{}` replaced by the next of `args`
+pub fn translate_fmt(locale: Locale, template: &str, args: &[&dyn fmt::Display]) -> String {
+    let template = locale.lookup(template).unwrap_or(template);
+    let mut text = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        text.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+/// `text` in the current locale
+pub fn tr(text: &str) -> String {
+    translate(current(), text)
+}
+
+/// `template` in the current locale, filled with `args`
+pub fn trf(template: &str, args: &[&dyn fmt::Display]) -> String {
+    translate_fmt(current(), template, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(translate(Locale::En, "File not found"), "File not found");
+        assert_eq!(
+            translate(Locale::Ko, "File not found"),
+            "파일을 찾을 수 없습니다"
+        );
+        assert_eq!(
+            translate(Locale::Ko, "Search failed: bad field"),
+            "검색 실패: bad field"
+        );
+        assert_eq!(translate(Locale::Ko, "Anything else"), "Anything else");
+        assert_eq!(
+            translate_fmt(Locale::Ko, "{} files indexed", &[&42]),
+            "파일 42개 색인됨"
+        );
+        assert_eq!(
+            translate_fmt(Locale::En, "Indexing... {} files", &[&7]),
+            "Indexing... 7 files"
+        );
+    }
+
+    #[test]
+    fn test_catalog_has_no_duplicates() {
+        for (i, (english, _)) in KO.iter().enumerate() {
+            assert!(
+                !KO[i + 1..].iter().any(|(other, _)| other == english),
+                "{} appears twice",
+                english
+            );
+        }
+        assert_eq!(
+            serde_json::to_string(&Locale::Ko).unwrap(),
+            "\"ko\"".to_string()
+        );
+    }
+}
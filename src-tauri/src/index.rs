@@ -1,13 +1,482 @@
 // Indexing with sled
 
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use sled::Db;
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::{Db, Transactional, Tree};
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 use walkdir::WalkDir;
 
+/// Stable entity ID for a path (hex-encoded SHA-256 of the path string)
+pub fn path_id(path_str: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(path_str.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Per-root traversal settings
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WalkOptions {
+    /// Descend into symlinked (and on Windows, junctioned) directories. Each real
+    /// directory is walked once; further links to it are indexed but not entered.
+    pub follow_symlinks: bool,
+    /// Leave hidden entries (and everything below hidden directories) out of the index
+    pub skip_hidden: bool,
+    /// Store last-access times. Off by default since many systems mount with
+    /// relatime/noatime, making the value unreliable.
+    pub record_accessed: bool,
+}
+
+/// Resolve `root` to its canonical form so the same directory reached through
+/// `..`, symlinks or different spelling is recognised. Roots that cannot be
+/// resolved, e.g. missing ones, are kept as given.
+pub fn canonical_root(root: &str) -> String {
+    match fs::canonicalize(root) {
+        Ok(canonical) => crate::longpath::simplify(canonical)
+            .to_string_lossy()
+            .to_string(),
+        Err(_) => root.to_string(),
+    }
+}
+
+/// Canonicalize `roots` and drop duplicates and roots nested inside another
+/// root, which the enclosing root's walk already covers. Returns the remaining
+/// roots in their original order and a note for each root dropped.
+pub fn merge_roots(roots: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut merged: Vec<(String, String)> = Vec::new();
+    let mut notes = Vec::new();
+    for root in roots {
+        let canonical = canonical_root(root);
+        if let Some((covering, _)) = merged
+            .iter()
+            .find(|(_, existing)| Path::new(&canonical).starts_with(existing))
+        {
+            notes.push(format!(
+                "Skipping root {}: already covered by {}",
+                root, covering
+            ));
+            continue;
+        }
+        merged.retain(|(original, existing)| {
+            let nested = Path::new(existing).starts_with(&canonical);
+            if nested {
+                notes.push(format!(
+                    "Skipping root {}: already covered by {}",
+                    original, root
+                ));
+            }
+            !nested
+        });
+        merged.push((root.clone(), canonical));
+    }
+    (
+        merged.into_iter().map(|(_, canonical)| canonical).collect(),
+        notes,
+    )
+}
+
+/// Identity of a directory on disk, used to detect cycles and duplicate links
+#[cfg(unix)]
+type DirKey = (u64, u64);
+#[cfg(not(unix))]
+type DirKey = std::path::PathBuf;
+
+#[cfg(unix)]
+fn dir_key(_path: &Path, metadata: &fs::Metadata) -> Option<DirKey> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_key(path: &Path, _metadata: &fs::Metadata) -> Option<DirKey> {
+    // Canonicalizing resolves symlinks and junctions to the target directory
+    fs::canonicalize(path).ok()
+}
+
+/// Identity of the file behind `path` as "volume:number" in hex: device and
+/// inode on Unix, volume serial and file index on Windows. Stable across
+/// renames and shared by hard links; None where the platform has no such id.
+#[cfg(unix)]
+pub fn file_id(_path: &Path, metadata: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    Some(format!("{:x}:{:x}", metadata.dev(), metadata.ino()))
+}
+
+#[cfg(windows)]
+pub fn file_id(path: &Path, _metadata: &fs::Metadata) -> Option<String> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+        FILE_READ_ATTRIBUTES,
+    };
+
+    // Attribute access only, so files locked by other processes still open;
+    // backup semantics allow directories
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES)
+        .share_mode(0x7)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+        .ok()?;
+    // SAFETY: BY_HANDLE_FILE_INFORMATION is plain data; all-zero is valid
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    // SAFETY: the handle is open for the duration of the call
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+        return None;
+    }
+    let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+    Some(format!("{:x}:{:x}", info.dwVolumeSerialNumber, index))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn file_id(_path: &Path, _metadata: &fs::Metadata) -> Option<String> {
+    None
+}
+
+/// Name of the account owning `path`, from the owner SID of its security
+/// descriptor. Unix entries carry the uid instead; see `file_attributes`.
+#[cfg(windows)]
+pub fn owner_name(path: &Path) -> Option<String> {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::{GetSecurityInfo, SE_FILE_OBJECT};
+    use windows_sys::Win32::Security::{
+        GetLengthSid, LookupAccountSidW, OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID,
+        SID_NAME_USE,
+    };
+    use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_BACKUP_SEMANTICS, READ_CONTROL};
+
+    thread_local! {
+        // Most files share a few owners, and a lookup may ask a domain controller
+        static NAMES: RefCell<HashMap<Vec<u8>, Option<String>>> = RefCell::new(HashMap::new());
+    }
+
+    let file = fs::OpenOptions::new()
+        .access_mode(READ_CONTROL)
+        .share_mode(0x7)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+        .ok()?;
+    let mut owner: PSID = std::ptr::null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    // SAFETY: the handle is open for the duration of the call; `owner` points
+    // into `descriptor`, which is freed below
+    let status = unsafe {
+        GetSecurityInfo(
+            file.as_raw_handle() as _,
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION,
+            &mut owner,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut descriptor,
+        )
+    };
+    if status != 0 {
+        return None;
+    }
+
+    let lookup = || {
+        let (mut name, mut domain) = ([0u16; 256], [0u16; 256]);
+        let (mut name_len, mut domain_len) = (name.len() as u32, domain.len() as u32);
+        let mut kind: SID_NAME_USE = 0;
+        // SAFETY: each buffer is passed with its length in characters
+        let found = unsafe {
+            LookupAccountSidW(
+                std::ptr::null(),
+                owner,
+                name.as_mut_ptr(),
+                &mut name_len,
+                domain.as_mut_ptr(),
+                &mut domain_len,
+                &mut kind,
+            )
+        };
+        (found != 0).then(|| String::from_utf16_lossy(&name[..name_len as usize]))
+    };
+    // SAFETY: `owner` is a valid SID while `descriptor` is alive
+    let sid = unsafe {
+        std::slice::from_raw_parts(owner as *const u8, GetLengthSid(owner) as usize).to_vec()
+    };
+    let name = NAMES.with(|names| names.borrow_mut().entry(sid).or_insert_with(lookup).clone());
+    // SAFETY: allocated by GetSecurityInfo and no longer referenced
+    unsafe { LocalFree(descriptor as _) };
+    name
+}
+
+#[cfg(not(windows))]
+pub fn owner_name(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Dotfiles on every platform; on Windows also entries carrying the hidden or
+/// system attribute
+pub fn is_hidden(path: &Path, metadata: &fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0 {
+            return true;
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = metadata;
+
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+/// Whether the file is a cloud placeholder whose content is not on disk:
+/// OneDrive and other Cloud Files providers on Windows, or a dataless file
+/// of iCloud Drive or Dropbox on macOS. Reading it would download it.
+pub fn is_online_only(metadata: &fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+        const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+        const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+        metadata.file_attributes()
+            & (FILE_ATTRIBUTE_OFFLINE
+                | FILE_ATTRIBUTE_RECALL_ON_OPEN
+                | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+            != 0
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        const SF_DATALESS: u32 = 0x40000000;
+        metadata.st_flags() & SF_DATALESS != 0
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Where the symlink or junction at `path` points, with a relative target
+/// joined to the link's folder. Chains of links are not followed. None when
+/// `path` is no link.
+pub fn link_target(path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    // Absolute targets of links inside a WSL distro are paths in that distro
+    if let Some(target) = crate::wsl::link_target(path, &target.to_string_lossy()) {
+        return Some(target);
+    }
+    let target = match path.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target,
+    };
+    // Junction targets come back in verbatim form
+    Some(
+        crate::longpath::simplify(target)
+            .to_string_lossy()
+            .to_string(),
+    )
+}
+
+/// Build the entity for a single existing path outside of a walk
+pub fn entity_for_path(path: &Path) -> std::io::Result<crate::FileEntity> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(entity_from_metadata(path, &metadata)),
+        Err(e) => broken_link_entity(path).ok_or(e),
+    }
+    .map(|mut entity| {
+        crate::git::annotate(path, &mut entity.attributes);
+        entity
+    })
+}
+
+/// `entity_for_path` for an entry met on its own instead of by a walk from
+/// `root`, flagged as the walk would: hidden when it or a folder between it
+/// and `root` is, with its access time when `options` record them
+pub fn entity_below(
+    path: &Path,
+    root: Option<&Path>,
+    options: WalkOptions,
+) -> std::io::Result<crate::FileEntity> {
+    let mut entity = entity_for_path(path)?;
+    if let Some(root) = root {
+        entity.hidden = path != root && (entity.hidden || inside_hidden(path, root));
+    }
+    if options.record_accessed {
+        entity.accessed = fs::metadata(path)
+            .and_then(|metadata| metadata.accessed())
+            .ok()
+            .and_then(unix_secs);
+    }
+    Ok(entity)
+}
+
+/// Whether a folder between `path` and `root`, both excluded, is hidden
+pub fn inside_hidden(path: &Path, root: &Path) -> bool {
+    path.ancestors()
+        .skip(1)
+        .take_while(|folder| *folder != root && folder.starts_with(root))
+        .any(|folder| fs::metadata(folder).is_ok_and(|metadata| is_hidden(folder, &metadata)))
+}
+
+/// Entity for a symlink whose target is missing, or None if `path` is not one.
+/// The link's own metadata stands in for the target's.
+fn broken_link_entity(path: &Path) -> Option<crate::FileEntity> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if !metadata.file_type().is_symlink() || path.exists() {
+        return None;
+    }
+    let mut entity = entity_from_metadata(path, &metadata);
+    entity.attributes.broken_link = true;
+    Some(entity)
+}
+
+/// EACCES, or EPERM as macOS reports for folders the user has not granted
+fn is_permission_denied(error: Option<&std::io::Error>) -> bool {
+    error.is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+/// A broken symlink met `depth` levels into a walk, hidden if it lies inside the
+/// hidden directory at `hidden_below`
+fn broken_link_entry(
+    path: &Path,
+    depth: usize,
+    hidden_below: Option<usize>,
+) -> Option<crate::FileEntity> {
+    let mut entity = broken_link_entity(path)?;
+    entity.hidden = entity.hidden || hidden_below.is_some_and(|below| depth > below);
+    Some(entity)
+}
+
+fn entity_from_metadata(path: &Path, metadata: &fs::Metadata) -> crate::FileEntity {
+    let is_folder = metadata.is_dir();
+    let path_str = path.to_string_lossy().to_string();
+    let is_symlink = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+
+    crate::FileEntity {
+        id: path_id(&path_str),
+        name: path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string(),
+        path: path_str,
+        size: if is_folder { 0 } else { metadata.len() },
+        modified: metadata.modified().ok().and_then(unix_secs).unwrap_or(0),
+        is_folder,
+        hidden: is_hidden(path, metadata),
+        attributes: crate::FileAttributes {
+            file_id: file_id(path, metadata),
+            owner: owner_name(path),
+            is_symlink,
+            link_target: if is_symlink { link_target(path) } else { None },
+            project_markers: if is_folder {
+                crate::project::markers(path)
+            } else {
+                Vec::new()
+            },
+            // Reading the headers would download a placeholder
+            email: if is_folder || is_online_only(metadata) {
+                None
+            } else {
+                crate::email::headers(path)
+            },
+            ..file_attributes(metadata)
+        },
+        created: metadata.created().ok().and_then(unix_secs),
+        accessed: None,
+    }
+}
+
+/// Seconds since the Unix epoch; None for times before it
+pub fn unix_secs(time: std::time::SystemTime) -> Option<i64> {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs() as i64)
+}
+
+/// Permission metadata captured during traversal. Uid, gid and mode are only
+/// available on Unix; Windows reports the read-only attribute here and the
+/// owner through `owner_name`.
+pub fn file_attributes(metadata: &fs::Metadata) -> crate::FileAttributes {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        crate::FileAttributes {
+            readonly: metadata.permissions().readonly(),
+            uid: Some(metadata.uid()),
+            gid: Some(metadata.gid()),
+            mode: Some(metadata.mode() & 0o7777),
+            online_only: is_online_only(metadata),
+            ..Default::default()
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        crate::FileAttributes {
+            readonly: metadata.permissions().readonly(),
+            online_only: is_online_only(metadata),
+            ..Default::default()
+        }
+    }
+}
+
+/// sled tree recording changes the search index may not have committed yet
+const JOURNAL_TREE: &str = "journal";
+
+/// sled tree mapping each indexed path to its entity ID. Keys sort by path,
+/// so everything below a folder is one prefix scan.
+const PATH_TREE: &str = "paths";
+
+/// sled tree mapping entity IDs to their vectors; see `embed`
+const EMBEDDING_TREE: &str = "embeddings";
+
+/// Entity IDs changed in sled by one delta, pending until the search index
+/// has committed the same change
+pub type JournalRecord = (u64, Vec<String>);
+
 pub struct IndexManager {
     db: Db,
+    journal: Tree,
+    paths: Tree,
+    embeddings: Tree,
+}
+
+/// Writes to the entity tree and the path tree, applied together. Removed
+/// entries lose their vector too.
+#[derive(Default)]
+struct Changes {
+    entities: sled::Batch,
+    paths: sled::Batch,
+    embeddings: sled::Batch,
+}
+
+impl Changes {
+    fn upsert(&mut self, entity: &crate::FileEntity) -> Result<(), sled::Error> {
+        let value = bincode::serialize(entity)
+            .map_err(|e| sled::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+        self.entities.insert(entity.id.as_bytes(), value);
+        self.paths
+            .insert(entity.path.as_bytes(), entity.id.as_bytes());
+        Ok(())
+    }
+
+    fn remove(&mut self, id: &str, path: Option<&str>) {
+        self.entities.remove(id.as_bytes());
+        self.embeddings.remove(id.as_bytes());
+        if let Some(path) = path {
+            self.paths.remove(path.as_bytes());
+        }
+    }
 }
 
 impl IndexManager {
@@ -22,18 +491,75 @@ impl IndexManager {
             })?;
         }
         let db = sled::open(db_path)?;
-        Ok(IndexManager { db })
+        let journal = db.open_tree(JOURNAL_TREE)?;
+        let paths = db.open_tree(PATH_TREE)?;
+        let embeddings = db.open_tree(EMBEDDING_TREE)?;
+        let manager = IndexManager {
+            db,
+            journal,
+            paths,
+            embeddings,
+        };
+        // DBs from before the path tree get one filled in once
+        if manager.paths.is_empty() && !manager.db.is_empty() {
+            manager.fill_paths()?;
+        }
+        Ok(manager)
     }
 
-    pub fn save_file_entity(&self, entity: &crate::FileEntity) -> Result<(), sled::Error> {
-        let key = entity.id.as_bytes();
-        let value = bincode::serialize(entity)
-            .map_err(|e| sled::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
-        self.db.insert(key, value)?;
+    fn fill_paths(&self) -> Result<(), sled::Error> {
+        let mut batch = sled::Batch::default();
+        let mut filled = 0;
+        for item in self.db.iter() {
+            let (key, value) = item?;
+            match bincode::deserialize::<crate::FileEntity>(&value) {
+                Ok(entity) => {
+                    batch.insert(entity.path.as_bytes(), key);
+                    filled += 1;
+                }
+                Err(e) => log::warn!("Skipping undecodable index entry: {}", e),
+            }
+        }
+        self.paths.apply_batch(batch)?;
+        log::info!("Recorded the paths of {} indexed entries", filled);
         Ok(())
     }
 
-    #[allow(dead_code)]
+    /// Apply `changes`, and the journal record when given, atomically
+    fn apply(&self, changes: &Changes, record: Option<(u64, &[u8])>) -> Result<(), sled::Error> {
+        (&*self.db, &self.paths, &self.embeddings, &self.journal)
+            .transaction(|(entities, paths, embeddings, journal)| {
+                entities.apply_batch(&changes.entities)?;
+                paths.apply_batch(&changes.paths)?;
+                embeddings.apply_batch(&changes.embeddings)?;
+                if let Some((seq, record)) = record {
+                    journal.insert(&seq.to_be_bytes(), record)?;
+                }
+                Ok::<(), ConflictableTransactionError>(())
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) | TransactionError::Storage(e) => e,
+            })
+    }
+
+    /// Path stored for the entity `id`
+    fn stored_path(&self, id: &str) -> Result<Option<String>, sled::Error> {
+        Ok(self.get_file_entity(id)?.map(|entity| entity.path))
+    }
+
+    pub fn save_file_entity(&self, entity: &crate::FileEntity) -> Result<(), sled::Error> {
+        self.save_file_entities(std::slice::from_ref(entity))
+    }
+
+    /// Insert many entities with a single sled batch
+    pub fn save_file_entities(&self, entities: &[crate::FileEntity]) -> Result<(), sled::Error> {
+        let mut changes = Changes::default();
+        for entity in entities {
+            changes.upsert(entity)?;
+        }
+        self.apply(&changes, None)
+    }
+
     pub fn get_file_entity(&self, id: &str) -> Result<Option<crate::FileEntity>, sled::Error> {
         if let Some(data) = self.db.get(id.as_bytes())? {
             let entity: crate::FileEntity = bincode::deserialize(&data)
@@ -44,6 +570,12 @@ impl IndexManager {
         }
     }
 
+    /// Flush pending writes to disk
+    pub fn flush(&self) -> Result<(), sled::Error> {
+        self.db.flush()?;
+        Ok(())
+    }
+
     /// Count total files in the database
     pub fn count_files(&self) -> Result<usize, sled::Error> {
         let mut count = 0;
@@ -53,18 +585,209 @@ impl IndexManager {
         Ok(count)
     }
 
+    /// Look up an indexed entity by its full path
+    pub fn get_file_entity_by_path(
+        &self,
+        path: &str,
+    ) -> Result<Option<crate::FileEntity>, sled::Error> {
+        self.get_file_entity(&path_id(path))
+    }
+
+    /// List indexed entities whose parent directory is `dir`. Walks the path
+    /// tree below `dir`, jumping over the contents of each child folder.
+    pub fn list_children(&self, dir: &Path) -> Result<Vec<crate::FileEntity>, sled::Error> {
+        let sep = std::path::MAIN_SEPARATOR;
+        let mut prefix = dir.to_string_lossy().to_string();
+        if !prefix.ends_with(sep) {
+            prefix.push(sep);
+        }
+
+        let mut children = Vec::new();
+        let mut from = prefix.as_bytes().to_vec();
+        while let Some(item) = self.paths.range(from.as_slice()..).next() {
+            let (key, id) = item?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let rest = &key[prefix.len()..];
+            match rest.iter().position(|&b| b == sep as u8) {
+                // Below a child folder: everything there sorts before the
+                // folder's path with the separator's successor appended
+                Some(at) => {
+                    from = key[..prefix.len() + at + 1].to_vec();
+                    *from.last_mut().unwrap() += 1;
+                }
+                None => {
+                    match self.get_file_entity(&String::from_utf8_lossy(&id)) {
+                        Ok(Some(entity)) if Path::new(&entity.path).parent() == Some(dir) => {
+                            children.push(entity)
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::warn!("Skipping undecodable index entry: {}", e),
+                    }
+                    from = key.to_vec();
+                    from.push(0);
+                }
+            }
+        }
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(children)
+    }
+
+    /// IDs of the entities whose path starts with `prefix`
+    pub fn ids_with_prefix(&self, prefix: &str) -> Result<Vec<String>, sled::Error> {
+        self.paths
+            .scan_prefix(prefix.as_bytes())
+            .values()
+            .map(|id| Ok(String::from_utf8_lossy(&id?).to_string()))
+            .collect()
+    }
+
+    /// Entities whose path starts with `prefix`
+    pub fn entities_with_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<crate::FileEntity>, sled::Error> {
+        let mut found = Vec::new();
+        for id in self.ids_with_prefix(prefix)? {
+            match self.get_file_entity(&id) {
+                Ok(Some(entity)) if entity.path.starts_with(prefix) => found.push(entity),
+                Ok(_) => {}
+                Err(e) => log::warn!("Skipping undecodable index entry: {}", e),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Stream every stored entity into `sink`, returning how many were visited.
+    /// Unlike lookups, an undecodable entry is an error here, since callers
+    /// rely on seeing the complete set.
+    pub fn for_each_entity<F>(&self, mut sink: F) -> Result<usize, String>
+    where
+        F: FnMut(crate::FileEntity) -> Result<(), String>,
+    {
+        let mut visited = 0;
+        for item in self.db.iter() {
+            let (_key, value) = item.map_err(|e| format!("Failed to read index DB: {}", e))?;
+            let entity = bincode::deserialize::<crate::FileEntity>(&value)
+                .map_err(|e| format!("Undecodable index entry: {}", e))?;
+            sink(entity)?;
+            visited += 1;
+        }
+        Ok(visited)
+    }
+
+    /// Stamp of the vector stored for the entity `id`; see `embed::stamp`
+    pub fn embedding_stamp(&self, id: &str) -> Result<Option<u64>, sled::Error> {
+        Ok(self
+            .embeddings
+            .get(id.as_bytes())?
+            .and_then(|value| crate::embed::decode(&value))
+            .map(|(stamp, _)| stamp))
+    }
+
+    /// Store the vector of the entity `id`, unless it was removed meanwhile
+    pub fn save_embedding(&self, id: &str, stamp: u64, vector: &[f32]) -> Result<(), sled::Error> {
+        if self.db.contains_key(id.as_bytes())? {
+            self.embeddings
+                .insert(id.as_bytes(), crate::embed::encode(stamp, vector))?;
+        }
+        Ok(())
+    }
+
+    /// The `limit` stored vectors most similar to `query`, best first, by a
+    /// scan of every one
+    pub fn nearest_embeddings(
+        &self,
+        query: &[f32],
+        limit: usize,
+    ) -> Result<Vec<crate::embed::Neighbor>, sled::Error> {
+        let mut failed = None;
+        let vectors = self
+            .embeddings
+            .iter()
+            .map_while(|item| item.map_err(|e| failed = Some(e)).ok())
+            .filter_map(|(key, value)| match crate::embed::decode(&value) {
+                Some((stamp, vector)) => {
+                    Some((String::from_utf8_lossy(&key).to_string(), stamp, vector))
+                }
+                None => {
+                    log::warn!("Skipping undecodable embedding");
+                    None
+                }
+            });
+        let nearest = crate::embed::nearest(query, vectors, limit);
+        match failed {
+            Some(e) => Err(e),
+            None => Ok(nearest),
+        }
+    }
+
+    /// Collect every entity under `root_path`. Prefer `walk_directory` for large
+    /// trees, which streams entities instead of materializing them.
+    #[allow(dead_code)]
     pub fn traverse_directory(
         &self,
         root_path: &Path,
     ) -> Result<Vec<crate::FileEntity>, Box<dyn std::error::Error>> {
         let mut entities = Vec::new();
-        let mut errors = 0;
+        self.walk_directory(root_path, WalkOptions::default(), |entity| {
+            entities.push(entity);
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
+        Ok(entities)
+    }
 
-        for entry in WalkDir::new(root_path).follow_links(false) {
+    /// Walk `root_path`, handing each entity to `on_entity` as it is discovered
+    /// so memory use stays flat regardless of tree size. Unreadable entries are
+    /// logged and skipped; returns how many were skipped. An error from
+    /// `on_entity` stops the walk.
+    pub fn walk_directory<F, E>(
+        &self,
+        root_path: &Path,
+        options: WalkOptions,
+        mut on_entity: F,
+    ) -> Result<usize, E>
+    where
+        F: FnMut(crate::FileEntity) -> Result<(), E>,
+    {
+        let mut errors = 0;
+        // Permission denials are summarized rather than logged one by one, as
+        // a protected folder such as ~/Library can produce thousands
+        let mut denied = 0;
+        let mut visited_dirs: HashSet<DirKey> = HashSet::new();
+        // Depth of the hidden directory currently being walked; entries below it
+        // inherit the flag. WalkDir is depth-first, so leaving that depth ends it.
+        let mut hidden_below: Option<usize> = None;
+        let mut repos = crate::git::RepoStack::new(root_path);
+
+        let mut walker = WalkDir::new(root_path)
+            .follow_links(options.follow_symlinks)
+            .into_iter();
+        while let Some(entry) = walker.next() {
             let entry = match entry {
                 Ok(e) => e,
+                Err(e) if e.loop_ancestor().is_some() => {
+                    log::info!(
+                        "Skipping symlink cycle at {}",
+                        e.path().unwrap_or(root_path).display()
+                    );
+                    continue;
+                }
                 Err(e) => {
                     let path = e.path().unwrap_or(root_path);
+                    if let Some(entity) = broken_link_entry(path, e.depth(), hidden_below) {
+                        if !(entity.hidden && options.skip_hidden) {
+                            on_entity(entity)?;
+                        }
+                        continue;
+                    }
+                    errors += 1;
+                    if is_permission_denied(e.io_error()) {
+                        log::debug!("No permission to read {}: {}", path.display(), e);
+                        denied += 1;
+                        continue;
+                    }
                     let error_kind = e
                         .io_error()
                         .map(|io_err| format!("{:?}", io_err.kind()))
@@ -82,7 +805,6 @@ impl IndexManager {
                         error_kind,
                         error_code
                     );
-                    errors += 1;
                     continue;
                 }
             };
@@ -93,6 +815,18 @@ impl IndexManager {
             let metadata = match fs::metadata(path) {
                 Ok(m) => m,
                 Err(e) => {
+                    if let Some(entity) = broken_link_entry(path, entry.depth(), hidden_below) {
+                        if !(entity.hidden && options.skip_hidden) {
+                            on_entity(entity)?;
+                        }
+                        continue;
+                    }
+                    errors += 1;
+                    if is_permission_denied(Some(&e)) {
+                        log::debug!("No permission to stat {}: {}", path.display(), e);
+                        denied += 1;
+                        continue;
+                    }
                     let error_kind = format!("{:?}", e.kind());
                     let error_code = e
                         .raw_os_error()
@@ -106,12 +840,28 @@ impl IndexManager {
                         error_kind,
                         error_code
                     );
-                    errors += 1;
                     continue;
                 }
             };
 
             let is_folder = metadata.is_dir();
+
+            if hidden_below.is_some_and(|depth| entry.depth() <= depth) {
+                hidden_below = None;
+            }
+            // The root itself was chosen explicitly, so it never counts as hidden
+            let hidden =
+                hidden_below.is_some() || (entry.depth() > 0 && is_hidden(path, &metadata));
+            if hidden && options.skip_hidden {
+                if is_folder {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            if hidden && is_folder && hidden_below.is_none() {
+                hidden_below = Some(entry.depth());
+            }
+
             let size = if is_folder { 0 } else { metadata.len() };
 
             let modified = match metadata.modified() {
@@ -143,9 +893,33 @@ impl IndexManager {
                 .to_string();
 
             // Generate ID from path hash
-            let mut hasher = Sha256::new();
-            hasher.update(path_str.as_bytes());
-            let id = format!("{:x}", hasher.finalize());
+            let id = path_id(&path_str);
+
+            let mut attributes = crate::FileAttributes {
+                project_markers: if is_folder {
+                    crate::project::markers(path)
+                } else {
+                    Vec::new()
+                },
+                file_id: file_id(path, &metadata),
+                owner: owner_name(path),
+                is_symlink: entry.path_is_symlink(),
+                link_target: if entry.path_is_symlink() {
+                    link_target(path)
+                } else {
+                    None
+                },
+                email: if is_folder || is_online_only(&metadata) {
+                    None
+                } else {
+                    crate::email::headers(path)
+                },
+                ..file_attributes(&metadata)
+            };
+            let repo_root = attributes
+                .project_markers
+                .contains(&crate::project::ProjectMarker::Git);
+            repos.annotate(path, entry.depth(), repo_root, &mut attributes);
 
             let entity = crate::FileEntity {
                 id,
@@ -154,16 +928,42 @@ impl IndexManager {
                 size,
                 modified: modified as i64,
                 is_folder,
+                hidden,
+                attributes,
+                created: metadata.created().ok().and_then(unix_secs),
+                accessed: if options.record_accessed {
+                    metadata.accessed().ok().and_then(unix_secs)
+                } else {
+                    None
+                },
             };
 
-            entities.push(entity);
+            on_entity(entity)?;
+
+            // A directory reached again through another link is indexed under
+            // this path but its contents are not walked twice
+            if options.follow_symlinks && is_folder {
+                if let Some(key) = dir_key(path, &metadata) {
+                    if !visited_dirs.insert(key) {
+                        log::debug!(
+                            "Not descending into already indexed directory {}",
+                            path.display()
+                        );
+                        walker.skip_current_dir();
+                    }
+                }
+            }
         }
 
         if errors > 0 {
-            log::warn!("Skipped {} entries due to errors during traversal", errors);
+            log::warn!(
+                "Skipped {} entries due to errors during traversal ({} without permission)",
+                errors,
+                denied
+            );
         }
 
-        Ok(entities)
+        Ok(errors)
     }
 
     #[allow(dead_code)] // Reserved for future file watcher integration
@@ -175,48 +975,120 @@ impl IndexManager {
             return Ok(None);
         }
 
-        let metadata = fs::metadata(path)?;
-        let is_folder = metadata.is_dir();
-        let size = if is_folder { 0 } else { metadata.len() };
-        let modified = metadata
-            .modified()?
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let entity = entity_for_path(path)?;
+        self.save_file_entity(&entity)?;
+        Ok(Some(entity))
+    }
 
-        let path_str = path.to_string_lossy().to_string();
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
+    /// Remove `removed_ids` and save `upserted` as one journaled change.
+    /// Returns the journal sequence to pass to `complete_delta` once the
+    /// search index has committed the same change.
+    pub fn apply_delta(
+        &self,
+        removed_ids: &[String],
+        upserted: &[crate::FileEntity],
+    ) -> Result<u64, sled::Error> {
+        let mut changes = Changes::default();
+        for id in removed_ids {
+            changes.remove(id, self.stored_path(id)?.as_deref());
+        }
+        for entity in upserted {
+            changes.upsert(entity)?;
+        }
+        let changed: Vec<String> = removed_ids
+            .iter()
+            .cloned()
+            .chain(upserted.iter().map(|e| e.id.clone()))
+            .collect();
+        self.apply_journaled(&changes, &changed)
+    }
 
-        // Generate ID from path hash
-        let mut hasher = Sha256::new();
-        hasher.update(path_str.as_bytes());
-        let id = format!("{:x}", hasher.finalize());
+    /// Apply `changes` and their journal record atomically, then flush, so
+    /// the record is durable before the search index is touched
+    fn apply_journaled(&self, changes: &Changes, changed: &[String]) -> Result<u64, sled::Error> {
+        let seq = self.db.generate_id()?;
+        let record = bincode::serialize(changed)
+            .map_err(|e| sled::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+        self.apply(changes, Some((seq, &record)))?;
+        self.db.flush()?;
+        Ok(seq)
+    }
 
-        let entity = crate::FileEntity {
-            id,
-            name,
-            path: path_str,
-            size,
-            modified: modified as i64,
-            is_folder,
-        };
+    /// Drop the journal record of a delta the search index has committed
+    pub fn complete_delta(&self, seq: u64) -> Result<(), sled::Error> {
+        self.journal.remove(seq.to_be_bytes())?;
+        Ok(())
+    }
 
-        self.save_file_entity(&entity)?;
-        Ok(Some(entity))
+    /// Deltas whose search index commit may not have happened, oldest first
+    pub fn pending_deltas(&self) -> Result<Vec<JournalRecord>, sled::Error> {
+        let mut pending = Vec::new();
+        for item in self.journal.iter() {
+            let (key, value) = item?;
+            let Ok(seq) = <[u8; 8]>::try_from(key.as_ref()) else {
+                log::warn!("Skipping journal record with malformed key");
+                continue;
+            };
+            match bincode::deserialize::<Vec<String>>(&value) {
+                Ok(ids) => pending.push((u64::from_be_bytes(seq), ids)),
+                Err(e) => log::warn!("Skipping undecodable journal record: {}", e),
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Re-key the entry at `from` and everything indexed beneath it to live
+    /// under `to`, in one journaled sled batch. Only the entries below `from`
+    /// are read, found by a prefix scan of the path tree. Returns the removed
+    /// IDs, the moved entities and the journal sequence so the search index
+    /// can apply the same change.
+    #[allow(clippy::type_complexity)]
+    pub fn rename_entries(
+        &self,
+        from: &Path,
+        to: &Path,
+    ) -> Result<(Vec<String>, Vec<crate::FileEntity>, u64), sled::Error> {
+        let from_str = from.to_string_lossy().to_string();
+        let to_str = to.to_string_lossy().to_string();
+        let child_prefix = format!("{}{}", from_str, std::path::MAIN_SEPARATOR);
+
+        let mut moved = self.entities_with_prefix(&child_prefix)?;
+        if let Some(entity) = self.get_file_entity(&path_id(&from_str))? {
+            moved.push(entity);
+        }
+
+        let mut removed_ids = Vec::with_capacity(moved.len());
+        let mut changes = Changes::default();
+        for entity in &mut moved {
+            changes.remove(&entity.id, Some(&entity.path));
+            removed_ids.push(std::mem::take(&mut entity.id));
+
+            entity.path = format!("{}{}", to_str, &entity.path[from_str.len()..]);
+            entity.id = path_id(&entity.path);
+            if entity.path == to_str {
+                entity.name = to
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("")
+                    .to_string();
+            }
+            changes.upsert(entity)?;
+        }
+        let changed: Vec<String> = removed_ids
+            .iter()
+            .cloned()
+            .chain(moved.iter().map(|e| e.id.clone()))
+            .collect();
+        let seq = self.apply_journaled(&changes, &changed)?;
+        Ok((removed_ids, moved, seq))
     }
 
     #[allow(dead_code)] // Reserved for future file watcher integration
     pub fn remove_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        let path_str = path.to_string_lossy().to_string();
-        let mut hasher = Sha256::new();
-        hasher.update(path_str.as_bytes());
-        let id = format!("{:x}", hasher.finalize());
-
-        self.db.remove(id.as_bytes())?;
+        let path = path.to_string_lossy();
+        let mut changes = Changes::default();
+        changes.remove(&path_id(&path), Some(&path));
+        self.apply(&changes, None)?;
         Ok(())
     }
 }
@@ -229,6 +1101,18 @@ mod tests {
     use std::io::Write;
     use tempfile::tempdir;
 
+    /// Opens `db_path` again once sled's background threads from an earlier
+    /// manager let go of the lock file
+    fn reopen(db_path: &Path) -> IndexManager {
+        for _ in 0..100 {
+            if let Ok(manager) = IndexManager::new(db_path) {
+                return manager;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        IndexManager::new(db_path).unwrap()
+    }
+
     fn create_test_file_entity(
         path: &str,
         name: &str,
@@ -247,9 +1131,189 @@ mod tests {
             size,
             modified,
             is_folder,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
         }
     }
 
+    #[test]
+    fn test_rename_entries_rekeys_descendants() {
+        let temp_dir = tempdir().unwrap();
+        let manager = IndexManager::new(&temp_dir.path().join("test_db")).unwrap();
+        let sep = std::path::MAIN_SEPARATOR;
+        let dir = format!("{}data{}docs", sep, sep);
+        let child = format!("{}{}a.txt", dir, sep);
+        let sibling = format!("{}-old{}b.txt", dir, sep);
+        for (path, name, is_folder) in [
+            (&dir, "docs", true),
+            (&child, "a.txt", false),
+            (&sibling, "b.txt", false),
+        ] {
+            manager
+                .save_file_entity(&create_test_file_entity(path, name, 0, 0, is_folder))
+                .unwrap();
+        }
+
+        let renamed = format!("{}data{}papers", sep, sep);
+        let (removed, moved, seq) = manager
+            .rename_entries(Path::new(&dir), Path::new(&renamed))
+            .unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(moved.len(), 2);
+        assert_eq!(manager.pending_deltas().unwrap()[0].0, seq);
+
+        assert!(manager.get_file_entity(&path_id(&dir)).unwrap().is_none());
+        let folder = manager
+            .get_file_entity(&path_id(&renamed))
+            .unwrap()
+            .unwrap();
+        assert_eq!(folder.name, "papers");
+        let moved_child = format!("{}{}a.txt", renamed, sep);
+        let child_entity = manager
+            .get_file_entity(&path_id(&moved_child))
+            .unwrap()
+            .unwrap();
+        assert_eq!(child_entity.name, "a.txt");
+        // A sibling sharing the string prefix is not part of the folder
+        assert!(manager
+            .get_file_entity(&path_id(&sibling))
+            .unwrap()
+            .is_some());
+        assert_eq!(manager.count_files().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_path_tree_follows_changes() {
+        let temp_dir = tempdir().unwrap();
+        let manager = IndexManager::new(&temp_dir.path().join("test_db")).unwrap();
+        let sep = std::path::MAIN_SEPARATOR;
+        let dir = format!("{}data{}docs", sep, sep);
+        let child = create_test_file_entity(&format!("{}{}a.txt", dir, sep), "a.txt", 0, 0, false);
+        let sibling =
+            create_test_file_entity(&format!("{}-old{}b.txt", dir, sep), "b.txt", 0, 0, false);
+        manager
+            .save_file_entities(&[
+                create_test_file_entity(&dir, "docs", 0, 0, true),
+                child.clone(),
+                sibling.clone(),
+            ])
+            .unwrap();
+        let below = format!("{}{}", dir, sep);
+        assert_eq!(
+            manager.ids_with_prefix(&below).unwrap(),
+            vec![child.id.clone()]
+        );
+
+        // DBs written before the path tree existed get it filled in
+        manager.paths.clear().unwrap();
+        manager.fill_paths().unwrap();
+        assert_eq!(
+            manager.ids_with_prefix(&below).unwrap(),
+            vec![child.id.clone()]
+        );
+        manager
+            .apply_delta(std::slice::from_ref(&child.id), &[])
+            .unwrap();
+        assert!(manager.ids_with_prefix(&below).unwrap().is_empty());
+
+        // Renames move the paths of the folder and of what it holds
+        let renamed = format!("{}data{}papers", sep, sep);
+        manager
+            .rename_entries(Path::new(&format!("{}-old", dir)), Path::new(&renamed))
+            .unwrap();
+        assert_eq!(
+            manager
+                .ids_with_prefix(&format!("{}{}", renamed, sep))
+                .unwrap(),
+            vec![path_id(&format!("{}{}b.txt", renamed, sep))]
+        );
+        assert_eq!(manager.paths.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_delta_journals_until_completed() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db");
+        let kept = create_test_file_entity("/journal/kept.txt", "kept.txt", 1, 0, false);
+        let gone = create_test_file_entity("/journal/gone.txt", "gone.txt", 1, 0, false);
+
+        let (first, second) = {
+            let manager = IndexManager::new(&db_path).unwrap();
+            manager.save_file_entity(&gone).unwrap();
+            let first = manager
+                .apply_delta(std::slice::from_ref(&gone.id), std::slice::from_ref(&kept))
+                .unwrap();
+            let second = manager
+                .apply_delta(&[], std::slice::from_ref(&kept))
+                .unwrap();
+            assert!(second > first);
+            assert!(manager.get_file_entity(&gone.id).unwrap().is_none());
+            manager.complete_delta(second).unwrap();
+            (first, second)
+        };
+
+        // The uncompleted delta survives reopening; journal entries aren't files
+        let manager = reopen(&db_path);
+        let pending = manager.pending_deltas().unwrap();
+        assert_eq!(
+            pending,
+            vec![(first, vec![gone.id.clone(), kept.id.clone()])]
+        );
+        assert_ne!(pending[0].0, second);
+        assert_eq!(manager.count_files().unwrap(), 1);
+        manager.complete_delta(first).unwrap();
+        assert!(manager.pending_deltas().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_roots_drops_duplicate_and_nested_roots() {
+        let temp_dir = tempdir().unwrap();
+        let base = fs::canonicalize(temp_dir.path()).unwrap();
+        let docs = base.join("docs");
+        let nested = docs.join("nested");
+        let sibling = base.join("docs-old");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(&sibling).unwrap();
+        let s = |p: &Path| p.to_string_lossy().to_string();
+        let missing = s(&base.join("missing"));
+
+        let roots = vec![
+            s(&nested),
+            s(&docs.join("..").join("docs")),
+            s(&sibling),
+            s(&docs),
+            missing.clone(),
+        ];
+        let (merged, notes) = merge_roots(&roots);
+        assert_eq!(merged, vec![s(&docs), s(&sibling), missing]);
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].contains(&s(&nested)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_id_follows_renames_and_hard_links() {
+        let temp_dir = tempdir().unwrap();
+        let original = temp_dir.path().join("original.txt");
+        File::create(&original).unwrap();
+        let id = entity_for_path(&original).unwrap().attributes.file_id;
+        assert!(id.is_some());
+
+        let renamed = temp_dir.path().join("renamed.txt");
+        fs::rename(&original, &renamed).unwrap();
+        assert_eq!(entity_for_path(&renamed).unwrap().attributes.file_id, id);
+
+        let link = temp_dir.path().join("link.txt");
+        fs::hard_link(&renamed, &link).unwrap();
+        assert_eq!(entity_for_path(&link).unwrap().attributes.file_id, id);
+
+        let other = temp_dir.path().join("other.txt");
+        File::create(&other).unwrap();
+        assert_ne!(entity_for_path(&other).unwrap().attributes.file_id, id);
+    }
+
     #[test]
     fn test_index_manager_new() {
         let temp_dir = tempdir().unwrap();
@@ -338,6 +1402,33 @@ mod tests {
         assert_eq!(manager.count_files().unwrap(), 3);
     }
 
+    #[test]
+    fn test_save_file_entities_batch() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db");
+        let manager = IndexManager::new(&db_path).unwrap();
+
+        let entities: Vec<_> = (0..25)
+            .map(|i| {
+                create_test_file_entity(
+                    &format!("/batch/file{}.txt", i),
+                    &format!("file{}.txt", i),
+                    i,
+                    i as i64,
+                    false,
+                )
+            })
+            .collect();
+
+        manager.save_file_entities(&entities).unwrap();
+        assert_eq!(manager.count_files().unwrap(), 25);
+        let retrieved = manager.get_file_entity(&entities[7].id).unwrap().unwrap();
+        assert_eq!(retrieved.name, "file7.txt");
+
+        manager.save_file_entities(&[]).unwrap();
+        assert_eq!(manager.count_files().unwrap(), 25);
+    }
+
     #[test]
     fn test_save_updates_existing_entity() {
         let temp_dir = tempdir().unwrap();
@@ -365,6 +1456,57 @@ mod tests {
         assert_eq!(retrieved.modified, 500, "Entity should be updated");
     }
 
+    #[test]
+    fn test_get_file_entity_by_path() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db");
+        let manager = IndexManager::new(&db_path).unwrap();
+
+        let entity = create_test_file_entity("/path/to/file.txt", "file.txt", 10, 10, false);
+        manager.save_file_entity(&entity).unwrap();
+
+        let found = manager
+            .get_file_entity_by_path("/path/to/file.txt")
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, entity.id);
+        assert!(manager
+            .get_file_entity_by_path("/path/to/other.txt")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_list_children() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db");
+        let manager = IndexManager::new(&db_path).unwrap();
+
+        for entity in [
+            create_test_file_entity("/root/b.txt", "b.txt", 1, 1, false),
+            create_test_file_entity("/root/a", "a", 0, 1, true),
+            create_test_file_entity("/root/a/deep.txt", "deep.txt", 1, 1, false),
+            create_test_file_entity("/root/a/x/deeper.txt", "deeper.txt", 1, 1, false),
+            create_test_file_entity("/root/a.txt", "a.txt", 1, 1, false),
+            create_test_file_entity("/root/c/d.txt", "d.txt", 1, 1, false),
+            create_test_file_entity("/rootless.txt", "rootless.txt", 1, 1, false),
+            create_test_file_entity("/other/c.txt", "c.txt", 1, 1, false),
+        ] {
+            manager.save_file_entity(&entity).unwrap();
+        }
+
+        let children = manager.list_children(Path::new("/root")).unwrap();
+        let names: Vec<_> = children.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["a", "a.txt", "b.txt"],
+            "Only direct children, sorted"
+        );
+        let children = manager.list_children(Path::new("/root/a/")).unwrap();
+        let names: Vec<_> = children.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["deep.txt"]);
+    }
+
     #[test]
     fn test_traverse_directory() {
         let temp_dir = tempdir().unwrap();
@@ -403,6 +1545,237 @@ mod tests {
             .any(|e| e.name == "file3.txt" && !e.is_folder));
     }
 
+    #[test]
+    fn test_walk_directory_streams_and_stops_on_error() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db");
+        let manager = IndexManager::new(&db_path).unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        for i in 0..5 {
+            File::create(root.join(format!("f{}.txt", i))).unwrap();
+        }
+
+        let mut seen = 0;
+        let skipped = manager
+            .walk_directory(&root, WalkOptions::default(), |_entity| {
+                seen += 1;
+                Ok::<_, String>(())
+            })
+            .unwrap();
+        assert_eq!(seen, 6, "Root plus five files");
+        assert_eq!(skipped, 0);
+
+        let mut seen = 0;
+        let result = manager.walk_directory(&root, WalkOptions::default(), |_entity| {
+            seen += 1;
+            if seen == 2 {
+                Err("stop".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err("stop".to_string()));
+        assert_eq!(seen, 2, "Walk should stop at the first callback error");
+    }
+
+    #[test]
+    fn test_walk_flags_and_skips_hidden_entries() {
+        let temp_dir = tempdir().unwrap();
+        let manager = IndexManager::new(&temp_dir.path().join("test_db")).unwrap();
+        let root = temp_dir.path().join(".root");
+        fs::create_dir_all(root.join(".git").join("objects")).unwrap();
+        File::create(root.join(".git").join("objects").join("pack")).unwrap();
+        File::create(root.join(".env")).unwrap();
+        File::create(root.join("visible.txt")).unwrap();
+
+        let mut flags = std::collections::HashMap::new();
+        manager
+            .walk_directory(&root, WalkOptions::default(), |entity| {
+                flags.insert(entity.name, entity.hidden);
+                Ok::<_, String>(())
+            })
+            .unwrap();
+        assert!(!flags[".root"], "An explicit root is never hidden");
+        assert!(flags[".git"]);
+        assert!(flags["pack"], "Hidden directories hide their contents");
+        assert!(flags[".env"]);
+        assert!(!flags["visible.txt"]);
+
+        let mut names = Vec::new();
+        let options = WalkOptions {
+            skip_hidden: true,
+            ..WalkOptions::default()
+        };
+        manager
+            .walk_directory(&root, options, |entity| {
+                names.push(entity.name);
+                Ok::<_, String>(())
+            })
+            .unwrap();
+        names.sort();
+        assert_eq!(names, vec![".root".to_string(), "visible.txt".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_attributes_capture_unix_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("locked.txt");
+        File::create(&path).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+
+        let attributes = file_attributes(&fs::metadata(&path).unwrap());
+        assert!(attributes.readonly);
+        assert_eq!(attributes.mode, Some(0o444));
+        assert!(attributes.uid.is_some());
+        assert!(attributes.gid.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_follows_symlinks_without_cycles_or_duplicates() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = tempdir().unwrap();
+        let manager = IndexManager::new(&temp_dir.path().join("test_db")).unwrap();
+        let root = temp_dir.path().join("root");
+        let target = temp_dir.path().join("target");
+        fs::create_dir_all(root.join("real")).unwrap();
+        fs::create_dir(&target).unwrap();
+        File::create(root.join("real").join("inner.txt")).unwrap();
+        File::create(target.join("outside.txt")).unwrap();
+        symlink(&target, root.join("link_a")).unwrap();
+        symlink(&target, root.join("link_b")).unwrap();
+        symlink(&root, root.join("real").join("loop")).unwrap();
+
+        let collect = |options| {
+            let mut names = Vec::new();
+            let skipped = manager
+                .walk_directory(&root, options, |entity| {
+                    names.push(entity.name);
+                    Ok::<_, String>(())
+                })
+                .unwrap();
+            (names, skipped)
+        };
+
+        let (names, _) = collect(WalkOptions::default());
+        assert!(!names.contains(&"outside.txt".to_string()));
+
+        let (names, skipped) = collect(WalkOptions {
+            follow_symlinks: true,
+            ..WalkOptions::default()
+        });
+        assert_eq!(skipped, 0, "Cycles are not reported as errors");
+        assert_eq!(
+            names.iter().filter(|n| *n == "outside.txt").count(),
+            1,
+            "Target reached through two links is walked once"
+        );
+        assert!(names.contains(&"link_a".to_string()));
+        assert!(names.contains(&"link_b".to_string()));
+        assert_eq!(names.iter().filter(|n| *n == "inner.txt").count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_indexes_broken_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = tempdir().unwrap();
+        let manager = IndexManager::new(&temp_dir.path().join("test_db")).unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir_all(root.join(".cache")).unwrap();
+        File::create(root.join("file.txt")).unwrap();
+        symlink(root.join("file.txt"), root.join("working")).unwrap();
+        symlink(root.join("missing.txt"), root.join("dangling")).unwrap();
+        symlink(root.join("missing.txt"), root.join(".cache").join("stale")).unwrap();
+
+        for follow_symlinks in [false, true] {
+            let mut broken = Vec::new();
+            let skipped = manager
+                .walk_directory(
+                    &root,
+                    WalkOptions {
+                        follow_symlinks,
+                        ..WalkOptions::default()
+                    },
+                    |entity| {
+                        if entity.attributes.broken_link {
+                            broken.push((entity.name, entity.hidden));
+                        }
+                        Ok::<_, String>(())
+                    },
+                )
+                .unwrap();
+            broken.sort();
+            assert_eq!(skipped, 0);
+            assert_eq!(
+                broken,
+                vec![("dangling".to_string(), false), ("stale".to_string(), true)]
+            );
+        }
+
+        let entity = entity_for_path(&root.join("dangling")).unwrap();
+        assert!(entity.attributes.broken_link);
+        assert!(
+            !entity_for_path(&root.join("working"))
+                .unwrap()
+                .attributes
+                .broken_link
+        );
+        assert!(entity_for_path(&root.join("missing.txt")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_records_link_targets() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = tempdir().unwrap();
+        let manager = IndexManager::new(&temp_dir.path().join("test_db")).unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        File::create(root.join("file.txt")).unwrap();
+        symlink("../file.txt", root.join("sub").join("relative")).unwrap();
+        symlink(root.join("sub"), root.join("absolute")).unwrap();
+
+        let mut links = Vec::new();
+        manager
+            .walk_directory(&root, WalkOptions::default(), |entity| {
+                let attributes = entity.attributes;
+                if attributes.is_symlink || attributes.link_target.is_some() {
+                    links.push((entity.name, attributes.link_target));
+                }
+                Ok::<_, String>(())
+            })
+            .unwrap();
+        links.sort();
+        let target = |path: std::path::PathBuf| Some(path.to_string_lossy().to_string());
+        assert_eq!(
+            links,
+            vec![
+                ("absolute".to_string(), target(root.join("sub"))),
+                (
+                    "relative".to_string(),
+                    target(root.join("sub").join("../file.txt"))
+                ),
+            ]
+        );
+
+        let entity = entity_for_path(&root.join("absolute")).unwrap();
+        assert!(entity.is_folder && entity.attributes.is_symlink);
+        assert!(
+            !entity_for_path(&root.join("file.txt"))
+                .unwrap()
+                .attributes
+                .is_symlink
+        );
+    }
+
     #[test]
     fn test_traverse_directory_nonexistent() {
         let temp_dir = tempdir().unwrap();
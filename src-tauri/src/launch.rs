@@ -0,0 +1,93 @@
+// Searches requested from outside the app: crosseverything://search?q=...
+// deep links and command-line arguments, for launcher integrations
+
+use url::Url;
+
+/// Deep link scheme, registered through the deep-link plugin config
+pub const URL_SCHEME: &str = "crosseverything";
+const SEARCH_FLAG: &str = "--search";
+
+/// Query of a `crosseverything://search?q=...` link
+pub fn query_from_url(url: &Url) -> Option<String> {
+    if url.scheme() != URL_SCHEME || url.host_str() != Some("search") {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "q")
+        .map(|(_, query)| query.into_owned())
+        .filter(|query| !query.trim().is_empty())
+}
+
+/// Query given on the command line as `--search <query>`, `--search=<query>`
+/// or the first plain argument. `args` excludes the program name. Deep links
+/// are left to the deep-link plugin, and other flags are skipped.
+pub fn query_from_args(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    let mut plain = None;
+    while let Some(arg) = args.next() {
+        if arg == SEARCH_FLAG {
+            return args.next().cloned().filter(|q| !q.trim().is_empty());
+        }
+        if let Some(query) = arg
+            .strip_prefix(SEARCH_FLAG)
+            .and_then(|a| a.strip_prefix('='))
+        {
+            return Some(query.to_string()).filter(|q| !q.trim().is_empty());
+        }
+        let is_link = arg
+            .strip_prefix(URL_SCHEME)
+            .is_some_and(|rest| rest.starts_with(':'));
+        if plain.is_none() && !arg.starts_with('-') && !is_link && !arg.trim().is_empty() {
+            plain = Some(arg.clone());
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_query_from_url() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert_eq!(
+            query_from_url(&url("crosseverything://search?q=report%202024+pdf")),
+            Some("report 2024 pdf".to_string())
+        );
+        assert_eq!(
+            query_from_url(&url("crosseverything://search?limit=5&q=*.rs")),
+            Some("*.rs".to_string())
+        );
+        assert_eq!(query_from_url(&url("crosseverything://search?q=")), None);
+        assert_eq!(query_from_url(&url("crosseverything://open?q=x")), None);
+        assert_eq!(query_from_url(&url("https://search?q=x")), None);
+    }
+
+    #[test]
+    fn test_query_from_args() {
+        assert_eq!(
+            query_from_args(&args(&["--search", "foo bar"])),
+            Some("foo bar".to_string())
+        );
+        assert_eq!(
+            query_from_args(&args(&["--hidden", "--search=*.txt"])),
+            Some("*.txt".to_string())
+        );
+        assert_eq!(
+            query_from_args(&args(&["--autostart", "notes"])),
+            Some("notes".to_string())
+        );
+        assert_eq!(query_from_args(&args(&["--hidden"])), None);
+        assert_eq!(query_from_args(&args(&["--search"])), None);
+        assert_eq!(
+            query_from_args(&args(&["crosseverything://search?q=x"])),
+            None,
+            "Deep links go through the plugin"
+        );
+    }
+}
@@ -1,16 +1,66 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
+mod actions;
+mod backup;
+mod builder;
+mod checksum;
+mod clipboard;
+mod diagnostics;
+mod elevation;
+mod email;
+mod embed;
+mod error;
+mod explorer;
+mod format;
+mod git;
+mod grep;
+mod i18n;
 mod index;
+mod launch;
+mod links;
+mod longpath;
+mod marks;
+mod mcp;
+mod metrics;
+mod ocr;
+mod onboarding;
+mod permissions;
+mod picker;
+mod portable;
+mod privacy;
+mod profile;
+mod project;
+mod quarantine;
+mod queue;
+mod rebuild;
+mod remote;
+mod rename;
 mod search;
+mod settings;
+mod shred;
+mod snapshot;
+mod streams;
+mod table;
+mod transfer;
+mod tray;
+mod undo;
+mod volumes;
 mod watcher;
+mod window_session;
+mod wsl;
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+mod xattr;
 
 use chrono::{DateTime, Utc};
+use error::{AppError, ErrorKind};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
-use tantivy::schema::Value;
-use tauri::menu::{Menu, MenuItem};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tokio::sync::{Mutex, RwLock};
 
 /// Initialize logging to file with rotation
 fn init_logging(log_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
@@ -21,7 +71,7 @@ fn init_logging(log_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
         .log_to_file(
             flexi_logger::FileSpec::default()
                 .directory(log_dir)
-                .basename("crosseverything"),
+                .basename(diagnostics::LOG_BASENAME),
         )
         .rotate(
             flexi_logger::Criterion::Size(10_000_000), // 10MB per file
@@ -48,49 +98,211 @@ pub struct FileEntity {
     pub size: u64,
     pub modified: i64, // Unix timestamp in seconds
     pub is_folder: bool,
+    /// Dotfile, or hidden/system on Windows, or inside such a directory
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub attributes: FileAttributes,
+    /// Creation (birth) time where the platform and file system report it
+    #[serde(default)]
+    pub created: Option<i64>,
+    /// Last access time; only recorded for roots that opt in
+    #[serde(default)]
+    pub accessed: Option<i64>,
+}
+
+/// Ownership and permission bits, searchable via `attrib:`, `owner:`,
+/// `group:` and `mode:` filters
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FileAttributes {
+    pub readonly: bool,
+    pub uid: Option<u32>,  // Unix only
+    pub gid: Option<u32>,  // Unix only
+    pub mode: Option<u32>, // Unix permission bits (mode & 0o7777)
+    /// Symbolic link whose target no longer exists
+    #[serde(default)]
+    pub broken_link: bool,
+    /// Platform file identity shared by every hard link to a file and kept
+    /// across renames; see `index::file_id`
+    #[serde(default)]
+    pub file_id: Option<String>,
+    /// Symbolic link, or junction on Windows
+    #[serde(default)]
+    pub is_symlink: bool,
+    /// Where the link points, a relative target resolved against the link's
+    /// folder; see `index::link_target`
+    #[serde(default)]
+    pub link_target: Option<String>,
+    /// Root of the Git work tree the entry lies in
+    #[serde(default)]
+    pub repo_root: Option<String>,
+    /// Status in that work tree when the entry was indexed; None for the
+    /// root itself, Git's own files, or when git could not be run
+    #[serde(default)]
+    pub git_status: Option<git::GitStatus>,
+    /// What makes the folder a project root; empty for other entries
+    #[serde(default)]
+    pub project_markers: Vec<project::ProjectMarker>,
+    /// Color label the user gave the entry; see `marks`
+    #[serde(default)]
+    pub label: Option<marks::ColorLabel>,
+    /// Star rating the user gave the entry, 1 to `marks::MAX_RATING`
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// Note the user attached to the entry, searched with its name
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Sender, subject and date of a saved email, matched by `from:` and
+    /// `subject:`
+    #[serde(default)]
+    pub email: Option<email::EmailHeaders>,
+    /// Cloud placeholder whose content is not downloaded; see
+    /// `index::is_online_only`. Such files are not read, so as not to fetch them.
+    #[serde(default)]
+    pub online_only: bool,
+    /// Account owning the entry, looked up from its owner SID. Windows only;
+    /// Unix entries carry `uid` instead.
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Text the OCR command read in an image, searched with its name; see
+    /// `ocr`
+    #[serde(default)]
+    pub ocr_text: Option<String>,
 }
 
 #[derive(Clone)]
 struct AppState {
-    index_manager: Arc<Mutex<Option<index::IndexManager>>>,
-    search_index: Arc<Mutex<Option<search::SearchIndex>>>,
-    #[allow(dead_code)] // Reserved for future file watcher integration
+    index_manager: Arc<RwLock<Option<index::IndexManager>>>,
+    /// Searches take a snapshot of the handle instead of holding the lock, so
+    /// an index swap never waits behind a slow query
+    search_index: Arc<RwLock<Option<Arc<search::SearchIndex>>>>,
     file_watcher: Arc<Mutex<Option<watcher::FileWatcher>>>,
-    is_indexing: Arc<Mutex<bool>>,
-    total_files: Arc<Mutex<usize>>,
-    last_updated: Arc<Mutex<Option<i64>>>,
+    is_indexing: Arc<AtomicBool>,
+    /// Entries walked so far by the running build
+    index_progress: Arc<AtomicUsize>,
+    /// Watcher changes are held rather than applied while set
+    watcher_paused: Arc<AtomicBool>,
+    /// The last index build failed; cleared by the next one that succeeds
+    index_failed: Arc<AtomicBool>,
+    /// Shared by changes to the live index, held exclusively while a backup
+    /// reads its files
+    index_writes: Arc<RwLock<()>>,
+    total_files: Arc<RwLock<usize>>,
+    last_updated: Arc<RwLock<Option<i64>>>,
+    mcp_server: Arc<Mutex<Option<mcp::McpHandle>>>,
+    /// Agent serving the index to other machines, while running
+    agent: Arc<Mutex<Option<remote::AgentHandle>>>,
+    profiles: Arc<RwLock<Option<profile::ProfileStore>>>,
+    search_sessions: Arc<Mutex<search::SearchSessions>>,
+    query_cache: Arc<Mutex<search::QueryCache>>,
+    /// Search requested by a deep link or launch argument, until the window
+    /// takes it. Filled from sync plugin callbacks, hence std's Mutex.
+    pending_search: Arc<std::sync::Mutex<Option<String>>>,
+    metrics: Arc<Mutex<metrics::Metrics>>,
+    /// Cancellation of searches started with a search_id, by that id
+    running_searches: Arc<std::sync::Mutex<HashMap<String, search::CancelToken>>>,
+    /// Labels and ratings, opened at startup; unset when that failed
+    marks: Arc<std::sync::OnceLock<marks::MarkStore>>,
+    /// Copies and moves started with a transfer_id, by that id
+    running_transfers: Arc<std::sync::Mutex<HashMap<String, RunningTransfer>>>,
+    /// Search context of each window, loaded on first use
+    window_sessions: Arc<Mutex<Option<window_session::SessionStore>>>,
+    /// Renames, moves and trashing to undo, opened at startup; unset when
+    /// that failed
+    journal: Arc<std::sync::OnceLock<undo::UndoJournal>>,
+    /// Held while an operation is undone or redone, so two calls can't
+    /// replay the same one
+    replaying: Arc<Mutex<()>>,
+    /// First-run setup progress, opened at startup; unset when that failed
+    onboarding: Arc<std::sync::OnceLock<onboarding::OnboardingStore>>,
+    /// Images waiting for the OCR worker
+    ocr_queue: Arc<std::sync::Mutex<queue::PathQueue>>,
+    /// Entries waiting for the embedding worker
+    embed_queue: Arc<std::sync::Mutex<queue::PathQueue>>,
+}
+
+/// Handles on a running copy or move: its cancellation, and where answers
+/// to its conflicts go
+struct RunningTransfer {
+    cancel: search::CancelToken,
+    answers: std::sync::mpsc::Sender<transfer::ConflictAnswer>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         AppState {
-            index_manager: Arc::new(Mutex::new(None)),
-            search_index: Arc::new(Mutex::new(None)),
+            index_manager: Arc::new(RwLock::new(None)),
+            search_index: Arc::new(RwLock::new(None)),
             file_watcher: Arc::new(Mutex::new(None)),
-            is_indexing: Arc::new(Mutex::new(false)),
-            total_files: Arc::new(Mutex::new(0)),
-            last_updated: Arc::new(Mutex::new(None)),
+            is_indexing: Arc::new(AtomicBool::new(false)),
+            index_progress: Arc::new(AtomicUsize::new(0)),
+            watcher_paused: Arc::new(AtomicBool::new(false)),
+            index_failed: Arc::new(AtomicBool::new(false)),
+            index_writes: Arc::new(RwLock::new(())),
+            total_files: Arc::new(RwLock::new(0)),
+            last_updated: Arc::new(RwLock::new(None)),
+            mcp_server: Arc::new(Mutex::new(None)),
+            agent: Arc::new(Mutex::new(None)),
+            profiles: Arc::new(RwLock::new(None)),
+            search_sessions: Arc::new(Mutex::new(search::SearchSessions::default())),
+            query_cache: Arc::new(Mutex::new(search::QueryCache::default())),
+            pending_search: Arc::new(std::sync::Mutex::new(None)),
+            metrics: Arc::new(Mutex::new(metrics::Metrics::default())),
+            running_searches: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            marks: Arc::new(std::sync::OnceLock::new()),
+            running_transfers: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            window_sessions: Arc::new(Mutex::new(None)),
+            journal: Arc::new(std::sync::OnceLock::new()),
+            replaying: Arc::new(Mutex::new(())),
+            onboarding: Arc::new(std::sync::OnceLock::new()),
+            ocr_queue: Arc::new(std::sync::Mutex::new(queue::PathQueue::default())),
+            embed_queue: Arc::new(std::sync::Mutex::new(queue::PathQueue::default())),
         }
     }
 }
 
+impl AppState {
+    /// Handle to the live search index for one read. The lock is released
+    /// before returning, so the caller may search for as long as it needs.
+    async fn search_snapshot(&self) -> Option<Arc<search::SearchIndex>> {
+        self.search_index.read().await.clone()
+    }
+}
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-/// Load existing index if available
-async fn load_existing_index(
+/// Run `f` against the profile registry, loading it from disk on first use
+async fn with_profiles<T>(
     app: &tauri::AppHandle,
-    state: &tauri::State<'_, AppState>,
-) -> Result<bool, String> {
-    let app_data_dir = app
-        .path()
-        .app_local_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    state: &AppState,
+    f: impl FnOnce(&mut profile::ProfileStore) -> T,
+) -> Result<T, String> {
+    let mut profiles = state.profiles.write().await;
+    if profiles.is_none() {
+        *profiles = Some(profile::ProfileStore::load(&app_data_dir(app)?));
+    }
+    Ok(f(profiles.as_mut().unwrap()))
+}
+
+/// Directory holding the active profile's index generations
+async fn active_index_dir(
+    app: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<std::path::PathBuf, String> {
+    with_profiles(app, state, |profiles| {
+        profiles.data_dir(&profiles.active().name)
+    })
+    .await
+}
 
-    let db_path = app_data_dir.join(".index_db");
-    let search_index_path = app_data_dir.join(".search_index");
+/// Load existing index if available
+async fn load_existing_index(app: &tauri::AppHandle, state: &AppState) -> Result<bool, String> {
+    let dir = active_index_dir(app, state).await?;
+    rebuild::reap(&dir);
+    let (db_path, search_index_path) = rebuild::live_paths(&dir);
 
     // Check if both index files exist
     if !db_path.exists() || !search_index_path.exists() {
@@ -109,14 +321,32 @@ async fn load_existing_index(
         }
     };
 
-    let search_index = match search::SearchIndex::new(&search_index_path) {
-        Ok(index) => index,
-        Err(e) => {
-            log::warn!("Failed to open existing search index: {}, will rebuild", e);
-            return Ok(false);
+    let search_index = if search::SearchIndex::schema_version(&search_index_path)
+        != Some(search::SCHEMA_VERSION)
+    {
+        match migrate_search_index(&index_manager, &dir, &db_path) {
+            Ok(index) => index,
+            Err(e) => {
+                log::warn!("Failed to migrate search index: {}, will rebuild", e);
+                return Ok(false);
+            }
+        }
+    } else {
+        match search::SearchIndex::new(&search_index_path) {
+            Ok(index) => index,
+            Err(e) => {
+                log::warn!("Failed to open existing search index: {}, will rebuild", e);
+                return Ok(false);
+            }
         }
     };
 
+    // Changes journaled in sled but lost from the search index by a crash
+    if let Err(e) = builder::replay_journal(&index_manager, &search_index) {
+        log::warn!("Failed to replay index journal: {}, will rebuild", e);
+        return Ok(false);
+    }
+
     // Count files in DB
     let total_files = match index_manager.count_files() {
         Ok(count) => {
@@ -129,84 +359,354 @@ async fn load_existing_index(
         }
     };
 
+    let search_index = Arc::new(search_index);
+    if app_data_dir(app).is_ok_and(|dir| settings::Settings::load(&dir).warm_up_index) {
+        spawn_warm_up(&search_index);
+    }
+
     // Update state
-    *state.index_manager.lock().unwrap() = Some(index_manager);
-    *state.search_index.lock().unwrap() = Some(search_index);
-    *state.total_files.lock().unwrap() = total_files;
-    *state.last_updated.lock().unwrap() = Some(
+    *state.index_manager.write().await = Some(index_manager);
+    *state.search_index.write().await = Some(search_index);
+    *state.total_files.write().await = total_files;
+    *state.last_updated.write().await = Some(
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64,
     );
+    for queue in [&state.ocr_queue, &state.embed_queue] {
+        queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .request_backlog();
+    }
 
     log::info!("Existing index loaded successfully");
     Ok(true)
 }
 
+/// Warm up `search_index` on a blocking thread. Only a weak handle is kept,
+/// so closing the index ends the warm-up instead of waiting for it.
+fn spawn_warm_up(search_index: &Arc<search::SearchIndex>) {
+    let search_index = Arc::downgrade(search_index);
+    tauri::async_runtime::spawn_blocking(move || {
+        let start = std::time::Instant::now();
+        match search::SearchIndex::warm_up(&search_index) {
+            Ok(Some(stats)) => log::info!(
+                "Warmed up {} segments ({} terms, {} postings, {} sort values) in {:?}",
+                stats.segments,
+                stats.terms,
+                stats.postings,
+                stats.fast_values,
+                start.elapsed()
+            ),
+            Ok(None) => log::debug!("Index closed during warm-up"),
+            Err(e) => log::warn!("Failed to warm up search index: {}", e),
+        }
+    });
+}
+
+/// Bring the search index of `dir`, built with an older schema, up to date by
+/// regenerating it from sled, whose live DB is `db_path`, into a new
+/// generation and making that live
+fn migrate_search_index(
+    index_manager: &index::IndexManager,
+    dir: &Path,
+    db_path: &Path,
+) -> Result<search::SearchIndex, String> {
+    log::info!(
+        "Search index schema version {:?} is outdated, migrating to {}",
+        search::SearchIndex::schema_version(&rebuild::live_paths(dir).1),
+        search::SCHEMA_VERSION
+    );
+    let (_, staging) = rebuild::new_generation(dir)
+        .map_err(|e| format!("Failed to create index generation: {}", e))?;
+    stage_search_index(index_manager, &staging)?;
+    rebuild::commit(dir, (db_path, &staging))
+        .map_err(|e| format!("Failed to swap in migrated index: {}", e))?;
+    rebuild::reap(dir);
+    search::SearchIndex::new(&staging).map_err(|e| format!("Failed to open search index: {}", e))
+}
+
+/// Regenerate the search index from sled into `staging`, returning the number
+/// of documents written
+fn stage_search_index(
+    index_manager: &index::IndexManager,
+    staging: &Path,
+) -> Result<usize, String> {
+    rebuild::clear_staging(staging).map_err(|e| e.to_string())?;
+
+    let staged = search::SearchIndex::new(staging)
+        .map_err(|e| format!("Failed to create search index: {}", e))?;
+    let result = builder::rebuild_search_index(index_manager, &staged);
+    // Release the staged handle so its directory can be renamed or removed
+    drop(staged);
+    if result.is_err() {
+        if let Err(e) = rebuild::clear_staging(staging) {
+            log::warn!("Failed to remove staging directory: {}", e);
+        }
+    }
+    result
+}
+
+/// Rebuild the live search index of `dir` from sled into a new generation and
+/// swap it in. The DB lock is held throughout so watcher updates wait instead
+/// of being missed by the rebuild.
+async fn rebuild_search_from_db(state: &AppState, dir: &Path) -> Result<usize, AppError> {
+    let (db_path, _) = rebuild::live_paths(dir);
+    let mut index_manager_guard = state.index_manager.write().await;
+    // A corrupt search index keeps the DB from loading, so open it here
+    if index_manager_guard.is_none() {
+        if !db_path.exists() {
+            return Err(AppError::index_not_ready());
+        }
+        let index_manager = index::IndexManager::new(&db_path)
+            .map_err(|e| format!("Failed to open index DB: {}", e))?;
+        *index_manager_guard = Some(index_manager);
+    }
+    let Some(index_manager) = index_manager_guard.as_ref() else {
+        return Err(AppError::index_not_ready());
+    };
+
+    let (_, staging) =
+        rebuild::new_generation(dir).map_err(|e| AppError::io(dir.to_string_lossy(), &e))?;
+    let documents = stage_search_index(index_manager, &staging)?;
+
+    // The previous index stays live unless the new one opens and is committed
+    let search_index = search::SearchIndex::new(&staging)
+        .map_err(|e| format!("Failed to open search index: {}", e))?;
+    if let Err(e) = rebuild::commit(dir, (&db_path, &staging)) {
+        log::error!("Failed to swap in rebuilt search index: {}", e);
+        return Err(format!("Failed to swap in rebuilt search index: {}", e).into());
+    }
+    *state.search_index.write().await = Some(Arc::new(search_index));
+    // Cached results may carry an opstamp the new index reuses
+    *state.query_cache.lock().await = search::QueryCache::default();
+    rebuild::reap(dir);
+
+    *state.total_files.write().await = documents;
+    Ok(documents)
+}
+
+/// Make the freshly built generation holding `staged` the live index of `dir`.
+/// It is opened before anything changes, so on failure the previous index
+/// stays live; its generation is deleted once nothing has it open.
+async fn swap_in_staged_index(
+    state: &AppState,
+    dir: &Path,
+    (staging_db_path, staging_search_path): (&Path, &Path),
+) -> Result<(), String> {
+    let index_manager = index::IndexManager::new(staging_db_path)
+        .map_err(|e| format!("Failed to open index DB: {}", e))?;
+    let search_index = search::SearchIndex::new(staging_search_path)
+        .map_err(|e| format!("Failed to open search index: {}", e))?;
+
+    let mut index_manager_guard = state.index_manager.write().await;
+    let mut search_index_guard = state.search_index.write().await;
+    if let Err(e) = rebuild::commit(dir, (staging_db_path, staging_search_path)) {
+        log::error!("Failed to swap in rebuilt index: {}", e);
+        return Err(format!("Failed to swap in rebuilt index: {}", e));
+    }
+    *index_manager_guard = Some(index_manager);
+    *search_index_guard = Some(Arc::new(search_index));
+    // Cached results may carry an opstamp the new index reuses
+    *state.query_cache.lock().await = search::QueryCache::default();
+    drop(index_manager_guard);
+    drop(search_index_guard);
+    // A rebuild reads the images and embeds the entries again
+    for queue in [&state.ocr_queue, &state.embed_queue] {
+        queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .request_backlog();
+    }
+
+    rebuild::reap(dir);
+    Ok(())
+}
+
 #[tauri::command]
 async fn build_index(
     paths: Vec<String>,
     force_rebuild: bool,
+    estimate: Option<bool>,
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
-    {
-        let mut is_indexing = state.is_indexing.lock().unwrap();
-        if *is_indexing {
-            log::warn!("Index build requested but indexing is already in progress");
-            return Ok(serde_json::json!({
-                "status": "failed",
-                "files_indexed": 0,
-                "errors": vec!["Indexing already in progress"]
-            }));
+) -> Result<serde_json::Value, AppError> {
+    if state.is_indexing.swap(true, Ordering::SeqCst) {
+        log::warn!("Index build requested but indexing is already in progress");
+        return Ok(serde_json::json!({
+            "status": "failed",
+            "files_indexed": 0,
+            "errors": vec!["Indexing already in progress"]
+        }));
+    }
+
+    let result = run_index_build(paths, force_rebuild, estimate, &app, &state).await;
+    state.is_indexing.store(false, Ordering::SeqCst);
+    state.index_failed.store(result.is_err(), Ordering::SeqCst);
+
+    // Distinct terminal events so the UI can leave its progress screen
+    match &result {
+        Ok(summary) => {
+            let _ = app.emit("index-complete", summary);
         }
-        *is_indexing = true;
-    } // MutexGuard dropped here
+        Err(e) => {
+            let _ = app.emit("index-failed", serde_json::json!({ "error": e }));
+        }
+    }
+    result
+}
 
-    // Get app local data directory for storing index files
-    let app_data_dir = app
-        .path()
-        .app_local_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+/// Payload for "index-progress" events. `total` never drops below `processed`
+/// since the estimate may be stale or unknown (0); `eta_secs` is null when no
+/// meaningful estimate exists.
+fn progress_payload(
+    root: &str,
+    current_dir: &str,
+    processed: usize,
+    total_estimated: usize,
+    elapsed: std::time::Duration,
+    errors: usize,
+) -> serde_json::Value {
+    let elapsed_secs = elapsed.as_secs_f64();
+    let files_per_sec = if elapsed_secs > 0.0 {
+        processed as f64 / elapsed_secs
+    } else {
+        0.0
+    };
+    let eta_secs = if total_estimated > processed && files_per_sec > 0.0 {
+        Some(((total_estimated - processed) as f64 / files_per_sec).round() as u64)
+    } else {
+        None
+    };
 
-    let db_path = app_data_dir.join(".index_db");
-    let search_index_path = app_data_dir.join(".search_index");
+    serde_json::json!({
+        "processed": processed,
+        "total": total_estimated.max(processed),
+        "root": root,
+        "current_dir": current_dir,
+        "files_per_sec": files_per_sec.round() as u64,
+        "elapsed_secs": elapsed.as_secs(),
+        "eta_secs": eta_secs,
+        "errors": errors
+    })
+}
 
-    // If force_rebuild is true, delete existing index
-    if force_rebuild {
-        log::info!("Force rebuild requested, deleting existing index...");
-        if db_path.exists() {
-            if let Err(e) = std::fs::remove_dir_all(&db_path) {
-                log::warn!("Failed to delete existing DB: {}", e);
-            } else {
-                log::info!("Deleted existing DB at {:?}", db_path);
-            }
+/// Estimated total entries for progress reporting. Uses the previous build's
+/// count when available; the full counting walk only runs when requested with
+/// `estimate = Some(true)`, or on a first build unless `estimate = Some(false)`.
+/// Returns 0 when the total is unknown.
+fn initial_estimate(
+    paths: &[String],
+    previous_count: Option<usize>,
+    estimate: Option<bool>,
+) -> usize {
+    match (estimate, previous_count) {
+        (Some(false), _) => {
+            log::info!("Skipping estimation pass");
+            0
         }
-        if search_index_path.exists() {
-            if let Err(e) = std::fs::remove_dir_all(&search_index_path) {
-                log::warn!("Failed to delete existing search index: {}", e);
-            } else {
-                log::info!("Deleted existing search index at {:?}", search_index_path);
+        (None, Some(count)) => {
+            log::info!("Using previous build's count as estimate: {} files", count);
+            count
+        }
+        _ => {
+            log::info!("Phase 1: Estimating total files...");
+            let start_time = std::time::Instant::now();
+            let mut total_estimated = 0;
+            for path_str in paths {
+                let path = Path::new(path_str);
+                if path.exists() {
+                    log::debug!("Counting files in: {}", path_str);
+                    // Rough estimate: count entries (this is approximate)
+                    let count = walkdir::WalkDir::new(path).into_iter().count();
+                    total_estimated += count;
+                    log::info!("Found approximately {} entries in {}", count, path_str);
+                }
             }
+            log::info!(
+                "Phase 1 complete: Estimated {} total files in {:.2}s",
+                total_estimated,
+                start_time.elapsed().as_secs_f64()
+            );
+            total_estimated
         }
+    }
+}
+
+/// Build the active profile's index into staging directories and swap it in.
+/// The live index keeps serving searches until the swap.
+async fn run_index_build(
+    paths: Vec<String>,
+    force_rebuild: bool,
+    estimate: Option<bool>,
+    app: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<serde_json::Value, AppError> {
+    let dir = active_index_dir(app, state).await?;
+    let (db_path, search_index_path) = rebuild::live_paths(&dir);
+
+    // Overlapping roots would index the same files twice
+    let mut warnings = Vec::new();
+    let paths = if paths.is_empty() {
+        paths
+    } else {
+        let (merged, notes) = index::merge_roots(&paths);
+        warnings.extend(notes);
+        merged
+    };
+
+    // Empty paths means "rebuild the active profile's roots"; otherwise remember them.
+    // The previous build's count only carries over while the roots are unchanged.
+    let (paths, previous_count) = if paths.is_empty() {
+        let (roots, previous_count) = with_profiles(app, state, |profiles| {
+            let active = profiles.active();
+            (active.roots.clone(), active.last_file_count)
+        })
+        .await?;
+        let (merged, notes) = index::merge_roots(&roots);
+        warnings.extend(notes);
+        (merged, previous_count)
     } else {
-        // Check if index already exists and is valid
-        if db_path.exists() && search_index_path.exists() {
-            log::info!("Existing index found, checking validity...");
-            // Try to load existing index
-            if let Ok(true) = load_existing_index(&app, &state).await {
-                log::info!("Using existing index, skipping rebuild");
-                *state.is_indexing.lock().unwrap() = false;
-                return Ok(serde_json::json!({
-                    "status": "completed",
-                    "files_indexed": 0,
-                    "errors": Vec::<String>::new(),
-                    "message": "Using existing index"
-                }));
+        with_profiles(app, state, |profiles| {
+            let active = profiles.active().clone();
+            let previous_count = active.last_file_count.filter(|_| active.roots == paths);
+            if let Err(e) = profiles
+                .set_roots(&active.name, paths.clone())
+                .and_then(|_| profiles.save())
+            {
+                log::warn!("Failed to record profile roots: {}", e);
             }
-            log::info!("Existing index is invalid, will rebuild");
+            (paths.clone(), previous_count)
+        })
+        .await?
+    };
+
+    // Reuse a valid existing index unless a rebuild is forced
+    if !force_rebuild && db_path.exists() && search_index_path.exists() {
+        log::info!("Existing index found, checking validity...");
+        if let Ok(true) = load_existing_index(app, state).await {
+            log::info!("Using existing index, skipping rebuild");
+            return Ok(serde_json::json!({
+                "status": "completed",
+                "files_indexed": 0,
+                "errors": Vec::<String>::new(),
+                "message": "Using existing index"
+            }));
         }
+        log::info!("Existing index is invalid, will rebuild");
+    }
+
+    // One warning per protected folder instead of an error per entry inside it
+    let permission_report = permissions::check_permissions();
+    for location in permissions::inaccessible_under(&permission_report, &paths) {
+        let warning = format!(
+            "No permission to read {}; grant access in System Settings to index it",
+            location.path
+        );
+        log::warn!("{}", warning);
+        warnings.push(warning);
     }
 
     log::info!("Starting index build for {} path(s)", paths.len());
@@ -214,61 +714,34 @@ async fn build_index(
         log::info!("Index path {}: {}", i + 1, path_str);
     }
 
-    // Get app local data directory for storing index files
-    let app_data_dir = app
-        .path()
-        .app_local_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    // Create subdirectories for index files
-    let db_path = app_data_dir.join(".index_db");
-    let search_index_path = app_data_dir.join(".search_index");
+    // Build into a new generation so the live index stays searchable
+    let (staging_db_path, staging_search_path) =
+        rebuild::new_generation(&dir).map_err(|e| AppError::io(dir.to_string_lossy(), &e))?;
+    log::debug!("Staging DB path: {:?}", staging_db_path);
+    log::debug!("Staging search index path: {:?}", staging_search_path);
 
-    log::debug!("DB path: {:?}", db_path);
-    log::debug!("Search index path: {:?}", search_index_path);
-
-    let index_manager = index::IndexManager::new(&db_path).map_err(|e| {
+    let index_manager = index::IndexManager::new(&staging_db_path).map_err(|e| {
         log::error!("Failed to create index manager: {}", e);
         format!("Failed to create index manager: {}", e)
     })?;
 
-    let search_index = search::SearchIndex::new(&search_index_path).map_err(|e| {
+    let search_index = search::SearchIndex::new(&staging_search_path).map_err(|e| {
         log::error!("Failed to create search index: {}", e);
         format!("Failed to create search index: {}", e)
     })?;
 
-    let schema = search_index.get_schema();
-    let mut writer = search_index
-        .writer()
-        .map_err(|e| format!("Failed to create index writer: {}", e))?;
+    let mut builder = builder::IndexBuilder::new(&index_manager, &search_index)?
+        .with_marks(state.marks.get().cloned());
 
-    let mut files_indexed = 0;
     let mut errors = Vec::new();
-    let mut total_estimated = 0;
-
-    // First pass: estimate total files
-    log::info!("Phase 1: Estimating total files...");
-    let start_time = std::time::Instant::now();
-    for path_str in &paths {
-        let path = Path::new(path_str);
-        if path.exists() {
-            log::debug!("Counting files in: {}", path_str);
-            // Rough estimate: count entries (this is approximate)
-            let count = walkdir::WalkDir::new(path).into_iter().count();
-            total_estimated += count;
-            log::info!("Found approximately {} entries in {}", count, path_str);
-        }
-    }
-    let estimate_time = start_time.elapsed();
-    log::info!(
-        "Phase 1 complete: Estimated {} total files in {:.2}s",
-        total_estimated,
-        estimate_time.as_secs_f64()
-    );
+    let total_estimated = initial_estimate(&paths, previous_count, estimate);
+    let active_profile = with_profiles(app, state, |profiles| profiles.active().clone()).await?;
 
     // Second pass: index files with progress updates
     log::info!("Phase 2: Indexing files...");
     let index_start_time = std::time::Instant::now();
+    let mut skipped_total = 0;
+    state.index_progress.store(0, Ordering::SeqCst);
     for path_str in &paths {
         let path = Path::new(path_str);
         if !path.exists() {
@@ -279,104 +752,49 @@ async fn build_index(
         }
 
         log::info!("Indexing directory: {}", path_str);
-        let entities = match index_manager.traverse_directory(path) {
-            Ok(entities) => entities,
-            Err(e) => {
-                let error_details = if let Some(io_err) = e.downcast_ref::<std::io::Error>() {
-                    let error_kind = format!("{:?}", io_err.kind());
-                    let error_code = io_err
-                        .raw_os_error()
-                        .map(|code| format!("os error {}", code))
-                        .unwrap_or_else(|| "no error code".to_string());
-                    format!("{} ({}), {}", io_err, error_kind, error_code)
-                } else {
-                    format!("{}", e)
-                };
-
-                log::error!(
-                    "Failed to traverse directory {}: {}",
-                    path_str,
-                    error_details
-                );
-                return Err(format!("Failed to traverse directory: {}", e));
-            }
-        };
-
-        log::info!("Found {} entities in {}", entities.len(), path_str);
-
-        for entity in entities {
-            // Save to sled
-            index_manager.save_file_entity(&entity).map_err(|e| {
-                log::error!("Failed to save entity {}: {}", entity.path, e);
-                format!("Failed to save entity: {}", e)
-            })?;
-
-            // Add to tantivy index
-            let mut doc = tantivy::TantivyDocument::default();
-            let name_field = schema
-                .get_field("name")
-                .map_err(|e| format!("Failed to get name field: {}", e))?;
-            let path_field = schema
-                .get_field("path")
-                .map_err(|e| format!("Failed to get path field: {}", e))?;
-            let size_field = schema
-                .get_field("size")
-                .map_err(|e| format!("Failed to get size field: {}", e))?;
-            let modified_field = schema
-                .get_field("modified")
-                .map_err(|e| format!("Failed to get modified field: {}", e))?;
-            let is_folder_field = schema
-                .get_field("is_folder")
-                .map_err(|e| format!("Failed to get is_folder field: {}", e))?;
-
-            doc.add_text(name_field, &entity.name);
-            doc.add_text(path_field, &entity.path);
-            doc.add_u64(size_field, entity.size);
-            doc.add_date(
-                modified_field,
-                tantivy::DateTime::from_timestamp_secs(entity.modified),
-            );
-            doc.add_bool(is_folder_field, entity.is_folder);
-
-            writer.add_document(doc).map_err(|e| {
-                log::error!(
-                    "Failed to add document to search index for {}: {}",
-                    entity.path,
-                    e
-                );
-                format!("Failed to add document: {}", e)
-            })?;
-
-            files_indexed += 1;
+        let error_count = errors.len() + skipped_total;
+        // Entities stream from the walk into the builder, which only buffers one batch
+        let options = active_profile.options_for(path_str);
+        if options.follow_symlinks {
+            log::info!("Following symlinks under {}", path_str);
+        }
+        let skipped = index_manager.walk_directory(path, options, |entity| {
+            let current_dir = Path::new(&entity.path)
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+            builder.add(entity)?;
 
             // Emit progress event every 50 files (more frequent updates)
+            let files_indexed = builder.files_indexed();
             if files_indexed % 50 == 0 {
-                let elapsed = index_start_time.elapsed();
-                let rate = files_indexed as f64 / elapsed.as_secs_f64();
-                let percentage = (files_indexed as f64 / total_estimated.max(1) as f64) * 100.0;
-                log::info!(
-                    "Progress: {}/{} files ({:.1}%), {:.0} files/sec",
+                state.index_progress.store(files_indexed, Ordering::SeqCst);
+                let payload = progress_payload(
+                    path_str,
+                    &current_dir,
                     files_indexed,
                     total_estimated,
-                    percentage,
-                    rate
+                    index_start_time.elapsed(),
+                    error_count,
                 );
-                let _ = app.emit(
-                    "index-progress",
-                    serde_json::json!({
-                        "processed": files_indexed,
-                        "total": total_estimated
-                    }),
+                log::info!(
+                    "Progress: {}/{} files, {} files/sec",
+                    files_indexed,
+                    payload["total"],
+                    payload["files_per_sec"]
                 );
+                let _ = app.emit("index-progress", payload);
             }
+            Ok::<(), String>(())
+        })?;
+
+        if skipped > 0 {
+            log::warn!("Skipped {} unreadable entries in {}", skipped, path_str);
+            skipped_total += skipped;
         }
     }
 
-    log::info!("Committing index...");
-    writer.commit().map_err(|e| {
-        log::error!("Failed to commit index: {}", e);
-        format!("Failed to commit index: {}", e)
-    })?;
+    let files_indexed = builder.finish()?;
 
     let total_time = index_start_time.elapsed();
     let rate = files_indexed as f64 / total_time.as_secs_f64();
@@ -394,183 +812,3888 @@ async fn build_index(
         }
     }
 
+    drop(index_manager);
+    drop(search_index);
+
+    swap_in_staged_index(state, &dir, (&staging_db_path, &staging_search_path)).await?;
+
     // Update state
-    *state.index_manager.lock().unwrap() = Some(index_manager);
-    *state.search_index.lock().unwrap() = Some(search_index);
-    *state.total_files.lock().unwrap() = files_indexed;
-    *state.last_updated.lock().unwrap() = Some(
+    *state.total_files.write().await = files_indexed;
+    *state.last_updated.write().await = Some(
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64,
     );
-    *state.is_indexing.lock().unwrap() = false;
+
+    // Remember the count so the next build can skip the estimation walk
+    with_profiles(app, state, |profiles| {
+        let active = profiles.active().name.clone();
+        profiles
+            .set_file_count(&active, files_indexed)
+            .and_then(|_| profiles.save())
+    })
+    .await?
+    .unwrap_or_else(|e| log::warn!("Failed to record file count: {}", e));
 
     // Emit final progress event
     let _ = app.emit(
         "index-progress",
-        serde_json::json!({
-            "processed": files_indexed,
-            "total": files_indexed
-        }),
+        progress_payload(
+            "",
+            "",
+            files_indexed,
+            files_indexed,
+            total_time,
+            errors.len() + skipped_total,
+        ),
     );
 
     // Note: File watcher integration will be implemented in a separate command
     // to avoid lifetime issues with async tasks
 
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
     Ok(serde_json::json!({
         "status": "completed",
         "files_indexed": files_indexed,
-        "errors": errors
+        "errors": errors,
+        "warnings": warnings
     }))
 }
 
+/// Query parameters shared by every profile searched for one request
+struct SearchParams<'a> {
+    query: &'a str,
+    use_regex: bool,
+    limit: usize,
+    filter: &'a search::SearchFilter,
+    /// Also count hits per extension, file type and top-level folder
+    facets: bool,
+    /// Return every match, unranked and without facets, instead of the top
+    /// `limit`; for exports
+    materialize_all: bool,
+    cancel: &'a search::CancelToken,
+}
+
+/// Run a query against one search index and convert the hits to results with
+/// their match spans filled in, leaving out private entries. Facets count
+/// folders directly below `roots`, or below the search scope when there is one.
+fn run_search(
+    search_index: &search::SearchIndex,
+    params: &SearchParams,
+    roots: &[String],
+) -> Result<(Vec<search::SearchResult>, Option<search::FacetCounts>), AppError> {
+    let SearchParams {
+        query,
+        use_regex,
+        limit,
+        filter,
+        facets,
+        materialize_all,
+        cancel,
+    } = *params;
+    let filter = &privacy::restrict(filter, privacy::Audience::User);
+    let scope: Vec<String> = filter
+        .scope
+        .iter()
+        .map(|scope| scope.to_string_lossy().to_string())
+        .collect();
+    let roots = if scope.is_empty() { roots } else { &scope };
+    let found = if materialize_all {
+        let mut docs = Vec::new();
+        search_index
+            .search_chunks(
+                query,
+                use_regex,
+                filter,
+                (None, search::DEFAULT_CHUNK_SIZE),
+                cancel,
+                |chunk| docs.extend(chunk),
+            )
+            .map(|_| (docs, None))
+    } else if facets {
+        search_index
+            .search_faceted(query, use_regex, limit, filter, roots, cancel)
+            .map(|(docs, counts)| (docs, Some(counts)))
+    } else {
+        search_index
+            .search_filtered(query, use_regex, limit, filter, cancel)
+            .map(|docs| (docs, None))
+    };
+    found
+        .and_then(|(docs, counts)| {
+            let mut results = search_index.to_results(&docs)?;
+            privacy::redact(&mut results, privacy::Audience::User);
+            let mut highlighter = search_index.highlighter(query, use_regex)?;
+            results.iter_mut().for_each(|r| highlighter.highlight(r));
+            Ok((results, counts))
+        })
+        .map_err(|e| search_error(e, query, cancel))
+}
+
+/// Error for a failed search, telling cancellation and timeouts apart
+fn search_error(e: tantivy::TantivyError, query: &str, cancel: &search::CancelToken) -> AppError {
+    if cancel.is_cancelled() {
+        log::debug!("Search cancelled: query='{}'", query);
+        AppError::new(ErrorKind::Cancelled, "Search was cancelled")
+    } else if cancel.is_expired() {
+        log::warn!("Search timed out: query='{}'", query);
+        AppError::new(ErrorKind::TimedOut, "Search took too long and was stopped")
+    } else if let tantivy::TantivyError::InvalidArgument(message) = e {
+        // Malformed filters and wildcard patterns too short to run
+        log::warn!("Invalid query '{}': {}", query, message);
+        AppError::new(ErrorKind::InvalidInput, message)
+    } else {
+        log::error!("Search failed for query '{}': {}", query, e);
+        AppError::new(ErrorKind::Internal, format!("Search failed: {}", e))
+    }
+}
+
+/// Search one profile: the loaded index for the active profile, otherwise
+/// its on-disk index opened read-only
+async fn search_profile(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    (name, active): (&str, &str),
+    params: &SearchParams<'_>,
+) -> Result<(Vec<search::SearchResult>, Option<search::FacetCounts>), AppError> {
+    let (roots, (_, search_index_path)) = with_profiles(app, state, |profiles| {
+        profiles
+            .get(name)
+            .map(|profile| (profile.roots.clone(), profiles.index_paths(name)))
+            .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("Unknown profile: {}", name)))
+    })
+    .await??;
+
+    if name == active {
+        let search_index = state
+            .search_snapshot()
+            .await
+            .ok_or_else(AppError::index_not_ready)?;
+        return run_search(&search_index, params, &roots);
+    }
+
+    if !search_index_path.exists() {
+        return Err(AppError::index_not_ready());
+    }
+    let search_index = search::SearchIndex::new(&search_index_path).map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            format!("Failed to open search index for {}: {}", name, e),
+        )
+        .with_path(search_index_path.to_string_lossy())
+    })?;
+    run_search(&search_index, params, &roots)
+}
+
+/// Filter for the include_hidden and scope arguments of the search commands
+fn search_filter(
+    include_hidden: Option<bool>,
+    scope: Option<String>,
+) -> Result<search::SearchFilter, AppError> {
+    let scope = scope.map(std::path::PathBuf::from);
+    if let Some(scope) = scope.as_ref().filter(|scope| !scope.is_absolute()) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "Search scope must be an absolute path",
+        )
+        .with_path(scope.to_string_lossy()));
+    }
+    Ok(search::SearchFilter {
+        exclude_hidden: !include_hidden.unwrap_or(true),
+        scope,
+        ..Default::default()
+    })
+}
+
+/// Registration of a search under its search_id, removed again on drop
+/// unless a newer search took the id over
+struct RunningSearch<'a> {
+    searches: &'a std::sync::Mutex<HashMap<String, search::CancelToken>>,
+    id: String,
+    cancel: search::CancelToken,
+}
+
+impl<'a> RunningSearch<'a> {
+    fn start(
+        searches: &'a std::sync::Mutex<HashMap<String, search::CancelToken>>,
+        id: &str,
+        cancel: &search::CancelToken,
+    ) -> Self {
+        if let Ok(mut running) = searches.lock() {
+            if let Some(previous) = running.insert(id.to_string(), cancel.clone()) {
+                previous.cancel();
+            }
+        }
+        RunningSearch {
+            searches,
+            id: id.to_string(),
+            cancel: cancel.clone(),
+        }
+    }
+}
+
+impl Drop for RunningSearch<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.searches.lock() {
+            if running
+                .get(&self.id)
+                .is_some_and(|token| token.is_same(&self.cancel))
+            {
+                running.remove(&self.id);
+            }
+        }
+    }
+}
+
+/// Time a stream may take unless the caller sets its own timeout, so an
+/// abandoned one does not keep a replaced index open
+const DEFAULT_STREAM_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_files(
     query: String,
     use_regex: bool,
     limit: Option<usize>,
+    profile: Option<String>,
+    include_hidden: Option<bool>,
+    facets: Option<bool>,
+    scope: Option<String>,
+    refine: Option<u64>,
+    search_id: Option<String>,
+    timeout_ms: Option<u64>,
+    materialize_all: Option<bool>,
+    remotes: Option<bool>,
+    snapshots: Option<bool>,
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
+) -> Result<search::SearchResponse, AppError> {
     let start_time = std::time::Instant::now();
     log::info!(
-        "Search request: query='{}', regex={}, limit={:?}",
+        "Search request: query='{}', regex={}, limit={:?}, profile={:?}, include_hidden={:?}, scope={:?}, refine={:?}",
         query,
         use_regex,
-        limit
+        limit,
+        profile,
+        include_hidden,
+        scope,
+        refine
     );
 
-    let search_index_guard = state.search_index.lock().unwrap();
-    let search_index = search_index_guard.as_ref().ok_or_else(|| {
+    let limit = limit.unwrap_or(search::MAX_RANKED_RESULTS);
+    let materialize_all = materialize_all.unwrap_or(false);
+    let step_filter = search_filter(include_hidden, scope)?;
+    let active = with_profiles(&app, &state, |profiles| profiles.active().name.clone()).await?;
+    let profile = profile.filter(|name| *name != active);
+    let selector = profile.clone().unwrap_or_else(|| active.clone());
+
+    // A refinement only searches the hits of an earlier session for the same profile
+    let previous = match refine {
+        Some(id) => {
+            let session = state.search_sessions.lock().await.get(id).cloned();
+            let session = session.ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::NotFound,
+                    format!("Search session {} has expired", id),
+                )
+            })?;
+            if session.profile != selector {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!("Search session {} belongs to another profile", id),
+                ));
+            }
+            Some(session)
+        }
+        None => None,
+    };
+    let filter = match &previous {
+        Some(session) => session.within(step_filter.clone()),
+        None => step_filter.clone(),
+    };
+
+    if profile.is_none() && state.search_index.read().await.is_none() {
         log::warn!("Search attempted but index is not ready");
-        "INDEX_NOT_READY".to_string()
-    })?;
+        return Err(AppError::index_not_ready());
+    }
+
+    // Validate regex if needed
+    if use_regex {
+        regex::Regex::new(&query).map_err(|e| {
+            log::warn!("Invalid regex pattern '{}': {}", query, e);
+            AppError::invalid_regex(e)
+        })?;
+    }
+
+    let cancel = match timeout_ms {
+        Some(0) => search::CancelToken::default(),
+        Some(ms) => search::CancelToken::with_timeout(std::time::Duration::from_millis(ms)),
+        None => search::CancelToken::with_timeout(search::DEFAULT_SEARCH_TIMEOUT),
+    };
+    let params = SearchParams {
+        query: &query,
+        use_regex,
+        limit,
+        filter: &filter,
+        facets: facets.unwrap_or(false),
+        materialize_all,
+        cancel: &cancel,
+    };
+
+    // Only the active profile's index is open, so only its searches are cached
+    let cache_key = search::CacheKey {
+        profile: selector.clone(),
+        query: query.clone(),
+        use_regex,
+        limit,
+        filter: filter.clone(),
+        facets: params.facets,
+    };
+    // Materialized result sets can be huge, so they bypass the cache
+    let opstamp = match profile {
+        None if !materialize_all => state
+            .search_index
+            .read()
+            .await
+            .as_ref()
+            .and_then(|search_index| search_index.opstamp().ok()),
+        _ => None,
+    };
+    let cached = match opstamp {
+        Some(opstamp) => state.query_cache.lock().await.get(&cache_key, opstamp),
+        None => None,
+    };
+    if cached.is_some() {
+        log::debug!("Search served from cache: query='{}'", query);
+    }
+
+    // A new search under the same id supersedes the one still running
+    let _running = search_id
+        .as_ref()
+        .map(|id| RunningSearch::start(&state.running_searches, id, &cancel));
+    let (mut results, facets) = match (cached, profile.as_deref()) {
+        (Some(cached), _) => cached,
+        (None, None) => {
+            let found = search_profile(&app, &state, (&active, &active), &params).await?;
+            if let Some(opstamp) = opstamp {
+                state
+                    .query_cache
+                    .lock()
+                    .await
+                    .insert(cache_key, opstamp, found.clone());
+            }
+            found
+        }
+        (None, Some(profile::ALL_PROFILES)) => {
+            let names: Vec<String> = with_profiles(&app, &state, |profiles| {
+                profiles.list().iter().map(|p| p.name.clone()).collect()
+            })
+            .await?;
+            let mut results = Vec::new();
+            let mut facets: Option<search::FacetCounts> = None;
+            for name in names {
+                match search_profile(&app, &state, (&name, &active), &params).await {
+                    Ok((found, counts)) => {
+                        results.extend(found.into_iter().map(|mut result| {
+                            result.profile = Some(name.clone());
+                            result
+                        }));
+                        if let Some(counts) = counts {
+                            facets.get_or_insert_with(Default::default).merge(counts);
+                        }
+                    }
+                    Err(e) if e.kind == ErrorKind::IndexNotReady => {
+                        log::debug!("Skipping profile {} without an index", name)
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if !materialize_all {
+                results.truncate(limit);
+            }
+            (results, facets)
+        }
+        (None, Some(name)) => search_profile(&app, &state, (name, &active), &params).await?,
+    };
+    // Checked on every answer rather than cached, so unblocking shows at once.
+    // Materialized sets are too large to check file by file.
+    if !materialize_all {
+        quarantine::mark(&mut results);
+    }
+    // Hits of other machines follow the local ones, up to `limit` from each
+    if remotes.unwrap_or(false) && !materialize_all {
+        let mut remote = search_remotes(&app, &query, use_regex, limit).await?;
+        privacy::redact(&mut remote, privacy::Audience::User);
+        results.extend(remote);
+    }
+    // Then those of unplugged disks, labeled with their snapshot
+    if snapshots.unwrap_or(false) && !materialize_all {
+        results.extend(search_snapshots(&app, &params)?);
+    }
+    // Relative dates age, so they are filled in after the cache
+    if !materialize_all {
+        format::annotate(&mut results, Utc::now().timestamp(), i18n::current());
+    }
+
+    let mut steps = previous.map(|session| session.steps).unwrap_or_default();
+    steps.push(search::SearchStep {
+        query: query.clone(),
+        use_regex,
+        filter: step_filter,
+    });
+    let session_id = state
+        .search_sessions
+        .lock()
+        .await
+        .insert(search::SearchSession {
+            profile: selector,
+            steps,
+        });
+
+    // Offered for text searches of the active profile that found nothing
+    let suggestions = match state.search_snapshot().await {
+        Some(search_index) if results.is_empty() && !use_regex && profile.is_none() => {
+            let query = query.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let hidden = privacy::hidden_paths(privacy::Audience::User);
+                search_index.suggest(&query, &hidden).unwrap_or_else(|e| {
+                    log::warn!("No spelling suggestions for '{}': {}", query, e);
+                    Vec::new()
+                })
+            })
+            .await
+            .map_err(|e| format!("Spelling suggestions failed: {}", e))?
+        }
+        _ => Vec::new(),
+    };
+
+    let elapsed = start_time.elapsed();
+    state.metrics.lock().await.search_latency.record(elapsed);
+    let search_time_ms = elapsed.as_millis() as u64;
+    log::info!(
+        "Search completed: {} results in {}ms (query='{}', regex={})",
+        results.len(),
+        search_time_ms,
+        query,
+        use_regex
+    );
+
+    Ok(search::SearchResponse {
+        total_found: results.len(),
+        results,
+        search_time_ms,
+        facets,
+        session_id,
+        suggestions,
+    })
+}
+
+/// Hits of the volume snapshots, up to `limit` from each. Snapshots that
+/// can't be opened are logged and left out.
+fn search_snapshots(
+    app: &tauri::AppHandle,
+    params: &SearchParams<'_>,
+) -> Result<Vec<search::SearchResult>, AppError> {
+    let store = snapshot::SnapshotStore::load(&app_data_dir(app)?);
+    let mut results = Vec::new();
+    for snapshot in store.list() {
+        let search_index = match search::SearchIndex::new(&store.search_index_path(&snapshot.id)) {
+            Ok(search_index) => search_index,
+            Err(e) => {
+                log::warn!("Skipping snapshot {}: {}", snapshot.id, e);
+                continue;
+            }
+        };
+        let roots = [snapshot.mount_point.clone()];
+        let (found, _) = run_search(&search_index, params, &roots)?;
+        results.extend(found.into_iter().map(|mut result| {
+            result.snapshot = Some(snapshot.label.clone());
+            result
+        }));
+    }
+    Ok(results)
+}
 
-    let limit = limit.unwrap_or(1000);
+/// Time a remote machine has to answer each step of a search
+const REMOTE_SEARCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Hits of the remote machines listed in settings, up to `limit` from each.
+/// Machines that can't be reached are logged and left out.
+async fn search_remotes(
+    app: &tauri::AppHandle,
+    query: &str,
+    use_regex: bool,
+    limit: usize,
+) -> Result<Vec<search::SearchResult>, String> {
+    let remotes = settings::Settings::load(&app_data_dir(app)?).remotes;
+    let searches: Vec<_> = remotes
+        .into_iter()
+        .map(|machine| {
+            let query = query.to_string();
+            tauri::async_runtime::spawn_blocking(move || {
+                let found =
+                    remote::search(&machine, &query, use_regex, limit, REMOTE_SEARCH_TIMEOUT);
+                (machine.name, found)
+            })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for search in searches {
+        match search
+            .await
+            .map_err(|e| format!("Remote search failed: {}", e))?
+        {
+            (_, Ok(found)) => results.extend(found),
+            (name, Err(e)) => log::warn!("Skipping remote machine {}: {}", name, e),
+        }
+    }
+    Ok(results)
+}
+
+/// Part of a streamed search, emitted as a `search-chunk` event
+#[derive(Debug, Clone, Serialize)]
+struct SearchChunk {
+    search_id: String,
+    /// Position of the first of `results` in the whole stream
+    offset: usize,
+    results: Vec<search::SearchResult>,
+}
+
+/// Outcome of stream_search, also emitted as `search-stream-complete`
+#[derive(Debug, Clone, Serialize)]
+struct StreamSummary {
+    search_id: String,
+    total: usize,
+    search_time_ms: u64,
+}
+
+/// Stream every match of `query` in the active profile as `search-chunk`
+/// events tagged with `search_id`, for result sets past the ranked limit of
+/// search_files. Matches come in index order, not by rank. `limit` caps the
+/// total, unlimited unless given, and `timeout_ms` the duration, two minutes
+/// unless given and unlimited when 0. cancel_search stops the stream.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn stream_search(
+    query: String,
+    use_regex: bool,
+    search_id: String,
+    limit: Option<usize>,
+    chunk_size: Option<usize>,
+    include_hidden: Option<bool>,
+    scope: Option<String>,
+    timeout_ms: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<StreamSummary, AppError> {
+    log::info!(
+        "Stream search request: query='{}', regex={}, id={}, limit={:?}",
+        query,
+        use_regex,
+        search_id,
+        limit
+    );
+    let filter = privacy::restrict(
+        &search_filter(include_hidden, scope)?,
+        privacy::Audience::User,
+    );
+    if use_regex {
+        regex::Regex::new(&query).map_err(AppError::invalid_regex)?;
+    }
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let cancel = match timeout_ms {
+        Some(0) => search::CancelToken::default(),
+        Some(ms) => search::CancelToken::with_timeout(std::time::Duration::from_millis(ms)),
+        None => search::CancelToken::with_timeout(DEFAULT_STREAM_TIMEOUT),
+    };
+    let _running = RunningSearch::start(&state.running_searches, &search_id, &cancel);
+    let start_time = std::time::Instant::now();
+
+    let emitter = app.clone();
+    let id = search_id.clone();
+    let total = tauri::async_runtime::spawn_blocking(move || {
+        let mut highlighter = search_index
+            .highlighter(&query, use_regex)
+            .map_err(|e| search_error(e, &query, &cancel))?;
+        let mut offset = 0;
+        search_index
+            .search_chunks(
+                &query,
+                use_regex,
+                &filter,
+                (limit, chunk_size.unwrap_or(search::DEFAULT_CHUNK_SIZE)),
+                &cancel,
+                |docs| match search_index.to_results(&docs) {
+                    Ok(mut results) => {
+                        privacy::redact(&mut results, privacy::Audience::User);
+                        results.iter_mut().for_each(|r| highlighter.highlight(r));
+                        quarantine::mark(&mut results);
+                        let count = results.len();
+                        let chunk = SearchChunk {
+                            search_id: id.clone(),
+                            offset,
+                            results,
+                        };
+                        let _ = emitter.emit("search-chunk", &chunk);
+                        offset += count;
+                    }
+                    Err(e) => log::warn!("Failed to read streamed results: {}", e),
+                },
+            )
+            .map_err(|e| search_error(e, &query, &cancel))
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))??;
+
+    let summary = StreamSummary {
+        search_id,
+        total,
+        search_time_ms: start_time.elapsed().as_millis() as u64,
+    };
+    log::info!(
+        "Stream search completed: {} results in {}ms",
+        summary.total,
+        summary.search_time_ms
+    );
+    let _ = app.emit("search-stream-complete", &summary);
+    Ok(summary)
+}
+
+/// Folders returned by group_search_by_folder unless the caller sets a limit
+const DEFAULT_FOLDER_GROUPS: usize = 1000;
+
+/// Entries returned by find_similar unless the caller sets a limit
+const DEFAULT_SIMILAR_RESULTS: usize = 50;
+
+/// Entries returned by semantic_search unless the caller sets a limit
+const DEFAULT_SEMANTIC_RESULTS: usize = 50;
+
+/// Suggestions of each kind autocomplete returns unless the caller sets a limit
+const DEFAULT_COMPLETIONS: usize = 8;
+
+/// Matches of `query` in the active profile collapsed into the folders
+/// directly containing them, with a count each, so searches with tens of
+/// thousands of hits in a few folders can be browsed folder by folder.
+/// `limit` caps the folders returned, not the matches counted.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn group_search_by_folder(
+    query: String,
+    use_regex: bool,
+    limit: Option<usize>,
+    include_hidden: Option<bool>,
+    scope: Option<String>,
+    search_id: Option<String>,
+    timeout_ms: Option<u64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<search::FolderGroups, AppError> {
+    log::info!(
+        "Folder search request: query='{}', regex={}, limit={:?}",
+        query,
+        use_regex,
+        limit
+    );
+    let filter = privacy::restrict(
+        &search_filter(include_hidden, scope)?,
+        privacy::Audience::User,
+    );
+    if use_regex {
+        regex::Regex::new(&query).map_err(AppError::invalid_regex)?;
+    }
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let cancel = match timeout_ms {
+        Some(0) => search::CancelToken::default(),
+        Some(ms) => search::CancelToken::with_timeout(std::time::Duration::from_millis(ms)),
+        None => search::CancelToken::with_timeout(search::DEFAULT_SEARCH_TIMEOUT),
+    };
+    let _running = search_id
+        .as_deref()
+        .map(|id| RunningSearch::start(&state.running_searches, id, &cancel));
+    let start_time = std::time::Instant::now();
+
+    let mut groups = tauri::async_runtime::spawn_blocking(move || {
+        search_index
+            .group_by_folder(
+                &query,
+                use_regex,
+                &filter,
+                limit.unwrap_or(DEFAULT_FOLDER_GROUPS),
+                &cancel,
+            )
+            .map_err(|e| search_error(e, &query, &cancel))
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))??;
+    let shown: u64 = groups.folders.iter().map(|group| group.count).sum();
+    let hidden = privacy::redact_by(&mut groups.folders, privacy::Audience::User, |group| {
+        &group.path
+    });
+    let still_shown: u64 = groups.folders.iter().map(|group| group.count).sum();
+    groups.total_folders -= hidden;
+    groups.total_found -= (shown - still_shown) as usize;
+
+    log::info!(
+        "Folder search completed: {} results in {} folders in {}ms",
+        groups.total_found,
+        groups.total_folders,
+        start_time.elapsed().as_millis()
+    );
+    Ok(groups)
+}
+
+/// Stop the search started with `search_id`, which then fails with
+/// `CANCELLED`. Returns false when no such search is running.
+#[tauri::command]
+async fn cancel_search(
+    search_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let cancel = state
+        .running_searches
+        .lock()
+        .ok()
+        .and_then(|mut running| running.remove(&search_id));
+    match cancel {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Entries of a built-in view of the active profile: `recently_modified`,
+/// `added_today`, `large_files`, `empty_folders` or `broken_symlinks`
+#[tauri::command]
+async fn get_smart_view(
+    name: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<search::SearchResult>, AppError> {
+    let view = search::SmartView::from_name(&name).ok_or_else(|| {
+        AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown smart view: {}", name),
+        )
+    })?;
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let hidden = privacy::hidden_paths(privacy::Audience::User);
+    search_index
+        .smart_view(view, limit.unwrap_or(1000), &hidden)
+        .and_then(|docs| search_index.to_results(&docs))
+        .map(|mut results| {
+            privacy::redact(&mut results, privacy::Audience::User);
+            quarantine::mark(&mut results);
+            results
+        })
+        .map_err(|e| {
+            log::error!("Smart view {} failed: {}", name, e);
+            AppError::new(ErrorKind::Internal, format!("Smart view failed: {}", e))
+        })
+}
+
+/// Most entries accepted by one search_batch call
+const MAX_BATCH_QUERIES: usize = 64;
+
+/// Entry of search_batch: a query, or a smart view when `view` is set
+#[derive(Debug, Clone, Deserialize)]
+struct BatchRequest {
+    #[serde(default)]
+    query: String,
+    #[serde(default)]
+    use_regex: bool,
+    view: Option<String>,
+    /// Hits to return besides the count; none by default
+    #[serde(default)]
+    limit: usize,
+}
+
+/// Outcome of one search_batch entry; a failed entry carries its error
+#[derive(Debug, Clone, Serialize)]
+struct BatchResponse {
+    count: usize,
+    results: Vec<search::SearchResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<AppError>,
+}
+
+impl BatchResponse {
+    fn failed(error: AppError) -> Self {
+        BatchResponse {
+            count: 0,
+            results: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Counts and top hits of several queries and smart views in one round trip,
+/// all answered from the same index snapshot. Entries fail independently.
+#[tauri::command]
+async fn search_batch(
+    queries: Vec<BatchRequest>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<BatchResponse>, AppError> {
+    if queries.len() > MAX_BATCH_QUERIES {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("At most {} queries per batch", MAX_BATCH_QUERIES),
+        ));
+    }
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+
+    let mut responses: Vec<Option<BatchResponse>> = Vec::with_capacity(queries.len());
+    let mut batch = Vec::new();
+    for request in &queries {
+        let query = match &request.view {
+            Some(name) => match search::SmartView::from_name(name) {
+                Some(view) => search::BatchQuery::View(view),
+                None => {
+                    responses.push(Some(BatchResponse::failed(AppError::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown smart view: {}", name),
+                    ))));
+                    continue;
+                }
+            },
+            None => {
+                if request.use_regex {
+                    if let Err(e) = regex::Regex::new(&request.query) {
+                        responses.push(Some(BatchResponse::failed(AppError::invalid_regex(e))));
+                        continue;
+                    }
+                }
+                search::BatchQuery::Query {
+                    query: request.query.clone(),
+                    use_regex: request.use_regex,
+                }
+            }
+        };
+        responses.push(None);
+        batch.push((query, request.limit));
+    }
+
+    let start = std::time::Instant::now();
+    let hidden = privacy::hidden_paths(privacy::Audience::User);
+    let hits = search_index.search_batch(&batch, &hidden).map_err(|e| {
+        log::error!("Batch search failed: {}", e);
+        AppError::new(ErrorKind::Internal, format!("Search failed: {}", e))
+    })?;
+    log::debug!(
+        "Batch of {} searches took {:?}",
+        batch.len(),
+        start.elapsed()
+    );
+
+    let mut hits = hits.into_iter();
+    Ok(queries
+        .iter()
+        .zip(responses)
+        .map(|(request, response)| {
+            response.unwrap_or_else(|| {
+                let found = hits.next().expect("one result per batched query");
+                found
+                    .and_then(|found| {
+                        let mut results = search_index.to_results(&found.docs)?;
+                        privacy::redact(&mut results, privacy::Audience::User);
+                        if request.view.is_none() {
+                            let mut highlighter =
+                                search_index.highlighter(&request.query, request.use_regex)?;
+                            results.iter_mut().for_each(|r| highlighter.highlight(r));
+                        }
+                        Ok(BatchResponse {
+                            count: found.count,
+                            results,
+                            error: None,
+                        })
+                    })
+                    .unwrap_or_else(|e| {
+                        log::error!("Batch entry '{}' failed: {}", request.query, e);
+                        BatchResponse::failed(AppError::new(
+                            ErrorKind::Internal,
+                            format!("Search failed: {}", e),
+                        ))
+                    })
+            })
+        })
+        .collect())
+}
+
+/// Outcome of find_broken_links
+#[derive(Debug, Clone, Serialize)]
+struct BrokenLinksReport {
+    links: Vec<links::BrokenLink>,
+    /// Indexed links whose targets were checked
+    checked: usize,
+    /// Whether `links` were moved to the trash
+    trashed: bool,
+}
+
+/// Check the targets of every indexed symlink and junction, and of .lnk
+/// shortcuts on Windows, returning the links whose target is missing. With
+/// `delete` they are moved to the trash and dropped from the index as
+/// move_to_trash does.
+#[tauri::command]
+async fn find_broken_links(
+    delete: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<BrokenLinksReport, AppError> {
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let start_time = std::time::Instant::now();
+    let hidden = privacy::hidden_paths(privacy::Audience::User);
+    let mut paths = search_index
+        .link_paths(cfg!(windows), &hidden)
+        .map_err(|e| {
+            log::error!("Failed to list indexed links: {}", e);
+            AppError::new(ErrorKind::Internal, format!("Search failed: {}", e))
+        })?;
+    privacy::redact_by(&mut paths, privacy::Audience::User, |path| path);
+    let checked = paths.len();
+    let links = tauri::async_runtime::spawn_blocking(move || {
+        paths
+            .iter()
+            .filter_map(|path| links::check(Path::new(path)))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| format!("Link check failed: {}", e))?;
+    log::info!(
+        "Found {} broken link(s) among {} in {}ms",
+        links.len(),
+        checked,
+        start_time.elapsed().as_millis()
+    );
+
+    let trashed = delete.unwrap_or(false) && !links.is_empty();
+    if trashed {
+        let paths: Vec<std::path::PathBuf> = links.iter().map(|link| (&link.path).into()).collect();
+        trash_and_unindex(&state, &paths).await?;
+    }
+    Ok(BrokenLinksReport {
+        links,
+        checked,
+        trashed,
+    })
+}
+
+/// Change the marks of `path` and refresh its indexed entry to match
+async fn update_marks(
+    state: &AppState,
+    path: &str,
+    change: impl FnOnce(&mut marks::FileMarks),
+) -> Result<marks::FileMarks, AppError> {
+    let store = state.marks.get().ok_or_else(|| {
+        AppError::new(
+            ErrorKind::Internal,
+            "Labels, ratings and notes are unavailable; see the log for why",
+        )
+    })?;
+    std::fs::symlink_metadata(path).map_err(|e| AppError::io(path, &e))?;
+    let mut file_marks = store
+        .get(path)
+        .map_err(|e| format!("Failed to read marks: {}", e))?;
+    change(&mut file_marks);
+    store
+        .set(path, &file_marks)
+        .map_err(|e| format!("Failed to save marks: {}", e))?;
+
+    let entity = match state.index_manager.read().await.as_ref() {
+        Some(index_manager) => index_manager
+            .get_file_entity_by_path(path)
+            .map_err(|e| format!("Failed to read index DB: {}", e))?,
+        None => None,
+    };
+    // Unindexed paths keep their marks for when they are indexed
+    if let Some(entity) = entity {
+        update_live_index(state, &[], &[entity]).await?;
+    }
+    Ok(file_marks)
+}
+
+/// Give `path` a color label, or clear it with None. On macOS it also
+/// becomes the Finder label. Searchable as `label:<color>`.
+#[tauri::command]
+async fn set_color_label(
+    path: String,
+    label: Option<marks::ColorLabel>,
+    state: tauri::State<'_, AppState>,
+) -> Result<marks::FileMarks, AppError> {
+    let file_marks = update_marks(&state, &path, |file_marks| file_marks.label = label).await?;
+    if let Err(e) = marks::set_finder_label(Path::new(&path), label) {
+        log::warn!("Failed to set the Finder label of {}: {}", path, e);
+    }
+    Ok(file_marks)
+}
+
+/// Rate `path` with 1 to 5 stars, or clear its rating with None.
+/// Searchable as `rating:>=4` and sortable with `sort:rating`.
+#[tauri::command]
+async fn set_rating(
+    path: String,
+    rating: Option<u8>,
+    state: tauri::State<'_, AppState>,
+) -> Result<marks::FileMarks, AppError> {
+    if rating.is_some_and(|stars| !(1..=marks::MAX_RATING).contains(&stars)) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Ratings go from 1 to {} stars", marks::MAX_RATING),
+        ));
+    }
+    update_marks(&state, &path, |file_marks| file_marks.rating = rating).await
+}
+
+/// Attach a free-text note to `path`, or remove it with None or a blank
+/// note. Text searches match notes like names.
+#[tauri::command]
+async fn set_note(
+    path: String,
+    note: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<marks::FileMarks, AppError> {
+    let note = note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note
+        .as_ref()
+        .is_some_and(|note| note.chars().count() > marks::MAX_NOTE_CHARS)
+    {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Notes are limited to {} characters", marks::MAX_NOTE_CHARS),
+        ));
+    }
+    update_marks(&state, &path, |file_marks| file_marks.note = note).await
+}
+
+/// Custom actions offered for `path`, in the order they were defined: those
+/// matching its extension and color label
+#[tauri::command]
+async fn list_actions(
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<actions::CustomAction>, AppError> {
+    let settings = settings::Settings::load(&app_data_dir(&app)?);
+    let (is_folder, label) = action_target(&state, &path)?;
+    Ok(settings
+        .actions
+        .into_iter()
+        .filter(|action| action.applies_to(Path::new(&path), is_folder, label))
+        .collect())
+}
+
+/// Run the custom action `id` on `path`. It must be one `list_actions`
+/// offers for the path.
+#[tauri::command]
+async fn run_action(
+    id: String,
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    let settings = settings::Settings::load(&app_data_dir(&app)?);
+    let action = settings
+        .actions
+        .iter()
+        .find(|action| action.id == id)
+        .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("No action {:?}", id)))?;
+    let (is_folder, label) = action_target(&state, &path)?;
+    if !action.applies_to(Path::new(&path), is_folder, label) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Action {:?} does not apply to this entry", action.name),
+        )
+        .with_path(path));
+    }
+    actions::run(action, Path::new(&path))
+}
+
+/// Whether `path` is a folder, and its color label, for matching actions
+fn action_target(
+    state: &AppState,
+    path: &str,
+) -> Result<(bool, Option<marks::ColorLabel>), AppError> {
+    let metadata = std::fs::metadata(path).map_err(|e| AppError::io(path, &e))?;
+    let label = state
+        .marks
+        .get()
+        .and_then(|store| store.get(path).ok())
+        .and_then(|file_marks| file_marks.label);
+    Ok((metadata.is_dir(), label))
+}
+
+/// Project roots found while indexing: folders with a Cargo.toml, a
+/// package.json or a Git work tree. Their names work as `project:<name>`
+/// search filters.
+#[tauri::command]
+async fn list_projects(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<project::Project>, AppError> {
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let hidden = privacy::hidden_paths(privacy::Audience::User);
+    let mut projects = tauri::async_runtime::spawn_blocking(move || search_index.projects(&hidden))
+        .await
+        .map_err(|e| format!("Listing projects failed: {}", e))?
+        .map_err(|e| {
+            log::error!("Failed to list projects: {}", e);
+            AppError::new(ErrorKind::Internal, format!("Search failed: {}", e))
+        })?;
+    privacy::redact_by(&mut projects, privacy::Audience::User, |project| {
+        &project.path
+    });
+    Ok(projects)
+}
+
+/// Other indexed paths of the file at `path`, i.e. its hard links. Symlinks
+/// resolving to the file share its ID but are left out.
+#[tauri::command]
+async fn find_hard_links(
+    path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<search::SearchResult>, AppError> {
+    let metadata = std::fs::metadata(&path).map_err(|e| AppError::io(path.as_str(), &e))?;
+    let Some(file_id) = index::file_id(Path::new(&path), &metadata) else {
+        return Ok(Vec::new());
+    };
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let results = search_index
+        .with_file_id(&file_id)
+        .and_then(|docs| search_index.to_results(&docs))
+        .map_err(|e| {
+            log::error!("Hard link lookup for {} failed: {}", path, e);
+            AppError::new(
+                ErrorKind::Internal,
+                format!("Hard link lookup failed: {}", e),
+            )
+        })?;
+    let mut results = results;
+    privacy::redact(&mut results, privacy::Audience::User);
+    Ok(results
+        .into_iter()
+        .filter(|result| result.path != path)
+        .filter(|result| {
+            std::fs::symlink_metadata(&result.path).is_ok_and(|m| !m.file_type().is_symlink())
+        })
+        .collect())
+}
+
+/// Entries whose names resemble that of the entry at `path`, best match
+/// first, e.g. copies and other versions of a document kept elsewhere
+#[tauri::command]
+async fn find_similar(
+    path: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<search::SearchResult>, AppError> {
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let limit = limit.unwrap_or(DEFAULT_SIMILAR_RESULTS);
+    let hidden = privacy::hidden_paths(privacy::Audience::User);
+    tauri::async_runtime::spawn_blocking(move || {
+        search_index
+            .find_similar(&path, limit, &hidden)
+            .and_then(|docs| search_index.to_results(&docs))
+            .map(|mut results| {
+                privacy::redact(&mut results, privacy::Audience::User);
+                results
+            })
+            .map_err(|e| {
+                log::error!("Similar file lookup for {} failed: {}", path, e);
+                AppError::new(ErrorKind::Internal, format!("Search failed: {}", e))
+            })
+    })
+    .await
+    .map_err(|e| format!("Similar file lookup failed: {}", e))?
+}
+
+/// Entries whose name, note or email subject is closest in meaning to
+/// `query`, best first, e.g. tax returns for "tax documents" whatever their
+/// names' language. Compares the vectors of the embedding command, so it is
+/// unavailable while none is set; entries not embedded yet are missed.
+#[tauri::command]
+async fn semantic_search(
+    query: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<search::SearchResult>, AppError> {
+    let command = embed::command().ok_or_else(|| {
+        AppError::new(
+            ErrorKind::InvalidInput,
+            "Semantic search needs an embedding command in the settings",
+        )
+    })?;
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(DEFAULT_SEMANTIC_RESULTS);
+    nearest_in_meaning(&state, &command, query, limit).await
+}
+
+/// The `limit` entries whose vector is most similar to that of `query`,
+/// both embedded with `command`
+async fn nearest_in_meaning(
+    state: &AppState,
+    command: &str,
+    query: String,
+    limit: usize,
+) -> Result<Vec<search::SearchResult>, AppError> {
+    let vector = {
+        let command = command.to_string();
+        tauri::async_runtime::spawn_blocking(move || embed::embed(&command, &[query]))
+            .await
+            .map_err(|e| format!("Embedding the query failed: {}", e))??
+            .pop()
+            .unwrap_or_default()
+    };
+
+    let index_manager = state.index_manager.read().await;
+    let index_manager = index_manager
+        .as_ref()
+        .ok_or_else(AppError::index_not_ready)?;
+    // Twice the limit leaves room for the stale and private ones dropped
+    let neighbors = index_manager
+        .nearest_embeddings(&vector, limit.saturating_mul(2))
+        .map_err(|e| format!("Failed to read index DB: {}", e))?;
+    let mut results = Vec::new();
+    for neighbor in neighbors {
+        let Some(entity) = index_manager
+            .get_file_entity(&neighbor.id)
+            .map_err(|e| format!("Failed to read index DB: {}", e))?
+        else {
+            continue;
+        };
+        // Changed since it was embedded; the worker has it queued
+        if embed::text(&entity).map(|text| embed::stamp(command, &text)) != Some(neighbor.stamp) {
+            continue;
+        }
+        results.push(search::SearchResult::from(entity));
+    }
+    privacy::redact(&mut results, privacy::Audience::User);
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Suggestions while `prefix` is typed into the search box: indexed name
+/// words completing its last word, earlier queries it starts, and folders
+/// whose name or path it starts
+#[tauri::command]
+async fn autocomplete(
+    prefix: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<search::Completions, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_COMPLETIONS);
+    let queries = state
+        .search_sessions
+        .lock()
+        .await
+        .recent_queries(&prefix, limit);
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+    let hidden = privacy::hidden_paths(privacy::Audience::User);
+    let (terms, mut folders) = tauri::async_runtime::spawn_blocking(move || {
+        search_index.complete(&prefix, limit, &hidden)
+    })
+    .await
+    .map_err(|e| format!("Completion failed: {}", e))?
+    .map_err(|e| {
+        log::error!("Failed to complete query: {}", e);
+        AppError::new(ErrorKind::Internal, format!("Completion failed: {}", e))
+    })?;
+    privacy::redact_by(&mut folders, privacy::Audience::User, |folder| folder);
+    Ok(search::Completions {
+        terms,
+        queries,
+        folders,
+    })
+}
+
+/// Rows `offset..offset + limit` of search session `session_id` as text for
+/// `columns`, in the order the search ranked them, with the total hit count.
+/// The session's last query is run again within the ones it refined, on the
+/// active profile's index.
+#[tauri::command]
+async fn get_results_table(
+    session_id: u64,
+    columns: Vec<table::Column>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<table::ResultsTable, AppError> {
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(table::MAX_ROWS).min(table::MAX_ROWS);
+    let session = state.search_sessions.lock().await.get(session_id).cloned();
+    let mut session = session.ok_or_else(|| {
+        AppError::new(
+            ErrorKind::NotFound,
+            format!("Search session {} has expired", session_id),
+        )
+    })?;
+    let active = with_profiles(&app, &state, |profiles| profiles.active().name.clone()).await?;
+    if session.profile != active {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Search session {} belongs to another profile", session_id),
+        ));
+    }
+    let Some(last) = session.steps.pop() else {
+        return Ok(table::ResultsTable {
+            columns,
+            rows: Vec::new(),
+            total_found: 0,
+        });
+    };
+    let filter = privacy::restrict(
+        &session.within(last.filter.clone()),
+        privacy::Audience::User,
+    );
+    let search_index = state
+        .search_snapshot()
+        .await
+        .ok_or_else(AppError::index_not_ready)?;
+
+    let cell_columns = columns.clone();
+    let (rows, total_found) = tauri::async_runtime::spawn_blocking(move || {
+        let cancel = search::CancelToken::with_timeout(search::DEFAULT_SEARCH_TIMEOUT);
+        let (docs, total_found) = search_index
+            .search_page(
+                &last.query,
+                last.use_regex,
+                &filter,
+                (offset, limit),
+                &cancel,
+            )
+            .map_err(|e| search_error(e, &last.query, &cancel))?;
+        let mut results = search_index
+            .to_results(&docs)
+            .map_err(|e| AppError::new(ErrorKind::Internal, format!("Search failed: {}", e)))?;
+        privacy::redact(&mut results, privacy::Audience::User);
+        let mut formatter = table::Formatter::default();
+        let rows = results
+            .iter()
+            .map(|result| formatter.row(result, &cell_columns))
+            .collect::<Vec<_>>();
+        Ok::<_, AppError>((rows, total_found))
+    })
+    .await
+    .map_err(|e| format!("Table task failed: {}", e))??;
+    Ok(table::ResultsTable {
+        columns,
+        rows,
+        total_found,
+    })
+}
+
+/// Search context last saved by this window, or by the window that saved
+/// last when this one has none, so a reopened or new window picks it up
+#[tauri::command]
+async fn get_session(
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<window_session::WindowSession, AppError> {
+    let mut sessions = state.window_sessions.lock().await;
+    if sessions.is_none() {
+        *sessions = Some(window_session::SessionStore::load(&app_data_dir(&app)?));
+    }
+    Ok(sessions.as_ref().unwrap().get(window.label()))
+}
+
+/// Remember the search context of this window; returns it as stored
+#[tauri::command]
+async fn set_session(
+    session: window_session::WindowSession,
+    window: tauri::WebviewWindow,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<window_session::WindowSession, AppError> {
+    session
+        .validate()
+        .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    let mut sessions = state.window_sessions.lock().await;
+    if sessions.is_none() {
+        *sessions = Some(window_session::SessionStore::load(&app_data_dir(&app)?));
+    }
+    Ok(sessions
+        .as_mut()
+        .unwrap()
+        .set(window.label(), session, chrono::Utc::now().timestamp())?)
+}
+
+/// Regenerate the active profile's search index from its DB without walking
+/// the filesystem, e.g. after a schema change or search index corruption
+#[tauri::command]
+async fn rebuild_search_index(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    if state.is_indexing.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(
+            ErrorKind::IndexingInProgress,
+            "Indexing is already in progress",
+        ));
+    }
+
+    let result = match active_index_dir(&app, &state).await {
+        Ok(dir) => {
+            let start = std::time::Instant::now();
+            rebuild_search_from_db(&state, &dir)
+                .await
+                .map(|documents| (documents, start.elapsed()))
+        }
+        Err(e) => Err(e.into()),
+    };
+    state.is_indexing.store(false, Ordering::SeqCst);
+
+    let (documents, elapsed) = result?;
+    log::info!(
+        "Search index rebuilt from DB: {} documents in {:.2}s",
+        documents,
+        elapsed.as_secs_f64()
+    );
+    Ok(serde_json::json!({
+        "status": "completed",
+        "files_indexed": documents,
+        "elapsed_ms": elapsed.as_millis() as u64
+    }))
+}
+
+/// Require `path` to be absolute, naming it `what` in the error
+fn absolute_path(path: String, what: &str) -> Result<std::path::PathBuf, AppError> {
+    let absolute = std::path::PathBuf::from(&path);
+    if !absolute.is_absolute() {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("{} must be an absolute path", what),
+        )
+        .with_path(path));
+    }
+    Ok(absolute)
+}
+
+/// Archive the live index. Both write locks are held so the watcher cannot
+/// change either store while it is being copied.
+async fn backup_to_archive(
+    state: &AppState,
+    (db_path, search_index_path): (&Path, &Path),
+    destination: &Path,
+) -> Result<(u64, backup::BackupManifest), AppError> {
+    // Changes wait until the archive is written; searches only need the read
+    // guards, which also keep a swap from replacing the files being archived
+    let _writes = state.index_writes.write().await;
+    let index_manager_guard = state.index_manager.read().await;
+    let search_index_guard = state.search_index.read().await;
+    let (Some(index_manager), Some(_)) =
+        (index_manager_guard.as_ref(), search_index_guard.as_ref())
+    else {
+        return Err(AppError::index_not_ready());
+    };
+    index_manager
+        .flush()
+        .map_err(|e| format!("Failed to flush index DB: {}", e))?;
+
+    let manifest = backup::BackupManifest::new(*state.total_files.read().await);
+    let paths = (
+        db_path.to_path_buf(),
+        search_index_path.to_path_buf(),
+        destination.to_path_buf(),
+    );
+    let archived = manifest.clone();
+    let bytes = tauri::async_runtime::spawn_blocking(move || {
+        let (db_path, search_index_path, destination) = paths;
+        backup::create_backup((&db_path, &search_index_path), &archived, &destination)
+    })
+    .await
+    .map_err(|e| format!("Backup task failed: {}", e))?
+    .map_err(|e| AppError::io(destination.to_string_lossy(), &e))?;
+    Ok((bytes, manifest))
+}
+
+/// Replace the live index of `dir` with the contents of an archive, migrating
+/// it first if it was made with an older schema. The live index is only
+/// touched once the archive has been unpacked and opened successfully.
+async fn restore_from_archive(
+    state: &AppState,
+    source: &Path,
+    dir: &Path,
+) -> Result<usize, AppError> {
+    let (staging_db_path, staging_search_path) =
+        rebuild::new_generation(dir).map_err(|e| AppError::io(dir.to_string_lossy(), &e))?;
+    let manifest = backup::extract_backup(source, (&staging_db_path, &staging_search_path))
+        .map_err(|e| AppError::io(source.to_string_lossy(), &e))?;
+    log::info!(
+        "Unpacked backup of {} files created at {}",
+        manifest.files,
+        manifest.created
+    );
+
+    {
+        let index_manager = index::IndexManager::new(&staging_db_path)
+            .map_err(|e| format!("Failed to open backed up index DB: {}", e))?;
+        if search::SearchIndex::schema_version(&staging_search_path) != Some(search::SCHEMA_VERSION)
+        {
+            stage_search_index(&index_manager, &staging_search_path)?;
+        }
+    }
+
+    swap_in_staged_index(state, dir, (&staging_db_path, &staging_search_path)).await?;
+    *state.query_cache.lock().await = search::QueryCache::default();
+
+    let total_files = match state.index_manager.read().await.as_ref() {
+        Some(index_manager) => index_manager
+            .count_files()
+            .map_err(|e| format!("Failed to count files in DB: {}", e))?,
+        None => 0,
+    };
+    *state.total_files.write().await = total_files;
+    *state.last_updated.write().await = Some(manifest.created);
+    Ok(total_files)
+}
+
+/// Snapshot the active profile's DB and search index into one compressed
+/// archive at `destination`
+#[tauri::command]
+async fn backup_index(
+    destination: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    let destination = absolute_path(destination, "Backup destination")?;
+    let (db_path, search_index_path) = rebuild::live_paths(&active_index_dir(&app, &state).await?);
+    let (bytes, manifest) =
+        backup_to_archive(&state, (&db_path, &search_index_path), &destination).await?;
+
+    log::info!(
+        "Backed up {} files to {:?} ({} bytes)",
+        manifest.files,
+        destination,
+        bytes
+    );
+    Ok(serde_json::json!({
+        "path": destination.to_string_lossy(),
+        "bytes": bytes,
+        "files": manifest.files
+    }))
+}
+
+/// Replace the active profile's index with one saved by `backup_index`
+#[tauri::command]
+async fn restore_index(
+    source: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    let source = absolute_path(source, "Backup archive")?;
+    if state.is_indexing.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(
+            ErrorKind::IndexingInProgress,
+            "Cannot restore a backup while indexing is in progress",
+        ));
+    }
+
+    let result = match active_index_dir(&app, &state).await {
+        Ok(dir) => restore_from_archive(&state, &source, &dir).await,
+        Err(e) => Err(e.into()),
+    };
+    state.is_indexing.store(false, Ordering::SeqCst);
+
+    let total_files = result?;
+    log::info!("Restored {} files from {:?}", total_files, source);
+    Ok(serde_json::json!({
+        "status": "completed",
+        "files": total_files
+    }))
+}
+
+/// Where the index, settings and logs live: the app data directory, or the
+/// portable data folder chosen at launch
+fn app_data_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    if let Some(location) = portable::current() {
+        return Ok(location.path.clone());
+    }
+    app.path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// Data folder of this run and what chose it; a new one applies after a
+/// restart
+#[tauri::command]
+fn get_data_location(app: tauri::AppHandle) -> Result<portable::DataLocation, AppError> {
+    Ok(portable::current()
+        .cloned()
+        .unwrap_or(portable::DataLocation {
+            path: app_data_dir(&app)?,
+            source: portable::Source::Default,
+        }))
+}
+
+#[tauri::command]
+fn get_settings(app: tauri::AppHandle) -> Result<settings::Settings, AppError> {
+    Ok(settings::Settings::load(&app_data_dir(&app)?))
+}
+
+/// Validate, persist and apply `settings`. Writer options take effect for the
+/// next index writer, so a build already running keeps its budget.
+#[tauri::command]
+async fn set_settings(
+    settings: settings::Settings,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<settings::Settings, AppError> {
+    settings
+        .validate()
+        .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    settings.save(&app_data_dir(&app)?)?;
+    search::set_writer_options(settings.writer)?;
+    log::info!(
+        "Index writer set to {} MB heap, {} thread(s)",
+        settings.writer.heap_mb,
+        settings.writer.threads
+    );
+    // Turning it off takes effect on the next launch
+    if settings.elevated_indexing && !elevation::status().backup_privilege {
+        apply_elevated_indexing();
+    }
+    apply_locale(&app, settings.locale);
+    privacy::set_paths(&settings.private_paths);
+    ocr::set_command(settings.ocr_command.as_deref());
+    if settings.ocr_command.is_some() {
+        state
+            .ocr_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .request_backlog();
+    }
+    embed::set_command(settings.embedding_command.as_deref());
+    if settings.embedding_command.is_some() {
+        state
+            .embed_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .request_backlog();
+    }
+    *state.query_cache.lock().await = search::QueryCache::default();
+    Ok(settings)
+}
+
+/// Return entries under the private paths until the app quits or
+/// lock_private_results is called
+#[tauri::command]
+async fn unlock_private_results(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    privacy::set_unlocked(true);
+    // Cached searches were redacted
+    *state.query_cache.lock().await = search::QueryCache::default();
+    log::info!("Private results unlocked for this session");
+    Ok(())
+}
+
+/// Hide entries under the private paths again
+#[tauri::command]
+async fn lock_private_results(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    privacy::set_unlocked(false);
+    *state.query_cache.lock().await = search::QueryCache::default();
+    log::info!("Private results locked");
+    Ok(())
+}
+
+/// Show tray labels and error messages in `locale`, matching the UI language
+#[tauri::command]
+async fn set_locale(
+    locale: i18n::Locale,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<i18n::Locale, AppError> {
+    let base_dir = app_data_dir(&app)?;
+    let mut settings = settings::Settings::load(&base_dir);
+    if settings.locale != locale {
+        settings.locale = locale;
+        settings.save(&base_dir)?;
+    }
+    apply_locale(&app, locale);
+    tray::refresh(&app, &state).await;
+    Ok(locale)
+}
+
+fn apply_locale(app: &tauri::AppHandle, locale: i18n::Locale) {
+    if i18n::current() != locale {
+        i18n::set_current(locale);
+        tray::relabel(app);
+        log::info!("Locale set to {:?}", locale);
+    }
+}
+
+/// Enable the backup privilege for `elevated_indexing`; without elevation the
+/// walk keeps skipping admin-only directories
+fn apply_elevated_indexing() {
+    match elevation::enable_backup_privilege() {
+        Ok(()) => log::info!("Backup privilege enabled; admin-only directories will be indexed"),
+        Err(e) => log::warn!(
+            "Elevated indexing is on but the backup privilege is unavailable ({}); relaunch elevated to use it",
+            e
+        ),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+struct AutostartStatus {
+    enabled: bool,
+    start_hidden: bool,
+}
+
+#[tauri::command]
+fn get_autostart(app: tauri::AppHandle) -> Result<AutostartStatus, AppError> {
+    use tauri_plugin_autostart::ManagerExt;
+
+    let enabled = app
+        .autolaunch()
+        .is_enabled()
+        .map_err(|e| format!("Failed to query autostart: {}", e))?;
+    let settings = settings::Settings::load(&app_data_dir(&app)?);
+    Ok(AutostartStatus {
+        enabled,
+        start_hidden: settings.start_hidden,
+    })
+}
+
+/// Launch the app at login, optionally staying in the tray until opened
+#[tauri::command]
+fn set_autostart(
+    enabled: bool,
+    start_hidden: bool,
+    app: tauri::AppHandle,
+) -> Result<AutostartStatus, AppError> {
+    use tauri_plugin_autostart::ManagerExt;
+
+    let base_dir = app_data_dir(&app)?;
+    let mut settings = settings::Settings::load(&base_dir);
+    settings.start_hidden = start_hidden;
+    settings.save(&base_dir)?;
+
+    let autolaunch = app.autolaunch();
+    if autolaunch
+        .is_enabled()
+        .map_err(|e| format!("Failed to query autostart: {}", e))?
+        != enabled
+    {
+        let changed = if enabled {
+            autolaunch.enable()
+        } else {
+            autolaunch.disable()
+        };
+        changed.map_err(|e| {
+            log::error!("Failed to update autostart: {}", e);
+            format!("Failed to update autostart: {}", e)
+        })?;
+    }
+    log::info!(
+        "Autostart {}{}",
+        if enabled { "enabled" } else { "disabled" },
+        if enabled && start_hidden {
+            ", hidden"
+        } else {
+            ""
+        }
+    );
+    Ok(AutostartStatus {
+        enabled,
+        start_hidden,
+    })
+}
+
+/// Whether the app runs elevated and reads through the backup privilege
+#[tauri::command]
+fn get_elevation_status() -> elevation::ElevationStatus {
+    elevation::status()
+}
+
+/// Restart the app elevated through a UAC prompt (Windows only). The index
+/// is closed first so the new instance can open it, and reopened if the
+/// prompt is cancelled.
+#[tauri::command]
+async fn relaunch_elevated(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    if !elevation::status().supported {
+        return elevation::relaunch_elevated();
+    }
+    if state.is_indexing.load(Ordering::SeqCst) {
+        return Err(AppError::new(
+            ErrorKind::IndexingInProgress,
+            "Cannot relaunch while indexing is in progress",
+        ));
+    }
+    // Searches still running keep their snapshot; none of them writes to it
+    *state.search_index.write().await = None;
+    *state.index_manager.write().await = None;
+    if let Err(e) = elevation::relaunch_elevated() {
+        if let Err(load_err) = load_existing_index(&app, &state).await {
+            log::error!("Failed to reopen index: {}", load_err);
+        }
+        return Err(e);
+    }
+    log::info!("Relaunched elevated; exiting");
+    app.exit(0);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_index_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    let is_indexing = state.is_indexing.load(Ordering::SeqCst);
+    let total_files = *state.total_files.read().await;
+    let last_updated = *state.last_updated.read().await;
+    let is_ready = state.search_index.read().await.is_some();
+    // None while no watcher is running
+    let watcher_healthy = state
+        .file_watcher
+        .lock()
+        .await
+        .as_ref()
+        .map(|w| w.is_healthy());
+
+    log::debug!(
+        "Index status requested: ready={}, files={}, indexing={}",
+        is_ready,
+        total_files,
+        is_indexing
+    );
+
+    Ok(serde_json::json!({
+        "is_ready": is_ready,
+        "total_files": total_files,
+        "last_updated": last_updated.map(format_timestamp_iso8601),
+        "indexing_in_progress": is_indexing,
+        "watcher_healthy": watcher_healthy
+    }))
+}
+
+/// Grep file contents of a result set, streaming matches as `grep-match` events.
+/// Either `paths` or `query` selects the files to scan.
+#[tauri::command]
+async fn grep_in_results(
+    pattern: String,
+    paths: Option<Vec<String>>,
+    query: Option<String>,
+    use_regex: Option<bool>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<grep::GrepSummary, AppError> {
+    let content_regex = regex::bytes::Regex::new(&pattern).map_err(|e| {
+        log::warn!("Invalid grep pattern '{}': {}", pattern, e);
+        AppError::invalid_regex(e)
+    })?;
+
+    let targets: Vec<std::path::PathBuf> = match (paths, query) {
+        (Some(paths), _) => paths.into_iter().map(std::path::PathBuf::from).collect(),
+        (None, Some(query)) => {
+            let use_regex = use_regex.unwrap_or(false);
+            let search_index = state
+                .search_snapshot()
+                .await
+                .ok_or_else(AppError::index_not_ready)?;
+            let filter = privacy::restrict(&Default::default(), privacy::Audience::User);
+            let mut results = search_index
+                .search_filtered(
+                    &query,
+                    use_regex,
+                    search::MAX_RANKED_RESULTS,
+                    &filter,
+                    &search::CancelToken::with_timeout(search::DEFAULT_SEARCH_TIMEOUT),
+                )
+                .and_then(|docs| search_index.to_results(&docs))
+                .map_err(|e| format!("Search failed: {}", e))?;
+            privacy::redact(&mut results, privacy::Audience::User);
+            results
+                .into_iter()
+                .filter(|result| !result.is_folder)
+                .map(|result| result.path.into())
+                .collect()
+        }
+        (None, None) => {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                "Either paths or query is required",
+            ))
+        }
+    };
+
+    log::info!(
+        "Grep request: pattern='{}' across {} file(s)",
+        pattern,
+        targets.len()
+    );
+    let start_time = std::time::Instant::now();
+
+    let emitter = app.clone();
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        grep::grep_files(&targets, &content_regex, |m| {
+            let _ = emitter.emit("grep-match", &m);
+        })
+    })
+    .await
+    .map_err(|e| format!("Grep task failed: {}", e))?;
+
+    log::info!(
+        "Grep completed: {} match(es) in {} file(s) in {}ms",
+        summary.total_matches,
+        summary.files_matched,
+        start_time.elapsed().as_millis()
+    );
+    let _ = app.emit("grep-complete", &summary);
+
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn list_profiles(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    Ok(with_profiles(&app, &state, |profiles| {
+        serde_json::json!({
+            "active": profiles.active().name,
+            "profiles": profiles.list()
+        })
+    })
+    .await?)
+}
+
+#[tauri::command]
+async fn create_profile(
+    name: String,
+    roots: Vec<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<profile::Profile, AppError> {
+    let (roots, notes) = index::merge_roots(&roots);
+    for note in &notes {
+        log::warn!("{}", note);
+    }
+    let created = with_profiles(&app, &state, |profiles| {
+        let created = profiles
+            .create(&name, roots)
+            .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+        profiles.save()?;
+        Ok::<_, AppError>(created)
+    })
+    .await??;
+    log::info!("Created profile '{}'", created.name);
+    Ok(created)
+}
+
+/// Set traversal options for one root of the active profile; applied on the next build
+#[tauri::command]
+async fn set_root_options(
+    root: String,
+    options: index::WalkOptions,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    with_profiles(&app, &state, |profiles| {
+        let active = profiles.active().name.clone();
+        profiles.set_root_options(&active, &root, options)?;
+        profiles.save()
+    })
+    .await??;
+    log::info!("Updated options for root '{}': {:?}", root, options);
+    Ok(())
+}
+
+/// A root's watch configuration and whether the watcher currently covers it
+#[derive(Debug, Clone, Serialize)]
+struct WatchedRoot {
+    root: String,
+    recursive: bool,
+    active: bool,
+}
+
+/// Replace the running watcher with one covering the active profile's watched
+/// roots. Roots that can no longer be watched are logged and skipped.
+async fn restore_watches(app: &tauri::AppHandle, state: &AppState) -> Result<(), String> {
+    let (watched, event_id) = with_profiles(app, state, |profiles| {
+        let active = profiles.active();
+        (active.watched_roots.clone(), active.watch_event_id)
+    })
+    .await?;
+
+    let mut file_watcher = state.file_watcher.lock().await;
+    *file_watcher = None;
+    if watched.is_empty() {
+        return Ok(());
+    }
+
+    let mut watcher =
+        watcher::FileWatcher::new().map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    // Changes made while the app was closed are replayed where supported
+    watcher.resume_from(event_id);
+    for (root, config) in &watched {
+        match watcher.watch(Path::new(root), *config) {
+            Ok(()) => log::info!(
+                "Watching '{}' (recursive: {}, strategy: {:?})",
+                root,
+                config.recursive,
+                config.strategy
+            ),
+            Err(e) => log::warn!("Failed to restore watch on '{}': {}", root, e),
+        }
+    }
+    *file_watcher = Some(watcher);
+    Ok(())
+}
+
+/// Persist the watcher's journal position for the active profile so the next
+/// launch only replays what changed after it
+async fn save_watch_checkpoint(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    checkpoint: Option<u64>,
+) -> Result<(), String> {
+    let Some(event_id) = checkpoint else {
+        return Ok(());
+    };
+    with_profiles(app, state, |profiles| {
+        if profiles.active().watch_event_id == Some(event_id) {
+            return Ok(());
+        }
+        let active = profiles.active().name.clone();
+        profiles.set_watch_event_id(&active, event_id)?;
+        profiles.save()
+    })
+    .await?
+}
+
+/// Start watching an indexed root of the active profile and persist the choice
+#[tauri::command]
+async fn start_watching(
+    root: String,
+    recursive: Option<bool>,
+    strategy: Option<watcher::WatchStrategy>,
+    poll_interval_secs: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    let config = watcher::WatchConfig {
+        recursive: recursive.unwrap_or(true),
+        // WSL shares send no change events
+        strategy: strategy.unwrap_or(if wsl::is_wsl_path(Path::new(&root)) {
+            watcher::WatchStrategy::Poll
+        } else {
+            watcher::WatchStrategy::Native
+        }),
+        poll_interval_secs: poll_interval_secs
+            .unwrap_or(watcher::DEFAULT_SHARE_POLL_SECS)
+            .max(watcher::MIN_SHARE_POLL_SECS),
+    };
+    let is_indexed_root = with_profiles(&app, &state, |profiles| {
+        profiles.active().roots.contains(&root)
+    })
+    .await?;
+    if !is_indexed_root {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "Only indexed roots of the active profile can be watched",
+        )
+        .with_path(root));
+    }
+
+    {
+        let mut file_watcher = state.file_watcher.lock().await;
+        if file_watcher.is_none() {
+            *file_watcher = Some(
+                watcher::FileWatcher::new()
+                    .map_err(|e| format!("Failed to create file watcher: {}", e))?,
+            );
+        }
+        let watcher = file_watcher.as_mut().unwrap();
+        let path = Path::new(&root);
+        // Re-registering applies a changed recursive flag or strategy
+        if watcher.is_watching(path) {
+            let _ = watcher.unwatch_path(path);
+        }
+        watcher.watch(path, config).map_err(|e| {
+            log::error!("Failed to watch '{}': {}", root, e);
+            AppError::new(ErrorKind::Io, format!("Failed to watch: {}", e)).with_path(&root)
+        })?;
+    }
+
+    with_profiles(&app, &state, |profiles| {
+        let active = profiles.active().name.clone();
+        profiles.set_watch(&active, &root, Some(config))?;
+        profiles.save()
+    })
+    .await??;
+    log::info!(
+        "Started watching '{}' (recursive: {}, strategy: {:?})",
+        root,
+        config.recursive,
+        config.strategy
+    );
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_watching(
+    root: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    let was_configured = with_profiles(&app, &state, |profiles| {
+        let active = profiles.active().name.clone();
+        let was_configured = profiles.active().watched_roots.contains_key(&root);
+        profiles
+            .set_watch(&active, &root, None)
+            .and_then(|_| profiles.save())
+            .map(|_| was_configured)
+    })
+    .await??;
+
+    let mut file_watcher = state.file_watcher.lock().await;
+    let was_active = match file_watcher.as_mut() {
+        Some(watcher) => watcher.unwatch_path(Path::new(&root)).is_ok(),
+        None => false,
+    };
+    if !was_configured && !was_active {
+        return Err(
+            AppError::new(ErrorKind::NotFound, "Root is not being watched").with_path(root),
+        );
+    }
+    log::info!("Stopped watching '{}'", root);
+    Ok(())
+}
+
+/// Report watcher coverage and OS watch limits, including subtrees that fell
+/// back to polling
+/// Rolling search latency, index update lag, watcher queue depth and cache
+/// memory, kept locally for diagnosing performance
+#[tauri::command]
+async fn get_metrics(
+    state: tauri::State<'_, AppState>,
+) -> Result<metrics::MetricsReport, AppError> {
+    let watcher_queue_depth = state
+        .file_watcher
+        .lock()
+        .await
+        .as_ref()
+        .map_or(0, |watcher| watcher.queued_changes());
+    let cache = {
+        let query_cache = state.query_cache.lock().await;
+        (query_cache.len(), query_cache.approx_bytes())
+    };
+    Ok(state
+        .metrics
+        .lock()
+        .await
+        .report(watcher_queue_depth, cache))
+}
+
+/// Lines get_recent_logs returns when no count is given
+const DEFAULT_LOG_LINES: usize = 200;
+
+/// The last `lines` lines of the app log across rotated files (default 200,
+/// at most 10 000)
+#[tauri::command]
+async fn get_recent_logs(
+    lines: Option<usize>,
+    app: tauri::AppHandle,
+) -> Result<Vec<String>, AppError> {
+    let log_dir = app_data_dir(&app)?;
+    diagnostics::recent_log_lines(&log_dir, lines.unwrap_or(DEFAULT_LOG_LINES))
+        .map_err(|e| AppError::io(log_dir.to_string_lossy(), &e))
+}
+
+/// Zip the logs, index and watcher stats, system info and settings (secrets
+/// redacted) into `destination` for a bug report
+#[tauri::command]
+async fn create_diagnostics_bundle(
+    destination: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    let destination = absolute_path(destination, "Diagnostics destination")?;
+    let base_dir = app_data_dir(&app)?;
+
+    let system = serde_json::json!({
+        "app_version": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "os_family": std::env::consts::FAMILY,
+        "arch": std::env::consts::ARCH,
+        "elevation": elevation::status(),
+    });
+    let index = serde_json::json!({
+        "status": get_index_status(state.clone()).await?,
+        "watcher": get_watcher_diagnostics(state.clone()).await?,
+        "watcher_paused": state.watcher_paused.load(Ordering::SeqCst),
+        "private_unlocked": privacy::is_unlocked(),
+        "schema_version": search::SCHEMA_VERSION,
+        "writer": search::writer_options(),
+    });
+    let mut settings = serde_json::to_value(settings::Settings::load(&base_dir))
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    diagnostics::redact_secrets(&mut settings);
+
+    let reports = [
+        ("system.json", system),
+        ("index.json", index),
+        ("settings.json", settings),
+    ];
+    let bytes = diagnostics::create_bundle(&destination, &reports, &base_dir)
+        .map_err(|e| AppError::io(destination.to_string_lossy(), &e))?;
+    log::info!(
+        "Wrote diagnostics bundle {:?} ({} bytes)",
+        destination,
+        bytes
+    );
+    Ok(serde_json::json!({
+        "path": destination.to_string_lossy(),
+        "bytes": bytes,
+    }))
+}
+
+#[tauri::command]
+async fn get_watcher_diagnostics(
+    state: tauri::State<'_, AppState>,
+) -> Result<watcher::WatcherDiagnostics, AppError> {
+    Ok(match state.file_watcher.lock().await.as_ref() {
+        Some(watcher) => watcher.diagnostics(),
+        None => watcher::WatcherDiagnostics::idle(),
+    })
+}
+
+#[tauri::command]
+async fn list_watched(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<WatchedRoot>, AppError> {
+    let watched = with_profiles(&app, &state, |profiles| {
+        profiles.active().watched_roots.clone()
+    })
+    .await?;
+    let file_watcher = state.file_watcher.lock().await;
+    Ok(watched
+        .into_iter()
+        .map(|(root, config)| WatchedRoot {
+            active: file_watcher
+                .as_ref()
+                .is_some_and(|w| w.is_watching(Path::new(&root))),
+            root,
+            recursive: config.recursive,
+        })
+        .collect())
+}
+
+/// Make `name` the active profile, loading its index if one has been built
+#[tauri::command]
+async fn switch_profile(
+    name: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    // Held for the whole switch, so no build starts against either profile
+    if state.is_indexing.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(
+            ErrorKind::IndexingInProgress,
+            "Cannot switch profiles while indexing is in progress",
+        ));
+    }
+    let result = run_profile_switch(&name, &app, &state).await;
+    state.is_indexing.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn run_profile_switch(
+    name: &str,
+    app: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<serde_json::Value, AppError> {
+    // Record how far the outgoing profile's watcher got before it is replaced
+    if let Some(watcher) = state.file_watcher.lock().await.take() {
+        if let Err(e) = save_watch_checkpoint(app, state, watcher.checkpoint()).await {
+            log::warn!("Failed to save watcher checkpoint: {}", e);
+        }
+    }
+
+    with_profiles(app, state, |profiles| {
+        profiles.switch(name)?;
+        profiles.save()
+    })
+    .await??;
+    log::info!("Switched to profile '{}'", name);
+
+    *state.index_manager.write().await = None;
+    *state.search_index.write().await = None;
+    *state.total_files.write().await = 0;
+    *state.last_updated.write().await = None;
+
+    let is_ready = load_existing_index(app, state).await?;
+    if let Err(e) = restore_watches(app, state).await {
+        log::warn!("Failed to restore watches for profile '{}': {}", name, e);
+    }
+
+    Ok(serde_json::json!({
+        "active": name,
+        "is_ready": is_ready
+    }))
+}
+
+/// Start the MCP server on localhost so AI assistants can query the index
+#[tauri::command]
+async fn start_mcp_server(
+    port: Option<u16>,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    let mut mcp_server = state.mcp_server.lock().await;
+    if let Some(handle) = mcp_server.as_ref() {
+        log::info!("MCP server already running on {}", handle.addr());
+        return Ok(serde_json::json!({ "address": handle.addr().to_string() }));
+    }
+
+    let server = mcp::McpServer::new(state.search_index.clone(), state.index_manager.clone());
+    let handle = mcp::start(server, port.unwrap_or(0)).map_err(|e| {
+        log::error!("Failed to start MCP server: {}", e);
+        AppError::new(ErrorKind::Io, format!("Failed to start MCP server: {}", e))
+    })?;
+    let address = handle.addr().to_string();
+    *mcp_server = Some(handle);
+
+    Ok(serde_json::json!({ "address": address }))
+}
+
+#[tauri::command]
+async fn stop_mcp_server(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    let handle = state.mcp_server.lock().await.take();
+    if let Some(handle) = handle {
+        handle.stop();
+    }
+    Ok(())
+}
+
+/// Serve the active profile's index to other machines on `port`, or the
+/// port saved with the agent settings. The first start saves a new token;
+/// returns the address and the token clients need.
+#[tauri::command]
+async fn start_agent(
+    port: Option<u16>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, AppError> {
+    start_agent_server(&app, &state, port).await
+}
+
+async fn start_agent_server(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    port: Option<u16>,
+) -> Result<serde_json::Value, AppError> {
+    let mut agent = state.agent.lock().await;
+    let base_dir = app_data_dir(app)?;
+    let mut settings = settings::Settings::load(&base_dir);
+    let mut agent_settings = match settings.agent.clone() {
+        Some(agent_settings) => agent_settings,
+        None => remote::AgentSettings::generate(remote::DEFAULT_AGENT_PORT)?,
+    };
+    if let Some(handle) = agent.as_ref() {
+        log::info!("Agent already running on {}", handle.addr());
+        return Ok(serde_json::json!({
+            "address": handle.addr().to_string(),
+            "token": agent_settings.token
+        }));
+    }
+    if let Some(port) = port {
+        agent_settings.port = port;
+    }
+    if settings.agent.as_ref() != Some(&agent_settings) {
+        settings.agent = Some(agent_settings.clone());
+        settings.save(&base_dir)?;
+    }
+
+    let server = remote::Agent::new(state.search_index.clone(), &agent_settings.token);
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], agent_settings.port));
+    let handle = remote::start(server, addr).map_err(|e| {
+        log::error!("Failed to start agent: {}", e);
+        AppError::new(ErrorKind::Io, format!("Failed to start agent: {}", e))
+    })?;
+    let address = handle.addr().to_string();
+    *agent = Some(handle);
+
+    Ok(serde_json::json!({ "address": address, "token": agent_settings.token }))
+}
+
+#[tauri::command]
+async fn stop_agent(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    let handle = state.agent.lock().await.take();
+    if let Some(handle) = handle {
+        handle.stop();
+    }
+    Ok(())
+}
+
+/// Apply removals and upserts to the loaded index so results reflect file
+/// operations without a rebuild
+async fn update_live_index(
+    state: &AppState,
+    removed_ids: &[String],
+    upserted: &[FileEntity],
+) -> Result<(), AppError> {
+    let _writes = state.index_writes.read().await;
+    let index_manager_guard = state.index_manager.read().await;
+    let search_index_guard = state.search_index.read().await;
+    let (Some(index_manager), Some(search_index)) =
+        (index_manager_guard.as_ref(), search_index_guard.as_ref())
+    else {
+        return Ok(());
+    };
+
+    let mut upserted = upserted.to_vec();
+    if let Some(marks) = state.marks.get() {
+        marks.apply(&mut upserted);
+    }
+    // Upserts of entries already indexed (renames, re-scans) don't add files
+    let new_entries = upserted
+        .iter()
+        .filter(|e| {
+            removed_ids.contains(&e.id) || matches!(index_manager.get_file_entity(&e.id), Ok(None))
+        })
+        .count();
+    // The journal record is cleared only after the search commit, so a crash
+    // in between is replayed on the next load
+    let seq = index_manager
+        .apply_delta(removed_ids, &upserted)
+        .map_err(|e| format!("Failed to update index DB: {}", e))?;
+    search_index
+        .apply_changes(removed_ids, &upserted)
+        .map_err(|e| format!("Failed to update search index: {}", e))?;
+    if let Err(e) = index_manager.complete_delta(seq) {
+        log::warn!("Failed to clear index journal record {}: {}", seq, e);
+    }
+
+    let mut total_files = state.total_files.write().await;
+    *total_files = (*total_files + new_entries).saturating_sub(removed_ids.len());
+    drop(total_files);
+
+    if ocr::command().is_some() {
+        let mut queue = state.ocr_queue.lock().unwrap_or_else(|e| e.into_inner());
+        for entity in upserted.iter().filter(|entity| ocr::wants(entity)) {
+            queue.push(&entity.path);
+        }
+    }
+    // The worker skips those whose vector is current
+    if embed::command().is_some() {
+        let mut queue = state.embed_queue.lock().unwrap_or_else(|e| e.into_inner());
+        for entity in upserted {
+            queue.push(&entity.path);
+        }
+    }
+    Ok(())
+}
+
+/// Root of the active profile holding `path`, with the walk options set for
+/// it; defaults when no root holds it
+async fn walk_context(
+    state: &AppState,
+    path: &Path,
+) -> (Option<std::path::PathBuf>, index::WalkOptions) {
+    let profiles = state.profiles.read().await;
+    let Some(profile) = profiles.as_ref().map(|profiles| profiles.active()) else {
+        return (None, index::WalkOptions::default());
+    };
+    profile
+        .roots
+        .iter()
+        .find(|root| path.starts_with(root))
+        .map(|root| {
+            (
+                Some(std::path::PathBuf::from(root)),
+                profile.options_for(root),
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Everything at and below `path` as a walk from its profile root would find
+/// it: with the root's options, and hidden inside a hidden folder
+async fn walk_subtree(
+    state: &AppState,
+    index_manager: &index::IndexManager,
+    path: &Path,
+) -> Result<Vec<FileEntity>, AppError> {
+    let (root, options) = walk_context(state, path).await;
+    // A walk never counts where it starts as hidden, so judge that here
+    let hidden = root.as_deref().is_some_and(|root| {
+        path != root
+            && (index::inside_hidden(path, root)
+                || std::fs::symlink_metadata(path)
+                    .is_ok_and(|metadata| index::is_hidden(path, &metadata)))
+    });
+    let mut entities = Vec::new();
+    if !path.exists() || (hidden && options.skip_hidden) {
+        return Ok(entities);
+    }
+    index_manager.walk_directory(path, options, |mut entity| {
+        entity.hidden |= hidden;
+        entities.push(entity);
+        Ok::<(), AppError>(())
+    })?;
+    Ok(entities)
+}
+
+/// Walk `path` again after the watcher lost events for it, dropping entries
+/// that vanished and upserting everything found
+async fn rescan_subtree(state: &AppState, path: &Path) -> Result<(), AppError> {
+    let (stale_ids, entities) = {
+        let index_manager_guard = state.index_manager.read().await;
+        let Some(index_manager) = index_manager_guard.as_ref() else {
+            return Ok(());
+        };
+
+        let entities = walk_subtree(state, index_manager, path).await?;
+        let found: std::collections::HashSet<&str> =
+            entities.iter().map(|e| e.id.as_str()).collect();
+        let stale_ids: Vec<String> = explorer::indexed_ids_under(index_manager, path)
+            .into_iter()
+            .filter(|id| !found.contains(id.as_str()))
+            .filter(|id| matches!(index_manager.get_file_entity(id), Ok(Some(_))))
+            .collect();
+        (stale_ids, entities)
+    };
+
+    log::info!(
+        "Re-scanned {}: {} entries, {} stale",
+        path.display(),
+        entities.len(),
+        stale_ids.len()
+    );
+    update_live_index(state, &stale_ids, &entities).await
+}
+
+/// Move the index entries of a renamed file or folder to the new path
+async fn apply_rename(state: &AppState, from: &Path, to: &Path) -> Result<(), AppError> {
+    let moved = {
+        let _writes = state.index_writes.read().await;
+        let index_manager_guard = state.index_manager.read().await;
+        let search_index_guard = state.search_index.read().await;
+        let (Some(index_manager), Some(search_index)) =
+            (index_manager_guard.as_ref(), search_index_guard.as_ref())
+        else {
+            return Ok(());
+        };
+        let (removed_ids, moved, seq) = index_manager
+            .rename_entries(from, to)
+            .map_err(|e| format!("Failed to update index DB: {}", e))?;
+        search_index
+            .apply_changes(&removed_ids, &moved)
+            .map_err(|e| format!("Failed to update search index: {}", e))?;
+        if let Err(e) = index_manager.complete_delta(seq) {
+            log::warn!("Failed to clear index journal record {}: {}", seq, e);
+        }
+        if let Some(marks) = state.marks.get() {
+            let (from, to) = (from.to_string_lossy(), to.to_string_lossy());
+            if let Err(e) = marks.rename(&from, &to) {
+                log::warn!("Failed to move marks of {} to {}: {}", from, to, e);
+            }
+        }
+        moved.len()
+    };
+
+    // Not indexed under the old name (e.g. moved in from an unindexed folder)
+    if moved == 0 {
+        return rescan_subtree(state, to).await;
+    }
+    Ok(())
+}
+
+/// An entry deleted earlier in a batch of watcher changes. A later create of
+/// the same file, matched by file ID, is applied as a rename instead.
+struct PendingDelete {
+    path: String,
+    file_id: String,
+    ids: Vec<String>,
+}
+
+/// Apply coalesced watcher changes to the live index in order, batching plain
+/// creates/updates/deletes between renames and re-scans. Returns every path
+/// the changes touched.
+async fn apply_watch_changes(
+    state: &AppState,
+    changes: Vec<watcher::FileChangeEvent>,
+) -> Result<Vec<String>, AppError> {
+    use watcher::FileChangeEvent;
+
+    let mut affected = Vec::new();
+    let mut removed_ids: Vec<String> = Vec::new();
+    let mut upserted: Vec<FileEntity> = Vec::new();
+    let mut rescanned = false;
+    let mut deleted: Vec<PendingDelete> = Vec::new();
+
+    for change in changes {
+        match change {
+            FileChangeEvent::Created(path) | FileChangeEvent::Modified(path) => {
+                let (root, options) = walk_context(state, Path::new(&path)).await;
+                let entity = index::entity_below(Path::new(&path), root.as_deref(), options);
+                // A file deleted earlier in the batch reappearing here was moved
+                let moved_from = entity
+                    .as_ref()
+                    .ok()
+                    .and_then(|e| e.attributes.file_id.as_deref())
+                    .and_then(|file_id| deleted.iter().position(|d| d.file_id == file_id))
+                    .map(|i| deleted.remove(i));
+                if let Some(moved) = moved_from {
+                    let moved_ids: std::collections::HashSet<&String> = moved.ids.iter().collect();
+                    removed_ids.retain(|id| !moved_ids.contains(id));
+                    update_live_index(state, &removed_ids, &upserted).await?;
+                    removed_ids.clear();
+                    upserted.clear();
+                    apply_rename(state, Path::new(&moved.path), Path::new(&path)).await?;
+                    // Refresh the moved entry's metadata as well
+                    if let Some(entity) = entity.ok().filter(|e| !e.is_folder) {
+                        upserted.push(entity);
+                    }
+                    affected.push(moved.path);
+                    affected.push(path);
+                    continue;
+                }
+
+                match entity {
+                    // A created folder may have arrived with contents (moved in)
+                    Ok(entity) if entity.is_folder => {
+                        update_live_index(state, &removed_ids, &upserted).await?;
+                        removed_ids.clear();
+                        upserted.clear();
+                        rescan_subtree(state, Path::new(&path)).await?;
+                    }
+                    // Left out of the index like the walk leaves it out
+                    Ok(entity) if entity.hidden && options.skip_hidden => {
+                        removed_ids.push(entity.id)
+                    }
+                    Ok(entity) => upserted.push(entity),
+                    // Already gone again; drop whatever the index still has
+                    Err(_) => removed_ids.push(index::path_id(&path)),
+                }
+                affected.push(path);
+            }
+            FileChangeEvent::Deleted(path) => {
+                if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+                    let ids: Vec<String> =
+                        explorer::indexed_ids_under(index_manager, Path::new(&path))
+                            .into_iter()
+                            .filter(|id| matches!(index_manager.get_file_entity(id), Ok(Some(_))))
+                            .collect();
+                    let file_id = index_manager
+                        .get_file_entity(&index::path_id(&path))
+                        .ok()
+                        .flatten()
+                        .and_then(|entity| entity.attributes.file_id);
+                    removed_ids.extend(ids.iter().cloned());
+                    if let Some(file_id) = file_id {
+                        deleted.push(PendingDelete {
+                            path: path.clone(),
+                            file_id,
+                            ids,
+                        });
+                    }
+                }
+                affected.push(path);
+            }
+            FileChangeEvent::Renamed(from, to) => {
+                update_live_index(state, &removed_ids, &upserted).await?;
+                removed_ids.clear();
+                upserted.clear();
+                apply_rename(state, Path::new(&from), Path::new(&to)).await?;
+                affected.push(from);
+                affected.push(to);
+            }
+            FileChangeEvent::Rescan(path) => {
+                update_live_index(state, &removed_ids, &upserted).await?;
+                removed_ids.clear();
+                upserted.clear();
+                rescan_subtree(state, Path::new(&path)).await?;
+                rescanned = true;
+                affected.push(path);
+            }
+        }
+    }
+    update_live_index(state, &removed_ids, &upserted).await?;
+
+    if rescanned {
+        if let Some(watcher) = state.file_watcher.lock().await.as_ref() {
+            watcher.mark_healthy();
+        }
+    }
+    Ok(affected)
+}
+
+/// Poll the file watcher on a dedicated thread for the app's lifetime,
+/// applying debounced changes to the index and emitting `files-changed` with
+/// `{ paths }` so the frontend can refresh visible results. The watcher's
+/// journal position is saved every `CHECKPOINT_INTERVAL` for replay at launch.
+/// Each path waits out `debounce` after its last change before it is applied.
+fn spawn_watcher_loop(app: tauri::AppHandle, debounce: std::time::Duration) {
+    std::thread::spawn(move || {
+        let mut last_checkpoint = std::time::Instant::now();
+        loop {
+            std::thread::sleep(watcher::POLL_INTERVAL);
+            let state = app.state::<AppState>();
+            tauri::async_runtime::block_on(async {
+                tray::refresh(&app, &state).await;
+                // Events stay queued in the watcher, and the checkpoint stays
+                // behind them, until resumed
+                if state.watcher_paused.load(Ordering::SeqCst) {
+                    return;
+                }
+                // Likewise while a backup is reading the index files
+                if state.index_writes.try_read().is_err() {
+                    return;
+                }
+                let (changes, since) = match state.file_watcher.lock().await.as_mut() {
+                    // Watchers are replaced when roots change, so set it each time
+                    Some(watcher) => {
+                        watcher.set_debounce_window(debounce);
+                        (watcher.debounced_events(), watcher.released_since())
+                    }
+                    None => return,
+                };
+                if !changes.is_empty() {
+                    match apply_watch_changes(&state, changes).await {
+                        Ok(paths) => {
+                            if let Some(since) = since {
+                                state
+                                    .metrics
+                                    .lock()
+                                    .await
+                                    .update_lag
+                                    .record(since.elapsed());
+                            }
+                            *state.last_updated.write().await =
+                                Some(chrono::Utc::now().timestamp());
+                            log::debug!("Applied watcher changes for {} path(s)", paths.len());
+                            if let Err(e) =
+                                app.emit("files-changed", serde_json::json!({ "paths": paths }))
+                            {
+                                log::error!("Failed to emit files-changed event: {}", e);
+                            }
+                        }
+                        Err(e) => log::error!("Failed to apply watcher changes: {}", e),
+                    }
+                }
+
+                if last_checkpoint.elapsed() >= watcher::CHECKPOINT_INTERVAL {
+                    last_checkpoint = std::time::Instant::now();
+                    // Held across the save so a profile switch cannot interleave
+                    if let Some(watcher) = state.file_watcher.lock().await.as_ref() {
+                        if let Err(e) =
+                            save_watch_checkpoint(&app, &state, watcher.checkpoint()).await
+                        {
+                            log::warn!("Failed to save watcher checkpoint: {}", e);
+                        }
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// How often the share poller checks for roots due a poll
+const SHARE_POLL_TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Poll the roots watched with the Poll strategy as they come due. Their
+/// changes join the watcher's queue, so they are applied like native events.
+fn spawn_share_poller(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SHARE_POLL_TICK);
+        let state = app.state::<AppState>();
+        tauri::async_runtime::block_on(async {
+            let due = match state.file_watcher.lock().await.as_mut() {
+                Some(watcher) => watcher.due_polls(std::time::Instant::now()),
+                None => return,
+            };
+            for mut polled in due {
+                // Stats happen without the index lock, so a slow share does
+                // not hold up searches
+                if !polled.is_seeded() {
+                    if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+                        if let Err(e) = polled.seed(index_manager) {
+                            log::warn!(
+                                "Failed to seed polling of {}: {}",
+                                polled.root.display(),
+                                e
+                            );
+                        }
+                    }
+                }
+                let changed = polled.changed_folders();
+                let changes = if changed.is_empty() {
+                    Vec::new()
+                } else {
+                    match state.index_manager.read().await.as_ref() {
+                        Some(index_manager) => {
+                            polled.compare(index_manager, &changed).unwrap_or_else(|e| {
+                                log::warn!("Failed to poll {}: {}", polled.root.display(), e);
+                                Vec::new()
+                            })
+                        }
+                        None => Vec::new(),
+                    }
+                };
+                if !changes.is_empty() {
+                    log::debug!(
+                        "Polling {} found {} change(s)",
+                        polled.root.display(),
+                        changes.len()
+                    );
+                }
+                if let Some(watcher) = state.file_watcher.lock().await.as_mut() {
+                    watcher.finish_poll(polled, changes);
+                }
+            }
+        });
+    });
+}
+
+/// How long the OCR and embedding workers wait when they are off or nothing
+/// is queued
+const WORKER_IDLE_TICK: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Read queued images with the OCR command, one at a time, for the app's
+/// lifetime
+fn spawn_ocr_worker(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        let Some(command) = ocr::command() else {
+            std::thread::sleep(WORKER_IDLE_TICK);
+            continue;
+        };
+        let state = app.state::<AppState>();
+        let backlog = state
+            .ocr_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take_backlog();
+        if backlog {
+            tauri::async_runtime::block_on(queue_ocr_backlog(&state));
+        }
+        let next = state
+            .ocr_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop();
+        let Some(path) = next else {
+            std::thread::sleep(WORKER_IDLE_TICK);
+            continue;
+        };
+        let read = recognize_image(&state, &command, Path::new(&path));
+        if let Err(e) = tauri::async_runtime::block_on(read) {
+            log::warn!("Failed to read the text in {}: {}", path, e);
+        }
+    });
+}
+
+/// Queue every indexed image whose text has not been read
+async fn queue_ocr_backlog(state: &AppState) {
+    let mut images = Vec::new();
+    if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+        let found = index_manager.for_each_entity(|entity| {
+            if ocr::wants(&entity) {
+                images.push(entity.path);
+            }
+            Ok(())
+        });
+        if let Err(e) = found {
+            log::warn!("Failed to look for images to read: {}", e);
+        }
+    }
+    if !images.is_empty() {
+        log::info!("Queued {} image(s) for OCR", images.len());
+    }
+    let mut queue = state.ocr_queue.lock().unwrap_or_else(|e| e.into_inner());
+    for path in &images {
+        queue.push(path);
+    }
+}
+
+/// Read the text in the indexed image at `path` with `command` and store it
+/// with the entry. An image changed meanwhile is left for the queued change.
+async fn recognize_image(state: &AppState, command: &str, path: &Path) -> Result<(), AppError> {
+    let indexed = || async {
+        match state.index_manager.read().await.as_ref() {
+            Some(index_manager) => index_manager
+                .get_file_entity_by_path(&path.to_string_lossy())
+                .map_err(|e| format!("Failed to read index DB: {}", e)),
+            None => Ok(None),
+        }
+    };
+    let Some(before) = indexed().await?.filter(ocr::wants) else {
+        return Ok(());
+    };
+    let text = ocr::recognize(command, path)?;
+    let Some(mut entity) = indexed().await? else {
+        return Ok(());
+    };
+    if (entity.modified, entity.size) != (before.modified, before.size) {
+        return Ok(());
+    }
+    log::debug!("Read {} character(s) in {}", text.len(), path.display());
+    entity.attributes.ocr_text = Some(text);
+    update_live_index(state, &[], &[entity]).await
+}
+
+/// Embed queued entries with the embedding command, a batch at a time, for
+/// the app's lifetime
+fn spawn_embed_worker(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        let Some(command) = embed::command() else {
+            std::thread::sleep(WORKER_IDLE_TICK);
+            continue;
+        };
+        let state = app.state::<AppState>();
+        let backlog = state
+            .embed_queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take_backlog();
+        if backlog {
+            tauri::async_runtime::block_on(queue_embed_backlog(&state, &command));
+        }
+        let paths: Vec<String> = {
+            let mut queue = state.embed_queue.lock().unwrap_or_else(|e| e.into_inner());
+            std::iter::from_fn(|| queue.pop())
+                .take(embed::BATCH)
+                .collect()
+        };
+        if paths.is_empty() {
+            std::thread::sleep(WORKER_IDLE_TICK);
+            continue;
+        }
+        let embedded = embed_entries(&state, &command, &paths);
+        if let Err(e) = tauri::async_runtime::block_on(embedded) {
+            log::warn!("Failed to embed {} entry(s): {}", paths.len(), e);
+        }
+    });
+}
+
+/// Queue every indexed entry whose vector is missing or stale
+async fn queue_embed_backlog(state: &AppState, command: &str) {
+    let mut stale = Vec::new();
+    if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+        let found = index_manager.for_each_entity(|entity| {
+            if let Some(text) = embed::text(&entity) {
+                let stored = index_manager
+                    .embedding_stamp(&entity.id)
+                    .map_err(|e| format!("Failed to read index DB: {}", e))?;
+                if stored != Some(embed::stamp(command, &text)) {
+                    stale.push(entity.path);
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = found {
+            log::warn!("Failed to look for entries to embed: {}", e);
+        }
+    }
+    if !stale.is_empty() {
+        log::info!("Queued {} entry(s) for embedding", stale.len());
+    }
+    let mut queue = state.embed_queue.lock().unwrap_or_else(|e| e.into_inner());
+    for path in &stale {
+        queue.push(path);
+    }
+}
+
+/// Embed the indexed entries at `paths` whose vector is missing or stale
+/// with `command`, and store the vectors
+async fn embed_entries(state: &AppState, command: &str, paths: &[String]) -> Result<(), AppError> {
+    let mut pending = Vec::new();
+    if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+        for path in paths {
+            let entity = index_manager
+                .get_file_entity_by_path(path)
+                .map_err(|e| format!("Failed to read index DB: {}", e))?;
+            let Some((entity, text)) =
+                entity.and_then(|entity| embed::text(&entity).map(|text| (entity, text)))
+            else {
+                continue;
+            };
+            let stamp = embed::stamp(command, &text);
+            let stored = index_manager
+                .embedding_stamp(&entity.id)
+                .map_err(|e| format!("Failed to read index DB: {}", e))?;
+            if stored != Some(stamp) {
+                pending.push((entity.id, stamp, text));
+            }
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let texts: Vec<String> = pending.iter().map(|(_, _, text)| text.clone()).collect();
+    let vectors = embed::embed(command, &texts)?;
+    let _writes = state.index_writes.read().await;
+    if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+        for ((id, stamp, _), vector) in pending.iter().zip(&vectors) {
+            index_manager
+                .save_embedding(id, *stamp, vector)
+                .map_err(|e| format!("Failed to save embedding: {}", e))?;
+        }
+    }
+    log::debug!("Embedded {} entry(s)", pending.len());
+    Ok(())
+}
+
+/// Move files or folders to the OS trash and drop them from the index
+#[tauri::command]
+async fn move_to_trash(
+    paths: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    trash_and_unindex(&state, &paths).await
+}
+
+/// Trash `paths`, journaling it for undo, and unindex them
+async fn trash_and_unindex(state: &AppState, paths: &[std::path::PathBuf]) -> Result<(), AppError> {
+    let time = chrono::Utc::now().timestamp();
+    explorer::move_to_trash(paths)?;
+    log::info!("Moved {} item(s) to trash", paths.len());
+    record_operation(
+        state,
+        undo::Operation::Trash {
+            paths: paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            time,
+        },
+    );
+    unindex_paths(state, paths).await
+}
+
+/// Drop the index entries of `paths` and everything below them
+async fn unindex_paths(state: &AppState, paths: &[std::path::PathBuf]) -> Result<(), AppError> {
+    let removed_ids: Vec<String> = match state.index_manager.read().await.as_ref() {
+        Some(index_manager) => paths
+            .iter()
+            .flat_map(|path| explorer::indexed_ids_under(index_manager, path))
+            .collect(),
+        None => Vec::new(),
+    };
+    update_live_index(state, &removed_ids, &[]).await
+}
+
+/// Copy or move `sources` into the folder `destination`, emitting
+/// `transfer-progress` events while it runs and `transfer-complete` with the
+/// summary at the end. Names taken in the destination are handled by
+/// `policy` (ask by default); under Ask each is sent as a `transfer-conflict`
+/// event and the transfer waits for answer_transfer_conflict.
+/// cancel_transfer stops it between chunks, keeping what was done. The index
+/// follows the transferred entries.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn transfer_files(
+    transfer_id: String,
+    kind: transfer::TransferKind,
+    sources: Vec<String>,
+    destination: String,
+    policy: Option<transfer::OverwritePolicy>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<transfer::TransferSummary, AppError> {
+    let request = transfer::TransferRequest {
+        id: transfer_id.clone(),
+        kind,
+        sources: sources.into_iter().map(Into::into).collect(),
+        destination: destination.into(),
+        policy: policy.unwrap_or(transfer::OverwritePolicy::Ask),
+    };
+    let cancel = search::CancelToken::default();
+    let (answers, receiver) = std::sync::mpsc::channel();
+    if let Ok(mut running) = state.running_transfers.lock() {
+        if running.contains_key(&transfer_id) {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("Transfer {} is already running", transfer_id),
+            ));
+        }
+        let handle = RunningTransfer {
+            cancel: cancel.clone(),
+            answers,
+        };
+        running.insert(transfer_id.clone(), handle);
+    }
+    log::info!(
+        "{:?} of {} item(s) to {} started as {}",
+        kind,
+        request.sources.len(),
+        request.destination.display(),
+        transfer_id
+    );
+
+    let emitter = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conflicts = emitter.clone();
+        transfer::run(
+            request,
+            &cancel,
+            &mut |progress| {
+                let _ = emitter.emit("transfer-progress", progress);
+            },
+            &mut |conflict| {
+                let _ = conflicts.emit("transfer-conflict", conflict);
+                transfer::wait_for_answer(&receiver, conflict.conflict_id, &cancel)
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Transfer task failed: {}", e));
+    if let Ok(mut running) = state.running_transfers.lock() {
+        running.remove(&transfer_id);
+    }
+    let summary = result??;
+
+    if kind == transfer::TransferKind::Move {
+        let moves: Vec<undo::Move> = summary
+            .transferred
+            .iter()
+            .filter(|entry| entry.complete)
+            .map(|entry| undo::Move {
+                from: entry.from.clone(),
+                to: entry.to.clone(),
+            })
+            .collect();
+        if !moves.is_empty() {
+            record_operation(&state, undo::Operation::Rename { moves });
+        }
+    }
+    for entry in &summary.transferred {
+        let (from, to) = (Path::new(&entry.from), Path::new(&entry.to));
+        let updated = match kind {
+            transfer::TransferKind::Move if entry.complete => apply_rename(&state, from, to).await,
+            transfer::TransferKind::Move => match rescan_subtree(&state, from).await {
+                Ok(()) => rescan_subtree(&state, to).await,
+                Err(e) => Err(e),
+            },
+            transfer::TransferKind::Copy => rescan_subtree(&state, to).await,
+        };
+        if let Err(e) = updated {
+            log::warn!("Failed to index transferred {}: {}", entry.to, e);
+        }
+    }
+    let _ = app.emit("transfer-complete", &summary);
+    Ok(summary)
+}
+
+/// Answer a `transfer-conflict` of the transfer `transfer_id`. Returns false
+/// when that transfer is no longer running.
+#[tauri::command]
+async fn answer_transfer_conflict(
+    transfer_id: String,
+    answer: transfer::ConflictAnswer,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let running = state
+        .running_transfers
+        .lock()
+        .map_err(|_| AppError::new(ErrorKind::Internal, "Transfer registry is poisoned"))?;
+    Ok(running
+        .get(&transfer_id)
+        .is_some_and(|transfer| transfer.answers.send(answer).is_ok()))
+}
+
+/// Stop the transfer `transfer_id`; entries already transferred stay. Returns
+/// false when no such transfer is running.
+#[tauri::command]
+async fn cancel_transfer(
+    transfer_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let cancel = state
+        .running_transfers
+        .lock()
+        .ok()
+        .and_then(|running| running.get(&transfer_id).map(|t| t.cancel.clone()));
+    match cancel {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Rename `paths` by `pattern`, in the order given for numbering. With
+/// `dry_run` only the proposed names are returned; otherwise the entries are
+/// renamed, all or none, and their index entries and marks follow. Either
+/// way the proposals come back, with the conflicts that stop a rename.
+#[tauri::command]
+async fn bulk_rename(
+    paths: Vec<String>,
+    pattern: rename::RenamePattern,
+    dry_run: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<rename::RenameProposal>, AppError> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    if let Some(relative) = paths.iter().find(|path| !path.is_absolute()) {
+        return Err(
+            AppError::new(ErrorKind::InvalidInput, "Paths must be absolute")
+                .with_path(relative.to_string_lossy()),
+        );
+    }
+    let proposals = rename::plan(&paths, &pattern)?;
+    if dry_run {
+        return Ok(proposals);
+    }
+    let renamed = rename::apply(&proposals)?;
+    let moves = renamed
+        .iter()
+        .map(|(from, to)| undo::Move {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+        })
+        .collect();
+    record_operation(&state, undo::Operation::Rename { moves });
+    for (from, to) in renamed {
+        apply_rename(&state, &from, &to).await?;
+    }
+    Ok(proposals)
+}
+
+/// Overwrite files `passes` times (default 1) before deleting them, and
+/// folders with everything in them, for sensitive documents. Their index
+/// entries, labels and notes go too. The report's warning tells what this
+/// can't reach: SSDs and copy-on-write file systems may keep old copies.
+#[tauri::command]
+async fn secure_delete(
+    paths: Vec<String>,
+    passes: Option<u32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<shred::ShredReport, AppError> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    let shredded = paths.clone();
+    let report =
+        tauri::async_runtime::spawn_blocking(move || shred::shred(&shredded, passes.unwrap_or(1)))
+            .await
+            .map_err(|e| format!("Secure delete task failed: {}", e))?;
+
+    // Whatever went before a failure is gone, so unindex in either case
+    let removed_ids: Vec<String> = match state.index_manager.read().await.as_ref() {
+        Some(index_manager) => paths
+            .iter()
+            .filter(|path| path.symlink_metadata().is_err())
+            .flat_map(|path| explorer::indexed_ids_under(index_manager, path))
+            .collect(),
+        None => Vec::new(),
+    };
+    if let Some(marks) = state.marks.get() {
+        for path in paths.iter().filter(|path| path.symlink_metadata().is_err()) {
+            if let Err(e) = marks.remove(&path.to_string_lossy()) {
+                log::warn!("Failed to drop marks of {}: {}", path.display(), e);
+            }
+        }
+    }
+    update_live_index(&state, &removed_ids, &[]).await?;
+    report
+}
+
+/// Hash the file at `path` with `algorithm`, emitting `checksum-progress`
+/// events while large files are read. With `expected`, a hash or a line of
+/// `sha256sum`-style output, the result also tells whether they match.
+#[tauri::command]
+async fn compute_checksum(
+    path: String,
+    algorithm: checksum::HashAlgorithm,
+    expected: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<checksum::Checksum, AppError> {
+    let start_time = std::time::Instant::now();
+    let checksum = tauri::async_runtime::spawn_blocking(move || {
+        checksum::compute(
+            Path::new(&path),
+            algorithm,
+            expected.as_deref(),
+            |progress| {
+                let _ = app.emit("checksum-progress", progress);
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Checksum task failed: {}", e))??;
+    log::info!(
+        "{:?} of {} ({} bytes) in {}ms{}",
+        checksum.algorithm,
+        checksum.path,
+        checksum.size,
+        start_time.elapsed().as_millis(),
+        match checksum.matches {
+            Some(true) => ", matches",
+            Some(false) => ", does not match",
+            None => "",
+        }
+    );
+    Ok(checksum)
+}
+
+/// Index entries put back from the trash, with everything in them
+async fn index_restored(state: &AppState, restored: &[std::path::PathBuf]) -> Result<(), AppError> {
+    let mut entities = Vec::new();
+    if let Some(index_manager) = state.index_manager.read().await.as_ref() {
+        for path in restored {
+            entities.extend(walk_subtree(state, index_manager, path).await?);
+        }
+    }
+    update_live_index(state, &[], &entities).await
+}
+
+/// Journal an operation done through the app so it can be undone
+fn record_operation(state: &AppState, operation: undo::Operation) {
+    if let Some(journal) = state.journal.get() {
+        if let Err(e) = journal.record(&operation) {
+            log::warn!("Failed to journal {:?}: {}", operation, e);
+        }
+    }
+}
+
+/// Reverse the newest journaled rename, move or trashing: entries go back
+/// to their old paths or out of the trash, and the index follows. Returns
+/// the operation undone, or None when there is nothing to undo.
+#[tauri::command]
+async fn undo_last_operation(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<undo::Operation>, AppError> {
+    replay_operation(&state, undo::Stack::Undo).await
+}
+
+/// Carry out again the operation undone last. Returns it, or None when
+/// there is nothing to redo.
+#[tauri::command]
+async fn redo_operation(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<undo::Operation>, AppError> {
+    replay_operation(&state, undo::Stack::Redo).await
+}
+
+/// Undo or redo the newest operation of `stack` and move it onto the other
+/// one. An operation whose entries are gone is dropped from the journal.
+async fn replay_operation(
+    state: &AppState,
+    stack: undo::Stack,
+) -> Result<Option<undo::Operation>, AppError> {
+    let _replaying = state.replaying.lock().await;
+    let journal = state.journal.get().ok_or_else(|| {
+        AppError::new(ErrorKind::Internal, "The undo journal could not be opened")
+    })?;
+    let Some((key, operation)) = journal
+        .last(stack)
+        .map_err(|e| format!("Failed to read undo journal: {}", e))?
+    else {
+        return Ok(None);
+    };
+    let undoing = stack == undo::Stack::Undo;
+
+    let replayed = match &operation {
+        undo::Operation::Rename { moves } => {
+            let moves = moves.clone();
+            tauri::async_runtime::spawn_blocking(move || undo::replay_moves(&moves, undoing))
+                .await
+                .map_err(|e| format!("Undo task failed: {}", e))?
+                .map(|steps| (operation.clone(), steps, Vec::new()))
+        }
+        undo::Operation::Trash { paths, time } if undoing => undo::restore_trashed(paths, *time)
+            .map(|restored| (operation.clone(), Vec::new(), restored)),
+        undo::Operation::Trash { paths, .. } => {
+            let time = chrono::Utc::now().timestamp();
+            let trashed: Vec<std::path::PathBuf> = paths.iter().map(Into::into).collect();
+            explorer::move_to_trash(&trashed).map(|()| {
+                let paths = paths.clone();
+                (
+                    undo::Operation::Trash { paths, time },
+                    Vec::new(),
+                    Vec::new(),
+                )
+            })
+        }
+    };
+    let (reversed, steps, restored) = match replayed {
+        Ok(replayed) => replayed,
+        Err(e) => {
+            if e.kind == ErrorKind::NotFound {
+                log::warn!("Dropping {:?} from the undo journal: {}", operation, e);
+                if let Err(e) = journal.discard(stack, &key) {
+                    log::warn!("Failed to update undo journal: {}", e);
+                }
+            }
+            return Err(e);
+        }
+    };
+    if let Err(e) = journal.shift(stack, &key, &reversed) {
+        log::warn!("Failed to update undo journal: {}", e);
+    }
+    log::info!(
+        "{} {:?}",
+        if undoing { "Undid" } else { "Redid" },
+        operation
+    );
+
+    for (from, to) in &steps {
+        if let Err(e) = apply_rename(state, from, to).await {
+            log::warn!("Failed to index {} after moving it: {}", to.display(), e);
+        }
+    }
+    if !restored.is_empty() {
+        index_restored(state, &restored).await?;
+    }
+    if let undo::Operation::Trash { paths, .. } = &reversed {
+        if !undoing {
+            let trashed: Vec<std::path::PathBuf> = paths.iter().map(Into::into).collect();
+            unindex_paths(state, &trashed).await?;
+        }
+    }
+    Ok(Some(operation))
+}
+
+#[tauri::command]
+async fn list_trash() -> Result<Vec<explorer::TrashEntry>, AppError> {
+    explorer::list_trash()
+}
+
+/// Restore trashed items by ID (see `list_trash`) and index them again
+#[tauri::command]
+async fn restore_from_trash(
+    ids: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, AppError> {
+    let restored = explorer::restore_from_trash(&ids)?;
+    log::info!("Restored {} item(s) from trash", restored.len());
+    index_restored(&state, &restored).await?;
+
+    Ok(restored
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Index a newly created entry immediately and return it
+async fn index_created(state: &AppState, path: &Path) -> Result<FileEntity, AppError> {
+    let entity =
+        index::entity_for_path(path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    update_live_index(state, &[], std::slice::from_ref(&entity)).await?;
+    Ok(entity)
+}
+
+/// Create a file in `parent` from a template ("text", "markdown" or "empty")
+#[tauri::command]
+async fn create_file(
+    parent: String,
+    name: Option<String>,
+    template: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<FileEntity, AppError> {
+    let path = explorer::create_file(Path::new(&parent), name.as_deref(), template.as_deref())?;
+    index_created(&state, &path).await
+}
+
+#[tauri::command]
+async fn create_folder(
+    parent: String,
+    name: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<FileEntity, AppError> {
+    let path = explorer::create_folder(Path::new(&parent), name.as_deref())?;
+    index_created(&state, &path).await
+}
+
+/// Stat data for a path, as shown in the properties window
+#[tauri::command]
+async fn get_file_properties(path: String) -> Result<explorer::FileProperties, AppError> {
+    explorer::file_properties(Path::new(&path))
+}
+
+/// Alternate data streams (Windows) or extended attributes (macOS, Linux) of
+/// a path, for auditing data hidden beside a file's content
+#[tauri::command]
+async fn inspect_streams(path: String) -> Result<Vec<streams::StreamEntry>, AppError> {
+    streams::inspect_streams(Path::new(&path))
+}
+
+/// Open the native file properties dialog for a path
+#[tauri::command]
+async fn show_properties(path: String, app: tauri::AppHandle) -> Result<(), AppError> {
+    explorer::show_properties(&app, Path::new(&path))
+}
+
+/// Remove the downloaded-from-internet marker from a file so it opens without
+/// a security prompt. Returns false when it had none; fails with `UNSUPPORTED`
+/// where the platform has no markers.
+#[tauri::command]
+async fn unblock_file(path: String) -> Result<bool, AppError> {
+    let unblocked = quarantine::unblock(Path::new(&path))?;
+    if unblocked {
+        log::info!("Removed download marker from {}", path);
+    }
+    Ok(unblocked)
+}
+
+/// Open a terminal in the selected folder (or the folder containing a file)
+#[tauri::command]
+async fn open_terminal_here(path: String, terminal: Option<String>) -> Result<(), AppError> {
+    explorer::open_terminal_here(Path::new(&path), terminal.as_deref())
+}
+
+/// Mounted volumes with their label, file system, space and kind, each
+/// flagged when it is a root of the active profile already
+#[tauri::command]
+async fn list_volumes(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<volumes::Volume>, AppError> {
+    let roots = with_profiles(&app, &state, |profiles| profiles.active().roots.clone()).await?;
+    let mut volumes = tauri::async_runtime::spawn_blocking(volumes::list)
+        .await
+        .map_err(|e| format!("Volume task failed: {}", e))?;
+    for volume in &mut volumes {
+        volume.indexed = roots
+            .iter()
+            .any(|root| Path::new(root) == Path::new(&volume.mount_point));
+    }
+    Ok(volumes)
+}
+
+/// Index the volume mounted at `mount_point` into a frozen snapshot that
+/// stays searchable once the disk is unplugged (search_files with
+/// `snapshots`). Taking one of the same disk again replaces it.
+#[tauri::command]
+async fn snapshot_volume(
+    mount_point: String,
+    app: tauri::AppHandle,
+) -> Result<snapshot::Snapshot, AppError> {
+    let base_dir = app_data_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let volume = volumes::list()
+            .into_iter()
+            .find(|volume| Path::new(&volume.mount_point) == Path::new(&mount_point))
+            .ok_or_else(|| {
+                AppError::new(ErrorKind::NotFound, "No volume is mounted there")
+                    .with_path(&mount_point)
+            })?;
+        let now = Utc::now().timestamp();
+        let snapshot = snapshot::SnapshotStore::load(&base_dir).take(&volume, now)?;
+        log::info!(
+            "Snapshot {} of {}: {} entries",
+            snapshot.id,
+            snapshot.mount_point,
+            snapshot.file_count
+        );
+        Ok(snapshot)
+    })
+    .await
+    .map_err(|e| format!("Snapshot task failed: {}", e))?
+}
+
+/// Volume snapshots, with `mounted` set for disks plugged in now
+#[tauri::command]
+async fn list_snapshots(app: tauri::AppHandle) -> Result<Vec<snapshot::Snapshot>, AppError> {
+    let base_dir = app_data_dir(&app)?;
+    let volumes = tauri::async_runtime::spawn_blocking(volumes::list)
+        .await
+        .map_err(|e| format!("Volume task failed: {}", e))?;
+    let mut snapshots = snapshot::SnapshotStore::load(&base_dir).list().to_vec();
+    for snapshot in &mut snapshots {
+        snapshot.mounted = volumes.iter().any(|volume| snapshot.is_of(volume));
+    }
+    Ok(snapshots)
+}
+
+#[tauri::command]
+fn delete_snapshot(id: String, app: tauri::AppHandle) -> Result<snapshot::Snapshot, AppError> {
+    let mut store = snapshot::SnapshotStore::load(&app_data_dir(&app)?);
+    store
+        .remove(&id)
+        .map_err(|e| AppError::new(ErrorKind::NotFound, e))
+}
+
+/// Let the user pick folders to index in the native dialog. Returns them
+/// canonicalized, leaving out unreadable ones, with warnings about overlaps
+/// among them and with the active profile's roots; none when cancelled.
+#[tauri::command]
+async fn pick_directories(
+    title: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<picker::PickedDirectories, AppError> {
+    let title = title.unwrap_or_else(|| "Choose folders to index".to_string());
+    let picked = tauri::async_runtime::spawn_blocking(move || picker::pick(&title))
+        .await
+        .map_err(|e| format!("Folder dialog task failed: {}", e))??;
+    let roots = with_profiles(&app, &state, |profiles| profiles.active().roots.clone()).await?;
+    let review = picker::review(&picked, &roots);
+    for warning in &review.warnings {
+        log::warn!("{}", warning);
+    }
+    Ok(review)
+}
+
+/// Share paths of the installed WSL distros, such as `\\wsl$\Ubuntu`, to
+/// index as roots; empty without WSL
+#[tauri::command]
+async fn list_wsl_distros() -> Result<Vec<String>, AppError> {
+    Ok(tauri::async_runtime::spawn_blocking(wsl::list_distros)
+        .await
+        .map_err(|e| format!("WSL task failed: {}", e))?)
+}
+
+/// Open an entry of a WSL distro inside it: with `program` such as `vim` on
+/// the entry, or a shell in its folder (Windows only)
+#[tauri::command]
+async fn open_in_wsl(path: String, program: Option<String>) -> Result<(), AppError> {
+    wsl::open_in_wsl(Path::new(&path), program.as_deref())
+}
+
+/// Show an entry selected in Explorer, WSL entries included (Windows only)
+#[tauri::command]
+async fn reveal_in_explorer(path: String) -> Result<(), AppError> {
+    wsl::reveal_in_explorer(Path::new(&path))
+}
+
+/// Launch an executable or installer with administrator rights (Windows only)
+#[tauri::command]
+async fn open_elevated(path: String) -> Result<(), AppError> {
+    explorer::open_elevated(Path::new(&path))
+}
+
+/// Optional explorer actions available on this platform
+#[tauri::command]
+fn get_platform_capabilities() -> explorer::PlatformCapabilities {
+    explorer::platform_capabilities()
+}
+
+/// Protected locations the app cannot read and whether it has Full Disk
+/// Access (macOS only; empty elsewhere)
+#[tauri::command]
+async fn check_permissions() -> permissions::PermissionReport {
+    permissions::check_permissions()
+}
+
+fn onboarding_store(state: &AppState) -> Result<&onboarding::OnboardingStore, AppError> {
+    state.onboarding.get().ok_or_else(|| {
+        AppError::new(
+            ErrorKind::Internal,
+            "The onboarding state could not be opened",
+        )
+    })
+}
+
+/// Where first-run setup stands
+#[tauri::command]
+async fn get_onboarding_state(
+    state: tauri::State<'_, AppState>,
+) -> Result<onboarding::OnboardingState, AppError> {
+    Ok(onboarding_store(&state)?
+        .load()
+        .map_err(|e| format!("Failed to read the onboarding state: {}", e))?)
+}
+
+/// Move first-run setup on by `event`. Fails with `INVALID_INPUT`, saying
+/// what is missing, while the current step's condition does not hold.
+#[tauri::command]
+async fn advance_onboarding(
+    event: onboarding::Event,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<onboarding::OnboardingState, AppError> {
+    let store = onboarding_store(&state)?;
+    let report = tauri::async_runtime::spawn_blocking(permissions::check_permissions)
+        .await
+        .map_err(|e| format!("Permission check failed: {}", e))?;
+    let facts = onboarding::Facts {
+        permissions_granted: report.full_disk_access != Some(false)
+            && report.inaccessible.is_empty(),
+        roots: with_profiles(&app, &state, |profiles| profiles.active().roots.clone()).await?,
+        index_built: !state.is_indexing.load(Ordering::SeqCst)
+            && state.last_updated.read().await.is_some(),
+    };
+    let current = store
+        .load()
+        .map_err(|e| format!("Failed to read the onboarding state: {}", e))?;
+    let next = current
+        .advance(event, &facts, chrono::Utc::now().timestamp())
+        .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    store
+        .save(&next)
+        .map_err(|e| format!("Failed to save the onboarding state: {}", e))?;
+    if next.step != current.step {
+        log::info!("Onboarding moved on to {:?}", next.step);
+    }
+    Ok(next)
+}
+
+/// Open the System Settings privacy pane that grants `protection` (macOS only)
+#[tauri::command]
+async fn open_permission_settings(protection: permissions::Protection) -> Result<(), AppError> {
+    permissions::open_settings(protection)
+}
+
+/// Copy a file's content to the clipboard as text, image or file reference;
+/// returns the format used
+#[tauri::command]
+async fn copy_file_to_clipboard(path: String) -> Result<String, AppError> {
+    clipboard::copy_file_to_clipboard(Path::new(&path)).map(str::to_string)
+}
 
-    // Validate regex if needed
-    if use_regex {
-        regex::Regex::new(&query).map_err(|e| {
-            log::warn!("Invalid regex pattern '{}': {}", query, e);
-            "INVALID_REGEX".to_string()
-        })?;
-    }
+/// Begin dragging the given result paths out of the window into other apps
+#[tauri::command]
+async fn start_drag(paths: Vec<String>, window: tauri::WebviewWindow) -> Result<(), AppError> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    explorer::start_drag(&window, &paths)
+}
 
-    let docs = search_index.search(&query, use_regex, limit).map_err(|e| {
-        log::error!("Search failed for query '{}': {}", query, e);
-        format!("Search failed: {}", e)
-    })?;
+/// Launch flag that keeps the main window hidden in the tray
+const HIDDEN_FLAG: &str = "--hidden";
+/// Passed by the login item so `start_hidden` applies only to autostarts
+const AUTOSTART_FLAG: &str = "--autostart";
+/// Launch flag that runs headless in the tray, serving the index to other
+/// machines; see `remote`
+const AGENT_FLAG: &str = "--agent";
+
+/// Whether a launch with `args` starts without showing the main window
+fn starts_hidden(args: &[String], settings: &settings::Settings) -> bool {
+    args.iter()
+        .any(|arg| arg == HIDDEN_FLAG || arg == AGENT_FLAG)
+        || (settings.start_hidden && args.iter().any(|arg| arg == AUTOSTART_FLAG))
+}
 
-    let schema = search_index.get_schema();
-    let name_field = schema
-        .get_field("name")
-        .map_err(|e| format!("Failed to get name field: {}", e))?;
-    let path_field = schema
-        .get_field("path")
-        .map_err(|e| format!("Failed to get path field: {}", e))?;
-    let size_field = schema
-        .get_field("size")
-        .map_err(|e| format!("Failed to get size field: {}", e))?;
-    let modified_field = schema
-        .get_field("modified")
-        .map_err(|e| format!("Failed to get modified field: {}", e))?;
-    let is_folder_field = schema
-        .get_field("is_folder")
-        .map_err(|e| format!("Failed to get is_folder field: {}", e))?;
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
 
-    let mut results = Vec::new();
-    for doc in docs {
-        let name = doc
-            .get_first(name_field)
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        let path = doc
-            .get_first(path_field)
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        let size = doc
-            .get_first(size_field)
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0);
-        let modified_ts = doc
-            .get_first(modified_field)
-            .and_then(|v| v.as_datetime())
-            .map(|d: tantivy::DateTime| d.into_timestamp_secs())
-            .unwrap_or_else(|| {
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() as i64
-            });
-        let is_folder = doc
-            .get_first(is_folder_field)
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
-
-        // Convert timestamp to ISO 8601 string manually
-        let modified_str = format_timestamp_iso8601(modified_ts);
-
-        results.push(serde_json::json!({
-            "name": name,
-            "path": path,
-            "size": size,
-            "modified": modified_str,
-            "is_folder": is_folder
-        }));
+/// Label prefix of search windows opened beside the main one
+const SEARCH_WINDOW_PREFIX: &str = "search-";
+static SEARCH_WINDOWS: AtomicUsize = AtomicUsize::new(0);
+
+/// Page of a search window, starting on `query` when given
+fn search_window_url(query: Option<&str>) -> String {
+    match query.filter(|q| !q.trim().is_empty()) {
+        Some(query) => format!(
+            "index.html?q={}",
+            url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+        ),
+        None => "index.html".to_string(),
     }
+}
 
-    let search_time_ms = start_time.elapsed().as_millis() as u64;
-    log::info!(
-        "Search completed: {} results in {}ms (query='{}', regex={})",
-        results.len(),
-        search_time_ms,
-        query,
-        use_regex
+/// Open another search window with its own query state
+fn open_search_window(
+    app: &tauri::AppHandle,
+    query: Option<&str>,
+) -> tauri::Result<tauri::WebviewWindow> {
+    let label = format!(
+        "{}{}",
+        SEARCH_WINDOW_PREFIX,
+        SEARCH_WINDOWS.fetch_add(1, Ordering::SeqCst) + 1
     );
+    log::info!("Opening search window {}", label);
+    let url = tauri::WebviewUrl::App(search_window_url(query).into());
+    tauri::WebviewWindowBuilder::new(app, label, url)
+        .title("cross-everything")
+        .inner_size(900.0, 500.0)
+        .min_inner_size(400.0, 300.0)
+        .build()
+}
 
-    Ok(serde_json::json!({
-        "results": results,
-        "total_found": results.len(),
-        "search_time_ms": search_time_ms
-    }))
+/// Hand `query` to the main window: kept until taken with
+/// take_pending_search and announced with a "search-request" event
+fn request_search(app: &tauri::AppHandle, query: String) {
+    log::info!("Search requested at launch: {}", query);
+    if let Ok(mut pending) = app.state::<AppState>().pending_search.lock() {
+        *pending = Some(query.clone());
+    }
+    show_main_window(app);
+    // Other search windows keep their own queries
+    let _ = app.emit_to("main", "search-request", query);
 }
 
+/// Open another search window beside the main one, optionally starting on
+/// `query`, and return its label. Each window keeps its own query and
+/// results; search sessions are already independent.
 #[tauri::command]
-async fn get_index_status(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
-    let is_indexing = *state.is_indexing.lock().unwrap();
-    let total_files = *state.total_files.lock().unwrap();
-    let last_updated = *state.last_updated.lock().unwrap();
-    let is_ready = state.search_index.lock().unwrap().is_some();
-
-    log::debug!(
-        "Index status requested: ready={}, files={}, indexing={}",
-        is_ready,
-        total_files,
-        is_indexing
-    );
+async fn new_search_window(
+    query: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<String, AppError> {
+    // Async so window creation does not block the main thread on Windows
+    let window = open_search_window(&app, query.as_deref())
+        .map_err(|e| format!("Failed to open search window: {}", e))?;
+    Ok(window.label().to_string())
+}
 
-    Ok(serde_json::json!({
-        "is_ready": is_ready,
-        "total_files": total_files,
-        "last_updated": last_updated.map(format_timestamp_iso8601),
-        "indexing_in_progress": is_indexing
-    }))
+/// Take the search requested by a deep link or launch argument, if any
+#[tauri::command]
+fn take_pending_search(state: tauri::State<'_, AppState>) -> Option<String> {
+    state.pending_search.lock().ok()?.take()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // Must come first so a second launch only forwards its arguments
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            show_main_window(app);
+            if let Some(query) = launch::query_from_args(args.get(1..).unwrap_or_default()) {
+                request_search(app, query);
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(
             tauri_plugin_autostart::Builder::new()
                 .app_name("CrossEverything")
+                .arg(AUTOSTART_FLAG)
                 .build(),
         )
         .setup(|app| {
+            // Portable mode moves everything below, logs included
+            if let Ok(default_dir) = app.path().app_local_data_dir() {
+                let args: Vec<String> = std::env::args().skip(1).collect();
+                let exe_dir = portable::exe_dir().unwrap_or_else(|| default_dir.clone());
+                let configured = settings::Settings::load(&default_dir).data_dir;
+                let location =
+                    portable::resolve(&args, &exe_dir, &default_dir, configured.as_deref());
+                portable::init(location, &default_dir);
+            }
+
             // Initialize logging
-            if let Ok(log_dir) = app.path().app_local_data_dir() {
+            if let Ok(log_dir) = app_data_dir(app.handle()) {
                 if let Err(e) = init_logging(&log_dir) {
                     eprintln!("Failed to initialize logging: {}", e);
                 } else {
@@ -579,50 +4702,122 @@ pub fn run() {
             }
 
             log::info!("CrossEverything starting up");
-
-            // Create system tray icon
-            let icon = app.default_window_icon().cloned();
-
-            let show_item = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
-
-            let mut tray_builder = tauri::tray::TrayIconBuilder::new()
-                .tooltip("CrossEverything")
-                .menu(&menu);
-
-            // Set icon if available
-            if let Some(icon_image) = icon {
-                tray_builder = tray_builder.icon(icon_image);
+            if let Some(location) = portable::current() {
+                log::info!(
+                    "Data folder: {} ({:?})",
+                    location.path.display(),
+                    location.source
+                );
             }
 
-            let _tray = tray_builder
-                .on_menu_event(move |app, event| match event.id.as_ref() {
-                    "show" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
+            match app_data_dir(app.handle()) {
+                Ok(dir) => {
+                    match marks::MarkStore::open(&dir) {
+                        Ok(store) => {
+                            let _ = app.state::<AppState>().marks.set(store);
                         }
+                        Err(e) => log::warn!("Failed to open labels and ratings: {}", e),
                     }
-                    "quit" => {
-                        app.exit(0);
+                    match undo::UndoJournal::open(&dir) {
+                        Ok(journal) => {
+                            let _ = app.state::<AppState>().journal.set(journal);
+                        }
+                        Err(e) => log::warn!("Failed to open the undo journal: {}", e),
                     }
-                    _ => {}
-                })
-                .on_tray_icon_event(|tray, event| {
-                    if let tauri::tray::TrayIconEvent::Click {
-                        button: tauri::tray::MouseButton::Left,
-                        ..
-                    } = event
-                    {
-                        if let Some(window) = tray.app_handle().get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
+                    match onboarding::OnboardingStore::open(&dir) {
+                        Ok(store) => {
+                            let _ = app.state::<AppState>().onboarding.set(store);
                         }
+                        Err(e) => log::warn!("Failed to open the onboarding state: {}", e),
                     }
-                })
-                .build(app)?;
+                }
+                Err(e) => log::warn!("No app data directory for labels and ratings: {}", e),
+            }
+
+            let settings = app_data_dir(app.handle())
+                .map(|dir| settings::Settings::load(&dir))
+                .unwrap_or_default();
+            if let Err(e) = search::set_writer_options(settings.writer) {
+                log::warn!("Failed to apply writer settings: {}", e);
+            }
+            if settings.elevated_indexing {
+                apply_elevated_indexing();
+            }
+            i18n::set_current(settings.locale);
+            privacy::set_paths(&settings.private_paths);
+            ocr::set_command(settings.ocr_command.as_deref());
+            embed::set_command(settings.embedding_command.as_deref());
+
+            // Installers register the scheme; this covers portable copies
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                log::warn!("Failed to register {}:// links: {}", launch::URL_SCHEME, e);
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    if let Some(query) = launch::query_from_url(&url) {
+                        request_search(&handle, query);
+                    }
+                }
+            });
+
+            // The window is created hidden and shown here unless started in
+            // the tray; a requested search always shows it
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            let startup_query = app
+                .deep_link()
+                .get_current()
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                .iter()
+                .find_map(launch::query_from_url)
+                .or_else(|| launch::query_from_args(&args));
+            if let Some(query) = startup_query {
+                request_search(app.handle(), query);
+            } else if starts_hidden(&args, &settings) {
+                log::info!("Starting hidden in the tray");
+            } else {
+                show_main_window(app.handle());
+            }
+            if args.iter().any(|arg| arg == AGENT_FLAG) {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = handle.state::<AppState>();
+                    // Headless, no window asks for the index to be loaded
+                    if state.search_index.read().await.is_none() {
+                        if let Err(e) = load_existing_index(&handle, &state).await {
+                            log::warn!("Agent found no index to serve: {}", e);
+                        }
+                    }
+                    match start_agent_server(&handle, &state, None).await {
+                        Ok(status) => log::info!(
+                            "Running as agent on {}; its token is in the settings file",
+                            status["address"]
+                        ),
+                        Err(e) => log::error!("Failed to start agent: {}", e),
+                    }
+                });
+            }
+
+            tray::create(app)?;
+
+            // Resume the active profile's watches from the last session
+            spawn_watcher_loop(
+                app.handle().clone(),
+                std::time::Duration::from_millis(settings.debounce_ms),
+            );
+            spawn_share_poller(app.handle().clone());
+            spawn_ocr_worker(app.handle().clone());
+            spawn_embed_worker(app.handle().clone());
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = handle.state::<AppState>();
+                if let Err(e) = restore_watches(&handle, &state).await {
+                    log::warn!("Failed to restore watches: {}", e);
+                }
+            });
 
             Ok(())
         })
@@ -631,13 +4826,97 @@ pub fn run() {
             greet,
             build_index,
             search_files,
-            get_index_status
+            cancel_search,
+            stream_search,
+            group_search_by_folder,
+            get_smart_view,
+            search_batch,
+            find_hard_links,
+            find_similar,
+            semantic_search,
+            autocomplete,
+            get_results_table,
+            get_session,
+            undo_last_operation,
+            redo_operation,
+            set_session,
+            find_broken_links,
+            list_projects,
+            set_color_label,
+            set_rating,
+            set_note,
+            list_actions,
+            run_action,
+            rebuild_search_index,
+            backup_index,
+            restore_index,
+            get_settings,
+            get_data_location,
+            set_settings,
+            set_locale,
+            unlock_private_results,
+            lock_private_results,
+            get_elevation_status,
+            get_autostart,
+            take_pending_search,
+            new_search_window,
+            set_autostart,
+            relaunch_elevated,
+            get_index_status,
+            grep_in_results,
+            list_profiles,
+            create_profile,
+            switch_profile,
+            set_root_options,
+            start_watching,
+            stop_watching,
+            list_watched,
+            get_watcher_diagnostics,
+            get_metrics,
+            get_recent_logs,
+            create_diagnostics_bundle,
+            start_mcp_server,
+            stop_mcp_server,
+            start_agent,
+            stop_agent,
+            move_to_trash,
+            list_trash,
+            secure_delete,
+            compute_checksum,
+            bulk_rename,
+            transfer_files,
+            answer_transfer_conflict,
+            cancel_transfer,
+            restore_from_trash,
+            show_properties,
+            unblock_file,
+            get_file_properties,
+            inspect_streams,
+            start_drag,
+            open_terminal_here,
+            list_volumes,
+            snapshot_volume,
+            list_snapshots,
+            delete_snapshot,
+            get_onboarding_state,
+            advance_onboarding,
+            pick_directories,
+            list_wsl_distros,
+            open_in_wsl,
+            reveal_in_explorer,
+            open_elevated,
+            get_platform_capabilities,
+            check_permissions,
+            open_permission_settings,
+            copy_file_to_clipboard,
+            create_file,
+            create_folder
         ])
-        .on_window_event(|app, event| {
-            // When window is closed, hide it instead of destroying it
+        .on_window_event(|window, event| {
+            // Closing the main window hides it to the tray; extra search
+            // windows close for real
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                // Get the main window
-                if let Some(window) = app.get_webview_window("main") {
+                if window.label() == "main" {
                     let _ = window.hide();
                     api.prevent_close();
                 }
@@ -657,6 +4936,33 @@ mod tests {
         assert_eq!(result, "Hello, World! You've been greeted from Rust!");
     }
 
+    #[test]
+    fn test_starts_hidden() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let mut settings = settings::Settings::default();
+        assert!(!starts_hidden(&args(&[]), &settings));
+        assert!(starts_hidden(&args(&["--hidden"]), &settings));
+        assert!(starts_hidden(&args(&["--agent"]), &settings));
+        assert!(!starts_hidden(&args(&["--autostart"]), &settings));
+
+        settings.start_hidden = true;
+        assert!(starts_hidden(&args(&["--autostart"]), &settings));
+        assert!(
+            !starts_hidden(&args(&[]), &settings),
+            "Manual launches show"
+        );
+    }
+
+    #[test]
+    fn test_search_window_url() {
+        assert_eq!(search_window_url(None), "index.html");
+        assert_eq!(search_window_url(Some("  ")), "index.html");
+        assert_eq!(
+            search_window_url(Some("report 2024&draft")),
+            "index.html?q=report+2024%26draft"
+        );
+    }
+
     #[test]
     fn test_greet_empty_name() {
         let result = greet("");
@@ -707,6 +5013,10 @@ mod tests {
             size: 1024,
             modified: 1640000000,
             is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
         };
 
         let serialized = serde_json::to_string(&entity).unwrap();
@@ -748,6 +5058,10 @@ mod tests {
             size: 2048,
             modified: 1640005000,
             is_folder: true,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
         };
 
         let serialized = serde_json::to_string(&original).unwrap();
@@ -770,6 +5084,10 @@ mod tests {
             size: 51200,
             modified: 1640000000,
             is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
         };
 
         let folder = FileEntity {
@@ -779,93 +5097,89 @@ mod tests {
             size: 0,
             modified: 1640000000,
             is_folder: true,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
         };
 
         assert!(!file.is_folder);
         assert!(folder.is_folder);
     }
 
-    #[test]
-    fn test_app_state_default() {
+    #[tokio::test]
+    async fn test_app_state_default() {
         let state = AppState::default();
 
         assert!(
-            state.index_manager.lock().unwrap().is_none(),
+            state.index_manager.read().await.is_none(),
             "Index manager should be None initially"
         );
         assert!(
-            state.search_index.lock().unwrap().is_none(),
+            state.search_index.read().await.is_none(),
             "Search index should be None initially"
         );
         assert!(
-            state.file_watcher.lock().unwrap().is_none(),
+            state.file_watcher.lock().await.is_none(),
             "File watcher should be None initially"
         );
-        assert_eq!(*state.is_indexing.lock().unwrap(), false);
-        assert_eq!(*state.total_files.lock().unwrap(), 0);
-        assert_eq!(*state.last_updated.lock().unwrap(), None);
+        assert!(!state.is_indexing.load(Ordering::SeqCst));
+        assert_eq!(*state.total_files.read().await, 0);
+        assert_eq!(*state.last_updated.read().await, None);
     }
 
     #[test]
-    fn test_app_state_is_indexing_mutex() {
+    fn test_app_state_is_indexing_flag() {
         let state = AppState::default();
 
-        {
-            let mut is_indexing = state.is_indexing.lock().unwrap();
-            *is_indexing = true;
-        }
-
-        assert_eq!(*state.is_indexing.lock().unwrap(), true);
+        assert!(!state.is_indexing.swap(true, Ordering::SeqCst));
+        assert!(state.is_indexing.load(Ordering::SeqCst));
 
-        {
-            let mut is_indexing = state.is_indexing.lock().unwrap();
-            *is_indexing = false;
-        }
-
-        assert_eq!(*state.is_indexing.lock().unwrap(), false);
+        state.is_indexing.store(false, Ordering::SeqCst);
+        assert!(!state.is_indexing.load(Ordering::SeqCst));
     }
 
-    #[test]
-    fn test_app_state_total_files_mutex() {
+    #[tokio::test]
+    async fn test_app_state_total_files_lock() {
         let state = AppState::default();
 
         {
-            let mut total_files = state.total_files.lock().unwrap();
+            let mut total_files = state.total_files.write().await;
             *total_files = 100;
         }
 
-        assert_eq!(*state.total_files.lock().unwrap(), 100);
+        assert_eq!(*state.total_files.read().await, 100);
     }
 
-    #[test]
-    fn test_app_state_last_updated_mutex() {
+    #[tokio::test]
+    async fn test_app_state_last_updated_lock() {
         let state = AppState::default();
 
         {
-            let mut last_updated = state.last_updated.lock().unwrap();
+            let mut last_updated = state.last_updated.write().await;
             *last_updated = Some(1640000000);
         }
 
-        assert_eq!(*state.last_updated.lock().unwrap(), Some(1640000000));
+        assert_eq!(*state.last_updated.read().await, Some(1640000000));
     }
 
-    #[test]
-    fn test_get_index_status_logic() {
+    #[tokio::test]
+    async fn test_get_index_status_logic() {
         let state = AppState::default();
 
-        let is_indexing = *state.is_indexing.lock().unwrap();
-        let total_files = *state.total_files.lock().unwrap();
-        let last_updated = *state.last_updated.lock().unwrap();
-        let is_ready = state.search_index.lock().unwrap().is_some();
+        let is_indexing = state.is_indexing.load(Ordering::SeqCst);
+        let total_files = *state.total_files.read().await;
+        let last_updated = *state.last_updated.read().await;
+        let is_ready = state.search_index.read().await.is_some();
 
-        assert_eq!(is_ready, false);
+        assert!(!is_ready);
         assert_eq!(total_files, 0);
         assert_eq!(last_updated, None);
-        assert_eq!(is_indexing, false);
+        assert!(!is_indexing);
     }
 
-    #[test]
-    fn test_get_index_status_with_mock_data() {
+    #[tokio::test]
+    async fn test_get_index_status_with_mock_data() {
         use tempfile::tempdir;
 
         let state = AppState::default();
@@ -877,36 +5191,393 @@ mod tests {
         let index_manager = index::IndexManager::new(&db_path).unwrap();
         let search_index = search::SearchIndex::new(&search_index_path).unwrap();
 
-        *state.index_manager.lock().unwrap() = Some(index_manager);
-        *state.search_index.lock().unwrap() = Some(search_index);
-        *state.total_files.lock().unwrap() = 42;
-        *state.last_updated.lock().unwrap() = Some(1640000000);
+        *state.index_manager.write().await = Some(index_manager);
+        *state.search_index.write().await = Some(Arc::new(search_index));
+        *state.total_files.write().await = 42;
+        *state.last_updated.write().await = Some(1640000000);
 
-        let is_indexing = *state.is_indexing.lock().unwrap();
-        let total_files = *state.total_files.lock().unwrap();
-        let last_updated = *state.last_updated.lock().unwrap();
-        let is_ready = state.search_index.lock().unwrap().is_some();
+        let is_indexing = state.is_indexing.load(Ordering::SeqCst);
+        let total_files = *state.total_files.read().await;
+        let last_updated = *state.last_updated.read().await;
+        let is_ready = state.search_index.read().await.is_some();
 
-        assert_eq!(is_ready, true);
+        assert!(is_ready);
         assert_eq!(total_files, 42);
         assert_ne!(last_updated, None);
-        assert_eq!(is_indexing, false);
+        assert!(!is_indexing);
+    }
+
+    #[tokio::test]
+    async fn test_apply_watch_changes_updates_live_index() {
+        use tempfile::tempdir;
+        use watcher::FileChangeEvent;
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+
+        let state = AppState::default();
+        *state.index_manager.write().await =
+            Some(index::IndexManager::new(&temp_dir.path().join("db")).unwrap());
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("search")).unwrap(),
+        ));
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+        let hits = |query: &'static str| {
+            let state = &state;
+            async move {
+                let guard = state.search_index.read().await;
+                guard
+                    .as_ref()
+                    .unwrap()
+                    .search(query, false, 10)
+                    .unwrap()
+                    .len()
+            }
+        };
+
+        std::fs::write(root.join("draft.txt"), "").unwrap();
+        let affected =
+            apply_watch_changes(&state, vec![FileChangeEvent::Created(path("draft.txt"))])
+                .await
+                .unwrap();
+        assert_eq!(affected, vec![path("draft.txt")]);
+        assert_eq!(hits("draft").await, 1);
+        assert_eq!(*state.total_files.read().await, 1);
+
+        std::fs::rename(root.join("draft.txt"), root.join("report.txt")).unwrap();
+        apply_watch_changes(
+            &state,
+            vec![FileChangeEvent::Renamed(
+                path("draft.txt"),
+                path("report.txt"),
+            )],
+        )
+        .await
+        .unwrap();
+        assert_eq!(hits("draft").await, 0);
+        assert_eq!(hits("report").await, 1);
+        assert_eq!(*state.total_files.read().await, 1);
+
+        std::fs::remove_file(root.join("report.txt")).unwrap();
+        apply_watch_changes(&state, vec![FileChangeEvent::Deleted(path("report.txt"))])
+            .await
+            .unwrap();
+        assert_eq!(hits("report").await, 0);
+        assert_eq!(*state.total_files.read().await, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_recognize_image_makes_its_text_searchable() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let state = AppState::default();
+        *state.index_manager.write().await =
+            Some(index::IndexManager::new(&temp_dir.path().join("db")).unwrap());
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("search")).unwrap(),
+        ));
+        let hits = |query: &'static str| {
+            let state = &state;
+            async move {
+                let guard = state.search_index.read().await;
+                guard
+                    .as_ref()
+                    .unwrap()
+                    .search(query, false, 10)
+                    .unwrap()
+                    .len()
+            }
+        };
+
+        let scan = temp_dir.path().join("scan.png");
+        std::fs::write(&scan, "").unwrap();
+        index_created(&state, &scan).await.unwrap();
+        assert_eq!(hits("invoice").await, 0);
+
+        recognize_image(&state, "echo Invoice  2024", &scan)
+            .await
+            .unwrap();
+        assert_eq!(hits("invoice").await, 1);
+        let entity = state
+            .index_manager
+            .read()
+            .await
+            .as_ref()
+            .unwrap()
+            .get_file_entity_by_path(&scan.to_string_lossy())
+            .unwrap()
+            .unwrap();
+        assert_eq!(entity.attributes.ocr_text.as_deref(), Some("Invoice 2024"));
+
+        // An image already read is not given to the command again
+        recognize_image(&state, "false", &scan).await.unwrap();
+        assert_eq!(*state.total_files.read().await, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_embedded_entries_are_found_by_meaning() {
+        use tempfile::tempdir;
+
+        // Stands in for a model: tax-related text points one way, the rest
+        // the other
+        let command = "sh -c 'while read -r text; do case $text in \
+            *tax*|*Steuer*) echo 1 0 ;; *) echo 0 1 ;; esac; done'";
+        let temp_dir = tempdir().unwrap();
+        let state = AppState::default();
+        *state.index_manager.write().await =
+            Some(index::IndexManager::new(&temp_dir.path().join("db")).unwrap());
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("search")).unwrap(),
+        ));
+        let names = |query: &'static str| {
+            let state = &state;
+            async move {
+                nearest_in_meaning(state, command, query.to_string(), 10)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|result| result.name)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let mut paths = Vec::new();
+        for name in ["Steuererklärung 2024.pdf", "holiday.jpg"] {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, "").unwrap();
+            index_created(&state, &path).await.unwrap();
+            paths.push(path.to_string_lossy().to_string());
+        }
+        assert!(names("tax documents").await.is_empty());
+
+        embed_entries(&state, command, &paths).await.unwrap();
+        assert_eq!(
+            names("tax documents").await,
+            vec!["Steuererklärung 2024.pdf", "holiday.jpg"]
+        );
+        assert_eq!(
+            names("beach").await,
+            vec!["holiday.jpg", "Steuererklärung 2024.pdf"]
+        );
+
+        // A vector left from an older text is skipped until embedded again
+        let mut holiday = state
+            .index_manager
+            .read()
+            .await
+            .as_ref()
+            .unwrap()
+            .get_file_entity_by_path(&paths[1])
+            .unwrap()
+            .unwrap();
+        holiday.attributes.note = Some("receipts for the tax office".to_string());
+        update_live_index(&state, &[], &[holiday]).await.unwrap();
+        assert_eq!(
+            names("tax documents").await,
+            vec!["Steuererklärung 2024.pdf"]
+        );
+        embed_entries(&state, command, &paths).await.unwrap();
+        assert_eq!(names("tax documents").await.len(), 2);
+
+        // Vectors go with their entries
+        update_live_index(&state, &[index::path_id(&paths[0])], &[])
+            .await
+            .unwrap();
+        assert_eq!(names("tax documents").await, vec!["holiday.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_follows_root_options() {
+        use watcher::FileChangeEvent;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        let hidden_dir = root.join(".cache");
+        std::fs::create_dir_all(hidden_dir.join("nested")).unwrap();
+        std::fs::write(hidden_dir.join("nested").join("a.txt"), "").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let state = AppState::default();
+        let mut profiles = profile::ProfileStore::load(&temp_dir.path().join("app"));
+        profiles
+            .set_roots(profile::DEFAULT_PROFILE, vec![root_str.clone()])
+            .unwrap();
+        *state.profiles.write().await = Some(profiles);
+        *state.index_manager.write().await =
+            Some(index::IndexManager::new(&temp_dir.path().join("db")).unwrap());
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("search")).unwrap(),
+        ));
+        let indexed = |path: &Path| {
+            let path = path.to_string_lossy().to_string();
+            let state = &state;
+            async move {
+                let guard = state.index_manager.read().await;
+                guard
+                    .as_ref()
+                    .unwrap()
+                    .get_file_entity_by_path(&path)
+                    .unwrap()
+            }
+        };
+
+        // Entries met one by one inherit the hidden flag of their folders
+        let file = hidden_dir.join("nested").join("a.txt");
+        apply_watch_changes(
+            &state,
+            vec![FileChangeEvent::Created(file.to_string_lossy().to_string())],
+        )
+        .await
+        .unwrap();
+        assert!(indexed(&file).await.unwrap().hidden);
+        rescan_subtree(&state, &hidden_dir.join("nested"))
+            .await
+            .unwrap();
+        assert!(indexed(&hidden_dir.join("nested")).await.unwrap().hidden);
+        assert!(indexed(&file).await.unwrap().accessed.is_none());
+
+        state
+            .profiles
+            .write()
+            .await
+            .as_mut()
+            .unwrap()
+            .set_root_options(
+                profile::DEFAULT_PROFILE,
+                &root_str,
+                index::WalkOptions {
+                    skip_hidden: true,
+                    record_accessed: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        rescan_subtree(&state, &hidden_dir.join("nested"))
+            .await
+            .unwrap();
+        assert!(indexed(&file).await.is_none());
+        assert!(indexed(&hidden_dir.join("nested")).await.is_none());
+
+        let visible = root.join("b.txt");
+        std::fs::write(&visible, "").unwrap();
+        apply_watch_changes(
+            &state,
+            vec![
+                FileChangeEvent::Created(file.to_string_lossy().to_string()),
+                FileChangeEvent::Created(visible.to_string_lossy().to_string()),
+            ],
+        )
+        .await
+        .unwrap();
+        assert!(indexed(&file).await.is_none());
+        assert!(indexed(&visible).await.unwrap().accessed.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_then_create_of_same_file_is_a_rename() {
+        use tempfile::tempdir;
+        use watcher::FileChangeEvent;
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        std::fs::create_dir_all(root.join("album")).unwrap();
+        std::fs::write(root.join("album").join("photo.jpg"), "").unwrap();
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+
+        let state = AppState::default();
+        let index_manager = index::IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        index_manager
+            .walk_directory(&root, index::WalkOptions::default(), |entity| {
+                index_manager
+                    .save_file_entity(&entity)
+                    .map_err(|e| e.to_string())
+            })
+            .unwrap();
+        *state.index_manager.write().await = Some(index_manager);
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("search")).unwrap(),
+        ));
+
+        // Watchers without rename pairing report a move as delete + create
+        std::fs::rename(root.join("album"), root.join("trip")).unwrap();
+        apply_watch_changes(
+            &state,
+            vec![
+                FileChangeEvent::Deleted(path("album")),
+                FileChangeEvent::Created(path("trip")),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let guard = state.index_manager.read().await;
+        let index_manager = guard.as_ref().unwrap();
+        let moved = root.join("trip").join("photo.jpg");
+        let photo = index_manager
+            .get_file_entity_by_path(&moved.to_string_lossy())
+            .unwrap()
+            .expect("Contents move with the folder");
+        assert!(photo.attributes.file_id.is_some());
+        assert!(index_manager
+            .get_file_entity_by_path(&path("album"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rescan_subtree_reconciles_index() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("kept.txt"), "").unwrap();
+        std::fs::write(root.join("vanished.txt"), "").unwrap();
+
+        let state = AppState::default();
+        let index_manager = index::IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        let search_index = search::SearchIndex::new(&temp_dir.path().join("search")).unwrap();
+        let mut builder = builder::IndexBuilder::new(&index_manager, &search_index).unwrap();
+        index_manager
+            .walk_directory(&root, index::WalkOptions::default(), |e| builder.add(e))
+            .unwrap();
+        *state.total_files.write().await = builder.finish().unwrap();
+        *state.index_manager.write().await = Some(index_manager);
+        *state.search_index.write().await = Some(Arc::new(search_index));
+
+        // Changes the watcher never reported
+        std::fs::remove_file(root.join("vanished.txt")).unwrap();
+        std::fs::write(root.join("appeared.txt"), "").unwrap();
+
+        rescan_subtree(&state, &root).await.unwrap();
+        assert_eq!(*state.total_files.read().await, 3);
+        let search_guard = state.search_index.read().await;
+        let search_index = search_guard.as_ref().unwrap();
+        assert!(search_index
+            .search("vanished", false, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(search_index.search("appeared", false, 10).unwrap().len(), 1);
     }
 
     #[test]
     fn test_get_index_status_indexing() {
         let state = AppState::default();
 
-        *state.is_indexing.lock().unwrap() = true;
+        state.is_indexing.store(true, Ordering::SeqCst);
 
-        let is_indexing = *state.is_indexing.lock().unwrap();
-        assert_eq!(is_indexing, true);
+        let is_indexing = state.is_indexing.load(Ordering::SeqCst);
+        assert!(is_indexing);
     }
 
-    #[test]
-    fn test_search_index_not_ready() {
+    #[tokio::test]
+    async fn test_search_index_not_ready() {
         let state = AppState::default();
-        let search_index_guard = state.search_index.lock().unwrap();
+        let search_index_guard = state.search_index.read().await;
         let search_index = search_index_guard.as_ref();
 
         assert!(
@@ -970,6 +5641,340 @@ mod tests {
         assert_eq!(results.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_swap_in_staged_index_replaces_live_index() {
+        use tempfile::tempdir;
+
+        fn add_doc(search_index: &search::SearchIndex, name: &str) {
+            let schema = search_index.get_schema();
+            let mut writer = search_index.writer().unwrap();
+            let mut doc = tantivy::TantivyDocument::default();
+            doc.add_text(schema.get_field("name").unwrap(), name);
+            doc.add_text(schema.get_field("path").unwrap(), format!("/{}", name));
+            writer.add_document(doc).unwrap();
+            writer.commit().unwrap();
+        }
+
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let (db_path, search_index_path) = rebuild::live_paths(dir);
+        let (staging_db_path, staging_search_path) = rebuild::new_generation(dir).unwrap();
+
+        let state = AppState::default();
+        let live = search::SearchIndex::new(&search_index_path).unwrap();
+        add_doc(&live, "oldfile.txt");
+        *state.index_manager.write().await = Some(index::IndexManager::new(&db_path).unwrap());
+        *state.search_index.write().await = Some(Arc::new(live));
+
+        {
+            let _staged_db = index::IndexManager::new(&staging_db_path).unwrap();
+            let staged = search::SearchIndex::new(&staging_search_path).unwrap();
+            add_doc(&staged, "newfile.txt");
+        }
+
+        swap_in_staged_index(&state, dir, (&staging_db_path, &staging_search_path))
+            .await
+            .unwrap();
+
+        let guard = state.search_index.read().await;
+        let search_index = guard.as_ref().expect("Index should stay available");
+        assert_eq!(search_index.search("newfile", false, 10).unwrap().len(), 1);
+        assert_eq!(search_index.search("oldfile", false, 10).unwrap().len(), 0);
+        assert_eq!(
+            rebuild::live_paths(dir),
+            (staging_db_path, staging_search_path)
+        );
+        assert!(!db_path.exists() && !search_index_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_force_rebuild_drops_cached_results() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let root = dir.join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::File::create(root.join("before.txt")).unwrap();
+
+        // A forced rebuild: a new generation built from the files as they are
+        let rebuild = || {
+            let (db_path, search_path) = rebuild::new_generation(dir).unwrap();
+            let index_manager = index::IndexManager::new(&db_path).unwrap();
+            index_manager.add_or_update_file(&root).unwrap();
+            for entry in std::fs::read_dir(&root).unwrap() {
+                index_manager
+                    .add_or_update_file(&entry.unwrap().path())
+                    .unwrap();
+            }
+            stage_search_index(&index_manager, &search_path).unwrap();
+            (db_path, search_path)
+        };
+        // What search_files does for the active profile
+        async fn search(state: &AppState) -> (u64, Vec<search::SearchResult>) {
+            let search_index = state.search_snapshot().await.unwrap();
+            let opstamp = search_index.opstamp().unwrap();
+            let key = search::CacheKey {
+                profile: profile::DEFAULT_PROFILE.to_string(),
+                query: "txt".to_string(),
+                use_regex: false,
+                limit: 10,
+                filter: Default::default(),
+                facets: false,
+            };
+            let mut cache = state.query_cache.lock().await;
+            if let Some((results, _)) = cache.get(&key, opstamp) {
+                return (opstamp, results);
+            }
+            let found = search_index
+                .search("txt", false, 10)
+                .and_then(|docs| search_index.to_results(&docs))
+                .unwrap();
+            cache.insert(key, opstamp, (found.clone(), None));
+            (opstamp, found)
+        }
+
+        let state = AppState::default();
+        let (db_path, search_path) = rebuild();
+        swap_in_staged_index(&state, dir, (&db_path, &search_path))
+            .await
+            .unwrap();
+        let (first_opstamp, first) = search(&state).await;
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].name, "before.txt");
+
+        std::fs::remove_file(root.join("before.txt")).unwrap();
+        std::fs::File::create(root.join("after.txt")).unwrap();
+        let (db_path, search_path) = rebuild();
+        swap_in_staged_index(&state, dir, (&db_path, &search_path))
+            .await
+            .unwrap();
+
+        // The fresh generation counts commits from scratch, like the old one did
+        let (second_opstamp, second) = search(&state).await;
+        assert_eq!(second_opstamp, first_opstamp);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].name, "after.txt");
+    }
+
+    #[test]
+    fn test_migrate_search_index_regenerates_from_sled() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::File::create(root.join("migrated.txt")).unwrap();
+
+        let dir = temp_dir.path();
+        let (db_path, search_index_path) = rebuild::live_paths(dir);
+        let index_manager = index::IndexManager::new(&db_path).unwrap();
+        index_manager.add_or_update_file(&root).unwrap();
+        index_manager
+            .add_or_update_file(&root.join("migrated.txt"))
+            .unwrap();
+
+        // An index from before versioning: empty, without a version marker
+        drop(search::SearchIndex::new(&search_index_path).unwrap());
+        std::fs::remove_file(search_index_path.join("schema_version")).unwrap();
+        assert!(search::SearchIndex::new(&search_index_path).is_err());
+
+        let migrated = migrate_search_index(&index_manager, dir, &db_path).unwrap();
+        assert_eq!(migrated.search("migrated", false, 10).unwrap().len(), 1);
+        let (live_db_path, live_search_path) = rebuild::live_paths(dir);
+        assert_eq!(live_db_path, db_path);
+        assert_eq!(
+            search::SearchIndex::schema_version(&live_search_path),
+            Some(search::SCHEMA_VERSION)
+        );
+        assert!(!search_index_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_search_from_db_replaces_live_index() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("rebuilt.txt");
+        std::fs::File::create(&file).unwrap();
+        let db_path = temp_dir.path().join(".index_db");
+        let search_index_path = temp_dir.path().join(".search_index");
+
+        // The DB knows the file but the live search index does not
+        {
+            let index_manager = index::IndexManager::new(&db_path).unwrap();
+            index_manager.add_or_update_file(&file).unwrap();
+            index_manager.flush().unwrap();
+        }
+        let state = AppState::default();
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&search_index_path).unwrap(),
+        ));
+
+        let documents = rebuild_search_from_db(&state, temp_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(documents, 1);
+        assert_eq!(*state.total_files.read().await, 1);
+        assert!(state.index_manager.read().await.is_some());
+
+        let guard = state.search_index.read().await;
+        let search_index = guard.as_ref().expect("Index should stay available");
+        assert_eq!(search_index.search("rebuilt", false, 10).unwrap().len(), 1);
+        assert_eq!(rebuild::live_paths(temp_dir.path()).0, db_path);
+        assert!(!search_index_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_search_snapshot_does_not_hold_the_lock() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = AppState::default();
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("search")).unwrap(),
+        ));
+
+        let snapshot = state.search_snapshot().await.unwrap();
+        let mut guard = state
+            .search_index
+            .try_write()
+            .expect("A snapshot leaves the lock free");
+
+        // Swapping does not wait for the snapshot, which stays usable
+        *guard = Some(Arc::new(
+            search::SearchIndex::new(&temp_dir.path().join("next")).unwrap(),
+        ));
+        drop(guard);
+        assert!(snapshot.search("anything", false, 10).unwrap().is_empty());
+        assert_eq!(Arc::strong_count(&snapshot), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_search_from_db_requires_db() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = AppState::default();
+        let err = rebuild_search_from_db(&state, temp_dir.path())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IndexNotReady);
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_round_trip() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let kept = temp_dir.path().join("kept.txt");
+        std::fs::File::create(&kept).unwrap();
+        let dir = temp_dir.path().join("index");
+        let (db_path, search_index_path) = rebuild::live_paths(&dir);
+
+        let state = AppState::default();
+        let archive = temp_dir.path().join("index.tar.zst");
+        let err = backup_to_archive(&state, (&db_path, &search_index_path), &archive)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IndexNotReady);
+
+        let index_manager = index::IndexManager::new(&db_path).unwrap();
+        index_manager.add_or_update_file(&kept).unwrap();
+        *state.index_manager.write().await = Some(index_manager);
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&search_index_path).unwrap(),
+        ));
+        rebuild_search_from_db(&state, &dir).await.unwrap();
+
+        // A search holding its guard doesn't stop the backup
+        let (db_path, search_index_path) = rebuild::live_paths(&dir);
+        let searching = state.search_index.read().await;
+        let (bytes, manifest) = tokio::time::timeout(
+            std::time::Duration::from_secs(30),
+            backup_to_archive(&state, (&db_path, &search_index_path), &archive),
+        )
+        .await
+        .expect("Backup should not wait for searches")
+        .unwrap();
+        drop(searching);
+        assert!(bytes > 0);
+        assert_eq!(manifest.files, 1);
+
+        // Lose the file from both stores, then bring it back from the archive
+        {
+            let guard = state.index_manager.read().await;
+            let index_manager = guard.as_ref().unwrap();
+            index_manager.remove_file(&kept).unwrap();
+        }
+        rebuild_search_from_db(&state, &dir).await.unwrap();
+        assert_eq!(*state.total_files.read().await, 0);
+
+        let restored = restore_from_archive(&state, &archive, &dir).await.unwrap();
+        assert_eq!(restored, 1);
+        assert_eq!(*state.total_files.read().await, 1);
+        let guard = state.search_index.read().await;
+        let search_index = guard.as_ref().expect("Index should stay available");
+        assert_eq!(search_index.search("kept", false, 10).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_restore_invalid_archive_keeps_live_index() {
+        use tempfile::tempdir;
+
+        let temp_dir = tempdir().unwrap();
+        let (_, search_index_path) = rebuild::live_paths(temp_dir.path());
+        let state = AppState::default();
+        *state.search_index.write().await = Some(Arc::new(
+            search::SearchIndex::new(&search_index_path).unwrap(),
+        ));
+
+        let archive = temp_dir.path().join("broken.tar.zst");
+        std::fs::write(&archive, "broken").unwrap();
+        assert!(restore_from_archive(&state, &archive, temp_dir.path())
+            .await
+            .is_err());
+        assert_eq!(rebuild::live_paths(temp_dir.path()).1, search_index_path);
+        assert!(state.search_index.read().await.is_some());
+        assert!(search_index_path.exists());
+
+        let err = absolute_path("relative.tar.zst".to_string(), "Backup archive").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_progress_payload_eta_and_rate() {
+        let payload = progress_payload(
+            "/root",
+            "/root/sub",
+            100,
+            300,
+            std::time::Duration::from_secs(10),
+            2,
+        );
+        assert_eq!(payload["files_per_sec"], 10);
+        assert_eq!(payload["eta_secs"], 20);
+        assert_eq!(payload["elapsed_secs"], 10);
+        assert_eq!(payload["root"], "/root");
+        assert_eq!(payload["current_dir"], "/root/sub");
+        assert_eq!(payload["errors"], 2);
+
+        // Unknown or exceeded estimate: total follows processed, ETA is null
+        let payload = progress_payload("", "", 100, 0, std::time::Duration::from_secs(1), 0);
+        assert_eq!(payload["total"], 100);
+        assert!(payload["eta_secs"].is_null());
+    }
+
+    #[test]
+    fn test_initial_estimate_modes() {
+        use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        std::fs::File::create(temp_dir.path().join("a.txt")).unwrap();
+        let paths = vec![temp_dir.path().to_string_lossy().to_string()];
+
+        // Previous count wins by default, so no walk is needed
+        assert_eq!(initial_estimate(&paths, Some(500), None), 500);
+        // First build walks the roots (root dir + one file)
+        assert_eq!(initial_estimate(&paths, None, None), 2);
+        // Explicit opt-in refreshes a stale count, opt-out skips entirely
+        assert_eq!(initial_estimate(&paths, Some(500), Some(true)), 2);
+        assert_eq!(initial_estimate(&paths, Some(500), Some(false)), 0);
+    }
+
     #[test]
     fn test_format_timestamp_with_chrono() {
         let timestamp = 1640000000;
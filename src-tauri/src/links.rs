@@ -0,0 +1,212 @@
+// Stale links: symlinks and junctions whose target is gone, and on Windows
+// .lnk shortcuts pointing at a missing local path
+
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Largest shortcut read; real ones are a few KB
+const MAX_SHORTCUT_BYTES: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Symlink,
+    Shortcut,
+}
+
+/// A link whose target no longer exists
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BrokenLink {
+    pub path: String,
+    pub kind: LinkKind,
+    /// Where the link pointed, when it could be read
+    pub target: Option<String>,
+}
+
+/// Whether `path` is named like a Windows shell shortcut
+pub fn is_shortcut(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("lnk"))
+}
+
+/// `path` as a broken link, or None when its target exists, it is no link,
+/// or it is gone itself. Shortcuts are only checked on Windows, and only when
+/// the drive their target is on is present, so an unplugged drive does not
+/// make every shortcut into it look stale.
+pub fn check(path: &Path) -> Option<BrokenLink> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    let path_str = path.to_string_lossy().to_string();
+    if metadata.file_type().is_symlink() {
+        return (!path.exists()).then(|| BrokenLink {
+            path: path_str,
+            kind: LinkKind::Symlink,
+            target: crate::index::link_target(path),
+        });
+    }
+    if !cfg!(windows)
+        || !metadata.is_file()
+        || !is_shortcut(path)
+        || crate::index::is_online_only(&metadata)
+    {
+        return None;
+    }
+    let mut data = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(MAX_SHORTCUT_BYTES).read_to_end(&mut data))
+        .ok()?;
+    let target = shortcut_target(&data)?;
+    let target_path = Path::new(&target);
+    let volume = target_path.ancestors().last()?;
+    if !volume.exists() || target_path.exists() {
+        return None;
+    }
+    Some(BrokenLink {
+        path: path_str,
+        kind: LinkKind::Shortcut,
+        target: Some(target),
+    })
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<usize> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize)
+}
+
+/// NUL-terminated string in the system code page; only ASCII is decoded
+/// faithfully, which covers the paths Windows stores this way
+fn ansi_at(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+/// NUL-terminated UTF-16 string
+fn unicode_at(data: &[u8], offset: usize) -> Option<String> {
+    let units: Vec<u16> = data
+        .get(offset..)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+/// Local path a shell link (MS-SHLLINK) points to, from its LinkInfo
+/// structure. None for network targets, targets given only as shell item IDs
+/// and malformed files.
+pub fn shortcut_target(data: &[u8]) -> Option<String> {
+    const HEADER_SIZE: usize = 0x4C;
+    const HAS_LINK_TARGET_ID_LIST: usize = 0x1;
+    const HAS_LINK_INFO: usize = 0x2;
+    const VOLUME_ID_AND_LOCAL_BASE_PATH: usize = 0x1;
+
+    if u32_at(data, 0)? != HEADER_SIZE {
+        return None;
+    }
+    let flags = u32_at(data, 0x14)?;
+    let mut offset = HEADER_SIZE;
+    if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+        offset += 2 + u16_at(data, offset)? as usize;
+    }
+    if flags & HAS_LINK_INFO == 0 {
+        return None;
+    }
+    let info = data.get(offset..offset + u32_at(data, offset)?)?;
+    let header_size = u32_at(info, 4)?;
+    if u32_at(info, 8)? & VOLUME_ID_AND_LOCAL_BASE_PATH == 0 {
+        return None;
+    }
+    // Unicode offsets follow the ANSI ones in newer headers
+    let (base, suffix) = if header_size >= 0x24 {
+        (
+            unicode_at(info, u32_at(info, 0x1C)?)?,
+            unicode_at(info, u32_at(info, 0x20)?)?,
+        )
+    } else {
+        (
+            ansi_at(info, u32_at(info, 0x10)?)?,
+            ansi_at(info, u32_at(info, 0x18)?)?,
+        )
+    };
+    (!base.is_empty()).then(|| base + &suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shortcut with an ANSI LinkInfo for `base` + `suffix`, behind an item ID
+    /// list as Explorer writes them
+    fn shortcut(base: &str, suffix: &str) -> Vec<u8> {
+        let mut data = vec![0u8; 0x4C];
+        data[0] = 0x4C;
+        data[0x14] = 0x1 | 0x2;
+        data.extend_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&[0xAA, 0xBB, 0, 0]);
+
+        let header_size = 0x1C;
+        let base_offset = header_size;
+        let suffix_offset = base_offset + base.len() + 1;
+        let size = suffix_offset + suffix.len() + 1;
+        for value in [size, header_size, 1, 0, base_offset, 0, suffix_offset] {
+            data.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        for text in [base, suffix] {
+            data.extend_from_slice(text.as_bytes());
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn test_shortcut_target() {
+        assert_eq!(
+            shortcut_target(&shortcut(r"C:\Users\me\", "report.docx")).as_deref(),
+            Some(r"C:\Users\me\report.docx")
+        );
+        assert_eq!(
+            shortcut_target(&shortcut(r"D:\Games\app.exe", "")).as_deref(),
+            Some(r"D:\Games\app.exe")
+        );
+
+        let mut network = shortcut(r"C:\x", "");
+        // LinkInfoFlags of a network-only target
+        let flags_at = 0x4C + 6 + 8;
+        network[flags_at] = 0x2;
+        assert_eq!(shortcut_target(&network), None);
+        assert_eq!(shortcut_target(b"not a shortcut"), None);
+        let truncated = shortcut(r"C:\Users\me\", "report.docx");
+        assert_eq!(shortcut_target(&truncated[..0x60]), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_finds_dangling_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        fs::write(&file, "x").unwrap();
+        symlink(&file, dir.path().join("working")).unwrap();
+        symlink(dir.path().join("gone.txt"), dir.path().join("dangling")).unwrap();
+
+        assert_eq!(check(&file), None);
+        assert_eq!(check(&dir.path().join("working")), None);
+        assert_eq!(check(&dir.path().join("missing")), None);
+        assert_eq!(
+            check(&dir.path().join("dangling")),
+            Some(BrokenLink {
+                path: dir.path().join("dangling").to_string_lossy().to_string(),
+                kind: LinkKind::Symlink,
+                target: Some(dir.path().join("gone.txt").to_string_lossy().to_string()),
+            })
+        );
+    }
+}
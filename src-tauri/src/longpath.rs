@@ -0,0 +1,165 @@
+// Windows path forms: verbatim (\\?\) prefixes for paths beyond MAX_PATH and
+// UNC shares. Everywhere else these are no-ops.
+
+use std::path::{Path, PathBuf};
+
+/// Longest path, including the terminating NUL, that Win32 and shell APIs
+/// accept without a verbatim prefix
+const MAX_PATH: usize = 260;
+
+const VERBATIM_PREFIX: &str = r"\\?\";
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// `path` without its verbatim prefix (`\\?\C:\x` becomes `C:\x`,
+/// `\\?\UNC\server\share` becomes `\\server\share`), or None when it has none
+/// or the plain form would exceed MAX_PATH
+#[cfg_attr(not(windows), allow(dead_code))]
+fn strip_verbatim(path: &str) -> Option<String> {
+    let plain = if let Some(unc) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        format!(r"\\{}", unc)
+    } else {
+        let rest = path.strip_prefix(VERBATIM_PREFIX)?;
+        // Only drive paths; other verbatim forms such as volume GUIDs stay
+        let bytes = rest.as_bytes();
+        if bytes.len() < 2 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' {
+            return None;
+        }
+        rest.to_string()
+    };
+    (plain.encode_utf16().count() < MAX_PATH).then_some(plain)
+}
+
+/// Verbatim form of an absolute drive or UNC path, which lifts the MAX_PATH
+/// limit of Win32 file APIs. Verbatim paths are not normalized, so forward
+/// slashes are converted here. Other paths are returned unchanged.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn add_verbatim(path: &str) -> String {
+    if path.starts_with(VERBATIM_PREFIX) {
+        return path.to_string();
+    }
+    let path = path.replace('/', r"\");
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return format!("{}{}", VERBATIM_UNC_PREFIX, unc);
+    }
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+        return format!("{}{}", VERBATIM_PREFIX, path);
+    }
+    path
+}
+
+/// Drop the verbatim prefix `fs::canonicalize` adds on Windows when the path
+/// works without it, so stored and displayed paths keep their familiar form
+pub fn simplify(path: PathBuf) -> PathBuf {
+    #[cfg(windows)]
+    if let Some(plain) = path.to_str().and_then(strip_verbatim) {
+        return PathBuf::from(plain);
+    }
+    path
+}
+
+/// NUL-terminated UTF-16 path for Win32 file APIs such as CreateFileW, with a
+/// verbatim prefix so deep paths and UNC shares open
+#[cfg(windows)]
+pub fn file_api_wide(path: &Path) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+
+    match path.to_str() {
+        Some(path) => add_verbatim(path).encode_utf16().chain(Some(0)).collect(),
+        None => path.as_os_str().encode_wide().chain(Some(0)).collect(),
+    }
+}
+
+/// NUL-terminated UTF-16 path for shell APIs, which reject verbatim paths.
+/// Paths beyond MAX_PATH are passed in their 8.3 short form when the volume
+/// provides one.
+#[cfg(windows)]
+pub fn shell_wide(path: &Path) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+
+    let plain: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    if plain.len() <= MAX_PATH {
+        return plain;
+    }
+    match short_path(path) {
+        Some(short) => short.encode_utf16().chain(Some(0)).collect(),
+        None => {
+            log::warn!("No short form for long path {}", path.display());
+            plain
+        }
+    }
+}
+
+/// Directory usable as a child process's working directory, which Windows
+/// limits to MAX_PATH
+#[cfg(windows)]
+pub fn process_dir(path: &Path) -> PathBuf {
+    if path.as_os_str().len() < MAX_PATH {
+        return path.to_path_buf();
+    }
+    short_path(path).map_or_else(|| path.to_path_buf(), PathBuf::from)
+}
+
+#[cfg(not(windows))]
+pub fn process_dir(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+#[cfg(windows)]
+fn short_path(path: &Path) -> Option<String> {
+    use windows_sys::Win32::Storage::FileSystem::GetShortPathNameW;
+
+    let long = file_api_wide(path);
+    // SAFETY: `long` is NUL-terminated; a null buffer queries the needed length
+    let needed = unsafe { GetShortPathNameW(long.as_ptr(), std::ptr::null_mut(), 0) };
+    if needed == 0 {
+        return None;
+    }
+    let mut buffer = vec![0u16; needed as usize];
+    // SAFETY: `buffer` holds `needed` UTF-16 units as the call requires
+    let written = unsafe { GetShortPathNameW(long.as_ptr(), buffer.as_mut_ptr(), needed) };
+    if written == 0 || written >= needed {
+        return None;
+    }
+    let short = String::from_utf16(&buffer[..written as usize]).ok()?;
+    strip_verbatim(&short)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_verbatim() {
+        assert_eq!(
+            strip_verbatim(r"\\?\C:\Users"),
+            Some(r"C:\Users".to_string())
+        );
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\dir"),
+            Some(r"\\server\share\dir".to_string())
+        );
+        assert_eq!(strip_verbatim(r"C:\Users"), None);
+        assert_eq!(strip_verbatim(r"\\?\Volume{1234}\dir"), None);
+
+        // Too long to work without the prefix
+        let deep = format!(r"\\?\C:\{}", "node_modules\\".repeat(30));
+        assert_eq!(strip_verbatim(&deep), None);
+    }
+
+    #[test]
+    fn test_add_verbatim() {
+        assert_eq!(add_verbatim(r"C:\Users"), r"\\?\C:\Users");
+        assert_eq!(add_verbatim("C:/Users/me"), r"\\?\C:\Users\me");
+        assert_eq!(add_verbatim(r"\\server\share"), r"\\?\UNC\server\share");
+        assert_eq!(add_verbatim(r"\\?\C:\Users"), r"\\?\C:\Users");
+        assert_eq!(add_verbatim("relative"), "relative");
+    }
+
+    #[test]
+    fn test_simplify_keeps_plain_paths() {
+        let path = PathBuf::from("/home/user/docs");
+        assert_eq!(simplify(path.clone()), path);
+        assert_eq!(process_dir(&path), path);
+    }
+}
@@ -0,0 +1,291 @@
+// Color labels, star ratings and notes users give files. They live in a sled DB of
+// their own, keyed by path, so they outlive index rebuilds and profile
+// switches, and are copied onto entities whenever those are indexed.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Folder of the marks DB inside the app data directory
+const MARKS_DB: &str = "marks";
+
+/// Highest star rating
+pub const MAX_RATING: u8 = 5;
+
+/// Longest note, in characters
+pub const MAX_NOTE_CHARS: usize = 4096;
+
+/// Finder's label colors
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl ColorLabel {
+    const ALL: [ColorLabel; 7] = [
+        ColorLabel::Red,
+        ColorLabel::Orange,
+        ColorLabel::Yellow,
+        ColorLabel::Green,
+        ColorLabel::Blue,
+        ColorLabel::Purple,
+        ColorLabel::Gray,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColorLabel::Red => "red",
+            ColorLabel::Orange => "orange",
+            ColorLabel::Yellow => "yellow",
+            ColorLabel::Green => "green",
+            ColorLabel::Blue => "blue",
+            ColorLabel::Purple => "purple",
+            ColorLabel::Gray => "gray",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ColorLabel> {
+        Self::ALL
+            .into_iter()
+            .find(|label| label.as_str().eq_ignore_ascii_case(value))
+    }
+
+    /// Index of the color in the Finder flags
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn finder_index(self) -> u8 {
+        match self {
+            ColorLabel::Gray => 1,
+            ColorLabel::Green => 2,
+            ColorLabel::Purple => 3,
+            ColorLabel::Blue => 4,
+            ColorLabel::Yellow => 5,
+            ColorLabel::Red => 6,
+            ColorLabel::Orange => 7,
+        }
+    }
+}
+
+/// Everything a user attached to one path. Stored as JSON so new kinds of
+/// marks can be added without migrating the DB.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FileMarks {
+    #[serde(default)]
+    pub label: Option<ColorLabel>,
+    /// 1 to MAX_RATING stars
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// Free text describing the path, found by full-text search
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl FileMarks {
+    fn is_empty(&self) -> bool {
+        *self == FileMarks::default()
+    }
+
+    /// Copy the marks onto an entity's attributes
+    pub fn apply_to(&self, attributes: &mut crate::FileAttributes) {
+        attributes.label = self.label;
+        attributes.rating = self.rating;
+        attributes.note = self.note.clone();
+    }
+}
+
+#[derive(Clone)]
+pub struct MarkStore {
+    db: sled::Db,
+}
+
+impl MarkStore {
+    /// Open the marks DB in `app_dir`, creating it on first use
+    pub fn open(app_dir: &Path) -> Result<MarkStore, sled::Error> {
+        Ok(MarkStore {
+            db: sled::open(app_dir.join(MARKS_DB))?,
+        })
+    }
+
+    /// Marks of `path`; empty when it has none
+    pub fn get(&self, path: &str) -> Result<FileMarks, sled::Error> {
+        let Some(value) = self.db.get(path.as_bytes())? else {
+            return Ok(FileMarks::default());
+        };
+        serde_json::from_slice(&value)
+            .map_err(|e| sled::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Replace the marks of `path`, dropping its entry once all are cleared
+    pub fn set(&self, path: &str, marks: &FileMarks) -> Result<(), sled::Error> {
+        if marks.is_empty() {
+            self.db.remove(path.as_bytes())?;
+        } else {
+            let value = serde_json::to_vec(marks)
+                .map_err(|e| sled::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+            self.db.insert(path.as_bytes(), value)?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Copy stored marks onto `entities` about to be indexed. Unreadable
+    /// marks are logged and left off rather than failing the indexing.
+    pub fn apply(&self, entities: &mut [crate::FileEntity]) {
+        if self.db.is_empty() {
+            return;
+        }
+        for entity in entities {
+            match self.get(&entity.path) {
+                Ok(marks) => marks.apply_to(&mut entity.attributes),
+                Err(e) => log::warn!("Failed to read marks of {}: {}", entity.path, e),
+            }
+        }
+    }
+
+    /// Move the marks of `from` and everything below it to `to`, following
+    /// a rename
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), sled::Error> {
+        let mut batch = sled::Batch::default();
+        for item in self.subtree(from) {
+            let (key, value) = item?;
+            let path = String::from_utf8_lossy(&key);
+            batch.insert(format!("{}{}", to, &path[from.len()..]).as_bytes(), value);
+            batch.remove(key);
+        }
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Drop the marks of `path` and everything below it, once deleted
+    pub fn remove(&self, path: &str) -> Result<(), sled::Error> {
+        let mut batch = sled::Batch::default();
+        for item in self.subtree(path) {
+            batch.remove(item?.0);
+        }
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Stored marks of `path` and of the paths below it
+    fn subtree<'a>(
+        &self,
+        path: &'a str,
+    ) -> impl Iterator<Item = Result<(sled::IVec, sled::IVec), sled::Error>> + 'a {
+        let child_prefix = format!("{}{}", path, std::path::MAIN_SEPARATOR);
+        self.db
+            .scan_prefix(path.as_bytes())
+            .filter(move |item| match item {
+                Ok((key, _)) => {
+                    let key = String::from_utf8_lossy(key);
+                    key == path || key.starts_with(&child_prefix)
+                }
+                Err(_) => true,
+            })
+    }
+}
+
+/// Show `label` as the Finder label of `path`, or clear it, through the
+/// color bits of the com.apple.FinderInfo attribute
+#[cfg(target_os = "macos")]
+pub fn set_finder_label(path: &Path, label: Option<ColorLabel>) -> std::io::Result<()> {
+    const FINDER_INFO: &[u8] = b"com.apple.FinderInfo";
+    let mut info = crate::xattr::get(path, FINDER_INFO)?.unwrap_or_default();
+    info.resize(32, 0);
+    info[9] = finder_flags(info[9], label);
+    if info.iter().all(|&b| b == 0) {
+        crate::xattr::remove(path, FINDER_INFO).map(|_| ())
+    } else {
+        crate::xattr::set(path, FINDER_INFO, &info)
+    }
+}
+
+/// Finder labels exist on macOS only
+#[cfg(not(target_os = "macos"))]
+pub fn set_finder_label(_path: &Path, _label: Option<ColorLabel>) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Low byte of the big-endian Finder flags with the label color, bits 1-3,
+/// replaced by `label`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn finder_flags(flags: u8, label: Option<ColorLabel>) -> u8 {
+    (flags & !0x0E) | (label.map_or(0, ColorLabel::finder_index) << 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_round_trip_and_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MarkStore::open(dir.path()).unwrap();
+        let sep = std::path::MAIN_SEPARATOR;
+        let path = |parts: &[&str]| format!("{}{}", sep, parts.join(&sep.to_string()));
+        let starred = FileMarks {
+            label: Some(ColorLabel::Red),
+            rating: Some(4),
+            note: Some("Sunset over the bay".to_string()),
+        };
+        store.set(&path(&["album", "a.jpg"]), &starred).unwrap();
+        store
+            .set(&path(&["album2", "b.jpg"]), &starred.clone())
+            .unwrap();
+        assert_eq!(store.get(&path(&["album", "a.jpg"])).unwrap(), starred);
+        assert!(store.get(&path(&["other"])).unwrap().is_empty());
+
+        store.rename(&path(&["album"]), &path(&["trip"])).unwrap();
+        assert!(store.get(&path(&["album", "a.jpg"])).unwrap().is_empty());
+        assert_eq!(store.get(&path(&["trip", "a.jpg"])).unwrap(), starred);
+        assert_eq!(
+            store.get(&path(&["album2", "b.jpg"])).unwrap(),
+            starred,
+            "Siblings sharing the prefix stay"
+        );
+
+        let mut entities = vec![crate::FileEntity {
+            id: "1".to_string(),
+            name: "a.jpg".to_string(),
+            path: path(&["trip", "a.jpg"]),
+            size: 0,
+            modified: 0,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        }];
+        store.apply(&mut entities);
+        assert_eq!(entities[0].attributes.label, Some(ColorLabel::Red));
+        assert_eq!(entities[0].attributes.rating, Some(4));
+        assert_eq!(
+            entities[0].attributes.note.as_deref(),
+            Some("Sunset over the bay")
+        );
+
+        store
+            .set(&path(&["trip", "a.jpg"]), &FileMarks::default())
+            .unwrap();
+        assert!(store.get(&path(&["trip", "a.jpg"])).unwrap().is_empty());
+
+        store.remove(&path(&["album"])).unwrap();
+        assert_eq!(store.get(&path(&["album2", "b.jpg"])).unwrap(), starred);
+        store.remove(&path(&["album2"])).unwrap();
+        assert!(store.get(&path(&["album2", "b.jpg"])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_finder_flags() {
+        assert_eq!(finder_flags(0, Some(ColorLabel::Red)), 0x0C);
+        assert_eq!(finder_flags(0x0C | 0x01, Some(ColorLabel::Gray)), 0x03);
+        assert_eq!(finder_flags(0xFF, None), 0xF1);
+        assert_eq!(ColorLabel::parse("Purple"), Some(ColorLabel::Purple));
+    }
+}
@@ -0,0 +1,517 @@
+// MCP (Model Context Protocol) server over localhost TCP
+//
+// Speaks newline-delimited JSON-RPC 2.0 and exposes the index through three
+// tools: `search`, `list` and `stat`.
+
+use crate::privacy::{self, Audience};
+use crate::search;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+/// Longest request read, so a client can't make the server buffer without end
+const MAX_LINE_BYTES: u64 = 64 * 1024;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Request handler shared by every connection. Connections run on plain
+/// threads, so state is read with `blocking_read`.
+#[derive(Clone)]
+pub struct McpServer {
+    search_index: Arc<RwLock<Option<Arc<crate::search::SearchIndex>>>>,
+    index_manager: Arc<RwLock<Option<crate::index::IndexManager>>>,
+}
+
+impl McpServer {
+    pub fn new(
+        search_index: Arc<RwLock<Option<Arc<crate::search::SearchIndex>>>>,
+        index_manager: Arc<RwLock<Option<crate::index::IndexManager>>>,
+    ) -> Self {
+        McpServer {
+            search_index,
+            index_manager,
+        }
+    }
+
+    /// Handle one JSON-RPC message; returns `None` for notifications
+    pub fn handle_message(&self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    &format!("Parse error: {}", e),
+                ))
+            }
+        };
+
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(|m| m.as_str()) {
+            Some(m) => m,
+            None => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    INVALID_REQUEST,
+                    "Missing method",
+                ))
+            }
+        };
+
+        // Notifications carry no id and never get a response
+        let id = id?;
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        log::debug!("MCP request: method={}", method);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": "cross-everything",
+                    "version": env!("CARGO_PKG_VERSION")
+                }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        let args = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let outcome = match name {
+            "search" => self.tool_search(&args),
+            "list" => self.tool_list(&args),
+            "stat" => self.tool_stat(&args),
+            _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+
+        // Tool failures are reported in-band so the assistant can see them
+        Ok(match outcome {
+            Ok(value) => json!({
+                "content": [{ "type": "text", "text": value.to_string() }],
+                "isError": false
+            }),
+            Err(message) => json!({
+                "content": [{ "type": "text", "text": message }],
+                "isError": true
+            }),
+        })
+    }
+
+    fn tool_search(&self, args: &Value) -> Result<Value, String> {
+        let query = required_str(args, "query")?;
+        let use_regex = args
+            .get("use_regex")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|l| l as usize)
+            .unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+        if use_regex {
+            regex::Regex::new(query).map_err(|_| "INVALID_REGEX".to_string())?;
+        }
+
+        // A snapshot, so the lock is not held during the search
+        let search_index = self
+            .search_index
+            .blocking_read()
+            .clone()
+            .ok_or("INDEX_NOT_READY")?;
+        // Private entries stay hidden from clients even while unlocked in the app
+        let filter = privacy::restrict(&Default::default(), Audience::External);
+        let cancel = search::CancelToken::with_timeout(search::DEFAULT_SEARCH_TIMEOUT);
+        let mut results = search_index
+            .search_filtered(query, use_regex, limit, &filter, &cancel)
+            .and_then(|docs| search_index.to_results(&docs))
+            .map_err(|e| {
+                if cancel.is_expired() {
+                    "TIMED_OUT".to_string()
+                } else {
+                    format!("Search failed: {}", e)
+                }
+            })?;
+        privacy::redact(&mut results, Audience::External);
+
+        Ok(json!({ "results": results, "total_found": results.len() }))
+    }
+
+    fn tool_list(&self, args: &Value) -> Result<Value, String> {
+        let path = required_str(args, "path")?;
+        let guard = self.index_manager.blocking_read();
+        let index_manager = guard.as_ref().ok_or("INDEX_NOT_READY")?;
+        let mut children = index_manager
+            .list_children(Path::new(path))
+            .map_err(|e| format!("Failed to list directory: {}", e))?;
+        privacy::redact_by(&mut children, Audience::External, |entity| &entity.path);
+
+        Ok(json!({ "path": path, "entries": children }))
+    }
+
+    fn tool_stat(&self, args: &Value) -> Result<Value, String> {
+        let path = required_str(args, "path")?;
+        let guard = self.index_manager.blocking_read();
+        let index_manager = guard.as_ref().ok_or("INDEX_NOT_READY")?;
+        let entity = index_manager
+            .get_file_entity_by_path(path)
+            .map_err(|e| format!("Failed to read index: {}", e))?
+            .filter(|entity| !privacy::is_hidden(&entity.path, Audience::External))
+            .ok_or_else(|| format!("Path is not indexed: {}", path))?;
+
+        Ok(json!({
+            "name": entity.name,
+            "path": entity.path,
+            "size": entity.size,
+            "modified": crate::format_timestamp_iso8601(entity.modified),
+            "is_folder": entity.is_folder
+        }))
+    }
+}
+
+fn required_str<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("Missing required argument: {}", key))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search",
+            "description": "Search indexed files and folders by name or path",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Search terms or regex pattern" },
+                    "use_regex": { "type": "boolean", "description": "Treat query as a regex on file names" },
+                    "limit": { "type": "integer", "description": "Maximum number of results" }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "list",
+            "description": "List indexed entries directly inside a folder",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Absolute folder path" }
+                },
+                "required": ["path"]
+            }
+        },
+        {
+            "name": "stat",
+            "description": "Get indexed metadata for a single path",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Absolute file or folder path" }
+                },
+                "required": ["path"]
+            }
+        }
+    ])
+}
+
+/// Running localhost listener; dropping the handle does not stop it, call `stop`
+pub struct McpHandle {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl McpHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        log::info!("MCP server on {} stopped", self.addr);
+    }
+}
+
+/// Bind to 127.0.0.1:`port` (0 picks a free port) and serve in a background thread
+pub fn start(server: McpServer, port: u16) -> std::io::Result<McpHandle> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let shutdown = Arc::new(AtomicBool::new(false));
+
+    let thread_shutdown = shutdown.clone();
+    let thread = thread::spawn(move || {
+        log::info!("MCP server listening on {}", addr);
+        while !thread_shutdown.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    log::info!("MCP client connected from {}", peer);
+                    let server = server.clone();
+                    let conn_shutdown = thread_shutdown.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = serve_connection(&server, stream, &conn_shutdown) {
+                            log::warn!("MCP connection from {} closed with error: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    log::error!("MCP accept failed: {}", e);
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+        }
+    });
+
+    Ok(McpHandle {
+        addr,
+        shutdown,
+        thread: Some(thread),
+    })
+}
+
+fn serve_connection(
+    server: &McpServer,
+    stream: TcpStream,
+    shutdown: &AtomicBool,
+) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let room = MAX_LINE_BYTES.saturating_sub(line.len() as u64);
+        match reader.by_ref().take(room).read_line(&mut line) {
+            Ok(_) if !line.ends_with('\n') => {
+                if line.len() as u64 >= MAX_LINE_BYTES {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Request too long",
+                    ));
+                }
+                // The stream ended, between requests or partway through one
+                break;
+            }
+            Ok(_) => {
+                if !line.trim().is_empty() {
+                    if let Some(response) = server.handle_message(line.trim()) {
+                        writeln!(writer, "{}", response)?;
+                        writer.flush()?;
+                    }
+                }
+                line.clear();
+            }
+            // Read timeout: keep any partial line and re-check the shutdown flag
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::IndexManager;
+    use crate::search::SearchIndex;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    fn empty_server() -> McpServer {
+        McpServer::new(Arc::new(RwLock::new(None)), Arc::new(RwLock::new(None)))
+    }
+
+    fn populated_server(root: &Path) -> McpServer {
+        File::create(root.join("alpha.txt")).unwrap();
+        std::fs::create_dir(root.join("nested")).unwrap();
+        File::create(root.join("nested").join("beta.txt")).unwrap();
+
+        let index_manager = IndexManager::new(&root.join(".db")).unwrap();
+        let search_index = SearchIndex::new(&root.join(".search")).unwrap();
+        let fields = search_index.fields().unwrap();
+        let mut writer = search_index.writer().unwrap();
+        for entity in index_manager.traverse_directory(root).unwrap() {
+            if entity.path.contains(".db") || entity.path.contains(".search") {
+                continue;
+            }
+            index_manager.save_file_entity(&entity).unwrap();
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        McpServer::new(
+            Arc::new(RwLock::new(Some(Arc::new(search_index)))),
+            Arc::new(RwLock::new(Some(index_manager))),
+        )
+    }
+
+    fn call(server: &McpServer, tool: &str, args: Value) -> Value {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": tool, "arguments": args }
+        });
+        server.handle_message(&request.to_string()).unwrap()["result"].clone()
+    }
+
+    #[test]
+    fn test_initialize() {
+        let server = empty_server();
+        let response = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+            .unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(response["result"]["serverInfo"]["name"], "cross-everything");
+    }
+
+    #[test]
+    fn test_notification_has_no_response() {
+        let server = empty_server();
+        let response =
+            server.handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#);
+        assert!(response.is_none(), "Notifications should not be answered");
+    }
+
+    #[test]
+    fn test_parse_error_and_unknown_method() {
+        let server = empty_server();
+        let response = server.handle_message("not json").unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let response = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":7,"method":"nope"}"#)
+            .unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 7);
+    }
+
+    #[test]
+    fn test_tools_list() {
+        let server = empty_server();
+        let response = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+            .unwrap();
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["search", "list", "stat"]);
+    }
+
+    #[test]
+    fn test_search_without_index_reports_tool_error() {
+        let server = empty_server();
+        let result = call(&server, "search", json!({ "query": "alpha" }));
+        assert_eq!(result["isError"], true);
+        assert_eq!(result["content"][0]["text"], "INDEX_NOT_READY");
+    }
+
+    #[test]
+    fn test_search_list_and_stat_tools() {
+        let temp_dir = tempdir().unwrap();
+        let server = populated_server(temp_dir.path());
+
+        let result = call(&server, "search", json!({ "query": "alpha" }));
+        assert_eq!(result["isError"], false);
+        let payload: Value =
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(payload["results"][0]["name"], "alpha.txt");
+
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let result = call(&server, "list", json!({ "path": root }));
+        let payload: Value =
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        let names: Vec<&str> = payload["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["alpha.txt", "nested"]);
+
+        let nested = temp_dir.path().join("nested").to_string_lossy().to_string();
+        let result = call(&server, "stat", json!({ "path": nested }));
+        let payload: Value =
+            serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(payload["is_folder"], true);
+
+        let result = call(
+            &server,
+            "stat",
+            json!({ "path": "/definitely/not/indexed" }),
+        );
+        assert_eq!(result["isError"], true);
+    }
+
+    #[test]
+    fn test_tcp_round_trip() {
+        let handle = start(empty_server(), 0).unwrap();
+        let stream = TcpStream::connect(handle.addr()).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        writeln!(writer, r#"{{"jsonrpc":"2.0","id":3,"method":"ping"}}"#).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 3);
+        assert_eq!(response["result"], json!({}));
+
+        // A request past the cap closes the connection instead of growing
+        let _ = writer.write_all(&vec![b'x'; MAX_LINE_BYTES as usize + 1]);
+        line.clear();
+        assert!(matches!(reader.read_line(&mut line), Ok(0) | Err(_)));
+
+        handle.stop();
+    }
+}
@@ -0,0 +1,127 @@
+// Rolling performance statistics for get_metrics. They stay in memory and
+// are never sent anywhere.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Samples kept per statistic; older ones are dropped
+const SAMPLE_LIMIT: usize = 1000;
+
+/// The most recent `SAMPLE_LIMIT` durations of one kind
+#[derive(Debug, Default)]
+pub struct LatencyWindow {
+    samples: VecDeque<Duration>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub avg_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyWindow {
+    pub fn record(&mut self, duration: Duration) {
+        if self.samples.len() == SAMPLE_LIMIT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    /// Nearest-rank percentiles over the kept samples; all zero when empty
+    pub fn summary(&self) -> LatencySummary {
+        if self.samples.is_empty() {
+            return LatencySummary::default();
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let percentile = |p: f64| {
+            let rank = (p * sorted.len() as f64).ceil() as usize;
+            ms(sorted[rank.clamp(1, sorted.len()) - 1])
+        };
+        let total: Duration = sorted.iter().sum();
+        LatencySummary {
+            count: sorted.len(),
+            avg_ms: ms(total) / sorted.len() as f64,
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: ms(sorted[sorted.len() - 1]),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Time for search_files to answer, cache hits included
+    pub search_latency: LatencyWindow,
+    /// Time from the first watcher event of a batch until the index held it
+    pub update_lag: LatencyWindow,
+}
+
+/// Output of get_metrics
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MetricsReport {
+    pub search_latency: LatencySummary,
+    pub update_lag: LatencySummary,
+    /// Changes waiting in the watcher's debouncer
+    pub watcher_queue_depth: usize,
+    pub cached_searches: usize,
+    /// Estimated memory held by cached results
+    pub cache_bytes: usize,
+}
+
+impl Metrics {
+    pub fn report(
+        &self,
+        watcher_queue_depth: usize,
+        (cached_searches, cache_bytes): (usize, usize),
+    ) -> MetricsReport {
+        MetricsReport {
+            search_latency: self.search_latency.summary(),
+            update_lag: self.update_lag.summary(),
+            watcher_queue_depth,
+            cached_searches,
+            cache_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_summary() {
+        let mut window = LatencyWindow::default();
+        assert_eq!(window.summary(), LatencySummary::default());
+
+        for ms in 1..=100 {
+            window.record(Duration::from_millis(ms));
+        }
+        let summary = window.summary();
+        assert_eq!(summary.count, 100);
+        assert!((summary.avg_ms - 50.5).abs() < 1e-9);
+        assert_eq!(summary.p50_ms, 50.0);
+        assert_eq!(summary.p95_ms, 95.0);
+        assert_eq!(summary.p99_ms, 99.0);
+        assert_eq!(summary.max_ms, 100.0);
+    }
+
+    #[test]
+    fn test_latency_window_keeps_recent_samples() {
+        let mut window = LatencyWindow::default();
+        window.record(Duration::from_secs(60));
+        for _ in 0..SAMPLE_LIMIT {
+            window.record(Duration::from_millis(2));
+        }
+        let summary = window.summary();
+        assert_eq!(summary.count, SAMPLE_LIMIT);
+        assert_eq!(summary.max_ms, 2.0, "The oldest sample was dropped");
+    }
+}
@@ -0,0 +1,118 @@
+// Text in images, read by an OCR command the user sets up, such as
+// `tesseract %path -`, so scans and screenshots are found by their words.
+// Off until a command is set. Images are queued as they are indexed and read
+// one at a time in the background; the text is kept with the entry in sled
+// and searched along with its name and note. A full rebuild reads them again.
+
+use std::path::Path;
+use std::sync::RwLock;
+
+/// Extensions of the images given to the command
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "tif", "tiff", "bmp", "webp"];
+/// Larger images are left alone
+const MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024;
+/// Most characters of text kept for one image
+pub const MAX_TEXT_CHARS: usize = 10_000;
+
+static COMMAND: RwLock<Option<String>> = RwLock::new(None);
+
+/// The command is a template like those of actions, so it must parse
+pub fn validate(command: Option<&str>) -> Result<(), String> {
+    match command {
+        Some(command) => crate::actions::split_command(command)
+            .map(|_| ())
+            .map_err(|e| format!("OCR command: {}", e)),
+        None => Ok(()),
+    }
+}
+
+/// Read images with `command` from now on; None turns OCR off
+pub fn set_command(command: Option<&str>) {
+    *COMMAND.write().unwrap_or_else(|e| e.into_inner()) = command.map(str::to_string);
+}
+
+pub fn command() -> Option<String> {
+    COMMAND.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Whether `entity` is an image whose text has not been read yet. Cloud
+/// placeholders are skipped, as reading them would download them.
+pub fn wants(entity: &crate::FileEntity) -> bool {
+    !entity.is_folder
+        && entity.attributes.ocr_text.is_none()
+        && !entity.attributes.online_only
+        && entity.size <= MAX_IMAGE_BYTES
+        && Path::new(&entity.name)
+            .extension()
+            .is_some_and(|extension| {
+                let extension = extension.to_string_lossy();
+                IMAGE_EXTENSIONS
+                    .iter()
+                    .any(|image| extension.eq_ignore_ascii_case(image))
+            })
+}
+
+/// Run `command` on the image at `path` and return what it printed, with
+/// runs of whitespace made single spaces
+pub fn recognize(command: &str, path: &Path) -> Result<String, String> {
+    let command_line = crate::actions::command_line(command, path)?;
+    let output = std::process::Command::new(&command_line[0])
+        .args(&command_line[1..])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", command_line[0], e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", command_line[0], output.status));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    Ok(text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TEXT_CHARS)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(name: &str) -> crate::FileEntity {
+        crate::FileEntity {
+            id: name.to_string(),
+            name: name.to_string(),
+            path: format!("/scans/{}", name),
+            size: 1024,
+            modified: 0,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        }
+    }
+
+    #[test]
+    fn test_wants_images_without_text() {
+        assert!(wants(&image("receipt.PNG")));
+        assert!(!wants(&image("receipt.pdf")));
+        let mut read = image("receipt.jpg");
+        read.attributes.ocr_text = Some("total 12.50".to_string());
+        assert!(!wants(&read));
+        let mut placeholder = image("receipt.jpg");
+        placeholder.attributes.online_only = true;
+        assert!(!wants(&placeholder));
+
+        assert!(validate(Some("tesseract %path -")).is_ok());
+        assert!(validate(Some("tesseract '%path")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recognize_collapses_whitespace() {
+        let text = recognize("echo 'Invoice   No.\t42' %name", Path::new("/scans/a.png")).unwrap();
+        assert_eq!(text, "Invoice No. 42 a.png");
+        assert!(recognize("false", Path::new("/scans/a.png")).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+// First-run setup: the steps a new user goes through before the first search,
+// kept in a sled DB of their own so the flow resumes where it was left after
+// a restart. Each step only advances once what it asks for is true.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Folder of the onboarding DB inside the app data directory
+const ONBOARDING_DB: &str = "onboarding";
+const STATE_KEY: &[u8] = b"state";
+
+/// Steps in the order they are taken
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    #[default]
+    Welcome,
+    /// Grant access to protected folders (macOS)
+    Permissions,
+    /// Choose the folders to index
+    Roots,
+    /// Wait for the first index build
+    Indexing,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// Go on once the current step's condition holds
+    Next,
+    /// Go on without it; only the permissions step can be skipped
+    Skip,
+    /// Start over from the welcome step
+    Reset,
+}
+
+/// What the steps wait for, as it is now
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Facts {
+    /// No protected location is out of reach
+    pub permissions_granted: bool,
+    /// Roots of the active profile
+    pub roots: Vec<String>,
+    /// An index has been built or loaded
+    pub index_built: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OnboardingState {
+    pub step: Step,
+    /// Access was granted when the permissions step was left; false when
+    /// skipped
+    pub permissions_granted: bool,
+    /// Roots chosen when the roots step was left
+    pub roots: Vec<String>,
+    pub index_built: bool,
+    /// When the state last changed, in Unix seconds
+    pub updated: i64,
+}
+
+impl OnboardingState {
+    /// Apply `event` given `facts`, failing with what is missing when the
+    /// current step can't be left yet
+    pub fn advance(&self, event: Event, facts: &Facts, now: i64) -> Result<Self, String> {
+        let mut next = self.clone();
+        next.updated = now;
+        if event == Event::Reset {
+            return Ok(OnboardingState {
+                updated: now,
+                ..Default::default()
+            });
+        }
+        if event == Event::Skip && self.step != Step::Permissions {
+            return Err(format!("The {:?} step can't be skipped", self.step));
+        }
+        next.step = match self.step {
+            Step::Welcome => Step::Permissions,
+            Step::Permissions => {
+                if event == Event::Next && !facts.permissions_granted {
+                    return Err("Access to some protected folders is still missing".to_string());
+                }
+                next.permissions_granted = event == Event::Next;
+                Step::Roots
+            }
+            Step::Roots => {
+                if facts.roots.is_empty() {
+                    return Err("Choose at least one folder to index".to_string());
+                }
+                next.roots = facts.roots.clone();
+                Step::Indexing
+            }
+            Step::Indexing => {
+                if !facts.index_built {
+                    return Err("The first index is not built yet".to_string());
+                }
+                next.index_built = true;
+                Step::Done
+            }
+            Step::Done => return Ok(self.clone()),
+        };
+        Ok(next)
+    }
+}
+
+/// Persistent onboarding state
+#[derive(Clone)]
+pub struct OnboardingStore {
+    db: sled::Db,
+}
+
+impl OnboardingStore {
+    /// Open the onboarding DB in `app_dir`, creating it on first use
+    pub fn open(app_dir: &Path) -> Result<OnboardingStore, sled::Error> {
+        Ok(OnboardingStore {
+            db: sled::open(app_dir.join(ONBOARDING_DB))?,
+        })
+    }
+
+    /// The state saved last, or the first step when there is none or it
+    /// can't be read
+    pub fn load(&self) -> Result<OnboardingState, sled::Error> {
+        let Some(value) = self.db.get(STATE_KEY)? else {
+            return Ok(OnboardingState::default());
+        };
+        Ok(serde_json::from_slice(&value).unwrap_or_else(|e| {
+            log::warn!("Ignoring malformed onboarding state: {}", e);
+            OnboardingState::default()
+        }))
+    }
+
+    pub fn save(&self, state: &OnboardingState) -> Result<(), sled::Error> {
+        let value = serde_json::to_vec(state).map_err(|e| {
+            sled::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })?;
+        self.db.insert(STATE_KEY, value)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_steps_advance_only_when_met() {
+        let mut facts = Facts::default();
+        let state = OnboardingState::default();
+        let state = state.advance(Event::Next, &facts, 1).unwrap();
+        assert_eq!(state.step, Step::Permissions);
+        assert!(state.advance(Event::Next, &facts, 2).is_err());
+        let state = state.advance(Event::Skip, &facts, 2).unwrap();
+        assert_eq!(
+            (state.step, state.permissions_granted),
+            (Step::Roots, false)
+        );
+
+        assert!(state.advance(Event::Skip, &facts, 3).is_err());
+        assert!(state.advance(Event::Next, &facts, 3).is_err());
+        facts.roots = vec!["/home/me".to_string()];
+        let state = state.advance(Event::Next, &facts, 3).unwrap();
+        assert_eq!(state.step, Step::Indexing);
+        assert_eq!(state.roots, facts.roots);
+
+        assert!(state.advance(Event::Next, &facts, 4).is_err());
+        facts.index_built = true;
+        let state = state.advance(Event::Next, &facts, 4).unwrap();
+        assert_eq!(state.step, Step::Done);
+        assert_eq!(state.advance(Event::Next, &facts, 5).unwrap(), state);
+
+        let reset = state.advance(Event::Reset, &facts, 6).unwrap();
+        assert_eq!((reset.step, reset.updated), (Step::Welcome, 6));
+    }
+
+    #[test]
+    fn test_state_persists() {
+        let temp_dir = tempdir().unwrap();
+        let store = OnboardingStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.load().unwrap(), OnboardingState::default());
+        let state = OnboardingState {
+            step: Step::Indexing,
+            roots: vec!["/srv".to_string()],
+            updated: 10,
+            ..Default::default()
+        };
+        store.save(&state).unwrap();
+        drop(store);
+        let store = OnboardingStore::open(temp_dir.path()).unwrap();
+        assert_eq!(store.load().unwrap(), state);
+    }
+}
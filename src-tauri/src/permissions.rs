@@ -0,0 +1,220 @@
+// Locations the OS keeps from the app until the user grants access. On macOS
+// these are the TCC-protected folders (Desktop, Documents, Downloads, other
+// volumes) and the data covered only by Full Disk Access.
+
+use crate::error::{AppError, ErrorKind};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The System Settings privacy pane that grants access to a location
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Protection {
+    /// Per-folder consent under Files and Folders
+    FilesAndFolders,
+    /// Removable and network volumes under Files and Folders
+    Volumes,
+    /// Readable only with Full Disk Access
+    FullDiskAccess,
+}
+
+impl Protection {
+    /// Deep link into System Settings
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    pub fn settings_url(self) -> &'static str {
+        match self {
+            Protection::FilesAndFolders | Protection::Volumes => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_FilesAndFolders"
+            }
+            Protection::FullDiskAccess => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_AllFiles"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProtectedLocation {
+    pub path: String,
+    pub protection: Protection,
+    pub settings_url: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PermissionReport {
+    /// None where the platform has no Full Disk Access setting
+    pub full_disk_access: Option<bool>,
+    /// Protected locations the app cannot read
+    pub inaccessible: Vec<ProtectedLocation>,
+}
+
+/// Folders under `home` and volumes under `volumes_dir` that macOS protects,
+/// skipping those that do not exist. The boot volume's entry in /Volumes is
+/// a symlink to `/` and is not protected.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn protected_candidates(home: &Path, volumes_dir: &Path) -> Vec<(PathBuf, Protection)> {
+    let mut candidates: Vec<(PathBuf, Protection)> = ["Desktop", "Documents", "Downloads"]
+        .iter()
+        .map(|name| (home.join(name), Protection::FilesAndFolders))
+        .chain(
+            ["Mail", "Messages", "Safari"]
+                .iter()
+                .map(|name| (home.join("Library").join(name), Protection::FullDiskAccess)),
+        )
+        .filter(|(path, _)| path.symlink_metadata().is_ok())
+        .collect();
+
+    if let Ok(entries) = std::fs::read_dir(volumes_dir) {
+        let mut volumes: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        volumes.sort();
+        candidates.extend(volumes.into_iter().map(|v| (v, Protection::Volumes)));
+    }
+    candidates
+}
+
+/// Whether listing `path` fails for lack of permission. TCC denials surface
+/// as EPERM rather than EACCES, which std maps to the same kind.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn is_denied(path: &Path) -> bool {
+    match std::fs::read_dir(path) {
+        Ok(_) => false,
+        Err(e) => e.kind() == io::ErrorKind::PermissionDenied,
+    }
+}
+
+/// Full Disk Access is the only way to read the user's TCC database, and
+/// probing it shows no consent prompt
+#[cfg(target_os = "macos")]
+fn has_full_disk_access(home: &Path) -> bool {
+    let tcc_db = home.join("Library/Application Support/com.apple.TCC/TCC.db");
+    match std::fs::File::open(tcc_db) {
+        Ok(_) => true,
+        Err(e) => e.kind() != io::ErrorKind::PermissionDenied,
+    }
+}
+
+/// Check which protected locations the app cannot read. Listing a
+/// Files-and-Folders location may show the macOS consent prompt for it.
+#[cfg(target_os = "macos")]
+pub fn check_permissions() -> PermissionReport {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        log::warn!("HOME is not set; cannot check protected locations");
+        return PermissionReport {
+            full_disk_access: None,
+            inaccessible: Vec::new(),
+        };
+    };
+    let inaccessible = protected_candidates(&home, Path::new("/Volumes"))
+        .into_iter()
+        .filter(|(path, _)| is_denied(path))
+        .map(|(path, protection)| ProtectedLocation {
+            path: path.to_string_lossy().to_string(),
+            protection,
+            settings_url: protection.settings_url(),
+        })
+        .collect();
+    PermissionReport {
+        full_disk_access: Some(has_full_disk_access(&home)),
+        inaccessible,
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn check_permissions() -> PermissionReport {
+    PermissionReport {
+        full_disk_access: None,
+        inaccessible: Vec::new(),
+    }
+}
+
+/// Entries of `report` inside any of `roots`, which an index build of those
+/// roots cannot read
+pub fn inaccessible_under<'a>(
+    report: &'a PermissionReport,
+    roots: &[String],
+) -> Vec<&'a ProtectedLocation> {
+    report
+        .inaccessible
+        .iter()
+        .filter(|location| {
+            roots
+                .iter()
+                .any(|root| Path::new(&location.path).starts_with(root))
+        })
+        .collect()
+}
+
+/// Open the System Settings pane that grants `protection`
+#[cfg(target_os = "macos")]
+pub fn open_settings(protection: Protection) -> Result<(), AppError> {
+    let status = std::process::Command::new("open")
+        .arg(protection.settings_url())
+        .status()
+        .map_err(|e| AppError::io("open", &e))?;
+    if !status.success() {
+        return Err(AppError::new(
+            ErrorKind::Internal,
+            format!("Failed to open System Settings: {}", status),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn open_settings(_protection: Protection) -> Result<(), AppError> {
+    Err(AppError::new(
+        ErrorKind::Unsupported,
+        "Privacy settings are only available on macOS",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_protected_candidates() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let volumes = temp_dir.path().join("Volumes");
+        fs::create_dir_all(home.join("Desktop")).unwrap();
+        fs::create_dir_all(home.join("Library/Mail")).unwrap();
+        fs::create_dir_all(volumes.join("USB")).unwrap();
+        fs::write(volumes.join(".DS_Store"), "").unwrap();
+
+        let candidates = protected_candidates(&home, &volumes);
+        assert_eq!(
+            candidates,
+            vec![
+                (home.join("Desktop"), Protection::FilesAndFolders),
+                (home.join("Library/Mail"), Protection::FullDiskAccess),
+                (volumes.join("USB"), Protection::Volumes),
+            ]
+        );
+        assert!(!is_denied(&home.join("Desktop")));
+        assert!(!is_denied(&home.join("Documents")), "Missing is not denied");
+    }
+
+    #[test]
+    fn test_inaccessible_under_roots() {
+        let location = |path: &str| ProtectedLocation {
+            path: path.to_string(),
+            protection: Protection::FilesAndFolders,
+            settings_url: Protection::FilesAndFolders.settings_url(),
+        };
+        let report = PermissionReport {
+            full_disk_access: Some(false),
+            inaccessible: vec![location("/Users/me/Desktop"), location("/Volumes/USB")],
+        };
+        let under = inaccessible_under(&report, &["/Users/me".to_string()]);
+        assert_eq!(under, vec![&report.inaccessible[0]]);
+        assert!(inaccessible_under(&report, &["/Users/other".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,411 @@
+// Native folder picker for choosing index roots: the shell's folder dialog
+// on Windows, `choose folder` on macOS, and zenity or kdialog on Linux. The
+// folders picked are canonicalized and checked before they are offered back.
+
+use crate::error::{AppError, ErrorKind};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Folders picked, ready to add as roots
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PickedDirectories {
+    /// Canonical paths of the readable folders picked, without ones nested
+    /// in another pick; empty when the dialog was cancelled
+    pub paths: Vec<String>,
+    /// Folders left out and overlaps with roots already indexed
+    pub warnings: Vec<String>,
+}
+
+/// Canonicalize `picked`, leaving out folders that can't be listed and ones
+/// inside another pick, and warn where a pick overlaps one of `roots`
+pub fn review(picked: &[PathBuf], roots: &[String]) -> PickedDirectories {
+    let mut warnings = Vec::new();
+    let readable: Vec<String> = picked
+        .iter()
+        .filter(|path| match std::fs::read_dir(path) {
+            Ok(_) => true,
+            Err(e) => {
+                warnings.push(format!("Skipping {}: {}", path.display(), e));
+                false
+            }
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    let (paths, notes) = crate::index::merge_roots(&readable);
+    warnings.extend(notes);
+    for path in &paths {
+        for root in roots {
+            let root_path = Path::new(root);
+            if Path::new(path) == root_path {
+                warnings.push(format!("{} is indexed already", path));
+            } else if Path::new(path).starts_with(root_path) {
+                warnings.push(format!("{} is inside the indexed root {}", path, root));
+            } else if root_path.starts_with(path) {
+                warnings.push(format!(
+                    "{} contains the indexed root {}, which it would replace",
+                    path, root
+                ));
+            }
+        }
+    }
+    PickedDirectories { paths, warnings }
+}
+
+/// Folders in the output of a dialog, one per line
+#[cfg_attr(windows, allow(dead_code))]
+fn parse_selection(output: &[u8]) -> Vec<PathBuf> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.trim_end_matches('/') {
+            "" => PathBuf::from("/"),
+            folder => PathBuf::from(folder),
+        })
+        .collect()
+}
+
+/// Show the folder dialog titled `title` and wait for the user. Returns the
+/// folders picked, none when the dialog is cancelled.
+pub fn pick(title: &str) -> Result<Vec<PathBuf>, AppError> {
+    sys::pick(title)
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::*;
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::core::{GUID, HRESULT};
+    use windows_sys::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, CLSCTX_INPROC_SERVER,
+        COINIT_APARTMENTTHREADED,
+    };
+
+    const CLSID_FILE_OPEN_DIALOG: GUID = GUID::from_u128(0xdc1c5a9c_e88a_4dde_a5a1_60f82a20aef7);
+    const IID_FILE_OPEN_DIALOG: GUID = GUID::from_u128(0xd57c7288_d4ad_4768_be02_9d969532d960);
+    const FOS_PICKFOLDERS: u32 = 0x20;
+    const FOS_FORCEFILESYSTEM: u32 = 0x40;
+    const FOS_ALLOWMULTISELECT: u32 = 0x200;
+    const SIGDN_FILESYSPATH: i32 = 0x8005_8000_u32 as i32;
+    /// HRESULT_FROM_WIN32(ERROR_CANCELLED), returned when the user cancels
+    const CANCELLED: HRESULT = 0x8007_04C7_u32 as i32;
+
+    /// Vtable entry not called here
+    type Slot = usize;
+    type Release = unsafe extern "system" fn(*mut c_void) -> u32;
+
+    /// IFileOpenDialog, with the IUnknown, IModalWindow and IFileDialog
+    /// methods it inherits
+    #[repr(C)]
+    struct FileOpenDialogVtbl {
+        _query_interface: Slot,
+        _add_ref: Slot,
+        release: Release,
+        show: unsafe extern "system" fn(*mut c_void, *mut c_void) -> HRESULT,
+        _set_file_types: Slot,
+        _set_file_type_index: Slot,
+        _get_file_type_index: Slot,
+        _advise: Slot,
+        _unadvise: Slot,
+        set_options: unsafe extern "system" fn(*mut c_void, u32) -> HRESULT,
+        get_options: unsafe extern "system" fn(*mut c_void, *mut u32) -> HRESULT,
+        _set_default_folder: Slot,
+        _set_folder: Slot,
+        _get_folder: Slot,
+        _get_current_selection: Slot,
+        _set_file_name: Slot,
+        _get_file_name: Slot,
+        set_title: unsafe extern "system" fn(*mut c_void, *const u16) -> HRESULT,
+        _set_ok_button_label: Slot,
+        _set_file_name_label: Slot,
+        _get_result: Slot,
+        _add_place: Slot,
+        _set_default_extension: Slot,
+        _close: Slot,
+        _set_client_guid: Slot,
+        _clear_client_data: Slot,
+        _set_filter: Slot,
+        get_results: unsafe extern "system" fn(*mut c_void, *mut *mut c_void) -> HRESULT,
+        _get_selected_items: Slot,
+    }
+
+    #[repr(C)]
+    struct ShellItemArrayVtbl {
+        _query_interface: Slot,
+        _add_ref: Slot,
+        release: Release,
+        _bind_to_handler: Slot,
+        _get_property_store: Slot,
+        _get_property_description_list: Slot,
+        _get_attributes: Slot,
+        get_count: unsafe extern "system" fn(*mut c_void, *mut u32) -> HRESULT,
+        get_item_at: unsafe extern "system" fn(*mut c_void, u32, *mut *mut c_void) -> HRESULT,
+        _enum_items: Slot,
+    }
+
+    #[repr(C)]
+    struct ShellItemVtbl {
+        _query_interface: Slot,
+        _add_ref: Slot,
+        release: Release,
+        _bind_to_handler: Slot,
+        _get_parent: Slot,
+        get_display_name: unsafe extern "system" fn(*mut c_void, i32, *mut *mut u16) -> HRESULT,
+        _get_attributes: Slot,
+        _compare: Slot,
+    }
+
+    /// The vtable of a COM object, its first field
+    ///
+    /// SAFETY: `object` must be a live COM object whose vtable starts like T
+    unsafe fn vtbl<'a, T>(object: *mut c_void) -> &'a T {
+        &**(object as *const *const T)
+    }
+
+    fn check(hr: HRESULT) -> Result<(), AppError> {
+        if hr < 0 {
+            let e = std::io::Error::from_raw_os_error(hr);
+            return Err(AppError::new(
+                ErrorKind::Internal,
+                format!("Folder dialog failed: {}", e),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn pick(title: &str) -> Result<Vec<PathBuf>, AppError> {
+        // The dialog needs a single-threaded apartment, which the calling
+        // thread may have been set up without
+        let title = title.to_string();
+        std::thread::spawn(move || {
+            // SAFETY: COM is initialized and uninitialized on this thread alone
+            unsafe {
+                check(CoInitializeEx(
+                    std::ptr::null(),
+                    COINIT_APARTMENTTHREADED as u32,
+                ))?;
+                let picked = show(&title);
+                CoUninitialize();
+                picked
+            }
+        })
+        .join()
+        .map_err(|_| AppError::new(ErrorKind::Internal, "Folder dialog thread panicked"))?
+    }
+
+    /// SAFETY: COM must be initialized on the calling thread
+    unsafe fn show(title: &str) -> Result<Vec<PathBuf>, AppError> {
+        let mut dialog: *mut c_void = std::ptr::null_mut();
+        check(CoCreateInstance(
+            &CLSID_FILE_OPEN_DIALOG,
+            std::ptr::null_mut(),
+            CLSCTX_INPROC_SERVER,
+            &IID_FILE_OPEN_DIALOG,
+            &mut dialog,
+        ))?;
+        let methods = vtbl::<FileOpenDialogVtbl>(dialog);
+        let picked = run(dialog, methods, title);
+        (methods.release)(dialog);
+        picked
+    }
+
+    /// SAFETY: `dialog` must be a live IFileOpenDialog with `methods`
+    unsafe fn run(
+        dialog: *mut c_void,
+        methods: &FileOpenDialogVtbl,
+        title: &str,
+    ) -> Result<Vec<PathBuf>, AppError> {
+        let mut options = 0u32;
+        check((methods.get_options)(dialog, &mut options))?;
+        check((methods.set_options)(
+            dialog,
+            options | FOS_PICKFOLDERS | FOS_FORCEFILESYSTEM | FOS_ALLOWMULTISELECT,
+        ))?;
+        let title: Vec<u16> = title.encode_utf16().chain(Some(0)).collect();
+        check((methods.set_title)(dialog, title.as_ptr()))?;
+        match (methods.show)(dialog, std::ptr::null_mut()) {
+            CANCELLED => return Ok(Vec::new()),
+            hr => check(hr)?,
+        }
+        let mut items: *mut c_void = std::ptr::null_mut();
+        check((methods.get_results)(dialog, &mut items))?;
+        let array = vtbl::<ShellItemArrayVtbl>(items);
+        let paths = item_paths(items, array);
+        (array.release)(items);
+        paths
+    }
+
+    /// SAFETY: `items` must be a live IShellItemArray with `array`
+    unsafe fn item_paths(
+        items: *mut c_void,
+        array: &ShellItemArrayVtbl,
+    ) -> Result<Vec<PathBuf>, AppError> {
+        let mut count = 0u32;
+        check((array.get_count)(items, &mut count))?;
+        let mut paths = Vec::new();
+        for index in 0..count {
+            let mut item: *mut c_void = std::ptr::null_mut();
+            check((array.get_item_at)(items, index, &mut item))?;
+            let methods = vtbl::<ShellItemVtbl>(item);
+            let mut name: *mut u16 = std::ptr::null_mut();
+            let named = (methods.get_display_name)(item, SIGDN_FILESYSPATH, &mut name);
+            if named >= 0 && !name.is_null() {
+                let len = (0..).take_while(|&i| *name.add(i) != 0).count();
+                let wide = std::slice::from_raw_parts(name, len);
+                paths.push(PathBuf::from(std::ffi::OsString::from_wide(wide)));
+                CoTaskMemFree(name as *const c_void);
+            }
+            (methods.release)(item);
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use super::*;
+
+    pub fn pick(title: &str) -> Result<Vec<PathBuf>, AppError> {
+        let prompt = title.replace('\\', "\\\\").replace('"', "\\\"");
+        let output = std::process::Command::new("osascript")
+            .args([
+                "-e",
+                &format!(
+                    "set picked to choose folder with prompt \"{}\" with multiple selections allowed",
+                    prompt
+                ),
+                "-e",
+                "set out to \"\"",
+                "-e",
+                "repeat with folder_alias in picked",
+                "-e",
+                "set out to out & POSIX path of folder_alias & linefeed",
+                "-e",
+                "end repeat",
+                "-e",
+                "return out",
+            ])
+            .output()
+            .map_err(|e| AppError::io("osascript", &e))?;
+        if output.status.success() {
+            return Ok(parse_selection(&output.stdout));
+        }
+        let error = String::from_utf8_lossy(&output.stderr);
+        // -128 is "User canceled"
+        if error.contains("-128") {
+            return Ok(Vec::new());
+        }
+        Err(AppError::new(
+            ErrorKind::Internal,
+            format!("Folder dialog failed: {}", error.trim()),
+        ))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod sys {
+    use super::*;
+
+    /// Dialog programs to try in order; kdialog picks a single folder
+    fn dialogs(title: &str) -> [(&'static str, Vec<String>); 2] {
+        [
+            (
+                "zenity",
+                vec![
+                    "--file-selection".to_string(),
+                    "--directory".to_string(),
+                    "--multiple".to_string(),
+                    "--separator=\n".to_string(),
+                    format!("--title={}", title),
+                ],
+            ),
+            (
+                "kdialog",
+                vec![
+                    "--getexistingdirectory".to_string(),
+                    std::env::var("HOME").unwrap_or_else(|_| "/".to_string()),
+                    "--title".to_string(),
+                    title.to_string(),
+                ],
+            ),
+        ]
+    }
+
+    pub fn pick(title: &str) -> Result<Vec<PathBuf>, AppError> {
+        for (program, args) in dialogs(title) {
+            let output = match std::process::Command::new(program).args(&args).output() {
+                Ok(output) => output,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(AppError::io(program, &e)),
+            };
+            // Both exit with 1 when cancelled
+            return match output.status.code() {
+                Some(0) => Ok(parse_selection(&output.stdout)),
+                Some(1) => Ok(Vec::new()),
+                _ => Err(AppError::new(
+                    ErrorKind::Internal,
+                    format!(
+                        "{} failed: {}",
+                        program,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ),
+                )),
+            };
+        }
+        Err(AppError::new(
+            ErrorKind::Unsupported,
+            "No folder dialog found; install zenity or kdialog",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(
+            parse_selection(b"/home/me/Music/\n/\n\n/srv/data\n"),
+            [
+                PathBuf::from("/home/me/Music"),
+                PathBuf::from("/"),
+                PathBuf::from("/srv/data")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_review_canonicalizes_and_warns() {
+        let temp_dir = tempdir().unwrap();
+        let canonical = |name: &str| {
+            crate::longpath::simplify(temp_dir.path().join(name).canonicalize().unwrap())
+                .to_string_lossy()
+                .to_string()
+        };
+        for folder in ["docs/notes", "music", "photos/2024"] {
+            std::fs::create_dir_all(temp_dir.path().join(folder)).unwrap();
+        }
+        let picked = [
+            temp_dir.path().join("docs/notes"),
+            temp_dir.path().join("docs/../docs"),
+            temp_dir.path().join("music"),
+            temp_dir.path().join("photos"),
+            temp_dir.path().join("missing"),
+        ];
+        let roots = [canonical("music"), canonical("photos/2024")];
+
+        let review = review(&picked, &roots);
+        assert_eq!(
+            review.paths,
+            [canonical("docs"), canonical("music"), canonical("photos")]
+        );
+        let warnings = review.warnings.join("\n");
+        assert!(warnings.contains("missing"), "{}", warnings);
+        assert!(warnings.contains("already covered"), "{}", warnings);
+        assert!(warnings.contains("indexed already"), "{}", warnings);
+        assert!(warnings.contains("which it would replace"), "{}", warnings);
+    }
+}
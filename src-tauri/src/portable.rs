@@ -0,0 +1,155 @@
+// Portable mode: the index, settings and logs live in a folder of the user's
+// choosing, by default next to the executable, instead of the app data
+// directory, so the app can run from a USB stick on any machine. Chosen at
+// launch with --portable[=<dir>] or by `data_dir` in the settings of the app
+// data directory.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const PORTABLE_FLAG: &str = "--portable";
+/// Folder next to the executable used by a bare --portable
+const PORTABLE_DIR: &str = "CrossEverythingData";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// The app data directory
+    Default,
+    /// The --portable flag
+    Flag,
+    /// `data_dir` in the settings
+    Settings,
+}
+
+/// Where the app keeps its data for this run
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DataLocation {
+    pub path: PathBuf,
+    pub source: Source,
+}
+
+static LOCATION: OnceLock<DataLocation> = OnceLock::new();
+
+/// Folder given by --portable in `args`, relative ones taken from `exe_dir`
+fn flag_dir(args: &[String], exe_dir: &Path) -> Option<PathBuf> {
+    args.iter().find_map(|arg| {
+        if arg == PORTABLE_FLAG {
+            return Some(exe_dir.join(PORTABLE_DIR));
+        }
+        let dir = arg.strip_prefix(PORTABLE_FLAG)?.strip_prefix('=')?;
+        (!dir.trim().is_empty()).then(|| exe_dir.join(dir))
+    })
+}
+
+/// Data location for `args`: the flag wins over `configured`, the settings'
+/// `data_dir`, which wins over `default_dir`
+pub fn resolve(
+    args: &[String],
+    exe_dir: &Path,
+    default_dir: &Path,
+    configured: Option<&str>,
+) -> DataLocation {
+    if let Some(path) = flag_dir(args, exe_dir) {
+        return DataLocation {
+            path,
+            source: Source::Flag,
+        };
+    }
+    match configured.filter(|dir| !dir.trim().is_empty()) {
+        Some(dir) => DataLocation {
+            path: exe_dir.join(dir),
+            source: Source::Settings,
+        },
+        None => DataLocation {
+            path: default_dir.to_path_buf(),
+            source: Source::Default,
+        },
+    }
+}
+
+/// Use `location` for the rest of the run; falls back to `default_dir` when
+/// it can't be created or written, as on a write-protected stick
+pub fn init(location: DataLocation, default_dir: &Path) -> &'static DataLocation {
+    let location = match writable(&location.path) {
+        Ok(()) => location,
+        Err(e) => {
+            eprintln!(
+                "Data folder {} is not writable ({}); using {}",
+                location.path.display(),
+                e,
+                default_dir.display()
+            );
+            DataLocation {
+                path: default_dir.to_path_buf(),
+                source: Source::Default,
+            }
+        }
+    };
+    LOCATION.get_or_init(|| location)
+}
+
+pub fn current() -> Option<&'static DataLocation> {
+    LOCATION.get()
+}
+
+fn writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".write_test");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
+
+/// Folder of the running executable
+pub fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()?
+        .parent()
+        .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_data_location() {
+        let exe_dir = Path::new("/media/stick/app");
+        let default_dir = Path::new("/home/me/.local/share/app");
+        let resolve =
+            |list: &[&str], configured| resolve(&args(list), exe_dir, default_dir, configured);
+
+        assert_eq!(
+            resolve(&[], None),
+            DataLocation {
+                path: default_dir.to_path_buf(),
+                source: Source::Default
+            }
+        );
+        assert_eq!(
+            resolve(&["--portable"], Some("/elsewhere")).path,
+            exe_dir.join(PORTABLE_DIR)
+        );
+        assert_eq!(
+            resolve(&["--portable=data"], None).path,
+            exe_dir.join("data")
+        );
+        assert_eq!(resolve(&["--portable="], None).source, Source::Default);
+        let configured = resolve(&["--hidden"], Some("/srv/app"));
+        assert_eq!(
+            (configured.path, configured.source),
+            (PathBuf::from("/srv/app"), Source::Settings)
+        );
+
+        let temp_dir = tempdir().unwrap();
+        assert!(writable(&temp_dir.path().join("new")).is_ok());
+        std::fs::write(temp_dir.path().join("blocker"), b"").unwrap();
+        assert!(writable(&temp_dir.path().join("blocker").join("data")).is_err());
+    }
+}
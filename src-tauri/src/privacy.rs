@@ -0,0 +1,151 @@
+// Private paths: whether or not a root covers them, entries under them are
+// left out of everything searches return until the user unlocks them for the
+// session, for computers shared with others. Searches exclude them in the
+// query itself, so they take no result slots and count towards no facet.
+// The list applies process-wide, but unlocking only applies to the user: the
+// MCP server and the agent serving other machines never see private entries.
+
+use crate::search::{SearchFilter, SearchResult};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+struct PrivacyState {
+    paths: Vec<PathBuf>,
+    /// Private entries are returned until the app quits or they are locked
+    unlocked: bool,
+}
+
+static PRIVACY: RwLock<PrivacyState> = RwLock::new(PrivacyState {
+    paths: Vec::new(),
+    unlocked: false,
+});
+
+/// Private paths must be absolute
+pub fn validate(paths: &[String]) -> Result<(), String> {
+    match paths.iter().find(|path| !Path::new(path).is_absolute()) {
+        Some(path) => Err(format!("Private path must be absolute: {}", path)),
+        None => Ok(()),
+    }
+}
+
+/// Hide entries under `paths` from now on
+pub fn set_paths(paths: &[String]) {
+    let mut privacy = PRIVACY.write().unwrap_or_else(|e| e.into_inner());
+    privacy.paths = paths.iter().map(PathBuf::from).collect();
+}
+
+pub fn set_unlocked(unlocked: bool) {
+    PRIVACY.write().unwrap_or_else(|e| e.into_inner()).unlocked = unlocked;
+}
+
+pub fn is_unlocked() -> bool {
+    PRIVACY.read().unwrap_or_else(|e| e.into_inner()).unlocked
+}
+
+/// Who results are for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Audience {
+    /// The user of this app, who can unlock private entries
+    User,
+    /// MCP clients and other machines, which never see them
+    External,
+}
+
+impl PrivacyState {
+    /// Paths hidden from `audience` right now
+    fn hidden_from(&self, audience: Audience) -> &[PathBuf] {
+        if audience == Audience::User && self.unlocked {
+            &[]
+        } else {
+            &self.paths
+        }
+    }
+}
+
+/// Paths whose entries `audience` may not see right now, for searches to
+/// exclude in their query
+pub fn hidden_paths(audience: Audience) -> Vec<PathBuf> {
+    let privacy = PRIVACY.read().unwrap_or_else(|e| e.into_inner());
+    privacy.hidden_from(audience).to_vec()
+}
+
+/// `filter` excluding what `audience` may not see. Every search of the
+/// index for results goes through here.
+pub fn restrict(filter: &SearchFilter, audience: Audience) -> SearchFilter {
+    SearchFilter {
+        exclude: hidden_paths(audience),
+        ..filter.clone()
+    }
+}
+
+/// Whether `path` lies in one of `private`, ignoring case on Windows
+fn is_under(path: &str, private: &[PathBuf]) -> bool {
+    let path = Path::new(path);
+    private.iter().any(|private| {
+        if cfg!(windows) {
+            let path = path.to_string_lossy().to_lowercase();
+            Path::new(&path).starts_with(private.to_string_lossy().to_lowercase())
+        } else {
+            path.starts_with(private)
+        }
+    })
+}
+
+/// Whether `path` is hidden from `audience` right now
+pub fn is_hidden(path: &str, audience: Audience) -> bool {
+    let privacy = PRIVACY.read().unwrap_or_else(|e| e.into_inner());
+    is_under(path, privacy.hidden_from(audience))
+}
+
+/// Drop the entries hidden from `audience` from `results`; returns how many
+/// were dropped. Searches already exclude them; this catches what a query
+/// can't, such as paths differing only in case on Windows or other machines'
+/// results.
+pub fn redact(results: &mut Vec<SearchResult>, audience: Audience) -> usize {
+    redact_by(results, audience, |result| &result.path)
+}
+
+/// Drop the items of `items` whose path, as given by `path_of`, is hidden
+/// from `audience`; returns how many were dropped
+pub fn redact_by<T>(items: &mut Vec<T>, audience: Audience, path_of: impl Fn(&T) -> &str) -> usize {
+    let privacy = PRIVACY.read().unwrap_or_else(|e| e.into_inner());
+    let hidden = privacy.hidden_from(audience);
+    if hidden.is_empty() {
+        return 0;
+    }
+    let before = items.len();
+    items.retain(|item| !is_under(path_of(item), hidden));
+    before - items.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_paths() {
+        let root = if cfg!(windows) {
+            r"C:\Users\me"
+        } else {
+            "/home/me"
+        };
+        let private = [Path::new(root).join("Diary")];
+        let inside = Path::new(root).join("Diary").join("2024.md");
+        let sibling = Path::new(root).join("Diary2").join("notes.md");
+        assert!(is_under(&inside.to_string_lossy(), &private));
+        assert!(is_under(&private[0].to_string_lossy(), &private));
+        assert!(!is_under(&sibling.to_string_lossy(), &private));
+        assert!(!is_under(root, &private));
+
+        assert!(validate(&[root.to_string()]).is_ok());
+        assert!(validate(&["Diary".to_string()]).is_err());
+
+        // Unlocking only lets the user see them
+        let state = PrivacyState {
+            paths: private.to_vec(),
+            unlocked: true,
+        };
+        assert!(state.hidden_from(Audience::User).is_empty());
+        assert_eq!(state.hidden_from(Audience::External), private);
+    }
+}
@@ -0,0 +1,419 @@
+// Named index profiles, each with its own roots, sled DB and tantivy directory
+
+use crate::index::WalkOptions;
+use crate::watcher::WatchConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PROFILE: &str = "Default";
+/// Profile selector that searches every profile at once, so no profile may
+/// be named this
+pub const ALL_PROFILES: &str = "*";
+const PROFILES_FILE: &str = "profiles.json";
+const PROFILES_DIR: &str = "profiles";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub roots: Vec<String>,
+    pub created: i64, // Unix timestamp in seconds
+    /// Entities indexed by the last completed build, used as the next build's estimate
+    #[serde(default)]
+    pub last_file_count: Option<usize>,
+    /// Traversal settings keyed by root; roots without an entry use the defaults
+    #[serde(default)]
+    pub root_options: BTreeMap<String, WalkOptions>,
+    /// Roots kept up to date by the file watcher, restored on startup
+    #[serde(default)]
+    pub watched_roots: BTreeMap<String, WatchConfig>,
+    /// Event journal position the index is up to date with; watching resumes
+    /// from here on the next launch (FSEvents ids on macOS)
+    #[serde(default)]
+    pub watch_event_id: Option<u64>,
+}
+
+impl Profile {
+    pub fn options_for(&self, root: &str) -> WalkOptions {
+        self.root_options.get(root).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileFile {
+    active: String,
+    profiles: Vec<Profile>,
+}
+
+/// Persistent registry of profiles stored as JSON in the app data directory
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    base_dir: PathBuf,
+    active: String,
+    profiles: Vec<Profile>,
+}
+
+impl ProfileStore {
+    /// Load the registry from `base_dir`, creating the default profile if missing
+    pub fn load(base_dir: &Path) -> Self {
+        let file_path = base_dir.join(PROFILES_FILE);
+        let parsed =
+            fs::read_to_string(&file_path).ok().and_then(|data| {
+                match serde_json::from_str::<ProfileFile>(&data) {
+                    Ok(file) => Some(file),
+                    Err(e) => {
+                        log::warn!("Ignoring malformed {}: {}", file_path.display(), e);
+                        None
+                    }
+                }
+            });
+
+        let mut store = match parsed {
+            Some(file) => ProfileStore {
+                base_dir: base_dir.to_path_buf(),
+                active: file.active,
+                profiles: file.profiles,
+            },
+            None => ProfileStore {
+                base_dir: base_dir.to_path_buf(),
+                active: DEFAULT_PROFILE.to_string(),
+                profiles: Vec::new(),
+            },
+        };
+
+        if store.get(DEFAULT_PROFILE).is_none() {
+            store
+                .profiles
+                .insert(0, new_profile(DEFAULT_PROFILE, Vec::new()));
+        }
+        if store.get(&store.active.clone()).is_none() {
+            store.active = DEFAULT_PROFILE.to_string();
+        }
+        store
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        fs::create_dir_all(&self.base_dir)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        let file = ProfileFile {
+            active: self.active.clone(),
+            profiles: self.profiles.clone(),
+        };
+        let data = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+        fs::write(self.base_dir.join(PROFILES_FILE), data)
+            .map_err(|e| format!("Failed to write profiles: {}", e))
+    }
+
+    pub fn active(&self) -> &Profile {
+        self.get(&self.active)
+            .expect("active profile is always registered")
+    }
+
+    pub fn list(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn create(&mut self, name: &str, roots: Vec<String>) -> Result<Profile, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profile name must not be empty".to_string());
+        }
+        if name == ALL_PROFILES {
+            return Err(format!(
+                "Profile name is reserved for searching all profiles: {}",
+                name
+            ));
+        }
+        if self.get(name).is_some() {
+            return Err(format!("Profile already exists: {}", name));
+        }
+        let slug = slugify(name);
+        if self.profiles.iter().any(|p| slugify(&p.name) == slug) {
+            return Err(format!(
+                "Profile name conflicts with an existing profile: {}",
+                name
+            ));
+        }
+
+        let profile = new_profile(name, roots);
+        self.profiles.push(profile.clone());
+        Ok(profile)
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<&Profile, String> {
+        if self.get(name).is_none() {
+            return Err(format!("Unknown profile: {}", name));
+        }
+        self.active = name.to_string();
+        Ok(self.active())
+    }
+
+    pub fn set_roots(&mut self, name: &str, roots: Vec<String>) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+        profile.roots = roots;
+        Ok(())
+    }
+
+    pub fn set_file_count(&mut self, name: &str, count: usize) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+        profile.last_file_count = Some(count);
+        Ok(())
+    }
+
+    pub fn set_root_options(
+        &mut self,
+        name: &str,
+        root: &str,
+        options: WalkOptions,
+    ) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+        if options == WalkOptions::default() {
+            profile.root_options.remove(root);
+        } else {
+            profile.root_options.insert(root.to_string(), options);
+        }
+        Ok(())
+    }
+
+    /// Enable watching `root` with `config`, or disable it with None
+    pub fn set_watch(
+        &mut self,
+        name: &str,
+        root: &str,
+        config: Option<WatchConfig>,
+    ) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+        match config {
+            Some(config) => {
+                profile.watched_roots.insert(root.to_string(), config);
+            }
+            None => {
+                profile.watched_roots.remove(root);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_watch_event_id(&mut self, name: &str, event_id: u64) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Unknown profile: {}", name))?;
+        profile.watch_event_id = Some(event_id);
+        Ok(())
+    }
+
+    /// Directory holding the sled DB and tantivy index for a profile.
+    /// The default profile keeps the legacy location so existing indexes still load.
+    pub fn data_dir(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.base_dir.clone()
+        } else {
+            self.base_dir.join(PROFILES_DIR).join(slugify(name))
+        }
+    }
+
+    /// (sled DB path, tantivy index path) live for a profile
+    pub fn index_paths(&self, name: &str) -> (PathBuf, PathBuf) {
+        crate::rebuild::live_paths(&self.data_dir(name))
+    }
+}
+
+fn new_profile(name: &str, roots: Vec<String>) -> Profile {
+    Profile {
+        name: name.to_string(),
+        roots,
+        created: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64,
+        last_file_count: None,
+        root_options: BTreeMap::new(),
+        watched_roots: BTreeMap::new(),
+        watch_event_id: None,
+    }
+}
+
+/// File-system safe directory name for a profile
+fn slugify(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_creates_default_profile() {
+        let temp_dir = tempdir().unwrap();
+        let store = ProfileStore::load(temp_dir.path());
+
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(store.active().name, DEFAULT_PROFILE);
+        let (db_path, search_path) = store.index_paths(DEFAULT_PROFILE);
+        assert_eq!(db_path, temp_dir.path().join(".index_db"));
+        assert_eq!(search_path, temp_dir.path().join(".search_index"));
+    }
+
+    #[test]
+    fn test_create_switch_and_persist() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = ProfileStore::load(temp_dir.path());
+
+        store.create("Work SSD", vec!["/work".to_string()]).unwrap();
+        store.switch("Work SSD").unwrap();
+        store.save().unwrap();
+
+        let reloaded = ProfileStore::load(temp_dir.path());
+        assert_eq!(reloaded.active().name, "Work SSD");
+        assert_eq!(reloaded.active().roots, vec!["/work".to_string()]);
+        assert_eq!(
+            reloaded.data_dir("Work SSD"),
+            temp_dir.path().join("profiles").join("work_ssd")
+        );
+    }
+
+    #[test]
+    fn test_create_rejects_duplicates_and_reserved_names() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = ProfileStore::load(temp_dir.path());
+
+        assert!(store.create("", Vec::new()).is_err());
+        assert!(store.create("  ", Vec::new()).is_err());
+        assert!(store.create("\t\n", Vec::new()).is_err());
+        assert!(store.create(ALL_PROFILES, Vec::new()).is_err());
+        assert!(store.create(" * ", Vec::new()).is_err());
+        store.create("NAS", Vec::new()).unwrap();
+        assert!(store.create("NAS", Vec::new()).is_err());
+        assert!(
+            store.create("nas", Vec::new()).is_err(),
+            "Names mapping to the same directory should conflict"
+        );
+    }
+
+    #[test]
+    fn test_switch_unknown_profile_fails() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = ProfileStore::load(temp_dir.path());
+
+        assert!(store.switch("missing").is_err());
+        assert_eq!(store.active().name, DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn test_load_falls_back_when_active_missing() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(PROFILES_FILE),
+            r#"{"active":"Gone","profiles":[]}"#,
+        )
+        .unwrap();
+
+        let store = ProfileStore::load(temp_dir.path());
+        assert_eq!(store.active().name, DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn test_file_count_persists_and_defaults_to_none() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(PROFILES_FILE),
+            r#"{"active":"Default","profiles":[{"name":"Default","roots":[],"created":0}]}"#,
+        )
+        .unwrap();
+
+        let mut store = ProfileStore::load(temp_dir.path());
+        assert_eq!(store.active().last_file_count, None);
+
+        store.set_file_count(DEFAULT_PROFILE, 42).unwrap();
+        store.save().unwrap();
+        let reloaded = ProfileStore::load(temp_dir.path());
+        assert_eq!(reloaded.active().last_file_count, Some(42));
+        assert!(store.set_file_count("missing", 1).is_err());
+    }
+
+    #[test]
+    fn test_root_options_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = ProfileStore::load(temp_dir.path());
+        let follow = WalkOptions {
+            follow_symlinks: true,
+            ..WalkOptions::default()
+        };
+
+        store
+            .set_root_options(DEFAULT_PROFILE, "/apps", follow)
+            .unwrap();
+        store.save().unwrap();
+
+        let mut reloaded = ProfileStore::load(temp_dir.path());
+        assert_eq!(reloaded.active().options_for("/apps"), follow);
+        assert_eq!(
+            reloaded.active().options_for("/other"),
+            WalkOptions::default()
+        );
+
+        // Resetting to defaults drops the entry
+        reloaded
+            .set_root_options(DEFAULT_PROFILE, "/apps", WalkOptions::default())
+            .unwrap();
+        assert!(reloaded.active().root_options.is_empty());
+    }
+
+    #[test]
+    fn test_watched_roots_persist() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = ProfileStore::load(temp_dir.path());
+        let shallow = WatchConfig {
+            recursive: false,
+            ..Default::default()
+        };
+
+        store
+            .set_watch(DEFAULT_PROFILE, "/src", Some(WatchConfig::default()))
+            .unwrap();
+        store
+            .set_watch(DEFAULT_PROFILE, "/tmp", Some(shallow))
+            .unwrap();
+        store.save().unwrap();
+
+        let mut reloaded = ProfileStore::load(temp_dir.path());
+        assert_eq!(reloaded.active().watched_roots.len(), 2);
+        assert_eq!(reloaded.active().watched_roots["/tmp"], shallow);
+
+        reloaded.set_watch(DEFAULT_PROFILE, "/src", None).unwrap();
+        assert_eq!(
+            reloaded.active().watched_roots.keys().collect::<Vec<_>>(),
+            vec!["/tmp"]
+        );
+        assert!(reloaded.set_watch("Missing", "/src", None).is_err());
+    }
+}
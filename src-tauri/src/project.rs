@@ -0,0 +1,94 @@
+// Project roots: folders holding a Cargo manifest, an npm package manifest or
+// a Git work tree, recognized while indexing so searches can be scoped to one
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectMarker {
+    /// Cargo.toml
+    Cargo,
+    /// package.json
+    Npm,
+    /// .git
+    Git,
+}
+
+impl ProjectMarker {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProjectMarker::Cargo => "cargo",
+            ProjectMarker::Npm => "npm",
+            ProjectMarker::Git => "git",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ProjectMarker> {
+        [ProjectMarker::Cargo, ProjectMarker::Npm, ProjectMarker::Git]
+            .into_iter()
+            .find(|marker| marker.as_str() == value)
+    }
+}
+
+/// A folder recognized as a project root
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Project {
+    /// Folder name, as `project:` filters match it case-insensitively
+    pub name: String,
+    pub path: String,
+    pub markers: Vec<ProjectMarker>,
+}
+
+/// Name `project:` filters match a project by: its folder name, lowercased
+pub fn project_name(root: &Path) -> String {
+    crate::git::repo_name(root)
+}
+
+/// Markers found in `dir`. Packages vendored under node_modules each carry
+/// a package.json but are dependencies rather than projects, so none are
+/// reported there.
+pub fn markers(dir: &Path) -> Vec<ProjectMarker> {
+    if dir.components().any(|c| c.as_os_str() == "node_modules") {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    for (file, marker) in [
+        ("Cargo.toml", ProjectMarker::Cargo),
+        ("package.json", ProjectMarker::Npm),
+    ] {
+        if dir.join(file).is_file() {
+            found.push(marker);
+        }
+    }
+    if crate::git::is_repo_root(dir) {
+        found.push(ProjectMarker::Git);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("App");
+        std::fs::create_dir_all(app.join(".git")).unwrap();
+        std::fs::write(app.join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(app.join("package.json"), "{}").unwrap();
+        let vendored = app.join("node_modules").join("left-pad");
+        std::fs::create_dir_all(&vendored).unwrap();
+        std::fs::write(vendored.join("package.json"), "{}").unwrap();
+
+        assert_eq!(
+            markers(&app),
+            vec![ProjectMarker::Cargo, ProjectMarker::Npm, ProjectMarker::Git]
+        );
+        assert!(markers(&vendored).is_empty());
+        assert!(markers(dir.path()).is_empty());
+        assert_eq!(project_name(&app), "app");
+        assert_eq!(ProjectMarker::parse("npm"), Some(ProjectMarker::Npm));
+    }
+}
@@ -0,0 +1,97 @@
+// Downloaded-from-internet markers: the Zone.Identifier stream Windows
+// attaches to downloads and the com.apple.quarantine attribute on macOS.
+// Other platforms have none, so nothing is ever flagged there.
+
+use crate::error::AppError;
+use std::path::Path;
+
+/// Whether markers can be read and removed on this platform
+pub const SUPPORTED: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Set `quarantined` on each result that carries a marker
+pub fn mark(results: &mut [crate::search::SearchResult]) {
+    if !SUPPORTED {
+        return;
+    }
+    for result in results {
+        result.quarantined = is_quarantined(Path::new(&result.path));
+    }
+}
+
+#[cfg(windows)]
+fn zone_stream(path: &Path) -> std::path::PathBuf {
+    let mut stream = path.as_os_str().to_os_string();
+    stream.push(":Zone.Identifier");
+    stream.into()
+}
+
+/// Whether `path` carries a marker. Unreadable files count as unmarked.
+#[cfg(windows)]
+pub fn is_quarantined(path: &Path) -> bool {
+    std::fs::metadata(zone_stream(path)).is_ok()
+}
+
+/// Remove the marker from `path` so it opens without a security prompt.
+/// Returns false when it had none.
+#[cfg(windows)]
+pub fn unblock(path: &Path) -> Result<bool, AppError> {
+    match std::fs::remove_file(zone_stream(path)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            // No stream, or no file at all
+            std::fs::metadata(path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+            Ok(false)
+        }
+        Err(e) => Err(AppError::io(path.to_string_lossy(), &e)),
+    }
+}
+
+/// Attribute macOS sets on downloads
+#[cfg(target_os = "macos")]
+const QUARANTINE_ATTR: &[u8] = b"com.apple.quarantine";
+
+/// Whether `path` carries a marker. Unreadable files count as unmarked.
+#[cfg(target_os = "macos")]
+pub fn is_quarantined(path: &Path) -> bool {
+    crate::xattr::size(path, QUARANTINE_ATTR).is_some()
+}
+
+/// Remove the marker from `path` so it opens without a Gatekeeper prompt.
+/// Returns false when it had none.
+#[cfg(target_os = "macos")]
+pub fn unblock(path: &Path) -> Result<bool, AppError> {
+    crate::xattr::remove(path, QUARANTINE_ATTR)
+        .map_err(|e| AppError::io(path.to_string_lossy(), &e))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn is_quarantined(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn unblock(path: &Path) -> Result<bool, AppError> {
+    Err(AppError::new(
+        crate::error::ErrorKind::Unsupported,
+        "Files carry no download marker on this platform",
+    )
+    .with_path(path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_file_is_not_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("local.txt");
+        std::fs::write(&file, "made here").unwrap();
+        assert!(!is_quarantined(&file));
+        if SUPPORTED {
+            assert!(!unblock(&file).unwrap());
+        } else {
+            assert!(unblock(&file).is_err());
+        }
+    }
+}
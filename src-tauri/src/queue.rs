@@ -0,0 +1,62 @@
+// Work for the background workers that read the index as it changes: OCR
+// and embeddings. Paths are queued as entries are indexed; a backlog request
+// has the worker check the whole index.
+
+use std::collections::{HashSet, VecDeque};
+
+/// Paths waiting for a background worker, each queued once
+#[derive(Debug, Default)]
+pub struct PathQueue {
+    order: VecDeque<String>,
+    queued: HashSet<String>,
+    /// The whole index is to be checked for work, after it was loaded or
+    /// replaced or the worker was turned on
+    backlog: bool,
+}
+
+impl PathQueue {
+    pub fn push(&mut self, path: &str) {
+        if self.queued.insert(path.to_string()) {
+            self.order.push_back(path.to_string());
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        let path = self.order.pop_front()?;
+        self.queued.remove(&path);
+        Some(path)
+    }
+
+    /// Have the whole index checked by the next `take_backlog`
+    pub fn request_backlog(&mut self) {
+        self.backlog = true;
+    }
+
+    /// Whether the whole index is to be checked, clearing the request
+    pub fn take_backlog(&mut self) -> bool {
+        std::mem::take(&mut self.backlog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_holds_each_path_once() {
+        let mut queue = PathQueue::default();
+        queue.push("/a.png");
+        queue.push("/b.png");
+        queue.push("/a.png");
+        assert_eq!(queue.pop().as_deref(), Some("/a.png"));
+        queue.push("/a.png");
+        assert_eq!(queue.pop().as_deref(), Some("/b.png"));
+        assert_eq!(queue.pop().as_deref(), Some("/a.png"));
+        assert_eq!(queue.pop(), None);
+
+        assert!(!queue.take_backlog());
+        queue.request_backlog();
+        assert!(queue.take_backlog());
+        assert!(!queue.take_backlog());
+    }
+}
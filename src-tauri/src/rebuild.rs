@@ -0,0 +1,305 @@
+// Double-buffered index rebuilds: build into a staging directory, then swap.
+//
+// A profile's DB and search index are built together into a generation
+// directory and made live by rewriting one pointer file, so they can never be
+// left from different builds. Older installs keep both directly in the
+// profile directory until their first rebuild.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const STAGING_SUFFIX: &str = ".building";
+const BACKUP_SUFFIX: &str = ".old";
+/// Names the live DB and search index of a profile directory
+const LIVE_FILE: &str = ".index_live.json";
+const GENERATION_PREFIX: &str = ".index-";
+const DB_DIR: &str = ".index_db";
+const SEARCH_DIR: &str = ".search_index";
+
+/// Contents of `LIVE_FILE`, relative to the profile directory
+#[derive(Debug, Serialize, Deserialize)]
+struct Live {
+    db: PathBuf,
+    search: PathBuf,
+}
+
+fn read_live(dir: &Path) -> Option<Live> {
+    let data = fs::read(dir.join(LIVE_FILE)).ok()?;
+    match serde_json::from_slice(&data) {
+        Ok(live) => Some(live),
+        Err(e) => {
+            log::error!("Ignoring malformed {:?}: {}", dir.join(LIVE_FILE), e);
+            None
+        }
+    }
+}
+
+/// (sled DB path, tantivy index path) live in `dir`
+pub fn live_paths(dir: &Path) -> (PathBuf, PathBuf) {
+    match read_live(dir) {
+        Some(live) => (dir.join(live.db), dir.join(live.search)),
+        None => (dir.join(DB_DIR), dir.join(SEARCH_DIR)),
+    }
+}
+
+/// Create an empty generation directory in `dir` for a build and return the
+/// (sled DB path, tantivy index path) to build into. Generations left by
+/// failed or interrupted builds are deleted first.
+pub fn new_generation(dir: &Path) -> io::Result<(PathBuf, PathBuf)> {
+    reap(dir);
+    fs::create_dir_all(dir)?;
+    let latest = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_prefix(GENERATION_PREFIX)?.parse().ok()
+        })
+        .max()
+        .unwrap_or(0u64);
+    let generation = dir.join(format!("{}{}", GENERATION_PREFIX, latest + 1));
+    fs::create_dir(&generation)?;
+    Ok((generation.join(DB_DIR), generation.join(SEARCH_DIR)))
+}
+
+/// Make `db` and `search`, both inside `dir`, its live index. The pointer
+/// file is replaced with one rename, so a crash leaves either the old pair or
+/// the new one.
+pub fn commit(dir: &Path, (db, search): (&Path, &Path)) -> io::Result<()> {
+    let relative = |path: &Path| {
+        path.strip_prefix(dir).map(Path::to_path_buf).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not inside {:?}", path, dir),
+            )
+        })
+    };
+    let live = Live {
+        db: relative(db)?,
+        search: relative(search)?,
+    };
+    let partial = with_suffix(&dir.join(LIVE_FILE), STAGING_SUFFIX);
+    let data = serde_json::to_vec_pretty(&live)?;
+    {
+        use std::io::Write;
+        let mut file = fs::File::create(&partial)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&partial, dir.join(LIVE_FILE))
+}
+
+/// Delete what in `dir` is no longer live: replaced generations, the legacy
+/// directories once a generation replaced them, and staging leftovers. Ones
+/// still open elsewhere, which Windows refuses to delete, are retried on the
+/// next call; every build and every load of the index makes one.
+pub fn reap(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let (db, search) = live_paths(dir);
+    let live = [db, search];
+    let legacy = [DB_DIR, SEARCH_DIR];
+    let leftovers: Vec<String> = legacy
+        .iter()
+        .flat_map(|name| {
+            [STAGING_SUFFIX, BACKUP_SUFFIX].map(|suffix| format!("{}{}", name, suffix))
+        })
+        .collect();
+
+    let mut stale = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(GENERATION_PREFIX) {
+            if live.iter().any(|live| live.starts_with(&path)) {
+                // Still holds the DB after a search-only rebuild, or vice versa
+                stale.extend(
+                    legacy
+                        .iter()
+                        .map(|name| path.join(name))
+                        .filter(|path| path.exists() && !live.contains(path)),
+                );
+            } else {
+                stale.push(path);
+            }
+        } else if legacy.contains(&name.as_str()) {
+            if !live.contains(&path) {
+                stale.push(path);
+            }
+        } else if leftovers.contains(&name) {
+            stale.push(path);
+        }
+    }
+
+    for path in stale {
+        match fs::remove_dir_all(&path) {
+            Ok(()) => log::info!("Removed replaced index {:?}", path),
+            Err(e) => log::debug!("Cannot remove {:?} yet: {}", path, e),
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Staging location next to a live index directory
+pub fn staging_path(live: &Path) -> PathBuf {
+    with_suffix(live, STAGING_SUFFIX)
+}
+
+/// Remove leftovers of an interrupted build so the staging directory starts empty
+pub fn clear_staging(staging: &Path) -> io::Result<()> {
+    if staging.exists() {
+        log::info!("Removing stale staging directory {:?}", staging);
+        fs::remove_dir_all(staging)?;
+    }
+    Ok(())
+}
+
+/// Replace `live` with `staging`. The previous live directory is kept as a
+/// backup until the rename succeeds and restored if it fails.
+pub fn promote(staging: &Path, live: &Path) -> io::Result<()> {
+    let backup = with_suffix(live, BACKUP_SUFFIX);
+    if backup.exists() {
+        fs::remove_dir_all(&backup)?;
+    }
+
+    let had_live = live.exists();
+    if had_live {
+        fs::rename(live, &backup)?;
+    }
+
+    if let Err(e) = fs::rename(staging, live) {
+        log::error!("Failed to promote {:?} to {:?}: {}", staging, live, e);
+        if had_live {
+            if let Err(restore_err) = fs::rename(&backup, live) {
+                log::error!(
+                    "Failed to restore previous index {:?}: {}",
+                    live,
+                    restore_err
+                );
+            }
+        }
+        return Err(e);
+    }
+
+    if had_live {
+        if let Err(e) = fs::remove_dir_all(&backup) {
+            log::warn!("Failed to remove previous index {:?}: {}", backup, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_staging_path_is_sibling() {
+        let live = Path::new("/data/.search_index");
+        assert_eq!(
+            staging_path(live),
+            PathBuf::from("/data/.search_index.building")
+        );
+    }
+
+    #[test]
+    fn test_promote_replaces_live_directory() {
+        let temp_dir = tempdir().unwrap();
+        let live = temp_dir.path().join(".search_index");
+        let staging = staging_path(&live);
+        fs::create_dir(&live).unwrap();
+        fs::write(live.join("old.txt"), "old").unwrap();
+        fs::create_dir(&staging).unwrap();
+        fs::write(staging.join("new.txt"), "new").unwrap();
+
+        promote(&staging, &live).unwrap();
+
+        assert!(live.join("new.txt").exists());
+        assert!(!live.join("old.txt").exists());
+        assert!(!staging.exists());
+        assert!(!with_suffix(&live, BACKUP_SUFFIX).exists());
+    }
+
+    #[test]
+    fn test_promote_without_existing_live() {
+        let temp_dir = tempdir().unwrap();
+        let live = temp_dir.path().join(".index_db");
+        let staging = staging_path(&live);
+        fs::create_dir(&staging).unwrap();
+
+        promote(&staging, &live).unwrap();
+        assert!(live.exists());
+    }
+
+    #[test]
+    fn test_promote_missing_staging_keeps_live() {
+        let temp_dir = tempdir().unwrap();
+        let live = temp_dir.path().join(".index_db");
+        fs::create_dir(&live).unwrap();
+        fs::write(live.join("keep.txt"), "keep").unwrap();
+
+        assert!(promote(&staging_path(&live), &live).is_err());
+        assert!(
+            live.join("keep.txt").exists(),
+            "Live index should be restored"
+        );
+    }
+
+    #[test]
+    fn test_generations_switch_together() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        // Legacy layout until the first commit
+        fs::create_dir(dir.join(DB_DIR)).unwrap();
+        fs::create_dir(dir.join(".index_db.building")).unwrap();
+        assert_eq!(live_paths(dir), (dir.join(DB_DIR), dir.join(SEARCH_DIR)));
+
+        let (db, search) = new_generation(dir).unwrap();
+        assert!(!dir.join(".index_db.building").exists());
+        assert!(dir.join(DB_DIR).exists(), "Legacy index stays live");
+        fs::create_dir(&db).unwrap();
+        fs::create_dir(&search).unwrap();
+        commit(dir, (&db, &search)).unwrap();
+        assert_eq!(live_paths(dir), (db.clone(), search.clone()));
+        reap(dir);
+        assert!(!dir.join(DB_DIR).exists());
+
+        // A search-only rebuild keeps the DB of the previous generation
+        let (_, rebuilt) = new_generation(dir).unwrap();
+        assert_ne!(rebuilt, search);
+        fs::create_dir(&rebuilt).unwrap();
+        commit(dir, (&db, &rebuilt)).unwrap();
+        reap(dir);
+        assert_eq!(live_paths(dir), (db.clone(), rebuilt.clone()));
+        assert!(db.exists() && rebuilt.exists());
+        assert!(!search.exists());
+
+        // An abandoned build is cleared by the next one
+        let (abandoned, _) = new_generation(dir).unwrap();
+        fs::create_dir(&abandoned).unwrap();
+        new_generation(dir).unwrap();
+        assert!(!abandoned.exists());
+        assert!(commit(dir, (&db, Path::new("/elsewhere"))).is_err());
+        assert_eq!(live_paths(dir), (db, rebuilt));
+    }
+
+    #[test]
+    fn test_clear_staging() {
+        let temp_dir = tempdir().unwrap();
+        let staging = temp_dir.path().join("x.building");
+        fs::create_dir(&staging).unwrap();
+        clear_staging(&staging).unwrap();
+        assert!(!staging.exists());
+        clear_staging(&staging).unwrap();
+    }
+}
@@ -0,0 +1,505 @@
+// Searching other machines: an agent serving this machine's index over TCP,
+// and the client that queries the agents listed in settings
+//
+// Speaks newline-delimited JSON. The agent opens with a random challenge and
+// the client answers with its HMAC-SHA256 under the shared token, so the
+// token never crosses the network. Traffic is not encrypted; agents are
+// meant for trusted networks.
+
+use crate::privacy::{self, Audience};
+use crate::search::{self, SearchResult};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const PROTOCOL_VERSION: u64 = 1;
+/// Port agents listen on unless told otherwise
+pub const DEFAULT_AGENT_PORT: u16 = 48650;
+/// Shortest token accepted, in characters
+pub const MIN_TOKEN_CHARS: usize = 16;
+/// Most hits an agent returns for one search
+const MAX_REMOTE_RESULTS: usize = 1000;
+/// Longest message read, so a client can't make the agent buffer without end
+const MAX_LINE_BYTES: u64 = 64 * 1024;
+/// Time a client has to answer the challenge
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A machine running the agent, searched along with the local index
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RemoteMachine {
+    /// Shown on its results
+    pub name: String,
+    /// `host:port` of its agent
+    pub address: String,
+    pub token: String,
+}
+
+/// How this machine's agent serves its index
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentSettings {
+    pub port: u16,
+    /// Secret clients prove they know
+    pub token: String,
+}
+
+impl AgentSettings {
+    /// Settings for a first start, with a new token
+    pub fn generate(port: u16) -> Result<AgentSettings, String> {
+        Ok(AgentSettings {
+            port,
+            token: random_hex(24)
+                .map_err(|e| format!("Failed to generate an agent token: {}", e))?,
+        })
+    }
+}
+
+/// Check the agent settings and the machines listed for searching
+pub fn validate(agent: Option<&AgentSettings>, remotes: &[RemoteMachine]) -> Result<(), String> {
+    if let Some(agent) = agent {
+        if agent.token.chars().count() < MIN_TOKEN_CHARS {
+            return Err(format!(
+                "Agent token must have at least {} characters",
+                MIN_TOKEN_CHARS
+            ));
+        }
+    }
+    let mut names = std::collections::HashSet::new();
+    for remote in remotes {
+        if remote.name.trim().is_empty() {
+            return Err("Remote machines need a name".to_string());
+        }
+        if !names.insert(remote.name.to_lowercase()) {
+            return Err(format!("Remote machine {} is listed twice", remote.name));
+        }
+        let port = remote.address.rsplit_once(':').map(|(_, port)| port);
+        if port.and_then(|port| port.parse::<u16>().ok()).is_none() {
+            return Err(format!(
+                "Address of {} must be host:port: {}",
+                remote.name, remote.address
+            ));
+        }
+        if remote.token.is_empty() {
+            return Err(format!("Remote machine {} has no token", remote.name));
+        }
+    }
+    Ok(())
+}
+
+/// Hex of `bytes` random bytes from the OS
+fn random_hex(bytes: usize) -> Result<String, getrandom::Error> {
+    let mut random = vec![0u8; bytes];
+    getrandom::getrandom(&mut random)?;
+    Ok(to_hex(&random))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// HMAC-SHA256 of `message` under `key`
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+/// Answer to `challenge` proving knowledge of `token`
+fn proof(token: &str, challenge: &str) -> String {
+    to_hex(&hmac(token.as_bytes(), challenge.as_bytes()))
+}
+
+/// Compare without stopping at the first difference, so timing doesn't tell
+/// how much of a proof was right
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Read one message of at most MAX_LINE_BYTES; None at the end of the stream.
+/// Bytes read before a timeout stay in `line`, so calling again with the
+/// same buffer picks the message up where it stopped.
+fn read_message(
+    reader: &mut BufReader<TcpStream>,
+    line: &mut Vec<u8>,
+) -> std::io::Result<Option<Value>> {
+    let room = MAX_LINE_BYTES.saturating_sub(line.len() as u64);
+    if reader.take(room).read_until(b'\n', line)? == 0 && line.is_empty() {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        if line.len() as u64 >= MAX_LINE_BYTES {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Message too long",
+            ));
+        }
+        // The stream ended partway through a message
+        return Ok(None);
+    }
+    let message = serde_json::from_slice(line);
+    line.clear();
+    message
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(writer: &mut TcpStream, message: &Value) -> std::io::Result<()> {
+    writeln!(writer, "{}", message)?;
+    writer.flush()
+}
+
+/// Searches run for clients. Connections run on plain threads, so the index
+/// is read with `blocking_read`.
+#[derive(Clone)]
+pub struct Agent {
+    search_index: Arc<RwLock<Option<Arc<crate::search::SearchIndex>>>>,
+    token: Arc<str>,
+}
+
+impl Agent {
+    pub fn new(
+        search_index: Arc<RwLock<Option<Arc<crate::search::SearchIndex>>>>,
+        token: &str,
+    ) -> Self {
+        Agent {
+            search_index,
+            token: token.into(),
+        }
+    }
+
+    /// Answer one search request of an authenticated client
+    fn handle_request(&self, request: &Value) -> Value {
+        let Some(query) = request.get("query").and_then(|q| q.as_str()) else {
+            return json!({ "error": "Missing query" });
+        };
+        let use_regex = request
+            .get("use_regex")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let limit = request
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(MAX_REMOTE_RESULTS, |l| (l as usize).min(MAX_REMOTE_RESULTS));
+        if use_regex {
+            if let Err(e) = regex::Regex::new(query) {
+                return json!({ "error": format!("Invalid regex: {}", e) });
+            }
+        }
+        // A snapshot, so the lock is not held during the search
+        let Some(search_index) = self.search_index.blocking_read().clone() else {
+            return json!({ "error": "INDEX_NOT_READY" });
+        };
+        // Private entries stay hidden from other machines even while unlocked here
+        let filter = privacy::restrict(&Default::default(), Audience::External);
+        let cancel = search::CancelToken::with_timeout(search::DEFAULT_SEARCH_TIMEOUT);
+        match search_index
+            .search_filtered(query, use_regex, limit, &filter, &cancel)
+            .and_then(|docs| search_index.to_results(&docs))
+        {
+            Ok(mut results) => {
+                privacy::redact(&mut results, Audience::External);
+                json!({ "results": results })
+            }
+            Err(_) if cancel.is_expired() => json!({ "error": "TIMED_OUT" }),
+            Err(e) => json!({ "error": format!("Search failed: {}", e) }),
+        }
+    }
+
+    fn serve_connection(&self, stream: TcpStream, shutdown: &AtomicBool) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let challenge = random_hex(32)?;
+        write_message(
+            &mut writer,
+            &json!({ "protocol": PROTOCOL_VERSION, "challenge": challenge }),
+        )?;
+        writer.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut line = Vec::new();
+        let answer = read_message(&mut reader, &mut line)?;
+        let answer = answer
+            .as_ref()
+            .and_then(|message| message.get("proof"))
+            .and_then(|proof| proof.as_str());
+        if !answer.is_some_and(|answer| same(answer, &proof(&self.token, &challenge))) {
+            write_message(&mut writer, &json!({ "error": "Authentication failed" }))?;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "Authentication failed",
+            ));
+        }
+        write_message(&mut writer, &json!({ "ok": true }))?;
+
+        // Short timeouts so the shutdown flag is seen while idle
+        writer.set_read_timeout(Some(Duration::from_millis(500)))?;
+        while !shutdown.load(Ordering::SeqCst) {
+            match read_message(&mut reader, &mut line) {
+                Ok(Some(request)) => write_message(&mut writer, &self.handle_request(&request))?,
+                Ok(None) => break,
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::WouldBlock
+                        || e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Running agent listener; dropping the handle does not stop it, call `stop`
+pub struct AgentHandle {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AgentHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        log::info!("Agent on {} stopped", self.addr);
+    }
+}
+
+/// Bind to `addr` and serve in a background thread
+pub fn start(agent: Agent, addr: SocketAddr) -> std::io::Result<AgentHandle> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let shutdown = Arc::new(AtomicBool::new(false));
+
+    let thread_shutdown = shutdown.clone();
+    let thread = thread::spawn(move || {
+        log::info!("Agent listening on {}", addr);
+        while !thread_shutdown.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    log::info!("Agent client connected from {}", peer);
+                    let agent = agent.clone();
+                    let conn_shutdown = thread_shutdown.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = agent.serve_connection(stream, &conn_shutdown) {
+                            log::warn!("Agent connection from {} closed with error: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    log::error!("Agent accept failed: {}", e);
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+        }
+    });
+
+    Ok(AgentHandle {
+        addr,
+        shutdown,
+        thread: Some(thread),
+    })
+}
+
+/// Search the index of the agent on `machine`, waiting at most `timeout` for
+/// each step. Hits come back tagged with the machine's name.
+pub fn search(
+    machine: &RemoteMachine,
+    query: &str,
+    use_regex: bool,
+    limit: usize,
+    timeout: Duration,
+) -> Result<Vec<SearchResult>, String> {
+    let addr = machine
+        .address
+        .to_socket_addrs()
+        .map_err(|e| format!("Unknown address {}: {}", machine.address, e))?
+        .next()
+        .ok_or_else(|| format!("Unknown address {}", machine.address))?;
+    let connect = || -> std::io::Result<Value> {
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let unexpected_end = || {
+            std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Agent closed the connection",
+            )
+        };
+
+        let mut line = Vec::new();
+        let greeting = read_message(&mut reader, &mut line)?.ok_or_else(unexpected_end)?;
+        let challenge = greeting
+            .get("challenge")
+            .and_then(|c| c.as_str())
+            .unwrap_or_default();
+        write_message(
+            &mut writer,
+            &json!({ "proof": proof(&machine.token, challenge) }),
+        )?;
+        let verdict = read_message(&mut reader, &mut line)?.ok_or_else(unexpected_end)?;
+        if verdict.get("ok").and_then(|ok| ok.as_bool()) != Some(true) {
+            return Ok(verdict);
+        }
+        write_message(
+            &mut writer,
+            &json!({ "query": query, "use_regex": use_regex, "limit": limit }),
+        )?;
+        read_message(&mut reader, &mut line)?.ok_or_else(unexpected_end)
+    };
+    let response = connect().map_err(|e| format!("{}: {}", machine.address, e))?;
+    if let Some(error) = response.get("error").and_then(|e| e.as_str()) {
+        return Err(error.to_string());
+    }
+    let results = response.get("results").cloned().unwrap_or_default();
+    let mut results: Vec<SearchResult> = serde_json::from_value(results)
+        .map_err(|e| format!("Unreadable results from {}: {}", machine.address, e))?;
+    for result in &mut results {
+        result.machine = Some(machine.name.clone());
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::IndexManager;
+    use crate::search::SearchIndex;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hmac() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            to_hex(&hmac(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert!(same("abc", "abc"));
+        assert!(!same("abc", "abd"));
+        assert!(!same("abc", "ab"));
+        let token = random_hex(24).unwrap();
+        assert_eq!(token.len(), 48);
+        assert_ne!(token, random_hex(24).unwrap());
+    }
+
+    #[test]
+    fn test_read_message_resumes_after_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+
+        // Cut inside the two bytes of the é
+        let sent = "{\"query\": \"caf\u{e9}\"}\n".as_bytes();
+        let (first, rest) = sent.split_at(sent.len() - 4);
+        client.write_all(first).unwrap();
+        let timed_out = read_message(&mut reader, &mut line).unwrap_err();
+        assert!(matches!(
+            timed_out.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ));
+        client.write_all(rest).unwrap();
+        let message = read_message(&mut reader, &mut line).unwrap().unwrap();
+        assert_eq!(message["query"], "caf\u{e9}");
+        assert!(line.is_empty());
+        drop(client);
+        assert!(read_message(&mut reader, &mut line).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let remote = RemoteMachine {
+            name: "nas".to_string(),
+            address: "nas.local:48650".to_string(),
+            token: "secret".to_string(),
+        };
+        assert!(validate(None, std::slice::from_ref(&remote)).is_ok());
+        assert!(validate(None, &[remote.clone(), remote.clone()]).is_err());
+        let no_port = RemoteMachine {
+            address: "nas.local".to_string(),
+            ..remote.clone()
+        };
+        assert!(validate(None, &[no_port]).is_err());
+        let short = AgentSettings {
+            port: DEFAULT_AGENT_PORT,
+            token: "short".to_string(),
+        };
+        assert!(validate(Some(&short), &[]).is_err());
+        assert!(validate(
+            Some(&AgentSettings::generate(DEFAULT_AGENT_PORT).unwrap()),
+            &[]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_agent_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("files");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("budget.xlsx"), "x").unwrap();
+        let index_manager = IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        let search_index = SearchIndex::new(&temp_dir.path().join("search")).unwrap();
+        let fields = search_index.fields().unwrap();
+        let mut writer = search_index.writer().unwrap();
+        for entity in index_manager.traverse_directory(&root).unwrap() {
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let settings = AgentSettings::generate(0).unwrap();
+        let agent = Agent::new(
+            Arc::new(RwLock::new(Some(Arc::new(search_index)))),
+            &settings.token,
+        );
+        let handle = start(agent, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let machine = RemoteMachine {
+            name: "nas".to_string(),
+            address: handle.addr().to_string(),
+            token: settings.token.clone(),
+        };
+        let timeout = Duration::from_secs(5);
+
+        let results = search(&machine, "budget", false, 10, timeout).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "budget.xlsx");
+        assert_eq!(results[0].machine.as_deref(), Some("nas"));
+        assert!(search(&machine, "(", true, 10, timeout)
+            .unwrap_err()
+            .starts_with("Invalid regex"));
+
+        let intruder = RemoteMachine {
+            token: "wrong-token-wrong-token".to_string(),
+            ..machine
+        };
+        assert_eq!(
+            search(&intruder, "budget", false, 10, timeout).unwrap_err(),
+            "Authentication failed"
+        );
+        handle.stop();
+    }
+}
@@ -0,0 +1,395 @@
+// Bulk renames: a pattern of regex replacement, case change and numbering
+// applied to many entries at once, previewed before anything is renamed
+
+use crate::error::{AppError, ErrorKind};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseTransform {
+    Lower,
+    Upper,
+    /// First letter of every word uppercase, the rest lowercase
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberPosition {
+    Prefix,
+    Suffix,
+}
+
+/// Sequence numbers added to names in the order the entries were given
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Numbering {
+    pub start: u64,
+    pub step: u64,
+    /// Zero-padded to this many digits
+    pub width: usize,
+    pub separator: String,
+    pub position: NumberPosition,
+}
+
+impl Default for Numbering {
+    fn default() -> Self {
+        Numbering {
+            start: 1,
+            step: 1,
+            width: 0,
+            separator: " ".to_string(),
+            position: NumberPosition::Suffix,
+        }
+    }
+}
+
+/// How `bulk_rename` changes each name, applied in field order
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RenamePattern {
+    /// Regex replaced everywhere in the name; `(?i)` makes it case-insensitive
+    pub find: Option<String>,
+    /// Replacement for `find`; `$1` and `${name}` refer to its groups
+    pub replace: String,
+    pub case: Option<CaseTransform>,
+    pub numbering: Option<Numbering>,
+    /// Change the extension too; by default only the part before it is
+    pub include_extension: bool,
+}
+
+/// One entry of a bulk rename
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RenameProposal {
+    pub from: String,
+    pub to: String,
+    /// Why the entry can't be renamed as proposed. Nothing is renamed while
+    /// any proposal of a batch has a conflict.
+    pub conflict: Option<String>,
+}
+
+/// Proposed new names of `paths`, with conflicts: invalid names, two
+/// entries ending up with the same name, and names already taken by entries
+/// not renamed away in the same batch
+pub fn plan(paths: &[PathBuf], pattern: &RenamePattern) -> Result<Vec<RenameProposal>, AppError> {
+    let find = pattern
+        .find
+        .as_deref()
+        .filter(|find| !find.is_empty())
+        .map(Regex::new)
+        .transpose()
+        .map_err(AppError::invalid_regex)?;
+    let sources: HashSet<String> = paths.iter().map(|path| case_key(path)).collect();
+    if let Some(nested) = paths.iter().find(|path| {
+        path.ancestors()
+            .skip(1)
+            .any(|a| sources.contains(&case_key(a)))
+    }) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "Entries can't be renamed together with a folder containing them",
+        )
+        .with_path(nested.to_string_lossy()));
+    }
+
+    let mut proposals = Vec::with_capacity(paths.len());
+    let mut targets: HashMap<String, usize> = HashMap::new();
+    for (index, path) in paths.iter().enumerate() {
+        let from = path.to_string_lossy().to_string();
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(
+                AppError::new(ErrorKind::InvalidInput, "Can't rename a root").with_path(from),
+            );
+        };
+        let name = name.to_string_lossy();
+        let new_name = rename(&name, index as u64, pattern, find.as_ref());
+        let target = parent.join(&new_name);
+        let key = case_key(&target);
+        let conflict = if path.symlink_metadata().is_err() {
+            Some("File not found".to_string())
+        } else if let Err(e) = crate::explorer::validate_entry_name(&new_name) {
+            Some(e.message)
+        } else if let Some(&other) = targets.get(&key) {
+            Some(format!("Same new name as {}", paths[other].display()))
+        } else if target != *path && target.symlink_metadata().is_ok() && !sources.contains(&key) {
+            Some("An entry with this name exists".to_string())
+        } else {
+            None
+        };
+        targets.insert(key, index);
+        proposals.push(RenameProposal {
+            from,
+            to: target.to_string_lossy().to_string(),
+            conflict,
+        });
+    }
+    Ok(proposals)
+}
+
+/// `name` renamed by `pattern` as the `index`th entry of its batch
+fn rename(name: &str, index: u64, pattern: &RenamePattern, find: Option<&Regex>) -> String {
+    let (stem, extension) = match Path::new(name).extension() {
+        Some(extension) if !pattern.include_extension => {
+            let extension = extension.to_string_lossy();
+            (&name[..name.len() - extension.len() - 1], Some(extension))
+        }
+        _ => (name, None),
+    };
+    let mut stem = match find {
+        Some(find) => find.replace_all(stem, pattern.replace.as_str()).to_string(),
+        None => stem.to_string(),
+    };
+    if let Some(case) = pattern.case {
+        stem = change_case(&stem, case);
+    }
+    if let Some(numbering) = &pattern.numbering {
+        let number = format!(
+            "{:0width$}",
+            numbering
+                .start
+                .saturating_add(numbering.step.saturating_mul(index)),
+            width = numbering.width
+        );
+        stem = match numbering.position {
+            NumberPosition::Prefix => format!("{}{}{}", number, numbering.separator, stem),
+            NumberPosition::Suffix => format!("{}{}{}", stem, numbering.separator, number),
+        };
+    }
+    match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem,
+    }
+}
+
+fn change_case(text: &str, case: CaseTransform) -> String {
+    match case {
+        CaseTransform::Lower => text.to_lowercase(),
+        CaseTransform::Upper => text.to_uppercase(),
+        CaseTransform::Title => {
+            let mut titled = String::with_capacity(text.len());
+            let mut word_start = true;
+            for c in text.chars() {
+                if word_start {
+                    titled.extend(c.to_uppercase());
+                } else {
+                    titled.extend(c.to_lowercase());
+                }
+                word_start = !c.is_alphanumeric() && c != '\'';
+            }
+            titled
+        }
+    }
+}
+
+/// Key two paths share when they name the same entry: case-insensitive file
+/// systems are the default on Windows and macOS
+fn case_key(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(any(windows, target_os = "macos")) {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Carry out `proposals` from `plan`, all or nothing: when one rename fails
+/// the ones done are undone. Entries whose names swap or shift along a chain
+/// are moved through temporary names first. Returns the renames done.
+pub fn apply(proposals: &[RenameProposal]) -> Result<Vec<(PathBuf, PathBuf)>, AppError> {
+    if let Some(conflict) = proposals.iter().find(|p| p.conflict.is_some()) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            conflict.conflict.clone().unwrap_or_default(),
+        )
+        .with_path(&conflict.from));
+    }
+    let renames: Vec<(PathBuf, PathBuf)> = proposals
+        .iter()
+        .filter(|p| p.from != p.to)
+        .map(|p| (PathBuf::from(&p.from), PathBuf::from(&p.to)))
+        .collect();
+    let sources: HashSet<String> = renames.iter().map(|(from, _)| case_key(from)).collect();
+    let chained = renames.iter().any(|(from, to)| {
+        let key = case_key(to);
+        key != case_key(from) && sources.contains(&key)
+    });
+
+    let steps: Vec<(PathBuf, PathBuf)> = if chained {
+        let staged: Vec<PathBuf> = renames
+            .iter()
+            .enumerate()
+            .map(|(i, (from, _))| {
+                let name = from.file_name().unwrap_or_default().to_string_lossy();
+                from.with_file_name(format!(".{}.renaming-{}", name, i))
+            })
+            .collect();
+        let to_staged = renames
+            .iter()
+            .zip(&staged)
+            .map(|((from, _), s)| (from.clone(), s.clone()));
+        let from_staged = renames
+            .iter()
+            .zip(&staged)
+            .map(|((_, to), s)| (s.clone(), to.clone()));
+        to_staged.chain(from_staged).collect()
+    } else {
+        renames.clone()
+    };
+
+    for (done, (from, to)) in steps.iter().enumerate() {
+        if let Err(e) = std::fs::rename(from, to) {
+            for (undo_from, undo_to) in steps[..done].iter().rev() {
+                if let Err(undo) = std::fs::rename(undo_to, undo_from) {
+                    log::error!(
+                        "Failed to undo rename of {} to {}: {}",
+                        undo_from.display(),
+                        undo_to.display(),
+                        undo
+                    );
+                }
+            }
+            return Err(AppError::io(from.to_string_lossy(), &e));
+        }
+    }
+    log::info!("Renamed {} entries", renames.len());
+    Ok(renames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renamed(name: &str, index: u64, pattern: &RenamePattern) -> String {
+        let find = pattern.find.as_deref().map(|f| Regex::new(f).unwrap());
+        rename(name, index, pattern, find.as_ref())
+    }
+
+    #[test]
+    fn test_rename_transforms() {
+        let pattern = RenamePattern {
+            find: Some(r"IMG_(\d+)".to_string()),
+            replace: "trip $1".to_string(),
+            case: Some(CaseTransform::Title),
+            numbering: Some(Numbering {
+                width: 3,
+                separator: "-".to_string(),
+                position: NumberPosition::Prefix,
+                ..Default::default()
+            }),
+            include_extension: false,
+        };
+        assert_eq!(renamed("IMG_0412.JPG", 0, &pattern), "001-Trip 0412.JPG");
+        assert_eq!(renamed("IMG_0413.JPG", 1, &pattern), "002-Trip 0413.JPG");
+
+        let lower = RenamePattern {
+            case: Some(CaseTransform::Lower),
+            ..Default::default()
+        };
+        assert_eq!(renamed("README.MD", 0, &lower), "readme.MD");
+        let lower_all = RenamePattern {
+            include_extension: true,
+            ..lower
+        };
+        assert_eq!(renamed("README.MD", 0, &lower_all), "readme.md");
+        assert_eq!(renamed(".Bashrc", 0, &lower_all), ".bashrc");
+
+        let numbered = RenamePattern {
+            numbering: Some(Numbering {
+                start: 10,
+                step: 5,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(renamed("notes.txt", 2, &numbered), "notes 20.txt");
+        assert_eq!(
+            change_case("o'neil's_NOTES", CaseTransform::Title),
+            "O'neil's_Notes"
+        );
+    }
+
+    #[test]
+    fn test_plan_reports_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "taken.txt"] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+        let paths = |names: &[&str]| -> Vec<PathBuf> {
+            names.iter().map(|name| dir.path().join(name)).collect()
+        };
+        let replace = |find: &str, replace: &str| RenamePattern {
+            find: Some(find.to_string()),
+            replace: replace.to_string(),
+            ..Default::default()
+        };
+
+        let proposals = plan(&paths(&["a.txt", "b.txt"]), &replace("^[ab]$", "c")).unwrap();
+        assert_eq!(proposals[0].conflict, None);
+        assert!(proposals[1].conflict.is_some(), "Both would become c.txt");
+
+        let proposals = plan(&paths(&["a.txt"]), &replace("a", "taken")).unwrap();
+        assert!(proposals[0].conflict.is_some());
+        let proposals = plan(&paths(&["a.txt"]), &replace("a", "x/y")).unwrap();
+        assert!(proposals[0].conflict.is_some());
+        let proposals = plan(&paths(&["gone.txt"]), &replace("g", "h")).unwrap();
+        assert!(proposals[0].conflict.is_some());
+        assert!(plan(&paths(&["a.txt"]), &replace("(", "")).is_err());
+        assert!(plan(
+            &[dir.path().to_path_buf(), dir.path().join("a.txt")],
+            &replace("a", "z")
+        )
+        .is_err());
+
+        assert!(apply(&plan(&paths(&["a.txt"]), &replace("a", "taken")).unwrap()).is_err());
+        assert!(
+            dir.path().join("a.txt").exists(),
+            "Nothing renamed on conflict"
+        );
+    }
+
+    #[test]
+    fn test_apply_swaps_names() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+        let pattern = RenamePattern {
+            find: Some("^(a|b)$".to_string()),
+            replace: "x$1".to_string(),
+            ..Default::default()
+        };
+        let paths = vec![dir.path().join("a.txt"), dir.path().join("c.txt")];
+        let done = apply(&plan(&paths, &pattern).unwrap()).unwrap();
+        assert_eq!(
+            done,
+            vec![(dir.path().join("a.txt"), dir.path().join("xa.txt"))]
+        );
+
+        let to = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        let swap = vec![
+            RenameProposal {
+                from: to("b.txt"),
+                to: to("c.txt"),
+                conflict: None,
+            },
+            RenameProposal {
+                from: to("c.txt"),
+                to: to("b.txt"),
+                conflict: None,
+            },
+        ];
+        apply(&swap).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b.txt")).unwrap(),
+            "c.txt"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("c.txt")).unwrap(),
+            "b.txt"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+}
@@ -1,18 +1,1352 @@
 // Search logic with tantivy
 
-use std::path::Path;
+use chrono::{Datelike, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::ops::Bound;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use tantivy::{
-    collector::TopDocs,
+    collector::{Count, DocSetCollector, FacetCollector, TopDocs},
     directory::MmapDirectory,
-    query::{Query, QueryParser, RegexQuery},
-    schema::{Schema, SchemaBuilder, STORED, TEXT},
-    Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument,
+    query::{
+        AllQuery, BooleanQuery, BoostQuery, MoreLikeThisQuery, Occur, PhrasePrefixQuery,
+        PhraseQuery, Query, QueryParser, RangeQuery, RegexQuery, TermQuery,
+    },
+    schema::{
+        Facet, FacetOptions, Field, IndexRecordOption, OwnedValue, Schema, SchemaBuilder,
+        TextFieldIndexing, TextOptions, Value, FAST, INDEXED, STORED, STRING, TEXT,
+    },
+    tokenizer::{RemoveLongFilter, TextAnalyzer, TokenStream},
+    DocAddress, Index, IndexReader, IndexWriter, Order, ReloadPolicy, Score, Searcher,
+    TantivyDocument, Term,
 };
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+mod cancel;
+mod cjk;
+mod code;
+mod complete;
+mod folders;
+mod glob;
+mod lang;
+mod prefix;
+mod spelling;
+mod stream;
+mod translit;
+
+pub use cancel::CancelToken;
+use cancel::Cancellable;
+use complete::CompletionIndex;
+use folders::FolderCollector;
+use stream::ChunkCollector;
+
+/// Version of the schema and of the documents built for it. Bump it with
+/// every change to either, so existing indexes are migrated on open.
+pub const SCHEMA_VERSION: u32 = 15;
+
+/// File in the index directory recording the `SCHEMA_VERSION` it was built with
+const SCHEMA_VERSION_FILE: &str = "schema_version";
+
+/// Most hard links reported for one file
+const LINK_LIMIT: usize = 1000;
+
+/// Most words of a name, note or subject that similar entries are matched on
+const SIMILAR_MAX_TERMS: usize = 25;
+
+/// Most hits a ranked search returns; larger result sets are streamed
+pub const MAX_RANKED_RESULTS: usize = 1000;
+/// Completion candidates fetched per one wanted while some paths are excluded
+const HIDDEN_COMPLETION_FACTOR: usize = 4;
+/// Time a search may take unless the caller sets its own timeout
+pub const DEFAULT_SEARCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Hits per chunk when streaming, unless the caller picks a size
+pub const DEFAULT_CHUNK_SIZE: usize = 500;
+/// Chunks collected ahead of the consumer before the search waits for it
+const CHUNK_BACKLOG: usize = 4;
+
+/// Most values reported per facet
+pub const FACET_LIMIT: usize = 50;
+
+/// tantivy's per-thread writer memory bounds, in MB
+const MIN_HEAP_MB_PER_THREAD: usize = 15;
+const MAX_HEAP_MB_PER_THREAD: usize = 4000;
+/// Most writer threads a user may request
+const MAX_WRITER_THREADS: usize = 32;
+
+/// One clause of a `BooleanQuery`
+type Clause = (Occur, Box<dyn Query>);
+
+/// Fields text queries search, with their boosts. Exact matches score on
+/// both name and path, accent- or width-variant ones on the folded fields
+/// alone, CJK fragments on the name n-grams, the words of identifiers like
+/// "MyHttpServer" on the code words, Korean or pinyin initials on the
+/// transliterated name, words of the user's notes on the folded note, words
+/// read in images on the folded OCR text, and other forms of a word on the
+/// stemmed name and the note in its language.
+const EXACT_FIELDS: [(&str, f32); 2] = [("name", 2.0), ("path", 1.0)];
+const FOLDED_FIELDS: [(&str, f32); 12] = [
+    ("name_folded", 2.0),
+    ("path_folded", 1.0),
+    ("name_ngram", 1.5),
+    ("name_words", 1.5),
+    ("name_initials", 0.5),
+    ("note_folded", 1.0),
+    ("ocr_text_folded", 0.5),
+    ("name_en", 0.5),
+    ("name_de", 0.5),
+    ("note_en", 0.5),
+    ("note_de", 0.5),
+    ("note_ko", 0.5),
+];
+/// Boost of names with a word starting with a plain query
+const PREFIX_BOOST: f32 = 2.0;
+
+/// Text fields every name search reads, warmed after the index is loaded
+const WARM_TEXT_FIELDS: [&str; 6] = [
+    "name",
+    "name_folded",
+    "name_ngram",
+    "name_words",
+    "name_initials",
+    "name_prefix",
+];
+/// Fast fields behind the size and date sort orders
+const WARM_FAST_FIELDS: [&str; 3] = ["size", "modified", "created"];
+
+/// Memory budget and worker threads of every index writer
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct WriterOptions {
+    /// Total heap shared by the writer threads, in MB
+    pub heap_mb: usize,
+    /// 0 lets tantivy choose from the CPU count and heap
+    pub threads: usize,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WRITER_DEFAULTS
+    }
+}
+
+const WRITER_DEFAULTS: WriterOptions = WriterOptions {
+    heap_mb: 50,
+    threads: 0,
+};
+
+impl WriterOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threads > MAX_WRITER_THREADS {
+            return Err(format!(
+                "Writer threads must be at most {}",
+                MAX_WRITER_THREADS
+            ));
+        }
+        let threads = self.threads.max(1);
+        if self.heap_mb < MIN_HEAP_MB_PER_THREAD * threads {
+            return Err(format!(
+                "Writer heap must be at least {} MB for {} thread(s)",
+                MIN_HEAP_MB_PER_THREAD * threads,
+                threads
+            ));
+        }
+        if self.heap_mb / threads > MAX_HEAP_MB_PER_THREAD {
+            return Err(format!(
+                "Writer heap must be at most {} MB per thread",
+                MAX_HEAP_MB_PER_THREAD
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Options used by `SearchIndex::writer`, shared by every index the process opens
+static WRITER_OPTIONS: RwLock<WriterOptions> = RwLock::new(WRITER_DEFAULTS);
+
+pub fn writer_options() -> WriterOptions {
+    *WRITER_OPTIONS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Apply `options` to writers created from now on
+pub fn set_writer_options(options: WriterOptions) -> Result<(), String> {
+    options.validate()?;
+    *WRITER_OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = options;
+    Ok(())
+}
+
+/// Most search sessions kept for refinement
+const SESSION_LIMIT: usize = 32;
+
+/// Most searches kept in the query cache
+const CACHE_LIMIT: usize = 64;
+
+/// Files modified within this many days are recently modified
+const RECENT_DAYS: u64 = 7;
+
+/// Files at least this large are large files
+const LARGE_FILE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Extensions grouped into the file types offered as search facets
+const FILE_TYPES: &[(&str, &[&str])] = &[
+    (
+        "document",
+        &[
+            "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "xls", "xlsx", "ods", "csv", "ppt",
+            "pptx", "odp", "epub",
+        ],
+    ),
+    (
+        "image",
+        &[
+            "png", "jpg", "jpeg", "gif", "bmp", "svg", "webp", "tif", "tiff", "heic", "ico",
+        ],
+    ),
+    ("audio", &["mp3", "wav", "flac", "aac", "ogg", "m4a", "wma"]),
+    ("video", &["mp4", "mkv", "avi", "mov", "webm", "wmv", "m4v"]),
+    (
+        "archive",
+        &["zip", "tar", "gz", "tgz", "7z", "rar", "bz2", "xz", "iso"],
+    ),
+    (
+        "code",
+        &[
+            "rs", "ts", "tsx", "js", "jsx", "py", "c", "h", "cpp", "hpp", "cs", "java", "go", "rb",
+            "php", "sh", "json", "toml", "yaml", "yml", "html", "css", "sql",
+        ],
+    ),
+];
+
+/// Lowercased extension of a file entity; folders have none
+fn entity_extension(entity: &crate::FileEntity) -> Option<String> {
+    if entity.is_folder {
+        return None;
+    }
+    Path::new(&entity.name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+}
+
+/// Fold text for accent- and width-insensitive matching: compatibility
+/// decomposition with combining marks dropped, then recomposed so Hangul and
+/// kana compare equal however they were composed. Kana voicing marks are kept
+/// because they change the word.
+pub fn fold(text: &str) -> String {
+    text.nfkd()
+        .filter(|&c| !is_combining_mark(c) || matches!(c, '\u{3099}' | '\u{309A}'))
+        .nfc()
+        .collect()
+}
+
+/// File type facet value for an entity
+pub fn file_type(entity: &crate::FileEntity) -> &'static str {
+    if entity.is_folder {
+        return "folder";
+    }
+    let Some(extension) = entity_extension(entity) else {
+        return "other";
+    };
+    FILE_TYPES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(file_type, _)| *file_type)
+        .unwrap_or("other")
+}
+
+/// Hierarchical facet for a directory, one segment per path component, so a
+/// facet query for a directory also covers everything below it
+pub fn dir_facet(path: &Path) -> Facet {
+    Facet::from_path(path.components().filter_map(|component| match component {
+        Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().to_string()),
+        Component::Normal(segment) => Some(segment.to_string_lossy().to_string()),
+        _ => None,
+    }))
+}
+
+/// Whether `query_str` is only words of letters and digits, which plain_query
+/// handles without the query parser
+fn is_plain(query_str: &str) -> bool {
+    !query_str.trim().is_empty()
+        && query_str.split_whitespace().all(|word| {
+            word.chars().all(char::is_alphanumeric) && !matches!(word, "AND" | "OR" | "NOT")
+        })
+}
+
+/// Token texts `analyzer` makes of `text`
+fn tokens_of(analyzer: &mut TextAnalyzer, text: &str) -> Vec<String> {
+    let mut stream = analyzer.token_stream(text);
+    let mut tokens = Vec::new();
+    while stream.advance() {
+        tokens.push(stream.token().text.clone());
+    }
+    tokens
+}
+
+/// Top `limit` matches of `query`, by the highest values of the fast field
+/// `sort` when given and by score otherwise
+fn top_by(
+    searcher: &Searcher,
+    query: &dyn Query,
+    sort: Option<&str>,
+    limit: usize,
+) -> Result<Vec<DocAddress>, tantivy::TantivyError> {
+    Ok(match sort {
+        Some(field) => searcher
+            .search(
+                query,
+                &TopDocs::with_limit(limit).order_by_u64_field(field, Order::Desc),
+            )?
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect(),
+        None => searcher
+            .search(query, &TopDocs::with_limit(limit))?
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect(),
+    })
+}
+
+fn retrieve_addresses(
+    searcher: &Searcher,
+    addresses: &[DocAddress],
+) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+    addresses
+        .iter()
+        .map(|address| searcher.doc(*address))
+        .collect()
+}
+
+fn retrieve(
+    searcher: &Searcher,
+    top_docs: Vec<(Score, DocAddress)>,
+) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+    top_docs
+        .into_iter()
+        .map(|(_score, doc_address)| searcher.doc(doc_address))
+        .collect()
+}
+
+/// Schema fields resolved once, so bulk indexing doesn't look them up per document
+#[derive(Debug, Clone, Copy)]
+pub struct DocFields {
+    pub id: Field,
+    pub name: Field,
+    pub path: Field,
+    pub name_folded: Field,
+    pub path_folded: Field,
+    pub name_ngram: Field,
+    pub name_initials: Field,
+    pub name_prefix: Field,
+    pub name_words: Field,
+    pub name_raw: Field,
+    pub size: Field,
+    pub modified: Field,
+    pub is_folder: Field,
+    pub hidden: Field,
+    pub readonly: Field,
+    pub uid: Field,
+    pub gid: Field,
+    pub mode: Field,
+    pub created: Field,
+    pub accessed: Field,
+    pub broken_link: Field,
+    pub file_id: Field,
+    pub is_symlink: Field,
+    pub link_target: Field,
+    pub online_only: Field,
+    pub repo: Field,
+    pub repo_root: Field,
+    pub owner: Field,
+    pub owner_name: Field,
+    pub git_status: Field,
+    pub project: Field,
+    pub project_marker: Field,
+    pub label: Field,
+    pub note: Field,
+    pub note_folded: Field,
+    pub ocr_text_folded: Field,
+    /// `name_en` and `name_de`, in `lang::STEMMED` order
+    pub name_stemmed: [Field; 2],
+    /// Note field of each of `lang::Language::ALL`
+    pub note_languages: [(lang::Language, Field); 3],
+    pub rating: Field,
+    pub email_from: Field,
+    pub email_from_folded: Field,
+    pub email_subject: Field,
+    pub email_subject_folded: Field,
+    pub email_date: Field,
+    pub extension: Field,
+    pub file_type: Field,
+    pub dir: Field,
+}
+
+impl DocFields {
+    pub fn build_document(&self, entity: &crate::FileEntity) -> TantivyDocument {
+        let mut doc = TantivyDocument::default();
+        doc.add_text(self.id, &entity.id);
+        // Names are indexed in NFC; the path stays as on disk so it can be opened
+        doc.add_text(self.name, entity.name.nfc().collect::<String>());
+        doc.add_text(self.path, &entity.path);
+        let name_folded = fold(&entity.name);
+        doc.add_text(self.name_ngram, &name_folded);
+        doc.add_text(self.name_initials, translit::initials(&name_folded));
+        doc.add_text(self.name_prefix, &name_folded);
+        doc.add_text(self.name_words, &name_folded);
+        doc.add_text(self.name_raw, &name_folded);
+        for field in self.name_stemmed {
+            doc.add_text(field, &name_folded);
+        }
+        doc.add_text(self.name_folded, name_folded);
+        doc.add_text(self.path_folded, fold(&entity.path));
+        doc.add_u64(self.size, entity.size);
+        doc.add_date(
+            self.modified,
+            tantivy::DateTime::from_timestamp_secs(entity.modified),
+        );
+        doc.add_bool(self.is_folder, entity.is_folder);
+        doc.add_bool(self.hidden, entity.hidden);
+        let attributes = &entity.attributes;
+        doc.add_bool(self.readonly, attributes.readonly);
+        doc.add_bool(self.broken_link, attributes.broken_link);
+        if let Some(file_id) = &attributes.file_id {
+            doc.add_text(self.file_id, file_id);
+        }
+        doc.add_bool(self.is_symlink, attributes.is_symlink);
+        if let Some(target) = &attributes.link_target {
+            doc.add_text(self.link_target, target);
+        }
+        doc.add_bool(self.online_only, attributes.online_only);
+        if let Some(owner) = &attributes.owner {
+            doc.add_text(self.owner, owner.to_lowercase());
+            doc.add_text(self.owner_name, owner);
+        }
+        if let Some(root) = &attributes.repo_root {
+            doc.add_text(self.repo, crate::git::repo_name(Path::new(root)));
+            doc.add_text(self.repo_root, root);
+        }
+        if let Some(status) = attributes.git_status {
+            doc.add_text(self.git_status, status.as_str());
+        }
+        if !attributes.project_markers.is_empty() {
+            doc.add_text(
+                self.project,
+                crate::project::project_name(Path::new(&entity.path)),
+            );
+        }
+        for marker in &attributes.project_markers {
+            doc.add_text(self.project_marker, marker.as_str());
+        }
+        if let Some(label) = attributes.label {
+            doc.add_text(self.label, label.as_str());
+        }
+        if let Some(rating) = attributes.rating {
+            doc.add_u64(self.rating, rating as u64);
+        }
+        if let Some(note) = &attributes.note {
+            doc.add_text(self.note, note);
+            doc.add_text(self.note_folded, fold(note));
+            if let Some(language) = lang::detect(note) {
+                for (_, field) in self.note_languages.iter().filter(|(l, _)| *l == language) {
+                    doc.add_text(*field, fold(note));
+                }
+            }
+        }
+        if let Some(text) = &attributes.ocr_text {
+            doc.add_text(self.ocr_text_folded, fold(text));
+        }
+        if let Some(email) = &attributes.email {
+            for (field, folded, value) in [
+                (self.email_from, self.email_from_folded, &email.from),
+                (
+                    self.email_subject,
+                    self.email_subject_folded,
+                    &email.subject,
+                ),
+            ] {
+                if let Some(value) = value {
+                    doc.add_text(field, value);
+                    doc.add_text(folded, fold(value));
+                }
+            }
+            if let Some(secs) = email.date {
+                doc.add_i64(self.email_date, secs);
+            }
+        }
+        for (field, value) in [
+            (self.uid, attributes.uid),
+            (self.gid, attributes.gid),
+            (self.mode, attributes.mode),
+        ] {
+            if let Some(value) = value {
+                doc.add_u64(field, value as u64);
+            }
+        }
+        for (field, value) in [
+            (self.created, entity.created),
+            (self.accessed, entity.accessed),
+        ] {
+            if let Some(secs) = value {
+                doc.add_date(field, tantivy::DateTime::from_timestamp_secs(secs));
+            }
+        }
+        if let Some(extension) = entity_extension(entity) {
+            doc.add_facet(self.extension, Facet::from_path([extension]));
+        }
+        doc.add_facet(self.file_type, Facet::from_path([file_type(entity)]));
+        if let Some(parent) = Path::new(&entity.path).parent() {
+            doc.add_facet(self.dir, dir_facet(parent));
+        }
+        doc
+    }
+
+    /// Read a stored document back into the entity it was built from
+    pub fn read_entity(&self, doc: &TantivyDocument) -> crate::FileEntity {
+        let text = |field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let optional_text = |field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        let number = |field| doc.get_first(field).and_then(|v| v.as_u64());
+        let timestamp = |field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_datetime())
+                .map(|d| d.into_timestamp_secs())
+        };
+        let flag = |field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+        };
+
+        crate::FileEntity {
+            id: text(self.id),
+            name: text(self.name),
+            path: text(self.path),
+            size: number(self.size).unwrap_or(0),
+            modified: timestamp(self.modified).unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs() as i64
+            }),
+            is_folder: flag(self.is_folder),
+            hidden: flag(self.hidden),
+            attributes: crate::FileAttributes {
+                readonly: flag(self.readonly),
+                uid: number(self.uid).map(|v| v as u32),
+                gid: number(self.gid).map(|v| v as u32),
+                mode: number(self.mode).map(|v| v as u32),
+                broken_link: flag(self.broken_link),
+                file_id: doc
+                    .get_first(self.file_id)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                is_symlink: flag(self.is_symlink),
+                online_only: flag(self.online_only),
+                owner: doc
+                    .get_first(self.owner_name)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                // Only kept in sled
+                ocr_text: None,
+                link_target: doc
+                    .get_first(self.link_target)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                repo_root: doc
+                    .get_first(self.repo_root)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                git_status: doc
+                    .get_first(self.git_status)
+                    .and_then(|v| v.as_str())
+                    .and_then(crate::git::GitStatus::parse),
+                project_markers: project_markers(doc, self.project_marker),
+                label: doc
+                    .get_first(self.label)
+                    .and_then(|v| v.as_str())
+                    .and_then(crate::marks::ColorLabel::parse),
+                rating: number(self.rating).map(|v| v as u8),
+                note: doc
+                    .get_first(self.note)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                email: {
+                    let email = crate::email::EmailHeaders {
+                        from: optional_text(self.email_from),
+                        subject: optional_text(self.email_subject),
+                        date: doc.get_first(self.email_date).and_then(|v| v.as_i64()),
+                    };
+                    (email != crate::email::EmailHeaders::default()).then_some(email)
+                },
+            },
+            created: timestamp(self.created),
+            accessed: timestamp(self.accessed),
+        }
+    }
+}
+
+/// Markers stored for a project root, in the order they were found
+fn project_markers(doc: &TantivyDocument, field: Field) -> Vec<crate::project::ProjectMarker> {
+    doc.get_all(field)
+        .filter_map(|v| v.as_str())
+        .filter_map(crate::project::ProjectMarker::parse)
+        .collect()
+}
+
+/// One search hit as returned to clients, and by agents on other machines
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchResult {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub modified: String, // ISO 8601
+    pub is_folder: bool,
+    pub hidden: bool,
+    pub created: Option<String>,
+    pub accessed: Option<String>,
+    pub attributes: crate::FileAttributes,
+    pub matches: MatchSpans,
+    /// Carries a downloaded-from-internet marker; see `quarantine`
+    pub quarantined: bool,
+    /// Profile the hit came from when searching across all profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Remote machine the hit came from; see `remote`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine: Option<String>,
+    /// Label of the volume snapshot the hit came from; the disk may be
+    /// unplugged. See `snapshot`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    /// Size and dates as shown, in the user's locale; see `format::annotate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<crate::format::ResultDisplay>,
+}
+
+impl SearchResult {
+    /// Rough heap and inline size, for cache accounting
+    pub fn approx_bytes(&self) -> usize {
+        let strings = [Some(&self.name), Some(&self.path), Some(&self.modified)]
+            .into_iter()
+            .chain([self.created.as_ref(), self.accessed.as_ref()])
+            .chain([
+                self.attributes.file_id.as_ref(),
+                self.attributes.link_target.as_ref(),
+                self.attributes.repo_root.as_ref(),
+                self.profile.as_ref(),
+                self.machine.as_ref(),
+                self.snapshot.as_ref(),
+            ])
+            .flatten()
+            .map(String::len)
+            .sum::<usize>();
+        let spans = (self.matches.name.len() + self.matches.path.len())
+            * std::mem::size_of::<(usize, usize)>();
+        std::mem::size_of::<SearchResult>() + strings + spans
+    }
+}
+
+impl From<crate::FileEntity> for SearchResult {
+    fn from(entity: crate::FileEntity) -> Self {
+        SearchResult {
+            name: entity.name,
+            path: entity.path,
+            size: entity.size,
+            modified: crate::format_timestamp_iso8601(entity.modified),
+            is_folder: entity.is_folder,
+            hidden: entity.hidden,
+            created: entity.created.map(crate::format_timestamp_iso8601),
+            accessed: entity.accessed.map(crate::format_timestamp_iso8601),
+            attributes: entity.attributes,
+            matches: MatchSpans::default(),
+            quarantined: false,
+            profile: None,
+            machine: None,
+            snapshot: None,
+            display: None,
+        }
+    }
+}
+
+/// Matched fragments of a hit as half-open `[start, end)` ranges counted in
+/// chars (Unicode scalar values), so clients can bold them without re-running
+/// the query
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MatchSpans {
+    pub name: Vec<(usize, usize)>,
+    pub path: Vec<(usize, usize)>,
+}
+
+/// Post-match pass that locates the tokens a query matched in a hit's name
+/// and path, using the same tokenizer as the indexed fields
+pub struct Highlighter {
+    analyzer: TextAnalyzer,
+    /// Lowercased, folded query terms for text queries
+    terms: HashSet<String>,
+    /// Whole-token pattern for regex queries, which only search the name
+    regex: Option<regex::Regex>,
+    /// Last word of a plain query, which also matches the start of name words
+    prefix: Option<String>,
+}
+
+impl Highlighter {
+    /// Fill in `result.matches`
+    pub fn highlight(&mut self, result: &mut SearchResult) {
+        result.matches = MatchSpans {
+            name: self.spans(&result.name, true),
+            path: if self.regex.is_some() {
+                Vec::new()
+            } else {
+                self.spans(&result.path, false)
+            },
+        };
+    }
+
+    fn spans(&mut self, text: &str, is_name: bool) -> Vec<(usize, usize)> {
+        let mut byte_spans = Vec::new();
+        let prefix = self.prefix.as_deref().filter(|_| is_name);
+        let mut stream = self.analyzer.token_stream(text);
+        while stream.advance() {
+            let token = stream.token();
+            let matched = match &self.regex {
+                Some(regex) => regex.is_match(&token.text),
+                None => self.terms.contains(&fold(&token.text)),
+            };
+            if matched {
+                byte_spans.push((token.offset_from, token.offset_to));
+            } else if let Some(prefix) = prefix.filter(|p| fold(&token.text).starts_with(p)) {
+                // Only the typed part of the word
+                let word = &text[token.offset_from..token.offset_to];
+                let end = word
+                    .char_indices()
+                    .nth(prefix.chars().count())
+                    .map_or(word.len(), |(at, _)| at);
+                byte_spans.push((token.offset_from, token.offset_from + end));
+            }
+        }
+        // Words inside identifiers, like "Http" in "MyHttpServer"
+        if is_name && self.regex.is_none() {
+            byte_spans.extend(
+                code::word_spans(text).into_iter().filter(|&(from, to)| {
+                    self.terms.contains(&fold(&text[from..to].to_lowercase()))
+                }),
+            );
+        }
+        // CJK terms match inside longer names, like the n-gram field does
+        if self.regex.is_none() {
+            for term in self.terms.iter().filter(|t| t.chars().any(cjk::is_cjk)) {
+                byte_spans.extend(
+                    text.match_indices(term.as_str())
+                        .map(|(at, found)| (at, at + found.len())),
+                );
+            }
+            byte_spans.sort();
+            byte_spans.dedup();
+        }
+        byte_spans
+            .into_iter()
+            .map(|(from, to)| {
+                let start = text[..from].chars().count();
+                (start, start + text[from..to].chars().count())
+            })
+            .collect()
+    }
+}
+
+/// Number of hits sharing one facet value
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u64,
+}
+
+impl FacetCount {
+    fn from_pairs(pairs: impl IntoIterator<Item = (String, u64)>) -> Vec<FacetCount> {
+        pairs
+            .into_iter()
+            .map(|(value, count)| FacetCount { value, count })
+            .collect()
+    }
+
+    /// Most frequent first, ties by value
+    fn order(a: &FacetCount, b: &FacetCount) -> std::cmp::Ordering {
+        b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value))
+    }
+}
+
+/// Aggregate hit counts for a filter sidebar. Folders are the directories
+/// directly below each indexed root, reported as full paths.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct FacetCounts {
+    pub extensions: Vec<FacetCount>,
+    pub file_types: Vec<FacetCount>,
+    pub folders: Vec<FacetCount>,
+}
+
+impl FacetCounts {
+    /// Add counts from another index, e.g. when searching every profile
+    pub fn merge(&mut self, other: FacetCounts) {
+        for (mine, theirs) in [
+            (&mut self.extensions, other.extensions),
+            (&mut self.file_types, other.file_types),
+            (&mut self.folders, other.folders),
+        ] {
+            for count in theirs {
+                match mine.iter_mut().find(|c| c.value == count.value) {
+                    Some(existing) => existing.count += count.count,
+                    None => mine.push(count),
+                }
+            }
+            mine.sort_by(FacetCount::order);
+            mine.truncate(FACET_LIMIT);
+        }
+    }
+}
+
+/// Hits directly inside one folder
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FolderGroup {
+    pub path: String,
+    pub count: u64,
+}
+
+/// Hits of a search collapsed into the folders containing them
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct FolderGroups {
+    /// All matches, including those without a parent folder
+    pub total_found: usize,
+    /// Folders with at least one match, before `folders` was truncated
+    pub total_folders: usize,
+    /// Most matches first, ties by path
+    pub folders: Vec<FolderGroup>,
+}
+
+/// Output of the `search_files` command
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    pub total_found: usize,
+    pub search_time_ms: u64,
+    /// Present when the request asked for facet counts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<FacetCounts>,
+    /// Pass back as `refine` to search within these results
+    pub session_id: u64,
+    /// Corrected queries to offer when nothing was found
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+/// One query of a search session, as the caller sent it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchStep {
+    pub query: String,
+    pub use_regex: bool,
+    pub filter: SearchFilter,
+}
+
+/// A search and the searches it refined, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSession {
+    /// Profile selector the session was searched with
+    pub profile: String,
+    pub steps: Vec<SearchStep>,
+}
+
+impl SearchSession {
+    /// Filter restricting a search to this session's hits
+    pub fn within(&self, filter: SearchFilter) -> SearchFilter {
+        SearchFilter {
+            within: self.steps.clone(),
+            ..filter
+        }
+    }
+}
+
+/// Recent search sessions by id, dropping the oldest past `SESSION_LIMIT`
+#[derive(Debug, Default)]
+pub struct SearchSessions {
+    next_id: u64,
+    sessions: VecDeque<(u64, SearchSession)>,
+}
+
+impl SearchSessions {
+    pub fn insert(&mut self, session: SearchSession) -> u64 {
+        self.next_id += 1;
+        if self.sessions.len() == SESSION_LIMIT {
+            self.sessions.pop_front();
+        }
+        self.sessions.push_back((self.next_id, session));
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&SearchSession> {
+        self.sessions
+            .iter()
+            .find(|(session_id, _)| *session_id == id)
+            .map(|(_, session)| session)
+    }
+
+    /// Up to `limit` distinct queries of recent sessions starting with
+    /// `prefix`, ignoring case and accents, newest first
+    pub fn recent_queries(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let prefix = fold(prefix).to_lowercase();
+        let mut queries: Vec<String> = Vec::new();
+        for (_, session) in self.sessions.iter().rev() {
+            let Some(step) = session.steps.last() else {
+                continue;
+            };
+            let query = step.query.trim();
+            if queries.len() == limit {
+                break;
+            }
+            if !query.is_empty()
+                && fold(query).to_lowercase().starts_with(&prefix)
+                && !queries.iter().any(|seen| seen == query)
+            {
+                queries.push(query.to_string());
+            }
+        }
+        queries
+    }
+}
+
+/// Suggestions while a query is typed
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Completions {
+    /// Indexed name words completing the word being typed, most common first
+    pub terms: Vec<String>,
+    /// Earlier queries the text typed so far starts
+    pub queries: Vec<String>,
+    /// Folders whose name, or path when it holds a separator, starts with
+    /// the text typed so far
+    pub folders: Vec<String>,
+}
+
+/// Entry of a batch search
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchQuery {
+    Query { query: String, use_regex: bool },
+    View(SmartView),
+}
+
+/// Total matches of a batch entry and its top hits
+#[derive(Debug, Default)]
+pub struct BatchHits {
+    pub count: usize,
+    pub docs: Vec<TantivyDocument>,
+}
+
+/// Query of a smart view and the fast field it sorts by
+type ViewQuery = (Box<dyn Query>, Option<&'static str>);
+
+/// Built-in views served by `get_smart_view` without any query syntax
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmartView {
+    /// Files modified in the last `RECENT_DAYS` days, newest first
+    RecentlyModified,
+    /// Entries created since local midnight, newest first
+    AddedToday,
+    /// Files of at least `LARGE_FILE_BYTES`, largest first
+    LargeFiles,
+    /// Folders with nothing indexed below them, most recently modified first
+    EmptyFolders,
+    /// Symlinks whose target is missing
+    BrokenSymlinks,
+}
+
+impl SmartView {
+    pub fn from_name(name: &str) -> Option<SmartView> {
+        match name {
+            "recently_modified" => Some(SmartView::RecentlyModified),
+            "added_today" => Some(SmartView::AddedToday),
+            "large_files" => Some(SmartView::LargeFiles),
+            "empty_folders" => Some(SmartView::EmptyFolders),
+            "broken_symlinks" => Some(SmartView::BrokenSymlinks),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that determines the results of a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheKey {
+    pub profile: String,
+    pub query: String,
+    pub use_regex: bool,
+    pub limit: usize,
+    pub filter: SearchFilter,
+    pub facets: bool,
+}
+
+/// Results and facet counts of one search
+pub type CachedSearch = (Vec<SearchResult>, Option<FacetCounts>);
+
+/// Least recently used search results, valid for one index opstamp
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    opstamp: u64,
+    entries: VecDeque<(CacheKey, CachedSearch)>,
+}
+
+impl QueryCache {
+    /// Cached results for `key`, discarding everything if the index has been
+    /// committed to since the results were stored
+    pub fn get(&mut self, key: &CacheKey, opstamp: u64) -> Option<CachedSearch> {
+        if opstamp != self.opstamp {
+            self.entries.clear();
+            self.opstamp = opstamp;
+            return None;
+        }
+        let position = self.entries.iter().position(|(cached, _)| cached == key)?;
+        let entry = self.entries.remove(position)?;
+        let hit = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(hit)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Estimated memory held by the cached results
+    pub fn approx_bytes(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|(_, (results, _))| {
+                results
+                    .iter()
+                    .map(SearchResult::approx_bytes)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    pub fn insert(&mut self, key: CacheKey, opstamp: u64, search: CachedSearch) {
+        if opstamp != self.opstamp {
+            self.entries.clear();
+            self.opstamp = opstamp;
+        }
+        self.entries.retain(|(cached, _)| *cached != key);
+        if self.entries.len() == CACHE_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, search));
+    }
+}
+
+/// Query-time restrictions applied on top of the text or regex query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilter {
+    pub exclude_hidden: bool,
+    pub readonly: Option<bool>,
+    /// Only symlinks and junctions when true, from `type:link`
+    pub symlink: Option<bool>,
+    /// Only cloud placeholders when true, from `attrib:online-only`, or only
+    /// files stored locally when false, from `attrib:local`
+    pub online_only: Option<bool>,
+    /// Only entries with this Git status, from `git:<status>`; `tracked`
+    /// includes modified files
+    pub git: Option<crate::git::GitStatus>,
+    /// Only entries inside a Git work tree of this folder name, from
+    /// `repo:<name>`, matched case-insensitively
+    pub repo: Option<String>,
+    /// Only entries below a project root of this folder name, from
+    /// `project:<name>`, matched case-insensitively
+    pub project: Option<String>,
+    /// Only entries with this color label, from `label:<color>`
+    pub label: Option<crate::marks::ColorLabel>,
+    /// Inclusive range of star ratings, from `rating:<n>`, `rating:>=<n>`
+    /// and the like; unrated entries never match
+    pub rating: Option<(u8, u8)>,
+    /// Only saved emails whose sender contains these words, from
+    /// `from:<words>`; punctuation separates words, so `from:bob@example.com`
+    /// matches that address
+    pub from: Option<String>,
+    /// Only saved emails whose subject contains these words, from
+    /// `subject:<words>`, such as `subject:march-invoice`
+    pub subject: Option<String>,
+    /// How ranked hits are ordered, from `sort:rating`. Not a restriction,
+    /// but carried here so every search path and the cache key see it.
+    pub order: ResultOrder,
+    pub uid: Option<u32>,
+    /// Only entries owned by this account, lowercased, from `owner:<name>`.
+    /// Matched by the owner's name on Windows and by the name's uid in
+    /// `/etc/passwd` on Unix.
+    pub owner: Option<String>,
+    pub gid: Option<u32>,
+    pub mode: Option<u32>,
+    /// Half-open `[start, end)` ranges in Unix seconds
+    pub created: Option<(i64, i64)>,
+    pub modified: Option<(i64, i64)>,
+    pub accessed: Option<(i64, i64)>,
+    /// Only match entries below this directory
+    pub scope: Option<PathBuf>,
+    /// Only match hits of every one of these earlier searches
+    pub within: Vec<SearchStep>,
+    /// Never match entries at or below these paths. Set from the private
+    /// paths for each search rather than kept in a session's steps.
+    pub exclude: Vec<PathBuf>,
+}
+
+impl SearchFilter {
+    fn has_attribute_filters(&self) -> bool {
+        self.scope.is_some()
+            || !self.within.is_empty()
+            || self.readonly.is_some()
+            || self.symlink.is_some()
+            || self.online_only.is_some()
+            || self.git.is_some()
+            || self.repo.is_some()
+            || self.project.is_some()
+            || self.label.is_some()
+            || self.rating.is_some()
+            || self.from.is_some()
+            || self.subject.is_some()
+            || self.uid.is_some()
+            || self.owner.is_some()
+            || self.gid.is_some()
+            || self.mode.is_some()
+            || self.created.is_some()
+            || self.modified.is_some()
+            || self.accessed.is_some()
+    }
+
+    /// Split inline filters (`attrib:readonly`, `attrib:writable`,
+    /// `attrib:online-only`, `attrib:local`, `type:link`, `git:<status>`,
+    /// `repo:<name>`, `project:<name>`, `label:<color>`, `rating:<spec>`,
+    /// `sort:rating`, `from:<words>`, `subject:<words>`, `owner:<uid>` or
+    /// `owner:<name>`, `group:<gid>`, `mode:<octal>`, and `dc:`/`dm:`/`da:`
+    /// date filters for created/modified/accessed) out of a query, returning the remaining query
+    pub fn extract(query_str: &str) -> Result<(String, SearchFilter), tantivy::TantivyError> {
+        Self::extract_at(query_str, chrono::Local::now())
+    }
+
+    fn extract_at<Tz: TimeZone>(
+        query_str: &str,
+        now: chrono::DateTime<Tz>,
+    ) -> Result<(String, SearchFilter), tantivy::TantivyError> {
+        let invalid = |token: &str| {
+            tantivy::TantivyError::InvalidArgument(format!("Invalid filter: {}", token))
+        };
+        let mut filter = SearchFilter::default();
+        let mut rest = Vec::new();
+
+        for token in query_str.split_whitespace() {
+            match token.split_once(':') {
+                Some(("attrib", "readonly")) => filter.readonly = Some(true),
+                Some(("attrib", "writable")) => filter.readonly = Some(false),
+                Some(("attrib", "online-only")) => filter.online_only = Some(true),
+                Some(("attrib", "local")) => filter.online_only = Some(false),
+                Some(("type", "link")) => filter.symlink = Some(true),
+                Some(("git", status)) => {
+                    filter.git =
+                        Some(crate::git::GitStatus::parse(status).ok_or_else(|| invalid(token))?)
+                }
+                Some(("repo", name)) if !name.is_empty() => filter.repo = Some(name.to_lowercase()),
+                Some(("project", name)) if !name.is_empty() => {
+                    filter.project = Some(name.to_lowercase())
+                }
+                Some(("label", color)) => {
+                    filter.label =
+                        Some(crate::marks::ColorLabel::parse(color).ok_or_else(|| invalid(token))?)
+                }
+                Some(("rating", spec)) => {
+                    filter.rating = Some(rating_range(spec).ok_or_else(|| invalid(token))?)
+                }
+                Some(("sort", "rating")) => filter.order = ResultOrder::Rating,
+                Some(("from", words)) if !words.is_empty() => filter.from = Some(words.to_string()),
+                Some(("subject", words)) if !words.is_empty() => {
+                    filter.subject = Some(words.to_string())
+                }
+                Some(("owner", owner)) if !owner.is_empty() => match owner.parse() {
+                    Ok(uid) => filter.uid = Some(uid),
+                    Err(_) => filter.owner = Some(owner.to_lowercase()),
+                },
+                Some(("group", gid)) => filter.gid = Some(gid.parse().map_err(|_| invalid(token))?),
+                Some(("mode", mode)) => {
+                    filter.mode = Some(u32::from_str_radix(mode, 8).map_err(|_| invalid(token))?)
+                }
+                Some(("dc", spec)) => {
+                    filter.created = Some(date_range(spec, &now).ok_or_else(|| invalid(token))?)
+                }
+                Some(("dm", spec)) => {
+                    filter.modified = Some(date_range(spec, &now).ok_or_else(|| invalid(token))?)
+                }
+                Some(("da", spec)) => {
+                    filter.accessed = Some(date_range(spec, &now).ok_or_else(|| invalid(token))?)
+                }
+                Some(("attrib" | "type" | "repo" | "project" | "sort" | "from" | "subject", _)) => {
+                    return Err(invalid(token))
+                }
+                _ => rest.push(token),
+            }
+        }
+        Ok((rest.join(" "), filter))
+    }
+
+    /// Inline filters from the query take precedence over caller-supplied ones
+    fn merged(&self, inline: SearchFilter) -> SearchFilter {
+        SearchFilter {
+            exclude_hidden: self.exclude_hidden || inline.exclude_hidden,
+            readonly: inline.readonly.or(self.readonly),
+            symlink: inline.symlink.or(self.symlink),
+            online_only: inline.online_only.or(self.online_only),
+            git: inline.git.or(self.git),
+            repo: inline.repo.or_else(|| self.repo.clone()),
+            project: inline.project.or_else(|| self.project.clone()),
+            label: inline.label.or(self.label),
+            rating: inline.rating.or(self.rating),
+            from: inline.from.or_else(|| self.from.clone()),
+            subject: inline.subject.or_else(|| self.subject.clone()),
+            order: match inline.order {
+                ResultOrder::Relevance => self.order,
+                order => order,
+            },
+            uid: inline.uid.or(self.uid),
+            owner: inline.owner.or_else(|| self.owner.clone()),
+            gid: inline.gid.or(self.gid),
+            mode: inline.mode.or(self.mode),
+            created: inline.created.or(self.created),
+            modified: inline.modified.or(self.modified),
+            accessed: inline.accessed.or(self.accessed),
+            scope: inline.scope.or_else(|| self.scope.clone()),
+            within: self.within.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+}
+
+/// Order of ranked search hits
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResultOrder {
+    #[default]
+    Relevance,
+    /// Highest rated first, relevance breaking ties; unrated entries last
+    Rating,
+}
+
+/// Resolve a rating filter value (`4`, `>=4`, `>3`, `<=2` or `<3`) to an
+/// inclusive range of stars; None when it is malformed or selects nothing
+fn rating_range(spec: &str) -> Option<(u8, u8)> {
+    let (op, stars) = [">=", "<=", ">", "<", "="]
+        .into_iter()
+        .find_map(|op| Some((op, spec.strip_prefix(op)?)))
+        .unwrap_or(("=", spec));
+    let stars: u8 = stars.parse().ok()?;
+    let (low, high) = match op {
+        ">=" => (stars, crate::marks::MAX_RATING),
+        ">" => (stars.checked_add(1)?, crate::marks::MAX_RATING),
+        "<=" => (1, stars),
+        "<" => (1, stars.checked_sub(1)?),
+        _ => (stars, stars),
+    };
+    let low = low.max(1);
+    let high = high.min(crate::marks::MAX_RATING);
+    (low <= high).then_some((low, high))
+}
+
+/// Order of the hits of `query_str`, which may override `filter`'s with an
+/// inline `sort:`; the query was already validated by `build_query`
+fn result_order(query_str: &str, use_regex: bool, filter: &SearchFilter) -> ResultOrder {
+    match SearchFilter::extract(query_str) {
+        Ok((_, inline)) if !use_regex => filter.merged(inline).order,
+        _ => filter.order,
+    }
+}
+
+/// Score rating order adds per star, above any relevance score
+const RATING_RANK: Score = 1000.0;
+
+/// Collector of the top `limit` hits in `order`
+fn ranked(
+    limit: usize,
+    order: ResultOrder,
+) -> impl tantivy::collector::Collector<Fruit = Vec<(Score, DocAddress)>> {
+    ranked_page(0, limit, order)
+}
+
+/// Collector of hits `offset..offset + limit` in `order`, at most
+/// `MAX_RANKED_RESULTS` of them however deep the page
+fn ranked_page(
+    offset: usize,
+    limit: usize,
+    order: ResultOrder,
+) -> impl tantivy::collector::Collector<Fruit = Vec<(Score, DocAddress)>> {
+    TopDocs::with_limit(limit.min(MAX_RANKED_RESULTS))
+        .and_offset(offset)
+        .tweak_score(move |segment: &tantivy::SegmentReader| {
+            let ratings = match order {
+                ResultOrder::Relevance => None,
+                ResultOrder::Rating => segment.fast_fields().u64("rating").ok(),
+            };
+            move |doc: tantivy::DocId, score: Score| match &ratings {
+                Some(ratings) => ratings.first(doc).unwrap_or(0) as Score * RATING_RANK + score,
+                None => score,
+            }
+        })
+}
+
+/// Resolve a date filter value to a `[start, end)` range in `now`'s time zone.
+/// Accepts `today`, `yesterday`, `thisweek`, `lastweek`, `thismonth`, `thisyear`,
+/// a `YYYY-MM-DD` day, or an inclusive `YYYY-MM-DD..YYYY-MM-DD` span.
+fn date_range<Tz: TimeZone>(spec: &str, now: &chrono::DateTime<Tz>) -> Option<(i64, i64)> {
+    let tz = now.timezone();
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|dt| tz.from_local_datetime(&dt).earliest())
+            .map(|dt| dt.timestamp())
+    };
+    let span = |start: NaiveDate, end: NaiveDate| Some((midnight(start)?, midnight(end)?));
+
+    let today = now.date_naive();
+    let day = chrono::Days::new(1);
+    let week_start = today - chrono::Days::new(today.weekday().num_days_from_monday() as u64);
+    let month_start = today.with_day(1)?;
+    let year_start = NaiveDate::from_ymd_opt(today.year(), 1, 1)?;
+
+    match spec {
+        "today" => span(today, today + day),
+        "yesterday" => span(today - day, today),
+        "thisweek" => span(week_start, week_start + chrono::Days::new(7)),
+        "lastweek" => span(week_start - chrono::Days::new(7), week_start),
+        "thismonth" => span(month_start, month_start + chrono::Months::new(1)),
+        "thisyear" => span(year_start, NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)?),
+        _ => {
+            let (first, last) = spec.split_once("..").unwrap_or((spec, spec));
+            let first = NaiveDate::parse_from_str(first, "%Y-%m-%d").ok()?;
+            let last = NaiveDate::parse_from_str(last, "%Y-%m-%d").ok()?;
+            if last < first {
+                return None;
+            }
+            span(first, last + day)
+        }
+    }
+}
+
+/// What a warm-up read
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WarmUpStats {
+    pub segments: usize,
+    pub terms: u64,
+    pub postings: u64,
+    pub fast_values: u64,
+}
 
 pub struct SearchIndex {
     index: Index,
     reader: IndexReader,
     schema: Schema,
+    /// Built on the first completion request after each commit
+    completions: Mutex<Option<Arc<CompletionIndex>>>,
 }
 
 impl SearchIndex {
@@ -20,21 +1354,174 @@ impl SearchIndex {
         let mut schema_builder = SchemaBuilder::default();
 
         // Define schema fields
+        let _id_field = schema_builder.add_text_field("id", STRING | STORED);
         let _name_field = schema_builder.add_text_field("name", TEXT | STORED);
         let _path_field = schema_builder.add_text_field("path", TEXT | STORED);
-        let _size_field = schema_builder.add_u64_field("size", STORED);
-        let _modified_field = schema_builder.add_date_field("modified", STORED);
-        let _is_folder_field = schema_builder.add_bool_field("is_folder", STORED);
+        let _name_folded_field = schema_builder.add_text_field("name_folded", TEXT);
+        let _path_folded_field = schema_builder.add_text_field("path_folded", TEXT);
+        // CJK names have no spaces, so partial terms need n-grams to match
+        let ngram_indexing = TextFieldIndexing::default()
+            .set_tokenizer(cjk::TOKENIZER_NAME)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let _name_ngram_field = schema_builder.add_text_field(
+            "name_ngram",
+            TextOptions::default().set_indexing_options(ngram_indexing),
+        );
+        // Choseong and pinyin initials, e.g. "ㅅㅈ" for "사진" or "zp" for "照片"
+        let initials_indexing = TextFieldIndexing::default()
+            .set_tokenizer(cjk::INITIALS_TOKENIZER_NAME)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let _name_initials_field = schema_builder.add_text_field(
+            "name_initials",
+            TextOptions::default().set_indexing_options(initials_indexing),
+        );
+        // Word starts, so typing the beginning of a name is a single term lookup
+        let prefix_indexing = TextFieldIndexing::default()
+            .set_tokenizer(prefix::TOKENIZER_NAME)
+            .set_index_option(IndexRecordOption::Basic);
+        let _name_prefix_field = schema_builder.add_text_field(
+            "name_prefix",
+            TextOptions::default().set_indexing_options(prefix_indexing),
+        );
+        // Words of camelCase, snake_case and kebab-case names
+        let words_indexing = TextFieldIndexing::default()
+            .set_tokenizer(code::TOKENIZER_NAME)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let _name_words_field = schema_builder.add_text_field(
+            "name_words",
+            TextOptions::default().set_indexing_options(words_indexing),
+        );
+        // The whole folded name as one lowercased term, for wildcard patterns
+        let raw_indexing = TextFieldIndexing::default()
+            .set_tokenizer(glob::TOKENIZER_NAME)
+            .set_index_option(IndexRecordOption::Basic);
+        let _name_raw_field = schema_builder.add_text_field(
+            "name_raw",
+            TextOptions::default().set_indexing_options(raw_indexing),
+        );
+        let _size_field = schema_builder.add_u64_field("size", INDEXED | STORED | FAST);
+        let _modified_field = schema_builder.add_date_field("modified", INDEXED | STORED | FAST);
+        let _is_folder_field = schema_builder.add_bool_field("is_folder", INDEXED | STORED);
+        let _hidden_field = schema_builder.add_bool_field("hidden", INDEXED | STORED);
+        let _readonly_field = schema_builder.add_bool_field("readonly", INDEXED | STORED);
+        let _uid_field = schema_builder.add_u64_field("uid", INDEXED | STORED);
+        let _gid_field = schema_builder.add_u64_field("gid", INDEXED | STORED);
+        let _mode_field = schema_builder.add_u64_field("mode", INDEXED | STORED);
+        let _created_field = schema_builder.add_date_field("created", INDEXED | STORED | FAST);
+        let _accessed_field = schema_builder.add_date_field("accessed", INDEXED | STORED);
+        let _broken_link_field = schema_builder.add_bool_field("broken_link", INDEXED | STORED);
+        let _file_id_field = schema_builder.add_text_field("file_id", STRING | STORED);
+        let _is_symlink_field = schema_builder.add_bool_field("is_symlink", INDEXED | STORED);
+        let _link_target_field = schema_builder.add_text_field("link_target", STORED);
+        let _online_only_field = schema_builder.add_bool_field("online_only", INDEXED | STORED);
+        // Lowercased folder name of the enclosing work tree, for `repo:`
+        let _repo_field = schema_builder.add_text_field("repo", STRING);
+        let _repo_root_field = schema_builder.add_text_field("repo_root", STORED);
+        // Lowercased account name of the owner, for `owner:` on Windows
+        let _owner_field = schema_builder.add_text_field("owner", STRING);
+        let _owner_name_field = schema_builder.add_text_field("owner_name", STORED);
+        let _git_status_field = schema_builder.add_text_field("git_status", STRING | STORED);
+        // Lowercased folder name of project roots, for `project:`
+        let _project_field = schema_builder.add_text_field("project", STRING);
+        let _project_marker_field =
+            schema_builder.add_text_field("project_marker", STRING | STORED);
+        let _label_field = schema_builder.add_text_field("label", STRING | STORED);
+        let _note_field = schema_builder.add_text_field("note", STORED);
+        let _note_folded_field = schema_builder.add_text_field("note_folded", TEXT);
+        // Not stored: results don't carry the text, and sled keeps it
+        let _ocr_text_folded_field = schema_builder.add_text_field("ocr_text_folded", TEXT);
+        // Names stemmed in every language with a stemmer, notes in the one
+        // they are written in
+        let language_fields = lang::STEMMED
+            .iter()
+            .map(|language| (language.name_field(), *language))
+            .chain(
+                lang::Language::ALL
+                    .iter()
+                    .map(|language| (language.note_field(), *language)),
+            );
+        for (name, language) in language_fields {
+            let indexing = TextFieldIndexing::default()
+                .set_tokenizer(language.tokenizer_name())
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+            schema_builder
+                .add_text_field(name, TextOptions::default().set_indexing_options(indexing));
+        }
+        // Headers of saved emails as read, and folded for `from:` and `subject:`
+        let _email_from_field = schema_builder.add_text_field("email_from", STORED);
+        let _email_from_folded_field = schema_builder.add_text_field("email_from_folded", TEXT);
+        let _email_subject_field = schema_builder.add_text_field("email_subject", STORED);
+        let _email_subject_folded_field =
+            schema_builder.add_text_field("email_subject_folded", TEXT);
+        let _email_date_field = schema_builder.add_i64_field("email_date", STORED);
+        let _rating_field = schema_builder.add_u64_field("rating", INDEXED | STORED | FAST);
+        let _extension_field = schema_builder.add_facet_field("extension", FacetOptions::default());
+        let _file_type_field = schema_builder.add_facet_field("file_type", FacetOptions::default());
+        let _dir_field = schema_builder.add_facet_field("dir", FacetOptions::default());
 
         let schema = schema_builder.build();
 
         // Create or open index
         let index = if index_path.exists() {
-            Index::open(MmapDirectory::open(index_path)?)?
+            // Indexes built before a schema change must be migrated first
+            let version = Self::schema_version(index_path);
+            if version != Some(SCHEMA_VERSION) {
+                return Err(tantivy::TantivyError::SchemaError(format!(
+                    "Search index schema version {} is outdated, expected {}",
+                    version.unwrap_or(0),
+                    SCHEMA_VERSION
+                )));
+            }
+            let index = Index::open(MmapDirectory::open(index_path)?)?;
+            if index.schema() != schema {
+                return Err(tantivy::TantivyError::SchemaError(
+                    "Search index schema is outdated".to_string(),
+                ));
+            }
+            index
         } else {
             std::fs::create_dir_all(index_path)?;
-            Index::create_in_dir(index_path, schema.clone())?
+            let index = Index::create_in_dir(index_path, schema.clone())?;
+            std::fs::write(
+                index_path.join(SCHEMA_VERSION_FILE),
+                SCHEMA_VERSION.to_string(),
+            )?;
+            index
         };
+        for (name, tokenizer) in [
+            (cjk::TOKENIZER_NAME, cjk::CjkBigramTokenizer::names()),
+            (
+                cjk::INITIALS_TOKENIZER_NAME,
+                cjk::CjkBigramTokenizer::initials(),
+            ),
+        ] {
+            index.tokenizers().register(
+                name,
+                TextAnalyzer::builder(tokenizer)
+                    .filter(RemoveLongFilter::limit(40))
+                    .build(),
+            );
+        }
+        index.tokenizers().register(
+            prefix::TOKENIZER_NAME,
+            TextAnalyzer::from(prefix::PrefixTokenizer),
+        );
+        index
+            .tokenizers()
+            .register(glob::TOKENIZER_NAME, glob::analyzer());
+        index.tokenizers().register(
+            code::TOKENIZER_NAME,
+            TextAnalyzer::builder(code::CodeWordTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .build(),
+        );
+        for language in lang::STEMMED {
+            if let Some(analyzer) = language.analyzer() {
+                index
+                    .tokenizers()
+                    .register(language.tokenizer_name(), analyzer);
+            }
+        }
 
         // Use Manual reload policy - we'll reload manually when needed
         let reader = index
@@ -46,214 +1533,3366 @@ impl SearchIndex {
             index,
             reader,
             schema,
+            completions: Mutex::new(None),
         })
     }
 
+    /// Schema version an index directory was built with; None when it predates
+    /// versioning or cannot be read
+    pub fn schema_version(index_path: &Path) -> Option<u32> {
+        std::fs::read_to_string(index_path.join(SCHEMA_VERSION_FILE))
+            .ok()
+            .and_then(|version| version.trim().parse().ok())
+    }
+
+    #[allow(dead_code)] // Bulk indexing uses fields(); kept for ad-hoc schema access
     pub fn get_schema(&self) -> &Schema {
         &self.schema
     }
 
+    pub fn fields(&self) -> Result<DocFields, tantivy::TantivyError> {
+        Ok(DocFields {
+            id: self.schema.get_field("id")?,
+            name: self.schema.get_field("name")?,
+            path: self.schema.get_field("path")?,
+            name_folded: self.schema.get_field("name_folded")?,
+            path_folded: self.schema.get_field("path_folded")?,
+            name_ngram: self.schema.get_field("name_ngram")?,
+            name_initials: self.schema.get_field("name_initials")?,
+            name_prefix: self.schema.get_field("name_prefix")?,
+            name_words: self.schema.get_field("name_words")?,
+            name_raw: self.schema.get_field("name_raw")?,
+            size: self.schema.get_field("size")?,
+            modified: self.schema.get_field("modified")?,
+            is_folder: self.schema.get_field("is_folder")?,
+            hidden: self.schema.get_field("hidden")?,
+            readonly: self.schema.get_field("readonly")?,
+            uid: self.schema.get_field("uid")?,
+            gid: self.schema.get_field("gid")?,
+            mode: self.schema.get_field("mode")?,
+            created: self.schema.get_field("created")?,
+            accessed: self.schema.get_field("accessed")?,
+            broken_link: self.schema.get_field("broken_link")?,
+            file_id: self.schema.get_field("file_id")?,
+            is_symlink: self.schema.get_field("is_symlink")?,
+            link_target: self.schema.get_field("link_target")?,
+            online_only: self.schema.get_field("online_only")?,
+            repo: self.schema.get_field("repo")?,
+            repo_root: self.schema.get_field("repo_root")?,
+            owner: self.schema.get_field("owner")?,
+            owner_name: self.schema.get_field("owner_name")?,
+            git_status: self.schema.get_field("git_status")?,
+            project: self.schema.get_field("project")?,
+            project_marker: self.schema.get_field("project_marker")?,
+            label: self.schema.get_field("label")?,
+            note: self.schema.get_field("note")?,
+            note_folded: self.schema.get_field("note_folded")?,
+            ocr_text_folded: self.schema.get_field("ocr_text_folded")?,
+            name_stemmed: [
+                self.schema.get_field(lang::STEMMED[0].name_field())?,
+                self.schema.get_field(lang::STEMMED[1].name_field())?,
+            ],
+            note_languages: [
+                (lang::Language::English, self.schema.get_field("note_en")?),
+                (lang::Language::German, self.schema.get_field("note_de")?),
+                (lang::Language::Korean, self.schema.get_field("note_ko")?),
+            ],
+            email_from: self.schema.get_field("email_from")?,
+            email_from_folded: self.schema.get_field("email_from_folded")?,
+            email_subject: self.schema.get_field("email_subject")?,
+            email_subject_folded: self.schema.get_field("email_subject_folded")?,
+            email_date: self.schema.get_field("email_date")?,
+            rating: self.schema.get_field("rating")?,
+            extension: self.schema.get_field("extension")?,
+            file_type: self.schema.get_field("file_type")?,
+            dir: self.schema.get_field("dir")?,
+        })
+    }
+
     pub fn writer(&self) -> Result<IndexWriter, tantivy::TantivyError> {
-        self.index.writer(50_000_000)
+        let options = writer_options();
+        let heap = options.heap_mb * 1_000_000;
+        match options.threads {
+            0 => self.index.writer(heap),
+            threads => self.index.writer_with_num_threads(threads, heap),
+        }
     }
 
+    /// Delete `removed_ids` and insert or replace `upserted` in one commit, so
+    /// searches never see a renamed entry under both paths or neither
+    pub fn apply_changes(
+        &self,
+        removed_ids: &[String],
+        upserted: &[crate::FileEntity],
+    ) -> Result<(), tantivy::TantivyError> {
+        let fields = self.fields()?;
+        let mut writer = self.writer()?;
+        for id in removed_ids {
+            writer.delete_term(Term::from_field_text(fields.id, id));
+        }
+        for entity in upserted {
+            writer.delete_term(Term::from_field_text(fields.id, &entity.id));
+            writer.add_document(fields.build_document(entity))?;
+        }
+        writer.commit()?;
+        Ok(())
+    }
+
+    #[cfg(test)]
     pub fn search(
         &self,
         query_str: &str,
         use_regex: bool,
         limit: usize,
     ) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
-        // Early return for empty queries
-        if query_str.trim().is_empty() {
+        self.search_filtered(
+            query_str,
+            use_regex,
+            limit,
+            &SearchFilter::default(),
+            &CancelToken::default(),
+        )
+    }
+
+    /// Top `limit` matches of `query_str`, failing once `cancel` fires
+    pub fn search_filtered(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+        limit: usize,
+        filter: &SearchFilter,
+        cancel: &CancelToken,
+    ) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+        let Some(query) = self.build_query(query_str, use_regex, filter)? else {
             return Ok(Vec::new());
-        }
+        };
+        let searcher = self.reader.searcher();
+        // Use TopDocs collector with limit for efficient result retrieval
+        let top_docs = searcher.search(
+            &*query,
+            &Cancellable {
+                inner: ranked(limit, result_order(query_str, use_regex, filter)),
+                cancel: cancel.clone(),
+            },
+        )?;
+        retrieve(&searcher, top_docs)
+    }
+
+    /// Matches `offset..offset + limit` of `query_str` in rank order, with how
+    /// many there are in all
+    pub fn search_page(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+        filter: &SearchFilter,
+        (offset, limit): (usize, usize),
+        cancel: &CancelToken,
+    ) -> Result<(Vec<TantivyDocument>, usize), tantivy::TantivyError> {
+        let Some(query) = self.build_query(query_str, use_regex, filter)? else {
+            return Ok((Vec::new(), 0));
+        };
+        let searcher = self.reader.searcher();
+        let order = result_order(query_str, use_regex, filter);
+        let (mut top_docs, total) = searcher.search(
+            &*query,
+            &Cancellable {
+                // TopDocs wants at least one hit
+                inner: (ranked_page(offset, limit.max(1), order), Count),
+                cancel: cancel.clone(),
+            },
+        )?;
+        top_docs.truncate(limit);
+        Ok((retrieve(&searcher, top_docs)?, total))
+    }
+
+    /// Every match of `query_str` in index order rather than by rank, handed
+    /// to `on_chunk` in chunks of `chunk_size` documents while the search
+    /// goes on. Stops after `limit` documents when given. Returns how many
+    /// documents were handed over.
+    pub fn search_chunks(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+        filter: &SearchFilter,
+        (limit, chunk_size): (Option<usize>, usize),
+        cancel: &CancelToken,
+        mut on_chunk: impl FnMut(Vec<TantivyDocument>),
+    ) -> Result<usize, tantivy::TantivyError> {
+        let Some(query) = self.build_query(query_str, use_regex, filter)? else {
+            return Ok(0);
+        };
+        let limit = limit.unwrap_or(usize::MAX);
+        let searcher = self.reader.searcher();
+        let (sender, receiver) = std::sync::mpsc::sync_channel(CHUNK_BACKLOG);
+        let collector = Cancellable {
+            inner: ChunkCollector {
+                sender,
+                chunk_size: chunk_size.max(1),
+            },
+            cancel: cancel.clone(),
+        };
+        std::thread::scope(|scope| {
+            // The collector, and with it the sender, is dropped once the
+            // search ends, which ends the loop below
+            let (searcher, query) = (&searcher, &*query);
+            let searching = scope.spawn(move || searcher.search(query, &collector));
+            let mut handed = 0;
+            for addresses in receiver {
+                cancel.check()?;
+                let take = addresses.len().min(limit - handed);
+                let docs = retrieve_addresses(searcher, &addresses[..take])?;
+                handed += docs.len();
+                on_chunk(docs);
+                if handed >= limit {
+                    // Dropping the receiver makes the collector skip the rest
+                    break;
+                }
+            }
+            searching.join().map_err(|_| {
+                tantivy::TantivyError::InternalError("Search thread panicked".to_string())
+            })??;
+            Ok(handed)
+        })
+    }
+
+    /// `search_filtered` plus hit counts per extension, file type and folder
+    /// directly below each of `roots`, over all matches rather than the top `limit`
+    pub fn search_faceted(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+        limit: usize,
+        filter: &SearchFilter,
+        roots: &[String],
+        cancel: &CancelToken,
+    ) -> Result<(Vec<TantivyDocument>, FacetCounts), tantivy::TantivyError> {
+        let Some(query) = self.build_query(query_str, use_regex, filter)? else {
+            return Ok((Vec::new(), FacetCounts::default()));
+        };
+        let root_facets: Vec<(&String, Facet)> = roots
+            .iter()
+            .map(|root| (root, dir_facet(Path::new(root))))
+            .collect();
+
+        let mut extensions = FacetCollector::for_field("extension");
+        extensions.add_facet(Facet::root());
+        let mut file_types = FacetCollector::for_field("file_type");
+        file_types.add_facet(Facet::root());
+        let mut folders = FacetCollector::for_field("dir");
+        for (_, facet) in &root_facets {
+            folders.add_facet(facet.clone());
+        }
+
+        let searcher = self.reader.searcher();
+        let (top_docs, extensions, file_types, folders) = searcher.search(
+            &*query,
+            &Cancellable {
+                inner: (
+                    ranked(limit, result_order(query_str, use_regex, filter)),
+                    extensions,
+                    file_types,
+                    folders,
+                ),
+                cancel: cancel.clone(),
+            },
+        )?;
+
+        let leaf = |facet: &Facet| facet.to_path().last().map(|s| s.to_string());
+        let top = |counts: &tantivy::collector::FacetCounts, facet: &Facet| {
+            counts
+                .top_k(facet.clone(), FACET_LIMIT)
+                .into_iter()
+                .filter_map(|(facet, count)| Some((leaf(facet)?, count)))
+                .collect::<Vec<_>>()
+        };
+        let mut facets = FacetCounts {
+            extensions: FacetCount::from_pairs(top(&extensions, &Facet::root())),
+            file_types: FacetCount::from_pairs(top(&file_types, &Facet::root())),
+            folders: Vec::new(),
+        };
+        for (root, facet) in &root_facets {
+            let children = top(&folders, facet).into_iter().map(|(name, count)| {
+                (
+                    Path::new(root).join(name).to_string_lossy().to_string(),
+                    count,
+                )
+            });
+            facets.folders.extend(FacetCount::from_pairs(children));
+        }
+        facets.folders.sort_by(FacetCount::order);
+        facets.folders.truncate(FACET_LIMIT);
+
+        Ok((retrieve(&searcher, top_docs)?, facets))
+    }
+
+    /// Hits of `query_str` counted per folder directly containing them, for
+    /// searches whose matches crowd into a few folders. Every match is
+    /// counted; `limit` caps the folders returned.
+    pub fn group_by_folder(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+        filter: &SearchFilter,
+        limit: usize,
+        cancel: &CancelToken,
+    ) -> Result<FolderGroups, tantivy::TantivyError> {
+        let Some(query) = self.build_query(query_str, use_regex, filter)? else {
+            return Ok(FolderGroups::default());
+        };
+        let searcher = self.reader.searcher();
+        let (total_found, folders) = searcher.search(
+            &*query,
+            &Cancellable {
+                inner: (Count, FolderCollector),
+                cancel: cancel.clone(),
+            },
+        )?;
+
+        // Encoded facets sort like their paths, so ties stay in path order
+        let mut folders: Vec<_> = folders.into_iter().collect();
+        folders.sort_by(|(a, (a_count, _)), (b, (b_count, _))| {
+            b_count.cmp(a_count).then_with(|| a.cmp(b))
+        });
+        let total_folders = folders.len();
+        folders.truncate(limit);
+
+        let samples: Vec<DocAddress> = folders.iter().map(|(_, (_, sample))| *sample).collect();
+        let samples = self.to_results(&retrieve_addresses(&searcher, &samples)?)?;
+        let folders = folders
+            .into_iter()
+            .zip(samples)
+            .filter_map(|((_, (count, _)), sample)| {
+                let path = Path::new(&sample.path).parent()?;
+                Some(FolderGroup {
+                    path: path.to_string_lossy().to_string(),
+                    count,
+                })
+            })
+            .collect();
+        Ok(FolderGroups {
+            total_found,
+            total_folders,
+            folders,
+        })
+    }
+
+    /// Clauses keeping the entries at or below `exclude` out of a query
+    fn exclusions(&self, exclude: &[PathBuf]) -> Result<Vec<Clause>, tantivy::TantivyError> {
+        let fields = self.fields()?;
+        Ok(exclude
+            .iter()
+            .flat_map(|path| {
+                let id = crate::index::path_id(&path.to_string_lossy());
+                // A folder's facet term is indexed for everything below it
+                [
+                    Term::from_field_text(fields.id, &id),
+                    Term::from_facet(fields.dir, &dir_facet(path)),
+                ]
+            })
+            .map(|term| -> Clause {
+                (
+                    Occur::MustNot,
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+                )
+            })
+            .collect())
+    }
+
+    /// `query` without the entries at or below `exclude`
+    fn excluding(
+        &self,
+        query: Box<dyn Query>,
+        exclude: &[PathBuf],
+    ) -> Result<Box<dyn Query>, tantivy::TantivyError> {
+        if exclude.is_empty() {
+            return Ok(query);
+        }
+        let mut clauses = vec![(Occur::Must, query)];
+        clauses.extend(self.exclusions(exclude)?);
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// Whether `query` matches anything outside `exclude`
+    fn matches_outside(
+        &self,
+        searcher: &Searcher,
+        query: Box<dyn Query>,
+        exclude: &[PathBuf],
+    ) -> Result<bool, tantivy::TantivyError> {
+        let query = self.excluding(query, exclude)?;
+        Ok(searcher.search(&*query, &Count)? > 0)
+    }
+
+    /// Query for `query_str` with `filter` applied, or None when nothing can match
+    fn build_query(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+        filter: &SearchFilter,
+    ) -> Result<Option<Box<dyn Query>>, tantivy::TantivyError> {
+        // Inline filters are only recognized in text queries; a regex is used verbatim
+        let (query_str, filter) = if use_regex {
+            (query_str.to_string(), filter.clone())
+        } else {
+            let (rest, inline) = SearchFilter::extract(query_str)?;
+            (rest, filter.merged(inline))
+        };
+
+        // Wildcard words match whole names; the other words form the text query
+        let (query_str, globs) = if use_regex {
+            (query_str, Vec::new())
+        } else {
+            glob::split(&query_str)
+        };
+
+        // Early return for empty queries, unless filters alone select documents
+        let match_all = query_str.trim().is_empty();
+        if match_all && globs.is_empty() && !filter.has_attribute_filters() {
+            return Ok(None);
+        }
+
+        // Reload reader to get latest index updates
+        self.reader.reload()?;
+
+        let schema = self.schema.clone();
+
+        // Names are indexed in NFC, so compare queries in the same form
+        let query_str: String = query_str.nfc().collect();
+        let query: Box<dyn Query> = if match_all {
+            Box::new(AllQuery)
+        } else if use_regex {
+            // For regex queries, search in name field
+            let name_field = schema.get_field("name")?;
+            Box::new(RegexQuery::from_pattern(&query_str, name_field)?)
+        } else if is_plain(&query_str) {
+            self.plain_query(&query_str)?
+        } else {
+            // For text queries, use query parser with optimized settings
+            // Boost name field matches (2x) over path matches for better relevance
+            let parse = |fields: &[(&str, f32)], query_str: &str| {
+                let fields = fields
+                    .iter()
+                    .map(|(name, boost)| Ok((schema.get_field(name)?, *boost)))
+                    .collect::<Result<Vec<_>, tantivy::TantivyError>>()?;
+                let mut query_parser = QueryParser::for_index(
+                    &self.index,
+                    fields.iter().map(|(field, _)| *field).collect(),
+                );
+                for (field, boost) in fields {
+                    query_parser.set_field_boost(field, boost);
+                }
+                Ok::<_, tantivy::TantivyError>(query_parser.parse_query(query_str)?)
+            };
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Should, parse(&EXACT_FIELDS, &query_str)?),
+                (Occur::Should, parse(&FOLDED_FIELDS, &fold(&query_str))?),
+            ]))
+        };
+
+        let term_query = |term: Term| -> Box<dyn Query> {
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+        };
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        if !globs.is_empty() {
+            let name_raw = schema.get_field("name_raw")?;
+            for glob in &globs {
+                let regex = glob::to_regex(&glob.pattern)
+                    .map_err(tantivy::TantivyError::InvalidArgument)?;
+                let occur = if glob.exclude {
+                    Occur::MustNot
+                } else {
+                    Occur::Must
+                };
+                clauses.push((occur, Box::new(RegexQuery::from_pattern(&regex, name_raw)?)));
+            }
+        }
+        for step in &filter.within {
+            let Some(previous) = self.build_query(&step.query, step.use_regex, &step.filter)?
+            else {
+                return Ok(None);
+            };
+            clauses.push((Occur::Must, previous));
+        }
+        if filter.exclude_hidden {
+            let hidden = schema.get_field("hidden")?;
+            clauses.push((
+                Occur::MustNot,
+                term_query(Term::from_field_bool(hidden, true)),
+            ));
+        }
+        clauses.extend(self.exclusions(&filter.exclude)?);
+        if let Some(scope) = &filter.scope {
+            // Facet terms are indexed for every ancestor, so this covers the whole subtree
+            let dir = schema.get_field("dir")?;
+            clauses.push((
+                Occur::Must,
+                term_query(Term::from_facet(dir, &dir_facet(scope))),
+            ));
+        }
+        for (name, value) in [
+            ("readonly", filter.readonly),
+            ("is_symlink", filter.symlink),
+            ("online_only", filter.online_only),
+        ] {
+            if let Some(value) = value {
+                let field = schema.get_field(name)?;
+                clauses.push((Occur::Must, term_query(Term::from_field_bool(field, value))));
+            }
+        }
+        if let Some(name) = &filter.repo {
+            let repo = schema.get_field("repo")?;
+            clauses.push((Occur::Must, term_query(Term::from_field_text(repo, name))));
+        }
+        if let Some(name) = &filter.owner {
+            let owner = schema.get_field("owner")?;
+            let mut owners: Vec<Clause> = vec![(
+                Occur::Should,
+                term_query(Term::from_field_text(owner, name)),
+            )];
+            if let Some(uid) = crate::table::uid_of(name) {
+                let field = schema.get_field("uid")?;
+                owners.push((
+                    Occur::Should,
+                    term_query(Term::from_field_u64(field, uid as u64)),
+                ));
+            }
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(owners))));
+        }
+        if let Some(status) = filter.git {
+            let git_status = schema.get_field("git_status")?;
+            let status_query = |status: crate::git::GitStatus| {
+                term_query(Term::from_field_text(git_status, status.as_str()))
+            };
+            let query: Box<dyn Query> = if status == crate::git::GitStatus::Tracked {
+                Box::new(BooleanQuery::new(vec![
+                    (Occur::Should, status_query(crate::git::GitStatus::Tracked)),
+                    (Occur::Should, status_query(crate::git::GitStatus::Modified)),
+                ]))
+            } else {
+                status_query(status)
+            };
+            clauses.push((Occur::Must, query));
+        }
+        if let Some(label) = filter.label {
+            let field = schema.get_field("label")?;
+            clauses.push((
+                Occur::Must,
+                term_query(Term::from_field_text(field, label.as_str())),
+            ));
+        }
+        if let Some((low, high)) = filter.rating {
+            let field = schema.get_field("rating")?;
+            clauses.push((
+                Occur::Must,
+                Box::new(RangeQuery::new(
+                    Bound::Included(Term::from_field_u64(field, low as u64)),
+                    Bound::Included(Term::from_field_u64(field, high as u64)),
+                )),
+            ));
+        }
+        for (name, words) in [
+            ("email_from_folded", &filter.from),
+            ("email_subject_folded", &filter.subject),
+        ] {
+            let Some(words) = words else {
+                continue;
+            };
+            let field = schema.get_field(name)?;
+            let mut analyzer = self.index.tokenizer_for_field(field)?;
+            let mut terms: Vec<Term> = tokens_of(&mut analyzer, &fold(words))
+                .iter()
+                .map(|token| Term::from_field_text(field, token))
+                .collect();
+            let query: Box<dyn Query> = match terms.len() {
+                // Only punctuation, which no header word can match
+                0 => return Ok(None),
+                1 => term_query(terms.remove(0)),
+                _ => Box::new(PhraseQuery::new(terms)),
+            };
+            clauses.push((Occur::Must, query));
+        }
+        if let Some(name) = &filter.project {
+            let project = schema.get_field("project")?;
+            let roots = self.find_projects(&TermQuery::new(
+                Term::from_field_text(project, name),
+                IndexRecordOption::Basic,
+            ))?;
+            if roots.is_empty() {
+                return Ok(None);
+            }
+            // Projects sharing a name are searched together
+            let dir = schema.get_field("dir")?;
+            let scopes = roots
+                .iter()
+                .map(|root| -> (Occur, Box<dyn Query>) {
+                    (
+                        Occur::Should,
+                        term_query(Term::from_facet(dir, &dir_facet(Path::new(&root.path)))),
+                    )
+                })
+                .collect();
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(scopes))));
+        }
+        for (name, value) in [
+            ("uid", filter.uid),
+            ("gid", filter.gid),
+            ("mode", filter.mode),
+        ] {
+            if let Some(value) = value {
+                let field = schema.get_field(name)?;
+                clauses.push((
+                    Occur::Must,
+                    term_query(Term::from_field_u64(field, value as u64)),
+                ));
+            }
+        }
+
+        for (name, range) in [
+            ("created", filter.created),
+            ("modified", filter.modified),
+            ("accessed", filter.accessed),
+        ] {
+            if let Some((start, end)) = range {
+                let field = schema.get_field(name)?;
+                let bound = |secs| {
+                    Term::from_field_date(field, tantivy::DateTime::from_timestamp_secs(secs))
+                };
+                clauses.push((
+                    Occur::Must,
+                    Box::new(RangeQuery::new(
+                        Bound::Included(bound(start)),
+                        Bound::Excluded(bound(end)),
+                    )),
+                ));
+            }
+        }
+
+        if clauses.is_empty() {
+            return Ok(Some(query));
+        }
+        clauses.push((Occur::Must, query));
+        Ok(Some(Box::new(BooleanQuery::new(clauses))))
+    }
+
+    /// Query for plain words, built from terms rather than through the query
+    /// parser. It matches what the parser would plus names with a word that
+    /// starts with the query: one `name_prefix` term for a single short word,
+    /// otherwise a phrase prefix on the folded name.
+    fn plain_query(&self, query_str: &str) -> Result<Box<dyn Query>, tantivy::TantivyError> {
+        let folded = fold(query_str);
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for (fields, text) in [
+            (&EXACT_FIELDS[..], query_str),
+            (&FOLDED_FIELDS[..], folded.as_str()),
+        ] {
+            for (name, boost) in fields {
+                let field = self.schema.get_field(name)?;
+                let mut analyzer = self.index.tokenizer_for_field(field)?;
+                // The parser also analyzes each word on its own
+                for word in text.split_whitespace() {
+                    let mut terms: Vec<Term> = tokens_of(&mut analyzer, word)
+                        .iter()
+                        .map(|token| Term::from_field_text(field, token))
+                        .collect();
+                    let query: Box<dyn Query> = match terms.len() {
+                        0 => continue,
+                        1 => Box::new(TermQuery::new(
+                            terms.remove(0),
+                            IndexRecordOption::WithFreqs,
+                        )),
+                        _ => Box::new(PhraseQuery::new(terms)),
+                    };
+                    clauses.push((Occur::Should, Box::new(BoostQuery::new(query, *boost))));
+                }
+            }
+        }
+
+        let name_folded = self.schema.get_field("name_folded")?;
+        let mut analyzer = self.index.tokenizer_for_field(name_folded)?;
+        let words = tokens_of(&mut analyzer, &folded);
+        let prefix: Option<Box<dyn Query>> = match words.as_slice() {
+            [] => None,
+            [word] if word.chars().count() <= prefix::MAX_PREFIX_CHARS => {
+                Some(Box::new(TermQuery::new(
+                    Term::from_field_text(self.schema.get_field("name_prefix")?, word),
+                    IndexRecordOption::Basic,
+                )))
+            }
+            _ => Some(Box::new(PhrasePrefixQuery::new(
+                words
+                    .iter()
+                    .map(|word| Term::from_field_text(name_folded, word))
+                    .collect(),
+            ))),
+        };
+        if let Some(prefix) = prefix {
+            clauses.push((
+                Occur::Should,
+                Box::new(BoostQuery::new(prefix, PREFIX_BOOST)),
+            ));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// Highlighter for hits of `query_str`, interpreted as `search_filtered` does
+    pub fn highlighter(
+        &self,
+        query_str: &str,
+        use_regex: bool,
+    ) -> Result<Highlighter, tantivy::TantivyError> {
+        let mut analyzer = self.index.tokenizers().get("default").ok_or_else(|| {
+            tantivy::TantivyError::InvalidArgument("Missing default tokenizer".to_string())
+        })?;
+
+        if use_regex {
+            // RegexQuery matches whole terms, so anchor the pattern the same way
+            let regex = regex::Regex::new(&format!("^(?:{})$", query_str))
+                .map_err(|e| tantivy::TantivyError::InvalidArgument(e.to_string()))?;
+            return Ok(Highlighter {
+                analyzer,
+                terms: HashSet::new(),
+                regex: Some(regex),
+                prefix: None,
+            });
+        }
+
+        let (rest, _) = SearchFilter::extract(query_str)?;
+        let prefix = if is_plain(&rest) {
+            let words = tokens_of(&mut analyzer, &fold(&rest.nfc().collect::<String>()));
+            words.last().cloned()
+        } else {
+            None
+        };
+        let mut terms = HashSet::new();
+        for word in rest.split_whitespace() {
+            // Excluded terms and boolean operators never match anything
+            if word.starts_with('-') || matches!(word, "AND" | "OR" | "NOT") {
+                continue;
+            }
+            let word = word.trim_start_matches('+');
+            let word = match word.split_once(':') {
+                Some(("name" | "path", value)) => value,
+                _ => word,
+            };
+            let word: String = word.nfc().collect();
+            let mut stream = analyzer.token_stream(&word);
+            while stream.advance() {
+                terms.insert(fold(&stream.token().text));
+            }
+        }
+        Ok(Highlighter {
+            analyzer,
+            terms,
+            regex: None,
+            prefix,
+        })
+    }
+
+    /// Convert stored documents into the results returned to clients
+    pub fn to_results(
+        &self,
+        docs: &[TantivyDocument],
+    ) -> Result<Vec<SearchResult>, tantivy::TantivyError> {
+        let fields = self.fields()?;
+        Ok(docs
+            .iter()
+            .map(|doc| fields.read_entity(doc).into())
+            .collect())
+    }
+
+    /// Entries of a built-in view, using range queries and sorts on fast fields
+    /// Every indexed path of the file with `file_id`, i.e. all its hard links
+    pub fn with_file_id(
+        &self,
+        file_id: &str,
+    ) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(self.fields()?.file_id, file_id),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(LINK_LIMIT))?;
+        retrieve(&searcher, top_docs)
+    }
+
+    /// Entries sharing the rarest words of the name of the entry at `path`,
+    /// such as other versions of the same report in other folders. Its note
+    /// and email subject count too, being the only text the index holds about
+    /// what a file contains. The extension is left out, as nearly every file
+    /// shares it with many others. Empty when `path` isn't indexed. Entries
+    /// at or below `exclude` are left out.
+    pub fn find_similar(
+        &self,
+        path: &str,
+        limit: usize,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let fields = self.fields()?;
+        let id = Term::from_field_text(fields.id, &crate::index::path_id(path));
+        let found = searcher.search(
+            &TermQuery::new(id.clone(), IndexRecordOption::Basic),
+            &TopDocs::with_limit(1),
+        )?;
+        let Some((_, address)) = found.first() else {
+            return Ok(Vec::new());
+        };
+        let entity = fields.read_entity(&searcher.doc(*address)?);
+
+        let name = Path::new(&entity.name);
+        let stem = match name.file_stem() {
+            Some(stem) if !entity.is_folder => stem.to_string_lossy(),
+            _ => entity.name.as_str().into(),
+        };
+        let attributes = &entity.attributes;
+        let subject = attributes.email.as_ref().and_then(|e| e.subject.as_ref());
+        let sources = [
+            (fields.name_words, Some(stem.as_ref())),
+            (fields.note_folded, attributes.note.as_deref()),
+            (fields.email_subject_folded, subject.map(String::as_str)),
+        ]
+        .into_iter()
+        .filter_map(|(field, text)| Some((field, vec![OwnedValue::Str(fold(text?))])))
+        .collect();
+        let similar = MoreLikeThisQuery::builder()
+            // Names are short, so a word found once in one other entry counts
+            .with_min_doc_frequency(1)
+            .with_min_term_frequency(1)
+            .with_max_query_terms(SIMILAR_MAX_TERMS)
+            .with_document_fields(sources);
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![
+            (Occur::Must, Box::new(similar)),
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_bool(fields.is_folder, entity.is_folder),
+                    IndexRecordOption::Basic,
+                )),
+            ),
+            (
+                Occur::MustNot,
+                Box::new(TermQuery::new(id, IndexRecordOption::Basic)),
+            ),
+        ];
+        clauses.extend(self.exclusions(exclude)?);
+        let query = BooleanQuery::new(clauses);
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        retrieve(&searcher, top_docs)
+    }
+
+    /// Paths of every indexed symlink, junction and broken link, plus .lnk
+    /// files when `shortcuts` is set, for checking their targets on disk.
+    /// Links at or below `exclude` are left out.
+    pub fn link_paths(
+        &self,
+        shortcuts: bool,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<String>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let fields = self.fields()?;
+        let mut terms = vec![
+            Term::from_field_bool(fields.is_symlink, true),
+            Term::from_field_bool(fields.broken_link, true),
+        ];
+        if shortcuts {
+            terms.push(Term::from_facet(
+                fields.extension,
+                &Facet::from_path(["lnk"]),
+            ));
+        }
+        let query = BooleanQuery::new(
+            terms
+                .into_iter()
+                .map(|term| -> (Occur, Box<dyn Query>) {
+                    (
+                        Occur::Should,
+                        Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+                    )
+                })
+                .collect(),
+        );
+        let query = self.excluding(Box::new(query), exclude)?;
+        let addresses: Vec<DocAddress> = searcher
+            .search(&*query, &DocSetCollector)?
+            .into_iter()
+            .collect();
+        let mut paths: Vec<String> = retrieve_addresses(&searcher, &addresses)?
+            .iter()
+            .filter_map(|doc| doc.get_first(fields.path)?.as_str().map(str::to_string))
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Every indexed project root outside `exclude`, sorted by name then path
+    pub fn projects(
+        &self,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<crate::project::Project>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let fields = self.fields()?;
+        let query = BooleanQuery::new(
+            [
+                crate::project::ProjectMarker::Cargo,
+                crate::project::ProjectMarker::Npm,
+                crate::project::ProjectMarker::Git,
+            ]
+            .into_iter()
+            .map(|marker| -> (Occur, Box<dyn Query>) {
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(fields.project_marker, marker.as_str()),
+                        IndexRecordOption::Basic,
+                    )),
+                )
+            })
+            .collect(),
+        );
+        let mut projects = self.find_projects(&*self.excluding(Box::new(query), exclude)?)?;
+        projects.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path)));
+        Ok(projects)
+    }
+
+    /// Project roots matching `query` in the current searcher
+    fn find_projects(
+        &self,
+        query: &dyn Query,
+    ) -> Result<Vec<crate::project::Project>, tantivy::TantivyError> {
+        let searcher = self.reader.searcher();
+        let fields = self.fields()?;
+        let addresses: Vec<DocAddress> = searcher
+            .search(query, &DocSetCollector)?
+            .into_iter()
+            .collect();
+        Ok(retrieve_addresses(&searcher, &addresses)?
+            .iter()
+            .filter_map(|doc| {
+                let path = doc.get_first(fields.path)?.as_str()?.to_string();
+                Some(crate::project::Project {
+                    name: Path::new(&path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    markers: project_markers(doc, fields.project_marker),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// Top `limit` entries of `view` outside `exclude`
+    pub fn smart_view(
+        &self,
+        view: SmartView,
+        limit: usize,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+        self.smart_view_at(view, limit, chrono::Local::now(), exclude)
+    }
+
+    fn smart_view_at<Tz: TimeZone>(
+        &self,
+        view: SmartView,
+        limit: usize,
+        now: chrono::DateTime<Tz>,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<TantivyDocument>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let limit = limit.clamp(1, MAX_RANKED_RESULTS);
+        let addresses = match self.view_query(view, &now)? {
+            Some((query, sort)) => {
+                top_by(&searcher, &*self.excluding(query, exclude)?, sort, limit)?
+            }
+            None => self.empty_folders(&searcher, limit, exclude)?,
+        };
+        retrieve_addresses(&searcher, &addresses)
+    }
+
+    /// Counts and top `limit` hits of each query or view, all against one
+    /// searcher so they see the same commit, leaving out entries at or below
+    /// `exclude`. Entries fail independently.
+    pub fn search_batch(
+        &self,
+        queries: &[(BatchQuery, usize)],
+        exclude: &[PathBuf],
+    ) -> Result<Vec<Result<BatchHits, tantivy::TantivyError>>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let now = chrono::Local::now();
+        Ok(queries
+            .iter()
+            .map(|(query, limit)| self.batch_hits(&searcher, query, *limit, &now, exclude))
+            .collect())
+    }
+
+    fn batch_hits<Tz: TimeZone>(
+        &self,
+        searcher: &Searcher,
+        query: &BatchQuery,
+        limit: usize,
+        now: &chrono::DateTime<Tz>,
+        exclude: &[PathBuf],
+    ) -> Result<BatchHits, tantivy::TantivyError> {
+        let limit = limit.min(MAX_RANKED_RESULTS);
+        let (query, sort) = match query {
+            BatchQuery::Query { query, use_regex } => {
+                let filter = SearchFilter {
+                    exclude: exclude.to_vec(),
+                    ..Default::default()
+                };
+                match self.build_query(query, *use_regex, &filter)? {
+                    Some(query) => (query, None),
+                    None => return Ok(BatchHits::default()),
+                }
+            }
+            BatchQuery::View(view) => match self.view_query(*view, now)? {
+                Some((query, sort)) => (self.excluding(query, exclude)?, sort),
+                None => {
+                    let empty = self.empty_folders(searcher, usize::MAX, exclude)?;
+                    return Ok(BatchHits {
+                        count: empty.len(),
+                        docs: retrieve_addresses(searcher, &empty[..limit.min(empty.len())])?,
+                    });
+                }
+            },
+        };
+        let count = searcher.search(&*query, &Count)?;
+        let docs = if limit == 0 {
+            Vec::new()
+        } else {
+            retrieve_addresses(searcher, &top_by(searcher, &*query, sort, limit)?)?
+        };
+        Ok(BatchHits { count, docs })
+    }
+
+    /// Query selecting the entries of `view` and the fast field they are
+    /// sorted by, highest first. None for empty folders, which are checked
+    /// one by one.
+    fn view_query<Tz: TimeZone>(
+        &self,
+        view: SmartView,
+        now: &chrono::DateTime<Tz>,
+    ) -> Result<Option<ViewQuery>, tantivy::TantivyError> {
+        let fields = self.fields()?;
+        let flag = |field, value| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_bool(field, value),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let since = |field, secs| -> Box<dyn Query> {
+            let from = Term::from_field_date(field, tantivy::DateTime::from_timestamp_secs(secs));
+            Box::new(RangeQuery::new(Bound::Included(from), Bound::Unbounded))
+        };
+
+        Ok(Some(match view {
+            SmartView::RecentlyModified => {
+                let cutoff = now.timestamp() - (RECENT_DAYS * 24 * 60 * 60) as i64;
+                let files = BooleanQuery::new(vec![
+                    (Occur::Must, flag(fields.is_folder, false)),
+                    (Occur::Must, since(fields.modified, cutoff)),
+                ]);
+                (Box::new(files), Some("modified"))
+            }
+            SmartView::AddedToday => {
+                let (midnight, _) = date_range("today", now).ok_or_else(|| {
+                    tantivy::TantivyError::InvalidArgument("Invalid local date".to_string())
+                })?;
+                (since(fields.created, midnight), Some("created"))
+            }
+            SmartView::LargeFiles => {
+                let from = Term::from_field_u64(fields.size, LARGE_FILE_BYTES);
+                let large = RangeQuery::new(Bound::Included(from), Bound::Unbounded);
+                (Box::new(large), Some("size"))
+            }
+            SmartView::EmptyFolders => return Ok(None),
+            SmartView::BrokenSymlinks => (flag(fields.broken_link, true), None),
+        }))
+    }
+
+    /// Folders outside `exclude` with nothing indexed below them, most
+    /// recently modified first, up to `limit`
+    fn empty_folders(
+        &self,
+        searcher: &Searcher,
+        limit: usize,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<DocAddress>, tantivy::TantivyError> {
+        let fields = self.fields()?;
+        let folders = self.excluding(
+            Box::new(TermQuery::new(
+                Term::from_field_bool(fields.is_folder, true),
+                IndexRecordOption::Basic,
+            )),
+            exclude,
+        )?;
+        let total = searcher.search(&*folders, &Count)?.max(1);
+        let mut empty = Vec::new();
+        for address in top_by(searcher, &*folders, Some("modified"), total)? {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let path = fields.read_entity(&doc).path;
+            // A folder's facet term is indexed for everything below it
+            let below = TermQuery::new(
+                Term::from_facet(fields.dir, &dir_facet(Path::new(&path))),
+                IndexRecordOption::Basic,
+            );
+            if searcher.search(&below, &Count)? == 0 {
+                empty.push(address);
+                if empty.len() == limit {
+                    break;
+                }
+            }
+        }
+        Ok(empty)
+    }
+
+    /// "Did you mean" rewrites of a text query that found nothing, from the
+    /// words of indexed names. Rewrites only matching entries at or below
+    /// `exclude` are left out, so they do not give those names away.
+    pub fn suggest(
+        &self,
+        query_str: &str,
+        exclude: &[PathBuf],
+    ) -> Result<Vec<String>, tantivy::TantivyError> {
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let field = self.fields()?.name_folded;
+        let mut analyzer = self.index.tokenizer_for_field(field)?;
+        let suggestions = spelling::suggest(&searcher, field, &mut analyzer, &fold(query_str))?;
+        if exclude.is_empty() {
+            return Ok(suggestions);
+        }
+        let mut visible = Vec::new();
+        for suggestion in suggestions {
+            if let Some(query) = self.build_query(&suggestion, false, &SearchFilter::default())? {
+                if self.matches_outside(&searcher, query, exclude)? {
+                    visible.push(suggestion);
+                }
+            }
+        }
+        Ok(visible)
+    }
+
+    /// Name words completing the last word of `prefix`, and folders whose name
+    /// or path starts with it, up to `limit` of each. No words are offered
+    /// once a word is finished with a space. Words only found at or below
+    /// `exclude`, and folders there, are left out.
+    pub fn complete(
+        &self,
+        prefix: &str,
+        limit: usize,
+        exclude: &[PathBuf],
+    ) -> Result<(Vec<String>, Vec<String>), tantivy::TantivyError> {
+        let completions = self.completion_index()?;
+        let word = match prefix.split_whitespace().last() {
+            Some(word) if !prefix.ends_with(char::is_whitespace) => word,
+            _ => "",
+        };
+        if exclude.is_empty() {
+            return Ok((
+                completions.words(word, limit),
+                completions.folders(prefix.trim(), limit),
+            ));
+        }
+
+        // Fetch extra candidates so those left out don't shorten the list
+        let candidates = limit.saturating_mul(HIDDEN_COMPLETION_FACTOR);
+        let searcher = self.reader.searcher();
+        let field = self.fields()?.name_folded;
+        let mut words = Vec::new();
+        for word in completions.words(word, candidates) {
+            let query = Box::new(TermQuery::new(
+                Term::from_field_text(field, &word),
+                IndexRecordOption::Basic,
+            ));
+            if self.matches_outside(&searcher, query, exclude)? {
+                words.push(word);
+                if words.len() == limit {
+                    break;
+                }
+            }
+        }
+        let folders = completions
+            .folders(prefix.trim(), candidates)
+            .into_iter()
+            .filter(|folder| {
+                !exclude
+                    .iter()
+                    .any(|path| Path::new(folder).starts_with(path))
+            })
+            .take(limit)
+            .collect();
+        Ok((words, folders))
+    }
+
+    /// Completion FSTs of the last commit, built when first asked for after it
+    fn completion_index(&self) -> Result<Arc<CompletionIndex>, tantivy::TantivyError> {
+        let opstamp = self.opstamp()?;
+        // Held while building, so concurrent requests wait for one build
+        let mut cached = self.completions.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(completions) = cached.as_ref().filter(|c| c.opstamp == opstamp) {
+            return Ok(completions.clone());
+        }
+
+        let start_time = std::time::Instant::now();
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+        let fields = self.fields()?;
+        let query = TermQuery::new(
+            Term::from_field_bool(fields.is_folder, true),
+            IndexRecordOption::Basic,
+        );
+        let addresses: Vec<DocAddress> = searcher
+            .search(&query, &DocSetCollector)?
+            .into_iter()
+            .collect();
+        let folders = retrieve_addresses(&searcher, &addresses)?
+            .iter()
+            .filter_map(|doc| Some(doc.get_first(fields.path)?.as_str()?.to_string()))
+            .collect();
+        let completions = Arc::new(CompletionIndex::build(
+            &searcher,
+            fields.name_folded,
+            folders,
+            opstamp,
+        )?);
+        log::debug!(
+            "Built completions for opstamp {} in {}ms",
+            opstamp,
+            start_time.elapsed().as_millis()
+        );
+        *cached = Some(completions.clone());
+        Ok(completions)
+    }
+
+    /// Stamp of the last commit, which changes whenever the index does
+    pub fn opstamp(&self) -> Result<u64, tantivy::TantivyError> {
+        Ok(self.index.load_metas()?.opstamp)
+    }
+
+    /// Read the term dictionaries and postings of the name fields and the
+    /// sort fast fields of every segment, so the pages of the memory-mapped
+    /// files are resident before the first query. Each field is one step
+    /// that upgrades `index` anew, so a swap waits for one step at most.
+    /// Returns None when the index was closed before the warm-up finished.
+    pub fn warm_up(index: &Weak<SearchIndex>) -> tantivy::Result<Option<WarmUpStats>> {
+        let Some(segments) = index
+            .upgrade()
+            .map(|index| index.reader.searcher().segment_readers().len())
+        else {
+            return Ok(None);
+        };
+        let mut stats = WarmUpStats {
+            segments,
+            ..WarmUpStats::default()
+        };
+        let steps = WARM_TEXT_FIELDS.iter().map(|name| (*name, true));
+        let steps = steps.chain(WARM_FAST_FIELDS.iter().map(|name| (*name, false)));
+        for ordinal in 0..segments {
+            for (name, is_text) in steps.clone() {
+                let Some(index) = index.upgrade() else {
+                    return Ok(None);
+                };
+                // Merges may have replaced segments since the count was taken
+                let searcher = index.reader.searcher();
+                let Some(segment) = searcher.segment_readers().get(ordinal) else {
+                    return Ok(Some(stats));
+                };
+                if is_text {
+                    let field = index.schema.get_field(name)?;
+                    let inverted_index = segment.inverted_index(field)?;
+                    let mut terms = inverted_index.terms().stream()?;
+                    while terms.advance() {
+                        let mut postings = inverted_index.read_block_postings_from_terminfo(
+                            terms.value(),
+                            IndexRecordOption::Basic,
+                        )?;
+                        while !postings.docs().is_empty() {
+                            stats.postings += postings.docs().len() as u64;
+                            postings.advance();
+                        }
+                        stats.terms += 1;
+                    }
+                } else if let Some((column, _)) = segment.fast_fields().u64_lenient(name)? {
+                    // Dates are stored as u64 too, so one reader covers every sort field
+                    stats.fast_values += column.values.iter().count() as u64;
+                }
+            }
+        }
+        Ok(Some(stats))
+    }
+
+    // Note: reload() is called internally in search() method
+    // This public method is kept for potential future use
+    #[allow(dead_code)]
+    pub fn reload(&self) -> Result<(), tantivy::TantivyError> {
+        self.reader.reload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_test_index(path: &std::path::Path) -> SearchIndex {
+        SearchIndex::new(path).expect("Failed to create test index")
+    }
+
+    fn populate_test_index(index: &SearchIndex) -> Result<(), tantivy::TantivyError> {
+        let schema = index.get_schema();
+        let name_field = schema.get_field("name")?;
+        let path_field = schema.get_field("path")?;
+        let size_field = schema.get_field("size")?;
+        let modified_field = schema.get_field("modified")?;
+        let is_folder_field = schema.get_field("is_folder")?;
+
+        let mut writer = index.writer()?;
+
+        let documents = vec![
+            (
+                "document.txt",
+                "/home/user/documents/document.txt",
+                1024,
+                1640000000,
+                false,
+            ),
+            (
+                "report.pdf",
+                "/home/user/reports/report.pdf",
+                51200,
+                1640001000,
+                false,
+            ),
+            (
+                "image.jpg",
+                "/home/user/images/image.jpg",
+                204800,
+                1640002000,
+                false,
+            ),
+            ("folder", "/home/user/folder", 0, 1640003000, true),
+            (
+                "notes.txt",
+                "/home/user/folder/notes.txt",
+                512,
+                1640004000,
+                false,
+            ),
+        ];
+
+        for (name, path, size, modified, is_folder) in documents {
+            let mut doc = tantivy::TantivyDocument::default();
+            doc.add_text(name_field, name);
+            doc.add_text(path_field, path);
+            doc.add_u64(size_field, size);
+            doc.add_date(
+                modified_field,
+                tantivy::DateTime::from_timestamp_secs(modified),
+            );
+            doc.add_bool(is_folder_field, is_folder);
+            writer.add_document(doc)?;
+        }
+
+        writer.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_index_new_creates_directory() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("test_index");
+
+        assert!(
+            !index_path.exists(),
+            "Index path should not exist initially"
+        );
+
+        let index = SearchIndex::new(&index_path);
+        assert!(index.is_ok(), "SearchIndex::new should succeed");
+
+        assert!(index_path.exists(), "Index directory should be created");
+    }
+
+    #[test]
+    fn test_search_index_new_opens_existing_index() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("test_index");
+
+        let index1 = create_test_index(&index_path);
+        populate_test_index(&index1).unwrap();
+
+        let index2 = SearchIndex::new(&index_path);
+        assert!(index2.is_ok(), "Should be able to open existing index");
+    }
+
+    #[test]
+    fn test_get_schema() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("test_index");
+        let index = create_test_index(&index_path);
+
+        let schema = index.get_schema();
+        assert!(schema.get_field("name").is_ok(), "Should have name field");
+        assert!(schema.get_field("path").is_ok(), "Should have path field");
+        assert!(schema.get_field("size").is_ok(), "Should have size field");
+        assert!(
+            schema.get_field("modified").is_ok(),
+            "Should have modified field"
+        );
+        assert!(
+            schema.get_field("is_folder").is_ok(),
+            "Should have is_folder field"
+        );
+    }
+
+    #[test]
+    fn test_writer() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("test_index");
+        let index = create_test_index(&index_path);
+
+        let writer = index.writer();
+        assert!(writer.is_ok(), "Should be able to create writer");
+
+        let writer = writer.unwrap();
+        let schema = index.get_schema();
+        let name_field = schema.get_field("name").unwrap();
+        let path_field = schema.get_field("path").unwrap();
+        let size_field = schema.get_field("size").unwrap();
+        let modified_field = schema.get_field("modified").unwrap();
+        let is_folder_field = schema.get_field("is_folder").unwrap();
+
+        let mut doc = tantivy::TantivyDocument::default();
+        doc.add_text(name_field, "test.txt");
+        doc.add_text(path_field, "/test.txt");
+        doc.add_u64(size_field, 100);
+        doc.add_date(modified_field, tantivy::DateTime::from_timestamp_secs(1000));
+        doc.add_bool(is_folder_field, false);
+
+        let result = writer.add_document(doc);
+        assert!(result.is_ok(), "Should be able to add document");
+    }
+
+    #[test]
+    fn test_fields_build_document() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("test_index");
+        let index = create_test_index(&index_path);
+
+        let fields = index.fields().unwrap();
+        let entity = crate::FileEntity {
+            id: "id".to_string(),
+            name: "built.txt".to_string(),
+            path: "/tmp/built.txt".to_string(),
+            size: 42,
+            modified: 1640000000,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        };
+
+        let mut writer = index.writer().unwrap();
+        writer.add_document(fields.build_document(&entity)).unwrap();
+        writer.commit().unwrap();
+
+        let results = index.search("built", false, 10).unwrap();
+        assert_eq!(results.len(), 1);
+        let entity_back = fields.read_entity(&results[0]);
+        assert_eq!(entity_back.id, "id");
+        assert_eq!(entity_back.path, "/tmp/built.txt");
+        assert_eq!(entity_back.size, 42);
+        assert_eq!(entity_back.modified, 1640000000);
+
+        let result = &index.to_results(&results).unwrap()[0];
+        assert_eq!(result.modified, "2021-12-20T11:33:20Z");
+        let json = serde_json::to_value(result).unwrap();
+        assert_eq!(json["path"], "/tmp/built.txt");
+        assert!(json.get("profile").is_none());
+    }
+
+    #[test]
+    fn test_highlighter_marks_matched_tokens() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let result = |name: &str, path: &str| {
+            SearchResult::from(crate::FileEntity {
+                id: path.to_string(),
+                name: name.to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 0,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            })
+        };
+
+        let mut hit = result(
+            "Annual Report-2024.pdf",
+            "/docs/reports/Annual Report-2024.pdf",
+        );
+        index
+            .highlighter("report 2024 -annual attrib:readonly", false)
+            .unwrap()
+            .highlight(&mut hit);
+        assert_eq!(hit.matches.name, vec![(7, 13), (14, 18)]);
+        assert_eq!(hit.matches.path, vec![(21, 27), (28, 32)]);
+
+        // Offsets count chars, not bytes
+        let mut hit = result("Über Bericht.txt", "/Über Bericht.txt");
+        index
+            .highlighter("bericht", false)
+            .unwrap()
+            .highlight(&mut hit);
+        assert_eq!(hit.matches.name, vec![(5, 12)]);
+
+        // Regex queries match whole name tokens and never the path
+        let mut hit = result("Annual Report-2024.pdf", "/docs/report/a.pdf");
+        index
+            .highlighter("rep.*", true)
+            .unwrap()
+            .highlight(&mut hit);
+        assert_eq!(hit.matches.name, vec![(7, 13)]);
+        assert!(hit.matches.path.is_empty());
+
+        // Words inside identifiers are marked in the name
+        let mut hit = result("MyHttpServer.rs", "/src/MyHttpServer.rs");
+        index
+            .highlighter("http server", false)
+            .unwrap()
+            .highlight(&mut hit);
+        assert_eq!(hit.matches.name, vec![(2, 6), (6, 12)]);
+    }
+
+    #[test]
+    fn test_search_matches_identifier_words() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+        let mut writer = index.writer().unwrap();
+        for name in [
+            "MyHttpServer.rs",
+            "http_client.py",
+            "server-config.json",
+            "httpserver.md",
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/src/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            let mut names: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert!(names("http").contains(&"MyHttpServer.rs".to_string()));
+        assert!(names("server").contains(&"MyHttpServer.rs".to_string()));
+        assert_eq!(
+            names("\"http server\""),
+            vec!["MyHttpServer.rs"],
+            "Identifier words are adjacent like separate words"
+        );
+    }
+
+    #[test]
+    fn test_search_page_reaches_past_the_ranked_limit() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = (0..MAX_RANKED_RESULTS + 5)
+            .map(|i| {
+                let path = format!("/home/user/file{}.txt", i);
+                crate::FileEntity {
+                    id: path.clone(),
+                    name: format!("file{}.txt", i),
+                    path,
+                    size: 0,
+                    modified: 0,
+                    is_folder: false,
+                    hidden: false,
+                    attributes: Default::default(),
+                    created: None,
+                    accessed: None,
+                }
+            })
+            .collect();
+        index.apply_changes(&[], &entities).unwrap();
+        index.reload().unwrap();
+
+        let page = |offset, limit| {
+            index
+                .search_page(
+                    "file",
+                    false,
+                    &SearchFilter::default(),
+                    (offset, limit),
+                    &CancelToken::default(),
+                )
+                .unwrap()
+        };
+        let (docs, total) = page(MAX_RANKED_RESULTS, 10);
+        assert_eq!(docs.len(), 5);
+        assert_eq!(total, MAX_RANKED_RESULTS + 5);
+        let (docs, total) = page(0, 0);
+        assert!(docs.is_empty());
+        assert_eq!(total, MAX_RANKED_RESULTS + 5);
+
+        // Pages line up with one ranked search
+        let ranked = index.to_results(&page(0, 20).0).unwrap();
+        let second = index.to_results(&page(10, 10).0).unwrap();
+        assert_eq!(ranked[10..], second[..]);
+    }
+
+    #[test]
+    fn test_search_faceted_counts_all_matches() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = [
+            ("/home/user/docs/report.pdf", false),
+            ("/home/user/docs/2024/report.PDF", false),
+            ("/home/user/code/report.rs", false),
+            ("/home/user/report", true),
+            ("/home/user/notes.txt", false),
+        ]
+        .into_iter()
+        .map(|(path, is_folder)| crate::FileEntity {
+            id: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_folder,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        })
+        .collect();
+        index.apply_changes(&[], &entities).unwrap();
+        index.reload().unwrap();
+
+        let (docs, facets) = index
+            .search_faceted(
+                "report",
+                false,
+                1,
+                &SearchFilter::default(),
+                &["/home/user".to_string()],
+                &CancelToken::default(),
+            )
+            .unwrap();
+        assert_eq!(docs.len(), 1, "limit applies to hits, not to counts");
+
+        let pairs = |counts: &[FacetCount]| {
+            counts
+                .iter()
+                .map(|c| (c.value.clone(), c.count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            pairs(&facets.extensions),
+            vec![("pdf".to_string(), 2), ("rs".to_string(), 1)]
+        );
+        assert_eq!(
+            pairs(&facets.file_types),
+            vec![
+                ("document".to_string(), 2),
+                ("code".to_string(), 1),
+                ("folder".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            pairs(&facets.folders),
+            vec![
+                ("/home/user/docs".to_string(), 2),
+                ("/home/user/code".to_string(), 1)
+            ]
+        );
+
+        let mut merged = facets.clone();
+        merged.merge(facets);
+        assert_eq!(merged.extensions[0].count, 4);
+    }
+
+    #[test]
+    fn test_exclude_leaves_entries_out_before_ranking() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = [
+            ("/home/user/private", true),
+            ("/home/user/private/report.pdf", false),
+            ("/home/user/private/deep/report.txt", false),
+            ("/home/user/private/secretplan.txt", false),
+            ("/home/user/docs/report.doc", false),
+        ]
+        .into_iter()
+        .map(|(path, is_folder)| crate::FileEntity {
+            id: crate::index::path_id(path),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_folder,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        })
+        .collect();
+        index.apply_changes(&[], &entities).unwrap();
+        index.reload().unwrap();
+        let exclude = vec![PathBuf::from("/home/user/private")];
+        let filter = SearchFilter {
+            exclude: exclude.clone(),
+            ..Default::default()
+        };
+
+        // Private hits take no slot and count towards no facet
+        let (docs, facets) = index
+            .search_faceted(
+                "report",
+                false,
+                1,
+                &filter,
+                &["/home/user".to_string()],
+                &CancelToken::default(),
+            )
+            .unwrap();
+        let results = index.to_results(&docs).unwrap();
+        assert_eq!(results[0].path, "/home/user/docs/report.doc");
+        let values =
+            |counts: &[FacetCount]| counts.iter().map(|c| c.value.clone()).collect::<Vec<_>>();
+        assert_eq!(values(&facets.extensions), vec!["doc"]);
+        assert_eq!(values(&facets.folders), vec!["/home/user/docs"]);
+        let private = index
+            .search_filtered("private", false, 10, &filter, &CancelToken::default())
+            .unwrap();
+        assert!(private.is_empty());
+
+        assert_eq!(
+            index.complete("secr", 10, &[]).unwrap().0,
+            vec!["secretplan"]
+        );
+        assert!(index.complete("secr", 10, &exclude).unwrap().0.is_empty());
+        assert!(index
+            .complete("/home/user/pri", 10, &exclude)
+            .unwrap()
+            .1
+            .is_empty());
+        assert!(index.suggest("secretplna", &exclude).unwrap().is_empty());
+        assert!(index.projects(&exclude).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_filtered_excludes_hidden() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for (name, hidden) in [("config.toml", false), (".config", true)] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/home/user/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let all = index.search("config", false, 10).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let filter = SearchFilter {
+            exclude_hidden: true,
+            ..Default::default()
+        };
+        let visible = index
+            .search_filtered("config", false, 10, &filter, &CancelToken::default())
+            .unwrap();
+        assert_eq!(visible.len(), 1);
+        let result = &index.to_results(&visible).unwrap()[0];
+        assert_eq!(result.name, "config.toml");
+        assert!(!result.hidden);
+    }
+
+    #[test]
+    fn test_search_filtered_scope_limits_to_subtree() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = [
+            ("/home/user/projects", true),
+            ("/home/user/projects/notes.md", false),
+            ("/home/user/projects/app/notes.md", false),
+            ("/home/user/projects-old/notes.md", false),
+            ("/home/user/notes.md", false),
+        ]
+        .into_iter()
+        .map(|(path, is_folder)| crate::FileEntity {
+            id: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_folder,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        })
+        .collect();
+        index.apply_changes(&[], &entities).unwrap();
+
+        let filter = SearchFilter {
+            scope: Some(PathBuf::from("/home/user/projects")),
+            ..Default::default()
+        };
+        let paths = |query: &str| {
+            let docs = index
+                .search_filtered(query, false, 10, &filter, &CancelToken::default())
+                .unwrap();
+            let mut paths: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        let expected = vec![
+            "/home/user/projects/app/notes.md".to_string(),
+            "/home/user/projects/notes.md".to_string(),
+        ];
+        assert_eq!(paths("notes"), expected);
+        // A scope alone lists everything below the directory, but not the directory itself
+        assert_eq!(paths(""), expected);
+    }
+
+    #[test]
+    fn test_fold_ignores_diacritics_width_and_composition() {
+        assert_eq!(fold("résumé"), "resume");
+        assert_eq!(fold("re\u{301}sume\u{301}"), "resume");
+        assert_eq!(fold("ＲＥＰＯＲＴ２０２４"), "REPORT2024");
+        // Hangul in jamo form recomposes into syllables
+        assert_eq!(
+            fold("\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}"),
+            "한글"
+        );
+        // Half-width kana widen; voicing marks are kept
+        assert_eq!(fold("ｶﾞｲﾄﾞ"), "ガイド");
+        assert_eq!(fold("か\u{3099}"), "が");
+        assert_ne!(fold("が"), fold("か"));
+    }
+
+    #[test]
+    fn test_search_matches_across_accents_and_normalization_forms() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = [
+            "/docs/résumé.pdf",
+            // Decomposed, as HFS+ stores names
+            "/docs/\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}.txt",
+            "/docs/ｶﾞｲﾄﾞ.md",
+        ]
+        .into_iter()
+        .map(|path| crate::FileEntity {
+            id: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        })
+        .collect();
+        index.apply_changes(&[], &entities).unwrap();
+
+        let names = |query: &str| {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("resume"), vec!["résumé.pdf"]);
+        assert_eq!(names("RÉSUMÉ"), vec!["résumé.pdf"]);
+        // Names come back in NFC
+        assert_eq!(names("한글"), vec!["한글.txt"]);
+        assert_eq!(names("ガイド"), vec!["ｶﾞｲﾄﾞ.md"]);
+
+        let mut hit = index
+            .to_results(&index.search("resume", false, 1).unwrap())
+            .unwrap();
+        index
+            .highlighter("resume", false)
+            .unwrap()
+            .highlight(&mut hit[0]);
+        assert_eq!(hit[0].matches.name, vec![(0, 6)]);
+    }
+
+    #[test]
+    fn test_search_finds_cjk_name_fragments() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = ["/photos/여행사진2024.jpg", "/docs/東京タワー.pdf"]
+            .into_iter()
+            .map(|path| crate::FileEntity {
+                id: path.to_string(),
+                name: Path::new(path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 0,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            })
+            .collect();
+        index.apply_changes(&[], &entities).unwrap();
+
+        let names = |query: &str| {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("사진"), vec!["여행사진2024.jpg"]);
+        assert_eq!(names("タワー"), vec!["東京タワー.pdf"]);
+        assert_eq!(names("東京 pdf"), vec!["東京タワー.pdf"]);
+        // Fragments must be adjacent in the name
+        assert!(names("진여").is_empty());
+
+        let mut hits = index
+            .to_results(&index.search("사진", false, 1).unwrap())
+            .unwrap();
+        index
+            .highlighter("사진", false)
+            .unwrap()
+            .highlight(&mut hits[0]);
+        assert_eq!(hits[0].matches.name, vec![(2, 4)]);
+    }
+
+    #[test]
+    fn test_search_matches_word_prefixes() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = [
+            "/docs/Annual Report 2024.pdf",
+            "/docs/reporting/notes.txt",
+            "/docs/Résumé final.docx",
+            "/docs/supercalifragilistic.txt",
+        ]
+        .into_iter()
+        .map(|path| crate::FileEntity {
+            id: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        })
+        .collect();
+        index.apply_changes(&[], &entities).unwrap();
+
+        let names = |query: &str| {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("rep"), vec!["Annual Report 2024.pdf"]);
+        assert_eq!(names("Annual Rep"), vec!["Annual Report 2024.pdf"]);
+        assert_eq!(names("resu"), vec!["Résumé final.docx"]);
+        // Longer than the indexed prefixes, so found through the folded name
+        assert_eq!(names("supercalifrag"), vec!["supercalifragilistic.txt"]);
+        // Prefixes of path components are not matched
+        assert!(names("notes").contains(&"notes.txt".to_string()));
+        assert!(!names("reporti").contains(&"notes.txt".to_string()));
+
+        let mut hits = index
+            .to_results(&index.search("annual rep", false, 1).unwrap())
+            .unwrap();
+        index
+            .highlighter("annual rep", false)
+            .unwrap()
+            .highlight(&mut hits[0]);
+        assert_eq!(hits[0].matches.name, vec![(0, 6), (7, 10)]);
+    }
+
+    #[test]
+    fn test_search_by_choseong_and_pinyin_initials() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = ["/photos/여행사진.jpg", "/photos/我的照片.png"]
+            .into_iter()
+            .map(|path| crate::FileEntity {
+                id: path.to_string(),
+                name: Path::new(path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 0,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            })
+            .collect();
+        index.apply_changes(&[], &entities).unwrap();
+
+        let names = |query: &str| {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("ㅅㅈ"), vec!["여행사진.jpg"]);
+        assert_eq!(names("ㅇㅎㅅㅈ"), vec!["여행사진.jpg"]);
+        assert_eq!(names("zp"), vec!["我的照片.png"]);
+        assert_eq!(names("WDZP"), vec!["我的照片.png"]);
+        assert!(names("ㅈㅅ").is_empty());
+    }
+
+    #[test]
+    fn test_smart_views() {
+        use chrono::Utc;
+
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        let ago = |hours: i64| now.timestamp() - hours * 60 * 60;
+        let entity = |path: &str, is_folder, modified, created, size| crate::FileEntity {
+            id: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size,
+            modified,
+            is_folder,
+            hidden: false,
+            attributes: Default::default(),
+            created,
+            accessed: None,
+        };
+        let mut link = entity("/r/link", false, ago(1000), None, 0);
+        link.attributes.broken_link = true;
+        let entities = vec![
+            entity("/r/old.txt", false, ago(30 * 24), None, 10),
+            entity("/r/new.txt", false, ago(24), Some(ago(2)), 10),
+            entity("/r/newer.iso", false, ago(1), None, 200 * 1024 * 1024),
+            entity("/r/empty", true, ago(3 * 24), Some(ago(3)), 0),
+            entity("/r/full", true, ago(500), None, 0),
+            entity("/r/full/a.txt", false, ago(500), None, 0),
+            link,
+        ];
+        index.apply_changes(&[], &entities).unwrap();
+
+        let names = |view| {
+            let docs = index.smart_view_at(view, 10, now, &[]).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(SmartView::RecentlyModified),
+            vec!["newer.iso", "new.txt"]
+        );
+        assert_eq!(names(SmartView::AddedToday), vec!["new.txt", "empty"]);
+        assert_eq!(names(SmartView::LargeFiles), vec!["newer.iso"]);
+        assert_eq!(names(SmartView::EmptyFolders), vec!["empty"]);
+        assert_eq!(names(SmartView::BrokenSymlinks), vec!["link"]);
+        assert_eq!(
+            SmartView::from_name("large_files"),
+            Some(SmartView::LargeFiles)
+        );
+        assert_eq!(SmartView::from_name("huge_files"), None);
+    }
+
+    #[test]
+    fn test_group_by_folder_counts_direct_children() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> = [
+            "/data/logs/a.log",
+            "/data/logs/b.log",
+            "/data/logs/old/c.log",
+            "/data/d.log",
+            "/data/e.log",
+            "/data/logs/readme.txt",
+        ]
+        .into_iter()
+        .map(|path| crate::FileEntity {
+            id: path.to_string(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        })
+        .collect();
+        index.apply_changes(&[], &entities).unwrap();
+        index.reload().unwrap();
+
+        let group = |limit| {
+            index
+                .group_by_folder(
+                    "log",
+                    false,
+                    &SearchFilter::default(),
+                    limit,
+                    &CancelToken::default(),
+                )
+                .unwrap()
+        };
+        let path = |path: &str| Path::new(path).to_string_lossy().to_string();
+        let groups = group(10);
+        assert_eq!(groups.total_found, 5);
+        assert_eq!(groups.total_folders, 3);
+        assert_eq!(
+            groups.folders,
+            vec![
+                FolderGroup {
+                    path: path("/data"),
+                    count: 2
+                },
+                FolderGroup {
+                    path: path("/data/logs"),
+                    count: 2
+                },
+                FolderGroup {
+                    path: path("/data/logs/old"),
+                    count: 1
+                },
+            ]
+        );
+
+        let limited = group(1);
+        assert_eq!((limited.total_folders, limited.folders.len()), (3, 1));
+    }
+
+    #[test]
+    fn test_search_batch_shares_one_searcher() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        populate_test_index(&index).unwrap();
+
+        let query = |query: &str, use_regex| BatchQuery::Query {
+            query: query.to_string(),
+            use_regex,
+        };
+        let batch = index
+            .search_batch(
+                &[
+                    (query("txt", false), 1),
+                    (query("report", false), 0),
+                    (query("(", true), 5),
+                    (BatchQuery::View(SmartView::EmptyFolders), 5),
+                ],
+                &[],
+            )
+            .unwrap();
+        let txt = batch[0].as_ref().unwrap();
+        assert_eq!((txt.count, txt.docs.len()), (2, 1));
+        let report = batch[1].as_ref().unwrap();
+        assert_eq!((report.count, report.docs.len()), (1, 0));
+        assert!(batch[2].is_err(), "A bad entry fails alone");
+        assert_eq!(batch[3].as_ref().unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_refine_searches_within_previous_hits() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        populate_test_index(&index).unwrap();
+
+        let mut sessions = SearchSessions::default();
+        let first = sessions.insert(SearchSession {
+            profile: "default".to_string(),
+            steps: vec![SearchStep {
+                query: "txt".to_string(),
+                use_regex: false,
+                filter: SearchFilter::default(),
+            }],
+        });
+        let session = sessions.get(first).unwrap().clone();
+        let names = |query: &str, use_regex: bool| {
+            let filter = session.within(SearchFilter::default());
+            let docs = index
+                .search_filtered(query, use_regex, 10, &filter, &CancelToken::default())
+                .unwrap();
+            let mut names: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        // "folder" alone also matches the folder itself, which is not a .txt hit
+        assert_eq!(names("folder", false), vec!["notes.txt"]);
+        assert_eq!(names("doc.*", true), vec!["document.txt"]);
+        assert_eq!(names("", false), vec!["document.txt", "notes.txt"]);
+
+        // Only the most recent sessions are kept
+        for _ in 0..SESSION_LIMIT {
+            sessions.insert(session.clone());
+        }
+        assert!(sessions.get(first).is_none());
+        assert!(sessions.get(first + SESSION_LIMIT as u64).is_some());
+    }
+
+    #[test]
+    fn test_query_cache_invalidated_by_commits() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        populate_test_index(&index).unwrap();
+
+        let key = |query: &str| CacheKey {
+            profile: "default".to_string(),
+            query: query.to_string(),
+            use_regex: false,
+            limit: 10,
+            filter: SearchFilter::default(),
+            facets: false,
+        };
+        let search = |query: &str| -> CachedSearch {
+            let docs = index.search(query, false, 10).unwrap();
+            (index.to_results(&docs).unwrap(), None)
+        };
+
+        let mut cache = QueryCache::default();
+        let opstamp = index.opstamp().unwrap();
+        assert!(cache.get(&key("notes"), opstamp).is_none());
+        cache.insert(key("notes"), opstamp, search("notes"));
+        assert_eq!(cache.get(&key("notes"), opstamp), Some(search("notes")));
+        assert!(cache.get(&key("report"), opstamp).is_none());
+
+        // The least recently used entry is evicted first
+        for i in 0..CACHE_LIMIT - 1 {
+            cache.insert(key(&format!("q{}", i)), opstamp, (Vec::new(), None));
+        }
+        assert!(cache.get(&key("notes"), opstamp).is_some());
+        cache.insert(key("report"), opstamp, search("report"));
+        assert!(cache.get(&key("q0"), opstamp).is_none());
+        assert!(cache.get(&key("notes"), opstamp).is_some());
+
+        index.apply_changes(&["missing".to_string()], &[]).unwrap();
+        let committed = index.opstamp().unwrap();
+        assert_ne!(committed, opstamp);
+        assert!(cache.get(&key("notes"), committed).is_none());
+    }
+
+    #[test]
+    fn test_extract_inline_filters() {
+        let (rest, filter) =
+            SearchFilter::extract("report attrib:readonly owner:1000 mode:644").unwrap();
+        assert_eq!(rest, "report");
+        assert_eq!(filter.readonly, Some(true));
+        assert_eq!(filter.uid, Some(1000));
+        assert_eq!(filter.mode, Some(0o644));
+        assert_eq!(filter.gid, None);
+
+        let (_, filter) = SearchFilter::extract("owner:Alice").unwrap();
+        assert_eq!((filter.uid, filter.owner.as_deref()), (None, Some("alice")));
+        assert!(SearchFilter::extract("attrib:bogus").is_err());
+
+        let (rest, filter) = SearchFilter::extract("type:link lib").unwrap();
+        assert_eq!((rest.as_str(), filter.symlink), ("lib", Some(true)));
+        assert!(SearchFilter::extract("type:pipe").is_err());
+
+        let (rest, filter) = SearchFilter::extract("git:modified repo:My-App main").unwrap();
+        assert_eq!(rest, "main");
+        assert_eq!(filter.git, Some(crate::git::GitStatus::Modified));
+        assert_eq!(filter.repo.as_deref(), Some("my-app"));
+        assert!(SearchFilter::extract("git:dirty").is_err());
+        assert!(SearchFilter::extract("repo:").is_err());
+        let (_, filter) = SearchFilter::extract("project:Site").unwrap();
+        assert_eq!(filter.project.as_deref(), Some("site"));
+
+        let (rest, filter) = SearchFilter::extract("label:Red rating:>=4 sort:rating jpg").unwrap();
+        assert_eq!(rest, "jpg");
+        assert_eq!(filter.label, Some(crate::marks::ColorLabel::Red));
+        assert_eq!(filter.rating, Some((4, 5)));
+        assert_eq!(filter.order, ResultOrder::Rating);
+        for (spec, range) in [
+            ("3", (3, 3)),
+            (">3", (4, 5)),
+            ("<=2", (1, 2)),
+            ("<3", (1, 2)),
+        ] {
+            let (_, filter) = SearchFilter::extract(&format!("rating:{}", spec)).unwrap();
+            assert_eq!(filter.rating, Some(range), "rating:{}", spec);
+        }
+        for bad in [
+            "label:teal",
+            "rating:>5",
+            "rating:<1",
+            "rating:many",
+            "sort:size",
+        ] {
+            assert!(SearchFilter::extract(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_search_label_and_rating() {
+        use crate::marks::ColorLabel;
+
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for (name, label, rating) in [
+            ("beach.jpg", Some(ColorLabel::Red), Some(3)),
+            ("sunset.jpg", None, Some(5)),
+            ("blurry.jpg", Some(ColorLabel::Gray), Some(1)),
+            ("receipt.jpg", Some(ColorLabel::Red), None),
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/photos/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    label,
+                    rating,
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect()
+        };
+        let sorted = |query: &str| {
+            let mut names = names(query);
+            names.sort();
+            names
+        };
+        assert_eq!(sorted("label:red"), vec!["beach.jpg", "receipt.jpg"]);
+        assert_eq!(sorted("rating:>=3"), vec!["beach.jpg", "sunset.jpg"]);
+        assert_eq!(sorted("jpg label:red rating:3"), vec!["beach.jpg"]);
+        assert!(names("rating:2").is_empty());
+        assert_eq!(
+            names("jpg sort:rating"),
+            vec!["sunset.jpg", "beach.jpg", "blurry.jpg", "receipt.jpg"],
+            "Highest rated first, unrated last"
+        );
+
+        let result = &index
+            .to_results(&index.search("sunset", false, 1).unwrap())
+            .unwrap()[0];
+        assert_eq!(result.attributes.rating, Some(5));
+        assert_eq!(result.attributes.label, None);
+    }
+
+    #[test]
+    fn test_search_wildcards() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for name in [
+            "server.log",
+            "Server.LOG.1",
+            "error report.log",
+            "Annual Report.pdf",
+            "report.tmp",
+            "IMG_0412.jpg",
+            "IMG_12.jpg",
+            "Café.log",
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/logs/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            let mut names: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names("*.log"),
+            vec!["Café.log", "error report.log", "server.log"]
+        );
+        assert_eq!(
+            names("*report*"),
+            vec!["Annual Report.pdf", "error report.log", "report.tmp"]
+        );
+        assert_eq!(names("*report* -*.tmp -*.pdf"), vec!["error report.log"]);
+        assert_eq!(names("IMG_????.jpg"), vec!["IMG_0412.jpg"]);
+        assert_eq!(names("cafe*"), vec!["Café.log"]);
+        assert_eq!(names("server *.log*"), vec!["Server.LOG.1", "server.log"]);
+        assert!(index.search("*", false, 10).is_err());
+        assert!(index.search("*.a", false, 10).is_ok());
+        assert!(index.search("?*", false, 10).is_err());
+    }
+
+    #[test]
+    fn test_complete_words_and_folders() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let add = |writer: &mut IndexWriter, path: &str, is_folder: bool| {
+            let entity = crate::FileEntity {
+                id: path.to_string(),
+                name: path.rsplit('/').next().unwrap().to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 1640000000,
+                is_folder,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        };
+        let mut writer = index.writer().unwrap();
+        for (path, is_folder) in [
+            ("/home/me/Reports", true),
+            ("/home/me/Reports/report 2023.pdf", false),
+            ("/home/me/Reports/report 2024.pdf", false),
+            ("/home/me/Reports/Résumé.docx", false),
+            ("/home/me/Reports/old", true),
+            ("/home/me/Reports/old/replay.log", false),
+            ("/archive/reports", true),
+        ] {
+            add(&mut writer, path, is_folder);
+        }
+        writer.commit().unwrap();
+
+        let (terms, folders) = index.complete("annual rep", 10, &[]).unwrap();
+        assert_eq!(terms, vec!["report", "reports", "replay"]);
+        assert_eq!(
+            folders,
+            Vec::<String>::new(),
+            "Folder names must start the text"
+        );
+        let (terms, folders) = index.complete("rep", 2, &[]).unwrap();
+        assert_eq!(terms, vec!["report", "reports"]);
+        assert_eq!(folders, vec!["/archive/reports", "/home/me/Reports"]);
+        assert_eq!(index.complete("resu", 10, &[]).unwrap().0, vec!["resume"]);
+        let (terms, folders) = index.complete("/home/me/reports/", 10, &[]).unwrap();
+        assert!(terms.is_empty());
+        assert_eq!(folders, vec!["/home/me/Reports/old"]);
+        assert!(index.complete("report ", 10, &[]).unwrap().0.is_empty());
+        assert_eq!(index.complete("", 10, &[]).unwrap(), (vec![], vec![]));
+
+        // Rebuilt after the next commit
+        add(&mut writer, "/home/me/repos", true);
+        writer.commit().unwrap();
+        assert_eq!(
+            index.complete("repo", 10, &[]).unwrap().1,
+            vec!["/archive/reports", "/home/me/Reports", "/home/me/repos"]
+        );
+    }
+
+    #[test]
+    fn test_recent_queries() {
+        let mut sessions = SearchSessions::default();
+        for query in ["invoice", "Résumé 2024", "invoice", "  ", "report"] {
+            sessions.insert(SearchSession {
+                profile: "default".to_string(),
+                steps: vec![SearchStep {
+                    query: query.to_string(),
+                    use_regex: false,
+                    filter: SearchFilter::default(),
+                }],
+            });
+        }
+        assert_eq!(
+            sessions.recent_queries("", 10),
+            vec!["report", "invoice", "Résumé 2024"]
+        );
+        assert_eq!(sessions.recent_queries("resu", 10), vec!["Résumé 2024"]);
+        assert_eq!(sessions.recent_queries("", 1), vec!["report"]);
+    }
+
+    #[test]
+    fn test_spelling_suggestions() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for name in [
+            "Annual Report 2023.pdf",
+            "report card.docx",
+            "budget.xlsx",
+            "budgie.jpg",
+            "budgie nest.jpg",
+            "Café menu.txt",
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/docs/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        assert_eq!(
+            index.suggest("anual reprot", &[]).unwrap(),
+            vec!["annual report"]
+        );
+        assert_eq!(
+            index.suggest("budgte ext:pdf", &[]).unwrap(),
+            vec!["budgie ext:pdf", "budget ext:pdf"],
+            "More common terms first"
+        );
+        assert_eq!(index.suggest("cafe mneu", &[]).unwrap(), vec!["cafe menu"]);
+        assert!(index.suggest("report 2032", &[]).unwrap().is_empty());
+        assert!(index.suggest("zzzzzz -reprot", &[]).unwrap().is_empty());
+        assert!(index.suggest("", &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for (path, is_folder) in [
+            ("/work/QuarterlyReport_2023_v2.docx", false),
+            ("/backup/old/quarterly-report-2023-final.docx", false),
+            ("/mail/Quarterly report 2023 draft.pdf", false),
+            ("/work/quarterly-report", true),
+            ("/work/budget_2023.xlsx", false),
+            ("/work/shopping list.docx", false),
+        ] {
+            let name = path.rsplit('/').next().unwrap();
+            let entity = crate::FileEntity {
+                id: crate::index::path_id(path),
+                name: name.to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 1640000000,
+                is_folder,
+                hidden: false,
+                attributes: Default::default(),
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let similar = |path: &str| -> Vec<String> {
+            let docs = index.find_similar(path, 10, &[]).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.path)
+                .collect()
+        };
+        let found = similar("/work/QuarterlyReport_2023_v2.docx");
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert_eq!(
+            found[2], "/work/budget_2023.xlsx",
+            "Fewest shared words last"
+        );
+        assert!(!found.contains(&"/work/shopping list.docx".to_string()));
+        assert!(!found.contains(&"/work/quarterly-report".to_string()));
+        assert!(similar("/work/shopping list.docx").is_empty());
+        assert!(similar("/not/indexed.txt").is_empty());
+    }
 
-        // Reload reader to get latest index updates
-        self.reader.reload()?;
+    #[test]
+    fn test_search_matches_notes() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-        let searcher = self.reader.searcher();
-        let schema = self.schema.clone();
+        let mut writer = index.writer().unwrap();
+        for (name, note) in [
+            (
+                "IMG_0412.jpg",
+                Some("Café opening night with the whole team"),
+            ),
+            ("IMG_0413.jpg", None),
+            ("invoice.pdf", Some("Paid in March")),
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/files/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    note: note.map(str::to_string),
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
 
-        let query: Box<dyn Query> = if use_regex {
-            // For regex queries, search in name field
-            let name_field = schema.get_field("name")?;
-            Box::new(RegexQuery::from_pattern(query_str, name_field)?)
-        } else {
-            // For text queries, use query parser with optimized settings
-            let name_field = schema.get_field("name")?;
-            let path_field = schema.get_field("path")?;
-            let mut query_parser =
-                QueryParser::for_index(&self.index, vec![name_field, path_field]);
-            // Boost name field matches (2x) over path matches for better relevance
-            query_parser.set_field_boost(name_field, 2.0);
-            query_parser.set_field_boost(path_field, 1.0);
-            Box::new(query_parser.parse_query(query_str)?)
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect()
         };
+        assert_eq!(names("opening night"), vec!["IMG_0412.jpg"]);
+        assert_eq!(names("cafe"), vec!["IMG_0412.jpg"], "Notes are folded");
+        assert_eq!(names("march OR team").len(), 2);
+        assert_eq!(names("paid pdf"), vec!["invoice.pdf"]);
+        assert!(names("night jpg").contains(&"IMG_0412.jpg".to_string()));
 
-        // Use TopDocs collector with limit for efficient result retrieval
-        let top_docs = searcher.search(&*query, &TopDocs::with_limit(limit.min(1000)))?;
+        let result = &index
+            .to_results(&index.search("march", false, 1).unwrap())
+            .unwrap()[0];
+        assert_eq!(result.attributes.note.as_deref(), Some("Paid in March"));
+    }
+
+    #[test]
+    fn test_search_stems_names_and_notes() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-        // Pre-allocate result vector with expected capacity
-        let mut results = Vec::with_capacity(top_docs.len());
-        for (_score, doc_address) in top_docs {
-            let retrieved_doc = searcher.doc(doc_address)?;
-            results.push(retrieved_doc);
+        let mut writer = index.writer().unwrap();
+        for (name, note) in [
+            ("Invoices 2023.pdf", None),
+            ("Rechnungen.pdf", None),
+            (
+                "scan_1.pdf",
+                Some("The invoices for the office rent were paid last week"),
+            ),
+            (
+                "scan_2.pdf",
+                Some("Die Rechnungen für die Miete wurden letzte Woche bezahlt"),
+            ),
+            ("scan_3.pdf", Some("지난주 사무실 임대료 영수증")),
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/files/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    note: note.map(str::to_string),
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
         }
+        writer.commit().unwrap();
 
-        Ok(results)
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            let mut names: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("invoice"), vec!["Invoices 2023.pdf", "scan_1.pdf"]);
+        assert_eq!(names("rechnung"), vec!["Rechnungen.pdf", "scan_2.pdf"]);
+        assert_eq!(names("renting"), vec!["scan_1.pdf"]);
+        assert_eq!(names("임대료"), vec!["scan_3.pdf"]);
     }
 
-    // Note: reload() is called internally in search() method
-    // This public method is kept for potential future use
-    #[allow(dead_code)]
-    pub fn reload(&self) -> Result<(), tantivy::TantivyError> {
-        self.reader.reload()
-    }
-}
+    #[test]
+    fn test_search_email_filters() {
+        use crate::email::EmailHeaders;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tantivy::schema::*;
-    use tempfile::tempdir;
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-    fn create_test_index(path: &std::path::Path) -> SearchIndex {
-        SearchIndex::new(path).expect("Failed to create test index")
+        let mut writer = index.writer().unwrap();
+        for (name, from, subject) in [
+            (
+                "march.eml",
+                "Zoë Park <zoe@billing.example.com>",
+                "March invoice",
+            ),
+            ("lunch.eml", "Bob <bob@example.com>", "Lunch on Friday?"),
+            ("invoice.pdf", "", ""),
+        ] {
+            let email = (!from.is_empty()).then(|| EmailHeaders {
+                from: Some(from.to_string()),
+                subject: Some(subject.to_string()),
+                date: Some(1640000000),
+            });
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/mail/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    email,
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            let mut names: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("from:zoe"), vec!["march.eml"], "Senders are folded");
+        assert_eq!(names("from:bob@example.com"), vec!["lunch.eml"]);
+        assert_eq!(names("from:example.com").len(), 2);
+        assert_eq!(names("subject:Invoice"), vec!["march.eml"]);
+        assert_eq!(names("march subject:march-invoice"), vec!["march.eml"]);
+        assert!(names("subject:invoice-march").is_empty());
+        assert!(names("from:alice").is_empty());
+        assert!(names("from:@").is_empty());
+
+        let result = &index
+            .to_results(&index.search("subject:lunch", false, 1).unwrap())
+            .unwrap()[0];
+        let email = result.attributes.email.as_ref().unwrap();
+        assert_eq!(email.subject.as_deref(), Some("Lunch on Friday?"));
+        assert_eq!(email.date, Some(1640000000));
+
+        let (rest, filter) = SearchFilter::extract("from:Bob subject:lunch notes").unwrap();
+        assert_eq!(rest, "notes");
+        assert_eq!(filter.from.as_deref(), Some("Bob"));
+        assert_eq!(filter.subject.as_deref(), Some("lunch"));
+        assert!(SearchFilter::extract("from:").is_err());
     }
 
-    fn populate_test_index(index: &SearchIndex) -> Result<(), tantivy::TantivyError> {
-        let schema = index.get_schema();
-        let name_field = schema.get_field("name")?;
-        let path_field = schema.get_field("path")?;
-        let size_field = schema.get_field("size")?;
-        let modified_field = schema.get_field("modified")?;
-        let is_folder_field = schema.get_field("is_folder")?;
+    #[test]
+    fn test_search_git_filters() {
+        use crate::git::GitStatus;
 
-        let mut writer = index.writer()?;
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-        let documents = vec![
+        let mut writer = index.writer().unwrap();
+        for (path, repo_root, git_status) in [
             (
-                "document.txt",
-                "/home/user/documents/document.txt",
-                1024,
-                1640000000,
-                false,
+                "/code/App/main.rs",
+                Some("/code/App"),
+                Some(GitStatus::Tracked),
             ),
             (
-                "report.pdf",
-                "/home/user/reports/report.pdf",
-                51200,
-                1640001000,
-                false,
+                "/code/App/lib.rs",
+                Some("/code/App"),
+                Some(GitStatus::Modified),
             ),
             (
-                "image.jpg",
-                "/home/user/images/image.jpg",
-                204800,
-                1640002000,
-                false,
+                "/code/App/target",
+                Some("/code/App"),
+                Some(GitStatus::Ignored),
             ),
-            ("folder", "/home/user/folder", 0, 1640003000, true),
             (
-                "notes.txt",
-                "/home/user/folder/notes.txt",
-                512,
-                1640004000,
-                false,
+                "/code/tool/main.rs",
+                Some("/code/tool"),
+                Some(GitStatus::Untracked),
             ),
-        ];
+            ("/notes/main.rs", None, None),
+        ] {
+            let entity = crate::FileEntity {
+                id: path.to_string(),
+                name: path.rsplit('/').next().unwrap().to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    repo_root: repo_root.map(str::to_string),
+                    git_status,
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
 
-        for (name, path, size, modified, is_folder) in documents {
-            let mut doc = tantivy::TantivyDocument::default();
-            doc.add_text(name_field, name);
-            doc.add_text(path_field, path);
-            doc.add_u64(size_field, size);
-            doc.add_date(
-                modified_field,
-                tantivy::DateTime::from_timestamp_secs(modified),
-            );
-            doc.add_bool(is_folder_field, is_folder);
-            writer.add_document(doc)?;
+        let paths = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            let mut paths: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(paths("git:modified"), vec!["/code/App/lib.rs"]);
+        assert_eq!(
+            paths("git:tracked"),
+            vec!["/code/App/lib.rs", "/code/App/main.rs"],
+            "Modified files are tracked too"
+        );
+        assert_eq!(paths("main repo:app"), vec!["/code/App/main.rs"]);
+        assert_eq!(paths("main git:untracked"), vec!["/code/tool/main.rs"]);
+        assert!(paths("repo:missing").is_empty());
+
+        let result = &index
+            .to_results(&index.search("lib", false, 1).unwrap())
+            .unwrap()[0];
+        assert_eq!(result.attributes.repo_root.as_deref(), Some("/code/App"));
+        assert_eq!(result.attributes.git_status, Some(GitStatus::Modified));
+    }
+
+    #[test]
+    fn test_projects_and_project_filter() {
+        use crate::project::ProjectMarker;
+
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
+
+        let mut writer = index.writer().unwrap();
+        for (path, is_folder, project_markers) in [
+            (
+                "/code/Site",
+                true,
+                vec![ProjectMarker::Npm, ProjectMarker::Git],
+            ),
+            ("/code/Site/index.js", false, vec![]),
+            ("/old/site", true, vec![ProjectMarker::Npm]),
+            ("/old/site/index.js", false, vec![]),
+            ("/code/engine", true, vec![ProjectMarker::Cargo]),
+            ("/code/engine/index.js", false, vec![]),
+            ("/code/index.js", false, vec![]),
+        ] {
+            let entity = crate::FileEntity {
+                id: path.to_string(),
+                name: path.rsplit('/').next().unwrap().to_string(),
+                path: path.to_string(),
+                size: 0,
+                modified: 1640000000,
+                is_folder,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    project_markers,
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
         }
+        writer.commit().unwrap();
 
-        writer.commit()?;
-        Ok(())
+        let projects = index.projects(&[]).unwrap();
+        let listed: Vec<(&str, &str)> = projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.path.as_str()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("Site", "/code/Site"),
+                ("engine", "/code/engine"),
+                ("site", "/old/site"),
+            ]
+        );
+        assert_eq!(
+            projects[0].markers,
+            vec![ProjectMarker::Npm, ProjectMarker::Git]
+        );
+
+        let paths = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            let mut paths: Vec<String> = index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(
+            paths("index project:site"),
+            vec!["/code/Site/index.js", "/old/site/index.js"],
+            "Same-named projects are searched together"
+        );
+        assert_eq!(paths("project:engine"), vec!["/code/engine/index.js"]);
+        assert!(paths("index project:missing").is_empty());
     }
 
     #[test]
-    fn test_search_index_new_creates_directory() {
+    fn test_search_attribute_filters() {
         let temp_dir = tempdir().unwrap();
-        let index_path = temp_dir.path().join("test_index");
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-        assert!(
-            !index_path.exists(),
-            "Index path should not exist initially"
+        let mut writer = index.writer().unwrap();
+        for (name, readonly, uid) in [
+            ("locked.cfg", true, 0),
+            ("open.cfg", false, 1000),
+            ("linked.cfg", false, 1000),
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/etc/{}", name),
+                size: 0,
+                modified: 1640000000,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    readonly,
+                    uid: Some(uid),
+                    gid: Some(uid),
+                    mode: Some(if readonly { 0o444 } else { 0o644 }),
+                    is_symlink: name == "linked.cfg",
+                    link_target: (name == "linked.cfg").then(|| "/etc/open.cfg".to_string()),
+                    online_only: name == "open.cfg",
+                    owner: (name == "linked.cfg").then(|| "Alice".to_string()),
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let names = |query: &str| -> Vec<String> {
+            let docs = index.search(query, false, 10).unwrap();
+            index
+                .to_results(&docs)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.name)
+                .collect()
+        };
+        assert_eq!(names("cfg attrib:readonly"), vec!["locked.cfg"]);
+        let mut writable = names("attrib:writable");
+        writable.sort();
+        assert_eq!(
+            writable,
+            vec!["linked.cfg", "open.cfg"],
+            "Filters alone match all"
         );
+        assert_eq!(names("type:link"), vec!["linked.cfg"]);
+        assert_eq!(names("open owner:1000"), vec!["open.cfg"]);
+        assert_eq!(names("mode:444"), vec!["locked.cfg"]);
+        assert!(names("owner:42").is_empty());
+        assert_eq!(names("owner:alice"), vec!["linked.cfg"]);
+        assert!(names("owner:nobody-here").is_empty());
+        #[cfg(unix)]
+        assert_eq!(names("owner:root"), vec!["locked.cfg"]);
+        assert_eq!(names("cfg attrib:online-only"), vec!["open.cfg"]);
+        assert_eq!(names("cfg attrib:local").len(), 2);
 
-        let index = SearchIndex::new(&index_path);
-        assert!(index.is_ok(), "SearchIndex::new should succeed");
+        let docs = index.search("locked", false, 1).unwrap();
+        let result = &index.to_results(&docs).unwrap()[0];
+        assert!(result.attributes.readonly);
+        assert_eq!(result.attributes.mode, Some(0o444));
 
-        assert!(index_path.exists(), "Index directory should be created");
+        let docs = index.search("type:link", false, 1).unwrap();
+        let link = &index.to_results(&docs).unwrap()[0];
+        assert!(link.attributes.is_symlink);
+        assert_eq!(
+            link.attributes.link_target.as_deref(),
+            Some("/etc/open.cfg")
+        );
     }
 
     #[test]
-    fn test_search_index_new_opens_existing_index() {
+    fn test_link_paths() {
         let temp_dir = tempdir().unwrap();
-        let index_path = temp_dir.path().join("test_index");
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-        let index1 = create_test_index(&index_path);
-        populate_test_index(&index1).unwrap();
+        let mut writer = index.writer().unwrap();
+        for (name, is_symlink, broken_link) in [
+            ("plain.txt", false, false),
+            ("linked", true, false),
+            ("dangling", false, true),
+            ("App.LNK", false, false),
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/links/{}", name),
+                size: 0,
+                modified: 0,
+                is_folder: false,
+                hidden: false,
+                attributes: crate::FileAttributes {
+                    is_symlink,
+                    broken_link,
+                    ..Default::default()
+                },
+                created: None,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
 
-        let index2 = SearchIndex::new(&index_path);
-        assert!(index2.is_ok(), "Should be able to open existing index");
+        assert_eq!(
+            index.link_paths(false, &[]).unwrap(),
+            vec!["/links/dangling", "/links/linked"]
+        );
+        assert_eq!(
+            index.link_paths(true, &[]).unwrap(),
+            vec!["/links/App.LNK", "/links/dangling", "/links/linked"]
+        );
     }
 
     #[test]
-    fn test_get_schema() {
-        let temp_dir = tempdir().unwrap();
-        let index_path = temp_dir.path().join("test_index");
-        let index = create_test_index(&index_path);
+    fn test_date_range_keywords() {
+        use chrono::Utc;
+        // Wednesday 2024-03-13 15:30 UTC
+        let now = Utc.with_ymd_and_hms(2024, 3, 13, 15, 30, 0).unwrap();
+        let ts = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().timestamp();
 
-        let schema = index.get_schema();
-        assert!(schema.get_field("name").is_ok(), "Should have name field");
-        assert!(schema.get_field("path").is_ok(), "Should have path field");
-        assert!(schema.get_field("size").is_ok(), "Should have size field");
-        assert!(
-            schema.get_field("modified").is_ok(),
-            "Should have modified field"
+        assert_eq!(
+            date_range("today", &now),
+            Some((ts(2024, 3, 13), ts(2024, 3, 14)))
         );
-        assert!(
-            schema.get_field("is_folder").is_ok(),
-            "Should have is_folder field"
+        assert_eq!(
+            date_range("yesterday", &now),
+            Some((ts(2024, 3, 12), ts(2024, 3, 13)))
+        );
+        assert_eq!(
+            date_range("thisweek", &now),
+            Some((ts(2024, 3, 11), ts(2024, 3, 18)))
+        );
+        assert_eq!(
+            date_range("lastweek", &now),
+            Some((ts(2024, 3, 4), ts(2024, 3, 11)))
+        );
+        assert_eq!(
+            date_range("thismonth", &now),
+            Some((ts(2024, 3, 1), ts(2024, 4, 1)))
+        );
+        assert_eq!(
+            date_range("thisyear", &now),
+            Some((ts(2024, 1, 1), ts(2025, 1, 1)))
+        );
+        assert_eq!(
+            date_range("2023-12-31..2024-01-02", &now),
+            Some((ts(2023, 12, 31), ts(2024, 1, 3)))
         );
+        assert_eq!(date_range("2024-01-02..2023-12-31", &now), None);
+        assert_eq!(date_range("someday", &now), None);
     }
 
     #[test]
-    fn test_writer() {
+    fn test_search_created_date_filter() {
         let temp_dir = tempdir().unwrap();
-        let index_path = temp_dir.path().join("test_index");
-        let index = create_test_index(&index_path);
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let fields = index.fields().unwrap();
 
-        let writer = index.writer();
-        assert!(writer.is_ok(), "Should be able to create writer");
+        let mut writer = index.writer().unwrap();
+        for (name, created) in [
+            ("new.log", Some(1_710_000_000)),
+            ("old.log", Some(1_600_000_000)),
+            ("unknown.log", None),
+        ] {
+            let entity = crate::FileEntity {
+                id: name.to_string(),
+                name: name.to_string(),
+                path: format!("/var/{}", name),
+                size: 0,
+                modified: 1_710_000_000,
+                is_folder: false,
+                hidden: false,
+                attributes: Default::default(),
+                created,
+                accessed: None,
+            };
+            writer.add_document(fields.build_document(&entity)).unwrap();
+        }
+        writer.commit().unwrap();
 
-        let writer = writer.unwrap();
-        let schema = index.get_schema();
-        let name_field = schema.get_field("name").unwrap();
-        let path_field = schema.get_field("path").unwrap();
-        let size_field = schema.get_field("size").unwrap();
-        let modified_field = schema.get_field("modified").unwrap();
-        let is_folder_field = schema.get_field("is_folder").unwrap();
+        // 1_710_000_000 is 2024-03-09T16:00:00Z
+        let found = index
+            .search("log dc:2024-03-01..2024-03-31", false, 10)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        let result = &index.to_results(&found).unwrap()[0];
+        assert_eq!(result.name, "new.log");
+        assert_eq!(result.created.as_deref(), Some("2024-03-09T16:00:00Z"));
+        assert_eq!(result.accessed, None);
 
-        let mut doc = tantivy::TantivyDocument::default();
-        doc.add_text(name_field, "test.txt");
-        doc.add_text(path_field, "/test.txt");
-        doc.add_u64(size_field, 100);
-        doc.add_date(modified_field, tantivy::DateTime::from_timestamp_secs(1000));
-        doc.add_bool(is_folder_field, false);
+        assert_eq!(index.search("dm:2024-03-09", false, 10).unwrap().len(), 3);
+        assert!(index.search("log dc:never", false, 10).is_err());
+    }
 
-        let result = writer.add_document(doc);
-        assert!(result.is_ok(), "Should be able to add document");
+    #[test]
+    fn test_apply_changes_upserts_and_removes() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let mut entity = crate::FileEntity {
+            id: crate::index::path_id("/tmp/draft.md"),
+            name: "draft.md".to_string(),
+            path: "/tmp/draft.md".to_string(),
+            size: 1,
+            modified: 1640000000,
+            is_folder: false,
+            hidden: false,
+            attributes: Default::default(),
+            created: None,
+            accessed: None,
+        };
+
+        index.apply_changes(&[], &[entity.clone()]).unwrap();
+        entity.size = 2;
+        index.apply_changes(&[], &[entity.clone()]).unwrap();
+        let found = index.search("draft", false, 10).unwrap();
+        assert_eq!(found.len(), 1, "Upsert replaces the previous document");
+        assert_eq!(index.to_results(&found).unwrap()[0].size, 2);
+
+        // A rename removes the old document and adds the new one together
+        let mut renamed = entity.clone();
+        renamed.path = "/tmp/final.md".to_string();
+        renamed.name = "final.md".to_string();
+        renamed.id = crate::index::path_id(&renamed.path);
+        index
+            .apply_changes(
+                std::slice::from_ref(&entity.id),
+                std::slice::from_ref(&renamed),
+            )
+            .unwrap();
+        assert!(index.search("draft", false, 10).unwrap().is_empty());
+        assert_eq!(index.search("final", false, 10).unwrap().len(), 1);
+
+        index.apply_changes(&[renamed.id], &[]).unwrap();
+        assert!(index.search("final", false, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_with_file_id_finds_every_link() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let entities: Vec<crate::FileEntity> =
+            [("a.txt", "1:10"), ("b.txt", "1:10"), ("c.txt", "1:11")]
+                .into_iter()
+                .map(|(name, file_id)| crate::FileEntity {
+                    id: name.to_string(),
+                    name: name.to_string(),
+                    path: format!("/links/{}", name),
+                    size: 0,
+                    modified: 1640000000,
+                    is_folder: false,
+                    hidden: false,
+                    attributes: crate::FileAttributes {
+                        file_id: Some(file_id.to_string()),
+                        ..Default::default()
+                    },
+                    created: None,
+                    accessed: None,
+                })
+                .collect();
+        index.apply_changes(&[], &entities).unwrap();
+
+        let fields = index.fields().unwrap();
+        let mut linked: Vec<String> = index
+            .with_file_id("1:10")
+            .unwrap()
+            .iter()
+            .map(|doc| fields.read_entity(doc).path)
+            .collect();
+        linked.sort();
+        assert_eq!(linked, vec!["/links/a.txt", "/links/b.txt"]);
+        let entity = fields.read_entity(&index.with_file_id("1:11").unwrap()[0]);
+        assert_eq!(entity.attributes.file_id.as_deref(), Some("1:11"));
+        assert!(index.with_file_id("2:10").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_rejects_outdated_schema() {
+        let temp_dir = tempdir().unwrap();
+        let index_path = temp_dir.path().join("old_index");
+        std::fs::create_dir_all(&index_path).unwrap();
+
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("name", TEXT | STORED);
+        Index::create_in_dir(&index_path, schema_builder.build()).unwrap();
+
+        assert_eq!(SearchIndex::schema_version(&index_path), None);
+        assert!(SearchIndex::new(&index_path).is_err());
+
+        // A current schema with an older version marker is outdated too
+        let current_path = temp_dir.path().join("current_index");
+        drop(SearchIndex::new(&current_path).unwrap());
+        assert_eq!(
+            SearchIndex::schema_version(&current_path),
+            Some(SCHEMA_VERSION)
+        );
+        std::fs::write(current_path.join(SCHEMA_VERSION_FILE), "0").unwrap();
+        assert!(SearchIndex::new(&current_path).is_err());
     }
 
     #[test]
@@ -463,6 +5102,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cancelled_search_fails() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        populate_test_index(&index).unwrap();
+
+        let cancel = CancelToken::default();
+        let search = |cancel: &CancelToken| {
+            index.search_filtered("txt", false, 10, &SearchFilter::default(), cancel)
+        };
+        assert!(!search(&cancel).unwrap().is_empty());
+        cancel.cancel();
+        assert!(search(&cancel).is_err());
+        assert!(search(&CancelToken::with_timeout(std::time::Duration::ZERO)).is_err());
+    }
+
+    #[test]
+    fn test_search_chunks_streams_past_ranked_limit() {
+        let temp_dir = tempdir().unwrap();
+        let index = create_test_index(&temp_dir.path().join("test_index"));
+        let name_field = index.get_schema().get_field("name").unwrap();
+        let mut writer = index.writer().unwrap();
+        for i in 0..1205 {
+            let mut doc = tantivy::TantivyDocument::default();
+            doc.add_text(name_field, format!("file{}.txt", i));
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let chunks = |limit: Option<usize>| {
+            let mut sizes = Vec::new();
+            let handed = index
+                .search_chunks(
+                    "txt",
+                    false,
+                    &SearchFilter::default(),
+                    (limit, 500),
+                    &CancelToken::default(),
+                    |docs| sizes.push(docs.len()),
+                )
+                .unwrap();
+            assert_eq!(handed, sizes.iter().sum::<usize>());
+            sizes
+        };
+        let sizes = chunks(None);
+        assert_eq!(sizes.iter().sum::<usize>(), 1205);
+        assert!(sizes.iter().all(|size| *size <= 500));
+        assert_eq!(chunks(Some(700)).iter().sum::<usize>(), 700);
+
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        assert!(index
+            .search_chunks(
+                "txt",
+                false,
+                &SearchFilter::default(),
+                (None, 500),
+                &cancel,
+                |_| {}
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let temp_dir = tempdir().unwrap();
+        let index = std::sync::Arc::new(create_test_index(&temp_dir.path().join("test_index")));
+        populate_test_index(&index).unwrap();
+        index.reload().unwrap();
+
+        let stats = SearchIndex::warm_up(&std::sync::Arc::downgrade(&index))
+            .unwrap()
+            .expect("The index is still open");
+        assert_eq!(stats.segments, 1);
+        assert!(stats.terms > 0 && stats.postings >= stats.terms);
+        assert_eq!(stats.fast_values, 10, "Size and modified of five files");
+
+        let closed = std::sync::Arc::downgrade(&index);
+        drop(index);
+        assert_eq!(SearchIndex::warm_up(&closed).unwrap(), None);
+    }
+
     #[test]
     fn test_search_with_folders() {
         let temp_dir = tempdir().unwrap();
@@ -489,7 +5210,7 @@ mod tests {
         writer.commit().unwrap();
 
         let results = index.search("folder", false, 10).unwrap();
-        assert!(results.len() >= 1, "Should find folder");
+        assert!(!results.is_empty(), "Should find folder");
 
         let doc = &results[0];
         let is_folder = doc
@@ -516,4 +5237,20 @@ mod tests {
         let results = index2.search("document", false, 10).unwrap();
         assert_eq!(results.len(), 1, "Should find document in reopened index");
     }
+
+    #[test]
+    fn test_writer_options_validation() {
+        assert!(WriterOptions::default().validate().is_ok());
+        let options = |heap_mb, threads| WriterOptions { heap_mb, threads };
+        assert!(options(14, 0).validate().is_err());
+        assert!(options(60, 4).validate().is_ok());
+        assert!(options(59, 4).validate().is_err());
+        assert!(options(8000, 1).validate().is_err());
+        assert!(options(8000, 2).validate().is_ok());
+        assert!(options(1000, 33).validate().is_err());
+
+        // Missing fields fall back to the defaults
+        let parsed: WriterOptions = serde_json::from_str(r#"{"threads": 2}"#).unwrap();
+        assert_eq!(parsed, options(50, 2));
+    }
 }
@@ -0,0 +1,119 @@
+// Cooperative cancellation of running searches, by request or deadline
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::{query::Weight, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Shared between a search and whoever may abandon it. Clones observe the
+/// same cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    /// A token that also expires `timeout` from now
+    pub fn with_timeout(timeout: Duration) -> Self {
+        CancelToken {
+            cancelled: Arc::default(),
+            deadline: Instant::now().checked_add(timeout),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Whether `other` is a clone of this token
+    pub fn is_same(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Error out once the search should stop
+    pub fn check(&self) -> tantivy::Result<()> {
+        if self.is_cancelled() {
+            Err(TantivyError::InternalError("Search cancelled".to_string()))
+        } else if self.is_expired() {
+            Err(TantivyError::InternalError("Search timed out".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Wraps a collector so the search stops between segments once `cancel`
+/// fires. Segments are handed to the inner collector whole, keeping its
+/// optimizations such as TopDocs pruning.
+pub struct Cancellable<C> {
+    pub inner: C,
+    pub cancel: CancelToken,
+}
+
+impl<C: Collector> Collector for Cancellable<C> {
+    type Fruit = C::Fruit;
+    type Child = C::Child;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        self.cancel.check()?;
+        self.inner.for_segment(segment_local_id, segment)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        // A segment may have run past the deadline; its hits are not returned
+        self.cancel.check()?;
+        self.inner.merge_fruits(segment_fruits)
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<<Self::Child as SegmentCollector>::Fruit> {
+        self.cancel.check()?;
+        self.inner.collect_segment(weight, segment_ord, reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::default();
+        assert!(token.check().is_ok());
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(token.check().is_err());
+
+        let expired = CancelToken::with_timeout(Duration::ZERO);
+        assert!(expired.is_expired() && !expired.is_cancelled());
+        assert!(CancelToken::with_timeout(Duration::from_secs(60))
+            .check()
+            .is_ok());
+    }
+}
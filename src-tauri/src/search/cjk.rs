@@ -0,0 +1,176 @@
+// Bigram tokenization for CJK file names
+
+use tantivy::tokenizer::{Token, TokenStream, Tokenizer};
+
+/// Name under which the name tokenizer is registered with each index
+pub const TOKENIZER_NAME: &str = "cjk_bigram";
+/// Name under which the initials tokenizer is registered with each index
+pub const INITIALS_TOKENIZER_NAME: &str = "initials_bigram";
+
+/// Chinese, Japanese and Korean scripts, which do not separate words with spaces
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}'     // Hangul Jamo
+        | '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3130}'..='\u{318F}'   // Hangul Compatibility Jamo
+        | '\u{31F0}'..='\u{31FF}'   // Katakana Phonetic Extensions
+        | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}'   // Halfwidth Katakana
+        | '\u{20000}'..='\u{2FFFF}' // CJK Extensions B and later
+    )
+}
+
+/// Splits names into lowercased words, except that runs of CJK characters
+/// become overlapping bigrams, so a name like "여행사진" is found by any
+/// two-character fragment of it. A lone CJK character is kept as is.
+#[derive(Clone)]
+pub struct CjkBigramTokenizer {
+    /// Characters whose runs are split into bigrams
+    grams: fn(char) -> bool,
+}
+
+impl CjkBigramTokenizer {
+    pub fn names() -> Self {
+        CjkBigramTokenizer { grams: is_cjk }
+    }
+
+    /// Bigrams of every word, for transliterated initials like "wdzp"
+    pub fn initials() -> Self {
+        CjkBigramTokenizer {
+            grams: char::is_alphanumeric,
+        }
+    }
+}
+
+impl Tokenizer for CjkBigramTokenizer {
+    type TokenStream<'a> = CjkBigramTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        CjkBigramTokenStream {
+            tokens: tokenize(text, self.grams).into_iter(),
+            token: Token::default(),
+        }
+    }
+}
+
+pub struct CjkBigramTokenStream {
+    tokens: std::vec::IntoIter<Token>,
+    token: Token,
+}
+
+impl TokenStream for CjkBigramTokenStream {
+    fn advance(&mut self) -> bool {
+        match self.tokens.next() {
+            Some(token) => {
+                self.token = token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+fn tokenize(text: &str, grams: fn(char) -> bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut push = |offset_from: usize, offset_to: usize, text: String| {
+        tokens.push(Token {
+            offset_from,
+            offset_to,
+            position: tokens.len(),
+            text,
+            position_length: 1,
+        })
+    };
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let end_of = |i: usize| chars.get(i).map_or(text.len(), |(offset, _)| *offset);
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        if grams(c) {
+            let run = chars[i..].iter().take_while(|(_, c)| grams(*c)).count();
+            if run == 1 {
+                push(start, end_of(i + 1), c.to_lowercase().collect());
+            }
+            for j in i..i + run.saturating_sub(1) {
+                push(
+                    chars[j].0,
+                    end_of(j + 2),
+                    text[chars[j].0..end_of(j + 2)].to_lowercase(),
+                );
+            }
+            i += run;
+        } else if c.is_alphanumeric() {
+            let run = chars[i..]
+                .iter()
+                .take_while(|(_, c)| c.is_alphanumeric() && !grams(*c))
+                .count();
+            push(
+                start,
+                end_of(i + run),
+                text[start..end_of(i + run)].to_lowercase(),
+            );
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str) -> Vec<String> {
+        let mut tokenizer = CjkBigramTokenizer::names();
+        let mut stream = tokenizer.token_stream(text);
+        let mut texts = Vec::new();
+        while stream.advance() {
+            texts.push(stream.token().text.clone());
+        }
+        texts
+    }
+
+    #[test]
+    fn test_cjk_runs_become_bigrams() {
+        assert_eq!(texts("여행사진"), vec!["여행", "행사", "사진"]);
+        assert_eq!(
+            texts("IMG_東京タワー2024.JPG"),
+            vec!["img", "東京", "京タ", "タワ", "ワー", "2024", "jpg"]
+        );
+        assert_eq!(texts("第1章"), vec!["第", "1", "章"]);
+        assert!(texts("--").is_empty());
+
+        let mut tokenizer = CjkBigramTokenizer::initials();
+        let mut stream = tokenizer.token_stream("wdzp x");
+        let mut grams = Vec::new();
+        while stream.advance() {
+            grams.push(stream.token().text.clone());
+        }
+        assert_eq!(grams, vec!["wd", "dz", "zp", "x"]);
+    }
+
+    #[test]
+    fn test_offsets_and_positions() {
+        let mut tokenizer = CjkBigramTokenizer::names();
+        let mut stream = tokenizer.token_stream("a 사진첩");
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            let token = stream.token();
+            tokens.push((token.offset_from, token.offset_to, token.position));
+        }
+        assert_eq!(tokens, vec![(0, 1, 0), (2, 8, 1), (5, 11, 2)]);
+    }
+}
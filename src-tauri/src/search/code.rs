@@ -0,0 +1,135 @@
+// Identifier-aware tokenization, so the words inside camelCase, snake_case and
+// kebab-case names are terms of their own
+
+use tantivy::tokenizer::{Token, TokenStream, Tokenizer};
+
+/// Name under which the code word tokenizer is registered with each index
+pub const TOKENIZER_NAME: &str = "code_words";
+
+/// Splits names into alphanumeric runs like the default tokenizer, then each
+/// run where its case changes, emitting the lowercased parts. "MyHTTPServer.rs"
+/// becomes "my", "http", "server", "rs" and "parse_v2Args" becomes "parse",
+/// "v2", "args".
+#[derive(Clone, Default)]
+pub struct CodeWordTokenizer;
+
+impl Tokenizer for CodeWordTokenizer {
+    type TokenStream<'a> = CodeWordTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        let tokens: Vec<Token> = word_spans(text)
+            .into_iter()
+            .enumerate()
+            .map(|(position, (from, to))| Token {
+                offset_from: from,
+                offset_to: to,
+                position,
+                text: text[from..to].to_lowercase(),
+                position_length: 1,
+            })
+            .collect();
+        CodeWordTokenStream {
+            tokens: tokens.into_iter(),
+            token: Token::default(),
+        }
+    }
+}
+
+pub struct CodeWordTokenStream {
+    tokens: std::vec::IntoIter<Token>,
+    token: Token,
+}
+
+impl TokenStream for CodeWordTokenStream {
+    fn advance(&mut self) -> bool {
+        match self.tokens.next() {
+            Some(token) => {
+                self.token = token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+/// Byte ranges of the words in `text`. A word ends before an uppercase letter
+/// following a lowercase letter or digit, and before the last capital of an
+/// acronym followed by lowercase, as in "HTTP|Server".
+pub fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut start: Option<usize> = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if let Some(from) = start.take() {
+                spans.push((from, at));
+            }
+            continue;
+        }
+        let Some(from) = start else {
+            start = Some(at);
+            continue;
+        };
+        let prev = chars[i - 1].1;
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let boundary = c.is_uppercase()
+            && (prev.is_lowercase()
+                || prev.is_numeric()
+                || (prev.is_uppercase() && next.is_some_and(char::is_lowercase)));
+        if boundary {
+            spans.push((from, at));
+            start = Some(at);
+        }
+    }
+    if let Some(from) = start {
+        spans.push((from, text.len()));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str) -> Vec<String> {
+        let mut tokenizer = CodeWordTokenizer;
+        let mut stream = tokenizer.token_stream(text);
+        let mut texts = Vec::new();
+        while stream.advance() {
+            texts.push(stream.token().text.clone());
+        }
+        texts
+    }
+
+    #[test]
+    fn test_code_words() {
+        assert_eq!(texts("MyHttpServer.rs"), vec!["my", "http", "server", "rs"]);
+        assert_eq!(texts("MyHTTPServer"), vec!["my", "http", "server"]);
+        assert_eq!(
+            texts("search_index-v2.ts"),
+            vec!["search", "index", "v2", "ts"]
+        );
+        assert_eq!(texts("parse_v2Args"), vec!["parse", "v2", "args"]);
+        assert_eq!(texts("README.md"), vec!["readme", "md"]);
+        assert_eq!(texts("ÉcoleÉlève"), vec!["école", "élève"]);
+        assert!(texts("--").is_empty());
+    }
+
+    #[test]
+    fn test_word_spans_cover_source_text() {
+        let text = "getURLForID";
+        let words: Vec<&str> = word_spans(text)
+            .into_iter()
+            .map(|(from, to)| &text[from..to])
+            .collect();
+        assert_eq!(words, vec!["get", "URL", "For", "ID"]);
+    }
+}
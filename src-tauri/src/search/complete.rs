@@ -0,0 +1,129 @@
+// Completion of the word being typed and of folder paths, from FSTs built
+// over the whole index so one prefix lookup covers every segment
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use tantivy::schema::Field;
+use tantivy::Searcher;
+use tantivy_fst::{IntoStreamer, Map, Streamer};
+
+/// Indexed name words and folders as of one commit
+pub struct CompletionIndex {
+    /// Stamp of the commit the FSTs were built from
+    pub opstamp: u64,
+    /// Lowercased, folded name words to the number of entries with them
+    words: Map<Vec<u8>>,
+    /// Keys of folder names, each followed by NUL and the key of the full
+    /// path, to the folder's position in `folders`
+    folder_names: Map<Vec<u8>>,
+    /// Keys of folder paths to the folder's position in `folders`
+    folder_paths: Map<Vec<u8>>,
+    folders: Vec<String>,
+}
+
+/// How names and paths are compared with what is typed
+fn key(text: &str) -> String {
+    super::fold(text).to_lowercase()
+}
+
+fn fst_error(e: tantivy_fst::Error) -> tantivy::TantivyError {
+    tantivy::TantivyError::InternalError(format!("Building completions failed: {}", e))
+}
+
+/// Values of the keys of `map` starting with `prefix`, in key order
+fn with_prefix(map: &Map<Vec<u8>>, prefix: &str) -> Vec<(Vec<u8>, u64)> {
+    let mut stream = map.range().ge(prefix).into_stream();
+    let mut found = Vec::new();
+    while let Some((key, value)) = stream.next() {
+        if !key.starts_with(prefix.as_bytes()) {
+            break;
+        }
+        found.push((key.to_vec(), value));
+    }
+    found
+}
+
+impl CompletionIndex {
+    /// Collect the terms of `word_field` from every segment of `searcher`,
+    /// and index `folders` by name and by path
+    pub fn build(
+        searcher: &Searcher,
+        word_field: Field,
+        folders: Vec<String>,
+        opstamp: u64,
+    ) -> tantivy::Result<CompletionIndex> {
+        let mut doc_freqs: BTreeMap<Vec<u8>, u64> = BTreeMap::new();
+        for segment in searcher.segment_readers() {
+            let inverted_index = segment.inverted_index(word_field)?;
+            let mut terms = inverted_index.terms().stream()?;
+            while terms.advance() {
+                *doc_freqs.entry(terms.key().to_vec()).or_default() +=
+                    terms.value().doc_freq as u64;
+            }
+        }
+
+        let mut names = Vec::with_capacity(folders.len());
+        let mut paths = Vec::with_capacity(folders.len());
+        for (position, folder) in folders.iter().enumerate() {
+            let path_key = key(folder);
+            let name = Path::new(folder)
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            names.push((format!("{}\0{}", key(&name), path_key), position as u64));
+            paths.push((path_key, position as u64));
+        }
+        // Keys must be unique and sorted; folders differing only in case share one
+        for keys in [&mut names, &mut paths] {
+            keys.sort();
+            keys.dedup_by(|a, b| a.0 == b.0);
+        }
+
+        Ok(CompletionIndex {
+            opstamp,
+            words: Map::from_iter(doc_freqs).map_err(fst_error)?,
+            folder_names: Map::from_iter(names).map_err(fst_error)?,
+            folder_paths: Map::from_iter(paths).map_err(fst_error)?,
+            folders,
+        })
+    }
+
+    /// Up to `limit` name words starting with `prefix`, those found in the
+    /// most entries first
+    pub fn words(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let prefix = key(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let mut words = with_prefix(&self.words, &prefix);
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        words
+            .into_iter()
+            .take(limit)
+            .filter_map(|(word, _)| String::from_utf8(word).ok())
+            .collect()
+    }
+
+    /// Up to `limit` folders whose path starts with `prefix` when it holds a
+    /// path separator, or whose name does otherwise, shallowest first
+    pub fn folders(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let prefix_key = key(prefix);
+        if prefix_key.is_empty() {
+            return Vec::new();
+        }
+        let map = if prefix.contains(['/', '\\']) {
+            &self.folder_paths
+        } else {
+            &self.folder_names
+        };
+        let mut folders: Vec<&String> = with_prefix(map, &prefix_key)
+            .into_iter()
+            .filter_map(|(_, position)| self.folders.get(position as usize))
+            .collect();
+        folders.sort_by(|a, b| {
+            let depth = |path: &str| Path::new(path).components().count();
+            depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
+        });
+        folders.into_iter().take(limit).cloned().collect()
+    }
+}
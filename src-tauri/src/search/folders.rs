@@ -0,0 +1,80 @@
+// Hit counts per containing folder, for the group by folder result mode
+
+use std::collections::HashMap;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::fastfield::FacetReader;
+use tantivy::schema::Facet;
+use tantivy::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// Hits of one folder and the address of one of them, from which the
+/// folder's path is read
+pub type FolderHits = (u64, DocAddress);
+
+/// Counts hits by the folder directly containing them, keyed by the encoded
+/// `dir` facet. Entries without a parent, such as filesystem roots, are left
+/// out.
+pub struct FolderCollector;
+
+pub struct FolderSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    dirs: FacetReader,
+    counts: HashMap<u64, (u64, DocId)>,
+}
+
+impl Collector for FolderCollector {
+    type Fruit = HashMap<String, FolderHits>;
+    type Child = FolderSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(FolderSegmentCollector {
+            segment_ord: segment_local_id,
+            dirs: segment.facet_reader("dir")?,
+            counts: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<HashMap<String, FolderHits>>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut merged: HashMap<String, FolderHits> = HashMap::new();
+        for fruit in segment_fruits {
+            for (dir, (count, sample)) in fruit {
+                merged.entry(dir).or_insert((0, sample)).0 += count;
+            }
+        }
+        Ok(merged)
+    }
+}
+
+impl SegmentCollector for FolderSegmentCollector {
+    type Fruit = HashMap<String, FolderHits>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if let Some(ord) = self.dirs.facet_ords(doc).next() {
+            self.counts.entry(ord).or_insert((0, doc)).0 += 1;
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut facet = Facet::root();
+        self.counts
+            .into_iter()
+            .filter_map(|(ord, (count, doc))| {
+                self.dirs.facet_from_ord(ord, &mut facet).ok()?;
+                Some((
+                    facet.encoded_str().to_string(),
+                    (count, DocAddress::new(self.segment_ord, doc)),
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,123 @@
+// Wildcard words such as `*.log` or `*report*`, matched against whole names
+// by walking the name_raw dictionary with an automaton rather than parsed
+// as words
+
+use tantivy::tokenizer::{LowerCaser, RawTokenizer, TextAnalyzer};
+
+/// Name under which the whole-name tokenizer is registered with each index
+pub const TOKENIZER_NAME: &str = "name_raw";
+/// Least characters besides wildcards a pattern needs. A shorter one would
+/// walk most of the name dictionary and match a good part of the index.
+pub const MIN_LITERAL_CHARS: usize = 2;
+
+/// One term per name, lowercased, for wildcard patterns to match whole
+pub fn analyzer() -> TextAnalyzer {
+    TextAnalyzer::builder(RawTokenizer::default())
+        .filter(LowerCaser)
+        .build()
+}
+
+/// Whether a query word is a wildcard pattern; filters are not
+fn is_glob(word: &str) -> bool {
+    word.contains(['*', '?']) && !word.contains(':')
+}
+
+/// A wildcard word of a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob {
+    pub pattern: String,
+    /// Written with a leading `-`, to leave matching names out
+    pub exclude: bool,
+}
+
+/// Split the wildcard words out of `query_str`, returning the other words
+/// and the patterns
+pub fn split(query_str: &str) -> (String, Vec<Glob>) {
+    let mut rest = Vec::new();
+    let mut globs = Vec::new();
+    for word in query_str.split_whitespace() {
+        if !is_glob(word) {
+            rest.push(word);
+        } else if let Some(pattern) = word.strip_prefix('-') {
+            globs.push(Glob {
+                pattern: pattern.to_string(),
+                exclude: true,
+            });
+        } else {
+            globs.push(Glob {
+                pattern: word.to_string(),
+                exclude: false,
+            });
+        }
+    }
+    (rest.join(" "), globs)
+}
+
+/// Regex matching the whole lowercased, folded names `pattern` matches: `*`
+/// stands for any run of characters and `?` for one. Fails for patterns
+/// with fewer than `MIN_LITERAL_CHARS` other characters.
+pub fn to_regex(pattern: &str) -> Result<String, String> {
+    let pattern = super::fold(pattern).to_lowercase();
+    let literal = pattern.chars().filter(|c| !matches!(c, '*' | '?')).count();
+    if literal < MIN_LITERAL_CHARS {
+        return Err(format!(
+            "Wildcard pattern {:?} needs at least {} characters besides * and ?",
+            pattern, MIN_LITERAL_CHARS
+        ));
+    }
+    let mut regex = String::with_capacity(pattern.len() * 2);
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                // Runs of stars are one star, and a smaller automaton
+                while chars.peek() == Some(&'*') {
+                    chars.next();
+                }
+                regex.push_str(".*");
+            }
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let (rest, globs) = split("error *.log -*.tmp type:link what?");
+        assert_eq!(rest, "error type:link");
+        assert_eq!(
+            globs,
+            vec![
+                Glob {
+                    pattern: "*.log".to_string(),
+                    exclude: false
+                },
+                Glob {
+                    pattern: "*.tmp".to_string(),
+                    exclude: true
+                },
+                Glob {
+                    pattern: "what?".to_string(),
+                    exclude: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_regex() {
+        assert_eq!(to_regex("*.LOG").unwrap(), r".*\.log");
+        assert_eq!(to_regex("**report*").unwrap(), ".*report.*");
+        assert_eq!(to_regex("IMG_????.jpg").unwrap(), r"img_....\.jpg");
+        assert_eq!(to_regex("café*").unwrap(), "cafe.*");
+        assert_eq!(to_regex("a+(1)*").unwrap(), r"a\+\(1\).*");
+        assert!(to_regex("*").is_err());
+        assert!(to_regex("*a?*").is_err());
+    }
+}
@@ -0,0 +1,138 @@
+// Languages of names and notes: stemming analyzers for the languages that
+// have one, so "invoices" finds "invoice" and "Rechnungen" finds "Rechnung",
+// and detection of the language a note is written in
+
+use tantivy::tokenizer::{LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    English,
+    German,
+    Korean,
+}
+
+/// Languages with a stemmer. Names are too short to tell their language, so
+/// they are indexed in each of these.
+pub const STEMMED: [Language; 2] = [Language::English, Language::German];
+
+impl Language {
+    /// Every language notes get a field of their own for
+    pub const ALL: [Language; 3] = [Language::English, Language::German, Language::Korean];
+
+    /// Tokenizer of the language's fields. Korean has no stemmer; its notes
+    /// are split into bigrams like CJK names, so a word is found whatever
+    /// particle follows it.
+    pub fn tokenizer_name(self) -> &'static str {
+        match self {
+            Language::English => "stem_en",
+            Language::German => "stem_de",
+            Language::Korean => super::cjk::TOKENIZER_NAME,
+        }
+    }
+
+    /// Analyzer to register under `tokenizer_name`; None for Korean, whose
+    /// tokenizer is registered with the other CJK ones
+    pub fn analyzer(self) -> Option<TextAnalyzer> {
+        let stemmer = match self {
+            Language::English => tantivy::tokenizer::Language::English,
+            Language::German => tantivy::tokenizer::Language::German,
+            Language::Korean => return None,
+        };
+        Some(
+            TextAnalyzer::builder(SimpleTokenizer::default())
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(Stemmer::new(stemmer))
+                .build(),
+        )
+    }
+
+    /// Field holding names stemmed for the language. Korean names are found
+    /// through their bigrams in `name_ngram`.
+    pub fn name_field(self) -> &'static str {
+        match self {
+            Language::English => "name_en",
+            Language::German => "name_de",
+            Language::Korean => "name_ngram",
+        }
+    }
+
+    /// Field holding notes written in the language
+    pub fn note_field(self) -> &'static str {
+        match self {
+            Language::English => "note_en",
+            Language::German => "note_de",
+            Language::Korean => "note_ko",
+        }
+    }
+}
+
+/// Least confidence a detected language needs; lower than whatlang's
+/// `is_reliable`, which notes only a few words long rarely pass
+const MIN_CONFIDENCE: f64 = 0.5;
+
+/// Language `text` is written in, when it is one of `Language::ALL` and can
+/// be told with some confidence
+pub fn detect(text: &str) -> Option<Language> {
+    let info = whatlang::detect(text)?;
+    match info.lang() {
+        // Hangul is written for Korean alone
+        whatlang::Lang::Kor => Some(Language::Korean),
+        whatlang::Lang::Eng if info.confidence() >= MIN_CONFIDENCE => Some(Language::English),
+        whatlang::Lang::Deu if info.confidence() >= MIN_CONFIDENCE => Some(Language::German),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::tokenizer::TokenStream;
+
+    fn stems(language: Language, text: &str) -> Vec<String> {
+        let mut analyzer = language.analyzer().unwrap();
+        let mut stream = analyzer.token_stream(text);
+        let mut stems = Vec::new();
+        while stream.advance() {
+            stems.push(stream.token().text.clone());
+        }
+        stems
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect("The invoices for the office rent were paid last week"),
+            Some(Language::English)
+        );
+        assert_eq!(
+            detect("Die Rechnungen für die Miete wurden letzte Woche bezahlt"),
+            Some(Language::German)
+        );
+        assert_eq!(
+            detect("지난주 사무실 임대료 영수증"),
+            Some(Language::Korean)
+        );
+        assert_eq!(detect("Rechnung Miete März"), Some(Language::German));
+        assert_eq!(detect("meeting notes"), None);
+        assert_eq!(detect("ok"), None);
+        assert_eq!(
+            detect("Les factures du loyer ont été payées la semaine dernière"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_analyzers_stem() {
+        assert_eq!(
+            stems(Language::English, "Paid Invoices"),
+            vec!["paid", "invoic"]
+        );
+        assert_eq!(stems(Language::English, "invoice"), vec!["invoic"]);
+        assert_eq!(
+            stems(Language::German, "Rechnungen"),
+            stems(Language::German, "Rechnung")
+        );
+        assert!(Language::Korean.analyzer().is_none());
+    }
+}
@@ -0,0 +1,113 @@
+// Edge n-gram tokenization, so the start of any word of a name is one term
+
+use tantivy::tokenizer::{Token, TokenStream, Tokenizer};
+
+/// Name under which the prefix tokenizer is registered with each index
+pub const TOKENIZER_NAME: &str = "word_prefix";
+/// Longest prefix indexed per word; longer ones fall back to a phrase prefix
+/// query on the folded name
+pub const MAX_PREFIX_CHARS: usize = 8;
+
+/// Splits names into lowercased words like the default tokenizer and emits
+/// the first 1 to `MAX_PREFIX_CHARS` characters of each, so "Report.pdf"
+/// becomes "r", "re", ..., "report", "p", "pd", "pdf"
+#[derive(Clone, Default)]
+pub struct PrefixTokenizer;
+
+impl Tokenizer for PrefixTokenizer {
+    type TokenStream<'a> = PrefixTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        PrefixTokenStream {
+            tokens: tokenize(text).into_iter(),
+            token: Token::default(),
+        }
+    }
+}
+
+pub struct PrefixTokenStream {
+    tokens: std::vec::IntoIter<Token>,
+    token: Token,
+}
+
+impl TokenStream for PrefixTokenStream {
+    fn advance(&mut self) -> bool {
+        match self.tokens.next() {
+            Some(token) => {
+                self.token = token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !c.is_alphanumeric() {
+            continue;
+        }
+        let mut ends = Vec::new();
+        while let Some(&(at, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                break;
+            }
+            ends.push(at);
+            chars.next();
+        }
+        let word_end = chars.peek().map_or(text.len(), |(at, _)| *at);
+        ends.push(word_end);
+        for end in ends.into_iter().take(MAX_PREFIX_CHARS) {
+            tokens.push(Token {
+                offset_from: start,
+                offset_to: end,
+                position,
+                text: text[start..end].to_lowercase(),
+                position_length: 1,
+            });
+        }
+        position += 1;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str) -> Vec<String> {
+        let mut tokenizer = PrefixTokenizer;
+        let mut stream = tokenizer.token_stream(text);
+        let mut texts = Vec::new();
+        while stream.advance() {
+            texts.push(stream.token().text.clone());
+        }
+        texts
+    }
+
+    #[test]
+    fn test_word_prefixes() {
+        assert_eq!(
+            texts("Q3 Report.pdf"),
+            vec!["q", "q3", "r", "re", "rep", "repo", "repor", "report", "p", "pd", "pdf"]
+        );
+        assert_eq!(texts("사진첩"), vec!["사", "사진", "사진첩"]);
+        assert_eq!(
+            texts("abcdefghijk").last().map(String::as_str),
+            Some("abcdefgh"),
+            "Prefixes stop at MAX_PREFIX_CHARS"
+        );
+        assert!(texts("--").is_empty());
+    }
+}
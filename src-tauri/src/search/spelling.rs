@@ -0,0 +1,182 @@
+// "Did you mean" suggestions for searches that found nothing: misspelled
+// words of the query swapped for close terms of the index's dictionary
+
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA, SINK_STATE};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tantivy::schema::Field;
+use tantivy::tokenizer::{TextAnalyzer, TokenStream};
+use tantivy::{Searcher, Term};
+use tantivy_fst::Automaton;
+
+/// Most suggestions offered for one query
+pub const MAX_SUGGESTIONS: usize = 3;
+/// Shorter words are too close to too many others to correct
+const MIN_WORD_CHARS: usize = 3;
+/// Words up to this many characters may be one edit off, longer ones two
+const ONE_EDIT_CHARS: usize = 5;
+
+/// Levenshtein DFA walked over the term dictionary's FST, as tantivy's
+/// fuzzy query does
+struct Dfa<'a>(&'a DFA);
+
+impl Automaton for Dfa<'_> {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
+
+/// DFA matching terms within the edits allowed for `word`; swapping two
+/// neighbouring letters counts as one
+fn dfa(word: &str) -> DFA {
+    static ONE_EDIT: OnceLock<LevenshteinAutomatonBuilder> = OnceLock::new();
+    static TWO_EDITS: OnceLock<LevenshteinAutomatonBuilder> = OnceLock::new();
+    let builder = if word.chars().count() <= ONE_EDIT_CHARS {
+        ONE_EDIT.get_or_init(|| LevenshteinAutomatonBuilder::new(1, true))
+    } else {
+        TWO_EDITS.get_or_init(|| LevenshteinAutomatonBuilder::new(2, true))
+    };
+    builder.build_dfa(word)
+}
+
+/// Terms of `field` close to `word`, fewest edits and then most entries first
+fn candidates(searcher: &Searcher, field: Field, word: &str) -> tantivy::Result<Vec<String>> {
+    let dfa = dfa(word);
+    let mut doc_freqs: HashMap<Vec<u8>, u32> = HashMap::new();
+    for segment in searcher.segment_readers() {
+        let inverted_index = segment.inverted_index(field)?;
+        let mut terms = inverted_index.terms().search(Dfa(&dfa)).into_stream()?;
+        while terms.advance() {
+            *doc_freqs.entry(terms.key().to_vec()).or_default() += terms.value().doc_freq;
+        }
+    }
+    let mut ranked: Vec<(u8, u32, String)> = doc_freqs
+        .into_iter()
+        .filter_map(|(term, doc_freq)| {
+            let Distance::Exact(edits) = dfa.eval(&term) else {
+                return None;
+            };
+            Some((edits, doc_freq, String::from_utf8(term).ok()?))
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+    Ok(ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, term)| term)
+        .collect())
+}
+
+/// A misspelled word of the query: where it is and what could replace it
+struct Fix {
+    start: usize,
+    end: usize,
+    candidates: Vec<String>,
+}
+
+/// Rewrites of `query` with its misspelled words replaced by close terms of
+/// `field`, as `analyzer` splits it, most likely first. A word is misspelled
+/// when no entry has it. Filters, operators, excluded words, short words and
+/// words with digits are left as typed. Empty when nothing could be corrected.
+pub fn suggest(
+    searcher: &Searcher,
+    field: Field,
+    analyzer: &mut TextAnalyzer,
+    query: &str,
+) -> tantivy::Result<Vec<String>> {
+    let mut fixes = Vec::new();
+    for (offset, word) in words(query) {
+        if word.contains(':') || word.starts_with('-') || matches!(word, "AND" | "OR" | "NOT") {
+            continue;
+        }
+        let mut stream = analyzer.token_stream(word);
+        while stream.advance() {
+            let token = stream.token();
+            if token.text.chars().count() < MIN_WORD_CHARS
+                || token.text.chars().any(|c| c.is_numeric())
+                || searcher.doc_freq(&Term::from_field_text(field, &token.text))? > 0
+            {
+                continue;
+            }
+            let candidates = candidates(searcher, field, &token.text)?;
+            if !candidates.is_empty() {
+                fixes.push(Fix {
+                    start: offset + token.offset_from,
+                    end: offset + token.offset_to,
+                    candidates,
+                });
+            }
+        }
+    }
+    if fixes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // The best candidate for every word first, then the runners-up of one
+    // word at a time
+    let best: Vec<usize> = vec![0; fixes.len()];
+    let mut choices = vec![best.clone()];
+    for (i, fix) in fixes.iter().enumerate() {
+        for rank in 1..fix.candidates.len() {
+            let mut choice = best.clone();
+            choice[i] = rank;
+            choices.push(choice);
+        }
+    }
+    Ok(choices
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|choice| {
+            let mut suggestion = query.to_string();
+            for (fix, rank) in fixes.iter().zip(choice).rev() {
+                suggestion.replace_range(fix.start..fix.end, &fix.candidates[rank]);
+            }
+            suggestion
+        })
+        .collect())
+}
+
+/// Whitespace-separated words of `text` with their byte offsets
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (true, Some(from)) => {
+                words.push((from, &text[from..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words("  annual\u{3000}report  ext:pdf"),
+            vec![(2, "annual"), (11, "report"), (19, "ext:pdf")]
+        );
+        assert!(words(" ").is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+// Collection of every match in chunks, for result sets past the ranked limit
+
+use std::sync::mpsc::SyncSender;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// Sends the addresses of all matches in chunks of `chunk_size` while the
+/// segments are searched, so consumers can load and show the first chunks
+/// before the search is done. Once the receiver hangs up the rest of the
+/// matches are skipped.
+pub struct ChunkCollector {
+    pub sender: SyncSender<Vec<DocAddress>>,
+    pub chunk_size: usize,
+}
+
+pub struct ChunkSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    chunk: Vec<DocAddress>,
+    chunk_size: usize,
+    sender: SyncSender<Vec<DocAddress>>,
+    hung_up: bool,
+}
+
+impl ChunkSegmentCollector {
+    fn send(&mut self) {
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(self.chunk_size));
+        if !chunk.is_empty() && self.sender.send(chunk).is_err() {
+            self.hung_up = true;
+        }
+    }
+}
+
+impl Collector for ChunkCollector {
+    type Fruit = ();
+    type Child = ChunkSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        _segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(ChunkSegmentCollector {
+            segment_ord: segment_local_id,
+            chunk: Vec::with_capacity(self.chunk_size),
+            chunk_size: self.chunk_size,
+            sender: self.sender.clone(),
+            hung_up: false,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, _segment_fruits: Vec<()>) -> tantivy::Result<()> {
+        Ok(())
+    }
+}
+
+impl SegmentCollector for ChunkSegmentCollector {
+    type Fruit = ();
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if self.hung_up {
+            return;
+        }
+        self.chunk.push(DocAddress::new(self.segment_ord, doc));
+        if self.chunk.len() >= self.chunk_size {
+            self.send();
+        }
+    }
+
+    fn harvest(mut self) {
+        if !self.hung_up {
+            self.send();
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Initial-letter transliteration of Korean and Chinese names
+
+use pinyin::ToPinyin;
+
+/// Syllables in the Hangul Syllables block
+const HANGUL_SYLLABLES: u32 = 11172;
+/// Syllables sharing one initial consonant
+const SYLLABLES_PER_INITIAL: u32 = 588;
+
+/// Initial consonant (choseong) of a precomposed Hangul syllable, as a
+/// conjoining jamo so it matches folded queries typed as "ㅅ"
+fn choseong(c: char) -> Option<char> {
+    let index = (c as u32)
+        .checked_sub(0xAC00)
+        .filter(|index| *index < HANGUL_SYLLABLES)?;
+    char::from_u32(0x1100 + index / SYLLABLES_PER_INITIAL)
+}
+
+/// Initials of the Hangul syllables and Chinese characters in a folded name,
+/// one word per run of them: "여행사진" gives the jamo "ᄋᄒᄉᄌ" and "我的照片"
+/// gives "wdzp". Names with neither give an empty string.
+pub fn initials(name: &str) -> String {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for c in name.chars() {
+        if let Some(initial) = choseong(c) {
+            word.push(initial);
+        } else if let Some(pinyin) = c.to_pinyin() {
+            word.push_str(pinyin.first_letter());
+        } else if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::fold;
+
+    #[test]
+    fn test_initials_of_hangul_and_hanzi() {
+        assert_eq!(initials("여행사진_2024.jpg"), fold("ㅇㅎㅅㅈ"));
+        assert_eq!(initials("我的照片.png"), "wdzp");
+        assert_eq!(initials("사진 照片"), format!("{} zp", fold("ㅅㅈ")));
+        assert_eq!(initials("report.pdf"), "");
+    }
+}
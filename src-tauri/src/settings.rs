@@ -0,0 +1,180 @@
+// App-wide settings stored as JSON in the app data directory
+
+use crate::actions::CustomAction;
+use crate::i18n::Locale;
+use crate::remote::{AgentSettings, RemoteMachine};
+use crate::search::WriterOptions;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Memory and threads of the tantivy index writers
+    pub writer: WriterOptions,
+    /// Read admin-only directories through the backup privilege when the app
+    /// runs elevated (Windows)
+    pub elevated_indexing: bool,
+    /// Stay in the tray without showing the window when launched at login
+    pub start_hidden: bool,
+    /// Read the index into memory in the background once it is loaded, so
+    /// the first search does not wait on the disk
+    pub warm_up_index: bool,
+    /// Commands offered on search results; see `actions`
+    pub actions: Vec<CustomAction>,
+    /// Port and token of this machine's agent; set when it is first started
+    pub agent: Option<AgentSettings>,
+    /// Machines whose agents searches can include
+    pub remotes: Vec<RemoteMachine>,
+    /// Language of tray labels and error messages
+    pub locale: Locale,
+    /// Absolute paths whose entries searches leave out until unlocked for
+    /// the session; see `privacy`
+    pub private_paths: Vec<String>,
+    /// Folder for the index, settings and logs instead of the app data
+    /// directory, relative ones taken from the executable's folder. Only read
+    /// from the app data directory's settings, at launch; see `portable`.
+    pub data_dir: Option<String>,
+    /// Quiet period in milliseconds a changed path needs before the watcher
+    /// applies it to the index; read at launch
+    pub debounce_ms: u64,
+    /// Command printing the text in an image, run on indexed images in the
+    /// background so they are found by their words; OCR is off when unset.
+    /// A template like those of actions, such as `tesseract %path -`.
+    pub ocr_command: Option<String>,
+    /// Command printing a vector for each line of text it reads, run on the
+    /// names, notes and email subjects of indexed entries in the background
+    /// for semantic_search, which is off when unset; see `embed`
+    pub embedding_command: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            writer: WriterOptions::default(),
+            elevated_indexing: false,
+            start_hidden: false,
+            warm_up_index: true,
+            actions: Vec::new(),
+            agent: None,
+            remotes: Vec::new(),
+            locale: Locale::default(),
+            private_paths: Vec::new(),
+            data_dir: None,
+            debounce_ms: crate::watcher::DEFAULT_DEBOUNCE_WINDOW.as_millis() as u64,
+            ocr_command: None,
+            embedding_command: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Load settings from `base_dir`, falling back to the defaults when the
+    /// file is missing, malformed or holds invalid values
+    pub fn load(base_dir: &Path) -> Self {
+        let file_path = base_dir.join(SETTINGS_FILE);
+        let Ok(data) = fs::read_to_string(&file_path) else {
+            return Settings::default();
+        };
+        match serde_json::from_str::<Settings>(&data) {
+            Ok(settings) => match settings.validate() {
+                Ok(()) => settings,
+                Err(e) => {
+                    log::warn!("Ignoring invalid {}: {}", file_path.display(), e);
+                    Settings::default()
+                }
+            },
+            Err(e) => {
+                log::warn!("Ignoring malformed {}: {}", file_path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, base_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(base_dir)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(base_dir.join(SETTINGS_FILE), data)
+            .map_err(|e| format!("Failed to write settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.writer.validate()?;
+        crate::actions::validate(&self.actions)?;
+        crate::privacy::validate(&self.private_paths)?;
+        crate::ocr::validate(self.ocr_command.as_deref())?;
+        crate::embed::validate(self.embedding_command.as_deref())?;
+        let range = crate::watcher::DEBOUNCE_MS_RANGE;
+        if !range.contains(&self.debounce_ms) {
+            return Err(format!(
+                "Debounce window must be {} to {} ms, got {}",
+                range.start(),
+                range.end(),
+                self.debounce_ms
+            ));
+        }
+        crate::remote::validate(self.agent.as_ref(), &self.remotes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_settings_round_trip_and_fallbacks() {
+        let temp_dir = tempdir().unwrap();
+        assert_eq!(Settings::load(temp_dir.path()), Settings::default());
+
+        let settings = Settings {
+            writer: WriterOptions {
+                heap_mb: 512,
+                threads: 4,
+            },
+            elevated_indexing: true,
+            start_hidden: true,
+            warm_up_index: false,
+            actions: vec![CustomAction {
+                id: "code".to_string(),
+                name: "Open in VS Code".to_string(),
+                command: "code %path".to_string(),
+                extensions: vec!["rs".to_string()],
+                labels: Vec::new(),
+            }],
+            agent: Some(AgentSettings::generate(crate::remote::DEFAULT_AGENT_PORT).unwrap()),
+            remotes: vec![RemoteMachine {
+                name: "nas".to_string(),
+                address: "nas.local:48650".to_string(),
+                token: "secret".to_string(),
+            }],
+            locale: Locale::Ko,
+            private_paths: vec![std::env::temp_dir().to_string_lossy().to_string()],
+            data_dir: Some("data".to_string()),
+            debounce_ms: 2000,
+            ocr_command: Some("tesseract %path -".to_string()),
+            embedding_command: Some("embed --model all-MiniLM-L6-v2".to_string()),
+        };
+        settings.save(temp_dir.path()).unwrap();
+        assert_eq!(Settings::load(temp_dir.path()), settings);
+
+        let file_path = temp_dir.path().join(SETTINGS_FILE);
+        fs::write(&file_path, r#"{"writer": {"heap_mb": 1, "threads": 4}}"#).unwrap();
+        assert_eq!(Settings::load(temp_dir.path()), Settings::default());
+        fs::write(
+            &file_path,
+            r#"{"actions": [{"id": "x", "name": "X", "command": "'"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(Settings::load(temp_dir.path()), Settings::default());
+        fs::write(&file_path, r#"{"debounce_ms": 0}"#).unwrap();
+        assert_eq!(Settings::load(temp_dir.path()), Settings::default());
+        fs::write(&file_path, "not json").unwrap();
+        assert_eq!(Settings::load(temp_dir.path()), Settings::default());
+    }
+}
@@ -0,0 +1,307 @@
+// Secure deletion: file contents are overwritten in place before the file is
+// unlinked, for sensitive documents that must not be recoverable from the
+// free space of the disk
+
+use crate::error::{AppError, ErrorKind};
+use serde::Serialize;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Most overwrite passes accepted; one pass already defeats recovery tools
+/// on hard disks
+pub const MAX_PASSES: u32 = 7;
+
+/// Bytes written at a time
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Why overwriting is no guarantee, shown with every secure delete
+pub const STORAGE_WARNING: &str = "Overwriting only reliably destroys data on \
+    hard disks. SSDs and flash drives remap writes to fresh cells, and \
+    copy-on-write file systems (APFS, Btrfs, ZFS), snapshots, backups and \
+    cloud sync can keep earlier copies, so traces of the data may survive \
+    there. Full-disk encryption is the dependable protection on such storage.";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ShredReport {
+    pub files: u64,
+    pub folders: u64,
+    /// Bytes overwritten per pass
+    pub bytes: u64,
+    pub passes: u32,
+    pub warning: &'static str,
+}
+
+/// Overwrite every file at or below `paths` `passes` times, random data
+/// first and then alternating zeros and ones, and delete it. Links are
+/// removed without touching what they point to. Stops at the first failure;
+/// entries deleted up to then stay deleted.
+pub fn shred(paths: &[PathBuf], passes: u32) -> Result<ShredReport, AppError> {
+    if !(1..=MAX_PASSES).contains(&passes) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Passes go from 1 to {}", MAX_PASSES),
+        ));
+    }
+    for path in paths {
+        if !path.is_absolute() {
+            return Err(
+                AppError::new(ErrorKind::InvalidInput, "Paths must be absolute")
+                    .with_path(path.to_string_lossy()),
+            );
+        }
+        fs::symlink_metadata(path).map_err(|e| AppError::io(path.to_string_lossy(), &e))?;
+    }
+
+    let mut report = ShredReport {
+        files: 0,
+        folders: 0,
+        bytes: 0,
+        passes,
+        warning: STORAGE_WARNING,
+    };
+    let mut noise = Noise::new();
+    for path in paths {
+        // Children before their folder, which is empty by then
+        for entry in walkdir::WalkDir::new(path).contents_first(true) {
+            let entry = entry.map_err(|e| {
+                let path = e.path().unwrap_or(path).to_string_lossy().to_string();
+                AppError::new(ErrorKind::Io, e.to_string()).with_path(path)
+            })?;
+            let entry_path = entry.path();
+            let io_error = |e: std::io::Error| AppError::io(entry_path.to_string_lossy(), &e);
+            if entry.file_type().is_dir() {
+                fs::remove_dir(entry_path).map_err(io_error)?;
+                report.folders += 1;
+            } else if entry.file_type().is_file() {
+                report.bytes += overwrite(entry_path, passes, &mut noise).map_err(io_error)?;
+                remove_scrubbed(entry_path, &mut noise).map_err(io_error)?;
+                report.files += 1;
+            } else {
+                fs::remove_file(entry_path).map_err(io_error)?;
+                report.files += 1;
+            }
+        }
+    }
+    log::info!(
+        "Securely deleted {} file(s) and {} folder(s) with {} pass(es)",
+        report.files,
+        report.folders,
+        passes
+    );
+    Ok(report)
+}
+
+/// Overwrite the contents of the file at `path` in place, syncing each pass
+/// to the disk. Returns its length.
+fn overwrite(path: &Path, passes: u32, noise: &mut Noise) -> std::io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    refuse_other_links(path, &metadata)?;
+    let mut permissions = metadata.permissions();
+    if permissions.readonly() {
+        #[allow(clippy::permissions_set_readonly_false)] // The file is deleted right after
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions)?;
+    }
+    let length = metadata.len();
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    for pass in 0..passes {
+        match pass {
+            0 => {}
+            _ if pass % 2 == 1 => buffer.fill(0x00),
+            _ => buffer.fill(0xFF),
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let mut left = length;
+        while left > 0 {
+            let chunk = left.min(CHUNK_SIZE as u64) as usize;
+            if pass == 0 {
+                noise.fill(&mut buffer[..chunk]);
+            }
+            file.write_all(&buffer[..chunk])?;
+            left -= chunk as u64;
+        }
+        file.sync_all()?;
+    }
+    file.set_len(0)?;
+    file.sync_all()?;
+    Ok(length)
+}
+
+/// Overwriting a file with other hard links would destroy the data under
+/// those names while they stay behind
+#[cfg(unix)]
+fn refuse_other_links(_path: &Path, metadata: &fs::Metadata) -> std::io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    check_link_count(metadata.nlink())
+}
+
+#[cfg(windows)]
+fn refuse_other_links(path: &Path, _metadata: &fs::Metadata) -> std::io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_READ_ATTRIBUTES,
+    };
+
+    // std's metadata leaves the link count out, so ask the handle
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_READ_ATTRIBUTES)
+        .share_mode(0x7)
+        .open(path)?;
+    // SAFETY: BY_HANDLE_FILE_INFORMATION is plain data; all-zero is valid
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    // SAFETY: the handle is open for the duration of the call
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    check_link_count(u64::from(info.nNumberOfLinks))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn refuse_other_links(_path: &Path, _metadata: &fs::Metadata) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Cannot tell whether the file has other hard links",
+    ))
+}
+
+#[cfg(any(unix, windows))]
+fn check_link_count(links: u64) -> std::io::Result<()> {
+    if links > 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "The file has other hard links; delete those first",
+        ));
+    }
+    Ok(())
+}
+
+/// Rename the file to random characters before deleting it, so its name
+/// does not linger in the directory's free slots either
+fn remove_scrubbed(path: &Path, noise: &mut Noise) -> std::io::Result<()> {
+    let mut bytes = [0u8; 8];
+    noise.fill(&mut bytes);
+    let name: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let scrubbed = path.with_file_name(name);
+    let path = match fs::rename(path, &scrubbed) {
+        Ok(()) => scrubbed.as_path(),
+        Err(e) => {
+            log::debug!("Could not scrub the name of {}: {}", path.display(), e);
+            path
+        }
+    };
+    fs::remove_file(path)
+}
+
+/// xorshift64* stream: fast and unpredictable enough to hide what was
+/// written before, though no cryptographic generator
+struct Noise(u64);
+
+impl Noise {
+    fn new() -> Noise {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Noise((nanos ^ (std::process::id() as u64).rotate_left(32)) | 1)
+    }
+
+    fn fill(&mut self, buffer: &mut [u8]) {
+        for chunk in buffer.chunks_mut(8) {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            let value = self.0.wrapping_mul(0x2545_F491_4F6C_DD1D).to_le_bytes();
+            chunk.copy_from_slice(&value[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shred_removes_files_and_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("secrets");
+        fs::create_dir_all(folder.join("nested")).unwrap();
+        fs::write(folder.join("a.txt"), "password").unwrap();
+        fs::write(
+            folder.join("nested").join("b.bin"),
+            vec![7u8; CHUNK_SIZE + 10],
+        )
+        .unwrap();
+        let single = dir.path().join("key.pem");
+        fs::write(&single, "private").unwrap();
+        let mut readonly = fs::metadata(&single).unwrap().permissions();
+        readonly.set_readonly(true);
+        fs::set_permissions(&single, readonly).unwrap();
+
+        let report = shred(&[folder.clone(), single.clone()], 3).unwrap();
+        assert_eq!((report.files, report.folders), (3, 2));
+        assert_eq!(report.bytes, 8 + CHUNK_SIZE as u64 + 10 + 7);
+        assert!(!folder.exists());
+        assert!(!single.exists());
+        assert_eq!(
+            fs::read_dir(dir.path()).unwrap().count(),
+            0,
+            "No renamed leftovers"
+        );
+    }
+
+    #[test]
+    fn test_overwrite_replaces_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data");
+        fs::write(&file, vec![0xAB; 100]).unwrap();
+        let mut noise = Noise::new();
+        assert_eq!(overwrite(&file, 1, &mut noise).unwrap(), 100);
+        assert_eq!(fs::metadata(&file).unwrap().len(), 0);
+
+        let mut a = [0u8; 13];
+        noise.fill(&mut a);
+        assert!(a.iter().any(|&b| b != 0));
+    }
+
+    #[test]
+    fn test_shred_rejects_bad_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "x").unwrap();
+        assert!(shred(std::slice::from_ref(&file), 0).is_err());
+        assert!(shred(std::slice::from_ref(&file), MAX_PASSES + 1).is_err());
+        assert!(shred(&[dir.path().join("missing")], 1).is_err());
+        assert!(shred(&[PathBuf::from("relative.txt")], 1).is_err());
+        assert!(file.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shred_keeps_link_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        fs::write(&target, "keep").unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        shred(std::slice::from_ref(&link), 1).unwrap();
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep");
+    }
+
+    #[test]
+    fn test_shred_refuses_hard_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        fs::write(&target, "keep").unwrap();
+        let hard = dir.path().join("hard.txt");
+        fs::hard_link(&target, &hard).unwrap();
+        assert!(shred(std::slice::from_ref(&hard), 1).is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep");
+
+        fs::remove_file(&target).unwrap();
+        shred(std::slice::from_ref(&hard), 1).unwrap();
+        assert!(!hard.exists());
+    }
+}
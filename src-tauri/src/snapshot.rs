@@ -0,0 +1,214 @@
+// Frozen indexes of whole volumes, such as backup disks, kept searchable after
+// they are unplugged. A snapshot is written once when taken and never touched
+// again: it is not watched, not rebuilt with the profiles, and only goes away
+// when the user deletes it or takes it again.
+
+use crate::builder::IndexBuilder;
+use crate::index::{IndexManager, WalkOptions};
+use crate::search::SearchIndex;
+use crate::volumes::Volume;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder of the snapshots inside the app data directory
+const SNAPSHOTS_DIR: &str = "snapshots";
+const CATALOG_FILE: &str = "snapshots.json";
+const DB_DIR: &str = ".index_db";
+const SEARCH_DIR: &str = ".search_index";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Snapshot {
+    /// Taken from the label, so snapshotting the same disk again replaces it
+    pub id: String,
+    /// Volume label, or the mount point when it has none; shown on its hits
+    pub label: String,
+    pub mount_point: String,
+    pub file_system: Option<String>,
+    pub total_bytes: Option<u64>,
+    /// When it was taken, in Unix seconds
+    pub created: i64,
+    pub file_count: usize,
+    /// The volume is plugged in now; set when listed
+    #[serde(default, skip_deserializing)]
+    pub mounted: bool,
+}
+
+impl Snapshot {
+    /// Whether `volume` is the disk this snapshot was taken of
+    pub fn is_of(&self, volume: &Volume) -> bool {
+        volume.mount_point == self.mount_point && label_of(volume) == self.label
+    }
+}
+
+fn label_of(volume: &Volume) -> String {
+    volume
+        .label
+        .clone()
+        .filter(|label| !label.trim().is_empty())
+        .unwrap_or_else(|| volume.mount_point.clone())
+}
+
+/// Snapshots in the app data directory, described by a JSON catalog
+#[derive(Debug)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+    snapshots: Vec<Snapshot>,
+}
+
+impl SnapshotStore {
+    /// Catalog in `base_dir`, empty when missing or malformed
+    pub fn load(base_dir: &Path) -> Self {
+        let dir = base_dir.join(SNAPSHOTS_DIR);
+        let file_path = dir.join(CATALOG_FILE);
+        let snapshots = match fs::read_to_string(&file_path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                log::warn!("Ignoring malformed {}: {}", file_path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        SnapshotStore { dir, snapshots }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+        let data = serde_json::to_string_pretty(&self.snapshots)
+            .map_err(|e| format!("Failed to serialize snapshots: {}", e))?;
+        fs::write(self.dir.join(CATALOG_FILE), data)
+            .map_err(|e| format!("Failed to write snapshots: {}", e))
+    }
+
+    pub fn list(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
+
+    /// Directory holding the sled DB and tantivy index of snapshot `id`
+    fn data_dir(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    pub fn search_index_path(&self, id: &str) -> PathBuf {
+        self.data_dir(id).join(SEARCH_DIR)
+    }
+
+    /// Index every entry of `volume` into a new snapshot, replacing one taken
+    /// of the same disk before. The old one stays until the new one is
+    /// complete.
+    pub fn take(&mut self, volume: &Volume, now: i64) -> Result<Snapshot, String> {
+        let label = label_of(volume);
+        let id = slugify(&label);
+        let live = self.data_dir(&id);
+        let staging = crate::rebuild::staging_path(&live);
+        crate::rebuild::clear_staging(&staging)
+            .map_err(|e| format!("Failed to clear {}: {}", staging.display(), e))?;
+        let file_count = build(Path::new(&volume.mount_point), &staging)?;
+        crate::rebuild::promote(&staging, &live)
+            .map_err(|e| format!("Failed to store snapshot {}: {}", id, e))?;
+
+        let snapshot = Snapshot {
+            id,
+            label,
+            mount_point: volume.mount_point.clone(),
+            file_system: volume.file_system.clone(),
+            total_bytes: volume.total_bytes,
+            created: now,
+            file_count,
+            mounted: true,
+        };
+        self.snapshots.retain(|other| other.id != snapshot.id);
+        self.snapshots.push(snapshot.clone());
+        self.snapshots.sort_by(|a, b| a.label.cmp(&b.label));
+        self.save()?;
+        Ok(snapshot)
+    }
+
+    /// Forget snapshot `id` and delete its index
+    pub fn remove(&mut self, id: &str) -> Result<Snapshot, String> {
+        let position = self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.id == id)
+            .ok_or_else(|| format!("Unknown snapshot: {}", id))?;
+        let snapshot = self.snapshots.remove(position);
+        self.save()?;
+        let dir = self.data_dir(id);
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            log::warn!("Failed to delete {}: {}", dir.display(), e);
+        }
+        Ok(snapshot)
+    }
+}
+
+/// Walk `root` into a sled DB and tantivy index under `dir`; returns the
+/// number of entries
+fn build(root: &Path, dir: &Path) -> Result<usize, String> {
+    let index_manager = IndexManager::new(&dir.join(DB_DIR))
+        .map_err(|e| format!("Failed to create index manager: {}", e))?;
+    let search_index = SearchIndex::new(&dir.join(SEARCH_DIR))
+        .map_err(|e| format!("Failed to create search index: {}", e))?;
+    let mut builder = IndexBuilder::new(&index_manager, &search_index)?;
+    let skipped =
+        index_manager.walk_directory(root, WalkOptions::default(), |entity| builder.add(entity))?;
+    if skipped > 0 {
+        log::warn!(
+            "Skipped {} unreadable entries in {}",
+            skipped,
+            root.display()
+        );
+    }
+    builder.finish()
+}
+
+fn slugify(label: &str) -> String {
+    label
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_take_and_remove_snapshot() {
+        let disk = tempdir().unwrap();
+        fs::create_dir(disk.path().join("photos")).unwrap();
+        fs::write(disk.path().join("photos/beach.jpg"), b"jpg").unwrap();
+        let app_dir = tempdir().unwrap();
+        let volume = Volume {
+            mount_point: disk.path().to_string_lossy().to_string(),
+            label: Some("Backup 2024".to_string()),
+            removable: true,
+            ..Default::default()
+        };
+
+        let mut store = SnapshotStore::load(app_dir.path());
+        let snapshot = store.take(&volume, 100).unwrap();
+        assert_eq!(snapshot.id, "backup_2024");
+        assert!(snapshot.file_count >= 2);
+        assert!(snapshot.is_of(&volume));
+        // Taking it again replaces the first one
+        store.take(&volume, 200).unwrap();
+
+        let mut store = SnapshotStore::load(app_dir.path());
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(
+            (store.list()[0].created, store.list()[0].mounted),
+            (200, false)
+        );
+        let search_index = SearchIndex::new(&store.search_index_path("backup_2024")).unwrap();
+        let hits = search_index.search("beach", false, 10).unwrap();
+        assert_eq!(hits.len(), 1);
+
+        store.remove("backup_2024").unwrap();
+        assert!(store.remove("backup_2024").is_err());
+        assert!(!store.data_dir("backup_2024").exists());
+        assert!(SnapshotStore::load(app_dir.path()).list().is_empty());
+    }
+}
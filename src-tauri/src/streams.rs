@@ -0,0 +1,170 @@
+// Data attached to a file besides its content: NTFS alternate data streams on
+// Windows and extended attributes on macOS and Linux
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::path::Path;
+
+/// Whether inspect_streams works on this platform
+pub const SUPPORTED: bool = cfg!(any(windows, target_os = "macos", target_os = "linux"));
+
+/// Bytes of a stream shown as its preview
+const PREVIEW_BYTES: usize = 512;
+
+/// One alternate data stream or extended attribute
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StreamEntry {
+    pub name: String,
+    pub size: u64,
+    /// Start of the content when it is text, such as the source URL in a
+    /// Zone.Identifier stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+}
+
+/// `bytes` up to PREVIEW_BYTES as text, or None when they are binary
+#[cfg_attr(
+    not(any(windows, target_os = "macos", target_os = "linux")),
+    allow(dead_code)
+)]
+fn preview(bytes: &[u8]) -> Option<String> {
+    let mut end = bytes.len().min(PREVIEW_BYTES);
+    let text = loop {
+        match std::str::from_utf8(&bytes[..end]) {
+            Ok(text) => break text,
+            // Cut in the middle of a character
+            Err(e) if e.error_len().is_none() && end > 0 => end = e.valid_up_to(),
+            Err(_) => return None,
+        }
+    };
+    let binary = text
+        .chars()
+        .any(|c| c.is_control() && !c.is_ascii_whitespace());
+    (!text.is_empty() && !binary).then(|| text.to_string())
+}
+
+/// Alternate data streams of `path`, without the unnamed stream holding the
+/// file's content
+#[cfg(windows)]
+pub fn inspect_streams(path: &Path) -> Result<Vec<StreamEntry>, AppError> {
+    use std::io::Read;
+    use windows_sys::Win32::Foundation::{ERROR_HANDLE_EOF, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard,
+        WIN32_FIND_STREAM_DATA,
+    };
+
+    let wide = crate::longpath::file_api_wide(path);
+    // SAFETY: all-zero is a valid WIN32_FIND_STREAM_DATA
+    let mut data: WIN32_FIND_STREAM_DATA = unsafe { std::mem::zeroed() };
+    let data_ptr = std::ptr::addr_of_mut!(data).cast();
+    // SAFETY: `wide` is NUL-terminated and `data_ptr` points at a live struct
+    // of the type FindStreamInfoStandard fills
+    let handle = unsafe { FindFirstStreamW(wide.as_ptr(), FindStreamInfoStandard, data_ptr, 0) };
+    if handle == INVALID_HANDLE_VALUE {
+        let err = std::io::Error::last_os_error();
+        // Folders usually have no streams at all
+        if err.raw_os_error() == Some(ERROR_HANDLE_EOF as i32) {
+            return Ok(Vec::new());
+        }
+        return Err(AppError::io(path.to_string_lossy(), &err));
+    }
+
+    let mut entries = Vec::new();
+    loop {
+        let len = data
+            .cStreamName
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(data.cStreamName.len());
+        let raw = String::from_utf16_lossy(&data.cStreamName[..len]);
+        // Named streams look like ":Zone.Identifier:$DATA"; "::$DATA" is the content
+        let name = raw
+            .strip_prefix(':')
+            .and_then(|name| name.strip_suffix(":$DATA"))
+            .filter(|name| !name.is_empty());
+        if let Some(name) = name {
+            let mut stream = path.as_os_str().to_os_string();
+            stream.push(format!(":{}", name));
+            let mut head = Vec::new();
+            let preview = std::fs::File::open(&stream)
+                .and_then(|file| file.take(PREVIEW_BYTES as u64).read_to_end(&mut head))
+                .ok()
+                .and_then(|_| preview(&head));
+            entries.push(StreamEntry {
+                name: name.to_string(),
+                size: data.StreamSize.max(0) as u64,
+                preview,
+            });
+        }
+        // SAFETY: `handle` is an open stream search and `data_ptr` still valid
+        if unsafe { FindNextStreamW(handle, data_ptr) } == 0 {
+            break;
+        }
+    }
+    // SAFETY: `handle` came from FindFirstStreamW and is closed once
+    unsafe { FindClose(handle) };
+    Ok(entries)
+}
+
+/// Extended attributes of `path`, or of the link itself for a symlink
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+pub fn inspect_streams(path: &Path) -> Result<Vec<StreamEntry>, AppError> {
+    let io_error = |e: std::io::Error| AppError::io(path.to_string_lossy(), &e);
+    std::fs::symlink_metadata(path).map_err(io_error)?;
+    let mut entries = Vec::new();
+    for name in crate::xattr::list(path).map_err(io_error)? {
+        // Removed since it was listed
+        let Some(value) = crate::xattr::get(path, &name).map_err(io_error)? else {
+            continue;
+        };
+        entries.push(StreamEntry {
+            name: String::from_utf8_lossy(&name).to_string(),
+            size: value.len() as u64,
+            preview: preview(&value),
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn inspect_streams(path: &Path) -> Result<Vec<StreamEntry>, AppError> {
+    Err(AppError::new(
+        crate::error::ErrorKind::Unsupported,
+        "Listing streams and extended attributes is not supported on this platform",
+    )
+    .with_path(path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(
+            preview(b"[ZoneTransfer]\r\nZoneId=3\r\n").as_deref(),
+            Some("[ZoneTransfer]\r\nZoneId=3\r\n")
+        );
+        assert_eq!(preview(b"bplist00\x00\x01"), None);
+        assert_eq!(preview(b""), None);
+        let long = "é".repeat(PREVIEW_BYTES);
+        let cut = preview(long.as_bytes()).unwrap();
+        assert_eq!(cut.len(), PREVIEW_BYTES, "Cut at a character boundary");
+    }
+
+    #[test]
+    fn test_inspect_streams_of_plain_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("plain.txt");
+        std::fs::write(&file, "content").unwrap();
+        if SUPPORTED {
+            let streams = inspect_streams(&file).unwrap();
+            assert!(streams.iter().all(|s| !s.name.is_empty()));
+            assert!(inspect_streams(&dir.path().join("missing")).is_err());
+        } else {
+            assert!(inspect_streams(&file).is_err());
+        }
+    }
+}
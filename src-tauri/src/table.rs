@@ -0,0 +1,226 @@
+// Result rows as display text for the columns the user picked, so a large
+// table renders without the webview formatting every cell itself
+
+use crate::format;
+use crate::search::SearchResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Most rows returned by one call, as many as one ranked search returns
+pub const MAX_ROWS: usize = crate::search::MAX_RANKED_RESULTS;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Name,
+    Path,
+    Extension,
+    /// Folder holding the entry
+    Parent,
+    Size,
+    Modified,
+    Created,
+    /// Letters for read-only, hidden, symlink and online-only
+    Attributes,
+    /// Unix permission bits as `rwxr-xr-x`
+    Mode,
+    /// User name of the owner, or the uid when it has none
+    Owner,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TableRow {
+    /// Identifies the entry for actions on the row
+    pub path: String,
+    /// Cell text in the order of the requested columns
+    pub cells: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResultsTable {
+    pub columns: Vec<Column>,
+    pub rows: Vec<TableRow>,
+    /// Every hit of the search, not just the rows returned
+    pub total_found: usize,
+}
+
+/// Formats rows, remembering user names so each uid is looked up once
+#[derive(Debug, Default)]
+pub struct Formatter {
+    users: Option<HashMap<u32, String>>,
+}
+
+impl Formatter {
+    pub fn row(&mut self, result: &SearchResult, columns: &[Column]) -> TableRow {
+        TableRow {
+            path: result.path.clone(),
+            cells: columns
+                .iter()
+                .map(|column| self.cell(result, *column))
+                .collect(),
+        }
+    }
+
+    fn cell(&mut self, result: &SearchResult, column: Column) -> String {
+        let path = Path::new(&result.path);
+        match column {
+            Column::Name => result.name.clone(),
+            Column::Path => result.path.clone(),
+            Column::Extension if result.is_folder => String::new(),
+            Column::Extension => path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+            Column::Parent => path
+                .parent()
+                .map(|parent| parent.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            Column::Size if result.is_folder => String::new(),
+            Column::Size => format::size(result.size, crate::i18n::current()),
+            Column::Modified => format_date(&result.modified),
+            Column::Created => result
+                .created
+                .as_deref()
+                .map(format_date)
+                .unwrap_or_default(),
+            Column::Attributes => attribute_letters(result),
+            Column::Mode => result.attributes.mode.map(format_mode).unwrap_or_default(),
+            Column::Owner => match result.attributes.uid {
+                Some(uid) => self.user_name(uid),
+                None => result.attributes.owner.clone().unwrap_or_default(),
+            },
+        }
+    }
+
+    fn user_name(&mut self, uid: u32) -> String {
+        let users = self.users.get_or_insert_with(|| {
+            std::fs::read_to_string("/etc/passwd")
+                .map(|passwd| parse_passwd(&passwd))
+                .unwrap_or_default()
+        });
+        users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+}
+
+/// User names by uid in `/etc/passwd`. macOS keeps most accounts in
+/// Directory Services instead, so those show as uids.
+fn parse_passwd(passwd: &str) -> HashMap<u32, String> {
+    passwd
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Uid of the account `name` in `/etc/passwd`, for `owner:<name>`
+pub fn uid_of(name: &str) -> Option<u32> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    parse_passwd(&passwd)
+        .into_iter()
+        .find(|(_, user)| user.eq_ignore_ascii_case(name))
+        .map(|(uid, _)| uid)
+}
+
+/// ISO 8601 `timestamp` as local date and time in the current locale
+fn format_date(timestamp: &str) -> String {
+    format::parse_timestamp(timestamp)
+        .map(|timestamp| format::date_time(timestamp, crate::i18n::current()))
+        .unwrap_or_default()
+}
+
+fn attribute_letters(result: &SearchResult) -> String {
+    [
+        (result.attributes.readonly, 'R'),
+        (result.hidden, 'H'),
+        (result.attributes.is_symlink, 'L'),
+        (result.attributes.online_only, 'O'),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, letter)| *letter)
+    .collect()
+}
+
+fn format_mode(mode: u32) -> String {
+    (0..9)
+        .map(|bit| {
+            if mode & (0o400 >> bit) == 0 {
+                '-'
+            } else {
+                ['r', 'w', 'x'][bit % 3]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_cells() {
+        assert_eq!(format_mode(0o754), "rwxr-xr--");
+
+        let result = SearchResult {
+            name: "Report.PDF".to_string(),
+            path: "/home/me/docs/Report.PDF".to_string(),
+            size: 2048,
+            modified: "2024-03-01T10:00:00Z".to_string(),
+            is_folder: false,
+            hidden: true,
+            created: None,
+            accessed: None,
+            attributes: crate::FileAttributes {
+                readonly: true,
+                uid: Some(4242),
+                mode: Some(0o644),
+                ..Default::default()
+            },
+            matches: Default::default(),
+            quarantined: false,
+            profile: None,
+            machine: None,
+            snapshot: None,
+            display: None,
+        };
+        let columns = [
+            Column::Extension,
+            Column::Parent,
+            Column::Size,
+            Column::Created,
+            Column::Attributes,
+            Column::Mode,
+            Column::Owner,
+        ];
+        let mut formatter = Formatter {
+            users: Some(parse_passwd(
+                "# users\nroot:x:0:0::/root:/bin/sh\nme:x:4242:4242::/home/me:/bin/sh\n",
+            )),
+        };
+        let row = formatter.row(&result, &columns);
+        assert_eq!(row.path, result.path);
+        assert_eq!(
+            row.cells,
+            [
+                "pdf",
+                "/home/me/docs",
+                "2.0 KB",
+                "",
+                "RH",
+                "rw-r--r--",
+                "me"
+            ]
+        );
+        assert_eq!(
+            formatter.row(&result, &[Column::Modified]).cells,
+            [format_date("2024-03-01T10:00:00Z")]
+        );
+        assert!(!format_date("2024-03-01T10:00:00Z").is_empty());
+    }
+}
@@ -0,0 +1,787 @@
+// Copying and moving entries into a folder the way file managers do: with a
+// policy for names already taken there, progress reports and cancellation
+
+use crate::error::{AppError, ErrorKind};
+use crate::search::CancelToken;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Bytes copied between cancellation checks and progress reports
+const CHUNK_SIZE: usize = 1 << 20;
+/// Least time between two progress reports
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// How often a transfer waiting for a conflict answer checks for cancellation
+const ANSWER_POLL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    Copy,
+    Move,
+}
+
+/// What to do when the destination folder already has an entry of the same
+/// name. Folders are merged into existing folders rather than conflicting.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    Skip,
+    Replace,
+    /// Keep both, numbering the new one "Name (2).ext"
+    Rename,
+    /// Ask about each conflict through a `transfer-conflict` event
+    Ask,
+}
+
+/// Answer to one conflict
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictChoice {
+    Skip,
+    Replace,
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ConflictAnswer {
+    /// `conflict_id` of the conflict answered
+    pub conflict_id: u64,
+    pub choice: ConflictChoice,
+    /// Answer every later conflict of the transfer the same way
+    #[serde(default)]
+    pub apply_to_all: bool,
+}
+
+/// An entry whose name is taken in the destination, asked about under the
+/// Ask policy
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TransferConflict {
+    pub transfer_id: String,
+    /// Sequence number of the conflict within its transfer
+    pub conflict_id: u64,
+    pub source: String,
+    pub destination: String,
+    pub source_size: u64,
+    pub source_modified: Option<i64>,
+    pub destination_size: u64,
+    pub destination_modified: Option<i64>,
+    pub destination_is_folder: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub items_done: u64,
+    pub items_total: u64,
+    /// Source entry being transferred
+    pub current: String,
+}
+
+/// A top-level source and where it ended up
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TransferredEntry {
+    pub from: String,
+    pub to: String,
+    /// False when parts of a folder were skipped or failed
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TransferSummary {
+    pub transfer_id: String,
+    pub transferred: Vec<TransferredEntry>,
+    /// Sources skipped because of a conflict
+    pub skipped: Vec<String>,
+    /// Entries that could not be transferred; the others still were
+    pub failed: Vec<AppError>,
+    pub cancelled: bool,
+}
+
+pub struct TransferRequest {
+    pub id: String,
+    pub kind: TransferKind,
+    pub sources: Vec<PathBuf>,
+    /// Folder the sources are copied or moved into
+    pub destination: PathBuf,
+    pub policy: OverwritePolicy,
+}
+
+/// Wait for the answer to conflict `conflict_id`, dropping stale answers.
+/// None once `cancel` fires or nobody can answer any more.
+pub fn wait_for_answer(
+    answers: &Receiver<ConflictAnswer>,
+    conflict_id: u64,
+    cancel: &CancelToken,
+) -> Option<ConflictAnswer> {
+    loop {
+        match answers.recv_timeout(ANSWER_POLL) {
+            Ok(answer) if answer.conflict_id == conflict_id => return Some(answer),
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout) if !cancel.is_cancelled() => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Carry out `request`, reporting progress through `on_progress` and asking
+/// `ask` about conflicts under the Ask policy; `ask` returns None to cancel.
+/// Fails only when the request itself is invalid: problems with single
+/// entries are listed in the summary and the rest goes on.
+pub fn run(
+    request: TransferRequest,
+    cancel: &CancelToken,
+    on_progress: &mut dyn FnMut(&TransferProgress),
+    ask: &mut dyn FnMut(&TransferConflict) -> Option<ConflictAnswer>,
+) -> Result<TransferSummary, AppError> {
+    validate(&request)?;
+    let mut worker = Worker {
+        kind: request.kind,
+        policy: request.policy,
+        cancel,
+        progress: TransferProgress {
+            transfer_id: request.id.clone(),
+            bytes_done: 0,
+            bytes_total: 0,
+            items_done: 0,
+            items_total: 0,
+            current: String::new(),
+        },
+        last_report: Instant::now(),
+        on_progress,
+        ask,
+        next_conflict: 0,
+        summary: TransferSummary {
+            transfer_id: request.id.clone(),
+            transferred: Vec::new(),
+            skipped: Vec::new(),
+            failed: Vec::new(),
+            cancelled: false,
+        },
+    };
+    for source in &request.sources {
+        let (bytes, items) = measure(source);
+        worker.progress.bytes_total += bytes;
+        worker.progress.items_total += items;
+    }
+
+    for source in &request.sources {
+        if worker.stopped() {
+            break;
+        }
+        let Some(name) = source.file_name() else {
+            continue;
+        };
+        let target = request.destination.join(name);
+        if let Some(done) = worker.transfer(source, &target) {
+            worker.summary.transferred.push(TransferredEntry {
+                from: source.to_string_lossy().to_string(),
+                to: done.path.to_string_lossy().to_string(),
+                complete: done.complete,
+            });
+        }
+    }
+    worker.report(true);
+    let summary = worker.summary;
+    log::info!(
+        "{:?} {} done: {} transferred, {} skipped, {} failed{}",
+        request.kind,
+        request.id,
+        summary.transferred.len(),
+        summary.skipped.len(),
+        summary.failed.len(),
+        if summary.cancelled { ", cancelled" } else { "" }
+    );
+    Ok(summary)
+}
+
+fn validate(request: &TransferRequest) -> Result<(), AppError> {
+    let destination = &request.destination;
+    let metadata =
+        fs::metadata(destination).map_err(|e| AppError::io(destination.to_string_lossy(), &e))?;
+    if !metadata.is_dir() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Not a folder")
+            .with_path(destination.to_string_lossy()));
+    }
+    if request.sources.is_empty() {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "Nothing to transfer",
+        ));
+    }
+    for source in &request.sources {
+        let source_str = source.to_string_lossy();
+        if !source.is_absolute() || source.file_name().is_none() {
+            return Err(
+                AppError::new(ErrorKind::InvalidInput, "Invalid source path").with_path(source_str),
+            );
+        }
+        let metadata = fs::symlink_metadata(source).map_err(|e| AppError::io(source_str, &e))?;
+        if metadata.is_dir() && destination.starts_with(source) {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                "A folder can't be copied or moved into itself",
+            )
+            .with_path(source.to_string_lossy()));
+        }
+    }
+    Ok(())
+}
+
+/// Bytes of the files and count of the entries in the tree at `path`
+fn measure(path: &Path) -> (u64, u64) {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .fold((0, 0), |(bytes, items), entry| {
+            let size = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata.len(),
+                _ => 0,
+            };
+            (bytes + size, items + 1)
+        })
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    let secs = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// Where an entry was transferred to
+struct Done {
+    path: PathBuf,
+    complete: bool,
+}
+
+struct Worker<'a> {
+    kind: TransferKind,
+    policy: OverwritePolicy,
+    cancel: &'a CancelToken,
+    progress: TransferProgress,
+    last_report: Instant,
+    on_progress: &'a mut dyn FnMut(&TransferProgress),
+    ask: &'a mut dyn FnMut(&TransferConflict) -> Option<ConflictAnswer>,
+    next_conflict: u64,
+    summary: TransferSummary,
+}
+
+impl Worker<'_> {
+    fn stopped(&mut self) -> bool {
+        if self.cancel.is_cancelled() {
+            self.summary.cancelled = true;
+        }
+        self.summary.cancelled
+    }
+
+    fn report(&mut self, force: bool) {
+        if force || self.last_report.elapsed() >= PROGRESS_INTERVAL {
+            self.last_report = Instant::now();
+            (self.on_progress)(&self.progress);
+        }
+    }
+
+    /// Count the tree at `path` as done without transferring it
+    fn pass_over(&mut self, path: &Path) {
+        let (bytes, items) = measure(path);
+        self.progress.bytes_done += bytes;
+        self.progress.items_done += items;
+    }
+
+    fn fail(&mut self, path: &Path, err: &std::io::Error) {
+        log::warn!("Failed to transfer {}: {}", path.display(), err);
+        self.summary
+            .failed
+            .push(AppError::io(path.to_string_lossy(), err));
+    }
+
+    /// Transfer the entry at `source` to `target`, or to another name the
+    /// conflict policy picks. None when nothing of it was transferred.
+    fn transfer(&mut self, source: &Path, target: &Path) -> Option<Done> {
+        if self.stopped() {
+            return None;
+        }
+        self.progress.current = source.to_string_lossy().to_string();
+        self.report(false);
+        let metadata = match fs::symlink_metadata(source) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                self.fail(source, &e);
+                return None;
+            }
+        };
+        let existing = fs::symlink_metadata(target).ok();
+        if metadata.is_dir() && existing.as_ref().is_some_and(|e| e.is_dir()) {
+            if source == target {
+                // Moving a folder where it already is
+                self.pass_over(source);
+                return None;
+            }
+            return self.merge(source, target);
+        }
+
+        let target = match existing {
+            None => target.to_path_buf(),
+            // Copying into the folder the entry is in keeps both
+            Some(_) if source == target && self.kind == TransferKind::Copy => numbered(target),
+            Some(_) if source == target => {
+                self.pass_over(source);
+                return None;
+            }
+            Some(existing) => match self.resolve(source, &metadata, target, &existing)? {
+                ConflictChoice::Skip => {
+                    self.summary
+                        .skipped
+                        .push(source.to_string_lossy().to_string());
+                    self.pass_over(source);
+                    return None;
+                }
+                ConflictChoice::Rename => numbered(target),
+                ConflictChoice::Replace => {
+                    // Files are replaced by renaming over them once written
+                    let removed = if existing.is_dir() {
+                        fs::remove_dir_all(target)
+                    } else if metadata.is_dir() {
+                        fs::remove_file(target)
+                    } else {
+                        Ok(())
+                    };
+                    if let Err(e) = removed {
+                        self.fail(target, &e);
+                        self.pass_over(source);
+                        return None;
+                    }
+                    target.to_path_buf()
+                }
+            },
+        };
+
+        if self.kind == TransferKind::Move && fs::rename(source, &target).is_ok() {
+            self.pass_over(&target);
+            return Some(Done {
+                path: target,
+                complete: true,
+            });
+        }
+        if metadata.is_dir() {
+            if let Err(e) = fs::create_dir(&target) {
+                self.fail(&target, &e);
+                self.pass_over(source);
+                return None;
+            }
+            return self.merge(source, &target);
+        }
+        match self.copy_file(source, &metadata, &target) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => {
+                self.fail(source, &e);
+                return None;
+            }
+        }
+        if self.kind == TransferKind::Move {
+            if let Err(e) = fs::remove_file(source) {
+                self.fail(source, &e);
+                return Some(Done {
+                    path: target,
+                    complete: false,
+                });
+            }
+        }
+        Some(Done {
+            path: target,
+            complete: true,
+        })
+    }
+
+    /// Transfer the children of the folder `source` into the folder `target`
+    fn merge(&mut self, source: &Path, target: &Path) -> Option<Done> {
+        self.progress.items_done += 1;
+        let children = match fs::read_dir(source) {
+            Ok(children) => children,
+            Err(e) => {
+                self.fail(source, &e);
+                return None;
+            }
+        };
+        let mut complete = true;
+        for child in children {
+            let child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    self.fail(source, &e);
+                    complete = false;
+                    continue;
+                }
+            };
+            let done = self.transfer(&child.path(), &target.join(child.file_name()));
+            complete &= done.is_some_and(|done| done.complete);
+            if self.stopped() {
+                complete = false;
+                break;
+            }
+        }
+        if complete && self.kind == TransferKind::Move {
+            if let Err(e) = fs::remove_dir(source) {
+                self.fail(source, &e);
+                complete = false;
+            }
+        }
+        Some(Done {
+            path: target.to_path_buf(),
+            complete,
+        })
+    }
+
+    /// Settle the conflict of `source` with the entry at `target` under the
+    /// policy, asking when it says so. None when the transfer was cancelled.
+    fn resolve(
+        &mut self,
+        source: &Path,
+        metadata: &fs::Metadata,
+        target: &Path,
+        existing: &fs::Metadata,
+    ) -> Option<ConflictChoice> {
+        match self.policy {
+            OverwritePolicy::Skip => Some(ConflictChoice::Skip),
+            OverwritePolicy::Replace => Some(ConflictChoice::Replace),
+            OverwritePolicy::Rename => Some(ConflictChoice::Rename),
+            OverwritePolicy::Ask => {
+                self.next_conflict += 1;
+                let conflict = TransferConflict {
+                    transfer_id: self.progress.transfer_id.clone(),
+                    conflict_id: self.next_conflict,
+                    source: source.to_string_lossy().to_string(),
+                    destination: target.to_string_lossy().to_string(),
+                    source_size: metadata.len(),
+                    source_modified: modified_secs(metadata),
+                    destination_size: existing.len(),
+                    destination_modified: modified_secs(existing),
+                    destination_is_folder: existing.is_dir(),
+                };
+                self.report(true);
+                let Some(answer) = (self.ask)(&conflict) else {
+                    self.summary.cancelled = true;
+                    return None;
+                };
+                if answer.apply_to_all {
+                    self.policy = match answer.choice {
+                        ConflictChoice::Skip => OverwritePolicy::Skip,
+                        ConflictChoice::Replace => OverwritePolicy::Replace,
+                        ConflictChoice::Rename => OverwritePolicy::Rename,
+                    };
+                }
+                Some(answer.choice)
+            }
+        }
+    }
+
+    /// Copy a file or link to `target` through a temporary file next to it,
+    /// so a replaced file stays intact until the copy is complete. False when
+    /// cancelled midway, leaving nothing behind.
+    fn copy_file(
+        &mut self,
+        source: &Path,
+        metadata: &fs::Metadata,
+        target: &Path,
+    ) -> std::io::Result<bool> {
+        let name = target.file_name().unwrap_or_default().to_string_lossy();
+        let partial = target.with_file_name(format!(".{}.partial", name));
+        if metadata.file_type().is_symlink() && copy_link(source, &partial)? {
+            fs::rename(&partial, target)?;
+            self.progress.items_done += 1;
+            return Ok(true);
+        }
+
+        let mut reader = fs::File::open(source)?;
+        let mut writer = fs::File::create(&partial)?;
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let copied = loop {
+            if self.stopped() {
+                break Ok(false);
+            }
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break Ok(true),
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => break Err(e),
+            };
+            if let Err(e) = writer.write_all(&buffer[..read]) {
+                break Err(e);
+            }
+            self.progress.bytes_done += read as u64;
+            self.report(false);
+        };
+        let finished = copied.and_then(|copied| {
+            writer.sync_all()?;
+            drop(writer);
+            if copied {
+                fs::set_permissions(&partial, metadata.permissions())?;
+                fs::rename(&partial, target)?;
+            }
+            Ok(copied)
+        });
+        if matches!(finished, Ok(true)) {
+            self.progress.items_done += 1;
+        } else {
+            let _ = fs::remove_file(&partial);
+        }
+        finished
+    }
+}
+
+/// Recreate the link at `source` at `target`. False where links are copied
+/// as the file they point to instead.
+#[cfg(unix)]
+fn copy_link(source: &Path, target: &Path) -> std::io::Result<bool> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)?;
+    Ok(true)
+}
+
+/// Creating links takes a privilege on Windows
+#[cfg(not(unix))]
+fn copy_link(_source: &Path, _target: &Path) -> std::io::Result<bool> {
+    Ok(false)
+}
+
+/// Free "Name (2).ext" style name next to `target`
+fn numbered(target: &Path) -> PathBuf {
+    let parent = target.parent().unwrap_or(Path::new(""));
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    crate::explorer::unique_child(parent, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(
+        kind: TransferKind,
+        sources: &[PathBuf],
+        to: &Path,
+        policy: OverwritePolicy,
+    ) -> TransferRequest {
+        TransferRequest {
+            id: "t1".to_string(),
+            kind,
+            sources: sources.to_vec(),
+            destination: to.to_path_buf(),
+            policy,
+        }
+    }
+
+    fn run_quietly(request: TransferRequest) -> TransferSummary {
+        run(request, &CancelToken::default(), &mut |_| {}, &mut |_| {
+            panic!("No conflict expected")
+        })
+        .unwrap()
+    }
+
+    /// Source folder with album/a.jpg and album/sub/b.jpg, and a destination
+    /// folder already holding album/a.jpg
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let album = dir.path().join("src").join("album");
+        fs::create_dir_all(album.join("sub")).unwrap();
+        fs::write(album.join("a.jpg"), "new a").unwrap();
+        fs::write(album.join("sub").join("b.jpg"), "b").unwrap();
+        let destination = dir.path().join("dst");
+        fs::create_dir_all(destination.join("album")).unwrap();
+        fs::write(destination.join("album").join("a.jpg"), "old a").unwrap();
+        (dir, album, destination)
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_copy_policies() {
+        let (_dir, album, destination) = setup();
+        let copied = destination.join("album");
+
+        let summary = run_quietly(request(
+            TransferKind::Copy,
+            std::slice::from_ref(&album),
+            &destination,
+            OverwritePolicy::Skip,
+        ));
+        assert_eq!(read(&copied.join("a.jpg")), "old a");
+        assert_eq!(read(&copied.join("sub").join("b.jpg")), "b");
+        assert_eq!(
+            summary.skipped,
+            vec![album.join("a.jpg").to_string_lossy().to_string()]
+        );
+        assert!(!summary.transferred[0].complete);
+
+        let summary = run_quietly(request(
+            TransferKind::Copy,
+            std::slice::from_ref(&album),
+            &destination,
+            OverwritePolicy::Rename,
+        ));
+        assert_eq!(read(&copied.join("a.jpg")), "old a");
+        assert_eq!(read(&copied.join("a (2).jpg")), "new a");
+        assert_eq!(read(&copied.join("sub").join("b (2).jpg")), "b");
+        assert!(summary.transferred[0].complete);
+
+        run_quietly(request(
+            TransferKind::Copy,
+            std::slice::from_ref(&album),
+            &destination,
+            OverwritePolicy::Replace,
+        ));
+        assert_eq!(read(&copied.join("a.jpg")), "new a");
+        assert!(album.join("a.jpg").exists(), "Copies keep the source");
+        let leftovers = fs::read_dir(&copied)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".partial")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let summary = run_quietly(request(
+            TransferKind::Copy,
+            &[album.join("a.jpg")],
+            &album,
+            OverwritePolicy::Replace,
+        ));
+        assert_eq!(
+            summary.transferred[0].to,
+            album.join("a (2).jpg").to_string_lossy()
+        );
+        assert_eq!(
+            read(&album.join("a.jpg")),
+            "new a",
+            "Copying in place keeps both"
+        );
+    }
+
+    #[test]
+    fn test_move_merges_and_asks() {
+        let (dir, album, destination) = setup();
+        let mut asked = Vec::new();
+        let mut progress = None;
+        let summary = run(
+            request(
+                TransferKind::Move,
+                std::slice::from_ref(&album),
+                &destination,
+                OverwritePolicy::Ask,
+            ),
+            &CancelToken::default(),
+            &mut |p| progress = Some(p.clone()),
+            &mut |conflict| {
+                asked.push(conflict.destination.clone());
+                Some(ConflictAnswer {
+                    conflict_id: conflict.conflict_id,
+                    choice: ConflictChoice::Replace,
+                    apply_to_all: false,
+                })
+            },
+        )
+        .unwrap();
+        let moved = destination.join("album");
+        assert_eq!(
+            asked,
+            vec![moved.join("a.jpg").to_string_lossy().to_string()]
+        );
+        assert_eq!(read(&moved.join("a.jpg")), "new a");
+        assert_eq!(read(&moved.join("sub").join("b.jpg")), "b");
+        assert!(!album.exists(), "Merged source folder is removed");
+        assert!(summary.transferred[0].complete);
+        let progress = progress.unwrap();
+        assert_eq!(progress.items_done, progress.items_total);
+        assert_eq!(progress.bytes_done, progress.bytes_total);
+
+        fs::write(dir.path().join("c.txt"), "c").unwrap();
+        fs::write(destination.join("c.txt"), "old c").unwrap();
+        let summary = run(
+            request(
+                TransferKind::Move,
+                &[dir.path().join("c.txt")],
+                &destination,
+                OverwritePolicy::Ask,
+            ),
+            &CancelToken::default(),
+            &mut |_| {},
+            &mut |_| None,
+        )
+        .unwrap();
+        assert!(summary.cancelled);
+        assert!(summary.transferred.is_empty());
+        assert_eq!(read(&destination.join("c.txt")), "old c");
+        assert!(dir.path().join("c.txt").exists());
+    }
+
+    #[test]
+    fn test_rejects_invalid_requests() {
+        let (_dir, album, destination) = setup();
+        let into_itself = request(
+            TransferKind::Move,
+            std::slice::from_ref(&album),
+            &album.join("sub"),
+            OverwritePolicy::Skip,
+        );
+        assert!(run(
+            into_itself,
+            &CancelToken::default(),
+            &mut |_| {},
+            &mut |_| None
+        )
+        .is_err());
+        let missing = request(
+            TransferKind::Copy,
+            &[album.join("gone.jpg")],
+            &destination,
+            OverwritePolicy::Skip,
+        );
+        assert!(run(missing, &CancelToken::default(), &mut |_| {}, &mut |_| None).is_err());
+
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let summary = run(
+            request(
+                TransferKind::Copy,
+                std::slice::from_ref(&album),
+                &destination,
+                OverwritePolicy::Replace,
+            ),
+            &cancel,
+            &mut |_| {},
+            &mut |_| None,
+        )
+        .unwrap();
+        assert!(summary.cancelled);
+        assert_eq!(read(&destination.join("album").join("a.jpg")), "old a");
+    }
+
+    #[test]
+    fn test_wait_for_answer_skips_stale_answers() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let answer = |conflict_id| ConflictAnswer {
+            conflict_id,
+            choice: ConflictChoice::Skip,
+            apply_to_all: false,
+        };
+        sender.send(answer(1)).unwrap();
+        sender.send(answer(2)).unwrap();
+        let cancel = CancelToken::default();
+        assert_eq!(wait_for_answer(&receiver, 2, &cancel), Some(answer(2)));
+        cancel.cancel();
+        assert_eq!(wait_for_answer(&receiver, 3, &cancel), None);
+    }
+}
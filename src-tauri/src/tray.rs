@@ -0,0 +1,268 @@
+// System tray icon: live index status plus quick actions
+
+mod badge;
+
+use crate::i18n::{tr, trf};
+use crate::AppState;
+use badge::Badge;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use tauri::image::Image;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIcon;
+use tauri::{AppHandle, Manager, Wry};
+
+const TOOLTIP: &str = "CrossEverything";
+
+/// The menu bar tints template icons to match light or dark mode
+const TEMPLATE_ICON: bool = cfg!(target_os = "macos");
+
+/// App icon pixels the tray icon is drawn from
+struct BaseIcon {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl BaseIcon {
+    fn render(&self, badge: Badge) -> Image<'static> {
+        let rgba = badge::render(&self.rgba, self.width, self.height, badge, TEMPLATE_ICON);
+        Image::new_owned(rgba, self.width, self.height)
+    }
+}
+
+/// Menu items updated after the tray is built
+struct TrayMenu {
+    status: MenuItem<Wry>,
+    show: MenuItem<Wry>,
+    new_window: MenuItem<Wry>,
+    rebuild: MenuItem<Wry>,
+    pause: CheckMenuItem<Wry>,
+    quit: MenuItem<Wry>,
+    /// Text last shown by `status`, so unchanged states skip the update
+    shown: Mutex<String>,
+    tray: TrayIcon<Wry>,
+    base: Option<BaseIcon>,
+    /// Badge on the icon now
+    badge: Mutex<Badge>,
+}
+
+/// Text of the status item
+fn status_text(indexing: bool, processed: usize, ready: bool, total: usize) -> String {
+    if indexing {
+        trf("Indexing... {} files", &[&processed])
+    } else if ready {
+        trf("{} files indexed", &[&total])
+    } else {
+        tr("No index")
+    }
+}
+
+pub fn create(app: &tauri::App) -> tauri::Result<()> {
+    let status = MenuItem::with_id(app, "status", tr("No index"), false, None::<&str>)?;
+    let show_item = MenuItem::with_id(app, "show", tr("Show"), true, None::<&str>)?;
+    let new_window_item = MenuItem::with_id(
+        app,
+        "new_window",
+        tr("New search window"),
+        true,
+        None::<&str>,
+    )?;
+    let rebuild_item = MenuItem::with_id(app, "rebuild", tr("Rebuild index"), true, None::<&str>)?;
+    let pause =
+        CheckMenuItem::with_id(app, "pause", tr("Pause watcher"), true, false, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", tr("Quit"), true, None::<&str>)?;
+
+    let menu = Menu::with_items(
+        app,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app)?,
+            &show_item,
+            &new_window_item,
+            &rebuild_item,
+            &pause,
+            &PredefinedMenuItem::separator(app)?,
+            &quit_item,
+        ],
+    )?;
+
+    let base = app.default_window_icon().map(|icon| BaseIcon {
+        rgba: icon.rgba().to_vec(),
+        width: icon.width(),
+        height: icon.height(),
+    });
+    let mut tray_builder = tauri::tray::TrayIconBuilder::new()
+        .tooltip(TOOLTIP)
+        .menu(&menu);
+
+    // Set icon if available
+    if let Some(base) = &base {
+        tray_builder = tray_builder
+            .icon(base.render(Badge::None))
+            .icon_as_template(TEMPLATE_ICON);
+    }
+
+    let tray = tray_builder
+        .on_menu_event(move |app, event| match event.id.as_ref() {
+            "show" => crate::show_main_window(app),
+            "new_window" => {
+                if let Err(e) = crate::open_search_window(app, None) {
+                    log::error!("Failed to open search window: {}", e);
+                }
+            }
+            "rebuild" => rebuild_index(app.clone()),
+            "pause" => toggle_watcher_pause(app),
+            "quit" => {
+                app.exit(0);
+            }
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let tauri::tray::TrayIconEvent::Click {
+                button: tauri::tray::MouseButton::Left,
+                ..
+            } = event
+            {
+                crate::show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+
+    app.manage(TrayMenu {
+        status,
+        show: show_item,
+        new_window: new_window_item,
+        rebuild: rebuild_item,
+        pause,
+        quit: quit_item,
+        shown: Mutex::new(String::new()),
+        tray,
+        base,
+        badge: Mutex::new(Badge::None),
+    });
+    Ok(())
+}
+
+/// Rebuild the active profile's roots as build_index would, reporting
+/// through the same events
+fn rebuild_index(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        match crate::build_index(Vec::new(), true, None, app.clone(), state).await {
+            Ok(summary) if summary["status"] == "failed" => {
+                log::warn!("Rebuild from tray not started: {}", summary["errors"])
+            }
+            Ok(_) => log::info!("Rebuild from tray finished"),
+            Err(e) => log::error!("Rebuild from tray failed: {}", e),
+        }
+    });
+}
+
+fn toggle_watcher_pause(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let paused = !state.watcher_paused.load(Ordering::SeqCst);
+    state.watcher_paused.store(paused, Ordering::SeqCst);
+    if let Some(menu) = app.try_state::<TrayMenu>() {
+        let _ = menu.pause.set_checked(paused);
+    }
+    log::info!(
+        "Watcher {}",
+        if paused {
+            "paused; changes are held until resumed"
+        } else {
+            "resumed"
+        }
+    );
+}
+
+/// Show the menu in the current locale; the status item follows on the next
+/// `refresh`
+pub fn relabel(app: &AppHandle) {
+    let Some(menu) = app.try_state::<TrayMenu>() else {
+        return;
+    };
+    let labels = [
+        (&menu.show, "Show"),
+        (&menu.new_window, "New search window"),
+        (&menu.rebuild, "Rebuild index"),
+        (&menu.quit, "Quit"),
+    ];
+    for (item, label) in labels {
+        if let Err(e) = item.set_text(tr(label)) {
+            log::warn!("Failed to relabel tray item: {}", e);
+        }
+    }
+    if let Err(e) = menu.pause.set_text(tr("Pause watcher")) {
+        log::warn!("Failed to relabel tray item: {}", e);
+    }
+    if let Ok(mut shown) = menu.shown.lock() {
+        shown.clear();
+    };
+}
+
+/// Bring the status item, tooltip and icon badge up to date with the index
+/// state
+pub async fn refresh(app: &AppHandle, state: &AppState) {
+    let Some(menu) = app.try_state::<TrayMenu>() else {
+        return;
+    };
+    let indexing = state.is_indexing.load(Ordering::SeqCst);
+    let text = status_text(
+        indexing,
+        state.index_progress.load(Ordering::SeqCst),
+        state.search_index.read().await.is_some(),
+        *state.total_files.read().await,
+    );
+    let watcher_failed = state
+        .file_watcher
+        .lock()
+        .await
+        .as_ref()
+        .is_some_and(|watcher| !watcher.is_healthy());
+    let badge = badge::pick(
+        state.index_failed.load(Ordering::SeqCst) || watcher_failed,
+        indexing,
+        state.watcher_paused.load(Ordering::SeqCst),
+    );
+
+    if let Ok(mut shown) = menu.shown.lock() {
+        if *shown != text {
+            if let Err(e) = menu.status.set_text(&text) {
+                log::warn!("Failed to update tray status: {}", e);
+            }
+            let tooltip = format!("{} - {}", TOOLTIP, text);
+            if let Err(e) = menu.tray.set_tooltip(Some(tooltip)) {
+                log::warn!("Failed to update tray tooltip: {}", e);
+            }
+            *shown = text;
+        }
+    }
+    let Ok(mut shown_badge) = menu.badge.lock() else {
+        return;
+    };
+    if *shown_badge != badge {
+        if let Some(base) = &menu.base {
+            let updated = menu
+                .tray
+                .set_icon(Some(base.render(badge)))
+                .and_then(|()| menu.tray.set_icon_as_template(TEMPLATE_ICON));
+            if let Err(e) = updated {
+                log::warn!("Failed to update tray icon: {}", e);
+            }
+        }
+        *shown_badge = badge;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_text() {
+        assert_eq!(status_text(true, 1200, true, 50), "Indexing... 1200 files");
+        assert_eq!(status_text(false, 0, true, 50), "50 files indexed");
+        assert_eq!(status_text(false, 0, false, 0), "No index");
+    }
+}
@@ -0,0 +1,118 @@
+// Tray icon drawn from the app icon: a mono template on macOS, so the menu bar
+// tints it for light and dark mode, plus a corner badge for the index state.
+// Badges differ in shape as well as color, since templates keep only alpha.
+
+/// State shown in the corner of the icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    None,
+    /// Building the index; a ring
+    Indexing,
+    /// Watcher paused; a square
+    Paused,
+    /// Last build failed or the watcher lost events; a dot
+    Error,
+}
+
+impl Badge {
+    fn color(self) -> [u8; 3] {
+        match self {
+            Badge::None => [0, 0, 0],
+            Badge::Indexing => [0x3b, 0x82, 0xf6],
+            Badge::Paused => [0xf5, 0x9e, 0x0b],
+            Badge::Error => [0xef, 0x44, 0x44],
+        }
+    }
+}
+
+/// Worst state first, so an error is never hidden behind activity
+pub fn pick(failed: bool, indexing: bool, paused: bool) -> Badge {
+    if failed {
+        Badge::Error
+    } else if indexing {
+        Badge::Indexing
+    } else if paused {
+        Badge::Paused
+    } else {
+        Badge::None
+    }
+}
+
+/// `base` RGBA pixels with `badge` drawn in the bottom right corner, turned
+/// black with the original alpha when `template`
+pub fn render(base: &[u8], width: u32, height: u32, badge: Badge, template: bool) -> Vec<u8> {
+    let mut rgba = base.to_vec();
+    if template {
+        for pixel in rgba.chunks_exact_mut(4) {
+            pixel[..3].copy_from_slice(&[0, 0, 0]);
+        }
+    }
+    if badge == Badge::None {
+        return rgba;
+    }
+    let size = width.min(height) as f32;
+    let radius = size * 0.22;
+    // Cleared around the badge so it stands apart from the icon
+    let gap = radius * 0.35;
+    let (cx, cy) = (width as f32 - radius - 1.0, height as f32 - radius - 1.0);
+    let color = if template { [0, 0, 0] } else { badge.color() };
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let distance = match badge {
+                Badge::Paused => dx.abs().max(dy.abs()),
+                _ => (dx * dx + dy * dy).sqrt(),
+            };
+            if distance > radius + gap {
+                continue;
+            }
+            let inside =
+                distance <= radius && !(badge == Badge::Indexing && distance < radius * 0.55);
+            let offset = ((y * width + x) * 4) as usize;
+            let pixel = &mut rgba[offset..offset + 4];
+            if inside {
+                pixel[..3].copy_from_slice(&color);
+                pixel[3] = 255;
+            } else {
+                // The gap and the hole of the ring are see-through
+                pixel[3] = 0;
+            }
+        }
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * width + x) * 4) as usize;
+        rgba[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_render_badges() {
+        let (width, height) = (32, 32);
+        let base = [10u8, 20, 30, 255].repeat((width * height) as usize);
+
+        assert_eq!(render(&base, width, height, Badge::None, false), base);
+        let template = render(&base, width, height, Badge::None, true);
+        assert_eq!(pixel(&template, width, 0, 0), [0, 0, 0, 255]);
+
+        // Badge center is near (24, 24); the top left keeps the icon
+        let error = render(&base, width, height, Badge::Error, false);
+        assert_eq!(pixel(&error, width, 24, 24), [0xef, 0x44, 0x44, 255]);
+        assert_eq!(pixel(&error, width, 0, 0), [10, 20, 30, 255]);
+        let indexing = render(&base, width, height, Badge::Indexing, false);
+        assert_eq!(pixel(&indexing, width, 24, 24)[3], 0);
+        assert_eq!(pixel(&indexing, width, 24, 29), [0x3b, 0x82, 0xf6, 255]);
+        let paused = render(&base, width, height, Badge::Paused, true);
+        assert_eq!(pixel(&paused, width, 24, 24), [0, 0, 0, 255]);
+        assert_eq!(pixel(&paused, width, 24, 16)[3], 0);
+
+        assert_eq!(pick(true, true, true), Badge::Error);
+        assert_eq!(pick(false, true, true), Badge::Indexing);
+        assert_eq!(pick(false, false, false), Badge::None);
+    }
+}
@@ -0,0 +1,296 @@
+// Journal of the renames, moves and trashing done through the app, kept in
+// a sled DB of its own so they can be undone and redone, also after a restart
+
+use crate::error::{AppError, ErrorKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Folder of the journal DB inside the app data directory
+const JOURNAL_DB: &str = "operations";
+
+/// Most operations kept for undoing; older ones are forgotten
+pub const MAX_OPERATIONS: usize = 100;
+
+/// Seconds a trash entry's deletion time may be earlier than the time the
+/// trashing was recorded at, as trash implementations round it down
+const TRASH_TIME_SLACK: i64 = 2;
+
+/// An entry renamed or moved from one path to another
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Move {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Operation {
+    /// Entries renamed or moved, in the order it was done
+    Rename { moves: Vec<Move> },
+    /// Entries moved to the trash at `time`, in Unix seconds
+    Trash { paths: Vec<String>, time: i64 },
+}
+
+/// The two stacks of the journal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stack {
+    Undo,
+    Redo,
+}
+
+impl Stack {
+    fn tree(self) -> &'static str {
+        match self {
+            Stack::Undo => "undo",
+            Stack::Redo => "redo",
+        }
+    }
+
+    fn other(self) -> Stack {
+        match self {
+            Stack::Undo => Stack::Redo,
+            Stack::Redo => Stack::Undo,
+        }
+    }
+}
+
+fn data_error(e: serde_json::Error) -> sled::Error {
+    sled::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+#[derive(Clone)]
+pub struct UndoJournal {
+    db: sled::Db,
+}
+
+impl UndoJournal {
+    /// Open the journal DB in `app_dir`, creating it on first use
+    pub fn open(app_dir: &Path) -> Result<UndoJournal, sled::Error> {
+        Ok(UndoJournal {
+            db: sled::open(app_dir.join(JOURNAL_DB))?,
+        })
+    }
+
+    /// Push `operation` onto `stack`, dropping the oldest entries past
+    /// MAX_OPERATIONS
+    fn push(&self, stack: Stack, operation: &Operation) -> Result<(), sled::Error> {
+        let tree = self.db.open_tree(stack.tree())?;
+        let value = serde_json::to_vec(operation).map_err(data_error)?;
+        tree.insert(self.db.generate_id()?.to_be_bytes(), value)?;
+        while tree.len() > MAX_OPERATIONS {
+            tree.pop_min()?;
+        }
+        Ok(())
+    }
+
+    /// Record an operation just done. What was undone before can no longer
+    /// be redone.
+    pub fn record(&self, operation: &Operation) -> Result<(), sled::Error> {
+        self.push(Stack::Undo, operation)?;
+        self.db.open_tree(Stack::Redo.tree())?.clear()?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Newest operation of `stack`, with its key for `shift` and `discard`
+    pub fn last(&self, stack: Stack) -> Result<Option<(sled::IVec, Operation)>, sled::Error> {
+        let Some((key, value)) = self.db.open_tree(stack.tree())?.last()? else {
+            return Ok(None);
+        };
+        let operation = serde_json::from_slice(&value).map_err(data_error)?;
+        Ok(Some((key, operation)))
+    }
+
+    /// Take the entry `key` off `stack` and push `operation`, what reverses
+    /// it, onto the other stack
+    pub fn shift(
+        &self,
+        stack: Stack,
+        key: &sled::IVec,
+        operation: &Operation,
+    ) -> Result<(), sled::Error> {
+        self.db.open_tree(stack.tree())?.remove(key)?;
+        self.push(stack.other(), operation)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Drop the entry `key` of `stack`, for operations that can't be
+    /// carried out anymore
+    pub fn discard(&self, stack: Stack, key: &sled::IVec) -> Result<(), sled::Error> {
+        self.db.open_tree(stack.tree())?.remove(key)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Move `from` to `to`, which must not exist. Falls back to a transfer when
+/// the two are on different volumes and the entry keeps its name.
+fn move_entry(from: &Path, to: &Path) -> Result<(), AppError> {
+    if to.symlink_metadata().is_ok() {
+        return Err(
+            AppError::new(ErrorKind::InvalidInput, "Another entry has taken its place")
+                .with_path(to.to_string_lossy()),
+        );
+    }
+    let renamed = match std::fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+    let (Some(destination), true) = (to.parent(), from.file_name() == to.file_name()) else {
+        return Err(AppError::io(from.to_string_lossy(), &renamed));
+    };
+    let request = crate::transfer::TransferRequest {
+        id: "undo".to_string(),
+        kind: crate::transfer::TransferKind::Move,
+        sources: vec![from.to_path_buf()],
+        destination: destination.to_path_buf(),
+        policy: crate::transfer::OverwritePolicy::Skip,
+    };
+    let summary = crate::transfer::run(
+        request,
+        &crate::search::CancelToken::default(),
+        &mut |_| {},
+        &mut |_| None,
+    )?;
+    match summary.failed.into_iter().next() {
+        Some(failure) => Err(failure),
+        None if summary.transferred.iter().all(|entry| entry.complete) => Ok(()),
+        None => Err(AppError::new(ErrorKind::Io, "Entry was only partly moved")
+            .with_path(from.to_string_lossy())),
+    }
+}
+
+/// Carry out `moves` again, or reverse them when `backwards`. Returns the
+/// (from, to) pairs done, in order. When one fails those done before it are
+/// reversed, so it's all or nothing.
+pub fn replay_moves(moves: &[Move], backwards: bool) -> Result<Vec<(PathBuf, PathBuf)>, AppError> {
+    let steps: Vec<(PathBuf, PathBuf)> = if backwards {
+        moves
+            .iter()
+            .rev()
+            .map(|m| (PathBuf::from(&m.to), PathBuf::from(&m.from)))
+            .collect()
+    } else {
+        moves
+            .iter()
+            .map(|m| (PathBuf::from(&m.from), PathBuf::from(&m.to)))
+            .collect()
+    };
+    for (done, (from, to)) in steps.iter().enumerate() {
+        if let Err(e) = move_entry(from, to) {
+            for (undo_from, undo_to) in steps[..done].iter().rev() {
+                if let Err(undo) = move_entry(undo_to, undo_from) {
+                    log::error!(
+                        "Failed to move {} back to {}: {}",
+                        undo_to.display(),
+                        undo_from.display(),
+                        undo
+                    );
+                }
+            }
+            return Err(e);
+        }
+    }
+    Ok(steps)
+}
+
+/// Restore from the trash what trashing `paths` at `time` put there: for
+/// each path its newest trash entry deleted since. Nothing is restored when
+/// one of them is gone from the trash.
+pub fn restore_trashed(paths: &[String], time: i64) -> Result<Vec<PathBuf>, AppError> {
+    let entries = crate::explorer::list_trash()?;
+    let ids = paths
+        .iter()
+        .map(|path| {
+            entries
+                .iter()
+                .find(|entry| {
+                    entry.original_path == *path && entry.time_deleted >= time - TRASH_TIME_SLACK
+                })
+                .map(|entry| entry.id.clone())
+                .ok_or_else(|| {
+                    AppError::new(ErrorKind::NotFound, "No longer in the trash").with_path(path)
+                })
+        })
+        .collect::<Result<Vec<String>, AppError>>()?;
+    crate::explorer::restore_from_trash(&ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn rename(from: &str, to: &str) -> Operation {
+        Operation::Rename {
+            moves: vec![Move {
+                from: from.to_string(),
+                to: to.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_journal_stacks() {
+        let temp_dir = tempdir().unwrap();
+        let journal = UndoJournal::open(temp_dir.path()).unwrap();
+        assert!(journal.last(Stack::Undo).unwrap().is_none());
+
+        journal.record(&rename("/a", "/b")).unwrap();
+        journal.record(&rename("/b", "/c")).unwrap();
+        let (key, operation) = journal.last(Stack::Undo).unwrap().unwrap();
+        assert_eq!(operation, rename("/b", "/c"));
+        journal.shift(Stack::Undo, &key, &operation).unwrap();
+        assert_eq!(
+            journal.last(Stack::Undo).unwrap().unwrap().1,
+            rename("/a", "/b")
+        );
+        assert_eq!(journal.last(Stack::Redo).unwrap().unwrap().1, operation);
+
+        // A new operation ends what could be redone
+        journal.record(&rename("/x", "/y")).unwrap();
+        assert!(journal.last(Stack::Redo).unwrap().is_none());
+
+        for i in 0..MAX_OPERATIONS + 5 {
+            journal.record(&rename(&format!("/{}", i), "/z")).unwrap();
+        }
+        let undo = journal.db.open_tree(Stack::Undo.tree()).unwrap();
+        assert_eq!(undo.len(), MAX_OPERATIONS);
+        let (key, _) = journal.last(Stack::Undo).unwrap().unwrap();
+        journal.discard(Stack::Undo, &key).unwrap();
+        assert_eq!(undo.len(), MAX_OPERATIONS - 1);
+    }
+
+    #[test]
+    fn test_replay_moves() {
+        let temp_dir = tempdir().unwrap();
+        let path = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+        std::fs::write(path("a.txt"), "a").unwrap();
+        std::fs::create_dir(path("sub")).unwrap();
+        let moves = vec![
+            Move {
+                from: path("a.txt"),
+                to: path("b.txt"),
+            },
+            Move {
+                from: path("b.txt"),
+                to: path("sub/b.txt"),
+            },
+        ];
+        std::fs::rename(path("a.txt"), path("sub/b.txt")).unwrap();
+
+        replay_moves(&moves, true).unwrap();
+        assert!(Path::new(&path("a.txt")).exists());
+        assert!(!Path::new(&path("sub/b.txt")).exists());
+        replay_moves(&moves, false).unwrap();
+        assert!(Path::new(&path("sub/b.txt")).exists());
+
+        // Undoing onto a name taken since changes nothing
+        std::fs::write(path("b.txt"), "new").unwrap();
+        let err = replay_moves(&moves, true).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidInput);
+        assert!(Path::new(&path("sub/b.txt")).exists());
+        assert!(!Path::new(&path("a.txt")).exists());
+    }
+}
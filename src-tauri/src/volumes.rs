@@ -0,0 +1,441 @@
+// Mounted volumes the user can pick as index roots: drive letters on Windows,
+// the mount table on Linux and macOS, with pseudo file systems left out
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Volume {
+    /// Where the volume is mounted, the path to index it by
+    pub mount_point: String,
+    pub label: Option<String>,
+    /// File system type, such as `NTFS`, `ext4` or `apfs`
+    pub file_system: Option<String>,
+    pub total_bytes: Option<u64>,
+    /// Space the user can still write to
+    pub free_bytes: Option<u64>,
+    /// USB sticks, memory cards and optical drives
+    pub removable: bool,
+    /// Network shares
+    pub network: bool,
+    /// A root of the active profile already; set by the command
+    pub indexed: bool,
+}
+
+/// Mounted volumes, sorted by mount point
+pub fn list() -> Vec<Volume> {
+    let mut volumes = sys::list();
+    volumes.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    volumes
+}
+
+/// One line of the Linux mount table
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq)]
+struct Mount {
+    device: String,
+    mount_point: String,
+    file_system: String,
+}
+
+/// File systems reached over the network
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const NETWORK_FILE_SYSTEMS: [&str; 11] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "sshfs",
+    "fuse.sshfs",
+    "davfs",
+    "afs",
+    "ceph",
+    "glusterfs",
+];
+
+/// Undo the octal escapes the mount table uses for spaces, tabs and the like
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes.get(i + 1..i + 4).and_then(|digits| {
+            std::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+        });
+        match code {
+            Some(code) if bytes[i] == b'\\' => {
+                out.push(code);
+                i += 4;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Volumes in `/proc/self/mounts` content: block devices and network shares.
+/// A device mounted in several places is listed once, at its shortest path.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_mounts(table: &str) -> Vec<Mount> {
+    let mut mounts: Vec<Mount> = Vec::new();
+    for line in table.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(device), Some(mount_point), Some(file_system)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let network = NETWORK_FILE_SYSTEMS.contains(&file_system);
+        // Snaps are read-only loop images
+        let block = device.starts_with("/dev/") && file_system != "squashfs";
+        if !network && !block {
+            continue;
+        }
+        let mount = Mount {
+            device: unescape_octal(device),
+            mount_point: unescape_octal(mount_point),
+            file_system: file_system.to_string(),
+        };
+        match mounts.iter_mut().find(|seen| seen.device == mount.device) {
+            Some(seen) if mount.mount_point.len() < seen.mount_point.len() => *seen = mount,
+            Some(_) => {}
+            None => mounts.push(mount),
+        }
+    }
+    mounts
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    pub fn list() -> Vec<Volume> {
+        let table = match std::fs::read_to_string("/proc/self/mounts") {
+            Ok(table) => table,
+            Err(e) => {
+                log::warn!("Failed to read the mount table: {}", e);
+                return Vec::new();
+            }
+        };
+        let labels = labels();
+        parse_mounts(&table)
+            .into_iter()
+            .map(|mount| {
+                let device = Path::new(&mount.device)
+                    .canonicalize()
+                    .unwrap_or_else(|_| PathBuf::from(&mount.device));
+                let (total_bytes, free_bytes) = space(&mount.mount_point).unzip();
+                Volume {
+                    label: labels.get(&device).cloned(),
+                    file_system: Some(mount.file_system.clone()),
+                    total_bytes,
+                    free_bytes,
+                    removable: is_removable(&device),
+                    network: NETWORK_FILE_SYSTEMS.contains(&mount.file_system.as_str()),
+                    mount_point: mount.mount_point,
+                    indexed: false,
+                }
+            })
+            .collect()
+    }
+
+    /// File system labels by device, from the names udev links them under
+    fn labels() -> HashMap<PathBuf, String> {
+        let Ok(entries) = std::fs::read_dir("/dev/disk/by-label") else {
+            return HashMap::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let device = entry.path().canonicalize().ok()?;
+                Some((device, unescape_hex(&entry.file_name().to_string_lossy())))
+            })
+            .collect()
+    }
+
+    /// Undo udev's `\x20` escapes
+    fn unescape_hex(name: &str) -> String {
+        let mut out = Vec::with_capacity(name.len());
+        let bytes = name.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let code = name
+                .get(i + 2..i + 4)
+                .filter(|_| bytes[i..].starts_with(b"\\x"))
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+            match code {
+                Some(code) => {
+                    out.push(code);
+                    i += 4;
+                }
+                None => {
+                    out.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&out).to_string()
+    }
+
+    /// Whether the disk holding `device` is removable or on USB
+    fn is_removable(device: &Path) -> bool {
+        let Some(name) = device.file_name() else {
+            return false;
+        };
+        let Ok(mut disk) = Path::new("/sys/class/block").join(name).canonicalize() else {
+            return false;
+        };
+        if disk.join("partition").exists() {
+            disk.pop();
+        }
+        let removable =
+            std::fs::read_to_string(disk.join("removable")).is_ok_and(|flag| flag.trim() == "1");
+        removable || disk.to_string_lossy().contains("/usb")
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    fn space(mount_point: &str) -> Option<(u64, u64)> {
+        use std::ffi::{c_char, c_int, c_ulong, CString};
+
+        /// The leading fields of `struct statvfs`, the same on every 64-bit
+        /// libc, padded past the size of the rest
+        #[repr(C)]
+        #[allow(dead_code)]
+        struct StatVfs {
+            f_bsize: c_ulong,
+            f_frsize: c_ulong,
+            f_blocks: u64,
+            f_bfree: u64,
+            f_bavail: u64,
+            _rest: [u64; 16],
+        }
+
+        extern "C" {
+            fn statvfs(path: *const c_char, buf: *mut StatVfs) -> c_int;
+        }
+
+        let path = CString::new(mount_point).ok()?;
+        // SAFETY: StatVfs is plain data; all-zero is a valid value
+        let mut stat: StatVfs = unsafe { std::mem::zeroed() };
+        // SAFETY: path is NUL-terminated and stat is larger than struct statvfs
+        if unsafe { statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        let unit = stat.f_frsize as u64;
+        Some((stat.f_blocks * unit, stat.f_bavail * unit))
+    }
+
+    #[cfg(not(target_pointer_width = "64"))]
+    fn space(_mount_point: &str) -> Option<(u64, u64)> {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use super::*;
+    use std::ffi::{c_char, c_int, CStr};
+
+    const MNT_NOWAIT: c_int = 2;
+    const MNT_REMOVABLE: u32 = 0x0000_0200;
+    const MNT_LOCAL: u32 = 0x0000_1000;
+    /// System volumes Finder does not show, such as /System/Volumes/VM
+    const MNT_DONTBROWSE: u32 = 0x0010_0000;
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct StatFs {
+        f_bsize: u32,
+        f_iosize: i32,
+        f_blocks: u64,
+        f_bfree: u64,
+        f_bavail: u64,
+        f_files: u64,
+        f_ffree: u64,
+        f_fsid: [i32; 2],
+        f_owner: u32,
+        f_type: u32,
+        f_flags: u32,
+        f_fssubtype: u32,
+        f_fstypename: [c_char; 16],
+        f_mntonname: [c_char; 1024],
+        f_mntfromname: [c_char; 1024],
+        f_flags_ext: u32,
+        f_reserved: [u32; 7],
+    }
+
+    extern "C" {
+        #[cfg_attr(target_arch = "x86_64", link_name = "getmntinfo$INODE64")]
+        fn getmntinfo(mntbufp: *mut *mut StatFs, flags: c_int) -> c_int;
+    }
+
+    fn text(chars: &[c_char]) -> String {
+        // SAFETY: the kernel NUL-terminates these fixed-size fields
+        unsafe { CStr::from_ptr(chars.as_ptr()) }
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn list() -> Vec<Volume> {
+        let mut mounts: *mut StatFs = std::ptr::null_mut();
+        // SAFETY: getmntinfo points mounts at a buffer it owns, valid until
+        // the next call on this thread
+        let count = unsafe { getmntinfo(&mut mounts, MNT_NOWAIT) };
+        if count <= 0 || mounts.is_null() {
+            log::warn!(
+                "Failed to read the mount table: {}",
+                std::io::Error::last_os_error()
+            );
+            return Vec::new();
+        }
+        // SAFETY: getmntinfo returned `count` entries at `mounts`
+        let mounts = unsafe { std::slice::from_raw_parts(mounts, count as usize) };
+        mounts
+            .iter()
+            .filter(|mount| mount.f_flags & MNT_DONTBROWSE == 0)
+            .filter(|mount| !matches!(text(&mount.f_fstypename).as_str(), "devfs" | "autofs"))
+            .map(|mount| {
+                let mount_point = text(&mount.f_mntonname);
+                let unit = mount.f_bsize as u64;
+                Volume {
+                    label: mount_point.strip_prefix("/Volumes/").map(str::to_string),
+                    file_system: Some(text(&mount.f_fstypename)),
+                    total_bytes: Some(mount.f_blocks * unit),
+                    free_bytes: Some(mount.f_bavail * unit),
+                    removable: mount.f_flags & MNT_REMOVABLE != 0,
+                    network: mount.f_flags & MNT_LOCAL == 0,
+                    mount_point,
+                    indexed: false,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::*;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDriveStringsW, GetVolumeInformationW,
+    };
+
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_REMOTE: u32 = 4;
+    const DRIVE_CDROM: u32 = 5;
+
+    fn text(buffer: &[u16]) -> Option<String> {
+        let end = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        (end > 0).then(|| String::from_utf16_lossy(&buffer[..end]))
+    }
+
+    pub fn list() -> Vec<Volume> {
+        let mut buffer = [0u16; 512];
+        // SAFETY: the buffer length passed is its size in characters
+        let len = unsafe { GetLogicalDriveStringsW(buffer.len() as u32, buffer.as_mut_ptr()) };
+        if len == 0 || len as usize > buffer.len() {
+            log::warn!("Failed to list drives: {}", std::io::Error::last_os_error());
+            return Vec::new();
+        }
+        // Roots such as "C:\", each NUL-terminated
+        buffer[..len as usize]
+            .split(|&c| c == 0)
+            .filter(|root| !root.is_empty())
+            .map(|root| {
+                let root: Vec<u16> = root.iter().copied().chain(Some(0)).collect();
+                drive(&root)
+            })
+            .collect()
+    }
+
+    /// Details of the drive at `root`, a NUL-terminated wide string. An empty
+    /// card reader or optical drive reports no label, file system or space.
+    fn drive(root: &[u16]) -> Volume {
+        // SAFETY: root is NUL-terminated
+        let drive_type = unsafe { GetDriveTypeW(root.as_ptr()) };
+        let mut label = [0u16; 261];
+        let mut file_system = [0u16; 261];
+        // SAFETY: the buffers are as long as the sizes passed; the optional
+        // outputs are null
+        let has_info = unsafe {
+            GetVolumeInformationW(
+                root.as_ptr(),
+                label.as_mut_ptr(),
+                label.len() as u32,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                file_system.as_mut_ptr(),
+                file_system.len() as u32,
+            )
+        } != 0;
+        let (mut free, mut total) = (0u64, 0u64);
+        // SAFETY: root is NUL-terminated and the outputs are valid u64s
+        let has_space = unsafe {
+            GetDiskFreeSpaceExW(root.as_ptr(), &mut free, &mut total, std::ptr::null_mut())
+        } != 0;
+        Volume {
+            mount_point: String::from_utf16_lossy(&root[..root.len() - 1]),
+            label: has_info.then(|| text(&label)).flatten(),
+            file_system: has_info.then(|| text(&file_system)).flatten(),
+            total_bytes: has_space.then_some(total),
+            free_bytes: has_space.then_some(free),
+            removable: matches!(drive_type, DRIVE_REMOVABLE | DRIVE_CDROM),
+            network: drive_type == DRIVE_REMOTE,
+            indexed: false,
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod sys {
+    use super::*;
+
+    pub fn list() -> Vec<Volume> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts() {
+        let table = "\
+sysfs /sys sysfs rw,nosuid 0 0
+proc /proc proc rw 0 0
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/nvme0n1p2 /var/lib/docker ext4 rw,relatime 0 0
+/dev/loop3 /snap/core/1 squashfs ro 0 0
+tmpfs /run tmpfs rw 0 0
+/dev/sdb1 /media/me/My\\040Stick vfat rw 0 0
+nas:/export /mnt/nas nfs4 rw 0 0
+";
+        let mounts = parse_mounts(table);
+        let points: Vec<&str> = mounts.iter().map(|m| m.mount_point.as_str()).collect();
+        assert_eq!(points, ["/", "/media/me/My Stick", "/mnt/nas"]);
+        assert_eq!(mounts[2].file_system, "nfs4");
+    }
+
+    #[test]
+    fn test_list_includes_root() {
+        let volumes = list();
+        if cfg!(any(target_os = "linux", target_os = "macos")) {
+            // Containers may mount / from an overlay rather than a device
+            if let Some(root) = volumes.iter().find(|v| v.mount_point == "/") {
+                assert!(root.total_bytes.is_some());
+            }
+        }
+        assert!(volumes
+            .windows(2)
+            .all(|pair| pair[0].mount_point <= pair[1].mount_point));
+    }
+}
@@ -1,32 +1,206 @@
 // File watching with notify
 
+use notify::event::{Flag, ModifyKind, RenameMode};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+#[cfg(any(target_os = "linux", test))]
+mod linux;
+#[cfg(any(target_os = "macos", test))]
+mod macos;
+mod share;
+#[cfg(any(windows, test))]
+mod windows;
+
+/// How often the background loop drains the watcher
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Quiet period a path needs before its coalesced change is released
+pub const DEFAULT_DEBOUNCE_WINDOW: Duration = Duration::from_millis(500);
+/// Range the debounce window may be configured in, in milliseconds
+pub const DEBOUNCE_MS_RANGE: std::ops::RangeInclusive<u64> = 50..=10_000;
+/// Minimum time between persisting the watcher's event journal position
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+/// Scan interval for subtrees that cannot get native watches
+#[cfg(target_os = "linux")]
+const POLL_FALLBACK_INTERVAL: Duration = Duration::from_secs(30);
+/// Share of the inotify limit left for other applications
+#[cfg(target_os = "linux")]
+const RESERVED_WATCH_FRACTION: usize = 10;
+
+/// Native backend used for recursive roots
+#[cfg(target_os = "linux")]
+const BACKEND: &str = "inotify";
+#[cfg(target_os = "macos")]
+const BACKEND: &str = "FSEvents";
+#[cfg(windows)]
+const BACKEND: &str = "ReadDirectoryChangesW";
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+const BACKEND: &str = "notify";
+
+/// A path that never goes quiet is still released after this many windows
+const MAX_DELAY_WINDOWS: u32 = 10;
+
+/// How often polled roots are checked unless configured otherwise
+pub const DEFAULT_SHARE_POLL_SECS: u64 = 60;
+/// Shortest interval a polled root may be checked at
+pub const MIN_SHARE_POLL_SECS: u64 = 5;
+
+/// How changes under a root are noticed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchStrategy {
+    /// The OS's change events
+    #[default]
+    Native,
+    /// Stat the root's folders on an interval, for network shares that send
+    /// no events; see `share`
+    Poll,
+}
+
+/// Persisted watch settings for one indexed root
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WatchConfig {
+    pub recursive: bool,
+    pub strategy: WatchStrategy,
+    /// Seconds between checks under the Poll strategy
+    pub poll_interval_secs: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            recursive: true,
+            strategy: WatchStrategy::Native,
+            poll_interval_secs: DEFAULT_SHARE_POLL_SECS,
+        }
+    }
+}
 
-#[allow(dead_code)] // Reserved for future file watcher integration
 pub struct FileWatcher {
     watcher: RecommendedWatcher,
     event_receiver: mpsc::Receiver<notify::Result<Event>>,
+    /// Recursive roots on Windows bypass notify's 16 KiB buffer and read
+    /// changes through their own large-buffer handle; on macOS they get their
+    /// own FSEvents stream so history can be replayed; on Linux they are
+    /// split to fit the inotify limit, with a poller for the remainder
+    #[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+    event_sender: mpsc::Sender<notify::Result<Event>>,
+    #[cfg(windows)]
+    volume_watches: HashMap<PathBuf, windows::VolumeWatch>,
+    #[cfg(target_os = "macos")]
+    root_streams: HashMap<PathBuf, macos::RootStream>,
+    #[cfg(target_os = "linux")]
+    split_watches: HashMap<PathBuf, linux::WatchPlan>,
+    #[cfg(target_os = "linux")]
+    poll_watcher: Option<notify::PollWatcher>,
+    #[cfg(target_os = "linux")]
+    watch_limit: Option<usize>,
+    /// Position in the system event journal reached by delivered events
+    /// (FSEvents ids on macOS, 0 where no journal is available)
+    event_id: Arc<AtomicU64>,
+    /// Journal position at which no changes were left waiting in the debouncer
+    checkpoint: Option<u64>,
     watched_paths: HashSet<PathBuf>,
+    /// Roots under the Poll strategy
+    share_roots: HashSet<PathBuf>,
+    /// Their polling state, taken out while a poll of them runs
+    idle_polls: HashMap<PathBuf, share::PolledRoot>,
+    debouncer: Debouncer,
+    /// Cleared when events were lost (queue overflow, backend error) and set
+    /// again once the affected subtrees have been re-scanned
+    healthy: AtomicBool,
 }
 
 impl FileWatcher {
-    #[allow(dead_code)] // Reserved for future file watcher integration
     pub fn new() -> Result<Self, notify::Error> {
         let (tx, rx) = mpsc::channel();
+        #[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+        let event_sender = tx.clone();
         let watcher = notify::recommended_watcher(tx)?;
 
         Ok(FileWatcher {
             watcher,
             event_receiver: rx,
+            #[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+            event_sender,
+            #[cfg(windows)]
+            volume_watches: HashMap::new(),
+            #[cfg(target_os = "macos")]
+            root_streams: HashMap::new(),
+            #[cfg(target_os = "linux")]
+            split_watches: HashMap::new(),
+            #[cfg(target_os = "linux")]
+            poll_watcher: None,
+            #[cfg(target_os = "linux")]
+            watch_limit: linux::max_user_watches(),
+            event_id: Arc::new(AtomicU64::new(0)),
+            checkpoint: None,
             watched_paths: HashSet::new(),
+            share_roots: HashSet::new(),
+            idle_polls: HashMap::new(),
+            debouncer: Debouncer::new(DEFAULT_DEBOUNCE_WINDOW),
+            healthy: AtomicBool::new(true),
         })
     }
 
-    #[allow(dead_code)] // Reserved for future file watcher integration
+    /// Watch `path` as `config` says
+    pub fn watch(&mut self, path: &Path, config: WatchConfig) -> Result<(), notify::Error> {
+        match config.strategy {
+            WatchStrategy::Native => self.watch_path(path, config.recursive),
+            WatchStrategy::Poll => {
+                if !path.is_dir() {
+                    return Err(notify::Error::path_not_found().add_path(path.to_path_buf()));
+                }
+                let interval = config.poll_interval_secs.max(MIN_SHARE_POLL_SECS);
+                self.idle_polls.insert(
+                    path.to_path_buf(),
+                    share::PolledRoot::new(path, Duration::from_secs(interval)),
+                );
+                self.share_roots.insert(path.to_path_buf());
+                self.watched_paths.insert(path.to_path_buf());
+                Ok(())
+            }
+        }
+    }
+
     pub fn watch_path(&mut self, path: &Path, recursive: bool) -> Result<(), notify::Error> {
+        #[cfg(windows)]
+        if recursive {
+            let watch = windows::VolumeWatch::start(path, true, self.event_sender.clone())?;
+            self.volume_watches.insert(path.to_path_buf(), watch);
+            self.watched_paths.insert(path.to_path_buf());
+            return Ok(());
+        }
+        #[cfg(target_os = "macos")]
+        if recursive {
+            let since = match self.event_id.load(Ordering::SeqCst) {
+                0 => None,
+                id => Some(id),
+            };
+            let stream = macos::RootStream::start(
+                path,
+                since,
+                self.event_sender.clone(),
+                self.event_id.clone(),
+            )?;
+            self.root_streams.insert(path.to_path_buf(), stream);
+            self.watched_paths.insert(path.to_path_buf());
+            return Ok(());
+        }
+        #[cfg(target_os = "linux")]
+        if recursive {
+            let plan = self.watch_split(path)?;
+            self.split_watches.insert(path.to_path_buf(), plan);
+            self.watched_paths.insert(path.to_path_buf());
+            return Ok(());
+        }
+
         let mode = if recursive {
             RecursiveMode::Recursive
         } else {
@@ -37,17 +211,183 @@ impl FileWatcher {
         Ok(())
     }
 
-    #[allow(dead_code)] // Reserved for future file watcher integration
     pub fn unwatch_path(&mut self, path: &Path) -> Result<(), notify::Error> {
         if !self.watched_paths.contains(path) {
             return Err(notify::Error::generic("Path is not being watched"));
         }
+        if self.share_roots.remove(path) {
+            self.idle_polls.remove(path);
+            self.watched_paths.remove(path);
+            return Ok(());
+        }
+        #[cfg(windows)]
+        if self.volume_watches.remove(path).is_some() {
+            self.watched_paths.remove(path);
+            return Ok(());
+        }
+        #[cfg(target_os = "macos")]
+        if self.root_streams.remove(path).is_some() {
+            self.watched_paths.remove(path);
+            return Ok(());
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(plan) = self.split_watches.remove(path) {
+            self.unwatch_split(&plan);
+            self.watched_paths.remove(path);
+            return Ok(());
+        }
         self.watcher.unwatch(path)?;
         self.watched_paths.remove(path);
         Ok(())
     }
 
-    #[allow(dead_code)] // Reserved for future file watcher integration
+    pub fn is_watching(&self, path: &Path) -> bool {
+        self.watched_paths.contains(path)
+    }
+
+    /// Take the polled roots whose interval has passed. Hand each back with
+    /// `finish_poll` once polled.
+    pub fn due_polls(&mut self, now: Instant) -> Vec<share::PolledRoot> {
+        let due: Vec<PathBuf> = self
+            .idle_polls
+            .iter()
+            .filter(|(_, polled)| polled.next_due <= now)
+            .map(|(root, _)| root.clone())
+            .collect();
+        due.iter()
+            .filter_map(|root| self.idle_polls.remove(root))
+            .collect()
+    }
+
+    /// Queue the changes a poll of `polled` found and schedule its next one,
+    /// unless the root was unwatched or watched anew meanwhile
+    pub fn finish_poll(&mut self, mut polled: share::PolledRoot, changes: Vec<FileChangeEvent>) {
+        let now = Instant::now();
+        for change in changes {
+            self.debouncer.push(change, now);
+        }
+        if self.share_roots.contains(&polled.root) && !self.idle_polls.contains_key(&polled.root) {
+            polled.next_due = now + polled.interval;
+            self.idle_polls.insert(polled.root.clone(), polled);
+        }
+    }
+
+    /// inotify watches still available to new roots
+    #[cfg(target_os = "linux")]
+    fn watch_budget(&self) -> usize {
+        let Some(limit) = self.watch_limit else {
+            return usize::MAX;
+        };
+        (limit - limit / RESERVED_WATCH_FRACTION).saturating_sub(self.watches_used())
+    }
+
+    /// Register a recursive root in pieces that fit the inotify budget.
+    /// Subtrees that do not fit, or whose registration fails, are polled.
+    #[cfg(target_os = "linux")]
+    fn watch_split(&mut self, root: &Path) -> Result<linux::WatchPlan, notify::Error> {
+        let plan = linux::plan_watches(root, self.watch_budget())
+            .map_err(|e| notify::Error::io(e).add_path(root.to_path_buf()))?;
+
+        let mut registered = linux::WatchPlan::default();
+        let mut polled = plan.polled;
+        for (path, dirs) in plan.recursive {
+            match self.watcher.watch(&path, RecursiveMode::Recursive) {
+                Ok(()) => registered.recursive.push((path, dirs)),
+                Err(e) => {
+                    // A partial recursive registration still holds watches
+                    log::warn!("Failed to watch '{}': {}", path.display(), e);
+                    let _ = self.watcher.unwatch(&path);
+                    polled.push(path);
+                }
+            }
+        }
+        for path in plan.single {
+            match self.watcher.watch(&path, RecursiveMode::NonRecursive) {
+                Ok(()) => registered.single.push(path),
+                Err(e) => {
+                    log::warn!("Failed to watch '{}': {}", path.display(), e);
+                    polled.push(path);
+                }
+            }
+        }
+
+        if !polled.is_empty() {
+            log::warn!(
+                "inotify watch limit reached under '{}'; polling {} subtree(s) instead",
+                root.display(),
+                polled.len()
+            );
+            if self.poll_watcher.is_none() {
+                let config = notify::Config::default().with_poll_interval(POLL_FALLBACK_INTERVAL);
+                self.poll_watcher =
+                    Some(notify::PollWatcher::new(self.event_sender.clone(), config)?);
+            }
+            let poller = self.poll_watcher.as_mut().unwrap();
+            for path in polled {
+                match poller.watch(&path, RecursiveMode::Recursive) {
+                    Ok(()) => registered.polled.push(path),
+                    Err(e) => log::warn!("Failed to poll '{}': {}", path.display(), e),
+                }
+            }
+        }
+        Ok(registered)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn unwatch_split(&mut self, plan: &linux::WatchPlan) {
+        let native = plan
+            .recursive
+            .iter()
+            .map(|(path, _)| path)
+            .chain(&plan.single);
+        for path in native {
+            let _ = self.watcher.unwatch(path);
+        }
+        if let Some(poller) = self.poll_watcher.as_mut() {
+            for path in &plan.polled {
+                let _ = poller.unwatch(path);
+            }
+        }
+    }
+
+    /// Kernel watches (or handles/streams) held for the watched roots
+    fn watches_used(&self) -> usize {
+        #[cfg(target_os = "linux")]
+        {
+            let split: usize = self.split_watches.values().map(|plan| plan.watches()).sum();
+            split + self.watched_paths.len() - self.split_watches.len()
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.watched_paths.len()
+        }
+    }
+
+    /// What the watcher covers and how close it is to OS limits
+    pub fn diagnostics(&self) -> WatcherDiagnostics {
+        let mut diagnostics = WatcherDiagnostics::idle();
+        diagnostics.watched_roots = self.watched_paths.len();
+        diagnostics.watches_used = self.watches_used();
+        diagnostics.healthy = self.is_healthy();
+        diagnostics.polled_roots = self
+            .share_roots
+            .iter()
+            .filter_map(|root| path_string(root))
+            .collect();
+        #[cfg(target_os = "linux")]
+        {
+            diagnostics.watch_limit = self.watch_limit;
+            diagnostics.polled_paths = self
+                .split_watches
+                .values()
+                .flat_map(|plan| &plan.polled)
+                .filter_map(|p| path_string(p))
+                .collect();
+        }
+        diagnostics
+    }
+
+    #[cfg(test)]
     pub fn try_recv(&self) -> Result<Option<Event>, mpsc::TryRecvError> {
         match self.event_receiver.try_recv() {
             Ok(Ok(event)) => Ok(Some(event)),
@@ -59,69 +399,439 @@ impl FileWatcher {
             Err(e) => Err(e),
         }
     }
+}
 
-    #[allow(dead_code)] // Reserved for future file watcher integration
-    pub fn recv(&self) -> Result<Option<Event>, mpsc::RecvError> {
-        match self.event_receiver.recv() {
-            Ok(Ok(event)) => Ok(Some(event)),
-            Ok(Err(e)) => {
-                log::error!("File watcher error: {:?}", e);
-                Ok(None)
-            }
-            Err(e) => Err(e),
+/// Snapshot reported by the `get_watcher_diagnostics` command
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherDiagnostics {
+    /// Native backend used for recursive roots
+    pub backend: &'static str,
+    /// fs.inotify.max_user_watches on Linux; None where no such limit applies
+    pub watch_limit: Option<usize>,
+    pub watches_used: usize,
+    pub watched_roots: usize,
+    /// Subtrees that could not get native watches and are polled instead
+    pub polled_paths: Vec<String>,
+    /// Roots set to be polled, for network shares without change events
+    pub polled_roots: Vec<String>,
+    pub healthy: bool,
+}
+
+impl WatcherDiagnostics {
+    /// Diagnostics while no watcher is running
+    pub fn idle() -> Self {
+        WatcherDiagnostics {
+            backend: BACKEND,
+            #[cfg(target_os = "linux")]
+            watch_limit: linux::max_user_watches(),
+            #[cfg(not(target_os = "linux"))]
+            watch_limit: None,
+            watches_used: 0,
+            watched_roots: 0,
+            polled_paths: Vec::new(),
+            polled_roots: Vec::new(),
+            healthy: true,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)] // Reserved for future file watcher integration
+#[derive(Debug, Clone, PartialEq)]
 pub enum FileChangeEvent {
     Created(String),
     Modified(String),
     Deleted(String),
+    /// Old and new path of a moved entry, so its index entry keeps identity
+    Renamed(String, String),
+    /// Events under this path were lost; it must be walked again
+    Rescan(String),
 }
 
-impl FileWatcher {
-    #[allow(dead_code)] // Reserved for future file watcher integration
-    pub fn process_events(&self) -> Vec<FileChangeEvent> {
-        let mut changes = Vec::new();
+fn path_string(path: &Path) -> Option<String> {
+    path.to_str().map(str::to_string)
+}
 
-        // Process all available events
-        loop {
-            match self.try_recv() {
-                Ok(Some(event)) => match event.kind {
-                    EventKind::Create(_) => {
-                        for path in event.paths {
-                            if let Some(path_str) = path.to_str() {
-                                changes.push(FileChangeEvent::Created(path_str.to_string()));
-                            }
+/// Map raw notify events to index changes, pairing rename halves.
+///
+/// inotify reports `From` and `To` halves sharing a cookie (tracker) followed
+/// by a `Both` event; Windows reports consecutive `From`/`To` halves without
+/// one; FSEvents only reports `Any` per path. A `From` never matched by a `To`
+/// means the entry left the watched tree, and an unmatched `To` means it
+/// arrived from outside.
+///
+/// Rescan notices (inotify queue overflow, dropped FSEvents) carry the lost
+/// subtree when known; without one, every watched root is re-scanned.
+fn translate_events(roots: &HashSet<PathBuf>, events: Vec<Event>) -> Vec<FileChangeEvent> {
+    let mut changes = Vec::new();
+    let mut pending_from: Option<(Option<usize>, String)> = None;
+    let mut paired_trackers: HashSet<usize> = HashSet::new();
+
+    for event in events {
+        if event.need_rescan() {
+            let paths: Vec<&PathBuf> = if event.paths.is_empty() {
+                roots.iter().collect()
+            } else {
+                event.paths.iter().collect()
+            };
+            changes.extend(
+                paths
+                    .into_iter()
+                    .filter_map(|p| path_string(p))
+                    .map(FileChangeEvent::Rescan),
+            );
+            continue;
+        }
+
+        let tracker = event.tracker();
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some((_, orphan)) = pending_from.take() {
+                    changes.push(FileChangeEvent::Deleted(orphan));
+                }
+                pending_from = event
+                    .paths
+                    .first()
+                    .and_then(|p| path_string(p))
+                    .map(|p| (tracker, p));
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                let Some(to) = event.paths.first().and_then(|p| path_string(p)) else {
+                    continue;
+                };
+                match pending_from.take() {
+                    Some((from_tracker, from)) if from_tracker == tracker => {
+                        if let Some(tracker) = tracker {
+                            paired_trackers.insert(tracker);
                         }
+                        changes.push(FileChangeEvent::Renamed(from, to));
                     }
-                    EventKind::Modify(_) => {
-                        for path in event.paths {
-                            if let Some(path_str) = path.to_str() {
-                                changes.push(FileChangeEvent::Modified(path_str.to_string()));
-                            }
+                    other => {
+                        if let Some((_, orphan)) = other {
+                            changes.push(FileChangeEvent::Deleted(orphan));
                         }
+                        changes.push(FileChangeEvent::Created(to));
                     }
-                    EventKind::Remove(_) => {
-                        for path in event.paths {
-                            if let Some(path_str) = path.to_str() {
-                                changes.push(FileChangeEvent::Deleted(path_str.to_string()));
-                            }
-                        }
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                // Already paired from its From/To halves
+                if tracker.is_some_and(|t| paired_trackers.contains(&t)) {
+                    continue;
+                }
+                if let [from, to] = event.paths.as_slice() {
+                    if let (Some(from), Some(to)) = (path_string(from), path_string(to)) {
+                        changes.push(FileChangeEvent::Renamed(from, to));
+                    }
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                // Unpaired rename notifications: the path either appeared or vanished
+                for path in event.paths {
+                    if let Some(path_str) = path_string(&path) {
+                        changes.push(if path.exists() {
+                            FileChangeEvent::Created(path_str)
+                        } else {
+                            FileChangeEvent::Deleted(path_str)
+                        });
+                    }
+                }
+            }
+            EventKind::Create(_) => {
+                for path in event.paths {
+                    if let Some(path_str) = path_string(&path) {
+                        changes.push(FileChangeEvent::Created(path_str));
+                    }
+                }
+            }
+            EventKind::Modify(_) => {
+                for path in event.paths {
+                    if let Some(path_str) = path_string(&path) {
+                        changes.push(FileChangeEvent::Modified(path_str));
                     }
-                    // Rename events in notify 6.x are handled as separate Create/Remove events
-                    // No special handling needed
-                    _ => {}
-                },
-                Ok(None) => break,
+                }
+            }
+            EventKind::Remove(_) => {
+                for path in event.paths {
+                    if let Some(path_str) = path_string(&path) {
+                        changes.push(FileChangeEvent::Deleted(path_str));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((_, orphan)) = pending_from {
+        changes.push(FileChangeEvent::Deleted(orphan));
+    }
+    changes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+struct PendingChange {
+    first: ChangeKind,
+    last: ChangeKind,
+    /// Indexed path this entry was renamed from during the window
+    renamed_from: Option<String>,
+    first_seen: Instant,
+    last_seen: Instant,
+}
+
+impl PendingChange {
+    /// Net effect of everything seen for the path during the window
+    fn net(self, path: String) -> Option<FileChangeEvent> {
+        if let Some(from) = self.renamed_from {
+            return Some(if self.last == ChangeKind::Deleted {
+                FileChangeEvent::Deleted(from)
+            } else {
+                FileChangeEvent::Renamed(from, path)
+            });
+        }
+        match (self.first, self.last) {
+            // Temp files created and removed within the window never surface
+            (ChangeKind::Created, ChangeKind::Deleted) => None,
+            (ChangeKind::Created, _) => Some(FileChangeEvent::Created(path)),
+            (_, ChangeKind::Deleted) => Some(FileChangeEvent::Deleted(path)),
+            // Delete-then-recreate (atomic saves) leaves an existing file changed
+            _ => Some(FileChangeEvent::Modified(path)),
+        }
+    }
+}
+
+/// Merges bursts of raw events into at most one change per path. A path is
+/// released once it has been quiet for `window`, or after
+/// `MAX_DELAY_WINDOWS` windows if it keeps changing.
+pub struct Debouncer {
+    window: Duration,
+    pending: HashMap<String, PendingChange>,
+    /// Re-scans are not delayed; the walk picks up the latest state anyway
+    rescans: Vec<String>,
+    /// When the oldest change of the last release was first seen
+    released_since: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Debouncer {
+            window,
+            pending: HashMap::new(),
+            rescans: Vec::new(),
+            released_since: None,
+        }
+    }
+
+    /// Paths and re-scans waiting to be released
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.rescans.len()
+    }
+
+    /// When the oldest change returned by the last drain was first seen;
+    /// None if it held only re-scans or nothing
+    pub fn released_since(&self) -> Option<Instant> {
+        self.released_since
+    }
+
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.rescans.is_empty()
+    }
+
+    pub fn push(&mut self, event: FileChangeEvent, now: Instant) {
+        let (kind, path) = match event {
+            FileChangeEvent::Created(path) => (ChangeKind::Created, path),
+            FileChangeEvent::Modified(path) => (ChangeKind::Modified, path),
+            FileChangeEvent::Deleted(path) => (ChangeKind::Deleted, path),
+            FileChangeEvent::Renamed(from, to) => return self.push_rename(from, to, now),
+            FileChangeEvent::Rescan(path) => {
+                if !self.rescans.contains(&path) {
+                    self.rescans.push(path);
+                }
+                return;
+            }
+        };
+        self.pending
+            .entry(path)
+            .and_modify(|change| {
+                change.last = kind;
+                change.last_seen = now;
+            })
+            .or_insert(PendingChange {
+                first: kind,
+                last: kind,
+                renamed_from: None,
+                first_seen: now,
+                last_seen: now,
+            });
+    }
+
+    /// The pending state moves with the entry: a file created and then renamed
+    /// within the window is simply created under its new name
+    fn push_rename(&mut self, from: String, to: String, now: Instant) {
+        let previous = self.pending.remove(&from);
+        let (first, renamed_from, first_seen) = match previous {
+            Some(change) if change.first == ChangeKind::Created => {
+                (ChangeKind::Created, None, change.first_seen)
+            }
+            Some(change) => (
+                ChangeKind::Modified,
+                Some(change.renamed_from.unwrap_or(from)),
+                change.first_seen,
+            ),
+            None => (ChangeKind::Modified, Some(from), now),
+        };
+        self.pending.insert(
+            to,
+            PendingChange {
+                first,
+                last: ChangeKind::Modified,
+                renamed_from,
+                first_seen,
+                last_seen: now,
+            },
+        );
+    }
+
+    /// Remove and return the coalesced changes whose paths are due at `now`,
+    /// oldest first
+    pub fn drain_ready(&mut self, now: Instant) -> Vec<FileChangeEvent> {
+        let max_delay = self.window * MAX_DELAY_WINDOWS;
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, change)| {
+                now.duration_since(change.last_seen) >= self.window
+                    || now.duration_since(change.first_seen) >= max_delay
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.release(due)
+    }
+
+    /// True when no change is waiting to be released
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty() && self.rescans.is_empty()
+    }
+
+    /// Release everything regardless of timing
+    #[cfg(test)]
+    pub fn flush(&mut self) -> Vec<FileChangeEvent> {
+        let all: Vec<String> = self.pending.keys().cloned().collect();
+        self.release(all)
+    }
+
+    fn release(&mut self, paths: Vec<String>) -> Vec<FileChangeEvent> {
+        let mut changes: Vec<(Instant, String, PendingChange)> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let change = self.pending.remove(&path)?;
+                Some((change.first_seen, path, change))
+            })
+            .collect();
+        changes.sort_by_key(|(first_seen, _, _)| *first_seen);
+        self.released_since = changes.first().map(|(first_seen, _, _)| *first_seen);
+        self.rescans
+            .drain(..)
+            .map(FileChangeEvent::Rescan)
+            .chain(
+                changes
+                    .into_iter()
+                    .filter_map(|(_, path, change)| change.net(path)),
+            )
+            .collect()
+    }
+}
+
+impl FileWatcher {
+    pub fn process_events(&self) -> Vec<FileChangeEvent> {
+        let mut events = Vec::new();
+
+        // Process all available events
+        loop {
+            match self.event_receiver.try_recv() {
+                Ok(Ok(event)) => events.push(event),
+                Ok(Err(e)) => {
+                    // A backend error means changes may have been dropped
+                    log::error!("File watcher error: {:?}", e);
+                    let mut rescan = Event::new(EventKind::Other).set_flag(Flag::Rescan);
+                    rescan.paths = e.paths;
+                    events.push(rescan);
+                }
                 Err(_) => break,
             }
         }
 
+        let changes = translate_events(&self.watched_paths, events);
+        if changes
+            .iter()
+            .any(|c| matches!(c, FileChangeEvent::Rescan(_)))
+        {
+            log::warn!("File watcher lost events; affected subtrees will be re-scanned");
+            self.healthy.store(false, Ordering::SeqCst);
+        }
         changes
     }
+
+    /// False while the index may be missing changes the watcher dropped
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::SeqCst)
+    }
+
+    /// Record that the subtrees reported by `Rescan` events were walked again
+    pub fn mark_healthy(&self) {
+        self.healthy.store(true, Ordering::SeqCst);
+    }
+
+    /// Change the debounce window used by `debounced_events`
+    pub fn set_debounce_window(&mut self, window: Duration) {
+        self.debouncer.set_window(window);
+    }
+
+    /// Feed newly arrived events into the debouncer and return the coalesced
+    /// changes that are due, so bursts become one index update per path
+    pub fn debounced_events(&mut self) -> Vec<FileChangeEvent> {
+        // Read before draining so every event up to `reached` is in the queue
+        let reached = self.event_id.load(Ordering::SeqCst);
+        let now = Instant::now();
+        for change in self.process_events() {
+            self.debouncer.push(change, now);
+        }
+        let ready = self.debouncer.drain_ready(now);
+        if reached != 0 && self.debouncer.is_idle() {
+            self.checkpoint = Some(reached);
+        }
+        ready
+    }
+
+    /// Changes waiting in the debouncer
+    pub fn queued_changes(&self) -> usize {
+        self.debouncer.len()
+    }
+
+    /// When the oldest change last returned by `debounced_events` happened
+    pub fn released_since(&self) -> Option<Instant> {
+        self.debouncer.released_since()
+    }
+
+    /// Replay changes recorded after `event_id` for roots watched from now
+    /// on. Only macOS keeps such a journal; elsewhere the id is just carried.
+    pub fn resume_from(&mut self, event_id: Option<u64>) {
+        self.event_id.store(event_id.unwrap_or(0), Ordering::SeqCst);
+    }
+
+    /// Journal position to resume from once the changes last returned by
+    /// `debounced_events` have been applied, if the platform has a journal
+    pub fn checkpoint(&self) -> Option<u64> {
+        self.checkpoint
+    }
 }
 
 #[cfg(test)]
@@ -187,6 +897,18 @@ mod tests {
         assert!(result.is_ok(), "Should be able to unwatch path");
     }
 
+    #[test]
+    fn test_is_watching_tracks_registrations() {
+        let temp_dir = tempdir().unwrap();
+        let mut watcher = create_test_watcher();
+        assert!(!watcher.is_watching(temp_dir.path()));
+
+        watcher.watch_path(temp_dir.path(), true).unwrap();
+        assert!(watcher.is_watching(temp_dir.path()));
+        watcher.unwatch_path(temp_dir.path()).unwrap();
+        assert!(!watcher.is_watching(temp_dir.path()));
+    }
+
     #[test]
     fn test_unwatch_non_watched_path() {
         let temp_dir = tempdir().unwrap();
@@ -227,6 +949,301 @@ mod tests {
         assert!(matches!(deleted, FileChangeEvent::Deleted(_)));
     }
 
+    #[test]
+    fn test_debouncer_coalesces_per_path() {
+        let window = Duration::from_millis(100);
+        let mut debouncer = Debouncer::new(window);
+        let start = Instant::now();
+
+        debouncer.push(FileChangeEvent::Created("/a".to_string()), start);
+        debouncer.push(FileChangeEvent::Modified("/a".to_string()), start);
+        debouncer.push(FileChangeEvent::Modified("/a".to_string()), start);
+        debouncer.push(FileChangeEvent::Created("/tmp~".to_string()), start);
+        debouncer.push(FileChangeEvent::Deleted("/tmp~".to_string()), start);
+        debouncer.push(FileChangeEvent::Deleted("/b".to_string()), start);
+        debouncer.push(FileChangeEvent::Created("/b".to_string()), start);
+
+        assert!(debouncer.drain_ready(start).is_empty());
+        assert_eq!(debouncer.len(), 3);
+        let changes = debouncer.drain_ready(start + window);
+        assert_eq!(debouncer.released_since(), Some(start));
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&FileChangeEvent::Created("/a".to_string())));
+        assert!(changes.contains(&FileChangeEvent::Modified("/b".to_string())));
+        assert!(debouncer.is_empty());
+    }
+
+    #[test]
+    fn test_debouncer_waits_for_quiet_but_caps_delay() {
+        let window = Duration::from_millis(100);
+        let mut debouncer = Debouncer::new(window);
+        let start = Instant::now();
+
+        debouncer.push(FileChangeEvent::Modified("/busy".to_string()), start);
+        debouncer.push(
+            FileChangeEvent::Modified("/busy".to_string()),
+            start + window / 2,
+        );
+        assert!(debouncer.drain_ready(start + window).is_empty());
+
+        // Keeps changing every half window, but is released at the cap
+        let mut now = start + window / 2;
+        let mut released = Vec::new();
+        while released.is_empty() {
+            now += window / 2;
+            debouncer.push(FileChangeEvent::Modified("/busy".to_string()), now);
+            released = debouncer.drain_ready(now);
+        }
+        assert_eq!(now.duration_since(start), window * MAX_DELAY_WINDOWS);
+        assert_eq!(
+            released,
+            vec![FileChangeEvent::Modified("/busy".to_string())]
+        );
+    }
+
+    fn rename_event(mode: RenameMode, paths: &[&str], tracker: Option<usize>) -> Event {
+        let mut event = Event::new(EventKind::Modify(ModifyKind::Name(mode)));
+        for path in paths {
+            event = event.add_path(PathBuf::from(path));
+        }
+        match tracker {
+            Some(tracker) => event.set_tracker(tracker),
+            None => event,
+        }
+    }
+
+    #[test]
+    fn test_translate_pairs_rename_halves() {
+        // inotify: From, To and Both share a cookie; only one rename results
+        let changes = translate_events(
+            &HashSet::new(),
+            vec![
+                rename_event(RenameMode::From, &["/w/old.txt"], Some(7)),
+                rename_event(RenameMode::To, &["/w/new.txt"], Some(7)),
+                rename_event(RenameMode::Both, &["/w/old.txt", "/w/new.txt"], Some(7)),
+            ],
+        );
+        assert_eq!(
+            changes,
+            vec![FileChangeEvent::Renamed(
+                "/w/old.txt".to_string(),
+                "/w/new.txt".to_string()
+            )]
+        );
+
+        // Windows: consecutive halves without a tracker
+        let changes = translate_events(
+            &HashSet::new(),
+            vec![
+                rename_event(RenameMode::From, &["/w/a"], None),
+                rename_event(RenameMode::To, &["/w/b"], None),
+            ],
+        );
+        assert_eq!(
+            changes,
+            vec![FileChangeEvent::Renamed(
+                "/w/a".to_string(),
+                "/w/b".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_translate_unpaired_halves() {
+        let changes = translate_events(
+            &HashSet::new(),
+            vec![
+                rename_event(RenameMode::From, &["/w/moved-out"], Some(1)),
+                rename_event(RenameMode::To, &["/w/moved-in"], Some(2)),
+            ],
+        );
+        assert_eq!(
+            changes,
+            vec![
+                FileChangeEvent::Deleted("/w/moved-out".to_string()),
+                FileChangeEvent::Created("/w/moved-in".to_string()),
+            ]
+        );
+
+        let changes = translate_events(
+            &HashSet::new(),
+            vec![rename_event(RenameMode::From, &["/w/gone"], None)],
+        );
+        assert_eq!(
+            changes,
+            vec![FileChangeEvent::Deleted("/w/gone".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_translate_rescan_notices() {
+        let roots: HashSet<PathBuf> = [PathBuf::from("/root-a")].into_iter().collect();
+        let overflow = Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        let targeted = Event::new(EventKind::Other)
+            .set_flag(Flag::Rescan)
+            .add_path(PathBuf::from("/root-a/sub"));
+
+        assert_eq!(
+            translate_events(&roots, vec![overflow, targeted]),
+            vec![
+                FileChangeEvent::Rescan("/root-a".to_string()),
+                FileChangeEvent::Rescan("/root-a/sub".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_debouncer_releases_rescans_immediately() {
+        let mut debouncer = Debouncer::new(Duration::from_secs(1));
+        let now = Instant::now();
+        debouncer.push(FileChangeEvent::Modified("/a/file".to_string()), now);
+        debouncer.push(FileChangeEvent::Rescan("/a".to_string()), now);
+        debouncer.push(FileChangeEvent::Rescan("/a".to_string()), now);
+
+        assert_eq!(
+            debouncer.drain_ready(now),
+            vec![FileChangeEvent::Rescan("/a".to_string())]
+        );
+        assert!(!debouncer.is_empty(), "Regular changes still wait");
+    }
+
+    #[test]
+    fn test_watcher_starts_healthy() {
+        let watcher = create_test_watcher();
+        assert!(watcher.is_healthy());
+        watcher.healthy.store(false, Ordering::SeqCst);
+        watcher.mark_healthy();
+        assert!(watcher.is_healthy());
+    }
+
+    #[test]
+    fn test_debouncer_follows_renames() {
+        let window = Duration::from_millis(100);
+        let mut debouncer = Debouncer::new(window);
+        let start = Instant::now();
+
+        debouncer.push(FileChangeEvent::Created("/new~".to_string()), start);
+        debouncer.push(
+            FileChangeEvent::Renamed("/new~".to_string(), "/saved".to_string()),
+            start,
+        );
+        debouncer.push(FileChangeEvent::Modified("/a".to_string()), start);
+        debouncer.push(
+            FileChangeEvent::Renamed("/a".to_string(), "/b".to_string()),
+            start,
+        );
+        debouncer.push(
+            FileChangeEvent::Renamed("/b".to_string(), "/c".to_string()),
+            start,
+        );
+
+        let changes = debouncer.drain_ready(start + window);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&FileChangeEvent::Created("/saved".to_string())));
+        assert!(changes.contains(&FileChangeEvent::Renamed(
+            "/a".to_string(),
+            "/c".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_detect_rename() {
+        let temp_dir = tempdir().unwrap();
+        let mut watcher = create_test_watcher();
+        watcher
+            .watch_path(temp_dir.path(), false)
+            .expect("Should be able to watch path");
+
+        let old_path = temp_dir.path().join("before.txt");
+        File::create(&old_path).expect("Should create file");
+        thread::sleep(Duration::from_millis(100));
+        watcher.process_events();
+
+        fs::rename(&old_path, temp_dir.path().join("after.txt")).unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        let events = watcher.process_events();
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, FileChangeEvent::Renamed(from, to)
+                if from.contains("before.txt") && to.contains("after.txt"))),
+            "Should pair the rename, got {:?}",
+            events
+        );
+    }
+
+    #[test]
+    fn test_debounced_events_from_watcher() {
+        let temp_dir = tempdir().unwrap();
+        let mut watcher = create_test_watcher();
+        watcher.set_debounce_window(Duration::from_millis(50));
+        watcher
+            .watch_path(temp_dir.path(), false)
+            .expect("Should be able to watch path");
+
+        let file_path = temp_dir.path().join("burst.txt");
+        for i in 0..5 {
+            fs::write(&file_path, format!("write {}", i)).unwrap();
+        }
+        thread::sleep(Duration::from_millis(200));
+
+        let mut events = watcher.debounced_events();
+        thread::sleep(Duration::from_millis(100));
+        events.extend(watcher.debounced_events());
+        let for_file: Vec<_> = events
+            .iter()
+            .filter(|e| matches!(e, FileChangeEvent::Created(p) | FileChangeEvent::Modified(p) if p.contains("burst.txt")))
+            .collect();
+        assert_eq!(for_file.len(), 1, "Burst should coalesce to one change");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_recursive_watch_polls_subtrees_over_limit() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+
+        let mut watcher = create_test_watcher();
+        // Only root and a fit; b is polled along with everything below it
+        watcher.watch_limit = Some(2);
+        watcher.watch_path(root, true).unwrap();
+        assert!(watcher.is_watching(root));
+
+        let diagnostics = watcher.diagnostics();
+        assert_eq!(diagnostics.backend, "inotify");
+        assert_eq!(diagnostics.watch_limit, Some(2));
+        assert_eq!(diagnostics.watches_used, 2);
+        assert_eq!(
+            diagnostics.polled_paths,
+            vec![root.join("a/b").to_string_lossy().to_string()]
+        );
+
+        watcher.unwatch_path(root).unwrap();
+        let diagnostics = watcher.diagnostics();
+        assert_eq!(diagnostics.watches_used, 0);
+        assert!(diagnostics.polled_paths.is_empty());
+    }
+
+    #[test]
+    fn test_checkpoint_waits_for_pending_changes() {
+        let mut watcher = create_test_watcher();
+        assert_eq!(watcher.checkpoint(), None);
+
+        watcher.resume_from(Some(42));
+        let now = Instant::now();
+        watcher
+            .debouncer
+            .push(FileChangeEvent::Modified("/r/a.txt".to_string()), now);
+        watcher.debounced_events();
+        assert_eq!(watcher.checkpoint(), None, "Change still pending");
+
+        watcher.debouncer.flush();
+        watcher.debounced_events();
+        assert_eq!(watcher.checkpoint(), Some(42));
+    }
+
     #[test]
     fn test_detect_file_creation() {
         let temp_dir = tempdir().unwrap();
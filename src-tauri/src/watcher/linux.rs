@@ -0,0 +1,141 @@
+// inotify needs one watch per directory and fails once
+// fs.inotify.max_user_watches is used up. Recursive roots are split so that
+// the biggest subtrees that fit are watched natively and the rest is polled.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[cfg(target_os = "linux")]
+const MAX_USER_WATCHES: &str = "/proc/sys/fs/inotify/max_user_watches";
+
+/// Per-user inotify watch limit, when the kernel exposes it
+#[cfg(target_os = "linux")]
+pub fn max_user_watches() -> Option<usize> {
+    std::fs::read_to_string(MAX_USER_WATCHES)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// How one recursive root is covered
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WatchPlan {
+    /// Subtrees watched recursively, with the directories (watches) in each
+    pub recursive: Vec<(PathBuf, usize)>,
+    /// Directories watched on their own because their subtree did not fit
+    pub single: Vec<PathBuf>,
+    /// Subtrees left to the polling fallback
+    pub polled: Vec<PathBuf>,
+}
+
+impl WatchPlan {
+    /// inotify watches the plan registers
+    pub fn watches(&self) -> usize {
+        self.recursive.iter().map(|(_, dirs)| dirs).sum::<usize>() + self.single.len()
+    }
+}
+
+/// Split `root` into natively watched and polled subtrees using at most
+/// `budget` watches. Fails when `root` cannot be read.
+pub fn plan_watches(root: &Path, budget: usize) -> std::io::Result<WatchPlan> {
+    // Directories per subtree and the child directories of each directory
+    let mut subtree_dirs: HashMap<PathBuf, usize> = HashMap::new();
+    let mut children: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for entry in WalkDir::new(root).follow_links(false) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.depth() == 0 => return Err(e.into()),
+            Err(_) => continue,
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let path = entry.into_path();
+        for ancestor in path.ancestors() {
+            *subtree_dirs.entry(ancestor.to_path_buf()).or_default() += 1;
+            if ancestor == root {
+                break;
+            }
+        }
+        if path != root {
+            if let Some(parent) = path.parent() {
+                children
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .push(path.clone());
+            }
+        }
+    }
+
+    let mut plan = WatchPlan::default();
+    let mut remaining = budget;
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let dirs = subtree_dirs.get(&dir).copied().unwrap_or(1);
+        if dirs <= remaining {
+            remaining -= dirs;
+            plan.recursive.push((dir, dirs));
+        } else if remaining > 0 {
+            remaining -= 1;
+            // Pop the smallest children first so as many subtrees as possible fit whole
+            let mut subdirs = children.remove(&dir).unwrap_or_default();
+            subdirs.sort_by_key(|d| Reverse(subtree_dirs.get(d).copied().unwrap_or(1)));
+            stack.extend(subdirs);
+            plan.single.push(dir);
+        } else {
+            plan.polled.push(dir);
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plan_watches_whole_tree_when_budget_allows() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/a1")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/file.txt"), "x").unwrap();
+
+        let plan = plan_watches(root, 100).unwrap();
+        assert_eq!(plan.recursive, vec![(root.to_path_buf(), 4)]);
+        assert_eq!(plan.watches(), 4);
+        assert!(plan.polled.is_empty());
+    }
+
+    #[test]
+    fn test_plan_watches_splits_and_polls_overflow() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("big/x/y")).unwrap();
+        fs::create_dir_all(root.join("big/z")).unwrap();
+        fs::create_dir_all(root.join("small")).unwrap();
+
+        // root and big get single watches; only small still fits whole
+        let plan = plan_watches(root, 3).unwrap();
+        assert_eq!(plan.single, vec![root.to_path_buf(), root.join("big")]);
+        assert_eq!(plan.watches(), 3);
+        assert_eq!(plan.recursive, vec![(root.join("small"), 1)]);
+        let covered: Vec<&PathBuf> = plan
+            .recursive
+            .iter()
+            .map(|(p, _)| p)
+            .chain(&plan.polled)
+            .collect();
+        assert_eq!(covered.len(), 3, "x, z and small are each covered once");
+        assert!(plan.polled.contains(&root.join("big/x")));
+    }
+
+    #[test]
+    fn test_plan_watches_missing_root_fails() {
+        let temp_dir = tempdir().unwrap();
+        assert!(plan_watches(&temp_dir.path().join("missing"), 10).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+// FSEvents backend for recursive roots on macOS. Streams are started from a
+// persisted event id, so changes made while the app was closed are replayed
+// from the system's event history before live events follow.
+
+use notify::event::{CreateKind, Flag, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind};
+use std::path::{Path, PathBuf};
+
+// FSEventStreamEventFlags, mirrored so the mapping can be tested anywhere
+const FLAG_MUST_SCAN_SUB_DIRS: u32 = 0x0000_0001;
+const FLAG_USER_DROPPED: u32 = 0x0000_0002;
+const FLAG_KERNEL_DROPPED: u32 = 0x0000_0004;
+const FLAG_EVENT_IDS_WRAPPED: u32 = 0x0000_0008;
+const FLAG_HISTORY_DONE: u32 = 0x0000_0010;
+const FLAG_ROOT_CHANGED: u32 = 0x0000_0020;
+const FLAG_ITEM_CREATED: u32 = 0x0000_0100;
+const FLAG_ITEM_REMOVED: u32 = 0x0000_0200;
+const FLAG_ITEM_INODE_META_MOD: u32 = 0x0000_0400;
+const FLAG_ITEM_RENAMED: u32 = 0x0000_0800;
+const FLAG_ITEM_MODIFIED: u32 = 0x0000_1000;
+const FLAG_ITEM_XATTR_MOD: u32 = 0x0000_8000;
+
+/// Translate one FSEvents record into a notify event. FSEvents coalesces
+/// flags per path, so a path that was both created and removed is reported
+/// as an unpaired rename and resolved against the disk like other renames.
+pub fn event_for_flags(path: PathBuf, flags: u32) -> Option<Event> {
+    if flags & FLAG_HISTORY_DONE != 0 {
+        return None;
+    }
+    let lost = FLAG_MUST_SCAN_SUB_DIRS
+        | FLAG_USER_DROPPED
+        | FLAG_KERNEL_DROPPED
+        | FLAG_EVENT_IDS_WRAPPED
+        | FLAG_ROOT_CHANGED;
+    if flags & lost != 0 {
+        return Some(
+            Event::new(EventKind::Other)
+                .set_flag(Flag::Rescan)
+                .add_path(path),
+        );
+    }
+
+    let created = flags & FLAG_ITEM_CREATED != 0;
+    let removed = flags & FLAG_ITEM_REMOVED != 0;
+    let kind = if flags & FLAG_ITEM_RENAMED != 0 || (created && removed) {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Any))
+    } else if removed {
+        EventKind::Remove(RemoveKind::Any)
+    } else if created {
+        EventKind::Create(CreateKind::Any)
+    } else if flags & (FLAG_ITEM_MODIFIED | FLAG_ITEM_INODE_META_MOD | FLAG_ITEM_XATTR_MOD) != 0 {
+        EventKind::Modify(ModifyKind::Any)
+    } else {
+        return None;
+    };
+    Some(Event::new(kind).add_path(path))
+}
+
+/// FSEvents reports resolved paths (/private/tmp for /tmp); map them back
+/// under the root as the user configured it so index ids stay stable
+pub fn rebase(path: PathBuf, resolved_root: &Path, root: &Path) -> PathBuf {
+    match path.strip_prefix(resolved_root) {
+        Ok(rest) if resolved_root != root => root.join(rest),
+        _ => path,
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use backend::RootStream;
+
+#[cfg(target_os = "macos")]
+mod backend {
+    use super::*;
+    use fsevent_sys::core_foundation as cf;
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_void};
+    use std::os::unix::ffi::OsStrExt;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread::JoinHandle;
+
+    /// Seconds FSEvents may batch changes before calling back
+    const LATENCY: f64 = 0.1;
+    /// How often the stream thread checks whether it should stop
+    const STOP_CHECK_SECONDS: f64 = 0.25;
+
+    type EventSender = mpsc::Sender<notify::Result<Event>>;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRunLoopRunInMode(
+            mode: cf::CFStringRef,
+            seconds: cf::CFTimeInterval,
+            return_after_source_handled: cf::Boolean,
+        ) -> i32;
+    }
+
+    struct StreamContext {
+        root: PathBuf,
+        resolved_root: PathBuf,
+        sender: EventSender,
+        last_event_id: Arc<AtomicU64>,
+    }
+
+    /// Latest event id issued system-wide, where a stream started "now" begins
+    fn current_event_id() -> u64 {
+        // SAFETY: no arguments, always safe to call
+        unsafe { fsevent_sys::FSEventsGetCurrentEventId() }
+    }
+
+    /// One FSEvents stream over a root, run on its own thread. Event ids of
+    /// delivered changes are published through `last_event_id`.
+    pub struct RootStream {
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl RootStream {
+        /// Start watching `root`, replaying history after `since` when given
+        pub fn start(
+            root: &Path,
+            since: Option<u64>,
+            sender: EventSender,
+            last_event_id: Arc<AtomicU64>,
+        ) -> notify::Result<Self> {
+            let resolved_root = root
+                .canonicalize()
+                .map_err(|e| notify::Error::io(e).add_path(root.to_path_buf()))?;
+            let c_root = CString::new(resolved_root.as_os_str().as_bytes())
+                .map_err(|_| notify::Error::generic("Path contains a NUL byte"))?;
+            if since.is_none() {
+                last_event_id.fetch_max(current_event_id(), Ordering::SeqCst);
+            }
+
+            let context = StreamContext {
+                root: root.to_path_buf(),
+                resolved_root,
+                sender,
+                last_event_id,
+            };
+            let stop = Arc::new(AtomicBool::new(false));
+            let (ready_tx, ready_rx) = mpsc::channel();
+            let thread = {
+                let stop = stop.clone();
+                std::thread::spawn(move || run_stream(c_root, since, context, &stop, ready_tx))
+            };
+            match ready_rx.recv() {
+                Ok(Ok(())) => Ok(RootStream {
+                    stop,
+                    thread: Some(thread),
+                }),
+                Ok(Err(e)) => {
+                    let _ = thread.join();
+                    Err(notify::Error::generic(&e).add_path(root.to_path_buf()))
+                }
+                Err(_) => Err(notify::Error::generic("FSEvents thread exited")),
+            }
+        }
+    }
+
+    impl Drop for RootStream {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn run_stream(
+        c_root: CString,
+        since: Option<u64>,
+        context: StreamContext,
+        stop: &AtomicBool,
+        ready: mpsc::Sender<Result<(), String>>,
+    ) {
+        let info = Box::into_raw(Box::new(context));
+        let stream_context = fsevent_sys::FSEventStreamContext {
+            version: 0,
+            info: info.cast(),
+            retain: None,
+            release: None,
+            copy_description: None,
+        };
+
+        // SAFETY: every CF object created here is released before returning,
+        // and `info` outlives the stream, which is invalidated first
+        unsafe {
+            let paths =
+                cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 1, &cf::kCFTypeArrayCallBacks);
+            let path = cf::CFStringCreateWithCString(
+                cf::kCFAllocatorDefault,
+                c_root.as_ptr(),
+                cf::kCFStringEncodingUTF8,
+            );
+            cf::CFArrayAppendValue(paths, path);
+            cf::CFRelease(path);
+
+            let stream = fsevent_sys::FSEventStreamCreate(
+                cf::kCFAllocatorDefault,
+                callback,
+                &stream_context,
+                paths,
+                since.unwrap_or(fsevent_sys::kFSEventStreamEventIdSinceNow),
+                LATENCY,
+                fsevent_sys::kFSEventStreamCreateFlagFileEvents
+                    | fsevent_sys::kFSEventStreamCreateFlagNoDefer
+                    | fsevent_sys::kFSEventStreamCreateFlagWatchRoot,
+            );
+            cf::CFRelease(paths);
+            if stream.is_null() {
+                let _ = ready.send(Err("Failed to create FSEvents stream".to_string()));
+                drop(Box::from_raw(info));
+                return;
+            }
+
+            fsevent_sys::FSEventStreamScheduleWithRunLoop(
+                stream,
+                cf::CFRunLoopGetCurrent(),
+                cf::kCFRunLoopDefaultMode,
+            );
+            if fsevent_sys::FSEventStreamStart(stream) == 0 {
+                let _ = ready.send(Err("Failed to start FSEvents stream".to_string()));
+            } else {
+                let _ = ready.send(Ok(()));
+                while !stop.load(Ordering::SeqCst) {
+                    CFRunLoopRunInMode(cf::kCFRunLoopDefaultMode, STOP_CHECK_SECONDS, 0);
+                }
+                fsevent_sys::FSEventStreamStop(stream);
+            }
+            fsevent_sys::FSEventStreamInvalidate(stream);
+            fsevent_sys::FSEventStreamRelease(stream);
+            drop(Box::from_raw(info));
+        }
+    }
+
+    extern "C" fn callback(
+        _stream: fsevent_sys::FSEventStreamRef,
+        info: *mut c_void,
+        count: usize,
+        paths: *mut c_void,
+        flags: *const fsevent_sys::FSEventStreamEventFlags,
+        ids: *const fsevent_sys::FSEventStreamEventId,
+    ) {
+        // SAFETY: FSEvents passes `count` C strings, flags and ids, and the
+        // context pointer registered in `run_stream`
+        let (context, paths, flags, ids) = unsafe {
+            (
+                &*(info as *const StreamContext),
+                std::slice::from_raw_parts(paths as *const *const c_char, count),
+                std::slice::from_raw_parts(flags, count),
+                std::slice::from_raw_parts(ids, count),
+            )
+        };
+
+        for ((&path, &flag), &id) in paths.iter().zip(flags).zip(ids) {
+            // SAFETY: each entry is a NUL-terminated path owned by FSEvents
+            let bytes = unsafe { CStr::from_ptr(path) }.to_bytes();
+            let path = PathBuf::from(std::ffi::OsStr::from_bytes(bytes));
+            let path = rebase(path, &context.resolved_root, &context.root);
+            if let Some(event) = event_for_flags(path, flag) {
+                let _ = context.sender.send(Ok(event));
+            }
+            // Published after the send so the id never runs ahead of the queue
+            context.last_event_id.fetch_max(id, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_for_flags_maps_item_changes() {
+        let path = PathBuf::from("/root/a.txt");
+        let kind = |flags| event_for_flags(path.clone(), flags).map(|e| e.kind);
+
+        assert_eq!(
+            kind(FLAG_ITEM_CREATED),
+            Some(EventKind::Create(CreateKind::Any))
+        );
+        assert_eq!(
+            kind(FLAG_ITEM_REMOVED),
+            Some(EventKind::Remove(RemoveKind::Any))
+        );
+        assert_eq!(
+            kind(FLAG_ITEM_MODIFIED | FLAG_ITEM_XATTR_MOD),
+            Some(EventKind::Modify(ModifyKind::Any))
+        );
+        // Coalesced create + remove is resolved against the disk later
+        assert_eq!(
+            kind(FLAG_ITEM_CREATED | FLAG_ITEM_REMOVED),
+            Some(EventKind::Modify(ModifyKind::Name(RenameMode::Any)))
+        );
+        assert_eq!(kind(FLAG_HISTORY_DONE), None);
+        assert_eq!(kind(0), None);
+    }
+
+    #[test]
+    fn test_event_for_flags_requests_rescan_when_history_is_lost() {
+        for flags in [
+            FLAG_MUST_SCAN_SUB_DIRS,
+            FLAG_KERNEL_DROPPED,
+            FLAG_ROOT_CHANGED,
+        ] {
+            let event = event_for_flags(PathBuf::from("/root/sub"), flags).unwrap();
+            assert!(event.need_rescan());
+            assert_eq!(event.paths, vec![PathBuf::from("/root/sub")]);
+        }
+    }
+
+    #[test]
+    fn test_rebase_restores_configured_root() {
+        assert_eq!(
+            rebase(
+                PathBuf::from("/private/tmp/x/y.txt"),
+                Path::new("/private/tmp"),
+                Path::new("/tmp")
+            ),
+            PathBuf::from("/tmp/x/y.txt")
+        );
+        assert_eq!(
+            rebase(
+                PathBuf::from("/elsewhere/y.txt"),
+                Path::new("/private/tmp"),
+                Path::new("/tmp")
+            ),
+            PathBuf::from("/elsewhere/y.txt")
+        );
+    }
+}
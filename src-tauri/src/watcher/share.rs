@@ -0,0 +1,233 @@
+// Network shares (SMB, NFS) rarely deliver change events. Roots on them are
+// polled instead: every indexed folder is stat'ed on an interval, and the
+// folders whose mtime moved have their listing compared with the index,
+// which finds entries added, removed or rewritten in them.
+
+use super::FileChangeEvent;
+use crate::index::{unix_secs, IndexManager};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// A root kept fresh by polling
+pub struct PolledRoot {
+    pub root: PathBuf,
+    pub interval: Duration,
+    pub next_due: Instant,
+    /// Folders under the root with the mtime they had when last compared;
+    /// empty until seeded from the index
+    seen: HashMap<String, i64>,
+}
+
+fn mtime(path: &Path) -> Option<i64> {
+    std::fs::metadata(path)
+        .ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(unix_secs)
+}
+
+fn is_under(path: &str, root: &Path) -> bool {
+    Path::new(path).starts_with(root)
+}
+
+impl PolledRoot {
+    /// Poll `root` every `interval`, starting now
+    pub fn new(root: &Path, interval: Duration) -> Self {
+        PolledRoot {
+            root: root.to_path_buf(),
+            interval,
+            next_due: Instant::now(),
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        !self.seen.is_empty()
+    }
+
+    /// Start from the folders as indexed, so changes made since the index
+    /// was built are found by the first poll
+    pub fn seed(&mut self, index_manager: &IndexManager) -> Result<(), sled::Error> {
+        let prefix = self.root.to_string_lossy();
+        for entity in index_manager.entities_with_prefix(&prefix)? {
+            if entity.is_folder && is_under(&entity.path, &self.root) {
+                self.seen.insert(entity.path, entity.modified);
+            }
+        }
+        // An unindexed root is listed in full on the first comparison
+        self.seen
+            .entry(self.root.to_string_lossy().to_string())
+            .or_insert(i64::MIN);
+        Ok(())
+    }
+
+    /// Stat every folder seen, returning those whose mtime moved or which
+    /// are gone. Reads no index, so it runs without holding one.
+    pub fn changed_folders(&self) -> Vec<String> {
+        let mut changed: Vec<String> = self
+            .seen
+            .iter()
+            .filter(|(folder, seen)| mtime(Path::new(folder)) != Some(**seen))
+            .map(|(folder, _)| folder.clone())
+            .collect();
+        // Parents first, so a folder that vanished with its parent is
+        // reported once, under the parent
+        changed.sort();
+        changed
+    }
+
+    /// Compare the listings of the `changed` folders with their indexed
+    /// children and remember their new mtimes. New folders are walked for
+    /// the folders below them, which the index picks up as it rescans them.
+    pub fn compare(
+        &mut self,
+        index_manager: &IndexManager,
+        changed: &[String],
+    ) -> Result<Vec<FileChangeEvent>, sled::Error> {
+        if changed.is_empty() {
+            return Ok(Vec::new());
+        }
+        let changed_set: HashSet<&str> = changed.iter().map(String::as_str).collect();
+        let mut indexed: HashMap<&str, Vec<crate::FileEntity>> = HashMap::new();
+        for entity in index_manager.entities_with_prefix(&self.root.to_string_lossy())? {
+            let parent = Path::new(&entity.path)
+                .parent()
+                .map(|parent| parent.to_string_lossy().to_string());
+            if let Some(parent) = parent.as_deref().and_then(|p| changed_set.get(p)) {
+                indexed.entry(parent).or_default().push(entity);
+            }
+        }
+
+        let mut changes = Vec::new();
+        let mut gone: Vec<&String> = Vec::new();
+        for folder in changed {
+            if gone.iter().any(|g| Path::new(folder).starts_with(g)) {
+                continue;
+            }
+            let Some(folder_mtime) = mtime(Path::new(folder)) else {
+                changes.push(FileChangeEvent::Deleted(folder.clone()));
+                gone.push(folder);
+                continue;
+            };
+            let entries = match std::fs::read_dir(folder) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::warn!("Failed to list polled folder {}: {}", folder, e);
+                    continue;
+                }
+            };
+            let mut listed: HashSet<String> = HashSet::new();
+            let children = indexed.remove(folder.as_str()).unwrap_or_default();
+            let by_path: HashMap<&str, &crate::FileEntity> =
+                children.iter().map(|e| (e.path.as_str(), e)).collect();
+            for entry in entries.flatten() {
+                let path = entry.path().to_string_lossy().to_string();
+                let Ok(metadata) = std::fs::metadata(entry.path()) else {
+                    continue;
+                };
+                let modified = metadata.modified().ok().and_then(unix_secs).unwrap_or(0);
+                match by_path.get(path.as_str()) {
+                    None => {
+                        if metadata.is_dir() {
+                            self.add_folders(&entry.path());
+                        }
+                        changes.push(FileChangeEvent::Created(path.clone()));
+                    }
+                    Some(entity)
+                        if !metadata.is_dir()
+                            && (entity.size != metadata.len() || entity.modified != modified) =>
+                    {
+                        changes.push(FileChangeEvent::Modified(path.clone()));
+                    }
+                    Some(_) => {}
+                }
+                listed.insert(path);
+            }
+            for child in &children {
+                if !listed.contains(&child.path) {
+                    changes.push(FileChangeEvent::Deleted(child.path.clone()));
+                }
+            }
+            self.seen.insert(folder.clone(), folder_mtime);
+        }
+        for folder in gone {
+            self.seen
+                .retain(|seen, _| !Path::new(seen).starts_with(folder));
+        }
+        Ok(changes)
+    }
+
+    /// Remember `folder` and the folders below it as they are now
+    fn add_folders(&mut self, folder: &Path) {
+        for entry in WalkDir::new(folder).into_iter().flatten() {
+            if entry.file_type().is_dir() {
+                if let Some(modified) = mtime(entry.path()) {
+                    self.seen
+                        .insert(entry.path().to_string_lossy().to_string(), modified);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_poll_finds_changes_in_changed_folders() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("share");
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/a.txt"), "a").unwrap();
+        std::fs::write(root.join("docs/b.txt"), "b").unwrap();
+        std::fs::write(root.join("top.txt"), "top").unwrap();
+        let index_manager = IndexManager::new(&temp_dir.path().join("db")).unwrap();
+        for entity in index_manager.traverse_directory(&root).unwrap() {
+            index_manager.save_file_entity(&entity).unwrap();
+        }
+
+        let mut polled = PolledRoot::new(&root, Duration::from_secs(60));
+        polled.seed(&index_manager).unwrap();
+        assert!(polled.is_seeded());
+        assert!(polled.changed_folders().is_empty());
+
+        std::fs::write(root.join("docs/a.txt"), "rewritten").unwrap();
+        std::fs::remove_file(root.join("docs/b.txt")).unwrap();
+        std::fs::create_dir_all(root.join("docs/new/deeper")).unwrap();
+        // The folder's mtime may not have moved within the same second
+        let docs = root.join("docs").to_string_lossy().to_string();
+        polled.seen.insert(docs.clone(), 0);
+        let changed = polled.changed_folders();
+        assert_eq!(changed, vec![docs.clone()]);
+
+        let mut changes = polled.compare(&index_manager, &changed).unwrap();
+        changes.sort_by_key(|change| format!("{:?}", change));
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+        assert_eq!(
+            changes,
+            vec![
+                FileChangeEvent::Created(path("docs/new")),
+                FileChangeEvent::Deleted(path("docs/b.txt")),
+                FileChangeEvent::Modified(path("docs/a.txt")),
+            ]
+        );
+        assert!(polled.changed_folders().is_empty(), "New mtimes are kept");
+        assert!(polled.seen.contains_key(&path("docs/new/deeper")));
+
+        std::fs::remove_dir_all(root.join("docs")).unwrap();
+        polled.seen.insert(root.to_string_lossy().to_string(), 0);
+        let changed = polled.changed_folders();
+        assert!(
+            changed.len() >= 3,
+            "docs, new and deeper are gone: {:?}",
+            changed
+        );
+        let changes = polled.compare(&index_manager, &changed).unwrap();
+        assert!(changes.contains(&FileChangeEvent::Deleted(docs)));
+        assert!(!changes.contains(&FileChangeEvent::Deleted(path("docs/new"))));
+        assert!(!polled.seen.contains_key(&path("docs/new")));
+    }
+}
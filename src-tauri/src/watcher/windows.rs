@@ -0,0 +1,288 @@
+// ReadDirectoryChangesW backend with large buffers for watching whole volumes.
+// notify's Windows backend uses a 16 KiB buffer, which overflows (and drops
+// every change in it) within seconds on a busy system drive.
+
+use notify::event::{CreateKind, Flag, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind};
+use std::path::{Path, PathBuf};
+
+const FILE_ACTION_ADDED: u32 = 1;
+const FILE_ACTION_REMOVED: u32 = 2;
+const FILE_ACTION_MODIFIED: u32 = 3;
+const FILE_ACTION_RENAMED_OLD_NAME: u32 = 4;
+const FILE_ACTION_RENAMED_NEW_NAME: u32 = 5;
+
+/// Lost-events notice for `root`, handled like notify's own rescan flag
+pub fn rescan_event(root: &Path) -> Event {
+    Event::new(EventKind::Other)
+        .set_flag(Flag::Rescan)
+        .add_path(root.to_path_buf())
+}
+
+/// Decode a buffer of FILE_NOTIFY_INFORMATION records (as filled in by
+/// ReadDirectoryChangesW) into notify events with absolute paths
+pub fn parse_notifications(buffer: &[u8], root: &Path) -> Vec<Event> {
+    let read_u32 = |at: usize| -> Option<u32> {
+        buffer
+            .get(at..at + 4)
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while let (Some(next), Some(action), Some(name_len)) =
+        (read_u32(offset), read_u32(offset + 4), read_u32(offset + 8))
+    {
+        let name_start = offset + 12;
+        let Some(name_bytes) = buffer.get(name_start..name_start + name_len as usize) else {
+            break;
+        };
+        let name: Vec<u16> = name_bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+            .collect();
+        let path: PathBuf = root.join(String::from_utf16_lossy(&name));
+
+        let kind = match action {
+            FILE_ACTION_ADDED => Some(EventKind::Create(CreateKind::Any)),
+            FILE_ACTION_REMOVED => Some(EventKind::Remove(RemoveKind::Any)),
+            FILE_ACTION_MODIFIED => Some(EventKind::Modify(ModifyKind::Any)),
+            FILE_ACTION_RENAMED_OLD_NAME => {
+                Some(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+            }
+            FILE_ACTION_RENAMED_NEW_NAME => {
+                Some(EventKind::Modify(ModifyKind::Name(RenameMode::To)))
+            }
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            events.push(Event::new(kind).add_path(path));
+        }
+
+        if next == 0 {
+            break;
+        }
+        offset += next as usize;
+    }
+    events
+}
+
+#[cfg(windows)]
+pub use backend::VolumeWatch;
+
+#[cfg(windows)]
+mod backend {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, ERROR_INVALID_PARAMETER, ERROR_NOTIFY_ENUM_DIR, ERROR_OPERATION_ABORTED,
+        HANDLE, INVALID_HANDLE_VALUE,
+    };
+    use windows_sys::Win32::Storage::FileSystem::{
+        CreateFileW, ReadDirectoryChangesW, FILE_FLAG_BACKUP_SEMANTICS, FILE_LIST_DIRECTORY,
+        FILE_NOTIFY_CHANGE_ATTRIBUTES, FILE_NOTIFY_CHANGE_DIR_NAME, FILE_NOTIFY_CHANGE_FILE_NAME,
+        FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SIZE, FILE_SHARE_DELETE, FILE_SHARE_READ,
+        FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows_sys::Win32::System::IO::CancelIoEx;
+
+    /// Change buffer for local volumes; roughly 10k notifications between reads
+    const BUFFER_SIZE: usize = 1024 * 1024;
+    /// ReadDirectoryChangesW rejects buffers above 64 KiB on network shares
+    const NETWORK_BUFFER_SIZE: usize = 64 * 1024;
+
+    const NOTIFY_FILTER: u32 = FILE_NOTIFY_CHANGE_FILE_NAME
+        | FILE_NOTIFY_CHANGE_DIR_NAME
+        | FILE_NOTIFY_CHANGE_SIZE
+        | FILE_NOTIFY_CHANGE_LAST_WRITE
+        | FILE_NOTIFY_CHANGE_ATTRIBUTES;
+
+    type EventSender = mpsc::Sender<notify::Result<Event>>;
+
+    /// One directory handle read on a dedicated thread. Dropping it cancels
+    /// the pending read and closes the handle.
+    pub struct VolumeWatch {
+        // Raw HANDLE stored as an integer so the watch can move between threads
+        handle: isize,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl VolumeWatch {
+        pub fn start(root: &Path, recursive: bool, sender: EventSender) -> notify::Result<Self> {
+            let wide = crate::longpath::file_api_wide(root);
+            // SAFETY: `wide` is a NUL-terminated UTF-16 path
+            let handle = unsafe {
+                CreateFileW(
+                    wide.as_ptr(),
+                    FILE_LIST_DIRECTORY,
+                    FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                    std::ptr::null(),
+                    OPEN_EXISTING,
+                    FILE_FLAG_BACKUP_SEMANTICS,
+                    std::ptr::null_mut(),
+                )
+            };
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(
+                    notify::Error::io(std::io::Error::last_os_error()).add_path(root.to_path_buf())
+                );
+            }
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let stop = stop.clone();
+                let root = root.to_path_buf();
+                let raw = handle as isize;
+                std::thread::spawn(move || {
+                    read_loop(raw as HANDLE, &root, recursive, &stop, &sender)
+                })
+            };
+            Ok(VolumeWatch {
+                handle: handle as isize,
+                stop,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl Drop for VolumeWatch {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            let Some(thread) = self.thread.take() else {
+                return;
+            };
+            // The read may not have started yet when the first cancel lands
+            for _ in 0..100 {
+                // SAFETY: the handle stays open until the reader thread exits
+                unsafe { CancelIoEx(self.handle as HANDLE, std::ptr::null()) };
+                if thread.is_finished() {
+                    let _ = thread.join();
+                    // SAFETY: no other user of the handle remains
+                    unsafe { CloseHandle(self.handle as HANDLE) };
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            // Leak the handle rather than close it under a blocked reader
+            log::warn!("Directory watch thread did not stop; leaving handle open");
+        }
+    }
+
+    fn read_loop(
+        handle: HANDLE,
+        root: &Path,
+        recursive: bool,
+        stop: &AtomicBool,
+        sender: &EventSender,
+    ) {
+        // u32 storage keeps the buffer DWORD-aligned as the API requires
+        let mut buffer = vec![0u32; BUFFER_SIZE / 4];
+        while !stop.load(Ordering::SeqCst) {
+            let mut bytes = 0u32;
+            // SAFETY: the buffer outlives this synchronous call
+            let ok = unsafe {
+                ReadDirectoryChangesW(
+                    handle,
+                    buffer.as_mut_ptr().cast(),
+                    (buffer.len() * 4) as u32,
+                    recursive as i32,
+                    NOTIFY_FILTER,
+                    &mut bytes,
+                    std::ptr::null_mut(),
+                    None,
+                )
+            };
+
+            let event = if ok == 0 {
+                let err = std::io::Error::last_os_error();
+                match err.raw_os_error().map(|code| code as u32) {
+                    Some(ERROR_OPERATION_ABORTED) => break,
+                    Some(ERROR_INVALID_PARAMETER) if buffer.len() * 4 > NETWORK_BUFFER_SIZE => {
+                        log::info!("Using network-sized change buffer for {}", root.display());
+                        buffer.truncate(NETWORK_BUFFER_SIZE / 4);
+                        continue;
+                    }
+                    Some(ERROR_NOTIFY_ENUM_DIR) => vec![Ok(rescan_event(root))],
+                    _ => {
+                        let _ =
+                            sender.send(Err(notify::Error::io(err).add_path(root.to_path_buf())));
+                        break;
+                    }
+                }
+            } else if bytes == 0 {
+                // The buffer overflowed and the system discarded its changes
+                vec![Ok(rescan_event(root))]
+            } else {
+                // SAFETY: the API wrote `bytes` bytes into the buffer
+                let filled = unsafe {
+                    std::slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), bytes as usize)
+                };
+                parse_notifications(filled, root)
+                    .into_iter()
+                    .map(Ok)
+                    .collect()
+            };
+
+            for event in event {
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(action: u32, name: &str, last: bool) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(|u| u.to_ne_bytes()).collect();
+        // Records are padded to DWORD boundaries
+        let len = (12 + name.len()).div_ceil(4) * 4;
+        let mut bytes = Vec::with_capacity(len);
+        bytes.extend(if last { 0u32 } else { len as u32 }.to_ne_bytes());
+        bytes.extend(action.to_ne_bytes());
+        bytes.extend((name.len() as u32).to_ne_bytes());
+        bytes.extend(&name);
+        bytes.resize(len, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_notifications_maps_actions() {
+        let root = Path::new("volume");
+        let mut buffer = record(FILE_ACTION_ADDED, "new.txt", false);
+        buffer.extend(record(FILE_ACTION_RENAMED_OLD_NAME, "dir\\a.txt", false));
+        buffer.extend(record(FILE_ACTION_RENAMED_NEW_NAME, "dir\\b.txt", false));
+        buffer.extend(record(FILE_ACTION_REMOVED, "gone.log", true));
+
+        let events = parse_notifications(&buffer, root);
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::Create(CreateKind::Any),
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                EventKind::Remove(RemoveKind::Any),
+            ]
+        );
+        assert_eq!(events[0].paths, vec![root.join("new.txt")]);
+        assert_eq!(events[3].paths, vec![root.join("gone.log")]);
+    }
+
+    #[test]
+    fn test_parse_notifications_stops_at_truncated_record() {
+        let mut buffer = record(FILE_ACTION_MODIFIED, "ok.txt", false);
+        buffer.extend(&record(FILE_ACTION_ADDED, "cut-off.txt", true)[..14]);
+
+        let events = parse_notifications(&buffer, Path::new("r"));
+        assert_eq!(events.len(), 1);
+        assert!(rescan_event(Path::new("r")).need_rescan());
+    }
+}
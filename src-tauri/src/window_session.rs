@@ -0,0 +1,206 @@
+// Search context of each window (query, options and sort), stored as JSON in
+// the app data directory so windows reopen on the search they showed last
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SESSIONS_FILE: &str = "window_sessions.json";
+/// Most windows remembered; the least recently saved ones are dropped
+const MAX_WINDOWS: usize = 16;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Relevance,
+    Name,
+    Path,
+    Size,
+    Modified,
+    Rating,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SortState {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WindowSession {
+    /// Query as typed, inline filters included
+    pub query: String,
+    pub use_regex: bool,
+    pub include_hidden: bool,
+    /// Absolute directory searches are limited to
+    pub scope: Option<String>,
+    /// Profile searched, or "*" for all of them; the active one when unset
+    pub profile: Option<String>,
+    pub sort: SortState,
+    /// When the session was saved, in Unix seconds; set on save
+    pub saved: i64,
+}
+
+impl WindowSession {
+    /// Reject a scope that is not an absolute path
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(scope) = &self.scope {
+            if !Path::new(scope).is_absolute() {
+                return Err(format!("Scope must be an absolute path: {}", scope));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SessionsFile {
+    /// Window that saved last
+    last_window: Option<String>,
+    windows: BTreeMap<String, WindowSession>,
+}
+
+/// Persistent sessions by window label
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    base_dir: PathBuf,
+    file: SessionsFile,
+}
+
+impl SessionStore {
+    /// Load the sessions from `base_dir`, starting empty when the file is
+    /// missing or malformed
+    pub fn load(base_dir: &Path) -> Self {
+        let file_path = base_dir.join(SESSIONS_FILE);
+        let file = match fs::read_to_string(&file_path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                log::warn!("Ignoring malformed {}: {}", file_path.display(), e);
+                SessionsFile::default()
+            }),
+            Err(_) => SessionsFile::default(),
+        };
+        SessionStore {
+            base_dir: base_dir.to_path_buf(),
+            file,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        fs::create_dir_all(&self.base_dir)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        let data = serde_json::to_string_pretty(&self.file)
+            .map_err(|e| format!("Failed to serialize window sessions: {}", e))?;
+        fs::write(self.base_dir.join(SESSIONS_FILE), data)
+            .map_err(|e| format!("Failed to write window sessions: {}", e))
+    }
+
+    /// Session of `window`. A window without one starts from the session
+    /// saved last by any window, so a new window continues the last search.
+    pub fn get(&self, window: &str) -> WindowSession {
+        self.file
+            .windows
+            .get(window)
+            .or_else(|| {
+                let last = self.file.last_window.as_ref()?;
+                self.file.windows.get(last)
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Validate, stamp and persist `session` as the one of `window`
+    pub fn set(
+        &mut self,
+        window: &str,
+        mut session: WindowSession,
+        now: i64,
+    ) -> Result<WindowSession, String> {
+        session.validate()?;
+        session.saved = now;
+        self.file
+            .windows
+            .insert(window.to_string(), session.clone());
+        self.file.last_window = Some(window.to_string());
+        while self.file.windows.len() > MAX_WINDOWS {
+            let oldest = self
+                .file
+                .windows
+                .iter()
+                .min_by_key(|(_, session)| session.saved)
+                .map(|(label, _)| label.clone());
+            if let Some(label) = oldest {
+                self.file.windows.remove(&label);
+            }
+        }
+        self.save()?;
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn session(query: &str) -> WindowSession {
+        WindowSession {
+            query: query.to_string(),
+            scope: Some(if cfg!(windows) { r"C:\Users" } else { "/home" }.to_string()),
+            sort: SortState {
+                key: SortKey::Modified,
+                descending: true,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sessions_persist_per_window() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = SessionStore::load(temp_dir.path());
+        assert_eq!(store.get("main"), WindowSession::default());
+
+        store.set("main", session("invoice"), 100).unwrap();
+        store.set("search-2", session("*.log"), 200).unwrap();
+
+        let store = SessionStore::load(temp_dir.path());
+        let main = store.get("main");
+        assert_eq!((main.query.as_str(), main.saved), ("invoice", 100));
+        assert_eq!(main.sort.key, SortKey::Modified);
+        assert_eq!(store.get("search-2").query, "*.log");
+        assert_eq!(
+            store.get("search-3").query,
+            "*.log",
+            "New windows continue the last search"
+        );
+    }
+
+    #[test]
+    fn test_set_rejects_relative_scope_and_caps_windows() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = SessionStore::load(temp_dir.path());
+        let mut relative = session("x");
+        relative.scope = Some("docs".to_string());
+        assert!(store.set("main", relative, 1).is_err());
+
+        for i in 0..MAX_WINDOWS + 2 {
+            store
+                .set(&format!("search-{}", i), session("x"), i as i64)
+                .unwrap();
+        }
+        let store = SessionStore::load(temp_dir.path());
+        assert_eq!(store.file.windows.len(), MAX_WINDOWS);
+        assert!(!store.file.windows.contains_key("search-0"));
+        assert!(store.file.windows.contains_key("search-17"));
+
+        fs::write(temp_dir.path().join(SESSIONS_FILE), "not json").unwrap();
+        assert_eq!(
+            SessionStore::load(temp_dir.path()).get("main"),
+            WindowSession::default()
+        );
+    }
+}
@@ -0,0 +1,250 @@
+// Windows Subsystem for Linux: Windows reaches each distro's file system at
+// \\wsl$\<distro>\ (or \\wsl.localhost\<distro>\), where it is indexed like
+// a network share. Entries there can be opened with Linux tools inside the
+// distro or revealed in Explorer.
+
+use crate::error::{AppError, ErrorKind};
+use std::path::Path;
+
+/// Whether WSL actions work on this platform
+pub const SUPPORTED: bool = cfg!(windows);
+
+/// Hosts the distros' file systems are shared under
+const WSL_HOSTS: [&str; 2] = ["wsl$", "wsl.localhost"];
+
+/// A path on a WSL share, split into its parts
+#[derive(Debug, Clone, PartialEq)]
+pub struct WslPath {
+    /// Share host as written, `wsl$` or `wsl.localhost`
+    pub host: String,
+    pub distro: String,
+    /// The same entry as seen inside the distro, such as `/home/me/notes.md`
+    pub linux_path: String,
+}
+
+impl WslPath {
+    /// Windows path of `linux_path` in the same distro, under the same host
+    pub fn windows_path(&self, linux_path: &str) -> String {
+        let mut path = format!(r"\\{}\{}", self.host, self.distro);
+        for part in linux_path.split('/').filter(|part| !part.is_empty()) {
+            path.push('\\');
+            path.push_str(part);
+        }
+        path
+    }
+
+    /// Folder of the entry inside the distro, the entry itself for folders
+    fn linux_dir(&self, is_dir: bool) -> String {
+        if is_dir {
+            return self.linux_path.clone();
+        }
+        match self.linux_path.rsplit_once('/') {
+            Some(("", _)) | None => "/".to_string(),
+            Some((dir, _)) => dir.to_string(),
+        }
+    }
+}
+
+/// `path` split into distro and Linux path, or None when it is not on a WSL
+/// share. Verbatim `\\?\UNC\` paths are understood too.
+pub fn parse(path: &str) -> Option<WslPath> {
+    let rest = path
+        .strip_prefix(r"\\?\UNC\")
+        .or_else(|| path.strip_prefix(r"\\"))?;
+    let mut parts = rest.split(['\\', '/']).filter(|part| !part.is_empty());
+    let host = parts.next()?;
+    if !WSL_HOSTS
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(host))
+    {
+        return None;
+    }
+    let distro = parts.next()?.to_string();
+    Some(WslPath {
+        host: host.to_string(),
+        distro,
+        linux_path: format!("/{}", parts.collect::<Vec<_>>().join("/")),
+    })
+}
+
+pub fn is_wsl_path(path: &Path) -> bool {
+    parse(&path.to_string_lossy()).is_some()
+}
+
+/// Windows path of an absolute Linux `target` of the link at `link`, which
+/// means the path inside the link's distro rather than on the Windows drive
+pub fn link_target(link: &Path, target: &str) -> Option<String> {
+    if !target.starts_with('/') {
+        return None;
+    }
+    Some(parse(&link.to_string_lossy())?.windows_path(target))
+}
+
+/// Distro names in the output of `wsl.exe --list --quiet`, which is UTF-16
+pub fn parse_distro_list(output: &[u8]) -> Vec<String> {
+    let units: Vec<u16> = output
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+        .lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}'))
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Share paths of the installed distros, for adding them as roots; empty
+/// where WSL is missing
+pub fn list_distros() -> Vec<String> {
+    if !SUPPORTED {
+        return Vec::new();
+    }
+    match std::process::Command::new("wsl.exe")
+        .args(["--list", "--quiet"])
+        .output()
+    {
+        Ok(output) if output.status.success() => parse_distro_list(&output.stdout)
+            .into_iter()
+            .map(|distro| format!(r"\\wsl$\{}", distro))
+            .collect(),
+        Ok(output) => {
+            log::debug!("wsl.exe --list failed with {}", output.status);
+            Vec::new()
+        }
+        Err(e) => {
+            log::debug!("WSL is not available: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Arguments of wsl.exe running `program` on the entry, or a shell in its
+/// folder without one
+fn wsl_args(wsl: &WslPath, is_dir: bool, program: Option<&str>) -> Vec<String> {
+    let mut args = vec![
+        "--distribution".to_string(),
+        wsl.distro.clone(),
+        "--cd".to_string(),
+        wsl.linux_dir(is_dir),
+    ];
+    if let Some(program) = program {
+        args.extend(["--exec".to_string(), program.to_string()]);
+        args.push(wsl.linux_path.clone());
+    }
+    args
+}
+
+/// Open the entry at `path` inside its distro: with `program`, such as `vim`,
+/// in a new console, or a shell in its folder without one
+pub fn open_in_wsl(path: &Path, program: Option<&str>) -> Result<(), AppError> {
+    let path_str = path.to_string_lossy().to_string();
+    let wsl = parse(&path_str).ok_or_else(|| {
+        AppError::new(ErrorKind::InvalidInput, "Not inside a WSL distro").with_path(&path_str)
+    })?;
+    let metadata = std::fs::metadata(path).map_err(|e| AppError::io(&path_str, &e))?;
+    let program = program.map(str::trim).filter(|program| !program.is_empty());
+    let args = wsl_args(&wsl, metadata.is_dir(), program);
+    spawn_console("wsl.exe", &args).map_err(|e| AppError::io(&path_str, &e))?;
+    log::info!(
+        "Opened {} in {} with {}",
+        wsl.linux_path,
+        wsl.distro,
+        program.unwrap_or("a shell")
+    );
+    Ok(())
+}
+
+/// Show `path` selected in an Explorer window
+pub fn reveal_in_explorer(path: &Path) -> Result<(), AppError> {
+    let path_str = path.to_string_lossy().to_string();
+    if !SUPPORTED {
+        return Err(AppError::new(
+            ErrorKind::Unsupported,
+            "Explorer is only available on Windows",
+        ));
+    }
+    if std::fs::symlink_metadata(path).is_err() {
+        return Err(AppError::new(ErrorKind::NotFound, "File not found").with_path(path_str));
+    }
+    // Explorer exits with 1 even when it succeeds, so it is not waited for
+    std::process::Command::new("explorer.exe")
+        .arg(format!("/select,{}", path_str))
+        .spawn()
+        .map_err(|e| AppError::io(&path_str, &e))?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn spawn_console(program: &str, args: &[String]) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NEW_CONSOLE: u32 = 0x10;
+    std::process::Command::new(program)
+        .args(args)
+        .creation_flags(CREATE_NEW_CONSOLE)
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(not(windows))]
+fn spawn_console(_program: &str, _args: &[String]) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "WSL is only available on Windows",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_map_paths() {
+        let wsl = parse(r"\\wsl$\Ubuntu\home\me\notes.md").unwrap();
+        assert_eq!(wsl.distro, "Ubuntu");
+        assert_eq!(wsl.linux_path, "/home/me/notes.md");
+        assert_eq!(wsl.linux_dir(false), "/home/me");
+        assert_eq!(
+            wsl.windows_path("/usr/bin/vim"),
+            r"\\wsl$\Ubuntu\usr\bin\vim"
+        );
+        assert_eq!(
+            parse(r"\\?\UNC\WSL.localhost\Debian").unwrap().linux_path,
+            "/"
+        );
+        assert!(parse(r"\\server\share\file").is_none());
+        assert!(parse(r"C:\wsl$\Ubuntu").is_none());
+        assert!(parse(r"\\wsl$").is_none());
+
+        let link = Path::new(r"\\wsl.localhost\Ubuntu\usr\bin\vi");
+        assert_eq!(
+            link_target(link, "/usr/bin/vim").as_deref(),
+            Some(r"\\wsl.localhost\Ubuntu\usr\bin\vim")
+        );
+        assert_eq!(link_target(link, "vim"), None);
+        assert_eq!(link_target(Path::new("/usr/bin/vi"), "/usr/bin/vim"), None);
+
+        assert_eq!(
+            wsl_args(&wsl, false, Some("vim")),
+            [
+                "--distribution",
+                "Ubuntu",
+                "--cd",
+                "/home/me",
+                "--exec",
+                "vim",
+                "/home/me/notes.md"
+            ]
+        );
+        assert_eq!(wsl_args(&wsl, true, None).len(), 4);
+    }
+
+    #[test]
+    fn test_parse_distro_list() {
+        let output: Vec<u8> = "\u{feff}Ubuntu\r\ndocker-desktop\r\n\r\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(parse_distro_list(&output), ["Ubuntu", "docker-desktop"]);
+    }
+}
@@ -0,0 +1,225 @@
+// Extended attributes of a path on macOS and Linux, without following
+// symlinks. Names are raw bytes as the kernel reports them.
+
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use super::*;
+
+    /// Act on a symlink itself rather than its target
+    const XATTR_NOFOLLOW: c_int = 0x0001;
+    pub const ENOATTR: i32 = 93;
+    pub const ENOTSUP: i32 = 45;
+
+    extern "C" {
+        fn listxattr(path: *const c_char, list: *mut c_char, size: usize, options: c_int) -> isize;
+        fn getxattr(
+            path: *const c_char,
+            name: *const c_char,
+            value: *mut c_void,
+            size: usize,
+            position: u32,
+            options: c_int,
+        ) -> isize;
+        fn removexattr(path: *const c_char, name: *const c_char, options: c_int) -> c_int;
+        fn setxattr(
+            path: *const c_char,
+            name: *const c_char,
+            value: *const c_void,
+            size: usize,
+            position: u32,
+            options: c_int,
+        ) -> c_int;
+    }
+
+    pub unsafe fn list(path: *const c_char, list: *mut c_char, size: usize) -> isize {
+        listxattr(path, list, size, XATTR_NOFOLLOW)
+    }
+
+    pub unsafe fn get(
+        path: *const c_char,
+        name: *const c_char,
+        value: *mut c_void,
+        size: usize,
+    ) -> isize {
+        getxattr(path, name, value, size, 0, XATTR_NOFOLLOW)
+    }
+
+    pub unsafe fn remove(path: *const c_char, name: *const c_char) -> c_int {
+        removexattr(path, name, XATTR_NOFOLLOW)
+    }
+
+    pub unsafe fn set(
+        path: *const c_char,
+        name: *const c_char,
+        value: *const c_void,
+        size: usize,
+    ) -> c_int {
+        setxattr(path, name, value, size, 0, XATTR_NOFOLLOW)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::*;
+
+    /// ENODATA, which Linux reports for a missing attribute
+    pub const ENOATTR: i32 = 61;
+    pub const ENOTSUP: i32 = 95;
+
+    extern "C" {
+        fn llistxattr(path: *const c_char, list: *mut c_char, size: usize) -> isize;
+        fn lgetxattr(
+            path: *const c_char,
+            name: *const c_char,
+            value: *mut c_void,
+            size: usize,
+        ) -> isize;
+        #[allow(dead_code)]
+        fn lremovexattr(path: *const c_char, name: *const c_char) -> c_int;
+        #[allow(dead_code)]
+        fn lsetxattr(
+            path: *const c_char,
+            name: *const c_char,
+            value: *const c_void,
+            size: usize,
+            flags: c_int,
+        ) -> c_int;
+    }
+
+    pub unsafe fn list(path: *const c_char, list: *mut c_char, size: usize) -> isize {
+        llistxattr(path, list, size)
+    }
+
+    pub unsafe fn get(
+        path: *const c_char,
+        name: *const c_char,
+        value: *mut c_void,
+        size: usize,
+    ) -> isize {
+        lgetxattr(path, name, value, size)
+    }
+
+    #[allow(dead_code)]
+    pub unsafe fn remove(path: *const c_char, name: *const c_char) -> c_int {
+        lremovexattr(path, name)
+    }
+
+    #[allow(dead_code)]
+    pub unsafe fn set(
+        path: *const c_char,
+        name: *const c_char,
+        value: *const c_void,
+        size: usize,
+    ) -> c_int {
+        lsetxattr(path, name, value, size, 0)
+    }
+}
+
+fn c_string(bytes: &[u8]) -> io::Result<CString> {
+    CString::new(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Run a size query followed by a read into a buffer of that size, retrying
+/// when the value grew in between
+fn read_sized(mut call: impl FnMut(*mut c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        let read = call(buffer.as_mut_ptr().cast(), buffer.len());
+        if read >= 0 {
+            buffer.truncate(read as usize);
+            return Ok(buffer);
+        }
+        let err = io::Error::last_os_error();
+        // ERANGE: too small for a value that grew since the size query
+        if err.raw_os_error() != Some(34) {
+            return Err(err);
+        }
+    }
+}
+
+/// Names of all attributes of `path`; none on filesystems without them
+pub fn list(path: &Path) -> io::Result<Vec<Vec<u8>>> {
+    let path = c_string(path.as_os_str().as_bytes())?;
+    // SAFETY: the path is NUL-terminated and the buffer holds `size` bytes
+    let names =
+        match read_sized(|buffer, size| unsafe { sys::list(path.as_ptr(), buffer.cast(), size) }) {
+            Ok(names) => names,
+            Err(e) if e.raw_os_error() == Some(sys::ENOTSUP) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+    Ok(names
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(<[u8]>::to_vec)
+        .collect())
+}
+
+/// Value of attribute `name`, or None when `path` has no such attribute
+pub fn get(path: &Path, name: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let (path, name) = (c_string(path.as_os_str().as_bytes())?, c_string(name)?);
+    // SAFETY: both strings are NUL-terminated and the buffer holds `size` bytes
+    match read_sized(|buffer, size| unsafe { sys::get(path.as_ptr(), name.as_ptr(), buffer, size) })
+    {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.raw_os_error() == Some(sys::ENOATTR) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Size of attribute `name`'s value, or None when it is missing or unreadable
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))] // Only quarantine uses it
+pub fn size(path: &Path, name: &[u8]) -> Option<u64> {
+    let (path, name) = (
+        c_string(path.as_os_str().as_bytes()).ok()?,
+        c_string(name).ok()?,
+    );
+    // SAFETY: both strings are NUL-terminated; a null buffer of size 0 only
+    // queries the value's length
+    let size = unsafe { sys::get(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+    u64::try_from(size).ok()
+}
+
+/// Set attribute `name` to `value`, creating or replacing it
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))] // Only Finder labels use it
+pub fn set(path: &Path, name: &[u8], value: &[u8]) -> io::Result<()> {
+    let (path, name) = (c_string(path.as_os_str().as_bytes())?, c_string(name)?);
+    // SAFETY: both strings are NUL-terminated and `value` holds `value.len()` bytes
+    if unsafe {
+        sys::set(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+        )
+    } == 0
+    {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Remove attribute `name`; Ok(false) when `path` had none
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn remove(path: &Path, name: &[u8]) -> io::Result<bool> {
+    let (path, name) = (c_string(path.as_os_str().as_bytes())?, c_string(name)?);
+    // SAFETY: both strings are NUL-terminated
+    if unsafe { sys::remove(path.as_ptr(), name.as_ptr()) } == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(sys::ENOATTR) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
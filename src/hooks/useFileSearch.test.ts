@@ -29,6 +29,7 @@ describe("useFileSearch", () => {
       results: mockResults,
       total_found: 1,
       search_time_ms: 10,
+      session_id: 1,
     };
 
     mockInvoke.mockResolvedValue(mockResponse);
@@ -60,7 +61,7 @@ describe("useFileSearch", () => {
   });
 
   it("should handle INVALID_REGEX error", async () => {
-    mockInvoke.mockRejectedValue("INVALID_REGEX");
+    mockInvoke.mockRejectedValue({ kind: "INVALID_REGEX", message: "Invalid regex: [invalid" });
 
     const { result } = renderHook(() => useFileSearch());
 
@@ -79,7 +80,7 @@ describe("useFileSearch", () => {
   });
 
   it("should handle INDEX_NOT_READY error", async () => {
-    mockInvoke.mockRejectedValue("INDEX_NOT_READY");
+    mockInvoke.mockRejectedValue({ kind: "INDEX_NOT_READY", message: "not ready" });
 
     const { result } = renderHook(() => useFileSearch());
 
@@ -174,6 +175,7 @@ describe("useFileSearch", () => {
       results: [],
       total_found: 0,
       search_time_ms: 10,
+      session_id: 1,
     };
     mockInvoke.mockResolvedValue(mockResponse);
 
@@ -1,6 +1,11 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useCallback, useState } from "react";
-import type { FileResult, SearchFilesInput, SearchFilesOutput } from "../types/search";
+import {
+  type FileResult,
+  isAppError,
+  type SearchFilesInput,
+  type SearchFilesOutput,
+} from "../types/search";
 
 interface UseFileSearchReturn {
   search: (input: SearchFilesInput) => Promise<void>;
@@ -26,14 +31,15 @@ export function useFileSearch(): UseFileSearchReturn {
 
       setResults(response.results);
     } catch (err) {
-      const errorMessage =
-        err === "INVALID_REGEX"
+      const errorMessage = !isAppError(err)
+        ? err instanceof Error
+          ? err.message
+          : "An error occurred during search"
+        : err.kind === "INVALID_REGEX"
           ? "Invalid regular expression pattern"
-          : err === "INDEX_NOT_READY"
+          : err.kind === "INDEX_NOT_READY"
             ? "Search index is not ready. Please build the index first."
-            : err instanceof Error
-              ? err.message
-              : "An error occurred during search";
+            : err.message;
       setError(errorMessage);
       setResults([]);
     } finally {
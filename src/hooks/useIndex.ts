@@ -6,6 +6,12 @@ import type { BuildIndexOutput, GetIndexStatusOutput } from "../types/search";
 interface IndexProgressEvent {
   processed: number;
   total: number;
+  root: string;
+  current_dir: string;
+  files_per_sec: number;
+  elapsed_secs: number;
+  eta_secs: number | null;
+  errors: number;
 }
 
 interface UseIndexReturn {
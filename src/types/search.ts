@@ -4,6 +4,13 @@ export interface SearchFilesInput {
   query: string;
   use_regex: boolean;
   limit?: number;
+  scope?: string; // Absolute directory to search within
+  refine?: number; // session_id of an earlier search to narrow down
+  search_id?: string; // Lets cancel_search abandon it; reusing one cancels the previous search
+  timeout_ms?: number; // Defaults to 10000; 0 disables the timeout
+  materialize_all?: boolean; // Every match, unranked and without facets; for exports
+  remotes?: boolean; // Also search the remote machines in settings, up to limit hits each
+  snapshots?: boolean; // Also search the volume snapshots, up to limit hits each
 }
 
 export interface FileResult {
@@ -12,12 +19,185 @@ export interface FileResult {
   size: number;
   modified: string; // ISO 8601 date string
   is_folder: boolean;
+  hidden?: boolean;
+  created?: string | null; // ISO 8601, null where unsupported
+  accessed?: string | null; // ISO 8601, null unless recorded for the root
+  attributes?: FileAttributes;
+  matches?: MatchSpans;
+  quarantined?: boolean; // Downloaded-from-internet marker; clear with unblock_file
+  profile?: string; // Source profile when searching all profiles
+  machine?: string; // Remote machine the hit came from
+  snapshot?: string; // Label of the volume snapshot the hit came from; show as offline
+  display?: ResultDisplay; // Set on search_files results, in the set_locale language
 }
 
+// Size and dates of a result as text to show
+export interface ResultDisplay {
+  size: string; // "1.5 MB"; empty for folders
+  modified: string; // "2 hours ago", or the date past 30 days
+  modified_at: string; // Full local date and time
+}
+
+// Half-open [start, end) ranges in code points (iterate with Array.from)
+export interface MatchSpans {
+  name: [number, number][];
+  path: [number, number][];
+}
+
+export interface FileAttributes {
+  readonly: boolean;
+  uid: number | null; // Unix only
+  gid: number | null; // Unix only
+  mode: number | null; // Unix permission bits
+  broken_link?: boolean; // Symlink whose target is missing
+  file_id?: string | null; // "volume:number" in hex, shared by hard links
+  is_symlink?: boolean; // Symlink or junction; matched by the type:link filter
+  link_target?: string | null; // Absolute target path, for "open target"
+  repo_root?: string | null; // Enclosing Git work tree; matched by repo:<folder name>
+  git_status?: GitStatus | null; // As of indexing; matched by git:<status>
+  project_markers?: ProjectMarker[]; // Set on project root folders
+  label?: ColorLabel | null; // Matched by label:<color>
+  rating?: number | null; // 1-5 stars; matched by rating:>=4, ordered by sort:rating
+  note?: string | null; // Matched by text searches
+  email?: EmailHeaders | null; // Saved .eml and Outlook .msg files; matched by from: and subject:
+  online_only?: boolean; // Cloud placeholder; matched by attrib:online-only
+  owner?: string | null; // Windows account name; matched by owner:<name>
+}
+
+export interface EmailHeaders {
+  from: string | null;
+  subject: string | null;
+  date: number | null; // Unix seconds
+}
+
+// git:tracked also matches modified files
+export type GitStatus = "tracked" | "modified" | "untracked" | "ignored";
+
+// Names accepted by get_smart_view
+export type SmartViewName =
+  | "recently_modified"
+  | "added_today"
+  | "large_files"
+  | "empty_folders"
+  | "broken_symlinks";
+
 export interface SearchFilesOutput {
   results: FileResult[];
   total_found: number;
   search_time_ms: number;
+  facets?: FacetCounts; // Present when requested with facets: true
+  session_id: number;
+  suggestions?: string[]; // "Did you mean" queries, present when nothing was found
+}
+
+// Arguments of stream_search; results arrive as "search-chunk" events
+export interface StreamSearchInput {
+  query: string;
+  use_regex: boolean;
+  search_id: string;
+  limit?: number; // Unlimited by default
+  chunk_size?: number; // Defaults to 500
+  include_hidden?: boolean;
+  scope?: string;
+  timeout_ms?: number; // Two minutes unless given; 0 for none
+}
+
+// Payload of the "search-chunk" event, in index order rather than by rank
+export interface SearchChunkEvent {
+  search_id: string;
+  offset: number; // Position of the first result in the whole stream
+  results: FileResult[];
+}
+
+// Result of stream_search and payload of "search-stream-complete"
+export interface StreamSummary {
+  search_id: string;
+  total: number;
+  search_time_ms: number;
+}
+
+// Result of inspect_streams: an NTFS alternate data stream or an extended attribute
+export interface StreamEntry {
+  name: string;
+  size: number;
+  preview?: string; // Leading text, absent for binary content
+}
+
+// Cargo.toml, package.json or .git
+export type ColorLabel =
+  | "red"
+  | "orange"
+  | "yellow"
+  | "green"
+  | "blue"
+  | "purple"
+  | "gray";
+
+// From set_color_label, set_rating and set_note: everything attached to the path
+export interface FileMarks {
+  label: ColorLabel | null;
+  rating: number | null;
+  note: string | null;
+}
+
+export type ProjectMarker = "cargo" | "npm" | "git";
+
+// From list_projects; `name` works as a project:<name> filter
+export interface Project {
+  name: string;
+  path: string;
+  markers: ProjectMarker[];
+}
+
+export interface BrokenLink {
+  path: string;
+  kind: "symlink" | "shortcut"; // Shortcuts are .lnk files, checked on Windows only
+  target: string | null;
+}
+
+// Result of find_broken_links
+export interface BrokenLinksReport {
+  links: BrokenLink[];
+  checked: number;
+  trashed: boolean; // Set when called with delete and links were found
+}
+
+export interface FolderGroup {
+  path: string;
+  count: number;
+}
+
+// Result of group_search_by_folder
+export interface FolderGroups {
+  total_found: number;
+  total_folders: number;
+  folders: FolderGroup[];
+}
+
+// Entry of search_batch: a query, or a smart view when view is set
+export interface BatchQuery {
+  query?: string;
+  use_regex?: boolean;
+  view?: string;
+  limit?: number;
+}
+
+export interface BatchResult {
+  count: number;
+  results: SearchResult[];
+  error?: AppError;
+}
+
+export interface FacetCount {
+  value: string;
+  count: number;
+}
+
+// Hit counts over the full result set, most frequent first
+export interface FacetCounts {
+  extensions: FacetCount[];
+  file_types: FacetCount[];
+  folders: FacetCount[]; // Folders directly below each profile root
 }
 
 export interface BuildIndexInput {
@@ -29,6 +209,26 @@ export interface BuildIndexOutput {
   status: "started" | "completed" | "failed";
   files_indexed: number;
   errors: string[];
+  warnings?: string[]; // Overlapping roots skipped and protected folders left out
+}
+
+// Output of rebuild_search_index, which regenerates search from the DB
+export interface RebuildSearchIndexOutput {
+  status: "completed";
+  files_indexed: number;
+  elapsed_ms: number;
+}
+
+// Output of backup_index; the archive is a zstd-compressed tar
+export interface BackupIndexOutput {
+  path: string;
+  bytes: number;
+  files: number;
+}
+
+export interface RestoreIndexOutput {
+  status: "completed";
+  files: number;
 }
 
 export interface GetIndexStatusOutput {
@@ -36,4 +236,358 @@ export interface GetIndexStatusOutput {
   total_files: number;
   last_updated: string | null; // ISO 8601 or null if never
   indexing_in_progress: boolean;
+  watcher_healthy?: boolean | null; // null when no watcher is running
+}
+
+// Output of get_watcher_diagnostics
+export interface WatcherDiagnostics {
+  backend: string;
+  watch_limit: number | null;
+  watches_used: number;
+  watched_roots: number;
+  polled_paths: string[];
+  polled_roots: string[]; // Roots watched with the "poll" strategy
+  healthy: boolean;
+}
+
+export interface LatencySummary {
+  count: number; // Samples kept, at most the last 1000
+  avg_ms: number;
+  p50_ms: number;
+  p95_ms: number;
+  p99_ms: number;
+  max_ms: number;
+}
+
+// Output of get_metrics; kept in memory only
+export interface MetricsReport {
+  search_latency: LatencySummary;
+  update_lag: LatencySummary; // First watcher event to index update
+  watcher_queue_depth: number;
+  cached_searches: number;
+  cache_bytes: number; // Estimate
+}
+
+// Output of create_diagnostics_bundle, a zip of logs, index stats, system
+// info and redacted settings. get_recent_logs({ lines? }) returns string[].
+export interface DiagnosticsBundleOutput {
+  path: string;
+  bytes: number;
+}
+
+// Payload of the "files-changed" event emitted after watcher updates
+export interface FilesChangedEvent {
+  paths: string[];
+}
+
+export type AppErrorKind =
+  | "INDEX_NOT_READY"
+  | "INVALID_REGEX"
+  | "INDEXING_IN_PROGRESS"
+  | "INVALID_INPUT"
+  | "NOT_FOUND"
+  | "IO"
+  | "UNSUPPORTED"
+  | "CANCELLED"
+  | "TIMED_OUT"
+  | "INTERNAL";
+
+// Error object rejected by Tauri commands
+export interface AppError {
+  kind: AppErrorKind;
+  message: string;
+  path?: string;
+  os_code?: number;
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return typeof err === "object" && err !== null && "kind" in err && "message" in err;
+}
+
+// Options of the tantivy index writers
+export interface WriterOptions {
+  heap_mb: number; // Total heap shared by the writer threads
+  threads: number; // 0 lets the indexer choose
+}
+
+// Input of set_settings and output of get_settings/set_settings
+export interface Settings {
+  writer: WriterOptions;
+  elevated_indexing?: boolean; // Windows: read admin-only folders when elevated
+  start_hidden?: boolean; // Autostart into the tray; set through set_autostart
+  warm_up_index?: boolean; // Read the index into memory after loading (default true)
+  actions?: CustomAction[]; // Offered on results through list_actions/run_action
+  agent?: AgentSettings | null; // Set when start_agent first runs
+  remotes?: RemoteMachine[]; // Searched when search_files is given remotes: true
+  locale?: Locale; // Language of tray labels and error messages; set through set_locale
+  private_paths?: string[]; // Absolute; left out of results until unlock_private_results
+  data_dir?: string | null; // Portable data folder, relative to the executable's; applies after restart
+  ocr_command?: string | null; // Prints the text in an image, e.g. "tesseract %path -"; OCR is off when unset
+  embedding_command?: string | null; // Prints a vector per line of text read; semantic_search is off when unset
+  debounce_ms?: number; // Quiet period before watcher changes are applied, 50-10000 (default 500); applies after restart
+}
+
+export type Locale = "en" | "ko";
+
+// This machine's agent, serving its index to others (launch with --agent to run headless)
+export interface AgentSettings {
+  port: number;
+  token: string; // Clients prove they know it; at least 16 characters
+}
+
+// Another machine running the agent
+export interface RemoteMachine {
+  name: string; // Shown on its results
+  address: string; // host:port
+  token: string;
+}
+
+// A command run on a result. `command` may use %path, %name and %dir;
+// empty extensions/labels mean any
+export interface CustomAction {
+  id: string;
+  name: string;
+  command: string;
+  extensions?: string[]; // Without the dot, e.g. "rs"
+  labels?: ColorLabel[];
+}
+
+// Output of get_autostart and set_autostart
+export interface AutostartStatus {
+  enabled: boolean;
+  start_hidden: boolean;
+}
+
+// Output of get_elevation_status
+export interface ElevationStatus {
+  supported: boolean; // Windows only
+  elevated: boolean;
+  backup_privilege: boolean;
+}
+
+// Input of bulk_rename, applied in field order to the name without its
+// extension unless include_extension is set
+export interface RenamePattern {
+  find?: string | null; // Regex; (?i) for case-insensitive
+  replace?: string; // $1 / ${name} refer to groups of find
+  case?: "lower" | "upper" | "title" | null;
+  numbering?: {
+    start?: number; // Default 1
+    step?: number; // Default 1
+    width?: number; // Zero padding
+    separator?: string; // Default " "
+    position?: "prefix" | "suffix"; // Default suffix
+  } | null;
+  include_extension?: boolean;
+}
+
+// Output of bulk_rename, for dry runs and applied renames alike. Nothing is
+// renamed while any proposal has a conflict.
+export interface RenameProposal {
+  from: string;
+  to: string;
+  conflict: string | null;
+}
+
+// transfer_files({ transferId, kind, sources, destination, policy? }) copies or
+// moves into the destination folder; policy defaults to "ask"
+export type TransferKind = "copy" | "move";
+export type OverwritePolicy = "skip" | "replace" | "rename" | "ask";
+export type ConflictChoice = "skip" | "replace" | "rename";
+
+// Payload of "transfer-progress" events
+export interface TransferProgress {
+  transfer_id: string;
+  bytes_done: number;
+  bytes_total: number;
+  items_done: number;
+  items_total: number;
+  current: string;
+}
+
+// Payload of "transfer-conflict" events under the "ask" policy; reply with
+// answer_transfer_conflict({ transferId, answer })
+export interface TransferConflict {
+  transfer_id: string;
+  conflict_id: number;
+  source: string;
+  destination: string;
+  source_size: number;
+  source_modified: number | null;
+  destination_size: number;
+  destination_modified: number | null;
+  destination_is_folder: boolean;
+}
+
+export interface ConflictAnswer {
+  conflict_id: number;
+  choice: ConflictChoice;
+  apply_to_all?: boolean;
+}
+
+// Output of transfer_files and payload of "transfer-complete"
+export interface TransferSummary {
+  transfer_id: string;
+  transferred: { from: string; to: string; complete: boolean }[];
+  skipped: string[];
+  failed: AppError[];
+  cancelled: boolean;
+}
+
+// Output of secure_delete({ paths, passes? }). Show `warning` with it:
+// overwriting can't reach old copies kept by SSDs or copy-on-write file systems
+export interface ShredReport {
+  files: number;
+  folders: number;
+  bytes: number;
+  passes: number;
+  warning: string;
+}
+
+export type HashAlgorithm = "md5" | "sha1" | "sha256" | "blake3";
+
+// Payload of "checksum-progress" events
+export interface ChecksumProgress {
+  path: string;
+  bytes_done: number;
+  bytes_total: number;
+}
+
+// Output of compute_checksum({ path, algorithm, expected? })
+export interface Checksum {
+  path: string;
+  algorithm: HashAlgorithm;
+  hash: string; // Lowercase hex
+  size: number;
+  matches: boolean | null; // Set when an expected hash was given
+}
+
+// From autocomplete, for the dropdown under the search box
+export interface Completions {
+  terms: string[]; // Name words completing the last word typed, most common first
+  queries: string[]; // Earlier queries starting with the text typed, newest first
+  folders: string[]; // Folders whose name, or path when typing one, starts with the text
+}
+
+export type SortKey = 'relevance' | 'name' | 'path' | 'size' | 'modified' | 'rating';
+
+// Search context of a window, from get_session and for set_session
+export interface WindowSession {
+  query: string;
+  use_regex: boolean;
+  include_hidden: boolean;
+  scope: string | null; // Absolute directory searches are limited to
+  profile: string | null; // '*' for all profiles; the active one when null
+  sort: { key: SortKey; descending: boolean };
+  saved: number; // Unix seconds, set by the backend on save
+}
+
+// An operation of the undo journal, from undo_last_operation and redo_operation
+export type JournaledOperation =
+  | { kind: 'rename'; moves: { from: string; to: string }[] } // Renames and moves, in order
+  | { kind: 'trash'; paths: string[]; time: number }; // Unix seconds
+
+// How start_watching notices changes under a root: OS events, or a periodic
+// stat of its folders for network shares that send none
+export type WatchStrategy = "native" | "poll";
+
+// Result of list_volumes: a mounted drive, partition or network share
+export interface Volume {
+  mount_point: string; // Path to index the volume by
+  label: string | null;
+  file_system: string | null; // "NTFS", "ext4", "apfs", ...
+  total_bytes: number | null;
+  free_bytes: number | null; // Space available to the user
+  removable: boolean;
+  network: boolean;
+  indexed: boolean; // Already a root of the active profile
+}
+
+// Result of pick_directories; paths is empty when the dialog was cancelled
+export interface PickedDirectories {
+  paths: string[]; // Canonical, readable, none nested in another
+  warnings: string[]; // Folders left out, overlaps with indexed roots
+}
+
+// First-run setup (get_onboarding_state / advance_onboarding)
+export type OnboardingStep = "welcome" | "permissions" | "roots" | "indexing" | "done";
+// "skip" only leaves the permissions step; "reset" starts over
+export type OnboardingEvent = "next" | "skip" | "reset";
+
+export interface OnboardingState {
+  step: OnboardingStep;
+  permissions_granted: boolean; // False when the step was skipped
+  roots: string[]; // Roots when the roots step was left
+  index_built: boolean;
+  updated: number; // Unix seconds
+}
+
+// Column of get_results_table; cells come back as display text
+export type ResultColumn =
+  | "name"
+  | "path"
+  | "extension"
+  | "parent"
+  | "size"
+  | "modified"
+  | "created"
+  | "attributes" // R read-only, H hidden, L symlink, O online-only
+  | "mode" // rwxr-xr-x (Unix)
+  | "owner"; // User name, or uid when unknown on Unix
+
+export interface TableRow {
+  path: string;
+  cells: string[]; // In the order of `columns`
+}
+
+export interface ResultsTable {
+  columns: ResultColumn[];
+  rows: TableRow[];
+  total_found: number; // Every hit, not just the rows returned
 }
+
+// Frozen index of a volume, searchable while the disk is unplugged;
+// taken with snapshot_volume, never watched or rebuilt
+export interface Snapshot {
+  id: string;
+  label: string; // Volume label, or the mount point without one
+  mount_point: string;
+  file_system: string | null;
+  total_bytes: number | null;
+  created: number; // Unix seconds
+  file_count: number;
+  mounted: boolean; // The disk is plugged in now
+}
+
+// What chose the data folder: launched with --portable[=<dir>], the
+// data_dir setting, or the app data directory
+export type DataSource = "default" | "flag" | "settings";
+
+// Returned by get_data_location
+export interface DataLocation {
+  path: string;
+  source: DataSource;
+}
+
+// Which System Settings privacy pane grants access (macOS)
+export type Protection = "files_and_folders" | "volumes" | "full_disk_access";
+
+export interface ProtectedLocation {
+  path: string;
+  protection: Protection;
+  settings_url: string;
+}
+
+// Output of check_permissions; open_permission_settings takes a Protection
+export interface PermissionReport {
+  full_disk_access: boolean | null; // null off macOS
+  inaccessible: ProtectedLocation[];
+}
+
+// new_search_window({ query? }) returns the new window's label. Its page
+// starts on the query given as the "q" URL parameter.
+
+// Payload of the "search-request" event (main window only), sent when a crosseverything://search?q=
+// link or launch argument asks for a search. Also kept for take_pending_search
+// so a request made before the window loaded is not lost.
+export type SearchRequestEvent = string;